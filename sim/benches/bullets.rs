@@ -0,0 +1,90 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use sim::state::Bullet;
+use sim::util::math::Vec2;
+use sim::util::pool::Pool;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts heap allocations, so the steady state can be checked for being allocation-free.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn bullet(id: u32) -> Bullet {
+    Bullet {
+        id,
+        weapon_id: 0,
+        origin: Vec2::zero(),
+        position: Vec2::zero(),
+        velocity: Vec2::new_from_f64(8.0, 0.0),
+        bounces: 0,
+        owner: None,
+        heading: None,
+    }
+}
+
+/// One tick of a busy fight: the oldest `fired` bullets expire, and as many new ones are fired.
+fn tick(pool: &mut Pool<Bullet>, next_id: &mut u32, fired: u32) {
+    let oldest_kept = *next_id - pool.len() as u32 + fired;
+    pool.retain_mut(|bullet| {
+        bullet.position = bullet.position + bullet.velocity;
+        bullet.id >= oldest_kept
+    });
+    for _ in 0..fired {
+        pool.insert(bullet(*next_id));
+        *next_id += 1;
+    }
+}
+
+fn bullets(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bullet_pool");
+
+    for count in [100, 1000] {
+        let fired = count / 10;
+        let mut pool = Pool::new();
+        let mut next_id = 0;
+        for _ in 0..count {
+            pool.insert(bullet(next_id));
+            next_id += 1;
+        }
+
+        // once the free list has grown to its working size, firing mustn't touch the heap
+        for _ in 0..100 {
+            tick(&mut pool, &mut next_id, fired);
+        }
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        for _ in 0..1000 {
+            tick(&mut pool, &mut next_id, fired);
+        }
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+        assert_eq!(allocations, 0, "steady-state firing allocated");
+
+        group.bench_function(BenchmarkId::new("fire_and_expire", count), |b| {
+            b.iter(|| tick(&mut pool, &mut next_id, fired))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bullets);
+criterion_main!(benches);
@@ -0,0 +1,34 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use fastnum::dec64;
+use sim::util::math::Vec2;
+use std::hint::black_box;
+
+fn math(c: &mut Criterion) {
+    let mut group = c.benchmark_group("vec2");
+    let position = Vec2::new_from_f64(412.37, 288.91);
+    let velocity = Vec2::new_from_f64(3.17, -1.42);
+    let dt = dec64!(0.25);
+    let limit = dec64!(2.5);
+
+    group.bench_function("add_scale", |b| {
+        b.iter(|| black_box(position) + black_box(velocity).scale(black_box(dt)))
+    });
+    group.bench_function("mul_add", |b| {
+        b.iter(|| black_box(position).mul_add(&black_box(velocity), black_box(dt)))
+    });
+    group.bench_function("sqrt_compare", |b| {
+        b.iter(|| black_box(velocity).length_squared().sqrt() > black_box(limit))
+    });
+    group.bench_function("is_longer_than", |b| {
+        b.iter(|| black_box(velocity).is_longer_than(black_box(limit)))
+    });
+    group.bench_function("normalize", |b| b.iter(|| black_box(velocity).normalize()));
+    group.bench_function("approx_normalize", |b| {
+        b.iter(|| black_box(velocity).approx_normalize())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, math);
+criterion_main!(benches);
@@ -0,0 +1,86 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use fastnum::dec64;
+use sim::arena::Arena;
+use sim::physics::collision::AABB;
+use sim::state::Obstacle;
+use sim::util::math::Vec2;
+use sim::util::spatial::SpatialHashMap;
+use std::hint::black_box;
+
+/// Boxes laid out on a jittered grid, so every density covers the whole map.
+fn boxes(count: u32) -> Vec<AABB> {
+    let columns = (count as f64).sqrt().ceil() as u32;
+    (0..count)
+        .map(|i| {
+            let x = (i % columns) as f64 * 1024.0 / columns as f64 + (i * 7 % 13) as f64;
+            let y = (i / columns) as f64 * 768.0 / columns as f64 + (i * 5 % 11) as f64;
+            AABB::new_from_size(Vec2::new_from_f64(x, y), Vec2::new_from_f64(20.0, 13.0))
+        })
+        .collect()
+}
+
+fn spatial(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spatial_hash");
+
+    for count in [10, 100, 1000] {
+        let boxes = boxes(count);
+
+        group.bench_with_input(BenchmarkId::new("insert", count), &boxes, |b, boxes| {
+            b.iter(|| {
+                let mut map = SpatialHashMap::new(dec64!(1024), dec64!(768), 16, 12);
+                for (id, aabb) in boxes.iter().enumerate() {
+                    map.insert(id as u32, aabb);
+                }
+                map
+            })
+        });
+
+        let mut map = SpatialHashMap::new(dec64!(1024), dec64!(768), 16, 12);
+        for (id, aabb) in boxes.iter().enumerate() {
+            map.insert(id as u32, aabb);
+        }
+        let probe = AABB::new_from_size(
+            Vec2::new_from_f64(512.0, 384.0),
+            Vec2::new_from_f64(200.0, 200.0),
+        );
+        group.bench_with_input(BenchmarkId::new("query", count), &map, |b, map| {
+            b.iter(|| map.query(black_box(&probe)))
+        });
+    }
+
+    group.finish();
+
+    let mut group = c.benchmark_group("arena_raycast");
+
+    for count in [10, 100, 1000] {
+        let obstacles: Vec<Obstacle> = boxes(count)
+            .into_iter()
+            .enumerate()
+            .map(|(id, aabb)| Obstacle {
+                id: id as u32,
+                aabb,
+            })
+            .collect();
+        let arena = Arena::new(dec64!(1024), dec64!(768), &obstacles);
+        // corner to corner, past every row and column of boxes
+        let from = Vec2::new_from_f64(1.0, 1.0);
+        let to = Vec2::new_from_f64(1023.0, 767.0);
+        group.bench_with_input(BenchmarkId::new("diagonal", count), &arena, |b, arena| {
+            b.iter(|| arena.raycast(black_box(from), black_box(to)))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("line_of_sight", count),
+            &arena,
+            |b, arena| {
+                b.iter(|| {
+                    arena.line_of_sight(black_box(from), black_box(Vec2::new_from_f64(60.0, 1.0)))
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, spatial);
+criterion_main!(benches);
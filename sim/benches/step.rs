@@ -0,0 +1,110 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use sim::arena::ArenaConfig;
+use sim::bots::{Circler, Tracker};
+use sim::config::SimConfig;
+use sim::rules::MatchConfig;
+use sim::sim::{SimEngine, TankSpawn};
+use sim::spec::Loadout;
+use sim::state::SimState;
+use sim::util::math::{Angle, ConvertToScalar, Vec2};
+use sim::vm::Execution;
+use sim::vm::isa::{Assembler, Opcode};
+
+/// Two teams facing off across the arena, half hunting and half circling, or all running
+/// [`busy_program`] if `programs` says how.
+fn engine(tanks: u32, programs: Option<Execution>) -> SimEngine {
+    let rules = MatchConfig {
+        max_tanks_per_team: u32::MAX,
+        ..MatchConfig::default()
+    };
+    let arena = ArenaConfig::default();
+    let config = SimConfig {
+        rules,
+        vm_execution: programs.unwrap_or_default(),
+        ..SimConfig::default()
+    }
+    .validate()
+    .expect("bench config is valid");
+    let mut engine = SimEngine::from_config(SimState::with_arena(0, &arena), &config);
+
+    let columns = (tanks as f64).sqrt().ceil() as u32;
+    for i in 0..tanks {
+        let team_id = i % 2;
+        let x = 32.0 + (i % columns) as f64 * 960.0 / columns as f64;
+        let y = 32.0 + (i / columns) as f64 * 704.0 / columns as f64;
+        let id = engine
+            .spawn_tank(TankSpawn {
+                team_id,
+                loadout: Loadout {
+                    spec_id: 1,
+                    weapons: vec![0, 1],
+                },
+                position: Vec2::new_from_f64(x, y),
+                angle: Angle::new((team_id as f64 * 3.0).to_scalar()),
+            })
+            .unwrap();
+        if programs.is_some() {
+            engine.load_program(id, busy_program()).unwrap();
+        } else if i % 4 < 2 {
+            engine.set_bot(id, Box::new(Tracker::default())).unwrap();
+        } else {
+            engine.set_bot(id, Box::new(Circler::default())).unwrap();
+        }
+    }
+
+    // let the fight get going so bullets are in flight
+    for _ in 0..30 {
+        engine.step();
+    }
+    engine
+}
+
+/// Uses its whole budget every tick, counting in RAM and driving in circles.
+fn busy_program() -> Vec<u8> {
+    let mut asm = Assembler::new();
+    let start = asm.label();
+    asm.bind(start)
+        .push(0)
+        .op(Opcode::Load)
+        .push(1)
+        .op(Opcode::Add)
+        .op(Opcode::Dup)
+        .push(0)
+        .op(Opcode::Store)
+        .push(1 << 16)
+        .op(Opcode::Mod)
+        .push(sim::vm::abi::LEFT_TRACK)
+        .op(Opcode::Store)
+        .jump(Opcode::Jmp, start);
+    asm.finish()
+}
+
+fn step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("step");
+    group.sample_size(10);
+
+    for tanks in [10, 100, 1000] {
+        let mut engine = engine(tanks, None);
+        group.bench_function(BenchmarkId::from_parameter(tanks), |b| {
+            b.iter(|| engine.step())
+        });
+    }
+
+    group.finish();
+}
+
+fn programs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("programs");
+    group.sample_size(10);
+
+    for execution in [Execution::Interpreted, Execution::Threaded] {
+        let mut engine = engine(100, Some(execution));
+        let name = format!("{execution:?}").to_lowercase();
+        group.bench_function(BenchmarkId::new("100", name), |b| b.iter(|| engine.step()));
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, step, programs);
+criterion_main!(benches);
@@ -0,0 +1,67 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use sim::state::VmState;
+use sim::vm::isa::{Assembler, Opcode};
+use sim::vm::threaded::{self, ThreadedProgram};
+use sim::vm::{self, Stack, VmFault, VmIo};
+
+const BUDGET: u32 = 100_000;
+
+/// No memory-mapped I/O at all, so only the interpreter itself is measured.
+struct NullIo;
+
+impl VmIo for NullIo {
+    fn read(&mut self, _address: u32) -> Option<u32> {
+        None
+    }
+
+    fn write(&mut self, _address: u32, _value: u32) -> bool {
+        false
+    }
+
+    fn syscall(&mut self, number: u8, _stack: &mut Stack) -> Result<(), VmFault> {
+        Err(VmFault::BadSyscall { number })
+    }
+}
+
+/// An endless loop mixing arithmetic, RAM traffic and branches.
+fn program() -> Vec<u8> {
+    let mut asm = Assembler::new();
+    let start = asm.label();
+    asm.bind(start)
+        .push(0)
+        .op(Opcode::Load)
+        .push(3)
+        .op(Opcode::Mul)
+        .push(7)
+        .op(Opcode::Add)
+        .op(Opcode::Dup)
+        .push(0)
+        .op(Opcode::Store)
+        .push(1000)
+        .op(Opcode::Mod)
+        .jump(Opcode::Jnz, start)
+        .jump(Opcode::Jmp, start);
+    asm.finish()
+}
+
+fn vm(c: &mut Criterion) {
+    let code = program();
+    let mut group = c.benchmark_group("vm");
+    group.throughput(Throughput::Elements(BUDGET as u64));
+
+    group.bench_function(BenchmarkId::new("instructions", "interpreted"), |b| {
+        let mut state = VmState::new();
+        b.iter(|| vm::run(&mut state, &code, BUDGET, &mut NullIo))
+    });
+
+    let program = ThreadedProgram::compile(&code);
+    group.bench_function(BenchmarkId::new("instructions", "threaded"), |b| {
+        let mut state = VmState::new();
+        b.iter(|| threaded::run(&mut state, &program, BUDGET, &mut NullIo))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, vm);
+criterion_main!(benches);
@@ -0,0 +1,433 @@
+use crate::arena::Arena;
+use crate::bots::BotCommand;
+use crate::physics::drivetrain::DriveInput;
+use crate::physics::turret::TurretCommand;
+use crate::sensors::{Contact, SensorData};
+use crate::spec::TankSpec;
+use crate::state::Tank;
+use crate::stats::{MatchStats, TankStats};
+use crate::util::math::{Scalar, Vec2};
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Values describing the tank itself at the start of every observation.
+pub const OWN_FEATURES: usize = 10;
+
+/// Values describing each enemy slot.
+pub const ENEMY_FEATURES: usize = 5;
+
+/// Drive choices in a discrete action: idle, forward, reverse, turn left, turn right.
+const DRIVE_CHOICES: u32 = 5;
+
+/// Turret choices in a discrete action: hold, slew left, slew right.
+const TURRET_CHOICES: u32 = 3;
+
+/// Number of distinct discrete actions, i.e. every drive choice with every turret choice,
+/// with and without firing.
+pub const DISCRETE_ACTIONS: u32 = DRIVE_CHOICES * TURRET_CHOICES * 2;
+
+/// The shape of the observations handed to a learning agent.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ObservationSpec {
+    /// Enemy slots, filled nearest first from what the tank can see.
+    pub nearest_enemies: usize,
+    /// Rays cast around the hull to find walls and obstacles, evenly spaced from dead ahead.
+    pub rays: usize,
+    /// How far rays reach, and the distance enemy offsets are measured against.
+    pub range: Scalar,
+}
+
+impl Default for ObservationSpec {
+    fn default() -> Self {
+        ObservationSpec {
+            nearest_enemies: 3,
+            rays: 8,
+            range: dec64!(400),
+        }
+    }
+}
+
+impl ObservationSpec {
+    /// Returns how many values each observation holds.
+    pub fn size(&self) -> usize {
+        OWN_FEATURES + self.nearest_enemies * ENEMY_FEATURES + self.rays
+    }
+}
+
+fn feature(value: Scalar) -> f32 {
+    value.to_f64() as f32
+}
+
+fn fraction(numerator: u32, denominator: u32) -> f32 {
+    if denominator == 0 {
+        return 0.0;
+    }
+    numerator as f32 / denominator as f32
+}
+
+/// Builds a tank's observation: a flat vector of [`ObservationSpec::size`] values, roughly in
+/// `[-1, 1]`, ready to feed a network.
+///
+/// The layout is the tank's own state, then one slot per enemy, then the ray distances:
+///
+/// - own: position as a fraction of the arena, heading and turret as cos/sin pairs, velocity
+///   in the hull's frame as a fraction of top speed, health fraction, and whether the first
+///   weapon is loaded;
+/// - enemy: whether the slot is filled, its offset in the hull's frame as a fraction of the
+///   range, and its velocity in the hull's frame as a fraction of our top speed;
+/// - ray: distance to the first wall or obstacle as a fraction of the range, 1 if none.
+///
+/// Only enemies in `sensors` are included, so agents see no more than programs do.
+pub fn observe(
+    spec: &ObservationSpec,
+    tank: &Tank,
+    tank_spec: &TankSpec,
+    sensors: &SensorData,
+    arena: &Arena,
+) -> Vec<f32> {
+    let mut observation = Vec::with_capacity(spec.size());
+    let top_speed = tank_spec.drivetrain.max_speed.max(dec64!(1));
+    let hull = tank.rotation();
+    let local = |vector: Vec2| hull.unrotate(vector);
+
+    let velocity = local(tank.velocity);
+    let turret = tank.turret_transform().rotation;
+    observation.extend([
+        feature(tank.position.x / arena.width()),
+        feature(tank.position.y / arena.height()),
+        feature(hull.cos()),
+        feature(hull.sin()),
+        feature(turret.cos()),
+        feature(turret.sin()),
+        feature(velocity.x / top_speed),
+        feature(velocity.y / top_speed),
+        fraction(tank.health, tank_spec.max_health),
+        if tank.reload.first() == Some(&0) {
+            1.0
+        } else {
+            0.0
+        },
+    ]);
+
+    let mut enemies: Vec<&Contact> = sensors
+        .contacts
+        .iter()
+        .filter(|contact| contact.team_id != tank.team_id)
+        .collect();
+    enemies.sort_by(|a, b| {
+        let distance = |contact: &Contact| contact.position.sub(&tank.position).length_squared();
+        distance(a).cmp(&distance(b)).then(a.id.cmp(&b.id))
+    });
+    for slot in 0..spec.nearest_enemies {
+        match enemies.get(slot) {
+            Some(enemy) => {
+                let offset = local(enemy.position.sub(&tank.position));
+                let velocity = local(enemy.velocity);
+                observation.extend([
+                    1.0,
+                    feature(offset.x / spec.range),
+                    feature(offset.y / spec.range),
+                    feature(velocity.x / top_speed),
+                    feature(velocity.y / top_speed),
+                ]);
+            }
+            None => observation.extend([0.0; ENEMY_FEATURES]),
+        }
+    }
+
+    let tau = Scalar::PI * dec64!(2);
+    for ray in 0..spec.rays {
+        let angle = tank.angle + tau * Scalar::from(ray as u32) / Scalar::from(spec.rays as u32);
+        let end = tank.position + angle.direction().scale(spec.range);
+        let distance = match arena.raycast_walls(tank.position, end) {
+            Some((_, hit)) => feature(hit.fraction),
+            None => 1.0,
+        };
+        observation.push(distance);
+    }
+
+    observation
+}
+
+/// What a learning agent tells its tank to do for a tick.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AgentAction {
+    /// One of [`DISCRETE_ACTIONS`] choices, laid out as `(fire * 3 + turret) * 5 + drive`:
+    ///
+    /// - drive: idle, forward, reverse, turn left, turn right;
+    /// - turret: hold, slew left, slew right;
+    /// - fire: hold fire, fire the first weapon.
+    ///
+    /// Out-of-range indices are treated as idle.
+    Discrete(u32),
+    /// Raw inputs, each clamped to `[-1, 1]`: the two tracks, the turret's slew as a fraction
+    /// of its top rate, and whether to fire the first weapon, which happens when positive.
+    Continuous {
+        left: Scalar,
+        right: Scalar,
+        turret: Scalar,
+        fire: Scalar,
+    },
+}
+
+impl Default for AgentAction {
+    fn default() -> Self {
+        AgentAction::Discrete(0)
+    }
+}
+
+impl AgentAction {
+    /// Turns the action into the commands the tank follows this tick.
+    ///
+    /// Turret slewing is expressed as a hull-relative target just past the turret's current
+    /// angle, so the turret turns at up to its top rate and stops when the agent stops.
+    pub fn decode(&self, tank: &Tank, tank_spec: &TankSpec) -> BotCommand {
+        let slew_rate = tank_spec.turret.max_slew_rate;
+        let slew = |amount: Scalar| {
+            if amount == dec64!(0) {
+                TurretCommand::Hold
+            } else {
+                TurretCommand::Relative(tank.turret_angle + amount * slew_rate)
+            }
+        };
+        let fire_slot = (!tank.loadout.weapons.is_empty()).then_some(0);
+
+        match *self {
+            AgentAction::Discrete(index) => {
+                let index = if index < DISCRETE_ACTIONS { index } else { 0 };
+                let drive = match index % DRIVE_CHOICES {
+                    1 => DriveInput::tracks(dec64!(1), dec64!(1)),
+                    2 => DriveInput::tracks(dec64!(-1), dec64!(-1)),
+                    3 => DriveInput::tracks(dec64!(-1), dec64!(1)),
+                    4 => DriveInput::tracks(dec64!(1), dec64!(-1)),
+                    _ => DriveInput::default(),
+                };
+                let turret = match (index / DRIVE_CHOICES) % TURRET_CHOICES {
+                    1 => slew(dec64!(1)),
+                    2 => slew(dec64!(-1)),
+                    _ => TurretCommand::Hold,
+                };
+                let fire = index / (DRIVE_CHOICES * TURRET_CHOICES) == 1;
+                BotCommand {
+                    drive,
+                    turret,
+                    fire: fire_slot.filter(|_| fire),
+                }
+            }
+            AgentAction::Continuous {
+                left,
+                right,
+                turret,
+                fire,
+            } => {
+                let unit = |value: Scalar| value.clamp(dec64!(-1), dec64!(1));
+                BotCommand {
+                    drive: DriveInput::tracks(unit(left), unit(right)),
+                    turret: slew(unit(turret)),
+                    fire: fire_slot.filter(|_| fire > dec64!(0)),
+                }
+            }
+        }
+    }
+}
+
+/// Scores how well a tank did over a step, from its stats before and after.
+///
+/// Implement this for custom shaping; [`WeightedReward`] covers the usual terms.
+pub trait RewardHook {
+    fn reward(&mut self, before: &TankStats, after: &TankStats) -> Scalar;
+}
+
+/// A reward summed from weighted changes in a tank's stats.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WeightedReward {
+    pub shot_fired: Scalar,
+    pub hit: Scalar,
+    /// Per point of damage dealt to enemies.
+    pub damage_dealt: Scalar,
+    /// Per point of damage taken, so usually negative.
+    pub damage_taken: Scalar,
+    pub kill: Scalar,
+    /// Per point of damage dealt to teammates, so usually negative.
+    #[serde(default)]
+    pub friendly_damage: Scalar,
+    /// Per tick spent alive.
+    pub survival: Scalar,
+}
+
+impl Default for WeightedReward {
+    fn default() -> Self {
+        WeightedReward {
+            shot_fired: dec64!(0),
+            hit: dec64!(0.1),
+            damage_dealt: dec64!(0.01),
+            damage_taken: dec64!(-0.01),
+            kill: dec64!(1),
+            friendly_damage: dec64!(-0.02),
+            survival: dec64!(0),
+        }
+    }
+}
+
+impl RewardHook for WeightedReward {
+    fn reward(&mut self, before: &TankStats, after: &TankStats) -> Scalar {
+        let delta = |after: u64, before: u64| Scalar::from(after.saturating_sub(before));
+        self.shot_fired * delta(after.shots_fired as u64, before.shots_fired as u64)
+            + self.hit * delta(after.hits as u64, before.hits as u64)
+            + self.damage_dealt * delta(after.damage_dealt as u64, before.damage_dealt as u64)
+            + self.damage_taken * delta(after.damage_taken as u64, before.damage_taken as u64)
+            + self.kill * delta(after.kills as u64, before.kills as u64)
+            + self.friendly_damage
+                * delta(after.friendly_damage as u64, before.friendly_damage as u64)
+            + self.survival * delta(after.ticks_alive, before.ticks_alive)
+    }
+}
+
+/// Hands out each tank's reward since it was last asked, from the match stats.
+pub struct RewardTracker<H: RewardHook> {
+    hook: H,
+    last: BTreeMap<u32, TankStats>,
+}
+
+impl<H: RewardHook> RewardTracker<H> {
+    pub fn new(hook: H) -> Self {
+        RewardTracker {
+            hook,
+            last: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the tank's reward since the last call for it, or since the match began.
+    pub fn reward(&mut self, stats: &MatchStats, tank_id: u32) -> Scalar {
+        let after = stats.tank(tank_id).cloned().unwrap_or_default();
+        let before = self.last.insert(tank_id, after.clone()).unwrap_or_default();
+        self.hook.reward(&before, &after)
+    }
+
+    /// Forgets every tank's last stats, e.g. when the match is reset.
+    pub fn clear(&mut self) {
+        self.last.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spec::SpecTable;
+    use crate::test_support;
+    use crate::util::math::Angle;
+
+    fn tank(x: f64, y: f64) -> Tank {
+        test_support::tank(0, 0, Vec2::new_from_f64(x, y))
+    }
+
+    fn contact(id: u32, team_id: u32, x: f64, y: f64) -> Contact {
+        Contact {
+            id,
+            team_id,
+            position: Vec2::new_from_f64(x, y),
+            velocity: Vec2::zero(),
+        }
+    }
+
+    #[test]
+    fn observe_should_fill_nearest_enemies_and_rays_to_fixed_size() {
+        // Arrange
+        let specs = SpecTable::default();
+        let arena = Arena::new(dec64!(1000), dec64!(1000), &[]);
+        let tank = tank(100.0, 500.0);
+        let sensors = SensorData {
+            zone: None,
+            missiles: Vec::new(),
+            contacts: vec![
+                contact(5, 1, 300.0, 500.0),
+                contact(6, 0, 120.0, 500.0),
+                contact(7, 1, 100.0, 600.0),
+            ],
+        };
+        let spec = ObservationSpec {
+            nearest_enemies: 3,
+            rays: 4,
+            range: dec64!(200),
+        };
+
+        // Act
+        let observation = observe(&spec, &tank, specs.tank(1).unwrap(), &sensors, &arena);
+
+        // Assert
+        assert_eq!(observation.len(), spec.size());
+        assert_eq!(observation[9], 1.0);
+        let enemies = &observation[OWN_FEATURES..OWN_FEATURES + 3 * ENEMY_FEATURES];
+        assert_eq!(enemies[..3], [1.0, 0.0, 0.5]);
+        assert_eq!(enemies[5..8], [1.0, 1.0, 0.0]);
+        assert_eq!(enemies[10..], [0.0; ENEMY_FEATURES]);
+        let rays = &observation[spec.size() - 4..];
+        assert_eq!(rays, [1.0, 1.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn decode_should_map_discrete_and_continuous_actions_to_commands() {
+        // Arrange
+        let specs = SpecTable::default();
+        let spec = specs.tank(1).unwrap();
+        let tank = tank(0.0, 0.0);
+        let slew = spec.turret.max_slew_rate;
+
+        // Act
+        let forward_left_fire = AgentAction::Discrete(21).decode(&tank, spec);
+        let out_of_range = AgentAction::Discrete(DISCRETE_ACTIONS).decode(&tank, spec);
+        let continuous = AgentAction::Continuous {
+            left: dec64!(2),
+            right: dec64!(-0.5),
+            turret: dec64!(-1),
+            fire: dec64!(0),
+        }
+        .decode(&tank, spec);
+
+        // Assert
+        assert_eq!(
+            forward_left_fire.drive,
+            DriveInput::tracks(dec64!(1), dec64!(1))
+        );
+        assert_eq!(
+            forward_left_fire.turret,
+            TurretCommand::Relative(Angle::new(slew))
+        );
+        assert_eq!(forward_left_fire.fire, Some(0));
+        assert_eq!(out_of_range, BotCommand::default());
+        assert_eq!(
+            continuous.drive,
+            DriveInput::tracks(dec64!(1), dec64!(-0.5))
+        );
+        assert_eq!(
+            continuous.turret,
+            TurretCommand::Relative(Angle::new(-slew))
+        );
+        assert_eq!(continuous.fire, None);
+    }
+
+    #[test]
+    fn reward_tracker_should_score_changes_since_last_call() {
+        // Arrange
+        let mut stats = MatchStats::default();
+        let mut tracker = RewardTracker::new(WeightedReward::default());
+        let mut tank = TankStats::default();
+        tank.hits = 1;
+        tank.damage_dealt = 20;
+        stats.tanks.insert(3, tank);
+
+        // Act
+        let first = tracker.reward(&stats, 3);
+        let idle = tracker.reward(&stats, 3);
+        let tank = stats.tanks.get_mut(&3).unwrap();
+        tank.kills = 1;
+        tank.damage_taken = 50;
+        let later = tracker.reward(&stats, 3);
+
+        // Assert
+        assert_eq!(first, dec64!(0.3));
+        assert_eq!(idle, dec64!(0));
+        assert_eq!(later, dec64!(0.5));
+    }
+}
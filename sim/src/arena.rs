@@ -0,0 +1,362 @@
+use crate::physics::bvh::StaticBvh;
+use crate::physics::collision::{AABB, SegmentHit, segment_vs_box};
+use crate::state::Obstacle;
+use crate::tags::Tag;
+use crate::triggers::TriggerShape;
+use crate::util::math::{Rotation, Scalar, Transform2, Vec2};
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The layout of a map, as authored.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ArenaConfig {
+    pub width: Scalar,
+    pub height: Scalar,
+    pub obstacles: Vec<AABB>,
+    /// Map triggers such as capture zones.
+    #[serde(default)]
+    pub triggers: Vec<TriggerShape>,
+    /// Where the map's designer intends tanks to start or respawn.
+    #[serde(default)]
+    pub spawn_points: Vec<Vec2>,
+    /// Tags for obstacles, by index in `obstacles`, e.g. to name a gate a scenario opens.
+    #[serde(default)]
+    pub obstacle_tags: BTreeMap<usize, Vec<Tag>>,
+    /// Tags for triggers, by index in `triggers`.
+    #[serde(default)]
+    pub trigger_tags: BTreeMap<usize, Vec<Tag>>,
+}
+
+impl Default for ArenaConfig {
+    fn default() -> Self {
+        ArenaConfig {
+            width: dec64!(1024),
+            height: dec64!(768),
+            obstacles: Vec::new(),
+            triggers: Vec::new(),
+            spawn_points: Vec::new(),
+            obstacle_tags: BTreeMap::new(),
+            trigger_tags: BTreeMap::new(),
+        }
+    }
+}
+
+/// Mistakes in an authored arena. Indices refer to the config's lists.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArenaIssue {
+    EmptyArena,
+    InvertedObstacle(usize),
+    ObstacleOutOfBounds(usize),
+    TriggerOutOfBounds(usize),
+    SpawnPointOutOfBounds(usize),
+    SpawnPointInObstacle {
+        spawn: usize,
+        obstacle: usize,
+    },
+    /// Tags were given for an obstacle the arena doesn't have.
+    TaggedMissingObstacle(usize),
+    /// Tags were given for a trigger the arena doesn't have.
+    TaggedMissingTrigger(usize),
+}
+
+impl fmt::Display for ArenaIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArenaIssue::EmptyArena => write!(f, "arena has no area"),
+            ArenaIssue::InvertedObstacle(index) => {
+                write!(f, "obstacle {index} has its min and max corners swapped")
+            }
+            ArenaIssue::ObstacleOutOfBounds(index) => {
+                write!(f, "obstacle {index} extends past the arena edges")
+            }
+            ArenaIssue::TriggerOutOfBounds(index) => {
+                write!(f, "trigger {index} extends past the arena edges")
+            }
+            ArenaIssue::SpawnPointOutOfBounds(index) => {
+                write!(f, "spawn point {index} is outside the arena")
+            }
+            ArenaIssue::SpawnPointInObstacle { spawn, obstacle } => {
+                write!(f, "spawn point {spawn} is inside obstacle {obstacle}")
+            }
+            ArenaIssue::TaggedMissingObstacle(index) => {
+                write!(f, "tags given for obstacle {index}, which doesn't exist")
+            }
+            ArenaIssue::TaggedMissingTrigger(index) => {
+                write!(f, "tags given for trigger {index}, which doesn't exist")
+            }
+        }
+    }
+}
+
+impl ArenaConfig {
+    /// Checks the layout for mistakes that would make a match misbehave, in list order.
+    pub fn validate(&self) -> Vec<ArenaIssue> {
+        if self.width <= dec64!(0) || self.height <= dec64!(0) {
+            return vec![ArenaIssue::EmptyArena];
+        }
+        let bounds = AABB::new(Vec2::zero(), Vec2::new(self.width, self.height));
+        let inside = |aabb: &AABB| bounds.contains(aabb.min) && bounds.contains(aabb.max);
+
+        let mut issues = Vec::new();
+        for (index, aabb) in self.obstacles.iter().enumerate() {
+            if aabb.min.x > aabb.max.x || aabb.min.y > aabb.max.y {
+                issues.push(ArenaIssue::InvertedObstacle(index));
+            } else if !inside(aabb) {
+                issues.push(ArenaIssue::ObstacleOutOfBounds(index));
+            }
+        }
+        for (index, shape) in self.triggers.iter().enumerate() {
+            if !inside(&shape.bounds()) {
+                issues.push(ArenaIssue::TriggerOutOfBounds(index));
+            }
+        }
+        for (spawn, point) in self.spawn_points.iter().enumerate() {
+            if !bounds.contains(*point) {
+                issues.push(ArenaIssue::SpawnPointOutOfBounds(spawn));
+                continue;
+            }
+            if let Some(obstacle) = self.obstacles.iter().position(|aabb| aabb.contains(*point)) {
+                issues.push(ArenaIssue::SpawnPointInObstacle { spawn, obstacle });
+            }
+        }
+        for index in self.obstacle_tags.keys() {
+            if *index >= self.obstacles.len() {
+                issues.push(ArenaIssue::TaggedMissingObstacle(*index));
+            }
+        }
+        for index in self.trigger_tags.keys() {
+            if *index >= self.triggers.len() {
+                issues.push(ArenaIssue::TaggedMissingTrigger(*index));
+            }
+        }
+        issues
+    }
+}
+
+/// Lookup structures over the arena's static geometry.
+///
+/// Rebuilt whenever the set of obstacles changes, which should be rare.
+pub struct Arena {
+    width: Scalar,
+    height: Scalar,
+    obstacles: Vec<Obstacle>,
+    /// Obstacles baked into a hierarchy for raycasts, which is most of what they're used for.
+    bvh: StaticBvh,
+}
+
+impl Arena {
+    pub fn new(width: Scalar, height: Scalar, obstacles: &[Obstacle]) -> Self {
+        let bvh = StaticBvh::new(
+            obstacles
+                .iter()
+                .map(|obstacle| (obstacle.id, obstacle.aabb)),
+        );
+        Arena {
+            width,
+            height,
+            obstacles: obstacles.to_vec(),
+            bvh,
+        }
+    }
+
+    pub fn width(&self) -> Scalar {
+        self.width
+    }
+
+    pub fn height(&self) -> Scalar {
+        self.height
+    }
+
+    /// Returns the arena bounds as a box.
+    pub fn bounds(&self) -> AABB {
+        AABB::new(Vec2::zero(), Vec2::new(self.width, self.height))
+    }
+
+    /// Returns the obstacle with the given ID, if it exists.
+    pub fn obstacle(&self, id: u32) -> Option<&Obstacle> {
+        self.obstacles.iter().find(|obstacle| obstacle.id == id)
+    }
+
+    /// Finds the first obstacle the segment `from -> to` runs into, if any.
+    ///
+    /// Ties are broken by obstacle ID.
+    pub fn raycast(&self, from: Vec2, to: Vec2) -> Option<(u32, SegmentHit)> {
+        self.bvh.raycast(from, to, |_, aabb| {
+            let frame = Transform2::new(aabb.center(), Rotation::IDENTITY);
+            segment_vs_box(from, to, &frame, aabb.half_extents())
+        })
+    }
+
+    /// Like [`Arena::raycast`], but the arena's edges count as walls too.
+    ///
+    /// Edge hits report the inward-facing normal, and no obstacle ID.
+    pub fn raycast_walls(&self, from: Vec2, to: Vec2) -> Option<(Option<u32>, SegmentHit)> {
+        let mut first = self.raycast(from, to).map(|(id, hit)| (Some(id), hit));
+
+        let delta = to.sub(&from);
+        let edges = [
+            (from.x, delta.x, dec64!(0), Vec2::new(dec64!(1), dec64!(0))),
+            (
+                from.x,
+                delta.x,
+                self.width,
+                Vec2::new(dec64!(-1), dec64!(0)),
+            ),
+            (from.y, delta.y, dec64!(0), Vec2::new(dec64!(0), dec64!(1))),
+            (
+                from.y,
+                delta.y,
+                self.height,
+                Vec2::new(dec64!(0), dec64!(-1)),
+            ),
+        ];
+        for (origin, speed, edge, normal) in edges {
+            // only crossings heading out of the arena count
+            if delta.dot(&normal) >= dec64!(0) {
+                continue;
+            }
+            let fraction = (edge - origin) / speed;
+            if fraction < dec64!(0) || fraction > dec64!(1) {
+                continue;
+            }
+            if first.is_none_or(|(_, best)| fraction < best.fraction) {
+                first = Some((None, SegmentHit { fraction, normal }));
+            }
+        }
+
+        first
+    }
+
+    /// Returns whether nothing static blocks the straight line between two points.
+    pub fn line_of_sight(&self, from: Vec2, to: Vec2) -> bool {
+        self.raycast(from, to).is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::math::ConvertToScalar;
+
+    fn arena() -> Arena {
+        let wall = Obstacle {
+            id: 7,
+            aabb: AABB::new(
+                Vec2::new_from_f64(90.0, 0.0),
+                Vec2::new_from_f64(110.0, 50.0),
+            ),
+        };
+        Arena::new(200.0.to_scalar(), 200.0.to_scalar(), &[wall])
+    }
+
+    #[test]
+    fn arena_config_validate_should_report_misplaced_geometry() {
+        // Arrange
+        let config = ArenaConfig {
+            width: dec64!(200),
+            height: dec64!(200),
+            obstacles: vec![
+                AABB::new(
+                    Vec2::new_from_f64(90.0, 0.0),
+                    Vec2::new_from_f64(110.0, 50.0),
+                ),
+                AABB::new(
+                    Vec2::new_from_f64(180.0, 180.0),
+                    Vec2::new_from_f64(220.0, 190.0),
+                ),
+            ],
+            triggers: vec![TriggerShape::Circle {
+                center: Vec2::new_from_f64(100.0, 100.0),
+                radius: dec64!(20),
+            }],
+            spawn_points: vec![
+                Vec2::new_from_f64(20.0, 20.0),
+                Vec2::new_from_f64(100.0, 25.0),
+                Vec2::new_from_f64(-5.0, 25.0),
+            ],
+            obstacle_tags: BTreeMap::from([(0, vec!["gate".into()]), (5, vec![Tag::Number(1)])]),
+            trigger_tags: BTreeMap::new(),
+        };
+
+        // Act
+        let issues = config.validate();
+
+        // Assert
+        assert_eq!(
+            issues,
+            vec![
+                ArenaIssue::ObstacleOutOfBounds(1),
+                ArenaIssue::SpawnPointInObstacle {
+                    spawn: 1,
+                    obstacle: 0
+                },
+                ArenaIssue::SpawnPointOutOfBounds(2),
+                ArenaIssue::TaggedMissingObstacle(5),
+            ]
+        );
+        assert!(ArenaConfig::default().validate().is_empty());
+    }
+
+    #[test]
+    fn arena_line_of_sight_when_wall_between_should_be_blocked() {
+        // Arrange
+        let arena = arena();
+
+        // Act
+        let blocked = arena.line_of_sight(
+            Vec2::new_from_f64(50.0, 25.0),
+            Vec2::new_from_f64(150.0, 25.0),
+        );
+        let clear = arena.line_of_sight(
+            Vec2::new_from_f64(50.0, 100.0),
+            Vec2::new_from_f64(150.0, 100.0),
+        );
+
+        // Assert
+        assert!(!blocked);
+        assert!(clear);
+    }
+
+    #[test]
+    fn arena_raycast_should_report_obstacle_and_hit_face() {
+        // Arrange
+        let arena = arena();
+
+        // Act
+        let hit = arena.raycast(
+            Vec2::new_from_f64(50.0, 25.0),
+            Vec2::new_from_f64(150.0, 25.0),
+        );
+
+        // Assert
+        let (id, hit) = hit.unwrap();
+        assert_eq!(id, 7);
+        assert_eq!(hit.fraction, dec64!(0.4));
+        assert_eq!(hit.normal, Vec2::new_from_f64(-1.0, 0.0));
+    }
+
+    #[test]
+    fn arena_raycast_walls_should_include_arena_edges() {
+        // Arrange
+        let arena = arena();
+
+        // Act
+        let edge = arena.raycast_walls(
+            Vec2::new_from_f64(150.0, 100.0),
+            Vec2::new_from_f64(250.0, 100.0),
+        );
+        let obstacle = arena.raycast_walls(
+            Vec2::new_from_f64(50.0, 25.0),
+            Vec2::new_from_f64(250.0, 25.0),
+        );
+
+        // Assert
+        let (id, hit) = edge.unwrap();
+        assert_eq!(id, None);
+        assert_eq!(hit.fraction, dec64!(0.5));
+        assert_eq!(hit.normal, Vec2::new_from_f64(-1.0, 0.0));
+        assert_eq!(obstacle.unwrap().0, Some(7));
+    }
+}
@@ -0,0 +1,346 @@
+use crate::physics::drivetrain::DriveInput;
+use crate::physics::turret::TurretCommand;
+use crate::sensors::{Contact, SensorData};
+use crate::state::Tank;
+use crate::util::math::{Angle, Scalar};
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+
+/// What a native controller gets to look at on its turn: its own tank and its sensors, never
+/// the whole state.
+pub struct BotView<'a> {
+    pub tick: u64,
+    pub tank: &'a Tank,
+    pub sensors: &'a SensorData,
+}
+
+/// Commands a native controller issues for one tick.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BotCommand {
+    pub drive: DriveInput,
+    pub turret: TurretCommand,
+    /// Weapon slot to fire whenever it's reloaded, or `None` to hold fire.
+    pub fire: Option<u32>,
+}
+
+/// Channels of a tank's controls a human has taken from its controller, for assisted play or
+/// to show a bot what to do. Each channel that's set wins over whatever the controller decided
+/// for it, and the controller keeps the rest.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ControlOverride {
+    pub drive: Option<DriveInput>,
+    pub turret: Option<TurretCommand>,
+    /// Weapon slot to fire, or `Some(None)` to hold fire.
+    pub fire: Option<Option<u32>>,
+}
+
+impl ControlOverride {
+    /// Returns whether no channel is overridden.
+    pub fn is_empty(&self) -> bool {
+        self.drive.is_none() && self.turret.is_none() && self.fire.is_none()
+    }
+
+    /// Replaces the overridden channels of the commands a tank's controller left it.
+    pub fn apply(&self, tank: &mut Tank) {
+        if let Some(drive) = self.drive {
+            tank.drive = drive;
+        }
+        if let Some(turret) = self.turret {
+            tank.turret = turret;
+        }
+        if let Some(fire) = self.fire {
+            tank.fire = fire;
+        }
+    }
+}
+
+/// A tank controller written in Rust rather than bytecode.
+///
+/// Used for built-in opponents, so VM bots have something to fight and headless matches can
+/// run before any programs exist. Controllers are `Send` so an engine can be moved to whichever
+/// thread runs it.
+pub trait BotController: Send {
+    /// Decides what the tank does this tick.
+    fn think(&mut self, view: &BotView) -> BotCommand;
+
+    /// Returns the bot in a form that can be saved with a match, if it has one.
+    fn save(&self) -> Option<SavedBot> {
+        None
+    }
+}
+
+/// A built-in bot, as stored in a match save.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SavedBot {
+    SittingDuck,
+    Circler(Circler),
+    Tracker(Tracker),
+    Shuttle(Shuttle),
+}
+
+impl SavedBot {
+    pub fn into_bot(self) -> Box<dyn BotController> {
+        match self {
+            SavedBot::SittingDuck => Box::new(SittingDuck),
+            SavedBot::Circler(bot) => Box::new(bot),
+            SavedBot::Tracker(bot) => Box::new(bot),
+            SavedBot::Shuttle(bot) => Box::new(bot),
+        }
+    }
+}
+
+/// Sits still and never fires. Target practice.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SittingDuck;
+
+impl BotController for SittingDuck {
+    fn think(&mut self, _view: &BotView) -> BotCommand {
+        BotCommand::default()
+    }
+
+    fn save(&self) -> Option<SavedBot> {
+        Some(SavedBot::SittingDuck)
+    }
+}
+
+/// Drives in endless circles without shooting, for practising leading a moving target.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Circler {
+    pub throttle: Scalar,
+    /// Positive circles towards increasing angles.
+    pub steer: Scalar,
+}
+
+impl Default for Circler {
+    fn default() -> Self {
+        Circler {
+            throttle: dec64!(0.6),
+            steer: dec64!(0.4),
+        }
+    }
+}
+
+impl BotController for Circler {
+    fn think(&mut self, _view: &BotView) -> BotCommand {
+        BotCommand {
+            drive: DriveInput::throttle_steer(self.throttle, self.steer),
+            ..BotCommand::default()
+        }
+    }
+
+    fn save(&self) -> Option<SavedBot> {
+        Some(SavedBot::Circler(*self))
+    }
+}
+
+/// Drives back and forth along a line without shooting, reversing every `period` ticks, for
+/// practising shots on a target that changes direction.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Shuttle {
+    pub throttle: Scalar,
+    pub period: u64,
+}
+
+impl Default for Shuttle {
+    fn default() -> Self {
+        Shuttle {
+            throttle: dec64!(0.6),
+            period: 120,
+        }
+    }
+}
+
+impl BotController for Shuttle {
+    fn think(&mut self, view: &BotView) -> BotCommand {
+        let throttle = if (view.tick / self.period.max(1)).is_multiple_of(2) {
+            self.throttle
+        } else {
+            -self.throttle
+        };
+        BotCommand {
+            drive: DriveInput::throttle_steer(throttle, dec64!(0)),
+            ..BotCommand::default()
+        }
+    }
+
+    fn save(&self) -> Option<SavedBot> {
+        Some(SavedBot::Shuttle(*self))
+    }
+}
+
+/// Hunts down the nearest enemy on radar, closing to `range` and firing whenever the gun is
+/// roughly on target. Turns on the spot to sweep for enemies when it sees none.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Tracker {
+    /// Distance to close to before stopping.
+    pub range: Scalar,
+    /// How far off target, in radians, the gun may be and still fire.
+    pub aim_tolerance: Scalar,
+}
+
+impl Default for Tracker {
+    fn default() -> Self {
+        Tracker {
+            range: dec64!(150),
+            aim_tolerance: dec64!(0.05),
+        }
+    }
+}
+
+impl BotController for Tracker {
+    fn think(&mut self, view: &BotView) -> BotCommand {
+        let tank = view.tank;
+        let nearest = view.sensors.contacts.iter().min_by(|a, b| {
+            let distance =
+                |contact: &Contact| contact.position.sub(&tank.position).length_squared();
+            distance(a).cmp(&distance(b)).then(a.id.cmp(&b.id))
+        });
+        let Some(target) = nearest else {
+            return BotCommand {
+                drive: DriveInput::tracks(dec64!(1), dec64!(-1)),
+                turret: TurretCommand::Relative(Angle::ZERO),
+                fire: None,
+            };
+        };
+
+        let offset = target.position.sub(&tank.position);
+        let (distance, bearing) = (offset.length_squared().sqrt(), Angle::of(offset));
+        let heading_error = tank.angle.shortest_to(bearing);
+        let aim_error = tank.turret_world_angle().shortest_to(bearing);
+
+        // turn towards the target, only driving once roughly facing it
+        let steer = (heading_error * dec64!(2)).clamp(dec64!(-1), dec64!(1));
+        let throttle = if distance > self.range && heading_error.abs() < dec64!(0.5) {
+            dec64!(1)
+        } else {
+            dec64!(0)
+        };
+
+        BotCommand {
+            drive: DriveInput::throttle_steer(throttle, steer),
+            turret: TurretCommand::Absolute(bearing),
+            fire: (aim_error.abs() <= self.aim_tolerance && !tank.loadout.weapons.is_empty())
+                .then_some(0),
+        }
+    }
+
+    fn save(&self) -> Option<SavedBot> {
+        Some(SavedBot::Tracker(*self))
+    }
+}
+
+/// Returns the built-in bot with the given name: `sitting_duck`, `circler`, `shuttle` or `tracker`.
+pub fn builtin(name: &str) -> Option<Box<dyn BotController>> {
+    let bot: Box<dyn BotController> = match name {
+        "sitting_duck" => Box::new(SittingDuck),
+        "circler" => Box::new(Circler::default()),
+        "tracker" => Box::new(Tracker::default()),
+        "shuttle" => Box::new(Shuttle::default()),
+        _ => return None,
+    };
+    Some(bot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use crate::util::math::Vec2;
+
+    fn tank(angle: Scalar, turret_angle: Scalar) -> Tank {
+        let mut tank = test_support::tank(0, 0, Vec2::zero());
+        tank.angle = Angle::new(angle);
+        tank.turret_angle = Angle::new(turret_angle);
+        tank
+    }
+
+    fn contact(id: u32, x: Scalar, y: Scalar) -> Contact {
+        Contact {
+            id,
+            team_id: 1,
+            position: Vec2::new(x, y),
+            velocity: Vec2::zero(),
+        }
+    }
+
+    #[test]
+    fn tracker_when_aimed_at_nearest_enemy_should_fire_and_close_in() {
+        // Arrange
+        let tank = tank(dec64!(0), dec64!(0));
+        let sensors = SensorData {
+            zone: None,
+            missiles: Vec::new(),
+            contacts: vec![
+                contact(5, dec64!(0), dec64!(900)),
+                contact(6, dec64!(400), dec64!(0)),
+            ],
+        };
+        let view = BotView {
+            tick: 0,
+            tank: &tank,
+            sensors: &sensors,
+        };
+
+        // Act
+        let command = Tracker::default().think(&view);
+
+        // Assert
+        assert_eq!(command.turret, TurretCommand::Absolute(Angle::ZERO));
+        assert_eq!(command.fire, Some(0));
+        assert_eq!(command.drive, DriveInput::tracks(dec64!(1), dec64!(1)));
+    }
+
+    #[test]
+    fn tracker_when_target_behind_should_turn_without_firing() {
+        // Arrange
+        let tank = tank(dec64!(0), dec64!(0));
+        let sensors = SensorData {
+            zone: None,
+            missiles: Vec::new(),
+            contacts: vec![contact(5, dec64!(-100), dec64!(1))],
+        };
+        let view = BotView {
+            tick: 0,
+            tank: &tank,
+            sensors: &sensors,
+        };
+
+        // Act
+        let command = Tracker::default().think(&view);
+
+        // Assert
+        assert_eq!(command.fire, None);
+        assert_eq!(command.drive.throttle(), dec64!(0));
+        assert!(command.drive.steer() > dec64!(0));
+    }
+
+    #[test]
+    fn tracker_when_nothing_seen_should_sweep_in_place() {
+        // Arrange
+        let tank = tank(dec64!(0), dec64!(0.3));
+        let sensors = SensorData::default();
+        let view = BotView {
+            tick: 0,
+            tank: &tank,
+            sensors: &sensors,
+        };
+
+        // Act
+        let command = Tracker::default().think(&view);
+
+        // Assert
+        assert_eq!(command.fire, None);
+        assert_eq!(command.drive.throttle(), dec64!(0));
+        assert_eq!(command.turret, TurretCommand::Relative(Angle::ZERO));
+    }
+
+    #[test]
+    fn builtin_should_look_up_bots_by_name() {
+        // Arrange & Act
+        let names = ["sitting_duck", "circler", "shuttle", "tracker", "skynet"];
+        let found: Vec<bool> = names.iter().map(|name| builtin(name).is_some()).collect();
+
+        // Assert
+        assert_eq!(found, [true, true, true, true, false]);
+    }
+}
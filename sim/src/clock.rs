@@ -0,0 +1,238 @@
+use crate::util::math::Scalar;
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+
+/// Ticks in a simulated second when the tick rate doesn't say, i.e. when fast-forwarding.
+pub const DEFAULT_TICKS_PER_SECOND: u32 = 60;
+
+/// How fast a running match advances.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TickRate {
+    /// Keep pace with wall-clock time at this many ticks per second.
+    PerSecond(f64),
+    /// Run a fixed number of ticks every frame, however long frames take. Fast-forward.
+    PerFrame(u32),
+}
+
+impl TickRate {
+    /// Returns how simulated time maps onto ticks at this rate.
+    ///
+    /// Real-time rates define the second, rounded to a whole number of ticks. Fast-forward
+    /// only changes how quickly seconds pass on screen, so it keeps the default second.
+    pub fn clock(&self) -> SimClock {
+        let ticks_per_second = match *self {
+            TickRate::PerSecond(ticks) if ticks >= 1.0 => ticks.round() as u32,
+            _ => DEFAULT_TICKS_PER_SECOND,
+        };
+        SimClock::new(ticks_per_second)
+    }
+}
+
+/// Converts between ticks and simulated seconds.
+///
+/// The sim only ever counts ticks. Seconds are for people: durations in configs, timers on
+/// screen, and timestamps in telemetry. Conversions use exact decimal arithmetic, so the same
+/// durations always come out as the same tick counts.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SimClock {
+    ticks_per_second: u32,
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        SimClock::new(DEFAULT_TICKS_PER_SECOND)
+    }
+}
+
+impl SimClock {
+    /// Creates a clock with the given number of ticks per second, at least one.
+    pub fn new(ticks_per_second: u32) -> Self {
+        SimClock {
+            ticks_per_second: ticks_per_second.max(1),
+        }
+    }
+
+    pub fn ticks_per_second(&self) -> u32 {
+        self.ticks_per_second
+    }
+
+    /// Returns how long the given number of ticks lasts, in seconds.
+    pub fn to_seconds(&self, ticks: u64) -> Scalar {
+        Scalar::from(ticks) / Scalar::from(self.ticks_per_second)
+    }
+
+    /// Returns the whole number of ticks closest to a duration in seconds, rounding halves up.
+    /// Negative durations are zero ticks.
+    pub fn to_ticks(&self, seconds: Scalar) -> u64 {
+        let ticks = (seconds * Scalar::from(self.ticks_per_second) + dec64!(0.5)).floor();
+        ticks.max(dec64!(0)).to_u64().unwrap_or(u64::MAX)
+    }
+}
+
+/// Decides how many ticks to run each frame: pause, single-step, real-time and fast-forward.
+///
+/// This only ever affects *when* ticks run, never what happens in them; the sim itself has no
+/// clock besides the tick count, so wall-clock time is tracked here in plain floats.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimeControl {
+    paused: bool,
+    rate: TickRate,
+    /// Cap on ticks per frame in real-time mode, so a long hitch doesn't snowball.
+    max_ticks_per_frame: u32,
+    accumulator: f64,
+    /// Ticks requested through [`TimeControl::request_ticks`], run even while paused.
+    pending: u32,
+}
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        TimeControl {
+            paused: false,
+            rate: TickRate::PerSecond(60.0),
+            max_ticks_per_frame: 8,
+            accumulator: 0.0,
+            pending: 0,
+        }
+    }
+}
+
+impl TimeControl {
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resumes from a pause. Time spent paused is not caught up on.
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.accumulator = 0.0;
+    }
+
+    pub fn rate(&self) -> TickRate {
+        self.rate
+    }
+
+    /// Changes the tick rate. Non-positive rates per second are ignored.
+    pub fn set_rate(&mut self, rate: TickRate) {
+        if let TickRate::PerSecond(ticks) = rate
+            && (ticks.is_nan() || ticks <= 0.0)
+        {
+            return;
+        }
+        self.rate = rate;
+        self.accumulator = 0.0;
+    }
+
+    pub fn set_max_ticks_per_frame(&mut self, max: u32) {
+        self.max_ticks_per_frame = max.max(1);
+    }
+
+    /// Queues ticks to run on the next frame, even if paused. Used to single-step.
+    pub fn request_ticks(&mut self, ticks: u32) {
+        self.pending = self.pending.saturating_add(ticks);
+    }
+
+    /// Advances wall-clock time by `delta` seconds and returns how many ticks to run now.
+    pub fn frame(&mut self, delta: f64) -> u32 {
+        let requested = std::mem::take(&mut self.pending);
+        if self.paused {
+            return requested;
+        }
+
+        let scheduled = match self.rate {
+            TickRate::PerFrame(ticks) => ticks,
+            TickRate::PerSecond(rate) => {
+                self.accumulator += delta.max(0.0) * rate;
+                let ticks = (self.accumulator.floor() as u32).min(self.max_ticks_per_frame);
+                self.accumulator -= ticks as f64;
+                // drop whatever the cap cut off instead of carrying it forever
+                self.accumulator = self.accumulator.min(1.0);
+                ticks
+            }
+        };
+        scheduled.saturating_add(requested)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_when_real_time_should_carry_fractional_ticks() {
+        // Arrange
+        let mut time = TimeControl::default();
+        time.set_rate(TickRate::PerSecond(10.0));
+
+        // Act
+        let ticks: Vec<u32> = (0..4).map(|_| time.frame(0.075)).collect();
+
+        // Assert
+        // 0.75 ticks per frame: 0.75, 1.5, 2.25, 3.0
+        assert_eq!(ticks, [0, 1, 1, 1]);
+    }
+
+    #[test]
+    fn frame_when_hitching_should_cap_ticks_per_frame() {
+        // Arrange
+        let mut time = TimeControl::default();
+        time.set_max_ticks_per_frame(4);
+
+        // Act
+        let hitch = time.frame(10.0);
+        let next = time.frame(1.0 / 60.0);
+
+        // Assert
+        assert_eq!(hitch, 4);
+        assert!(next <= 2);
+    }
+
+    #[test]
+    fn frame_when_paused_should_only_run_requested_ticks() {
+        // Arrange
+        let mut time = TimeControl::default();
+        time.pause();
+
+        // Act
+        let idle = time.frame(1.0);
+        time.request_ticks(1);
+        let stepped = time.frame(1.0);
+        let after = time.frame(1.0);
+
+        // Assert
+        assert_eq!((idle, stepped, after), (0, 1, 0));
+    }
+
+    #[test]
+    fn sim_clock_should_round_trip_ticks_and_seconds() {
+        // Arrange
+        let clock = TickRate::PerSecond(30.0).clock();
+
+        // Act
+        let seconds = clock.to_seconds(45);
+        let ticks = clock.to_ticks(dec64!(1.5));
+
+        // Assert
+        assert_eq!(seconds, dec64!(1.5));
+        assert_eq!(ticks, 45);
+        assert_eq!(clock.to_ticks(dec64!(0.05)), 2);
+        assert_eq!(clock.to_ticks(dec64!(-1)), 0);
+        assert_eq!(TickRate::PerFrame(50).clock(), SimClock::default());
+    }
+
+    #[test]
+    fn frame_when_fast_forwarding_should_ignore_frame_time() {
+        // Arrange
+        let mut time = TimeControl::default();
+        time.set_rate(TickRate::PerFrame(50));
+
+        // Act
+        let ticks = time.frame(0.0);
+
+        // Assert
+        assert_eq!(ticks, 50);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::agent::AgentAction;
+use crate::bots::{BotCommand, ControlOverride};
+use crate::effects::StatusEffectSpec;
+use crate::physics::drivetrain::DriveInput;
+use crate::physics::turret::TurretCommand;
+use crate::sim::TankSpawn;
+use crate::tags::Tag;
+use crate::util::math::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// A change to the match requested from outside the simulation.
+///
+/// Commands are queued and applied together at the start of the next tick, in the order they
+/// were queued, so nothing outside the engine touches the state mid-step. A tick's commands
+/// are also exactly what a lockstep peer or a replay needs to reproduce it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Command {
+    /// Adds a tank, subject to the match's loadout rules.
+    SpawnTank(TankSpawn),
+    /// Adds a projectile with no shooter, e.g. from a turret emplacement.
+    SpawnBullet {
+        weapon_id: u32,
+        position: Vec2,
+        velocity: Vec2,
+    },
+    /// Removes a tank, bullet, obstacle or trigger outright, without destroying it.
+    RemoveEntity {
+        entity_id: u32,
+    },
+    /// Puts a tag on an entity, e.g. so a later scripted command can find it.
+    TagEntity {
+        entity_id: u32,
+        tag: Tag,
+    },
+    /// Takes a tag off an entity.
+    UntagEntity {
+        entity_id: u32,
+        tag: Tag,
+    },
+    /// Removes every entity with a tag outright, like [`Command::RemoveEntity`], e.g. to open a
+    /// gate. Rejected if nothing has the tag.
+    RemoveTagged {
+        tag: Tag,
+    },
+    /// Moves a tank to another team. Team size and budget limits aren't rechecked.
+    SetTeam {
+        tank_id: u32,
+        team_id: u32,
+    },
+    SetDriveInput {
+        tank_id: u32,
+        input: DriveInput,
+    },
+    SetTurretCommand {
+        tank_id: u32,
+        command: TurretCommand,
+    },
+    SetFire {
+        tank_id: u32,
+        slot: Option<u32>,
+    },
+    /// Sets what a learning agent's tank does from the next tick onwards. Ignored unless the
+    /// tank is controlled by an agent.
+    SetAgentAction {
+        tank_id: u32,
+        action: AgentAction,
+    },
+    /// Sets what a manually controlled tank does from the next tick onwards, e.g. from a
+    /// human's gamepad. Ignored unless the tank is under manual control.
+    SetManualInput {
+        tank_id: u32,
+        input: BotCommand,
+    },
+    /// Takes channels of a tank's controls from whatever controls it, from the next tick
+    /// onwards, replacing any taken before. An empty override hands them all back.
+    SetOverride {
+        tank_id: u32,
+        channels: ControlOverride,
+    },
+    /// Puts a status effect on a tank, e.g. from a pickup scripted in the scenario.
+    ApplyEffect {
+        tank_id: u32,
+        effect: StatusEffectSpec,
+    },
+    /// Cheat: moves a tank, bringing it to a stop.
+    Teleport {
+        tank_id: u32,
+        position: Vec2,
+    },
+    /// Cheat: sets a living tank's health, destroying it at zero. Not capped by its class.
+    SetHealth {
+        tank_id: u32,
+        health: u32,
+    },
+    /// Cheat: hands a team's flag to a tank in capture the flag.
+    GiveFlag {
+        tank_id: u32,
+        flag_team_id: u32,
+    },
+    /// Cheat: destroys a tank, without a wreck blast, or removes any other entity.
+    Kill {
+        entity_id: u32,
+    },
+}
+
+impl Command {
+    /// Returns whether the command is a development cheat, only applied when the match rules
+    /// allow cheats.
+    pub fn is_cheat(&self) -> bool {
+        matches!(
+            self,
+            Command::Teleport { .. }
+                | Command::SetHealth { .. }
+                | Command::GiveFlag { .. }
+                | Command::Kill { .. }
+        )
+    }
+}
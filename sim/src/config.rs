@@ -0,0 +1,401 @@
+use crate::arena::{ArenaConfig, ArenaIssue};
+use crate::clock::{SimClock, TickRate};
+use crate::limits::BotLimits;
+use crate::physics::broadphase;
+use crate::rules::{FriendlyFire, MatchConfig};
+use crate::spec::{SpecError, SpecTable};
+use crate::util::math::Scalar;
+use crate::vm::{Execution, abi};
+use crate::watchdog::{self, WatchdogConfig};
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Cell sizes of the grids the engine lays over the arena.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GridResolution {
+    /// Cell size of the grid used to find colliding tanks.
+    pub broadphase_cell_size: Scalar,
+    /// Cell size of the path planning grid.
+    pub nav_cell_size: Scalar,
+}
+
+impl Default for GridResolution {
+    fn default() -> Self {
+        GridResolution {
+            broadphase_cell_size: dec64!(64),
+            nav_cell_size: dec64!(16),
+        }
+    }
+}
+
+/// Everything needed to set up a match, in one place.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SimConfig {
+    pub tick_rate: TickRate,
+    pub arena: ArenaConfig,
+    pub grid: GridResolution,
+    /// What every tank's controller may use per tick.
+    pub limits: BotLimits,
+    /// How tanks' programs are run. Changes how fast they run, never what they do.
+    #[serde(default)]
+    pub vm_execution: Execution,
+    pub specs: SpecTable,
+    /// Match rules, including physics settings such as substeps and ramming damage.
+    pub rules: MatchConfig,
+    /// Wall-clock budget per tick, for diagnosing slow ticks. Off by default, and unavailable
+    /// on wasm32, which has no clock to time ticks with.
+    #[serde(default)]
+    pub watchdog: Option<WatchdogConfig>,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig {
+            tick_rate: TickRate::PerSecond(60.0),
+            arena: ArenaConfig::default(),
+            grid: GridResolution::default(),
+            limits: BotLimits::default(),
+            vm_execution: Execution::default(),
+            specs: SpecTable::default(),
+            rules: MatchConfig::default(),
+            watchdog: None,
+        }
+    }
+}
+
+/// Problems that make a config unusable.
+#[derive(Debug)]
+pub enum ConfigError {
+    Parse(String),
+    InvalidTickRate,
+    Arena(ArenaIssue),
+    NonPositiveCellSize {
+        grid: &'static str,
+    },
+    /// Broad-phase cells smaller than a tank make every tank span several cells.
+    CellSmallerThanTank {
+        cell_size: Scalar,
+        spec_id: u32,
+    },
+    Specs(SpecError),
+    NoSubsteps,
+    NegativeRamDamage,
+    /// A physics tolerance is negative, or the tank speed limit isn't positive.
+    InvalidNumericPolicy,
+    NegativeZoneRadius,
+    NoRounds,
+    /// Reduced friendly fire can't deal more than full damage.
+    FriendlyFireAboveFull,
+    NegativeHandicap {
+        team_id: u32,
+    },
+    VmBudgetExceeded {
+        spec_id: u32,
+        clock_speed: u32,
+        max: u32,
+    },
+    /// Team ROMs can't outgrow the window they're mapped into.
+    TeamRomTooLarge {
+        words: u32,
+    },
+    /// A tick budget was set on a target without a wall clock, i.e. wasm32.
+    WatchdogUnsupported,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Parse(message) => write!(f, "invalid sim config: {message}"),
+            ConfigError::InvalidTickRate => write!(f, "tick rate must be positive"),
+            ConfigError::Arena(issue) => write!(f, "{issue}"),
+            ConfigError::NonPositiveCellSize { grid } => {
+                write!(f, "{grid} cell size must be positive")
+            }
+            ConfigError::CellSmallerThanTank { cell_size, spec_id } => write!(
+                f,
+                "broad-phase cells of {cell_size} are smaller than tank spec {spec_id}"
+            ),
+            ConfigError::Specs(error) => write!(f, "{error}"),
+            ConfigError::NoSubsteps => write!(f, "at least one physics substep is needed"),
+            ConfigError::NegativeRamDamage => write!(f, "ram damage can't be negative"),
+            ConfigError::InvalidNumericPolicy => {
+                write!(
+                    f,
+                    "physics tolerances can't be negative and tanks need a speed limit"
+                )
+            }
+            ConfigError::NegativeZoneRadius => write!(f, "safe zone radii can't be negative"),
+            ConfigError::NoRounds => write!(f, "a series needs at least one round"),
+            ConfigError::FriendlyFireAboveFull => {
+                write!(f, "reduced friendly fire can't exceed 100 percent")
+            }
+            ConfigError::NegativeHandicap { team_id } => {
+                write!(f, "team {team_id}'s handicap multipliers can't be negative")
+            }
+            ConfigError::VmBudgetExceeded {
+                spec_id,
+                clock_speed,
+                max,
+            } => write!(
+                f,
+                "tank spec {spec_id} runs {clock_speed} instructions per tick, over the limit of {max}"
+            ),
+            ConfigError::TeamRomTooLarge { words } => write!(
+                f,
+                "team ROMs of {words} words don't fit the {} words mapped for them",
+                abi::MAX_TEAM_ROM_WORDS
+            ),
+            ConfigError::WatchdogUnsupported => {
+                write!(f, "tick budgets need a wall clock, which this target lacks")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// A [`SimConfig`] that has passed [`SimConfig::validate`]. The only way to get one is to
+/// validate a config, so engines built from one can rely on it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidatedConfig(SimConfig);
+
+impl ValidatedConfig {
+    pub fn get(&self) -> &SimConfig {
+        &self.0
+    }
+}
+
+impl Default for ValidatedConfig {
+    fn default() -> Self {
+        SimConfig::default()
+            .validate()
+            .expect("default config is valid")
+    }
+}
+
+impl SimConfig {
+    /// Parses and validates a config.
+    pub fn from_json(json: &str) -> Result<ValidatedConfig, Vec<ConfigError>> {
+        let config: SimConfig =
+            serde_json::from_str(json).map_err(|e| vec![ConfigError::Parse(e.to_string())])?;
+        config.validate()
+    }
+
+    /// Returns how ticks map onto simulated seconds under this config's tick rate.
+    pub fn clock(&self) -> SimClock {
+        self.tick_rate.clock()
+    }
+
+    /// Checks the config, reporting every problem found rather than just the first.
+    ///
+    /// Durations given in seconds are converted to ticks along the way.
+    pub fn validate(mut self) -> Result<ValidatedConfig, Vec<ConfigError>> {
+        let mut errors = Vec::new();
+
+        if let TickRate::PerSecond(ticks) = self.tick_rate
+            && (ticks.is_nan() || ticks <= 0.0)
+        {
+            errors.push(ConfigError::InvalidTickRate);
+        }
+        if let TickRate::PerFrame(0) = self.tick_rate {
+            errors.push(ConfigError::InvalidTickRate);
+        }
+        errors.extend(self.arena.validate().into_iter().map(ConfigError::Arena));
+
+        let cells = [
+            ("broad-phase", self.grid.broadphase_cell_size),
+            ("navigation", self.grid.nav_cell_size),
+        ];
+        for (grid, cell_size) in cells {
+            if cell_size <= dec64!(0) {
+                errors.push(ConfigError::NonPositiveCellSize { grid });
+            }
+        }
+        for spec in &self.specs.tanks {
+            // a tank's broad-phase box, which can be as wide as its hull at any heading
+            let reach = broadphase::reach(spec) * dec64!(2);
+            if self.grid.broadphase_cell_size > dec64!(0) && self.grid.broadphase_cell_size < reach
+            {
+                errors.push(ConfigError::CellSmallerThanTank {
+                    cell_size: self.grid.broadphase_cell_size,
+                    spec_id: spec.id,
+                });
+            }
+            if spec.vm_clock_speed > self.limits.instructions_per_tick {
+                errors.push(ConfigError::VmBudgetExceeded {
+                    spec_id: spec.id,
+                    clock_speed: spec.vm_clock_speed,
+                    max: self.limits.instructions_per_tick,
+                });
+            }
+        }
+        if self.limits.team_rom_words > abi::MAX_TEAM_ROM_WORDS {
+            errors.push(ConfigError::TeamRomTooLarge {
+                words: self.limits.team_rom_words,
+            });
+        }
+        if self.watchdog.is_some() && !watchdog::HAS_CLOCK {
+            errors.push(ConfigError::WatchdogUnsupported);
+        }
+        if let Err(error) = self.specs.check_ids() {
+            errors.push(ConfigError::Specs(error));
+        }
+
+        if self.rules.substeps == 0 {
+            errors.push(ConfigError::NoSubsteps);
+        }
+        if self.rules.ram_damage < dec64!(0) {
+            errors.push(ConfigError::NegativeRamDamage);
+        }
+        if !self.rules.numeric.is_valid() {
+            errors.push(ConfigError::InvalidNumericPolicy);
+        }
+        if let Some(zone) = &self.rules.zone
+            && (zone.start_radius < dec64!(0) || zone.end_radius < dec64!(0))
+        {
+            errors.push(ConfigError::NegativeZoneRadius);
+        }
+        if self
+            .rules
+            .rounds
+            .as_ref()
+            .is_some_and(|rounds| rounds.best_of == 0)
+        {
+            errors.push(ConfigError::NoRounds);
+        }
+        if matches!(
+            self.rules.friendly_fire,
+            FriendlyFire::Reduced { percent } if percent > 100
+        ) {
+            errors.push(ConfigError::FriendlyFireAboveFull);
+        }
+        for (&team_id, handicap) in &self.rules.handicaps {
+            if handicap.is_negative() {
+                errors.push(ConfigError::NegativeHandicap { team_id });
+            }
+        }
+
+        if errors.is_empty() {
+            let clock = self.clock();
+            for weapon in self.specs.weapons.iter_mut() {
+                if let Some(seconds) = weapon.reload_seconds.take() {
+                    weapon.reload_ticks = clock.to_ticks(seconds).min(u32::MAX as u64) as u32;
+                }
+            }
+            Ok(ValidatedConfig(self))
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_when_watchdog_set_should_pass_only_where_ticks_can_be_timed() {
+        // Arrange
+        let config = SimConfig {
+            watchdog: Some(WatchdogConfig {
+                budget_micros: 1000,
+                throttle: false,
+            }),
+            ..SimConfig::default()
+        };
+
+        // Act
+        let errors = config.validate().err().unwrap_or_default();
+
+        // Assert
+        assert_eq!(errors.is_empty(), watchdog::HAS_CLOCK);
+        assert!(
+            errors
+                .iter()
+                .all(|error| matches!(error, ConfigError::WatchdogUnsupported))
+        );
+    }
+
+    #[test]
+    fn validate_when_default_should_pass() {
+        // Act
+        let result = SimConfig::default().validate();
+
+        // Assert
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn validate_should_convert_reload_seconds_at_tick_rate() {
+        // Arrange
+        let mut config = SimConfig {
+            tick_rate: TickRate::PerSecond(30.0),
+            ..SimConfig::default()
+        };
+        config.specs.weapons[0].reload_seconds = Some(dec64!(2.5));
+
+        // Act
+        let config = config.validate().unwrap();
+
+        // Assert
+        let cannon = &config.get().specs.weapons[0];
+        assert_eq!(cannon.reload_ticks, 75);
+        assert_eq!(cannon.reload_seconds, None);
+        assert_eq!(config.get().specs.weapons[1].reload_ticks, 6);
+    }
+
+    #[test]
+    fn validate_should_report_every_problem() {
+        // Arrange
+        let mut config = SimConfig::default();
+        config.grid.broadphase_cell_size = dec64!(10);
+        config.grid.nav_cell_size = dec64!(0);
+        config.limits.instructions_per_tick = 100;
+        config.rules.substeps = 0;
+        config.limits.team_rom_words = abi::MAX_TEAM_ROM_WORDS + 1;
+        config.rules.numeric.penetration_slop = dec64!(-0.1);
+        let duplicate = config.specs.weapons[0].clone();
+        config.specs.weapons.push(duplicate);
+
+        // Act
+        let errors = config.validate().unwrap_err();
+
+        // Assert
+        let messages: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+        assert!(matches!(
+            errors[0],
+            ConfigError::NonPositiveCellSize { grid: "navigation" }
+        ));
+        assert!(
+            errors
+                .iter()
+                .any(|error| matches!(error, ConfigError::CellSmallerThanTank { spec_id: 2, .. }))
+        );
+        assert!(errors.iter().any(|error| matches!(
+            error,
+            ConfigError::VmBudgetExceeded {
+                spec_id: 0,
+                clock_speed: 120,
+                ..
+            }
+        )));
+        assert!(
+            errors.iter().any(|error| matches!(
+                error,
+                ConfigError::Specs(SpecError::DuplicateWeaponSpec(_))
+            ))
+        );
+        assert!(messages.contains(&"at least one physics substep is needed".to_string()));
+        assert!(
+            errors
+                .iter()
+                .any(|error| matches!(error, ConfigError::TeamRomTooLarge { .. }))
+        );
+        assert!(
+            errors
+                .iter()
+                .any(|error| matches!(error, ConfigError::InvalidNumericPolicy))
+        );
+    }
+}
@@ -0,0 +1,317 @@
+use crate::commands::Command;
+use crate::log::Severity;
+use crate::sim::SimEngine;
+use crate::state::VmState;
+use crate::tags::Tag;
+use crate::util::math::Vec2;
+use std::fmt;
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// Words of VM memory per line of a dump.
+const DUMP_WIDTH: usize = 8;
+
+/// Log records printed when the console isn't told how many.
+const LOG_LINES: usize = 20;
+
+/// Problems with a console line.
+#[derive(Debug, PartialEq)]
+pub enum ConsoleError {
+    /// The match rules don't allow cheats.
+    CheatsDisabled,
+    Empty,
+    UnknownCommand(String),
+    /// The arguments didn't fit the command; holds its usage.
+    Usage(&'static str),
+    UnknownTank(u32),
+}
+
+impl fmt::Display for ConsoleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConsoleError::CheatsDisabled => write!(f, "cheats are disabled for this match"),
+            ConsoleError::Empty => write!(f, "no command given"),
+            ConsoleError::UnknownCommand(name) => write!(f, "unknown command {name:?}"),
+            ConsoleError::Usage(usage) => write!(f, "usage: {usage}"),
+            ConsoleError::UnknownTank(tank_id) => write!(f, "no tank with id {tank_id}"),
+        }
+    }
+}
+
+impl std::error::Error for ConsoleError {}
+
+/// A parsed console line.
+#[derive(Debug, PartialEq)]
+pub enum ConsoleCommand {
+    /// A cheat to queue for the next tick.
+    Queue(Command),
+    /// Prints a tank's VM registers and memory.
+    DumpMemory { tank_id: u32 },
+    /// Lists the entities with a tag.
+    ListTagged { tag: Tag },
+    /// Prints the last `count` log records at `min_severity` or above.
+    ShowLog {
+        min_severity: Severity,
+        count: usize,
+    },
+}
+
+/// Parses one line of the development console:
+///
+/// - `teleport <tank> <x> <y>`
+/// - `health <tank> <health>`
+/// - `give_flag <tank> <flag team>`
+/// - `kill <entity>`
+/// - `dump_memory <tank>`
+/// - `tag <entity> <tag>`
+/// - `tagged <tag>`
+/// - `log [debug|info|warn|error] [count]`
+pub fn parse(line: &str) -> Result<ConsoleCommand, ConsoleError> {
+    let mut words = line.split_whitespace();
+    let name = words.next().ok_or(ConsoleError::Empty)?;
+    let args: Vec<&str> = words.collect();
+    let command = match (name, args.as_slice()) {
+        ("teleport", [tank_id, x, y]) => Command::Teleport {
+            tank_id: arg(tank_id, TELEPORT)?,
+            position: Vec2::new(arg(x, TELEPORT)?, arg(y, TELEPORT)?),
+        },
+        ("teleport", _) => return Err(ConsoleError::Usage(TELEPORT)),
+        ("health", [tank_id, health]) => Command::SetHealth {
+            tank_id: arg(tank_id, HEALTH)?,
+            health: arg(health, HEALTH)?,
+        },
+        ("health", _) => return Err(ConsoleError::Usage(HEALTH)),
+        ("give_flag", [tank_id, flag_team_id]) => Command::GiveFlag {
+            tank_id: arg(tank_id, GIVE_FLAG)?,
+            flag_team_id: arg(flag_team_id, GIVE_FLAG)?,
+        },
+        ("give_flag", _) => return Err(ConsoleError::Usage(GIVE_FLAG)),
+        ("kill", [entity_id]) => Command::Kill {
+            entity_id: arg(entity_id, KILL)?,
+        },
+        ("kill", _) => return Err(ConsoleError::Usage(KILL)),
+        ("dump_memory", [tank_id]) => {
+            return Ok(ConsoleCommand::DumpMemory {
+                tank_id: arg(tank_id, DUMP_MEMORY)?,
+            });
+        }
+        ("dump_memory", _) => return Err(ConsoleError::Usage(DUMP_MEMORY)),
+        ("tag", [entity_id, tag]) => Command::TagEntity {
+            entity_id: arg(entity_id, TAG)?,
+            tag: arg(tag, TAG)?,
+        },
+        ("tag", _) => return Err(ConsoleError::Usage(TAG)),
+        ("tagged", [tag]) => {
+            return Ok(ConsoleCommand::ListTagged {
+                tag: arg(tag, TAGGED)?,
+            });
+        }
+        ("tagged", _) => return Err(ConsoleError::Usage(TAGGED)),
+        ("log", args) if args.len() <= 2 => {
+            return Ok(ConsoleCommand::ShowLog {
+                min_severity: args
+                    .first()
+                    .map_or(Ok(Severity::Debug), |word| arg(word, LOG))?,
+                count: args.get(1).map_or(Ok(LOG_LINES), |word| arg(word, LOG))?,
+            });
+        }
+        ("log", _) => return Err(ConsoleError::Usage(LOG)),
+        _ => return Err(ConsoleError::UnknownCommand(name.to_string())),
+    };
+    Ok(ConsoleCommand::Queue(command))
+}
+
+const TELEPORT: &str = "teleport <tank> <x> <y>";
+const HEALTH: &str = "health <tank> <health>";
+const GIVE_FLAG: &str = "give_flag <tank> <flag team>";
+const KILL: &str = "kill <entity>";
+const DUMP_MEMORY: &str = "dump_memory <tank>";
+const TAG: &str = "tag <entity> <tag>";
+const TAGGED: &str = "tagged <tag>";
+const LOG: &str = "log [debug|info|warn|error] [count]";
+
+fn arg<T: FromStr>(word: &str, usage: &'static str) -> Result<T, ConsoleError> {
+    word.parse().map_err(|_| ConsoleError::Usage(usage))
+}
+
+/// Runs a console line against a match, returning what to print.
+///
+/// Cheats go through the command queue like any other input, so they take effect next tick and
+/// show up in [`SimEngine::applied_commands`] for lockstep peers and replays to reproduce.
+pub fn execute(engine: &mut SimEngine, line: &str) -> Result<String, ConsoleError> {
+    if !engine.cheats_enabled() {
+        return Err(ConsoleError::CheatsDisabled);
+    }
+    match parse(line)? {
+        ConsoleCommand::Queue(command) => {
+            engine.queue_command(command);
+            Ok("queued for the next tick".to_string())
+        }
+        ConsoleCommand::DumpMemory { tank_id } => {
+            let tank = engine
+                .state()
+                .tank(tank_id)
+                .ok_or(ConsoleError::UnknownTank(tank_id))?;
+            Ok(dump_memory(&tank.vm))
+        }
+        ConsoleCommand::ListTagged { tag } => {
+            let ids: Vec<String> = engine
+                .state()
+                .tags
+                .tagged(&tag)
+                .iter()
+                .map(u32::to_string)
+                .collect();
+            Ok(ids.join(" "))
+        }
+        ConsoleCommand::ShowLog {
+            min_severity,
+            count,
+        } => Ok(engine.log().dump(min_severity, count)),
+    }
+}
+
+/// Formats a VM's registers, then its memory as hex, [`DUMP_WIDTH`] words to a line.
+pub fn dump_memory(vm: &VmState) -> String {
+    let mut out = format!(
+        "pc={} sp={} halted={} fault={:?}\n",
+        vm.pc, vm.sp, vm.halted, vm.fault
+    );
+    for (row, words) in vm.memory.chunks(DUMP_WIDTH).enumerate() {
+        let _ = write!(out, "{:04x}:", row * DUMP_WIDTH);
+        for word in words {
+            let _ = write!(out, " {word:08x}");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::SimEvent;
+    use crate::sim::TankSpawn;
+    use crate::state::SimState;
+    use fastnum::dec64;
+
+    fn engine(cheats: bool) -> (SimEngine, u32) {
+        let mut engine = SimEngine::new(SimState::new(1));
+        engine.set_cheats(cheats);
+        let tank = engine
+            .spawn_tank(TankSpawn::medium(0, Vec2::new_from_f64(100.0, 100.0)))
+            .unwrap();
+        (engine, tank)
+    }
+
+    #[test]
+    fn execute_when_cheats_enabled_should_apply_on_next_tick() {
+        // Arrange
+        let (mut engine, tank) = engine(true);
+
+        // Act
+        execute(&mut engine, &format!("teleport {tank} 250.5 40")).unwrap();
+        execute(&mut engine, &format!("kill {tank}")).unwrap();
+        engine.step();
+
+        // Assert
+        let moved = engine.state().tank(tank).unwrap();
+        assert_eq!(moved.position, Vec2::new(dec64!(250.5), dec64!(40)));
+        assert!(!moved.is_alive());
+        assert!(engine.events().contains(&SimEvent::TankDestroyed {
+            tank_id: tank,
+            killer: None
+        }));
+        assert_eq!(engine.applied_commands().len(), 2);
+    }
+
+    #[test]
+    fn execute_when_cheats_disabled_should_refuse() {
+        // Arrange
+        let (mut engine, tank) = engine(false);
+
+        // Act
+        let result = execute(&mut engine, &format!("health {tank} 1"));
+        engine.queue_command(Command::SetHealth {
+            tank_id: tank,
+            health: 1,
+        });
+        engine.step();
+
+        // Assert
+        assert_eq!(result, Err(ConsoleError::CheatsDisabled));
+        assert!(engine.state().tank(tank).unwrap().health > 1);
+        assert!(
+            engine
+                .events()
+                .contains(&SimEvent::CommandRejected { index: 0 })
+        );
+    }
+
+    #[test]
+    fn execute_when_tagging_should_list_entity_from_next_tick() {
+        // Arrange
+        let (mut engine, tank) = engine(true);
+
+        // Act
+        execute(&mut engine, &format!("tag {tank} scout")).unwrap();
+        let before = execute(&mut engine, "tagged scout").unwrap();
+        engine.step();
+        let after = execute(&mut engine, "tagged scout").unwrap();
+
+        // Assert
+        assert_eq!(before, "");
+        assert_eq!(after, tank.to_string());
+        assert_eq!(
+            parse("tagged 3"),
+            Ok(ConsoleCommand::ListTagged {
+                tag: Tag::Number(3)
+            })
+        );
+    }
+
+    #[test]
+    fn execute_log_should_show_why_commands_were_rejected() {
+        // Arrange
+        let (mut engine, _) = engine(true);
+        execute(&mut engine, "kill 999").unwrap();
+        engine.step();
+
+        // Act
+        let shown = execute(&mut engine, "log warn 5").unwrap();
+
+        // Assert
+        assert_eq!(
+            shown,
+            "[0] warn commands: rejected command 0: Kill { entity_id: 999 }\n"
+        );
+        assert_eq!(parse("log loud"), Err(ConsoleError::Usage(LOG)));
+    }
+
+    #[test]
+    fn parse_when_arguments_wrong_should_give_usage() {
+        // Arrange
+        let lines = [
+            "teleport 1 2",
+            "health one 5",
+            "warp 1",
+            "",
+            "dump_memory 3",
+        ];
+
+        // Act
+        let parsed: Vec<_> = lines.iter().map(|line| parse(line)).collect();
+
+        // Assert
+        assert_eq!(
+            parsed,
+            vec![
+                Err(ConsoleError::Usage(TELEPORT)),
+                Err(ConsoleError::Usage(HEALTH)),
+                Err(ConsoleError::UnknownCommand("warp".to_string())),
+                Err(ConsoleError::Empty),
+                Ok(ConsoleCommand::DumpMemory { tank_id: 3 }),
+            ]
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use crate::events::SimEvent;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Two tanks that have been touching since `since`.
+///
+/// The ID stays the same for as long as the hulls keep touching, so gameplay code can follow a
+/// contact from its first tick to its last.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TankContact {
+    pub id: u32,
+    /// The lower of the two tank IDs.
+    pub first_id: u32,
+    pub second_id: u32,
+    /// Tick the contact began on.
+    pub since: u64,
+}
+
+/// Hands out contact IDs and keeps the list of ongoing contacts.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Contacts {
+    next_id: u32,
+    pub active: Vec<TankContact>,
+}
+
+impl Contacts {
+    /// Returns the ongoing contact between two tanks, in either order.
+    pub fn between(&self, a: u32, b: u32) -> Option<&TankContact> {
+        let (first_id, second_id) = (a.min(b), a.max(b));
+        self.active
+            .iter()
+            .find(|contact| contact.first_id == first_id && contact.second_id == second_id)
+    }
+
+    /// Updates contacts from the pairs of tanks found touching during a tick, emitting an end
+    /// or a persist for each ongoing contact, in ID order, then a begin for each new one.
+    ///
+    /// Pairs may be given in either order and more than once.
+    pub fn update(&mut self, touching: &[(u32, u32)], tick: u64, events: &mut Vec<SimEvent>) {
+        let mut touching: BTreeSet<(u32, u32)> = touching
+            .iter()
+            .map(|(a, b)| (*a.min(b), *a.max(b)))
+            .collect();
+
+        self.active.retain(|contact| {
+            let pair = (contact.first_id, contact.second_id);
+            let persists = touching.remove(&pair);
+            let (contact_id, first_id, second_id) = (contact.id, pair.0, pair.1);
+            events.push(if persists {
+                SimEvent::ContactPersisted {
+                    contact_id,
+                    first_id,
+                    second_id,
+                }
+            } else {
+                SimEvent::ContactEnded {
+                    contact_id,
+                    first_id,
+                    second_id,
+                }
+            });
+            persists
+        });
+
+        for (first_id, second_id) in touching {
+            let id = self.next_id;
+            self.next_id += 1;
+            self.active.push(TankContact {
+                id,
+                first_id,
+                second_id,
+                since: tick,
+            });
+            events.push(SimEvent::ContactBegan {
+                contact_id: id,
+                first_id,
+                second_id,
+            });
+        }
+    }
+
+    /// Ends every contact involving a tank, e.g. when it's removed from the match.
+    pub fn forget(&mut self, tank_id: u32) {
+        self.active
+            .retain(|contact| contact.first_id != tank_id && contact.second_id != tank_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_should_keep_contact_ids_for_the_life_of_a_contact() {
+        // Arrange
+        let mut contacts = Contacts::default();
+
+        // Act
+        let mut began = Vec::new();
+        contacts.update(&[(4, 2), (2, 4)], 10, &mut began);
+        let mut persisted = Vec::new();
+        contacts.update(&[(2, 4), (4, 7)], 11, &mut persisted);
+        let mut ended = Vec::new();
+        contacts.update(&[(4, 7)], 12, &mut ended);
+
+        // Assert
+        assert_eq!(
+            began,
+            [SimEvent::ContactBegan {
+                contact_id: 0,
+                first_id: 2,
+                second_id: 4,
+            }]
+        );
+        assert_eq!(
+            persisted,
+            [
+                SimEvent::ContactPersisted {
+                    contact_id: 0,
+                    first_id: 2,
+                    second_id: 4,
+                },
+                SimEvent::ContactBegan {
+                    contact_id: 1,
+                    first_id: 4,
+                    second_id: 7,
+                },
+            ]
+        );
+        assert_eq!(
+            ended[0],
+            SimEvent::ContactEnded {
+                contact_id: 0,
+                first_id: 2,
+                second_id: 4,
+            }
+        );
+        let remaining = contacts.between(7, 4).unwrap();
+        assert_eq!((remaining.id, remaining.since), (1, 11));
+        assert_eq!(contacts.active.len(), 1);
+    }
+}
@@ -0,0 +1,233 @@
+use crate::spec::{ArmorSpec, WeaponSpec};
+use crate::util::math::{ConvertToScalar, Scalar, Vec2};
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+
+/// Shots striking armor at a shallower angle than this (cosine of ~70° from the normal) glance off.
+pub const RICOCHET_COS: Scalar = dec64!(0.342);
+
+/// Which face of a hull a shot struck.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArmorSide {
+    Front,
+    Side,
+    Rear,
+}
+
+impl ArmorSide {
+    /// Classifies a hit from the struck face's normal in the hull's local frame.
+    pub fn from_local_normal(normal: Vec2) -> Self {
+        if normal.x > dec64!(0) {
+            ArmorSide::Front
+        } else if normal.x < dec64!(0) {
+            ArmorSide::Rear
+        } else {
+            ArmorSide::Side
+        }
+    }
+
+    /// Returns the armor thickness covering this side.
+    pub fn thickness(&self, armor: &ArmorSpec) -> Scalar {
+        match self {
+            ArmorSide::Front => armor.front,
+            ArmorSide::Side => armor.side,
+            ArmorSide::Rear => armor.rear,
+        }
+    }
+}
+
+/// What happened when a shot struck armor.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HitOutcome {
+    Penetrated { damage: u32 },
+    Ricochet,
+    Absorbed,
+}
+
+/// Everything about an impact that matters for resolving its damage.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Impact {
+    pub side: ArmorSide,
+    /// Cosine of the angle between the shot and the struck face's normal. 1 is head-on.
+    pub cos_incidence: Scalar,
+    /// Distance the shot travelled before striking.
+    pub distance: Scalar,
+}
+
+/// Returns the fraction of a weapon's damage and penetration left after travelling `distance`.
+///
+/// Full strength up to `falloff_start`, then linear down to `min_falloff` at `falloff_end`.
+pub fn falloff_factor(weapon: &WeaponSpec, distance: Scalar) -> Scalar {
+    if distance <= weapon.falloff_start {
+        return dec64!(1);
+    }
+    if distance >= weapon.falloff_end {
+        return weapon.min_falloff;
+    }
+
+    let progress = (distance - weapon.falloff_start) / (weapon.falloff_end - weapon.falloff_start);
+    dec64!(1) - progress * (dec64!(1) - weapon.min_falloff)
+}
+
+/// Resolves a shot against a hull's armor.
+///
+/// Sloped armor is effectively thicker (`thickness / cos(incidence)`), and shots that strike
+/// too shallow ricochet regardless of penetration.
+pub fn resolve_hit(weapon: &WeaponSpec, armor: &ArmorSpec, impact: &Impact) -> HitOutcome {
+    if impact.cos_incidence < RICOCHET_COS {
+        return HitOutcome::Ricochet;
+    }
+
+    let falloff = falloff_factor(weapon, impact.distance);
+    let effective_thickness = impact.side.thickness(armor) / impact.cos_incidence;
+    if weapon.penetration * falloff < effective_thickness {
+        return HitOutcome::Absorbed;
+    }
+
+    let damage = (weapon.damage.to_scalar() * falloff)
+        .round(0)
+        .to_u32()
+        .unwrap_or(0);
+    HitOutcome::Penetrated { damage }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spec::SpecTable;
+
+    fn cannon() -> WeaponSpec {
+        SpecTable::default().weapon(0).unwrap().clone()
+    }
+
+    fn armor() -> ArmorSpec {
+        ArmorSpec {
+            front: 40.0.to_scalar(),
+            side: 20.0.to_scalar(),
+            rear: 10.0.to_scalar(),
+        }
+    }
+
+    fn impact(side: ArmorSide, cos_incidence: f64, distance: f64) -> Impact {
+        Impact {
+            side,
+            cos_incidence: cos_incidence.to_scalar(),
+            distance: distance.to_scalar(),
+        }
+    }
+
+    #[test]
+    fn armor_side_from_local_normal_should_classify_faces() {
+        // Arrange & Act & Assert
+        assert_eq!(
+            ArmorSide::from_local_normal(Vec2::new_from_f64(1.0, 0.0)),
+            ArmorSide::Front
+        );
+        assert_eq!(
+            ArmorSide::from_local_normal(Vec2::new_from_f64(-1.0, 0.0)),
+            ArmorSide::Rear
+        );
+        assert_eq!(
+            ArmorSide::from_local_normal(Vec2::new_from_f64(0.0, -1.0)),
+            ArmorSide::Side
+        );
+    }
+
+    #[test]
+    fn falloff_factor_should_interpolate_between_start_and_end() {
+        // Arrange
+        let weapon = cannon();
+        let midpoint = (weapon.falloff_start + weapon.falloff_end) / 2.0.to_scalar();
+
+        // Act & Assert
+        assert_eq!(falloff_factor(&weapon, 0.0.to_scalar()), 1.0.to_scalar());
+        assert_eq!(
+            falloff_factor(&weapon, midpoint),
+            (1.0.to_scalar() + weapon.min_falloff) / 2.0.to_scalar()
+        );
+        assert_eq!(
+            falloff_factor(&weapon, weapon.falloff_end * 2.0.to_scalar()),
+            weapon.min_falloff
+        );
+    }
+
+    #[test]
+    fn resolve_hit_when_head_on_at_close_range_should_penetrate_for_full_damage() {
+        // Arrange
+        let weapon = cannon();
+
+        // Act
+        let outcome = resolve_hit(&weapon, &armor(), &impact(ArmorSide::Rear, 1.0, 10.0));
+
+        // Assert
+        assert_eq!(
+            outcome,
+            HitOutcome::Penetrated {
+                damage: weapon.damage
+            }
+        );
+    }
+
+    #[test]
+    fn resolve_hit_when_grazing_should_ricochet() {
+        // Arrange
+        let weapon = cannon();
+
+        // Act
+        let outcome = resolve_hit(&weapon, &armor(), &impact(ArmorSide::Rear, 0.2, 10.0));
+
+        // Assert
+        assert_eq!(outcome, HitOutcome::Ricochet);
+    }
+
+    #[test]
+    fn resolve_hit_when_armor_too_thick_should_absorb() {
+        // Arrange
+        let weapon = cannon();
+        let heavy = ArmorSpec {
+            front: weapon.penetration * 2.0.to_scalar(),
+            ..armor()
+        };
+
+        // Act
+        let outcome = resolve_hit(&weapon, &heavy, &impact(ArmorSide::Front, 1.0, 10.0));
+
+        // Assert
+        assert_eq!(outcome, HitOutcome::Absorbed);
+    }
+
+    #[test]
+    fn resolve_hit_when_sloped_should_increase_effective_thickness() {
+        // Arrange
+        let weapon = cannon();
+        // just thin enough to stop a head-on shot, but not one at 60°
+        let borderline = ArmorSpec {
+            side: weapon.penetration * 0.6.to_scalar(),
+            ..armor()
+        };
+
+        // Act
+        let head_on = resolve_hit(&weapon, &borderline, &impact(ArmorSide::Side, 1.0, 10.0));
+        let sloped = resolve_hit(&weapon, &borderline, &impact(ArmorSide::Side, 0.5, 10.0));
+
+        // Assert
+        assert!(matches!(head_on, HitOutcome::Penetrated { .. }));
+        assert_eq!(sloped, HitOutcome::Absorbed);
+    }
+
+    #[test]
+    fn resolve_hit_when_far_away_should_deal_reduced_damage() {
+        // Arrange
+        let weapon = cannon();
+        let far = (weapon.falloff_end * 2.0.to_scalar()).to_f64();
+
+        // Act
+        let outcome = resolve_hit(&weapon, &armor(), &impact(ArmorSide::Rear, 1.0, far));
+
+        // Assert
+        match outcome {
+            HitOutcome::Penetrated { damage } => assert!(damage < weapon.damage),
+            other => panic!("expected penetration, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::physics::collision::{AABB, OrientedBox};
+use crate::util::math::{Scalar, Vec2};
+use std::collections::BTreeSet;
+
+/// Kinds of debug primitive that can be collected, each switched on separately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DebugCategory {
+    /// Obstacle and trigger bounds, and tank hulls.
+    Shapes,
+    /// Projectile sweeps.
+    Rays,
+    /// Where bullets struck and tanks collided.
+    Contacts,
+    /// How many tanks each cell of the broad-phase grid holds.
+    Grid,
+}
+
+impl DebugCategory {
+    pub const ALL: [DebugCategory; 4] = [
+        DebugCategory::Shapes,
+        DebugCategory::Rays,
+        DebugCategory::Contacts,
+        DebugCategory::Grid,
+    ];
+
+    /// Looks up a category by its lowercase name, e.g. `"contacts"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "shapes" => Some(DebugCategory::Shapes),
+            "rays" => Some(DebugCategory::Rays),
+            "contacts" => Some(DebugCategory::Contacts),
+            "grid" => Some(DebugCategory::Grid),
+            _ => None,
+        }
+    }
+}
+
+/// A point of contact and the surface normal there, in world space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DebugContact {
+    pub point: Vec2,
+    pub normal: Vec2,
+}
+
+/// Occupancy counts for a uniform grid, in row-major order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GridHeatmap {
+    pub columns: u32,
+    pub rows: u32,
+    pub cell_width: Scalar,
+    pub cell_height: Scalar,
+    pub counts: Vec<u32>,
+    /// A cell size that would suit the tanks better, if the current one is well off.
+    pub suggested_cell_size: Option<Scalar>,
+}
+
+/// Primitives collected over one tick, for drawing on top of the match.
+///
+/// Nothing is recorded for categories that are switched off, so leaving them all off costs a
+/// check per call site.
+#[derive(Clone, Debug, Default)]
+pub struct DebugDraw {
+    enabled: BTreeSet<DebugCategory>,
+    pub aabbs: Vec<AABB>,
+    pub boxes: Vec<OrientedBox>,
+    /// Segments as `(from, to)`.
+    pub rays: Vec<(Vec2, Vec2)>,
+    pub contacts: Vec<DebugContact>,
+    pub grid: Option<GridHeatmap>,
+}
+
+impl DebugDraw {
+    pub fn set_enabled(&mut self, category: DebugCategory, enabled: bool) {
+        if enabled {
+            self.enabled.insert(category);
+        } else {
+            self.enabled.remove(&category);
+            self.clear_category(category);
+        }
+    }
+
+    pub fn is_enabled(&self, category: DebugCategory) -> bool {
+        self.enabled.contains(&category)
+    }
+
+    /// Returns whether any category is switched on.
+    pub fn is_active(&self) -> bool {
+        !self.enabled.is_empty()
+    }
+
+    /// Drops everything collected, ready for the next tick.
+    pub fn clear(&mut self) {
+        for category in DebugCategory::ALL {
+            self.clear_category(category);
+        }
+    }
+
+    fn clear_category(&mut self, category: DebugCategory) {
+        match category {
+            DebugCategory::Shapes => {
+                self.aabbs.clear();
+                self.boxes.clear();
+            }
+            DebugCategory::Rays => self.rays.clear(),
+            DebugCategory::Contacts => self.contacts.clear(),
+            DebugCategory::Grid => self.grid = None,
+        }
+    }
+
+    pub fn aabb(&mut self, aabb: AABB) {
+        if self.is_enabled(DebugCategory::Shapes) {
+            self.aabbs.push(aabb);
+        }
+    }
+
+    pub fn oriented_box(&mut self, hull: OrientedBox) {
+        if self.is_enabled(DebugCategory::Shapes) {
+            self.boxes.push(hull);
+        }
+    }
+
+    pub fn ray(&mut self, from: Vec2, to: Vec2) {
+        if self.is_enabled(DebugCategory::Rays) {
+            self.rays.push((from, to));
+        }
+    }
+
+    pub fn contact(&mut self, point: Vec2, normal: Vec2) {
+        if self.is_enabled(DebugCategory::Contacts) {
+            self.contacts.push(DebugContact { point, normal });
+        }
+    }
+}
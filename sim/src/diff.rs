@@ -0,0 +1,319 @@
+use crate::state::{SimState, fnv1a};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// A part of the state that differences are reported against.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Entity {
+    /// Everything that isn't an entity: the clock, IDs, mode, contacts and bullet slots.
+    World,
+    Tank(u32),
+    Bullet(u32),
+    Obstacle(u32),
+    Trigger(u32),
+}
+
+impl fmt::Display for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Entity::World => write!(f, "world"),
+            Entity::Tank(id) => write!(f, "tank {id}"),
+            Entity::Bullet(id) => write!(f, "bullet {id}"),
+            Entity::Obstacle(id) => write!(f, "obstacle {id}"),
+            Entity::Trigger(id) => write!(f, "trigger {id}"),
+        }
+    }
+}
+
+/// One field that holds different values in the two states.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FieldDiff {
+    /// Dotted path to the field, e.g. `vm.stack.3` or `position.x`.
+    pub path: String,
+    /// The value on each side, or `null` where the field is missing.
+    pub left: Value,
+    pub right: Value,
+}
+
+/// How an entity differs between the two states.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EntityChange {
+    OnlyLeft,
+    OnlyRight,
+    Fields(Vec<FieldDiff>),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EntityDiff {
+    pub entity: Entity,
+    pub change: EntityChange,
+}
+
+/// Exactly where two states disagree, entity by entity and field by field.
+///
+/// For tracking down desyncs: once checksums say two runs diverged at some tick, diff their
+/// states at that tick to see what went wrong first.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StateDiff {
+    /// Differing entities, world first, then by kind and ID.
+    pub entities: Vec<EntityDiff>,
+}
+
+impl StateDiff {
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("diff is always serializable")
+    }
+}
+
+impl fmt::Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "states are identical");
+        }
+        for diff in &self.entities {
+            match &diff.change {
+                EntityChange::OnlyLeft => writeln!(f, "{}: only in left", diff.entity)?,
+                EntityChange::OnlyRight => writeln!(f, "{}: only in right", diff.entity)?,
+                EntityChange::Fields(fields) => {
+                    writeln!(f, "{}:", diff.entity)?;
+                    for field in fields {
+                        writeln!(f, "  {}: {} -> {}", field.path, field.left, field.right)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compares two states, reporting every entity and field that differs.
+pub fn diff_states(left: &SimState, right: &SimState) -> StateDiff {
+    let left = entities(left);
+    let right = entities(right);
+    let ids: BTreeSet<Entity> = left.keys().chain(right.keys()).copied().collect();
+    let entities = ids
+        .into_iter()
+        .filter_map(|entity| {
+            let change = match (left.get(&entity), right.get(&entity)) {
+                (Some(left), Some(right)) => {
+                    let mut fields = Vec::new();
+                    diff_values("", left, right, &mut fields);
+                    if fields.is_empty() {
+                        return None;
+                    }
+                    EntityChange::Fields(fields)
+                }
+                (Some(_), None) => EntityChange::OnlyLeft,
+                (None, _) => EntityChange::OnlyRight,
+            };
+            Some(EntityDiff { entity, change })
+        })
+        .collect();
+    StateDiff { entities }
+}
+
+/// A hash per entity, small enough to send between peers so they can find out which entities
+/// disagree without exchanging whole states.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StateDigest {
+    pub tick: u64,
+    pub hashes: BTreeMap<Entity, u64>,
+}
+
+impl StateDigest {
+    pub fn of(state: &SimState) -> Self {
+        let hashes = entities(state)
+            .into_iter()
+            .map(|(entity, value)| {
+                let bytes = serde_json::to_vec(&value).expect("state is always serializable");
+                (entity, fnv1a(&bytes))
+            })
+            .collect();
+        StateDigest {
+            tick: state.time,
+            hashes,
+        }
+    }
+
+    /// Returns the entities whose hashes differ, or that only one side has.
+    pub fn diff(&self, other: &StateDigest) -> Vec<Entity> {
+        let ids: BTreeSet<&Entity> = self.hashes.keys().chain(other.hashes.keys()).collect();
+        ids.into_iter()
+            .filter(|entity| self.hashes.get(entity) != other.hashes.get(entity))
+            .copied()
+            .collect()
+    }
+}
+
+/// Splits a state into its entities, each as JSON.
+fn entities(state: &SimState) -> BTreeMap<Entity, Value> {
+    let Value::Object(mut world) =
+        serde_json::to_value(state).expect("state is always serializable")
+    else {
+        unreachable!("state serializes as a map");
+    };
+    let mut entities = BTreeMap::new();
+    let mut split = |key: &str, kind: fn(u32) -> Entity, world: &mut Map<String, Value>| {
+        if let Some(Value::Array(items)) = world.remove(key) {
+            for item in items {
+                entities.insert(kind(id_of(&item)), item);
+            }
+        }
+    };
+    split("tanks", Entity::Tank, &mut world);
+    split("obstacles", Entity::Obstacle, &mut world);
+    split("triggers", Entity::Trigger, &mut world);
+
+    // bullets live in pool slots; which slot each sits in matters too, since it sets the order
+    // they're processed in
+    let mut slots = Vec::new();
+    if let Some(Value::Object(mut pool)) = world.remove("bullets") {
+        if let Some(Value::Array(pool_slots)) = pool.remove("slots") {
+            for mut slot in pool_slots {
+                let bullet = slot.get_mut("value").map(Value::take);
+                match bullet {
+                    Some(bullet) if !bullet.is_null() => {
+                        let id = id_of(&bullet);
+                        slots.push(Value::from(id));
+                        entities.insert(Entity::Bullet(id), bullet);
+                    }
+                    _ => slots.push(Value::Null),
+                }
+            }
+        }
+        world.insert("bullet_slots".to_string(), Value::Array(slots));
+        world.insert("bullet_pool".to_string(), Value::Object(pool));
+    }
+    entities.insert(Entity::World, Value::Object(world));
+    entities
+}
+
+fn id_of(entity: &Value) -> u32 {
+    entity["id"].as_u64().expect("entities have numeric IDs") as u32
+}
+
+fn diff_values(path: &str, left: &Value, right: &Value, out: &mut Vec<FieldDiff>) {
+    let join = |key: &dyn fmt::Display| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{path}.{key}")
+        }
+    };
+    match (left, right) {
+        (Value::Object(left), Value::Object(right)) => {
+            let keys: BTreeSet<&String> = left.keys().chain(right.keys()).collect();
+            for key in keys {
+                diff_values(
+                    &join(key),
+                    left.get(key).unwrap_or(&Value::Null),
+                    right.get(key).unwrap_or(&Value::Null),
+                    out,
+                );
+            }
+        }
+        (Value::Array(left), Value::Array(right)) => {
+            for index in 0..left.len().max(right.len()) {
+                diff_values(
+                    &join(&index),
+                    left.get(index).unwrap_or(&Value::Null),
+                    right.get(index).unwrap_or(&Value::Null),
+                    out,
+                );
+            }
+        }
+        _ if left != right => out.push(FieldDiff {
+            path: path.to_string(),
+            left: left.clone(),
+            right: right.clone(),
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{SimEngine, TankSpawn};
+    use crate::state::Bullet;
+    use crate::util::math::Vec2;
+
+    fn state() -> SimState {
+        let mut engine = SimEngine::new(SimState::new(4));
+        for x in [200.0, 600.0] {
+            engine
+                .spawn_tank(TankSpawn::medium(0, Vec2::new_from_f64(x, 300.0)))
+                .unwrap();
+        }
+        engine.state().clone()
+    }
+
+    #[test]
+    fn diff_states_when_identical_should_be_empty() {
+        // Arrange
+        let state = state();
+
+        // Act
+        let diff = diff_states(&state, &state.clone());
+
+        // Assert
+        assert!(diff.is_empty());
+        assert!(
+            StateDigest::of(&state)
+                .diff(&StateDigest::of(&state))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn diff_states_when_diverged_should_name_entities_and_fields() {
+        // Arrange
+        let left = state();
+        let mut right = left.clone();
+        let tank_id = right.tanks[1].id;
+        right.tanks[1].health -= 5;
+        right.tanks[1].vm.pc = 9;
+        let bullet_id = right.allocate_id();
+        right.bullets.insert(Bullet {
+            id: bullet_id,
+            weapon_id: 0,
+            origin: Vec2::zero(),
+            position: Vec2::zero(),
+            velocity: Vec2::zero(),
+            bounces: 0,
+            owner: None,
+            heading: None,
+        });
+
+        // Act
+        let diff = diff_states(&left, &right);
+
+        // Assert
+        let changed: Vec<_> = diff.entities.iter().map(|diff| diff.entity).collect();
+        assert_eq!(
+            changed,
+            vec![
+                Entity::World,
+                Entity::Tank(tank_id),
+                Entity::Bullet(bullet_id)
+            ]
+        );
+        let EntityChange::Fields(fields) = &diff.entities[1].change else {
+            panic!("expected field changes, got {diff:?}");
+        };
+        let paths: Vec<_> = fields.iter().map(|field| field.path.as_str()).collect();
+        assert_eq!(paths, vec!["health", "vm.pc"]);
+        assert_eq!(diff.entities[2].change, EntityChange::OnlyRight);
+        assert!(diff.to_string().contains("  vm.pc: 0 -> 9"));
+        assert_eq!(
+            StateDigest::of(&left).diff(&StateDigest::of(&right)),
+            changed
+        );
+    }
+}
@@ -0,0 +1,290 @@
+use crate::events::SimEvent;
+use crate::referee::{DamageSource, Judge};
+use crate::state::Tank;
+use crate::util::math::{Scalar, Vec2};
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+
+/// A timed condition on a tank.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum StatusKind {
+    /// The tank's controller is paused, and its controls held neutral.
+    Emp,
+    /// A cloud around the tank that radar can't see into, out of or through.
+    Smoke { radius: Scalar },
+    /// Damage every tick, ignoring armor.
+    Burn { damage_per_tick: u32 },
+    /// Caps the tank's speed, in units per tick.
+    Slow { max_speed: Scalar },
+}
+
+impl StatusKind {
+    /// The flag a tank's program sees for this kind in [`crate::vm::abi::SELF_STATUS`].
+    pub fn bit(&self) -> u32 {
+        match self {
+            StatusKind::Emp => 1,
+            StatusKind::Smoke { .. } => 2,
+            StatusKind::Burn { .. } => 4,
+            StatusKind::Slow { .. } => 8,
+        }
+    }
+}
+
+/// A status effect as a weapon or pickup hands it out.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StatusEffectSpec {
+    pub kind: StatusKind,
+    /// Ticks the effect lasts, starting with the tick after it's applied.
+    pub duration: u32,
+}
+
+/// A status effect on a tank.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    /// Ticks left, counting the current one.
+    pub remaining: u32,
+    /// Tank credited with any damage the effect does.
+    pub source: Option<u32>,
+}
+
+/// Puts an effect on a living tank. A tank has at most one effect of each kind, so applying
+/// one it already has replaces it, keeping whichever would last longer.
+pub fn apply(
+    tank: &mut Tank,
+    spec: &StatusEffectSpec,
+    source: Option<u32>,
+    events: &mut Vec<SimEvent>,
+) {
+    if !tank.is_alive() || tank.is_ghost() || spec.duration == 0 {
+        return;
+    }
+    let effect = StatusEffect {
+        kind: spec.kind,
+        remaining: spec.duration,
+        source,
+    };
+    let bit = spec.kind.bit();
+    match tank
+        .effects
+        .iter_mut()
+        .find(|effect| effect.kind.bit() == bit)
+    {
+        Some(existing) => {
+            let remaining = existing.remaining.max(spec.duration);
+            *existing = StatusEffect {
+                remaining,
+                ..effect
+            };
+        }
+        None => tank.effects.push(effect),
+    }
+    events.push(SimEvent::StatusApplied {
+        tank_id: tank.id,
+        kind: spec.kind,
+        duration: spec.duration,
+    });
+}
+
+/// Burns tanks that are on fire, then counts every effect down a tick, removing those that run
+/// out. Destroyed tanks lose all their effects.
+pub fn update(tanks: &mut [Tank], judge: &mut Judge, events: &mut Vec<SimEvent>) {
+    for tank in tanks.iter_mut() {
+        if tank.effects.is_empty() {
+            continue;
+        }
+        for index in 0..tank.effects.len() {
+            let StatusEffect { kind, source, .. } = tank.effects[index];
+            let StatusKind::Burn { damage_per_tick } = kind else {
+                continue;
+            };
+            if !tank.is_alive() || tank.is_ghost() || damage_per_tick == 0 {
+                continue;
+            }
+            let attacker = source;
+            let (damage, destroyed) =
+                judge.deal(tank, DamageSource::Burn { attacker }, damage_per_tick);
+            if damage == 0 {
+                continue;
+            }
+            events.push(SimEvent::BurnDamage {
+                attacker,
+                target_id: tank.id,
+                damage,
+            });
+            if destroyed {
+                events.push(SimEvent::TankDestroyed {
+                    tank_id: tank.id,
+                    killer: attacker,
+                });
+            }
+        }
+
+        let alive = tank.is_alive();
+        let tank_id = tank.id;
+        tank.effects.retain_mut(|effect| {
+            effect.remaining = effect.remaining.saturating_sub(1);
+            if alive && effect.remaining > 0 {
+                return true;
+            }
+            events.push(SimEvent::StatusExpired {
+                tank_id,
+                kind: effect.kind,
+            });
+            false
+        });
+    }
+}
+
+/// Returns whether the tank's controller is paused by an EMP.
+pub fn is_jammed(tank: &Tank) -> bool {
+    tank.effects
+        .iter()
+        .any(|effect| effect.kind == StatusKind::Emp)
+}
+
+/// Returns the lowest speed any slow on the tank caps it at, if it's slowed.
+pub fn speed_cap(tank: &Tank) -> Option<Scalar> {
+    tank.effects
+        .iter()
+        .filter_map(|effect| match effect.kind {
+            StatusKind::Slow { max_speed } => Some(max_speed),
+            _ => None,
+        })
+        .min()
+}
+
+/// Returns the tank's active effects as [`StatusKind::bit`] flags.
+pub fn status_bits(tank: &Tank) -> u32 {
+    tank.effects
+        .iter()
+        .fold(0, |bits, effect| bits | effect.kind.bit())
+}
+
+/// The smoke clouds hanging around living tanks, as centres and radii.
+pub fn smoke_clouds(tanks: &[Tank]) -> Vec<(Vec2, Scalar)> {
+    tanks
+        .iter()
+        .filter(|tank| tank.is_alive())
+        .flat_map(|tank| {
+            tank.effects.iter().filter_map(|effect| match effect.kind {
+                StatusKind::Smoke { radius } => Some((tank.position, radius)),
+                _ => None,
+            })
+        })
+        .collect()
+}
+
+/// Returns whether the line between two points passes through any of the clouds.
+pub fn obscured(clouds: &[(Vec2, Scalar)], from: Vec2, to: Vec2) -> bool {
+    let line = to.sub(&from);
+    let length_squared = line.length_squared();
+    clouds.iter().any(|(center, radius)| {
+        let offset = center.sub(&from);
+        // the point on the line closest to the cloud's centre
+        let along = if length_squared > dec64!(0) {
+            (offset.dot(&line) / length_squared).clamp(dec64!(0), dec64!(1))
+        } else {
+            dec64!(0)
+        };
+        let closest = from.add(&line.scale(along));
+        center.sub(&closest).length_squared() <= *radius * *radius
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn tank(id: u32, x: f64) -> Tank {
+        test_support::tank(id, 0, Vec2::new_from_f64(x, 100.0))
+    }
+
+    #[test]
+    fn update_when_burning_should_damage_each_tick_then_expire() {
+        // Arrange
+        let mut tanks = vec![tank(1, 100.0)];
+        let health = tanks[0].health;
+        let burn = StatusEffectSpec {
+            kind: StatusKind::Burn { damage_per_tick: 2 },
+            duration: 3,
+        };
+        let mut events = Vec::new();
+        apply(&mut tanks[0], &burn, Some(4), &mut events);
+
+        // Act
+        for _ in 0..5 {
+            update(&mut tanks, &mut Judge::none(), &mut events);
+        }
+
+        // Assert
+        assert_eq!(tanks[0].health, health - 6);
+        assert!(tanks[0].effects.is_empty());
+        let burns = events
+            .iter()
+            .filter(|event| {
+                **event
+                    == SimEvent::BurnDamage {
+                        attacker: Some(4),
+                        target_id: 1,
+                        damage: 2,
+                    }
+            })
+            .count();
+        assert_eq!(burns, 3);
+        assert_eq!(
+            events.last(),
+            Some(&SimEvent::StatusExpired {
+                tank_id: 1,
+                kind: burn.kind,
+            })
+        );
+    }
+
+    #[test]
+    fn apply_when_already_affected_should_keep_longer_duration() {
+        // Arrange
+        let mut tank = tank(1, 100.0);
+        let slow = |max_speed, duration| StatusEffectSpec {
+            kind: StatusKind::Slow { max_speed },
+            duration,
+        };
+        let mut events = Vec::new();
+
+        // Act
+        apply(&mut tank, &slow(dec64!(1), 10), None, &mut events);
+        apply(&mut tank, &slow(dec64!(2), 4), None, &mut events);
+
+        // Assert
+        assert_eq!(tank.effects.len(), 1);
+        assert_eq!(tank.effects[0].remaining, 10);
+        assert_eq!(speed_cap(&tank), Some(dec64!(2)));
+        assert_eq!(
+            status_bits(&tank),
+            StatusKind::Slow {
+                max_speed: dec64!(2)
+            }
+            .bit()
+        );
+    }
+
+    #[test]
+    fn obscured_should_block_lines_through_smoke() {
+        // Arrange
+        let mut smoker = tank(1, 200.0);
+        smoker.effects.push(StatusEffect {
+            kind: StatusKind::Smoke { radius: dec64!(30) },
+            remaining: 5,
+            source: None,
+        });
+        let clouds = smoke_clouds(&[smoker]);
+        let at = |x, y| Vec2::new_from_f64(x, y);
+
+        // Act & Assert
+        assert!(obscured(&clouds, at(100.0, 100.0), at(300.0, 100.0)));
+        assert!(obscured(&clouds, at(100.0, 100.0), at(200.0, 100.0)));
+        assert!(!obscured(&clouds, at(100.0, 150.0), at(300.0, 150.0)));
+        assert!(!obscured(&clouds, at(100.0, 100.0), at(150.0, 100.0)));
+    }
+}
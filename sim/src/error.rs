@@ -0,0 +1,173 @@
+use crate::config::ConfigError;
+use crate::console::ConsoleError;
+use crate::network::NetworkError;
+use crate::rules::LoadoutError;
+use crate::save::SaveError;
+use crate::vm::VmFault;
+use crate::vm::package::PackageError;
+use crate::vm::verify::VerifyError;
+use std::io;
+use thiserror::Error;
+
+/// Why a tank couldn't be handed a controller.
+#[derive(Debug, PartialEq, Error)]
+pub enum BotLoadError {
+    #[error("no tank with id {0}")]
+    UnknownTank(u32),
+    #[error("no built-in bot named {0:?}")]
+    UnknownBot(String),
+    #[error(transparent)]
+    Network(#[from] NetworkError),
+    /// A program's package was built for a VM this sim can't run.
+    #[error(transparent)]
+    Package(#[from] PackageError),
+    /// A program failed the checks run on it before it's allowed to run.
+    #[error("program rejected: {0}")]
+    Verify(#[from] VerifyError),
+    /// A team ROM is bigger than the match lets a team share.
+    #[error("team {team_id}'s ROM of {words} words is over the limit of {limit}")]
+    TeamRomTooLarge {
+        team_id: u32,
+        words: u32,
+        limit: u32,
+    },
+}
+
+/// Everything that can go wrong across the engine's public API.
+///
+/// Modules keep their own error types for the detail only they care about; this is what they
+/// turn into once they reach a caller, so frontends have one type to report. See
+/// [`SimError::kind`] for a stable name to branch on.
+#[derive(Debug, Error)]
+pub enum SimError {
+    /// Every problem found with a config.
+    #[error("invalid config: {}", join(.0))]
+    Config(Vec<ConfigError>),
+    #[error(transparent)]
+    BotLoad(#[from] BotLoadError),
+    /// A tank couldn't be spawned with the loadout it was given.
+    #[error("could not spawn tank: {0}")]
+    Spawn(#[from] LoadoutError),
+    /// A console command couldn't be run.
+    #[error(transparent)]
+    Console(#[from] ConsoleError),
+    /// No trigger volume has the given ID.
+    #[error("no trigger with id {0}")]
+    UnknownTrigger(u32),
+    /// A tank was given an agent's action but isn't controlled by an agent.
+    #[error("tank {0} isn't controlled by an agent")]
+    NotAnAgent(u32),
+    /// A rewind asked for a tick in the future or no longer in the history.
+    #[error("tick {0} is in the future or no longer in the history")]
+    TickUnavailable(u64),
+    /// A tank's program stopped for good.
+    #[error("tank {tank_id}'s program faulted: {fault:?}")]
+    VmFault { tank_id: u32, fault: VmFault },
+    /// A save, replay, network or request couldn't be encoded, decoded, read or written.
+    #[error("{0}")]
+    Serialization(String),
+    /// This engine's state doesn't match the checksum a peer or recording has for the tick.
+    #[error("desync at tick {tick}: expected checksum {expected:#018x}, got {actual:#018x}")]
+    DesyncDetected {
+        tick: u64,
+        expected: u64,
+        actual: u64,
+    },
+}
+
+impl SimError {
+    /// Returns a short, stable name for the kind of error, for frontends to branch on.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SimError::Config(_) => "config",
+            SimError::BotLoad(_) => "bot_load",
+            SimError::Spawn(_) => "spawn",
+            SimError::Console(_) => "console",
+            SimError::UnknownTrigger(_) => "unknown_trigger",
+            SimError::NotAnAgent(_) => "not_agent",
+            SimError::TickUnavailable(_) => "tick_unavailable",
+            SimError::VmFault { .. } => "vm_fault",
+            SimError::Serialization(_) => "serialization",
+            SimError::DesyncDetected { .. } => "desync",
+        }
+    }
+}
+
+fn join(errors: &[ConfigError]) -> String {
+    let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+    messages.join("; ")
+}
+
+impl From<Vec<ConfigError>> for SimError {
+    fn from(errors: Vec<ConfigError>) -> Self {
+        SimError::Config(errors)
+    }
+}
+
+impl From<NetworkError> for SimError {
+    fn from(error: NetworkError) -> Self {
+        SimError::BotLoad(error.into())
+    }
+}
+
+impl From<PackageError> for SimError {
+    fn from(error: PackageError) -> Self {
+        SimError::BotLoad(error.into())
+    }
+}
+
+impl From<VerifyError> for SimError {
+    fn from(error: VerifyError) -> Self {
+        SimError::BotLoad(error.into())
+    }
+}
+
+impl From<SaveError> for SimError {
+    fn from(error: SaveError) -> Self {
+        match error {
+            SaveError::Config(errors) => SimError::Config(errors),
+            error => SimError::Serialization(error.to_string()),
+        }
+    }
+}
+
+impl From<io::Error> for SimError {
+    fn from(error: io::Error) -> Self {
+        SimError::Serialization(error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for SimError {
+    fn from(error: serde_json::Error) -> Self {
+        SimError::Serialization(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_should_sort_module_errors_into_kinds() {
+        // Arrange
+        let config = SaveError::Config(vec![ConfigError::NoSubsteps, ConfigError::NoRounds]);
+        let header = SaveError::BadHeader;
+
+        // Act
+        let config = SimError::from(config);
+        let header = SimError::from(header);
+        let network = SimError::from(NetworkError::NoLayers);
+        let console = SimError::from(ConsoleError::CheatsDisabled);
+
+        // Assert
+        assert_eq!(config.kind(), "config");
+        assert_eq!(
+            config.to_string(),
+            "invalid config: at least one physics substep is needed; a series needs at least one round"
+        );
+        assert_eq!(header.kind(), "serialization");
+        assert_eq!(network.kind(), "bot_load");
+        assert_eq!(network.to_string(), "network has no layers");
+        assert_eq!(console.kind(), "console");
+    }
+}
@@ -0,0 +1,248 @@
+use crate::damage::{ArmorSide, HitOutcome};
+use crate::effects::StatusKind;
+use crate::explosions::ExplosionCause;
+use crate::limits::BotLimit;
+use crate::physics::impulse::ImpulseSource;
+use crate::util::math::{Angle, Scalar, Vec2};
+use crate::watchdog::PhaseTimings;
+use serde::{Deserialize, Serialize};
+
+/// What a projectile struck other than a tank, for picking impact sounds and particles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImpactMaterial {
+    /// An obstacle inside the arena.
+    Obstacle,
+    /// One of the arena's outer walls.
+    Wall,
+}
+
+/// Something notable that happened during a tick.
+///
+/// Besides what happened, some events carry where and how, so a presentation layer can place
+/// and pick effects without redoing the physics.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SimEvent {
+    /// A tank fired. `muzzle` and `angle` are where the projectile left the barrel and the
+    /// direction it was pointing.
+    ShotFired {
+        tank_id: u32,
+        bullet_id: u32,
+        weapon_id: u32,
+        muzzle: Vec2,
+        angle: Angle,
+    },
+    /// A projectile struck a tank's armor at `position`. `shooter` is the tank that fired it,
+    /// if any.
+    Hit {
+        bullet_id: u32,
+        shooter: Option<u32>,
+        target_id: u32,
+        side: ArmorSide,
+        outcome: HitOutcome,
+        position: Vec2,
+    },
+    /// A projectile stopped against a wall or obstacle. `normal` points out of the surface.
+    BulletStopped {
+        bullet_id: u32,
+        position: Vec2,
+        normal: Vec2,
+        material: ImpactMaterial,
+    },
+    /// A point-defense projectile shot down another, and both were removed. `shooter` fired
+    /// the interceptor, `target_owner` the projectile it downed.
+    Intercepted {
+        bullet_id: u32,
+        shooter: Option<u32>,
+        target_bullet_id: u32,
+        target_owner: Option<u32>,
+        position: Vec2,
+    },
+    /// A projectile bounced off a wall or obstacle, leaving along `heading`. `normal` points out
+    /// of the surface.
+    Ricochet {
+        bullet_id: u32,
+        position: Vec2,
+        normal: Vec2,
+        heading: Angle,
+        material: ImpactMaterial,
+    },
+    /// `damage` and `knockback` are the blast's strength at its centre.
+    Explosion {
+        cause: ExplosionCause,
+        center: Vec2,
+        radius: Scalar,
+        damage: u32,
+        knockback: Scalar,
+    },
+    /// A tank was pushed, changing its velocity by `impulse` divided by its mass.
+    Impulse {
+        tank_id: u32,
+        impulse: Vec2,
+        source: ImpulseSource,
+    },
+    /// A tank's hull was twisted by `kick` radians.
+    AngularImpulse {
+        tank_id: u32,
+        kick: Scalar,
+        source: ImpulseSource,
+    },
+    /// A collision hurt `target_id`. Both parties get one if the impact hurt them both.
+    Ram {
+        rammer_id: u32,
+        target_id: u32,
+        damage: u32,
+    },
+    /// Blast damage, which ignores armor. Followed by `TankDestroyed` if it was fatal.
+    /// `attacker` is the tank credited with the blast, if any.
+    SplashDamage {
+        cause: ExplosionCause,
+        attacker: Option<u32>,
+        target_id: u32,
+        damage: u32,
+    },
+    /// Damage for being outside the safe zone. Followed by `TankDestroyed` if it was fatal.
+    ZoneDamage {
+        target_id: u32,
+        damage: u32,
+    },
+    /// Damage from a burn effect. Followed by `TankDestroyed` if it was fatal. `attacker` is the
+    /// tank whose weapon set it alight, if any.
+    BurnDamage {
+        attacker: Option<u32>,
+        target_id: u32,
+        damage: u32,
+    },
+    /// A tank damaged a teammate, and the match's friendly-fire policy was applied. `damage` is
+    /// what the teammate took, or with `reflected`, what `attacker` took in its place.
+    FriendlyFire {
+        attacker: u32,
+        target_id: u32,
+        damage: u32,
+        reflected: bool,
+    },
+    /// `killer` is the tank credited with the blow that destroyed it, if any: the shooter of a
+    /// bullet or blast, or the rammer. Teammates and the tank itself can be credited too.
+    TankDestroyed {
+        tank_id: u32,
+        killer: Option<u32>,
+    },
+    /// A projectile laid a smoke screen.
+    SmokeDeployed {
+        obscurant_id: u32,
+        center: Vec2,
+        radius: Scalar,
+    },
+    /// A smoke cloud thinned out and no longer blocks radar.
+    SmokeDissipated {
+        obscurant_id: u32,
+    },
+    /// A status effect was put on a tank, or an existing one of the same kind replaced.
+    StatusApplied {
+        tank_id: u32,
+        kind: StatusKind,
+        duration: u32,
+    },
+    /// A status effect ran out, or was cleared when its tank was destroyed.
+    StatusExpired {
+        tank_id: u32,
+        kind: StatusKind,
+    },
+    TriggerEntered {
+        trigger_id: u32,
+        tank_id: u32,
+    },
+    TriggerExited {
+        trigger_id: u32,
+        tank_id: u32,
+    },
+    TankRespawned {
+        tank_id: u32,
+        position: Vec2,
+    },
+    /// An enemy picked up a team's flag.
+    FlagTaken {
+        team_id: u32,
+        tank_id: u32,
+    },
+    /// A team's flag carrier was destroyed, leaving the flag where it fell.
+    FlagDropped {
+        team_id: u32,
+    },
+    /// A team touched its own dropped flag, sending it home.
+    FlagReturned {
+        team_id: u32,
+    },
+    /// A team's flag was carried into the enemy base.
+    FlagCaptured {
+        team_id: u32,
+        tank_id: u32,
+    },
+    /// The hill changed hands. `None` when it's empty or contested.
+    HillControlChanged {
+        team_id: Option<u32>,
+    },
+    MatchWon {
+        team_id: u32,
+    },
+    /// A round of a multi-round match ended. `winner` is `None` when nobody was left standing.
+    RoundEnded {
+        round: u32,
+        winner: Option<u32>,
+    },
+    /// The arena was reset for the next round, which plays with `seed`.
+    RoundStarted {
+        round: u32,
+        seed: u64,
+    },
+    /// A multi-round match is over. `winner` is `None` when the leaders are tied on rounds.
+    SeriesDecided {
+        winner: Option<u32>,
+    },
+    /// Two tanks' hulls started touching. `contact_id` names the contact until it ends.
+    ContactBegan {
+        contact_id: u32,
+        first_id: u32,
+        second_id: u32,
+    },
+    /// Two tanks were still touching at some point this tick.
+    ContactPersisted {
+        contact_id: u32,
+        first_id: u32,
+        second_id: u32,
+    },
+    /// Two tanks stopped touching, or one of them was destroyed.
+    ContactEnded {
+        contact_id: u32,
+        first_id: u32,
+        second_id: u32,
+    },
+    /// A queued command added a tank.
+    TankSpawned {
+        tank_id: u32,
+        team_id: u32,
+    },
+    /// A queued command removed an entity outright.
+    EntityRemoved {
+        entity_id: u32,
+    },
+    /// A queued command couldn't be applied. `index` is its position in the tick's queue.
+    CommandRejected {
+        index: u32,
+    },
+    /// A tank's program was swapped out mid-match.
+    ProgramReloaded {
+        tank_id: u32,
+    },
+    /// A tank's controller went over one of the match's limits this tick.
+    LimitExceeded {
+        tank_id: u32,
+        limit: BotLimit,
+    },
+    /// The tick took longer than the watchdog's wall-clock budget. Unlike every other event,
+    /// this one depends on the machine and load, so replays and reruns won't reproduce it.
+    SimOverrun {
+        tick: u64,
+        budget_micros: u64,
+        timings: PhaseTimings,
+    },
+}
@@ -0,0 +1,273 @@
+use crate::arena::Arena;
+use crate::events::SimEvent;
+use crate::physics::collision::AABB;
+use crate::physics::impulse::{self, ImpulseSource};
+use crate::referee::{DamageSource, Judge};
+use crate::spec::{ExplosionSpec, SpecTable};
+use crate::state::Tank;
+use crate::util::math::{Scalar, Vec2};
+use crate::util::spatial::SpatialHashMap;
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Target size of a cell in the grid used to find tanks caught in a blast.
+const BLAST_CELL_SIZE: Scalar = dec64!(64);
+
+/// What set off an explosion.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ExplosionCause {
+    /// A projectile detonated where it stopped.
+    Bullet(u32),
+    /// A tank blew up as it was destroyed.
+    Wreck(u32),
+    /// Triggered from outside the match, e.g. by a scripted event.
+    External,
+}
+
+/// An explosion waiting to be resolved.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Explosion {
+    pub cause: ExplosionCause,
+    /// Tank to credit with the damage: whoever fired the bullet, or destroyed the wreck.
+    pub attacker: Option<u32>,
+    pub center: Vec2,
+    pub spec: ExplosionSpec,
+}
+
+/// Deals blast damage and knockback for each explosion in turn.
+///
+/// Damage and knockback fall off linearly from the centre to the edge of the blast, and
+/// anything behind static geometry is sheltered. Blast damage ignores armor. Tanks destroyed
+/// by a blast set off their own wreck explosions, which are resolved after the ones already
+/// queued.
+pub fn resolve(
+    explosions: Vec<Explosion>,
+    tanks: &mut [Tank],
+    specs: &SpecTable,
+    arena: &Arena,
+    judge: &mut Judge,
+    events: &mut Vec<SimEvent>,
+) {
+    if explosions.is_empty() {
+        return;
+    }
+
+    // tanks don't move while blasts resolve, so one index serves the whole chain
+    let grid_width = (arena.width() / BLAST_CELL_SIZE)
+        .ceil()
+        .to_u32()
+        .unwrap_or(1)
+        .max(1);
+    let grid_height = (arena.height() / BLAST_CELL_SIZE)
+        .ceil()
+        .to_u32()
+        .unwrap_or(1)
+        .max(1);
+    let mut index = SpatialHashMap::new(arena.width(), arena.height(), grid_width, grid_height);
+    for (slot, tank) in tanks.iter().enumerate() {
+        index.insert(slot as u32, &AABB::new(tank.position, tank.position));
+    }
+
+    let mut queue = VecDeque::from(explosions);
+    while let Some(explosion) = queue.pop_front() {
+        let Explosion {
+            cause,
+            attacker,
+            center,
+            spec,
+        } = explosion;
+        events.push(SimEvent::Explosion {
+            cause,
+            center,
+            radius: spec.radius,
+            damage: spec.damage,
+            knockback: spec.knockback,
+        });
+
+        let mut caught: Vec<u32> = index
+            .query_circle(center, spec.radius)
+            .into_iter()
+            .collect();
+        caught.sort_unstable();
+
+        for slot in caught {
+            let tank = &mut tanks[slot as usize];
+            let Some(tank_spec) = specs.tank(tank.loadout.spec_id) else {
+                continue;
+            };
+            if !tank.is_alive() || tank.is_ghost() {
+                continue;
+            }
+            let offset = tank.position.sub(&center);
+            if offset.is_longer_than(spec.radius) || !arena.line_of_sight(center, tank.position) {
+                continue;
+            }
+            let distance = offset.length();
+
+            tank.wake();
+            let falloff = dec64!(1) - distance / spec.radius;
+            if distance > dec64!(0) && spec.knockback > dec64!(0) {
+                // one division for the direction and strength together
+                let push = offset.scale(spec.knockback * falloff / distance);
+                let source = ImpulseSource::Explosion(cause);
+                impulse::apply(tank, tank_spec, push, source, events);
+            }
+
+            let damage = (Scalar::from(spec.damage) * falloff)
+                .floor()
+                .to_u32()
+                .unwrap_or(0);
+            if damage == 0 {
+                continue;
+            }
+            let source = DamageSource::Explosion { cause, attacker };
+            let (damage, destroyed) = judge.deal(tank, source, damage);
+            if damage == 0 {
+                continue;
+            }
+            events.push(SimEvent::SplashDamage {
+                cause,
+                attacker,
+                target_id: tank.id,
+                damage,
+            });
+            if destroyed {
+                events.push(SimEvent::TankDestroyed {
+                    tank_id: tank.id,
+                    killer: attacker,
+                });
+                if let Some(wreck) = &tank_spec.death_explosion {
+                    queue.push_back(Explosion {
+                        cause: ExplosionCause::Wreck(tank.id),
+                        attacker,
+                        center: tank.position,
+                        spec: wreck.clone(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::Obstacle;
+    use crate::test_support;
+
+    fn tank(id: u32, x: f64) -> Tank {
+        test_support::tank(id, 0, Vec2::new_from_f64(x, 100.0))
+    }
+
+    fn blast(center_x: f64, damage: u32) -> Explosion {
+        Explosion {
+            cause: ExplosionCause::External,
+            attacker: None,
+            center: Vec2::new_from_f64(center_x, 100.0),
+            spec: ExplosionSpec {
+                radius: dec64!(100),
+                damage,
+                knockback: dec64!(60),
+            },
+        }
+    }
+
+    #[test]
+    fn resolve_should_attenuate_with_distance_and_respect_cover() {
+        // Arrange
+        let wall = Obstacle {
+            id: 0,
+            aabb: AABB::new(
+                Vec2::new_from_f64(120.0, 0.0),
+                Vec2::new_from_f64(130.0, 200.0),
+            ),
+        };
+        let arena = Arena::new(dec64!(500), dec64!(500), &[wall]);
+        let mut tanks = vec![tank(1, 50.0), tank(2, 150.0), tank(3, 300.0)];
+
+        // Act
+        let mut events = Vec::new();
+        resolve(
+            vec![blast(100.0, 40)],
+            &mut tanks,
+            &SpecTable::default(),
+            &arena,
+            &mut Judge::none(),
+            &mut events,
+        );
+
+        // Assert
+        assert_eq!(
+            events,
+            vec![
+                SimEvent::Explosion {
+                    cause: ExplosionCause::External,
+                    center: Vec2::new_from_f64(100.0, 100.0),
+                    radius: dec64!(100),
+                    damage: 40,
+                    knockback: dec64!(60),
+                },
+                SimEvent::Impulse {
+                    tank_id: 1,
+                    impulse: Vec2::new_from_f64(-30.0, 0.0),
+                    source: ImpulseSource::Explosion(ExplosionCause::External),
+                },
+                SimEvent::SplashDamage {
+                    cause: ExplosionCause::External,
+                    attacker: None,
+                    target_id: 1,
+                    damage: 20,
+                },
+            ]
+        );
+        assert_eq!(tanks[0].velocity, Vec2::new_from_f64(-1.0, 0.0));
+        assert_eq!(tanks[1].health, 100);
+        assert_eq!(tanks[1].velocity, Vec2::zero());
+    }
+
+    #[test]
+    fn resolve_when_blast_destroys_tank_should_chain_its_wreck_explosion_and_credit() {
+        // Arrange
+        let mut specs = SpecTable::default();
+        specs.tanks[1].death_explosion = Some(ExplosionSpec {
+            radius: dec64!(100),
+            damage: 10,
+            knockback: dec64!(0),
+        });
+        let arena = Arena::new(dec64!(500), dec64!(500), &[]);
+        let mut tanks = vec![tank(1, 100.0), tank(2, 150.0)];
+        tanks[0].health = 5;
+        let mut shell = blast(100.0, 40);
+        shell.attacker = Some(7);
+
+        // Act
+        let mut events = Vec::new();
+        resolve(
+            vec![shell],
+            &mut tanks,
+            &specs,
+            &arena,
+            &mut Judge::none(),
+            &mut events,
+        );
+
+        // Assert
+        assert!(events.contains(&SimEvent::TankDestroyed {
+            tank_id: 1,
+            killer: Some(7)
+        }));
+        assert!(events.contains(&SimEvent::SplashDamage {
+            cause: ExplosionCause::Wreck(1),
+            attacker: Some(7),
+            target_id: 2,
+            damage: 5,
+        }));
+        // the wreck is already dead, so its own blast doesn't set it off again
+        let explosions = events
+            .iter()
+            .filter(|event| matches!(event, SimEvent::Explosion { .. }))
+            .count();
+        assert_eq!(explosions, 2);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::stats::{MatchStats, TankStats, TeamStats};
+use std::fmt::Write;
+
+/// Columns of the per-match table, in order.
+pub const MATCH_COLUMNS: &[&str] = &[
+    "match_id",
+    "seed",
+    "ticks",
+    "winner",
+    "teams",
+    "tanks",
+    "shots_fired",
+    "hits",
+    "damage_dealt",
+    "kills",
+];
+
+/// Columns of the per-tank table, in order.
+pub const TANK_COLUMNS: &[&str] = &[
+    "match_id",
+    "tank_id",
+    "team_id",
+    "won",
+    "shots_fired",
+    "hits",
+    "accuracy",
+    "damage_dealt",
+    "damage_taken",
+    "kills",
+    "distance_traveled",
+    "ticks_alive",
+    "vm_cycles",
+    "average_vm_cycles",
+    "friendly_hits",
+    "friendly_damage",
+    "interceptions",
+];
+
+/// Match results as two CSV tables, one row per match and one per tank, for loading whole
+/// tournaments into a spreadsheet or dataframe.
+///
+/// The columns are fixed, so exports from different runs can be concatenated; new columns are
+/// only ever added at the end. Tables share the `match_id` column to join on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResultsExport {
+    matches: String,
+    tanks: String,
+}
+
+impl Default for ResultsExport {
+    fn default() -> Self {
+        ResultsExport {
+            matches: header(MATCH_COLUMNS),
+            tanks: header(TANK_COLUMNS),
+        }
+    }
+}
+
+impl ResultsExport {
+    pub fn new() -> Self {
+        ResultsExport::default()
+    }
+
+    /// Adds a finished match. `winner` is the winning team, if any.
+    pub fn add_match(
+        &mut self,
+        match_id: &str,
+        seed: u64,
+        winner: Option<u32>,
+        stats: &MatchStats,
+    ) {
+        let match_id = escape(match_id);
+        let total = |field: fn(&TeamStats) -> u32| {
+            stats
+                .teams
+                .values()
+                .map(|team| field(team) as u64)
+                .sum::<u64>()
+        };
+        let _ = writeln!(
+            self.matches,
+            "{},{},{},{},{},{},{},{},{},{}",
+            match_id,
+            seed,
+            stats.ticks,
+            optional(winner),
+            stats.teams.len(),
+            stats.tanks.len(),
+            total(|team| team.shots_fired),
+            total(|team| team.hits),
+            total(|team| team.damage_dealt),
+            total(|team| team.kills),
+        );
+        for (tank_id, tank) in &stats.tanks {
+            self.add_tank(&match_id, *tank_id, winner, tank);
+        }
+    }
+
+    fn add_tank(&mut self, match_id: &str, tank_id: u32, winner: Option<u32>, tank: &TankStats) {
+        let _ = writeln!(
+            self.tanks,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            match_id,
+            tank_id,
+            tank.team_id,
+            winner == Some(tank.team_id),
+            tank.shots_fired,
+            tank.hits,
+            tank.accuracy,
+            tank.damage_dealt,
+            tank.damage_taken,
+            tank.kills,
+            tank.distance_traveled,
+            tank.ticks_alive,
+            tank.vm_cycles,
+            tank.average_vm_cycles,
+            tank.friendly_hits,
+            tank.friendly_damage,
+            tank.interceptions,
+        );
+    }
+
+    /// Returns the per-match table, header first.
+    pub fn matches_csv(&self) -> &str {
+        &self.matches
+    }
+
+    /// Returns the per-tank table, header first.
+    pub fn tanks_csv(&self) -> &str {
+        &self.tanks
+    }
+}
+
+fn header(columns: &[&str]) -> String {
+    let mut header = columns.join(",");
+    header.push('\n');
+    header
+}
+
+fn optional(value: Option<u32>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+/// Quotes a field if it would otherwise break the row, per RFC 4180.
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{SimEngine, TankSpawn};
+    use crate::state::SimState;
+    use crate::util::math::Vec2;
+
+    fn stats() -> MatchStats {
+        let mut engine = SimEngine::new(SimState::new(9));
+        for (team_id, x) in [(0, 100.0), (1, 400.0)] {
+            engine
+                .spawn_tank(TankSpawn::medium(team_id, Vec2::new_from_f64(x, 100.0)))
+                .unwrap();
+        }
+        for _ in 0..5 {
+            engine.step();
+        }
+        engine.stats().clone()
+    }
+
+    #[test]
+    fn add_match_should_write_one_row_per_match_and_tank() {
+        // Arrange
+        let stats = stats();
+        let mut export = ResultsExport::new();
+
+        // Act
+        export.add_match("round one, \"final\"", 9, Some(1), &stats);
+        export.add_match("2", 10, None, &stats);
+
+        // Assert
+        let matches: Vec<&str> = export.matches_csv().lines().collect();
+        assert_eq!(matches[0], MATCH_COLUMNS.join(","));
+        assert_eq!(matches[1], "\"round one, \"\"final\"\"\",9,5,1,2,2,0,0,0,0");
+        assert_eq!(matches[2], "2,10,5,,2,2,0,0,0,0");
+        let tanks: Vec<&str> = export.tanks_csv().lines().collect();
+        assert_eq!(tanks.len(), 5);
+        assert!(tanks[2].starts_with("\"round one, \"\"final\"\"\",1,1,true,0,0,"));
+        let width = |row: &&str| row.split(',').count();
+        assert!(
+            tanks[3..]
+                .iter()
+                .all(|row| width(row) == TANK_COLUMNS.len())
+        );
+    }
+}
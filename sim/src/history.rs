@@ -0,0 +1,161 @@
+use crate::state::SimState;
+use std::collections::VecDeque;
+
+/// Periodic snapshots of a running match, kept so it can be rewound.
+///
+/// Holds at most `capacity` snapshots, one every `interval` ticks, dropping the oldest first.
+/// A capacity of zero records nothing.
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    interval: u64,
+    capacity: usize,
+    snapshots: VecDeque<SimState>,
+}
+
+impl History {
+    pub fn new(interval: u64, capacity: usize) -> Self {
+        History {
+            interval: interval.max(1),
+            capacity,
+            snapshots: VecDeque::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Records the state if it falls on the snapshot interval.
+    ///
+    /// Anything recorded at or after the state's tick belongs to a timeline that has been
+    /// rewound past, so it's replaced.
+    pub fn record(&mut self, state: &SimState) {
+        if !self.is_enabled() || !state.time.is_multiple_of(self.interval) {
+            return;
+        }
+        while self
+            .snapshots
+            .back()
+            .is_some_and(|snapshot| snapshot.time >= state.time)
+        {
+            self.snapshots.pop_back();
+        }
+        self.snapshots.push_back(state.clone());
+        if self.snapshots.len() > self.capacity {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Returns the most recent snapshot taken at or before the given tick.
+    pub fn latest_at(&self, tick: u64) -> Option<&SimState> {
+        self.snapshots
+            .iter()
+            .rev()
+            .find(|snapshot| snapshot.time <= tick)
+    }
+
+    /// Returns the snapshots, oldest first.
+    pub fn snapshots(&self) -> impl Iterator<Item = &SimState> {
+        self.snapshots.iter()
+    }
+
+    /// Drops every snapshot taken after the given tick.
+    pub fn truncate_after(&mut self, tick: u64) {
+        while self
+            .snapshots
+            .back()
+            .is_some_and(|snapshot| snapshot.time > tick)
+        {
+            self.snapshots.pop_back();
+        }
+    }
+}
+
+/// A timeline abandoned by a rewind, frozen so it can still be replayed and compared against.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrozenTimeline {
+    snapshots: Vec<SimState>,
+    final_state: SimState,
+}
+
+impl FrozenTimeline {
+    pub fn new(snapshots: Vec<SimState>, final_state: SimState) -> Self {
+        FrozenTimeline {
+            snapshots,
+            final_state,
+        }
+    }
+
+    /// The first tick that can still be looked at.
+    pub fn start_tick(&self) -> u64 {
+        self.snapshots
+            .first()
+            .map_or(self.final_state.time, |snapshot| snapshot.time)
+    }
+
+    /// The tick the timeline was abandoned at.
+    pub fn end_tick(&self) -> u64 {
+        self.final_state.time
+    }
+
+    /// The state the timeline had reached when it was abandoned.
+    pub fn final_state(&self) -> &SimState {
+        &self.final_state
+    }
+
+    /// Returns the latest recorded state at or before the given tick.
+    pub fn at(&self, tick: u64) -> Option<&SimState> {
+        if tick >= self.final_state.time {
+            return Some(&self.final_state);
+        }
+        self.snapshots
+            .iter()
+            .rev()
+            .find(|snapshot| snapshot.time <= tick)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_at(time: u64) -> SimState {
+        SimState {
+            time,
+            ..SimState::new(0)
+        }
+    }
+
+    #[test]
+    fn record_should_respect_interval_and_capacity() {
+        // Arrange
+        let mut history = History::new(5, 3);
+
+        // Act
+        for time in 0..=20 {
+            history.record(&state_at(time));
+        }
+
+        // Assert
+        let times: Vec<u64> = history.snapshots().map(|s| s.time).collect();
+        assert_eq!(times, [10, 15, 20]);
+        assert_eq!(history.latest_at(14).unwrap().time, 10);
+        assert!(history.latest_at(9).is_none());
+    }
+
+    #[test]
+    fn record_when_rewound_should_replace_abandoned_snapshots() {
+        // Arrange
+        let mut history = History::new(1, 10);
+        for time in 0..5 {
+            history.record(&state_at(time));
+        }
+
+        // Act
+        history.record(&state_at(2));
+
+        // Assert
+        let times: Vec<u64> = history.snapshots().map(|s| s.time).collect();
+        assert_eq!(times, [0, 1, 2]);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::events::SimEvent;
+use crate::physics::collision::AABB;
+use crate::spec::SpecTable;
+use crate::state::{Bullet, Tank};
+use crate::util::math::{Scalar, Vec2};
+use crate::util::pool::{Handle, Pool};
+use crate::util::spatial::SpatialHashMap;
+use fastnum::dec64;
+use std::collections::BTreeMap;
+
+/// Target size of a cell in the grid used to find projectiles near an interceptor's path.
+const INTERCEPT_CELL_SIZE: Scalar = dec64!(64);
+
+/// One projectile's movement over the tick, before anything else stops it.
+struct Flight {
+    handle: Handle,
+    id: u32,
+    owner: Option<u32>,
+    start: Vec2,
+    velocity: Vec2,
+    /// How close the projectile must pass to another to shoot it down, if it can.
+    reach: Option<Scalar>,
+}
+
+impl Flight {
+    /// The box the projectile sweeps this tick, grown by `margin` on every side.
+    fn swept(&self, margin: Scalar) -> AABB {
+        let end = self.start + self.velocity;
+        AABB::new(
+            Vec2::new(
+                self.start.x.min(end.x) - margin,
+                self.start.y.min(end.y) - margin,
+            ),
+            Vec2::new(
+                self.start.x.max(end.x) + margin,
+                self.start.y.max(end.y) + margin,
+            ),
+        )
+    }
+}
+
+/// Lets projectiles from point-defense weapons shoot down other projectiles, before any of
+/// them move. Each interceptor, in ID order, takes out the first hostile projectile it passes
+/// within reach of this tick, and both are removed without detonating. Projectiles fired by
+/// the same team never intercept each other.
+///
+/// Interceptions are checked over each projectile's whole move, so one that would also have
+/// struck a tank or wall this tick is shot down first.
+pub fn resolve(
+    bullets: &mut Pool<Bullet>,
+    tanks: &[Tank],
+    specs: &SpecTable,
+    width: Scalar,
+    height: Scalar,
+    events: &mut Vec<SimEvent>,
+) {
+    let flights: Vec<Flight> = bullets
+        .handles()
+        .map(|(handle, bullet)| Flight {
+            handle,
+            id: bullet.id,
+            owner: bullet.owner,
+            start: bullet.position,
+            velocity: bullet.velocity,
+            reach: specs
+                .weapon(bullet.weapon_id)
+                .and_then(|weapon| weapon.intercept.as_ref())
+                .map(|intercept| intercept.radius),
+        })
+        .collect();
+    let mut interceptors: Vec<usize> = (0..flights.len())
+        .filter(|slot| flights[*slot].reach.is_some())
+        .collect();
+    if interceptors.is_empty() {
+        return;
+    }
+    interceptors.sort_by_key(|slot| flights[*slot].id);
+
+    let teams: BTreeMap<u32, u32> = tanks.iter().map(|tank| (tank.id, tank.team_id)).collect();
+    let team = |flight: &Flight| flight.owner.and_then(|owner| teams.get(&owner).copied());
+    let mut index = SpatialHashMap::with_cell_size(width, height, INTERCEPT_CELL_SIZE);
+    for (slot, flight) in flights.iter().enumerate() {
+        index.insert(slot as u32, &flight.swept(dec64!(0)));
+    }
+
+    let mut downed = vec![false; flights.len()];
+    for slot in interceptors {
+        let interceptor = &flights[slot];
+        let Some(reach) = interceptor.reach else {
+            continue;
+        };
+        if downed[slot] {
+            continue;
+        }
+        let own_team = team(interceptor);
+        let target = index
+            .query(&interceptor.swept(reach))
+            .into_iter()
+            .map(|other| other as usize)
+            .filter(|other| *other != slot && !downed[*other])
+            .filter(|other| own_team.is_none() || team(&flights[*other]) != own_team)
+            .filter_map(|other| {
+                closest_approach(interceptor, &flights[other], reach)
+                    .map(|fraction| (fraction, flights[other].id, other))
+            })
+            .min();
+        let Some((fraction, _, other)) = target else {
+            continue;
+        };
+        downed[slot] = true;
+        downed[other] = true;
+        let target = &flights[other];
+        events.push(SimEvent::Intercepted {
+            bullet_id: interceptor.id,
+            shooter: interceptor.owner,
+            target_bullet_id: target.id,
+            target_owner: target.owner,
+            position: interceptor.start.mul_add(&interceptor.velocity, fraction),
+        });
+    }
+
+    for (flight, downed) in flights.iter().zip(downed) {
+        if downed {
+            bullets.remove(flight.handle);
+        }
+    }
+}
+
+/// Returns how far through the tick two projectiles come closest, if they pass within `reach`
+/// of each other then.
+fn closest_approach(a: &Flight, b: &Flight, reach: Scalar) -> Option<Scalar> {
+    let offset = b.start.sub(&a.start);
+    let closing = b.velocity.sub(&a.velocity);
+    let speed_squared = closing.length_squared();
+    let fraction = if speed_squared > dec64!(0) {
+        (-offset.dot(&closing) / speed_squared).clamp(dec64!(0), dec64!(1))
+    } else {
+        dec64!(0)
+    };
+    let gap = offset.mul_add(&closing, fraction);
+    (gap.length_squared() <= reach * reach).then_some(fraction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spec::InterceptSpec;
+    use crate::test_support;
+
+    fn specs() -> SpecTable {
+        let mut specs = SpecTable::default();
+        specs.weapons[1].intercept = Some(InterceptSpec { radius: dec64!(3) });
+        specs
+    }
+
+    fn tank(id: u32, team_id: u32) -> Tank {
+        test_support::tank(id, team_id, Vec2::zero())
+    }
+
+    fn bullet(id: u32, weapon_id: u32, owner: u32, position: Vec2, velocity: Vec2) -> Bullet {
+        Bullet {
+            id,
+            weapon_id,
+            origin: position,
+            position,
+            velocity,
+            bounces: 0,
+            owner: Some(owner),
+            heading: None,
+        }
+    }
+
+    #[test]
+    fn resolve_when_paths_cross_should_down_both_and_credit_shooter() {
+        // Arrange
+        let tanks = [tank(1, 0), tank(2, 1)];
+        let mut bullets = Pool::default();
+        let at = |x, y| Vec2::new_from_f64(x, y);
+        bullets.insert(bullet(10, 0, 1, at(100.0, 94.0), at(0.0, 12.0)));
+        bullets.insert(bullet(11, 1, 2, at(92.0, 100.0), at(16.0, 0.0)));
+        bullets.insert(bullet(12, 0, 1, at(300.0, 300.0), at(12.0, 0.0)));
+        let mut events = Vec::new();
+
+        // Act
+        resolve(
+            &mut bullets,
+            &tanks,
+            &specs(),
+            dec64!(500),
+            dec64!(500),
+            &mut events,
+        );
+
+        // Assert
+        let left: Vec<u32> = bullets.iter().map(|bullet| bullet.id).collect();
+        assert_eq!(left, vec![12]);
+        assert_eq!(
+            events,
+            vec![SimEvent::Intercepted {
+                bullet_id: 11,
+                shooter: Some(2),
+                target_bullet_id: 10,
+                target_owner: Some(1),
+                position: at(100.0, 100.0),
+            }]
+        );
+    }
+
+    #[test]
+    fn resolve_when_target_is_friendly_or_out_of_reach_should_leave_it() {
+        // Arrange
+        let tanks = [tank(1, 0), tank(2, 0)];
+        let mut bullets = Pool::default();
+        let at = |x, y| Vec2::new_from_f64(x, y);
+        bullets.insert(bullet(10, 0, 1, at(100.0, 90.0), at(0.0, 12.0)));
+        bullets.insert(bullet(11, 1, 2, at(92.0, 100.0), at(16.0, 0.0)));
+        bullets.insert(bullet(12, 1, 2, at(92.0, 300.0), at(16.0, 0.0)));
+        let mut events = Vec::new();
+
+        // Act
+        resolve(
+            &mut bullets,
+            &tanks,
+            &specs(),
+            dec64!(500),
+            dec64!(500),
+            &mut events,
+        );
+
+        // Assert
+        assert_eq!(bullets.len(), 3);
+        assert!(events.is_empty());
+    }
+}
@@ -0,0 +1,134 @@
+use std::num::NonZeroUsize;
+
+/// Splits the parallel parts of a tick across threads.
+///
+/// Work is cut into contiguous chunks, one per thread, and the results are joined back in
+/// chunk order, so the caller sees exactly what a single thread would have produced. Jobs only
+/// get their own chunk and shared read-only data, so nothing they do depends on how the
+/// threads interleave. Without the `parallel` feature every job runs on the calling thread.
+///
+/// There are no standing workers: every call with more than one thread spawns a scoped OS
+/// thread per extra chunk and joins them before returning. That costs some tens of
+/// microseconds per call, paid twice a tick by the engine, so it only wins when each chunk has
+/// more work than that, as in big battles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScopedSplitter {
+    threads: NonZeroUsize,
+}
+
+impl Default for ScopedSplitter {
+    fn default() -> Self {
+        ScopedSplitter::new(NonZeroUsize::MIN)
+    }
+}
+
+impl ScopedSplitter {
+    pub fn new(threads: NonZeroUsize) -> Self {
+        ScopedSplitter { threads }
+    }
+
+    /// Returns how many threads jobs are spread over, counting the calling thread.
+    pub fn threads(&self) -> usize {
+        if cfg!(feature = "parallel") {
+            self.threads.get()
+        } else {
+            1
+        }
+    }
+
+    /// Runs `job` over chunks of `items`, each chunk with the index of its first item, and
+    /// returns everything the jobs produced in item order.
+    pub fn map<T, R, F>(&self, items: &[T], job: F) -> Vec<R>
+    where
+        T: Sync,
+        R: Send,
+        F: Fn(usize, &[T]) -> Vec<R> + Sync,
+    {
+        let size = self.chunk_size(items.len());
+        self.join(items.chunks(size).enumerate(), |(index, chunk)| {
+            job(index * size, chunk)
+        })
+    }
+
+    /// Runs `job` over chunks of `items` it may change, each chunk with the index of its first
+    /// item, and returns everything the jobs produced in item order.
+    pub fn map_mut<T, R, F>(&self, items: &mut [T], job: F) -> Vec<R>
+    where
+        T: Send,
+        R: Send,
+        F: Fn(usize, &mut [T]) -> Vec<R> + Sync,
+    {
+        let size = self.chunk_size(items.len());
+        self.join(items.chunks_mut(size).enumerate(), |(index, chunk)| {
+            job(index * size, chunk)
+        })
+    }
+
+    fn chunk_size(&self, len: usize) -> usize {
+        len.div_ceil(self.threads()).max(1)
+    }
+
+    /// Runs the first chunk on the calling thread and the rest on threads spawned for this call.
+    fn join<C, R, I, F>(&self, chunks: I, job: F) -> Vec<R>
+    where
+        C: Send,
+        R: Send,
+        I: Iterator<Item = C>,
+        F: Fn(C) -> Vec<R> + Sync,
+    {
+        let mut chunks = chunks.fuse();
+        let Some(first) = chunks.next() else {
+            return Vec::new();
+        };
+        if self.threads() == 1 {
+            let mut results = job(first);
+            results.extend(chunks.flat_map(&job));
+            return results;
+        }
+        std::thread::scope(|scope| {
+            let job = &job;
+            let others: Vec<_> = chunks
+                .map(|chunk| scope.spawn(move || job(chunk)))
+                .collect();
+            let mut results = job(first);
+            for other in others {
+                results.extend(other.join().expect("tick job panicked"));
+            }
+            results
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_mut_should_return_results_in_item_order_whatever_the_threads() {
+        // Arrange
+        let items: Vec<u32> = (0..37).collect();
+        let double = |start: usize, chunk: &mut [u32]| {
+            chunk
+                .iter_mut()
+                .enumerate()
+                .map(|(offset, item)| {
+                    *item *= 2;
+                    (start + offset, *item)
+                })
+                .collect()
+        };
+
+        // Act
+        let mut serial = items.clone();
+        let serial_results = ScopedSplitter::default().map_mut(&mut serial, double);
+        let mut parallel = items.clone();
+        let parallel_results =
+            ScopedSplitter::new(NonZeroUsize::new(4).unwrap()).map_mut(&mut parallel, double);
+
+        // Assert
+        assert_eq!(serial, parallel);
+        assert_eq!(serial_results, parallel_results);
+        let expected: Vec<(usize, u32)> = (0..37).map(|i| (i as usize, i * 2)).collect();
+        assert_eq!(parallel_results, expected);
+    }
+}
@@ -1,11 +1,68 @@
-use godot::prelude::*;
+pub mod agent;
+pub mod arena;
+pub mod bots;
+pub mod clock;
+pub mod commands;
+pub mod config;
+pub mod console;
+pub mod contacts;
+pub mod damage;
+pub mod debug_draw;
+pub mod diff;
+pub mod effects;
+pub mod error;
+pub mod events;
+pub mod explosions;
+pub mod export;
+pub mod history;
+pub mod intercept;
+pub mod jobs;
+pub mod lifecycle;
+pub mod limits;
+pub mod log;
+pub mod mapgen;
+pub mod modes;
+pub mod nav;
+pub mod network;
+pub mod obscurants;
+pub mod physics;
+pub mod ramming;
+pub mod ratings;
+pub mod referee;
+pub mod replay;
+pub mod respawn;
+pub mod rounds;
+pub mod rules;
+pub mod save;
+pub mod scenario;
+pub mod sensors;
+pub mod session;
+pub mod sim;
+pub mod spawns;
+pub mod spec;
+pub mod spectator;
+pub mod state;
+pub mod stats;
+pub mod symmetry;
+pub mod tags;
+pub mod telemetry;
+#[cfg(test)]
+mod test_support;
+pub mod tournament;
+pub mod training;
+pub mod triggers;
+pub mod util;
+pub mod visibility;
+pub mod vm;
+pub mod watchdog;
+pub mod worker;
+pub mod zone;
 
-mod sim;
-mod util;
-mod physics;
-mod state;
-
-struct SimExtension;
-
-#[gdextension]
-unsafe impl ExtensionLibrary for SimExtension {}
+#[cfg(feature = "godot")]
+mod node;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "godot")]
+mod resources;
+#[cfg(feature = "wasm")]
+mod wasm;
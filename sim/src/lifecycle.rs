@@ -0,0 +1,85 @@
+use crate::obscurants::SmokeSpec;
+use crate::util::math::{Angle, Vec2};
+
+/// Something waiting to enter the match.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Spawn {
+    /// A shell a tank fired.
+    Shot {
+        tank_id: u32,
+        weapon_id: u32,
+        muzzle: Vec2,
+        velocity: Vec2,
+        /// Where the turret pointed when it fired.
+        angle: Angle,
+    },
+    /// A smoke screen laid where a shell came down.
+    Smoke { smoke: SmokeSpec, center: Vec2 },
+}
+
+/// Entities created partway through a phase, held back until the phase ends.
+///
+/// Nothing else in the phase sees them arrive, so it can't matter which tank or bullet
+/// happened to be handled first. The engine applies the buffer at fixed points in a tick,
+/// handing out IDs and logging events as it does, so those reflect when the entity actually
+/// entered the match. It's always empty between ticks.
+///
+/// Entities leaving mid-phase are held back by the phases themselves: shot-down projectiles
+/// are removed once every interception is settled, and tanks destroyed by one projectile
+/// still stop the rest fired that tick.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Lifecycle {
+    spawns: Vec<Spawn>,
+}
+
+impl Lifecycle {
+    /// Queues something to enter the match. Spawns are applied in the order they were queued.
+    pub fn spawn(&mut self, spawn: Spawn) {
+        self.spawns.push(spawn);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spawns.is_empty()
+    }
+
+    /// Empties the buffer, returning the spawns in queue order.
+    pub fn take(&mut self) -> Vec<Spawn> {
+        std::mem::take(&mut self.spawns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fastnum::dec64;
+
+    #[test]
+    fn take_should_return_spawns_in_queue_order_and_empty_buffer() {
+        // Arrange
+        let mut lifecycle = Lifecycle::default();
+        let shot = |tank_id| Spawn::Shot {
+            tank_id,
+            weapon_id: 0,
+            muzzle: Vec2::zero(),
+            velocity: Vec2::zero(),
+            angle: Angle::ZERO,
+        };
+        let smoke = Spawn::Smoke {
+            smoke: SmokeSpec {
+                radius: dec64!(30),
+                duration: 60,
+            },
+            center: Vec2::zero(),
+        };
+        lifecycle.spawn(shot(2));
+        lifecycle.spawn(smoke.clone());
+        lifecycle.spawn(shot(1));
+
+        // Act
+        let spawns = lifecycle.take();
+
+        // Assert
+        assert_eq!(spawns, vec![shot(2), smoke, shot(1)]);
+        assert!(lifecycle.is_empty());
+    }
+}
@@ -0,0 +1,148 @@
+use crate::vm::{self, RunOutcome, RunReport, VmFault, abi};
+use serde::{Deserialize, Serialize};
+
+/// The resources a tank's controller may use, in one place.
+///
+/// Every controller runs under the same limits. Programs use all of them; native bots and
+/// networks run in Rust and only ever use what the engine hands them, so nothing here can be
+/// exceeded by them. Going over a limit never passes silently: the engine reports a
+/// [`SimEvent::LimitExceeded`](crate::events::SimEvent::LimitExceeded) for the tank.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BotLimits {
+    /// Most VM instructions any tank may run per tick, whatever its class's clock speed.
+    pub instructions_per_tick: u32,
+    /// Words of RAM each program gets, mapped from address zero.
+    pub memory_words: u32,
+    /// Words on each program's operand stack.
+    pub stack_words: u32,
+    /// Calls each program may have in progress at once.
+    #[serde(default = "default_call_depth")]
+    pub call_depth: u32,
+    /// Path queries each tank may make per tick. Extra queries are refused.
+    pub path_queries_per_tick: u32,
+    /// Words of constant data each team may share between its programs, counted once per team
+    /// however many tanks read it. See [`SimEngine::load_team_rom`].
+    ///
+    /// [`SimEngine::load_team_rom`]: crate::sim::SimEngine::load_team_rom
+    #[serde(default = "default_team_rom_words")]
+    pub team_rom_words: u32,
+}
+
+fn default_call_depth() -> u32 {
+    vm::CALL_DEPTH as u32
+}
+
+fn default_team_rom_words() -> u32 {
+    4096
+}
+
+impl Default for BotLimits {
+    fn default() -> Self {
+        BotLimits {
+            instructions_per_tick: 1000,
+            memory_words: vm::MEMORY_SIZE as u32,
+            stack_words: vm::STACK_SIZE as u32,
+            call_depth: default_call_depth(),
+            path_queries_per_tick: 1,
+            team_rom_words: default_team_rom_words(),
+        }
+    }
+}
+
+/// One of the [`BotLimits`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BotLimit {
+    /// The program used its whole budget without yielding, so it stopped mid-tick.
+    Instructions,
+    /// The program touched RAM past the end of its memory, and faulted.
+    Memory,
+    /// The program overflowed its stack, and faulted.
+    Stack,
+    /// The program nested calls too deep, and faulted.
+    CallDepth,
+    /// The program asked for more paths than it may plan per tick.
+    PathQueries,
+}
+
+/// Returns which limit a program went over during a run, if any.
+///
+/// Faults are only reported on the run that caused them, not on every later one.
+pub fn exceeded(report: &RunReport) -> Option<BotLimit> {
+    if report.cycles == 0 {
+        return None;
+    }
+    match report.outcome {
+        RunOutcome::OutOfCycles => Some(BotLimit::Instructions),
+        RunOutcome::Faulted(VmFault::StackOverflow) => Some(BotLimit::Stack),
+        RunOutcome::Faulted(VmFault::CallStackOverflow) => Some(BotLimit::CallDepth),
+        // anything below the memory-mapped registers would be RAM, had there been enough
+        RunOutcome::Faulted(VmFault::BadAddress { address }) if address < abi::TICK => {
+            Some(BotLimit::Memory)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::VmState;
+    use crate::vm::isa::{Assembler, Opcode};
+    use crate::vm::{Stack, VmIo};
+
+    struct NoIo;
+
+    impl VmIo for NoIo {
+        fn read(&mut self, _address: u32) -> Option<u32> {
+            None
+        }
+
+        fn write(&mut self, _address: u32, _value: u32) -> bool {
+            false
+        }
+
+        fn syscall(&mut self, number: u8, _stack: &mut Stack) -> Result<(), VmFault> {
+            Err(VmFault::BadSyscall { number })
+        }
+    }
+
+    #[test]
+    fn exceeded_should_name_the_limit_a_program_ran_into() {
+        // Arrange
+        let limits = BotLimits {
+            memory_words: 16,
+            stack_words: 2,
+            call_depth: 2,
+            ..BotLimits::default()
+        };
+        let past_memory = Assembler::new().push(16).op(Opcode::Load).finish();
+        let deep_stack = Assembler::new().push(1).push(2).push(3).finish();
+        let mut asm = Assembler::new();
+        let top = asm.label();
+        let spin = asm.bind(top).jump(Opcode::Jmp, top).finish();
+        let function = asm.label();
+        let recurse = asm.bind(function).jump(Opcode::Call, function).finish();
+        let run = |code: &[u8]| {
+            let mut state =
+                VmState::with_sizes(limits.memory_words, limits.stack_words, limits.call_depth);
+            let first = vm::run(&mut state, code, 10, &mut NoIo);
+            let second = vm::run(&mut state, code, 10, &mut NoIo);
+            (exceeded(&first), exceeded(&second))
+        };
+
+        // Act
+        let memory = run(&past_memory);
+        let stack = run(&deep_stack);
+        let instructions = run(&spin);
+        let calls = run(&recurse);
+
+        // Assert
+        assert_eq!(memory, (Some(BotLimit::Memory), None));
+        assert_eq!(stack, (Some(BotLimit::Stack), None));
+        assert_eq!(calls, (Some(BotLimit::CallDepth), None));
+        assert_eq!(
+            instructions,
+            (Some(BotLimit::Instructions), Some(BotLimit::Instructions))
+        );
+    }
+}
@@ -0,0 +1,293 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// Records kept before the oldest are dropped, unless set otherwise.
+pub const DEFAULT_LOG_CAPACITY: usize = 1024;
+
+/// How much a log record matters, least first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Severity {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// The part of the engine, or of whatever runs it, that wrote a log record.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Subsystem {
+    Engine,
+    /// Queued commands and scenario scripts.
+    Commands,
+    /// Tank programs.
+    Vm,
+    Telemetry,
+    Replay,
+    Save,
+    Config,
+    /// Whatever embeds the engine, such as the Godot node or a headless runner.
+    Host,
+}
+
+impl Severity {
+    fn name(self) -> &'static str {
+        match self {
+            Severity::Debug => "debug",
+            Severity::Info => "info",
+            Severity::Warn => "warn",
+            Severity::Error => "error",
+        }
+    }
+}
+
+impl Subsystem {
+    fn name(self) -> &'static str {
+        match self {
+            Subsystem::Engine => "engine",
+            Subsystem::Commands => "commands",
+            Subsystem::Vm => "vm",
+            Subsystem::Telemetry => "telemetry",
+            Subsystem::Replay => "replay",
+            Subsystem::Save => "save",
+            Subsystem::Config => "config",
+            Subsystem::Host => "host",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl fmt::Display for Subsystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Severity {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        [
+            Severity::Debug,
+            Severity::Info,
+            Severity::Warn,
+            Severity::Error,
+        ]
+        .into_iter()
+        .find(|severity| severity.name() == name)
+        .ok_or(())
+    }
+}
+
+/// One diagnostic, stamped with the tick it was written during.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LogRecord {
+    pub tick: u64,
+    pub severity: Severity,
+    pub subsystem: Subsystem,
+    pub message: String,
+}
+
+impl fmt::Display for LogRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {} {}: {}",
+            self.tick, self.severity, self.subsystem, self.message
+        )
+    }
+}
+
+/// The engine's diagnostics, as a ring buffer of the most recent records.
+///
+/// The simulation only ever writes to the log: nothing in a tick reads it back, and it isn't
+/// part of the state, saves or replays, so what gets logged never changes how a match plays.
+/// Headless runs and the Godot node see the same records. With the `tracing` feature, each
+/// record is also emitted as a `tracing` event.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimLog {
+    records: VecDeque<LogRecord>,
+    capacity: usize,
+    /// Records below this are discarded as they're written.
+    min_severity: Severity,
+    /// Records pushed out of the buffer to make room, since it was last cleared.
+    dropped: u64,
+}
+
+impl Default for SimLog {
+    fn default() -> Self {
+        SimLog::new(DEFAULT_LOG_CAPACITY)
+    }
+}
+
+impl SimLog {
+    /// Creates a log keeping the last `capacity` records at [`Severity::Info`] or above.
+    pub fn new(capacity: usize) -> Self {
+        SimLog {
+            records: VecDeque::new(),
+            capacity,
+            min_severity: Severity::Info,
+            dropped: 0,
+        }
+    }
+
+    /// Writes a record, dropping the oldest if the buffer is full.
+    pub fn record(
+        &mut self,
+        tick: u64,
+        severity: Severity,
+        subsystem: Subsystem,
+        message: impl Into<String>,
+    ) {
+        if severity < self.min_severity || self.capacity == 0 {
+            return;
+        }
+        let record = LogRecord {
+            tick,
+            severity,
+            subsystem,
+            message: message.into(),
+        };
+        #[cfg(feature = "tracing")]
+        emit(&record);
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+            self.dropped += 1;
+        }
+        self.records.push_back(record);
+    }
+
+    /// Returns whether records of this severity are kept, so callers can skip building
+    /// messages nobody will see.
+    pub fn is_enabled(&self, severity: Severity) -> bool {
+        severity >= self.min_severity && self.capacity > 0
+    }
+
+    pub fn min_severity(&self) -> Severity {
+        self.min_severity
+    }
+
+    /// Discards records below `severity` from now on. Records already kept stay.
+    pub fn set_min_severity(&mut self, severity: Severity) {
+        self.min_severity = severity;
+    }
+
+    /// Changes how many records are kept, dropping the oldest if there are too many.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.records.len() > capacity {
+            self.records.pop_front();
+            self.dropped += 1;
+        }
+    }
+
+    /// Returns the kept records, oldest first.
+    pub fn records(&self) -> impl Iterator<Item = &LogRecord> {
+        self.records.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Returns how many records were dropped to make room since the log was last cleared.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+        self.dropped = 0;
+    }
+
+    /// Formats the last `count` records at `min_severity` or above, one per line.
+    pub fn dump(&self, min_severity: Severity, count: usize) -> String {
+        let kept: Vec<&LogRecord> = self
+            .records
+            .iter()
+            .filter(|record| record.severity >= min_severity)
+            .collect();
+        let mut out = String::new();
+        if self.dropped > 0 {
+            let _ = writeln!(out, "({} older records dropped)", self.dropped);
+        }
+        for record in &kept[kept.len().saturating_sub(count)..] {
+            let _ = writeln!(out, "{record}");
+        }
+        out
+    }
+
+    /// Returns the kept records as a JSON array.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.records).unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "tracing")]
+fn emit(record: &LogRecord) {
+    let LogRecord {
+        tick,
+        subsystem,
+        message,
+        ..
+    } = record;
+    let subsystem = subsystem.name();
+    match record.severity {
+        Severity::Debug => tracing::debug!(tick, subsystem, "{message}"),
+        Severity::Info => tracing::info!(tick, subsystem, "{message}"),
+        Severity::Warn => tracing::warn!(tick, subsystem, "{message}"),
+        Severity::Error => tracing::error!(tick, subsystem, "{message}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_when_full_should_drop_oldest() {
+        // Arrange
+        let mut log = SimLog::new(2);
+
+        // Act
+        for tick in 0..3 {
+            log.record(tick, Severity::Warn, Subsystem::Vm, format!("fault {tick}"));
+        }
+
+        // Assert
+        let ticks: Vec<u64> = log.records().map(|record| record.tick).collect();
+        assert_eq!(ticks, vec![1, 2]);
+        assert_eq!(log.dropped(), 1);
+        assert_eq!(
+            log.dump(Severity::Debug, 1),
+            "(1 older records dropped)\n[2] warn vm: fault 2\n"
+        );
+    }
+
+    #[test]
+    fn record_when_below_min_severity_should_discard_it() {
+        // Arrange
+        let mut log = SimLog::default();
+        log.set_min_severity(Severity::Warn);
+
+        // Act
+        log.record(4, Severity::Info, Subsystem::Engine, "ignored");
+        log.record(4, Severity::Error, Subsystem::Telemetry, "kept");
+
+        // Assert
+        assert_eq!(log.len(), 1);
+        assert!(!log.is_enabled(Severity::Info));
+        assert_eq!(log.records().next().unwrap().message, "kept");
+    }
+}
@@ -0,0 +1,382 @@
+use crate::arena::ArenaConfig;
+use crate::config::SimConfig;
+use crate::nav::{self, NavGrid};
+use crate::physics::collision::AABB;
+use crate::spawns;
+use crate::symmetry::Symmetry;
+use crate::triggers::TriggerShape;
+use crate::util::math::{Scalar, Vec2};
+use crate::util::rng::Rng;
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Obstacle placements tried per layout before settling for the coverage reached.
+const PLACEMENTS: u32 = 256;
+
+/// Knobs for [`generate`].
+///
+/// Everything is in whole arena units, so mirrored layouts are exact.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GeneratorConfig {
+    pub width: u32,
+    pub height: u32,
+    /// Fraction of the arena to cover with obstacles, from 0 to 1. Layouts stop short of it
+    /// when there's no room left that keeps the map passable.
+    pub obstacle_density: Scalar,
+    pub min_obstacle_size: u32,
+    pub max_obstacle_size: u32,
+    pub spawn_points: u32,
+    /// Closest two spawn points may be.
+    pub spawn_spacing: u32,
+    /// Circular trigger zones to scatter, for modes and scenarios to hang effects on.
+    pub zones: u32,
+    pub zone_radius: u32,
+    /// Makes the layout fair by mirroring everything placed, if set.
+    pub symmetry: Option<Symmetry>,
+    /// Layouts to try before giving up on finding a connected one.
+    pub attempts: u32,
+    /// Places spawn points with [`spawns::place`] once the rest of the layout is down, rather
+    /// than at random. They then ignore `spawn_spacing` and `symmetry`.
+    #[serde(default)]
+    pub fair_spawns: bool,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        GeneratorConfig {
+            width: 1024,
+            height: 768,
+            obstacle_density: dec64!(0.15),
+            min_obstacle_size: 32,
+            max_obstacle_size: 128,
+            spawn_points: 2,
+            spawn_spacing: 256,
+            zones: 1,
+            zone_radius: 48,
+            symmetry: Some(Symmetry::HalfTurn),
+            attempts: 32,
+            fair_spawns: false,
+        }
+    }
+}
+
+/// Reasons no arena could be generated.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GenerateError {
+    /// The arena is too small to hold any obstacle or spawn point.
+    TooSmall,
+    /// Every layout tried left some spawn point or zone cut off from the rest.
+    NotConnected { attempts: u32 },
+}
+
+impl fmt::Display for GenerateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenerateError::TooSmall => write!(f, "arena is too small to generate a layout in"),
+            GenerateError::NotConnected { attempts } => {
+                write!(f, "no connected layout found in {attempts} attempts")
+            }
+        }
+    }
+}
+
+impl std::error::Error for GenerateError {}
+
+/// Generates an arena from a seed. The same seed, knobs and tank classes always give the same
+/// arena.
+///
+/// Every spawn point and zone is reachable from every other on the nav grid `sim` would plan
+/// with, so no tank starts boxed in. Layouts that fail the check are thrown away and another is
+/// drawn from the same seed.
+pub fn generate(
+    config: &GeneratorConfig,
+    sim: &SimConfig,
+    seed: u64,
+) -> Result<ArenaConfig, GenerateError> {
+    if config.width <= config.min_obstacle_size || config.height <= config.min_obstacle_size {
+        return Err(GenerateError::TooSmall);
+    }
+    let clearance = nav::clearance(&sim.specs);
+    let mut rng = Rng::new(seed);
+    for _ in 0..config.attempts {
+        let mut layout = Layout::new(config, clearance, sim.grid.nav_cell_size);
+        layout.place_obstacles(&mut rng);
+        if !layout.place_spawn_points(&mut rng) || !layout.place_zones(&mut rng) {
+            continue;
+        }
+        let mut arena = layout.arena;
+        if config.fair_spawns {
+            let mut trial = sim.clone();
+            trial.arena = arena;
+            let Some(points) = spawns::place(&trial, config.spawn_points) else {
+                continue;
+            };
+            arena = trial.arena;
+            arena.spawn_points = points;
+        }
+        if arena.validate().is_empty() && is_connected(&arena, sim) {
+            return Ok(arena);
+        }
+    }
+    Err(GenerateError::NotConnected {
+        attempts: config.attempts,
+    })
+}
+
+/// An arena being filled in.
+struct Layout<'a> {
+    config: &'a GeneratorConfig,
+    arena: ArenaConfig,
+    /// Room kept around obstacles, so tanks can fit between them and beside spawn points.
+    gap: Scalar,
+}
+
+impl<'a> Layout<'a> {
+    fn new(config: &'a GeneratorConfig, clearance: Scalar, cell_size: Scalar) -> Self {
+        Layout {
+            config,
+            arena: ArenaConfig {
+                width: Scalar::from(config.width),
+                height: Scalar::from(config.height),
+                ..ArenaConfig::default()
+            },
+            // obstacles are inflated by the clearance on the nav grid, and a corridor needs a
+            // couple of free cells between them to survive rasterizing
+            gap: (clearance + cell_size) * dec64!(2),
+        }
+    }
+
+    fn mirror(&self, point: Vec2) -> Option<Vec2> {
+        let symmetry = self.config.symmetry?;
+        Some(symmetry.apply(point, self.arena.width, self.arena.height))
+    }
+
+    fn random_point(&self, rng: &mut Rng, margin: u32) -> Vec2 {
+        let x = rng.range(margin, self.config.width.saturating_sub(margin));
+        let y = rng.range(margin, self.config.height.saturating_sub(margin));
+        Vec2::new(Scalar::from(x), Scalar::from(y))
+    }
+
+    /// Returns whether a point is clear of every obstacle by at least `margin`.
+    fn is_clear(&self, point: Vec2, margin: Scalar) -> bool {
+        self.arena
+            .obstacles
+            .iter()
+            .all(|aabb| !aabb.expand(margin).contains(point))
+    }
+
+    fn place_obstacles(&mut self, rng: &mut Rng) {
+        let config = self.config;
+        let max_size = config
+            .max_obstacle_size
+            .clamp(config.min_obstacle_size, config.width.min(config.height));
+        let target = self.arena.width * self.arena.height * config.obstacle_density;
+        let mut covered = dec64!(0);
+        for _ in 0..PLACEMENTS {
+            if covered >= target {
+                break;
+            }
+            let size = Vec2::new(
+                Scalar::from(rng.range(config.min_obstacle_size, max_size)),
+                Scalar::from(rng.range(config.min_obstacle_size, max_size)),
+            );
+            let min = self.random_point(rng, 0);
+            let max = min + size;
+            if max.x > self.arena.width || max.y > self.arena.height {
+                continue;
+            }
+            let aabb = AABB::new(min, max);
+            let mut placed = vec![aabb];
+            if let (Some(a), Some(b)) = (self.mirror(min), self.mirror(max)) {
+                let mirrored = AABB::new(a, b);
+                if mirrored != aabb {
+                    if mirrored.expand(self.gap).intersects(&aabb) {
+                        continue;
+                    }
+                    placed.push(mirrored);
+                }
+            }
+            let crowded = placed.iter().any(|new| {
+                self.arena
+                    .obstacles
+                    .iter()
+                    .any(|old| old.expand(self.gap).intersects(new))
+            });
+            if crowded {
+                continue;
+            }
+            for aabb in placed {
+                covered += (aabb.max.x - aabb.min.x) * (aabb.max.y - aabb.min.y);
+                self.arena.obstacles.push(aabb);
+            }
+        }
+    }
+
+    /// Places points clear of obstacles and at least `spacing` apart, mirrored in pairs when the
+    /// layout is symmetric, with the odd one out of a symmetric layout in the centre. Returns
+    /// `None` if there wasn't room for all of them.
+    fn place_points(
+        &self,
+        rng: &mut Rng,
+        count: u32,
+        margin: u32,
+        spacing: Scalar,
+    ) -> Option<Vec<Vec2>> {
+        let clear = self.gap + Scalar::from(margin);
+        let mut points: Vec<Vec2> = Vec::new();
+        let far_enough = |points: &[Vec2], point: Vec2| {
+            points
+                .iter()
+                .all(|other| other.sub(&point).length_squared() >= spacing * spacing)
+        };
+        let center = Vec2::new(self.arena.width / dec64!(2), self.arena.height / dec64!(2));
+        if self.config.symmetry.is_some() && count % 2 == 1 {
+            if !self.is_clear(center, clear) {
+                return None;
+            }
+            points.push(center);
+        }
+        for _ in 0..PLACEMENTS {
+            if points.len() as u32 >= count {
+                return Some(points);
+            }
+            let point = self.random_point(rng, margin.max(1));
+            if !self.is_clear(point, clear) || !far_enough(&points, point) {
+                continue;
+            }
+            points.push(point);
+            if let Some(mirrored) = self.mirror(point)
+                && (points.len() as u32) < count
+            {
+                if !far_enough(&points, mirrored) {
+                    points.pop();
+                    continue;
+                }
+                points.push(mirrored);
+            }
+        }
+        (points.len() as u32 >= count).then_some(points)
+    }
+
+    fn place_spawn_points(&mut self, rng: &mut Rng) -> bool {
+        let spacing = Scalar::from(self.config.spawn_spacing);
+        let Some(points) = self.place_points(rng, self.config.spawn_points, 0, spacing) else {
+            return false;
+        };
+        self.arena.spawn_points = points;
+        true
+    }
+
+    fn place_zones(&mut self, rng: &mut Rng) -> bool {
+        let radius = self.config.zone_radius;
+        let spacing = Scalar::from(radius * 2);
+        let Some(centers) = self.place_points(rng, self.config.zones, radius, spacing) else {
+            return false;
+        };
+        self.arena.triggers = centers
+            .into_iter()
+            .map(|center| TriggerShape::Circle {
+                center,
+                radius: Scalar::from(radius),
+            })
+            .collect();
+        true
+    }
+}
+
+/// Returns whether every spawn point and zone can reach every other.
+fn is_connected(arena: &ArenaConfig, sim: &SimConfig) -> bool {
+    let nav = NavGrid::for_arena(arena, &sim.specs, sim.grid.nav_cell_size);
+    let zones = arena.triggers.iter().map(|shape| shape.bounds().center());
+    let mut points = arena.spawn_points.iter().copied().chain(zones);
+    let Some(start) = points.next() else {
+        return true;
+    };
+    let blocked = |point: Vec2| {
+        nav.cell_at(point)
+            .is_none_or(|(x, y)| nav.is_blocked(x as i64, y as i64))
+    };
+    !blocked(start) && points.all(|point| nav.find_path(start, point).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symmetry;
+
+    #[test]
+    fn generate_should_repeat_for_seed_and_vary_between_seeds() {
+        // Arrange
+        let config = GeneratorConfig::default();
+        let sim = SimConfig::default();
+
+        // Act
+        let first = generate(&config, &sim, 5).unwrap();
+        let again = generate(&config, &sim, 5).unwrap();
+        let other = generate(&config, &sim, 6).unwrap();
+
+        // Assert
+        assert_eq!(first, again);
+        assert_ne!(first, other);
+        assert!(!first.obstacles.is_empty());
+    }
+
+    #[test]
+    fn generate_should_give_valid_fair_arenas() {
+        // Arrange
+        let config = GeneratorConfig {
+            spawn_points: 4,
+            spawn_spacing: 128,
+            zones: 3,
+            ..GeneratorConfig::default()
+        };
+        let mut sim = SimConfig::default();
+
+        for seed in 0..8 {
+            // Act
+            sim.arena = generate(&config, &sim, seed).unwrap();
+
+            // Assert
+            assert_eq!(sim.arena.validate(), vec![], "seed {seed}");
+            assert_eq!(sim.arena.spawn_points.len(), 4);
+            assert_eq!(sim.arena.triggers.len(), 3);
+            assert!(is_connected(&sim.arena, &sim), "seed {seed}");
+            let issues = symmetry::check(&sim, Symmetry::HalfTurn);
+            assert_eq!(issues, vec![], "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn generate_with_fair_spawns_should_balance_them() {
+        // Arrange
+        let config = GeneratorConfig {
+            fair_spawns: true,
+            ..GeneratorConfig::default()
+        };
+        let mut sim = SimConfig::default();
+
+        // Act
+        sim.arena = generate(&config, &sim, 3).unwrap();
+
+        // Assert
+        let fairness = spawns::check(&sim);
+        assert_eq!(sim.arena.spawn_points.len(), 2);
+        assert!(fairness.is_fair(dec64!(32)), "{fairness:?}");
+    }
+
+    #[test]
+    fn generate_when_arena_too_small_should_fail() {
+        // Arrange
+        let config = GeneratorConfig {
+            width: 16,
+            ..GeneratorConfig::default()
+        };
+
+        // Act
+        let result = generate(&config, &SimConfig::default(), 1);
+
+        // Assert
+        assert_eq!(result, Err(GenerateError::TooSmall));
+    }
+}
@@ -0,0 +1,463 @@
+use crate::events::SimEvent;
+use crate::rounds::RoundState;
+use crate::state::{SimState, Tank};
+use crate::triggers::{Trigger, TriggerShape};
+use crate::util::math::{Scalar, Vec2};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Where a team's flag lives in capture the flag.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FlagBase {
+    pub team_id: u32,
+    pub position: Vec2,
+    pub radius: Scalar,
+}
+
+/// The objective of a match.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum GameMode {
+    /// No objective beyond destroying the enemy.
+    #[default]
+    Deathmatch,
+    /// Grab the enemy flag and bring it home while your own flag is safe at base.
+    CaptureTheFlag {
+        bases: Vec<FlagBase>,
+        captures_to_win: u32,
+        /// How close a tank must get to a dropped flag to pick it up or return it.
+        pickup_radius: Scalar,
+    },
+    /// Hold the hill alone to build up control time.
+    KingOfTheHill {
+        hill: TriggerShape,
+        ticks_to_win: u64,
+    },
+}
+
+/// A flag in capture the flag.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Flag {
+    pub team_id: u32,
+    /// Trigger covering the flag's base.
+    pub base_trigger: u32,
+    pub home: Vec2,
+    pub position: Vec2,
+    pub carrier: Option<u32>,
+    pub at_base: bool,
+}
+
+/// The hill in king of the hill.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Hill {
+    pub trigger_id: u32,
+    /// Team holding the hill uncontested, if any.
+    pub controller: Option<u32>,
+}
+
+/// Mode-specific match progress. Part of the state, so it rewinds and replays with everything else.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModeState {
+    /// Captures in capture the flag, control ticks in king of the hill.
+    pub scores: BTreeMap<u32, u64>,
+    pub winner: Option<u32>,
+    pub flags: Vec<Flag>,
+    pub hill: Option<Hill>,
+    /// Progress through the series, when the match is played in rounds.
+    #[serde(default)]
+    pub rounds: Option<RoundState>,
+}
+
+impl ModeState {
+    /// Returns whether the objective has been placed in the arena yet.
+    pub fn is_set_up(&self) -> bool {
+        !self.flags.is_empty() || self.hill.is_some()
+    }
+}
+
+/// Places the mode's triggers in the arena and resets its progress.
+pub fn setup(mode: &GameMode, state: &mut SimState) {
+    state.mode = ModeState::default();
+    match mode {
+        GameMode::Deathmatch => {}
+        GameMode::CaptureTheFlag { bases, .. } => {
+            for base in bases {
+                let id = state.allocate_id();
+                let shape = TriggerShape::Circle {
+                    center: base.position,
+                    radius: base.radius,
+                };
+                state.triggers.push(Trigger::new(id, shape));
+                state.mode.scores.insert(base.team_id, 0);
+                state.mode.flags.push(Flag {
+                    team_id: base.team_id,
+                    base_trigger: id,
+                    home: base.position,
+                    position: base.position,
+                    carrier: None,
+                    at_base: true,
+                });
+            }
+        }
+        GameMode::KingOfTheHill { hill, .. } => {
+            let id = state.allocate_id();
+            state.triggers.push(Trigger::new(id, *hill));
+            state.mode.hill = Some(Hill {
+                trigger_id: id,
+                controller: None,
+            });
+        }
+    }
+}
+
+/// Hands a team's flag straight to a living tank of another team that isn't carrying one
+/// already, returning whether it could.
+pub fn give_flag(
+    state: &mut SimState,
+    flag_team_id: u32,
+    tank_id: u32,
+    events: &mut Vec<SimEvent>,
+) -> bool {
+    let Some(tank) = state.tank(tank_id).filter(|tank| tank.is_alive()) else {
+        return false;
+    };
+    let (team_id, position) = (tank.team_id, tank.position);
+    let flags = &mut state.mode.flags;
+    if team_id == flag_team_id || flags.iter().any(|flag| flag.carrier == Some(tank_id)) {
+        return false;
+    }
+    let Some(flag) = flags.iter_mut().find(|flag| flag.team_id == flag_team_id) else {
+        return false;
+    };
+    flag.carrier = Some(tank_id);
+    flag.at_base = false;
+    flag.position = position;
+    events.push(SimEvent::FlagTaken {
+        team_id: flag_team_id,
+        tank_id,
+    });
+    true
+}
+
+/// Advances the objective by one tick, based on who is where at the end of it.
+///
+/// Reads trigger occupancy, so it must run after triggers are updated.
+pub fn update(mode: &GameMode, state: &mut SimState, events: &mut Vec<SimEvent>) {
+    if state.mode.winner.is_some() {
+        return;
+    }
+    let SimState {
+        tanks,
+        triggers,
+        mode: progress,
+        ..
+    } = state;
+    let occupants = |trigger_id: u32| -> BTreeSet<u32> {
+        triggers
+            .iter()
+            .find(|trigger| trigger.id == trigger_id)
+            .map(|trigger| trigger.occupants.clone())
+            .unwrap_or_default()
+    };
+
+    match mode {
+        GameMode::Deathmatch => {}
+        GameMode::CaptureTheFlag {
+            captures_to_win,
+            pickup_radius,
+            ..
+        } => {
+            update_flags(progress, tanks, &occupants, *pickup_radius, events);
+            let leader = progress
+                .scores
+                .iter()
+                .find(|(_, score)| **score >= *captures_to_win as u64);
+            if let Some((team_id, _)) = leader {
+                progress.winner = Some(*team_id);
+                events.push(SimEvent::MatchWon { team_id: *team_id });
+            }
+        }
+        GameMode::KingOfTheHill { ticks_to_win, .. } => {
+            let Some(hill) = progress.hill.as_mut() else {
+                return;
+            };
+            let teams: BTreeSet<u32> = occupants(hill.trigger_id)
+                .iter()
+                .filter_map(|id| tanks.iter().find(|tank| tank.id == *id))
+                .map(|tank| tank.team_id)
+                .collect();
+            // contested or empty hills belong to nobody
+            let controller = match teams.len() {
+                1 => teams.first().copied(),
+                _ => None,
+            };
+            if controller != hill.controller {
+                hill.controller = controller;
+                events.push(SimEvent::HillControlChanged {
+                    team_id: controller,
+                });
+            }
+
+            let Some(team_id) = controller else {
+                return;
+            };
+            let score = progress.scores.entry(team_id).or_default();
+            *score += 1;
+            if *score >= *ticks_to_win {
+                progress.winner = Some(team_id);
+                events.push(SimEvent::MatchWon { team_id });
+            }
+        }
+    }
+}
+
+fn update_flags(
+    progress: &mut ModeState,
+    tanks: &[Tank],
+    occupants: &impl Fn(u32) -> BTreeSet<u32>,
+    pickup_radius: Scalar,
+    events: &mut Vec<SimEvent>,
+) {
+    let alive = |id: u32| tanks.iter().find(|tank| tank.id == id && tank.is_alive());
+
+    // carriers first: follow them, drop when they die
+    for flag in progress.flags.iter_mut() {
+        let Some(carrier) = flag.carrier else {
+            continue;
+        };
+        match alive(carrier) {
+            Some(tank) => flag.position = tank.position,
+            None => {
+                flag.carrier = None;
+                events.push(SimEvent::FlagDropped {
+                    team_id: flag.team_id,
+                });
+            }
+        }
+    }
+
+    // captures need the carrier's own flag safely at home
+    let home: Vec<(u32, u32, bool)> = progress
+        .flags
+        .iter()
+        .map(|flag| (flag.team_id, flag.base_trigger, flag.at_base))
+        .collect();
+    for flag in progress.flags.iter_mut() {
+        let Some(tank) = flag.carrier.and_then(alive) else {
+            continue;
+        };
+        let captured = home.iter().any(|(team_id, base, at_base)| {
+            *team_id == tank.team_id && *at_base && occupants(*base).contains(&tank.id)
+        });
+        if captured {
+            *progress.scores.entry(tank.team_id).or_default() += 1;
+            events.push(SimEvent::FlagCaptured {
+                team_id: flag.team_id,
+                tank_id: tank.id,
+            });
+            flag.carrier = None;
+            flag.at_base = true;
+            flag.position = flag.home;
+        }
+    }
+
+    // then pickups and returns, lowest tank ID first
+    let carrying: BTreeSet<u32> = progress
+        .flags
+        .iter()
+        .filter_map(|flag| flag.carrier)
+        .collect();
+    for flag in progress.flags.iter_mut() {
+        if flag.carrier.is_some() {
+            continue;
+        }
+        let nearby: Vec<&Tank> = if flag.at_base {
+            occupants(flag.base_trigger)
+                .into_iter()
+                .filter_map(alive)
+                .collect()
+        } else {
+            let mut nearby: Vec<&Tank> = tanks
+                .iter()
+                .filter(|tank| tank.is_alive())
+                .filter(|tank| {
+                    tank.position.sub(&flag.position).length_squared()
+                        <= pickup_radius * pickup_radius
+                })
+                .collect();
+            nearby.sort_by_key(|tank| tank.id);
+            nearby
+        };
+
+        let friendly = nearby.iter().any(|tank| tank.team_id == flag.team_id);
+        if !flag.at_base && friendly {
+            flag.at_base = true;
+            flag.position = flag.home;
+            events.push(SimEvent::FlagReturned {
+                team_id: flag.team_id,
+            });
+            continue;
+        }
+        let taker = nearby
+            .iter()
+            .find(|tank| tank.team_id != flag.team_id && !carrying.contains(&tank.id));
+        if let Some(tank) = taker {
+            flag.carrier = Some(tank.id);
+            flag.at_base = false;
+            flag.position = tank.position;
+            events.push(SimEvent::FlagTaken {
+                team_id: flag.team_id,
+                tank_id: tank.id,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use crate::triggers::TriggerIndex;
+    use fastnum::dec64;
+
+    fn tank(id: u32, team_id: u32, x: f64, y: f64) -> Tank {
+        test_support::tank(id, team_id, Vec2::new_from_f64(x, y))
+    }
+
+    fn start(mode: &GameMode, tanks: Vec<Tank>) -> SimState {
+        let mut state = SimState::new(0);
+        state.next_id = 100;
+        state.tanks = tanks;
+        setup(mode, &mut state);
+        state
+    }
+
+    /// Runs one tick of trigger and objective updates.
+    fn tick(mode: &GameMode, state: &mut SimState) -> Vec<SimEvent> {
+        let mut events = Vec::new();
+        let index = TriggerIndex::new(dec64!(1000), dec64!(1000), &state.triggers);
+        index.update(&mut state.triggers, &state.tanks, &mut events);
+        update(mode, state, &mut events);
+        events
+    }
+
+    fn king_of_the_hill(ticks_to_win: u64) -> GameMode {
+        GameMode::KingOfTheHill {
+            hill: TriggerShape::Circle {
+                center: Vec2::new_from_f64(500.0, 500.0),
+                radius: dec64!(50),
+            },
+            ticks_to_win,
+        }
+    }
+
+    fn capture_the_flag() -> GameMode {
+        GameMode::CaptureTheFlag {
+            bases: vec![
+                FlagBase {
+                    team_id: 0,
+                    position: Vec2::new_from_f64(100.0, 500.0),
+                    radius: dec64!(30),
+                },
+                FlagBase {
+                    team_id: 1,
+                    position: Vec2::new_from_f64(900.0, 500.0),
+                    radius: dec64!(30),
+                },
+            ],
+            captures_to_win: 1,
+            pickup_radius: dec64!(20),
+        }
+    }
+
+    #[test]
+    fn update_when_hill_held_alone_should_score_until_win() {
+        // Arrange
+        let mode = king_of_the_hill(3);
+        let mut state = start(&mode, vec![tank(1, 0, 500.0, 500.0)]);
+
+        // Act
+        let first = tick(&mode, &mut state);
+        tick(&mode, &mut state);
+        let last = tick(&mode, &mut state);
+        let after = tick(&mode, &mut state);
+
+        // Assert
+        assert!(first.contains(&SimEvent::HillControlChanged { team_id: Some(0) }));
+        assert!(last.contains(&SimEvent::MatchWon { team_id: 0 }));
+        assert!(after.is_empty());
+        assert_eq!(state.mode.scores[&0], 3);
+        assert_eq!(state.mode.winner, Some(0));
+    }
+
+    #[test]
+    fn update_when_hill_contested_should_not_score() {
+        // Arrange
+        let mode = king_of_the_hill(3);
+        let mut state = start(
+            &mode,
+            vec![tank(1, 0, 500.0, 500.0), tank(2, 1, 520.0, 500.0)],
+        );
+
+        // Act
+        for _ in 0..5 {
+            tick(&mode, &mut state);
+        }
+
+        // Assert
+        assert!(state.mode.scores.is_empty());
+        assert_eq!(state.mode.hill.unwrap().controller, None);
+        assert_eq!(state.mode.winner, None);
+    }
+
+    #[test]
+    fn update_when_enemy_flag_brought_home_should_capture() {
+        // Arrange
+        let mode = capture_the_flag();
+        let mut state = start(&mode, vec![tank(1, 0, 900.0, 500.0)]);
+
+        // Act
+        let taken = tick(&mode, &mut state);
+        state.tanks[0].position = Vec2::new_from_f64(100.0, 500.0);
+        let captured = tick(&mode, &mut state);
+
+        // Assert
+        assert!(taken.contains(&SimEvent::FlagTaken {
+            team_id: 1,
+            tank_id: 1
+        }));
+        assert!(captured.contains(&SimEvent::FlagCaptured {
+            team_id: 1,
+            tank_id: 1
+        }));
+        assert!(captured.contains(&SimEvent::MatchWon { team_id: 0 }));
+        assert_eq!(state.mode.scores[&0], 1);
+        assert!(state.mode.flags[1].at_base);
+        assert_eq!(state.mode.flags[1].carrier, None);
+    }
+
+    #[test]
+    fn update_when_carrier_destroyed_should_drop_flag_for_defenders_to_return() {
+        // Arrange
+        let mode = capture_the_flag();
+        let mut state = start(
+            &mode,
+            vec![tank(1, 0, 900.0, 500.0), tank(2, 1, 500.0, 500.0)],
+        );
+        tick(&mode, &mut state);
+        state.tanks[0].position = Vec2::new_from_f64(600.0, 500.0);
+        tick(&mode, &mut state);
+
+        // Act
+        state.tanks[0].health = 0;
+        let dropped = tick(&mode, &mut state);
+        state.tanks[1].position = Vec2::new_from_f64(610.0, 500.0);
+        let returned = tick(&mode, &mut state);
+
+        // Assert
+        assert!(dropped.contains(&SimEvent::FlagDropped { team_id: 1 }));
+        assert!(returned.contains(&SimEvent::FlagReturned { team_id: 1 }));
+        assert!(state.mode.flags[1].at_base);
+        assert_eq!(state.mode.flags[1].position, state.mode.flags[1].home);
+        assert_eq!(state.mode.scores[&0], 0);
+    }
+}
@@ -0,0 +1,366 @@
+use crate::arena::ArenaConfig;
+use crate::physics::collision::AABB;
+use crate::spec::SpecTable;
+use crate::state::Obstacle;
+use crate::util::math::{Scalar, Vec2};
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+/// Neighbour offsets, in the fixed order they're expanded in.
+const NEIGHBOURS: [(i64, i64); 8] = [
+    (1, 0),
+    (0, 1),
+    (-1, 0),
+    (0, -1),
+    (1, 1),
+    (-1, 1),
+    (-1, -1),
+    (1, -1),
+];
+
+/// Returns how far obstacles are inflated for path planning: half the widest hull, so any
+/// class can follow the paths.
+pub fn clearance(specs: &SpecTable) -> Scalar {
+    specs
+        .tanks
+        .iter()
+        .map(|spec| spec.hull_size.y / dec64!(2))
+        .max()
+        .unwrap_or(dec64!(0))
+}
+
+/// A coarse walkability grid over the arena, used for path planning.
+///
+/// Obstacles are inflated by a clearance before rasterizing, so paths through free cells keep
+/// hulls off the walls.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NavGrid {
+    width: u32,
+    height: u32,
+    cell_size: Scalar,
+    blocked: Vec<bool>,
+}
+
+impl NavGrid {
+    pub fn new(
+        arena_width: Scalar,
+        arena_height: Scalar,
+        cell_size: Scalar,
+        clearance: Scalar,
+        obstacles: &[Obstacle],
+    ) -> Self {
+        let width = (arena_width / cell_size)
+            .ceil()
+            .to_u32()
+            .unwrap_or(1)
+            .max(1);
+        let height = (arena_height / cell_size)
+            .ceil()
+            .to_u32()
+            .unwrap_or(1)
+            .max(1);
+        let inflated: Vec<AABB> = obstacles
+            .iter()
+            .map(|obstacle| obstacle.aabb.expand(clearance))
+            .collect();
+
+        let mut blocked = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let min = Vec2::new(cell_size * Scalar::from(x), cell_size * Scalar::from(y));
+                let cell = AABB::new(min, min + Vec2::new(cell_size, cell_size));
+                blocked.push(inflated.iter().any(|aabb| aabb.intersects(&cell)));
+            }
+        }
+
+        NavGrid {
+            width,
+            height,
+            cell_size,
+            blocked,
+        }
+    }
+
+    /// Builds the grid the engine would plan paths on for an authored arena.
+    pub fn for_arena(arena: &ArenaConfig, specs: &SpecTable, cell_size: Scalar) -> Self {
+        let obstacles: Vec<Obstacle> = arena
+            .obstacles
+            .iter()
+            .enumerate()
+            .map(|(id, aabb)| Obstacle {
+                id: id as u32,
+                aabb: *aabb,
+            })
+            .collect();
+        NavGrid::new(
+            arena.width,
+            arena.height,
+            cell_size,
+            clearance(specs),
+            &obstacles,
+        )
+    }
+
+    /// Number of columns.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Number of rows.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn cell_size(&self) -> Scalar {
+        self.cell_size
+    }
+
+    /// Returns whether a cell is impassable. Cells outside the grid are always impassable.
+    pub fn is_blocked(&self, x: i64, y: i64) -> bool {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return true;
+        }
+        self.blocked[(y * self.width as i64 + x) as usize]
+    }
+
+    /// Returns the cell containing a point, if it lies within the grid.
+    pub fn cell_at(&self, point: Vec2) -> Option<(u32, u32)> {
+        let x = (point.x / self.cell_size).floor().to_i64().ok()?;
+        let y = (point.y / self.cell_size).floor().to_i64().ok()?;
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return None;
+        }
+        Some((x as u32, y as u32))
+    }
+
+    /// Returns the world-space centre of a cell.
+    pub fn cell_center(&self, x: u32, y: u32) -> Vec2 {
+        let half = self.cell_size / dec64!(2);
+        Vec2::new(
+            self.cell_size * Scalar::from(x) + half,
+            self.cell_size * Scalar::from(y) + half,
+        )
+    }
+
+    /// Plans a path between two points with A*, returning the waypoints to visit in order.
+    ///
+    /// The start is not included, and the last waypoint is always `to` itself. Intermediate
+    /// waypoints are the centres of cells where the path changes direction. Paths never cut
+    /// corners past blocked cells. Returns `None` if either end is outside the grid, the goal is
+    /// blocked, or there is no way through.
+    ///
+    /// The search is fully deterministic: ties are broken the same way on every machine.
+    pub fn find_path(&self, from: Vec2, to: Vec2) -> Option<Vec<Vec2>> {
+        let start = self.cell_at(from)?;
+        let goal = self.cell_at(to)?;
+        if self.is_blocked(goal.0 as i64, goal.1 as i64) {
+            return None;
+        }
+
+        let cells = self.search(start, goal)?;
+        let mut waypoints = Vec::new();
+        for window in cells.windows(3) {
+            let [a, b, c] = [window[0], window[1], window[2]];
+            let incoming = (b.0 as i64 - a.0 as i64, b.1 as i64 - a.1 as i64);
+            let outgoing = (c.0 as i64 - b.0 as i64, c.1 as i64 - b.1 as i64);
+            if incoming != outgoing {
+                waypoints.push(self.cell_center(b.0, b.1));
+            }
+        }
+        waypoints.push(to);
+
+        Some(waypoints)
+    }
+
+    /// Returns how far a tank travels along the planned path between two points, if there is
+    /// one.
+    pub fn path_length(&self, from: Vec2, to: Vec2) -> Option<Scalar> {
+        let mut length = dec64!(0);
+        let mut at = from;
+        for waypoint in self.find_path(from, to)? {
+            length += waypoint.sub(&at).length_squared().sqrt();
+            at = waypoint;
+        }
+        Some(length)
+    }
+
+    /// Returns the first waypoint on the path from `from` to `to`, if there is a path.
+    pub fn next_waypoint(&self, from: Vec2, to: Vec2) -> Option<Vec2> {
+        self.find_path(from, to)?.first().copied()
+    }
+
+    /// Octile distance, which never overestimates with 8-connected movement.
+    fn heuristic(&self, (x, y): (u32, u32), (gx, gy): (u32, u32)) -> u32 {
+        let dx = x.abs_diff(gx);
+        let dy = y.abs_diff(gy);
+        STRAIGHT_COST * dx.max(dy) + (DIAGONAL_COST - STRAIGHT_COST) * dx.min(dy)
+    }
+
+    /// Returns the cells from `start` to `goal` inclusive.
+    fn search(&self, start: (u32, u32), goal: (u32, u32)) -> Option<Vec<(u32, u32)>> {
+        let width = self.width as usize;
+        let index = |(x, y): (u32, u32)| y as usize * width + x as usize;
+        let cell = |index: usize| ((index % width) as u32, (index / width) as u32);
+
+        let mut cost = vec![u32::MAX; self.blocked.len()];
+        let mut came_from = vec![usize::MAX; self.blocked.len()];
+        let mut closed = vec![false; self.blocked.len()];
+        // ordered by estimated total cost, then by remaining estimate, then by index
+        let mut open = BinaryHeap::new();
+
+        cost[index(start)] = 0;
+        let estimate = self.heuristic(start, goal);
+        open.push(Reverse((estimate, estimate, index(start))));
+
+        while let Some(Reverse((_, _, current))) = open.pop() {
+            if current == index(goal) {
+                let mut path = vec![goal];
+                let mut at = current;
+                while at != index(start) {
+                    at = came_from[at];
+                    path.push(cell(at));
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if std::mem::replace(&mut closed[current], true) {
+                continue;
+            }
+
+            let (x, y) = cell(current);
+            let (x, y) = (x as i64, y as i64);
+            for (dx, dy) in NEIGHBOURS {
+                if self.is_blocked(x + dx, y + dy) {
+                    continue;
+                }
+                let diagonal = dx != 0 && dy != 0;
+                if diagonal && (self.is_blocked(x + dx, y) || self.is_blocked(x, y + dy)) {
+                    continue;
+                }
+
+                let next = ((x + dx) as u32, (y + dy) as u32);
+                let step = if diagonal {
+                    DIAGONAL_COST
+                } else {
+                    STRAIGHT_COST
+                };
+                let next_cost = cost[current] + step;
+                if next_cost < cost[index(next)] {
+                    cost[index(next)] = next_cost;
+                    came_from[index(next)] = current;
+                    let remaining = self.heuristic(next, goal);
+                    open.push(Reverse((next_cost + remaining, remaining, index(next))));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(obstacles: &[AABB]) -> NavGrid {
+        let obstacles: Vec<Obstacle> = obstacles
+            .iter()
+            .enumerate()
+            .map(|(id, aabb)| Obstacle {
+                id: id as u32,
+                aabb: *aabb,
+            })
+            .collect();
+        NavGrid::new(dec64!(100), dec64!(100), dec64!(10), dec64!(0), &obstacles)
+    }
+
+    #[test]
+    fn find_path_when_unobstructed_should_go_straight_to_goal() {
+        // Arrange
+        let grid = grid(&[]);
+        let to = Vec2::new_from_f64(95.0, 5.0);
+
+        // Act
+        let path = grid.find_path(Vec2::new_from_f64(5.0, 5.0), to);
+
+        // Assert
+        assert_eq!(path, Some(vec![to]));
+    }
+
+    #[test]
+    fn find_path_when_wall_in_the_way_should_go_around_without_cutting_corners() {
+        // Arrange
+        // a wall across x = 50, open only along the bottom row
+        let wall = AABB::new(
+            Vec2::new_from_f64(50.0, 0.0),
+            Vec2::new_from_f64(60.0, 90.0),
+        );
+        let grid = grid(&[wall]);
+
+        // Act
+        let path = grid
+            .find_path(Vec2::new_from_f64(5.0, 5.0), Vec2::new_from_f64(95.0, 5.0))
+            .unwrap();
+
+        // Assert
+        assert!(path.iter().any(|point| point.y > dec64!(90)));
+        for point in &path {
+            let (x, y) = grid.cell_at(*point).unwrap();
+            assert!(!grid.is_blocked(x as i64, y as i64));
+        }
+        // the gap is a single cell with walls below it, so it can only be entered from the side
+        let cells = grid.search((0, 0), (9, 0)).unwrap();
+        let gap = cells.iter().position(|cell| *cell == (5, 9)).unwrap();
+        assert_eq!(cells[gap - 1], (4, 9));
+        assert_eq!(cells[gap + 1], (6, 9));
+    }
+
+    #[test]
+    fn find_path_when_goal_unreachable_should_return_none() {
+        // Arrange
+        let wall = AABB::new(
+            Vec2::new_from_f64(50.0, 0.0),
+            Vec2::new_from_f64(60.0, 100.0),
+        );
+        let grid = grid(&[wall]);
+
+        // Act
+        let walled_off =
+            grid.find_path(Vec2::new_from_f64(5.0, 5.0), Vec2::new_from_f64(95.0, 5.0));
+        let inside_wall =
+            grid.find_path(Vec2::new_from_f64(5.0, 5.0), Vec2::new_from_f64(55.0, 5.0));
+        let off_grid = grid.find_path(Vec2::new_from_f64(5.0, 5.0), Vec2::new_from_f64(-5.0, 5.0));
+
+        // Assert
+        assert_eq!(walled_off, None);
+        assert_eq!(inside_wall, None);
+        assert_eq!(off_grid, None);
+    }
+
+    #[test]
+    fn new_when_clearance_given_should_inflate_obstacles() {
+        // Arrange
+        let obstacles = [Obstacle {
+            id: 0,
+            aabb: AABB::new(
+                Vec2::new_from_f64(40.0, 40.0),
+                Vec2::new_from_f64(60.0, 60.0),
+            ),
+        }];
+
+        // Act
+        let tight = NavGrid::new(dec64!(100), dec64!(100), dec64!(10), dec64!(0), &obstacles);
+        let padded = NavGrid::new(dec64!(100), dec64!(100), dec64!(10), dec64!(5), &obstacles);
+
+        // Assert
+        assert!(!tight.is_blocked(3, 5));
+        assert!(tight.is_blocked(4, 5));
+        assert!(padded.is_blocked(3, 5));
+        assert!(!padded.is_blocked(2, 5));
+    }
+}
@@ -0,0 +1,361 @@
+use crate::agent::{AgentAction, DISCRETE_ACTIONS, ObservationSpec};
+use crate::util::math::Scalar;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Fractional bits in the network's fixed-point numbers.
+const FRACTION_BITS: u32 = 16;
+
+/// Values a continuous head outputs: left track, right track, turret slew and fire.
+const CONTINUOUS_OUTPUTS: usize = 4;
+
+/// Converts a float to fixed point, saturating at the ends of the range.
+fn quantize(value: f64) -> i32 {
+    (value * (1u64 << FRACTION_BITS) as f64)
+        .round()
+        .clamp(i32::MIN as f64, i32::MAX as f64) as i32
+}
+
+fn to_scalar(value: i32) -> Scalar {
+    Scalar::from(value) / Scalar::from(1u32 << FRACTION_BITS)
+}
+
+/// What a layer does to its outputs.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Activation {
+    #[default]
+    Linear,
+    Relu,
+    /// Clamps to `[-1, 1]`, standing in for tanh without needing transcendentals.
+    HardTanh,
+}
+
+/// A fully connected layer, as stored on disk.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LayerFile {
+    /// One row of input weights per output.
+    pub weights: Vec<Vec<f64>>,
+    pub biases: Vec<f64>,
+    #[serde(default)]
+    pub activation: Activation,
+}
+
+/// How the network's outputs become an action.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ActionHead {
+    /// One output per discrete action; the highest wins, the lowest index on ties.
+    Discrete,
+    /// Four outputs, used as a continuous action.
+    Continuous,
+}
+
+/// A trained network, as stored on disk: float weights plus how to feed it and read it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NetworkFile {
+    pub observation: ObservationSpec,
+    pub head: ActionHead,
+    pub layers: Vec<LayerFile>,
+}
+
+/// Errors produced while loading a network.
+#[derive(Debug, PartialEq)]
+pub enum NetworkError {
+    Parse(String),
+    NoLayers,
+    /// A layer has no outputs, or its bias count doesn't match its rows.
+    LayerShape {
+        layer: usize,
+    },
+    /// A layer's inputs don't match what feeds it.
+    InputSize {
+        layer: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// The last layer's outputs don't match the action head.
+    OutputSize {
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkError::Parse(message) => write!(f, "invalid network: {message}"),
+            NetworkError::NoLayers => write!(f, "network has no layers"),
+            NetworkError::LayerShape { layer } => {
+                write!(f, "layer {layer} has no outputs or mismatched biases")
+            }
+            NetworkError::InputSize {
+                layer,
+                expected,
+                found,
+            } => write!(f, "layer {layer} takes {found} inputs, expected {expected}"),
+            NetworkError::OutputSize { expected, found } => {
+                write!(f, "network outputs {found} values, expected {expected}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+/// A fully connected layer in fixed point.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Layer {
+    inputs: usize,
+    /// Row-major, one row of `inputs` weights per output.
+    weights: Vec<i32>,
+    biases: Vec<i32>,
+    activation: Activation,
+}
+
+impl Layer {
+    fn evaluate(&self, input: &[i32]) -> Vec<i32> {
+        let one = 1i64 << FRACTION_BITS;
+        self.biases
+            .iter()
+            .zip(self.weights.chunks(self.inputs))
+            .map(|(bias, row)| {
+                let sum: i64 = row
+                    .iter()
+                    .zip(input)
+                    .map(|(weight, value)| *weight as i64 * *value as i64)
+                    .sum();
+                let value = (sum >> FRACTION_BITS) + *bias as i64;
+                let value = match self.activation {
+                    Activation::Linear => value,
+                    Activation::Relu => value.max(0),
+                    Activation::HardTanh => value.clamp(-one, one),
+                };
+                value.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+            })
+            .collect()
+    }
+}
+
+/// A small feed-forward network that drives a tank from its observations.
+///
+/// Weights are quantized to fixed point on load and evaluated with integer arithmetic only,
+/// so a network picks the same actions on every machine and matches replay exactly.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NetworkController {
+    observation: ObservationSpec,
+    head: ActionHead,
+    layers: Vec<Layer>,
+}
+
+impl NetworkController {
+    /// Loads a network from a JSON [`NetworkFile`], checking that its layers fit together, its
+    /// first layer takes the observation and its last feeds the action head.
+    pub fn from_json(json: &str) -> Result<Self, NetworkError> {
+        let file: NetworkFile =
+            serde_json::from_str(json).map_err(|e| NetworkError::Parse(e.to_string()))?;
+        Self::from_file(file)
+    }
+
+    /// Quantizes an already parsed network, checking it the same way as
+    /// [`NetworkController::from_json`].
+    pub fn from_file(file: NetworkFile) -> Result<Self, NetworkError> {
+        if file.layers.is_empty() {
+            return Err(NetworkError::NoLayers);
+        }
+
+        let mut inputs = file.observation.size();
+        let mut layers = Vec::with_capacity(file.layers.len());
+        for (index, layer) in file.layers.iter().enumerate() {
+            if layer.biases.is_empty() || layer.weights.len() != layer.biases.len() {
+                return Err(NetworkError::LayerShape { layer: index });
+            }
+            if let Some(row) = layer.weights.iter().find(|row| row.len() != inputs) {
+                return Err(NetworkError::InputSize {
+                    layer: index,
+                    expected: inputs,
+                    found: row.len(),
+                });
+            }
+            layers.push(Layer {
+                inputs,
+                weights: layer
+                    .weights
+                    .iter()
+                    .flatten()
+                    .map(|w| quantize(*w))
+                    .collect(),
+                biases: layer.biases.iter().map(|b| quantize(*b)).collect(),
+                activation: layer.activation,
+            });
+            inputs = layer.biases.len();
+        }
+
+        let expected = match file.head {
+            ActionHead::Discrete => DISCRETE_ACTIONS as usize,
+            ActionHead::Continuous => CONTINUOUS_OUTPUTS,
+        };
+        if inputs != expected {
+            return Err(NetworkError::OutputSize {
+                expected,
+                found: inputs,
+            });
+        }
+
+        Ok(NetworkController {
+            observation: file.observation,
+            head: file.head,
+            layers,
+        })
+    }
+
+    /// Returns the observations the network expects.
+    pub fn observation_spec(&self) -> &ObservationSpec {
+        &self.observation
+    }
+
+    /// Picks an action for an observation built with [`NetworkController::observation_spec`].
+    pub fn act(&self, observation: &[f32]) -> AgentAction {
+        let mut values: Vec<i32> = observation
+            .iter()
+            .map(|value| quantize(*value as f64))
+            .collect();
+        for layer in &self.layers {
+            values = layer.evaluate(&values);
+        }
+
+        match self.head {
+            ActionHead::Discrete => {
+                let best = values.iter().enumerate().fold(0, |best, (index, value)| {
+                    if *value > values[best] { index } else { best }
+                });
+                AgentAction::Discrete(best as u32)
+            }
+            ActionHead::Continuous => AgentAction::Continuous {
+                left: to_scalar(values[0]),
+                right: to_scalar(values[1]),
+                turret: to_scalar(values[2]),
+                fire: to_scalar(values[3]),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fastnum::dec64;
+
+    fn spec() -> ObservationSpec {
+        ObservationSpec {
+            nearest_enemies: 0,
+            rays: 0,
+            range: dec64!(100),
+        }
+    }
+
+    fn network(
+        head: ActionHead,
+        layers: Vec<LayerFile>,
+    ) -> Result<NetworkController, NetworkError> {
+        NetworkController::from_file(NetworkFile {
+            observation: spec(),
+            head,
+            layers,
+        })
+    }
+
+    #[test]
+    fn act_should_evaluate_layers_in_fixed_point() {
+        // Arrange
+        // hidden = relu(x0 - x1), outputs = (2 * hidden, -hidden, 0.25, hidden - 0.5)
+        let inputs = spec().size();
+        let mut hidden = vec![0.0; inputs];
+        hidden[0] = 1.0;
+        hidden[1] = -1.0;
+        let net = network(
+            ActionHead::Continuous,
+            vec![
+                LayerFile {
+                    weights: vec![hidden],
+                    biases: vec![0.0],
+                    activation: Activation::Relu,
+                },
+                LayerFile {
+                    weights: vec![vec![2.0], vec![-1.0], vec![0.0], vec![1.0]],
+                    biases: vec![0.0, 0.0, 0.25, -0.5],
+                    activation: Activation::HardTanh,
+                },
+            ],
+        )
+        .unwrap();
+        let mut observation = vec![0.0; inputs];
+
+        // Act
+        observation[0] = 0.75;
+        observation[1] = 0.5;
+        let forward = net.act(&observation);
+        observation[1] = 1.0;
+        let clipped = net.act(&observation);
+
+        // Assert
+        assert_eq!(
+            forward,
+            AgentAction::Continuous {
+                left: dec64!(0.5),
+                right: dec64!(-0.25),
+                turret: dec64!(0.25),
+                fire: dec64!(-0.25),
+            }
+        );
+        assert_eq!(
+            clipped,
+            AgentAction::Continuous {
+                left: dec64!(0),
+                right: dec64!(0),
+                turret: dec64!(0.25),
+                fire: dec64!(-0.5),
+            }
+        );
+    }
+
+    #[test]
+    fn from_file_when_shapes_mismatch_should_report_where() {
+        // Arrange
+        let inputs = spec().size();
+        let layer = |rows: usize, columns: usize| LayerFile {
+            weights: vec![vec![0.0; columns]; rows],
+            biases: vec![0.0; rows],
+            activation: Activation::Linear,
+        };
+
+        // Act
+        let empty = network(ActionHead::Discrete, vec![]);
+        let wrong_input = network(ActionHead::Continuous, vec![layer(4, inputs + 1)]);
+        let wrong_output = network(ActionHead::Discrete, vec![layer(8, inputs), layer(4, 8)]);
+        let discrete = network(
+            ActionHead::Discrete,
+            vec![layer(DISCRETE_ACTIONS as usize, inputs)],
+        );
+
+        // Assert
+        assert_eq!(empty.unwrap_err(), NetworkError::NoLayers);
+        assert_eq!(
+            wrong_input.unwrap_err(),
+            NetworkError::InputSize {
+                layer: 0,
+                expected: inputs,
+                found: inputs + 1,
+            }
+        );
+        assert_eq!(
+            wrong_output.unwrap_err(),
+            NetworkError::OutputSize {
+                expected: DISCRETE_ACTIONS as usize,
+                found: 4,
+            }
+        );
+        assert_eq!(
+            discrete.unwrap().act(&vec![0.0; inputs]),
+            AgentAction::Discrete(0)
+        );
+    }
+}
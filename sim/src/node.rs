@@ -0,0 +1,1708 @@
+use crate::arena::ArenaConfig;
+use crate::bots::{self, BotCommand, ControlOverride};
+use crate::clock::{TickRate, TimeControl};
+use crate::commands::Command;
+use crate::config::{SimConfig, ValidatedConfig};
+use crate::console;
+use crate::debug_draw::DebugCategory;
+use crate::error::{BotLoadError, SimError};
+use crate::log::{Severity, Subsystem};
+use crate::modes::GameMode;
+use crate::nav::NavGrid;
+use crate::network::NetworkController;
+use crate::physics::collision::AABB;
+use crate::physics::drivetrain::DriveInput;
+use crate::physics::turret::TurretCommand;
+use crate::replay::{Replay, ReplayPlayer, TransformFrame};
+use crate::resources::{ArenaConfigResource, BotProgram, BotProgramResource};
+use crate::respawn::RespawnConfig;
+use crate::save::{MatchSave, SaveError};
+use crate::scenario::Scenario;
+use crate::sim::{ReloadPolicy, SimEngine, TankSpawn};
+use crate::spawns;
+use crate::spec::{ExplosionSpec, Loadout};
+use crate::spectator::EntitySummary;
+use crate::state::SimState;
+use crate::tags::Tag;
+use crate::telemetry::{BinarySink, JsonLinesSink, TelemetryFrame, TelemetrySink};
+use crate::training::{AttemptStats, Drill, ShootingRange};
+use crate::triggers::TriggerShape;
+use crate::util::math::{Angle, ConvertToScalar, Vec2};
+use crate::vm::profile::FunctionSymbol;
+use crate::worker::SimWorker;
+use godot::classes::ProjectSettings;
+use godot::prelude::*;
+use std::fs::File;
+use std::io::BufWriter;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+pub(crate) fn to_vec2(vector: Vector2) -> Vec2 {
+    Vec2::new_from_f64(vector.x as f64, vector.y as f64)
+}
+
+fn from_vec2(vector: Vec2) -> Vector2 {
+    Vector2::new(vector.x.to_f64() as f32, vector.y.to_f64() as f32)
+}
+
+/// Packs a navigation grid as `{ width, height, cell_size, cells }`, where `cells` holds one
+/// byte per cell in row-major order (1 blocked, 0 free).
+fn nav_grid_dict(nav: &NavGrid) -> Dictionary {
+    let mut cells = Vec::with_capacity((nav.width() * nav.height()) as usize);
+    for y in 0..nav.height() as i64 {
+        for x in 0..nav.width() as i64 {
+            cells.push(nav.is_blocked(x, y) as u8);
+        }
+    }
+    let mut dict = Dictionary::new();
+    dict.set("width", nav.width() as i64);
+    dict.set("height", nav.height() as i64);
+    dict.set("cell_size", nav.cell_size().to_f64());
+    dict.set("cells", PackedByteArray::from(cells));
+    dict
+}
+
+/// Packs a drill attempt's score as `{ ticks, shots_fired, hits, accuracy, drones_destroyed,
+/// cleared }`.
+fn attempt_dict(attempt: &AttemptStats) -> Dictionary {
+    let mut dict = Dictionary::new();
+    dict.set("ticks", attempt.ticks as i64);
+    dict.set("shots_fired", attempt.shots_fired as i64);
+    dict.set("hits", attempt.hits as i64);
+    dict.set("accuracy", attempt.accuracy.to_f64());
+    dict.set("drones_destroyed", attempt.drones_destroyed as i64);
+    dict.set("cleared", attempt.cleared);
+    dict
+}
+
+/// Packs an entity summary as a dictionary with a `kind` of "tank", "bullet", "obstacle" or
+/// "trigger", plus that kind's fields.
+fn entity_dict(summary: &EntitySummary) -> Dictionary {
+    let mut dict = Dictionary::new();
+    dict.set("id", summary.id() as i64);
+    match summary {
+        EntitySummary::Tank {
+            team_id,
+            position,
+            angle,
+            turret_angle,
+            health,
+            alive,
+            ..
+        } => {
+            dict.set("kind", "tank");
+            dict.set("team_id", *team_id as i64);
+            dict.set("position", from_vec2(*position));
+            dict.set("angle", angle.radians().to_f64());
+            dict.set("turret_angle", turret_angle.radians().to_f64());
+            dict.set("health", *health as i64);
+            dict.set("alive", *alive);
+        }
+        EntitySummary::Bullet {
+            weapon_id,
+            position,
+            velocity,
+            ..
+        } => {
+            dict.set("kind", "bullet");
+            dict.set("weapon_id", *weapon_id as i64);
+            dict.set("position", from_vec2(*position));
+            dict.set("velocity", from_vec2(*velocity));
+        }
+        EntitySummary::Obstacle { aabb, .. } => {
+            dict.set("kind", "obstacle");
+            dict.set("rect", to_rect2(aabb));
+        }
+        EntitySummary::Trigger { bounds, .. } => {
+            dict.set("kind", "trigger");
+            dict.set("rect", to_rect2(bounds));
+        }
+    }
+    dict
+}
+
+fn to_rect2(aabb: &AABB) -> Rect2 {
+    let min = from_vec2(aabb.min);
+    Rect2::new(min, from_vec2(aabb.max) - min)
+}
+
+pub(crate) fn to_aabb(rect: Rect2) -> AABB {
+    AABB::new(to_vec2(rect.position), to_vec2(rect.end()))
+}
+
+/// Packs a frame of transforms as `{ time, tanks, bullets }`, each tank a dictionary of `id`,
+/// `team_id`, `position`, `angle`, `turret_angle` and `alive`, and each bullet of `id` and
+/// `position`.
+fn transform_dict(frame: &TransformFrame) -> Dictionary {
+    let tanks: Array<Dictionary> = frame
+        .tanks
+        .iter()
+        .map(|tank| {
+            let mut dict = Dictionary::new();
+            dict.set("id", tank.id as i64);
+            dict.set("team_id", tank.team_id as i64);
+            dict.set("position", from_vec2(tank.position));
+            dict.set("angle", tank.angle.radians().to_f64());
+            dict.set("turret_angle", tank.turret_angle.radians().to_f64());
+            dict.set("alive", tank.alive);
+            dict
+        })
+        .collect();
+    let bullets: Array<Dictionary> = frame
+        .bullets
+        .iter()
+        .map(|bullet| {
+            let mut dict = Dictionary::new();
+            dict.set("id", bullet.id as i64);
+            dict.set("position", from_vec2(bullet.position));
+            dict
+        })
+        .collect();
+    let mut dict = Dictionary::new();
+    dict.set("time", frame.time);
+    dict.set("tanks", tanks);
+    dict.set("bullets", bullets);
+    dict
+}
+
+struct SimExtension;
+
+#[gdextension]
+unsafe impl ExtensionLibrary for SimExtension {}
+
+/// Drives a match from the scene tree.
+#[derive(GodotClass)]
+#[class(base=Node)]
+pub struct Simulation {
+    /// Ticks per second for matches started with `start_match`.
+    #[export]
+    tick_rate: f64,
+    /// Arena for matches started with `start_match`. The configured arena if unset.
+    #[export]
+    arena_layout: Option<Gd<ArenaConfigResource>>,
+    #[export]
+    seed: i64,
+    /// Teams the bots are dealt into, in turn.
+    #[export]
+    team_count: i64,
+    /// One tank per entry, spawned by `start_match`.
+    #[export]
+    bots: Array<Gd<BotProgramResource>>,
+    /// Whether to call `start_match` and start running when the node enters the tree.
+    #[export]
+    start_on_ready: bool,
+    /// Shared with the worker thread while the node is threaded.
+    engine: Arc<Mutex<SimEngine>>,
+    /// Steps the match off the main thread, when the node is threaded.
+    worker: Option<SimWorker>,
+    /// The latest tick the worker finished, with every event since the one before.
+    frame: Option<TelemetryFrame>,
+    /// The shooting drill being practised, if any.
+    range: Option<ShootingRange>,
+    /// Setup used for each new match.
+    config: ValidatedConfig,
+    time: TimeControl,
+    /// Why the last call that failed did, for `get_last_error`.
+    last_error: Option<SimError>,
+    base: Base<Node>,
+}
+
+#[godot_api]
+impl INode for Simulation {
+    fn init(base: Base<Node>) -> Self {
+        // start paused, so scenes that call `step` themselves keep full control
+        let mut time = TimeControl::default();
+        time.pause();
+        Simulation {
+            tick_rate: 60.0,
+            arena_layout: None,
+            seed: 0,
+            team_count: 2,
+            bots: Array::new(),
+            start_on_ready: false,
+            engine: Arc::new(Mutex::new(SimEngine::new(SimState::new(0)))),
+            worker: None,
+            frame: None,
+            range: None,
+            config: ValidatedConfig::default(),
+            time,
+            last_error: None,
+            base,
+        }
+    }
+
+    fn ready(&mut self) {
+        if self.start_on_ready && self.start_match() {
+            self.time.resume();
+        }
+    }
+
+    fn process(&mut self, delta: f64) {
+        let ticks = self.time.frame(delta);
+        let Some(worker) = &self.worker else {
+            for _ in 0..ticks {
+                self.engine().step();
+            }
+            return;
+        };
+        // ticks still queued count towards this frame's, so a worker that can't keep up slows
+        // the match down rather than falling ever further behind
+        worker.run(u64::from(ticks).saturating_sub(worker.pending()));
+        if let Some(frame) = worker.latest() {
+            self.frame = Some(frame);
+        }
+    }
+}
+
+impl Simulation {
+    /// Locks the engine, waiting for the worker to finish the tick in progress if threaded.
+    fn engine(&self) -> MutexGuard<'_, SimEngine> {
+        self.engine.lock().expect("simulation thread panicked")
+    }
+
+    /// Writes a warning to the match log, and to Godot's output.
+    fn warn(&mut self, subsystem: Subsystem, message: String) {
+        godot_warn!("{message}");
+        let mut engine = self.engine();
+        let tick = engine.state().time;
+        engine
+            .log_mut()
+            .record(tick, Severity::Warn, subsystem, message);
+    }
+
+    fn use_builtin(&mut self, tank_id: u32, name: String) -> bool {
+        let Some(bot) = bots::builtin(&name) else {
+            return self.fail(Subsystem::Host, BotLoadError::UnknownBot(name).into());
+        };
+        let loaded = self.engine().set_bot(tank_id, bot);
+        match loaded {
+            Ok(()) => true,
+            Err(error) => self.fail(Subsystem::Host, error),
+        }
+    }
+
+    /// Warns about an error and keeps it for `get_last_error`. Returns `false`, for calls that
+    /// report failure that way.
+    fn fail(&mut self, subsystem: Subsystem, error: SimError) -> bool {
+        self.warn(subsystem, error.to_string());
+        self.last_error = Some(error);
+        false
+    }
+}
+
+/// Describes an error as `{ kind, message }`, plus whichever of `tank_id`, `trigger_id`, `fault`,
+/// `tick`, `expected`, `actual` and `problems` apply to its kind.
+fn error_dict(error: &SimError) -> Dictionary {
+    let mut dict = Dictionary::new();
+    dict.set("kind", error.kind());
+    dict.set("message", error.to_string().as_str());
+    match error {
+        SimError::Config(errors) => {
+            let problems: PackedStringArray = errors
+                .iter()
+                .map(|error| GString::from(error.to_string().as_str()))
+                .collect();
+            dict.set("problems", problems);
+        }
+        SimError::BotLoad(BotLoadError::UnknownTank(tank_id)) => {
+            dict.set("tank_id", *tank_id as i64);
+        }
+        SimError::NotAnAgent(tank_id) => {
+            dict.set("tank_id", *tank_id as i64);
+        }
+        SimError::UnknownTrigger(trigger_id) => {
+            dict.set("trigger_id", *trigger_id as i64);
+        }
+        SimError::TickUnavailable(tick) => {
+            dict.set("tick", *tick as i64);
+        }
+        SimError::VmFault { tank_id, fault } => {
+            dict.set("tank_id", *tank_id as i64);
+            dict.set("fault", format!("{fault:?}").as_str());
+        }
+        SimError::DesyncDetected {
+            tick,
+            expected,
+            actual,
+        } => {
+            dict.set("tick", *tick as i64);
+            dict.set("expected", *expected as i64);
+            dict.set("actual", *actual as i64);
+        }
+        SimError::BotLoad(_)
+        | SimError::Spawn(_)
+        | SimError::Console(_)
+        | SimError::Serialization(_) => {}
+    }
+    dict
+}
+
+#[godot_api]
+impl Simulation {
+    /// Replaces the setup used by `reset` and `reset_with_mode` with a JSON [`SimConfig`], and
+    /// switches to its tick rate.
+    ///
+    /// Returns every problem found with the config; if there are any, nothing changes.
+    #[func]
+    fn configure(&mut self, config_json: GString) -> PackedStringArray {
+        match SimConfig::from_json(&config_json.to_string()) {
+            Ok(config) => {
+                self.time.set_rate(config.get().tick_rate);
+                self.config = config;
+                PackedStringArray::new()
+            }
+            Err(errors) => errors
+                .iter()
+                .map(|error| GString::from(error.to_string().as_str()))
+                .collect(),
+        }
+    }
+
+    /// Replaces the arena used by `reset` and `reset_with_mode` with an
+    /// [`ArenaConfigResource`], keeping the rest of the setup.
+    ///
+    /// Returns every problem found with the resulting config; if there are any, nothing changes.
+    #[func]
+    fn set_arena(&mut self, arena: Gd<ArenaConfigResource>) -> PackedStringArray {
+        let mut config = self.config.get().clone();
+        config.arena = arena.bind().arena();
+        match config.validate() {
+            Ok(config) => {
+                self.config = config;
+                PackedStringArray::new()
+            }
+            Err(errors) => errors
+                .iter()
+                .map(|error| GString::from(error.to_string().as_str()))
+                .collect(),
+        }
+    }
+
+    /// Starts a fresh, empty match with the given seed.
+    #[func]
+    fn reset(&mut self, seed: i64) {
+        let state = SimState::with_arena(seed as u64, &self.config.get().arena);
+        *self.engine() = SimEngine::from_config(state, &self.config);
+    }
+
+    /// Starts a fresh, empty match playing an objective mode, given as a JSON [`GameMode`].
+    ///
+    /// Returns `false` and leaves the current match alone if the mode couldn't be parsed.
+    #[func]
+    fn reset_with_mode(&mut self, seed: i64, mode_json: GString) -> bool {
+        let mode: GameMode = match serde_json::from_str(&mode_json.to_string()) {
+            Ok(mode) => mode,
+            Err(error) => {
+                self.warn(Subsystem::Config, format!("invalid game mode: {error}"));
+                return false;
+            }
+        };
+        let mut config = self.config.get().clone();
+        config.rules.mode = mode;
+        let config = match config.validate() {
+            Ok(config) => config,
+            Err(errors) => {
+                for error in errors {
+                    self.warn(Subsystem::Config, error.to_string());
+                }
+                return false;
+            }
+        };
+        let state = SimState::with_arena(seed as u64, &config.get().arena);
+        *self.engine() = SimEngine::from_config(state, &config);
+        true
+    }
+
+    /// Starts a fresh match set up from the exported properties: the configured setup with
+    /// `tick_rate` and `arena_layout` swapped in, seeded with `seed`, and a tank for each of
+    /// `bots`. Bots are dealt into `team_count` teams in turn and start on the arena's spawn
+    /// points, or on fairly placed ones if it doesn't have enough, facing the centre.
+    ///
+    /// Returns `false` if the setup was rejected or a bot couldn't be spawned or loaded, with
+    /// the reason in `get_last_error`, or if there was no room for the tanks.
+    #[func]
+    fn start_match(&mut self) -> bool {
+        if self.team_count < 1 {
+            self.warn(
+                Subsystem::Host,
+                "a match needs at least one team".to_string(),
+            );
+            return false;
+        }
+        let mut config = self.config.get().clone();
+        config.tick_rate = TickRate::PerSecond(self.tick_rate);
+        if let Some(arena) = &self.arena_layout {
+            config.arena = arena.bind().arena();
+        }
+        let config = match config.validate() {
+            Ok(config) => config,
+            Err(errors) => return self.fail(Subsystem::Config, errors.into()),
+        };
+        let bots: Vec<Gd<BotProgramResource>> = self.bots.iter_shared().collect();
+        let arena = &config.get().arena;
+        let positions = if arena.spawn_points.len() >= bots.len() {
+            arena.spawn_points[..bots.len()].to_vec()
+        } else if let Some(positions) = spawns::place(config.get(), bots.len() as u32) {
+            positions
+        } else {
+            let message = format!("no room to spawn {} tanks", bots.len());
+            self.warn(Subsystem::Host, message);
+            return false;
+        };
+        let half = 2.0.to_scalar();
+        let center = Vec2::new(arena.width / half, arena.height / half);
+
+        self.time.set_rate(config.get().tick_rate);
+        self.config = config;
+        self.reset(self.seed);
+        for (index, (bot, position)) in bots.into_iter().zip(positions).enumerate() {
+            let spawn = TankSpawn {
+                team_id: (index as i64 % self.team_count) as u32,
+                loadout: bot.bind().loadout(),
+                position,
+                angle: Angle::of(center - position),
+            };
+            let spawned = self.engine().spawn_tank(spawn);
+            let tank_id = match spawned {
+                Ok(tank_id) => tank_id,
+                Err(error) => return self.fail(Subsystem::Host, error),
+            };
+            if !self.load_bot(tank_id as i64, bot) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Spawns a tank and returns its ID, or -1 if the loadout was rejected.
+    #[func]
+    fn spawn_tank(
+        &mut self,
+        team_id: i64,
+        spec_id: i64,
+        weapons: PackedInt32Array,
+        position: Vector2,
+        angle: f64,
+    ) -> i64 {
+        let spawn = TankSpawn {
+            team_id: team_id as u32,
+            loadout: Loadout {
+                spec_id: spec_id as u32,
+                weapons: weapons.as_slice().iter().map(|id| *id as u32).collect(),
+            },
+            position: to_vec2(position),
+            angle: Angle::new(angle.to_scalar()),
+        };
+        let spawned = self.engine().spawn_tank(spawn);
+        match spawned {
+            Ok(id) => id as i64,
+            Err(error) => {
+                self.warn(Subsystem::Engine, error.to_string());
+                -1
+            }
+        }
+    }
+
+    /// Loads a bytecode program to control a tank. Returns `false` if the tank doesn't exist.
+    #[func]
+    fn load_program(&mut self, tank_id: i64, code: PackedByteArray) -> bool {
+        let loaded = self
+            .engine()
+            .load_program(tank_id as u32, code.as_slice().to_vec());
+        match loaded {
+            Ok(()) => true,
+            Err(error) => self.fail(Subsystem::Vm, error),
+        }
+    }
+
+    /// Maps constant data into every program on a team, replacing its previous ROM; an empty
+    /// array unmaps it. Returns `false` if it's over the team ROM limit.
+    #[func]
+    fn load_team_rom(&mut self, team_id: i64, words: PackedInt32Array) -> bool {
+        let rom = words.as_slice().iter().map(|word| *word as u32).collect();
+        let loaded = self.engine().load_team_rom(team_id as u32, rom);
+        match loaded {
+            Ok(()) => true,
+            Err(error) => self.fail(Subsystem::Vm, error),
+        }
+    }
+
+    /// Returns the fault that stopped a tank's program as an error dictionary (see
+    /// `get_last_error`), or an empty one if it's running fine.
+    #[func]
+    fn get_program_fault(&self, tank_id: i64) -> Dictionary {
+        match self.engine().check_program(tank_id as u32) {
+            Ok(()) => Dictionary::new(),
+            Err(error) => error_dict(&error),
+        }
+    }
+
+    /// Checks the match against a checksum of the same tick from a lockstep peer or a
+    /// recording. Returns `false`, with the details in `get_last_error`, if they've diverged.
+    #[func]
+    fn verify_checksum(&mut self, expected: i64) -> bool {
+        let verified = self.engine().verify_checksum(expected as u64);
+        match verified {
+            Ok(()) => true,
+            Err(error) => self.fail(Subsystem::Host, error),
+        }
+    }
+
+    /// Returns why the last call that failed did, as `{ kind, message }` plus details for the
+    /// kind. `kind` is one of `config`, `bot_load`, `spawn`, `vm_fault`, `serialization` or
+    /// `desync`.
+    /// Empty if nothing has failed yet.
+    #[func]
+    fn get_last_error(&self) -> Dictionary {
+        self.last_error
+            .as_ref()
+            .map_or_else(Dictionary::new, error_dict)
+    }
+
+    /// Swaps a tank's program at the next tick without restarting the match, keeping its RAM
+    /// if `keep_memory` is set. Returns `false` if the tank doesn't exist.
+    #[func]
+    fn reload_program(&mut self, tank_id: i64, code: PackedByteArray, keep_memory: bool) -> bool {
+        let policy = if keep_memory {
+            ReloadPolicy::KeepMemory
+        } else {
+            ReloadPolicy::Reset
+        };
+        let reloaded =
+            self.engine()
+                .reload_program(tank_id as u32, code.as_slice().to_vec(), policy);
+        match reloaded {
+            Ok(()) => true,
+            Err(error) => self.fail(Subsystem::Vm, error),
+        }
+    }
+
+    /// Queues a JSON [`Command`] to apply at the start of the next tick. Returns `false` if it
+    /// couldn't be parsed.
+    #[func]
+    fn queue_command(&mut self, command_json: GString) -> bool {
+        match serde_json::from_str::<Command>(&command_json.to_string()) {
+            Ok(command) => {
+                self.engine().queue_command(command);
+                true
+            }
+            Err(error) => {
+                self.warn(Subsystem::Commands, format!("invalid command: {error}"));
+                false
+            }
+        }
+    }
+
+    /// Hands a tank over to a built-in bot (`sitting_duck`, `circler`, `shuttle` or `tracker`).
+    ///
+    /// Returns `false` if the tank or the bot doesn't exist.
+    #[func]
+    fn set_builtin_bot(&mut self, tank_id: i64, name: GString) -> bool {
+        self.use_builtin(tank_id as u32, name.to_string())
+    }
+
+    /// Hands a tank over to the controller a [`BotProgramResource`] describes. Returns `false`
+    /// if the tank or a named built-in bot doesn't exist.
+    #[func]
+    fn load_bot(&mut self, tank_id: i64, program: Gd<BotProgramResource>) -> bool {
+        let loaded = match program.bind().program() {
+            BotProgram::Builtin(name) => return self.use_builtin(tank_id as u32, name),
+            BotProgram::Code(code) => self.engine().load_program(tank_id as u32, code),
+        };
+        match loaded {
+            Ok(()) => true,
+            Err(error) => self.fail(Subsystem::Vm, error),
+        }
+    }
+
+    /// Hands a tank over to a human driving it through `set_manual_input`. Returns `false` if
+    /// the tank doesn't exist.
+    #[func]
+    fn take_manual_control(&mut self, tank_id: i64) -> bool {
+        let taken = self.engine().take_manual_control(tank_id as u32);
+        match taken {
+            Ok(()) => true,
+            Err(error) => self.fail(Subsystem::Host, error),
+        }
+    }
+
+    /// Queues a human's input for a manually controlled tank, typically read from Godot input
+    /// every frame: track commands in `[-1, 1]`, a world-space turret aim in radians, and the
+    /// weapon slot to fire, or -1 to hold fire. It holds until the next input and goes through
+    /// the command queue, so replays record it.
+    #[func]
+    fn set_manual_input(&mut self, tank_id: i64, left: f64, right: f64, aim: f64, fire: i64) {
+        let input = BotCommand {
+            drive: DriveInput::tracks(left.to_scalar(), right.to_scalar()),
+            turret: TurretCommand::Absolute(Angle::new(aim.to_scalar())),
+            fire: u32::try_from(fire).ok(),
+        };
+        self.engine().queue_command(Command::SetManualInput {
+            tank_id: tank_id as u32,
+            input,
+        });
+    }
+
+    /// Takes channels of a tank's controls from whatever controls it, from the next tick
+    /// onwards, replacing any taken before: `drive` as a `Vector2` of left and right track
+    /// commands in `[-1, 1]`, `aim` as a world-space turret angle in radians, and `fire` as a
+    /// weapon slot, or -1 to hold fire. Channels left out stay with the controller, so an empty
+    /// dictionary hands them all back. Goes through the command queue, so replays record it.
+    #[func]
+    fn set_override(&mut self, tank_id: i64, channels: Dictionary) {
+        let channel = |key: &str| channels.get(key);
+        let channels = ControlOverride {
+            drive: channel("drive")
+                .and_then(|drive| drive.try_to::<Vector2>().ok())
+                .map(|drive| {
+                    DriveInput::tracks((drive.x as f64).to_scalar(), (drive.y as f64).to_scalar())
+                }),
+            turret: channel("aim")
+                .and_then(|aim| aim.try_to::<f64>().ok())
+                .map(|aim| TurretCommand::Absolute(Angle::new(aim.to_scalar()))),
+            fire: channel("fire")
+                .and_then(|fire| fire.try_to::<i64>().ok())
+                .map(|slot| u32::try_from(slot).ok()),
+        };
+        self.engine().queue_command(Command::SetOverride {
+            tank_id: tank_id as u32,
+            channels,
+        });
+    }
+
+    /// Hands a tank over to a trained network loaded from a JSON weights file. Returns `false`
+    /// if the file couldn't be read or checked, or the tank doesn't exist.
+    #[func]
+    fn load_network(&mut self, tank_id: i64, path: GString) -> bool {
+        let path = ProjectSettings::singleton()
+            .globalize_path(&path)
+            .to_string();
+        let loaded = std::fs::read_to_string(&path)
+            .map_err(|error| {
+                SimError::Serialization(format!("could not read network {path}: {error}"))
+            })
+            .and_then(|json| Ok(NetworkController::from_json(&json)?))
+            .and_then(|network| self.engine().set_network(tank_id as u32, network));
+        match loaded {
+            Ok(()) => true,
+            Err(error) => self.fail(Subsystem::Host, error),
+        }
+    }
+
+    /// Starts profiling a tank's program. `function_table` is a JSON list of
+    /// `{ name, start, end }` byte ranges, as emitted by the compiler. Returns `false` if the
+    /// table couldn't be parsed or the tank doesn't exist.
+    #[func]
+    fn enable_vm_profiling(&mut self, tank_id: i64, function_table: GString) -> bool {
+        let functions: Vec<FunctionSymbol> = match serde_json::from_str(&function_table.to_string())
+        {
+            Ok(functions) => functions,
+            Err(error) => {
+                self.warn(Subsystem::Vm, format!("invalid function table: {error}"));
+                return false;
+            }
+        };
+        let enabled = self.engine().enable_vm_profiling(tank_id as u32, functions);
+        match enabled {
+            Ok(()) => true,
+            Err(error) => self.fail(Subsystem::Vm, error),
+        }
+    }
+
+    /// Turns per-phase timing of each tick on or off, for a performance overlay.
+    #[func]
+    fn set_phase_timing(&mut self, enabled: bool) {
+        self.engine().set_phase_timing(enabled);
+    }
+
+    /// Returns where the last tick's wall-clock time went, in microseconds, as `{ vm,
+    /// integrate, broad_phase, narrow_phase, events, total }`. Empty unless timing is on.
+    #[func]
+    fn get_phase_timings(&self) -> Dictionary {
+        let mut dict = Dictionary::new();
+        if let Some(timings) = self.engine().last_tick_timings() {
+            dict.set("vm", timings.vm_micros as i64);
+            dict.set("integrate", timings.integrate_micros as i64);
+            dict.set("broad_phase", timings.broad_phase_micros as i64);
+            dict.set("narrow_phase", timings.narrow_phase_micros as i64);
+            dict.set("events", timings.events_micros as i64);
+            dict.set("total", timings.total_micros() as i64);
+        }
+        dict
+    }
+
+    /// Stops profiling a tank's program.
+    #[func]
+    fn disable_vm_profiling(&mut self, tank_id: i64) -> bool {
+        self.engine().disable_vm_profiling(tank_id as u32)
+    }
+
+    /// Advances the match by one tick.
+    #[func]
+    fn step(&mut self) {
+        self.engine().step();
+    }
+
+    /// Returns the events of the most recent tick as a JSON array, with the positions,
+    /// materials and strengths needed to pick sounds and particles.
+    #[func]
+    fn get_events_json(&self) -> GString {
+        GString::from(&serde_json::to_string(self.engine().events()).unwrap_or_default())
+    }
+
+    /// Returns the match log as a JSON array of `{ tick, severity, subsystem, message }`,
+    /// oldest first.
+    #[func]
+    fn get_log_json(&self) -> GString {
+        GString::from(&self.engine().log().to_json())
+    }
+
+    /// Keeps only log records at `severity` (`debug`, `info`, `warn` or `error`) or above from
+    /// now on. Returns `false` if the severity is unknown.
+    #[func]
+    fn set_log_level(&mut self, severity: GString) -> bool {
+        match severity.to_string().parse() {
+            Ok(severity) => {
+                self.engine().log_mut().set_min_severity(severity);
+                true
+            }
+            Err(()) => false,
+        }
+    }
+
+    /// Returns the current tick.
+    #[func]
+    fn get_tick(&self) -> i64 {
+        self.engine().state().time as i64
+    }
+
+    /// Returns the simulated seconds since the match began, at the configured tick rate.
+    #[func]
+    fn get_seconds(&self) -> f64 {
+        self.engine().seconds().to_f64()
+    }
+
+    /// Converts a number of ticks to simulated seconds, e.g. for countdowns and cooldown bars.
+    #[func]
+    fn ticks_to_seconds(&self, ticks: i64) -> f64 {
+        self.engine()
+            .clock()
+            .to_seconds(ticks.max(0) as u64)
+            .to_f64()
+    }
+
+    /// Converts simulated seconds to the nearest whole number of ticks.
+    #[func]
+    fn seconds_to_ticks(&self, seconds: f64) -> i64 {
+        self.engine().clock().to_ticks(seconds.to_scalar()) as i64
+    }
+
+    /// Starts advancing the match automatically every frame.
+    #[func]
+    fn resume(&mut self) {
+        self.time.resume();
+    }
+
+    /// Stops advancing the match automatically. `step` and `step_ticks` still work. When
+    /// threaded, ticks the worker hasn't started yet are dropped.
+    #[func]
+    fn pause(&mut self) {
+        self.time.pause();
+        if let Some(worker) = &self.worker {
+            worker.pause();
+        }
+    }
+
+    #[func]
+    fn is_paused(&self) -> bool {
+        self.time.is_paused()
+    }
+
+    /// Runs the given number of ticks on the next frame, even while paused.
+    #[func]
+    fn step_ticks(&mut self, ticks: i64) {
+        self.time.request_ticks(ticks.max(0) as u32);
+    }
+
+    /// Moves stepping the match onto a worker thread, so heavy battles don't stall rendering,
+    /// or back onto the main thread.
+    ///
+    /// Every other call still works while threaded, waiting at most for the tick in progress;
+    /// `get_frame` doesn't wait at all. Switching back waits for the tick in progress and drops
+    /// any still queued. Freeing the node shuts the worker down the same way.
+    #[func]
+    fn set_threaded(&mut self, enabled: bool) {
+        if !enabled {
+            if let Some(worker) = self.worker.take() {
+                worker.stop();
+            }
+            self.frame = None;
+        } else if self.worker.is_none() {
+            self.worker = Some(SimWorker::spawn(self.engine.clone()));
+        }
+    }
+
+    #[func]
+    fn is_threaded(&self) -> bool {
+        self.worker.is_some()
+    }
+
+    /// Returns the latest tick the worker has finished, as `{ tick, seconds, tanks, bullets,
+    /// events_json }`, without waiting on the tick in progress. Each tank is a dictionary of
+    /// `id`, `team_id`, `position`, `angle`, `turret_angle` and `health`, and each bullet of
+    /// `id` and `position`. `events_json` holds every event since the previous frame, in the
+    /// form `get_events_json` uses.
+    ///
+    /// Empty unless threaded and a tick has finished.
+    #[func]
+    fn get_frame(&self) -> Dictionary {
+        let Some(frame) = &self.frame else {
+            return Dictionary::new();
+        };
+        let tanks: Array<Dictionary> = frame
+            .tanks
+            .iter()
+            .map(|tank| {
+                let mut dict = Dictionary::new();
+                dict.set("id", tank.id as i64);
+                dict.set("team_id", tank.team_id as i64);
+                dict.set("position", from_vec2(tank.position));
+                dict.set("angle", tank.angle.radians().to_f64());
+                dict.set("turret_angle", tank.turret_angle.radians().to_f64());
+                dict.set("health", tank.health as i64);
+                dict
+            })
+            .collect();
+        let bullets: Array<Dictionary> = frame
+            .bullets
+            .iter()
+            .map(|bullet| {
+                let mut dict = Dictionary::new();
+                dict.set("id", bullet.id as i64);
+                dict.set("position", from_vec2(bullet.position));
+                dict
+            })
+            .collect();
+        let events = serde_json::to_string(&frame.events).unwrap_or_default();
+        let mut dict = Dictionary::new();
+        dict.set("tick", frame.tick as i64);
+        dict.set("seconds", frame.seconds.to_f64());
+        dict.set("tanks", tanks);
+        dict.set("bullets", bullets);
+        dict.set("events_json", events.as_str());
+        dict
+    }
+
+    /// Runs in real time at the given rate while resumed.
+    #[func]
+    fn set_ticks_per_second(&mut self, ticks_per_second: f64) {
+        self.time.set_rate(TickRate::PerSecond(ticks_per_second));
+    }
+
+    /// Runs a fixed number of ticks every frame while resumed, regardless of frame time.
+    #[func]
+    fn set_ticks_per_frame(&mut self, ticks_per_frame: i64) {
+        self.time
+            .set_rate(TickRate::PerFrame(ticks_per_frame.max(0) as u32));
+    }
+
+    /// Caps how many ticks real-time mode may run to catch up after a slow frame.
+    #[func]
+    fn set_max_ticks_per_frame(&mut self, max: i64) {
+        self.time.set_max_ticks_per_frame(max.max(1) as u32);
+    }
+
+    /// Starts recording a snapshot every `interval` ticks, keeping the most recent `capacity`.
+    #[func]
+    fn enable_history(&mut self, interval: i64, capacity: i64) {
+        self.engine()
+            .enable_history(interval.max(1) as u64, capacity.max(0) as usize);
+    }
+
+    /// Rewinds to an earlier tick and branches from there. The abandoned timeline is kept.
+    ///
+    /// Returns `false` if the tick is in the future or no longer in the history.
+    #[func]
+    fn rewind(&mut self, tick: i64) -> bool {
+        if tick < 0 {
+            self.warn(Subsystem::Engine, format!("can't rewind to tick {tick}"));
+            return false;
+        }
+        let rewound = self.engine().rewind(tick as u64);
+        match rewound {
+            Ok(()) => true,
+            Err(error) => self.fail(Subsystem::Engine, error),
+        }
+    }
+
+    /// Returns how many timelines have been abandoned by rewinding.
+    #[func]
+    fn get_timeline_count(&self) -> i64 {
+        self.engine().timelines().len() as i64
+    }
+
+    /// Returns the match statistics so far as a JSON string.
+    #[func]
+    fn get_stats_json(&self) -> GString {
+        GString::from(&self.engine().stats().to_json())
+    }
+
+    /// Returns a tank's running totals for scoreboards, or an empty dictionary if it has none.
+    #[func]
+    fn get_tank_stats(&self, tank_id: i64) -> Dictionary {
+        let mut dict = Dictionary::new();
+        let engine = self.engine();
+        let Some(stats) = engine.stats().tank(tank_id as u32) else {
+            return dict;
+        };
+        dict.set("team_id", stats.team_id as i64);
+        dict.set("shots_fired", stats.shots_fired as i64);
+        dict.set("hits", stats.hits as i64);
+        dict.set("accuracy", stats.accuracy.to_f64());
+        dict.set("damage_dealt", stats.damage_dealt as i64);
+        dict.set("damage_taken", stats.damage_taken as i64);
+        dict.set("kills", stats.kills as i64);
+        dict.set("distance_traveled", stats.distance_traveled.to_f64());
+        dict.set("ticks_alive", stats.ticks_alive as i64);
+        dict.set("average_vm_cycles", stats.average_vm_cycles.to_f64());
+        dict
+    }
+
+    /// Sets off an explosion on the next tick, e.g. for scripted events or debugging.
+    #[func]
+    fn explode(&mut self, center: Vector2, radius: f64, damage: i64, knockback: f64) {
+        let spec = ExplosionSpec {
+            radius: radius.to_scalar(),
+            damage: damage.max(0) as u32,
+            knockback: knockback.to_scalar(),
+        };
+        self.engine().explode(to_vec2(center), spec);
+    }
+
+    /// Allows or refuses cheat commands, such as those typed into `console`, for the rest of
+    /// the match.
+    #[func]
+    fn set_cheats(&mut self, enabled: bool) {
+        self.engine().set_cheats(enabled);
+    }
+
+    /// Runs a line of the development console, e.g. `teleport 3 200 150`, and returns what to
+    /// print. Cheats only work once allowed with `set_cheats`.
+    #[func]
+    fn console(&mut self, line: GString) -> GString {
+        match console::execute(&mut self.engine(), &line.to_string()) {
+            Ok(output) => GString::from(output.as_str()),
+            Err(error) => GString::from(error.to_string().as_str()),
+        }
+    }
+
+    /// Pushes a tank on the next tick. The change in velocity is `impulse` divided by its mass.
+    ///
+    /// Returns `false` if the tank doesn't exist.
+    #[func]
+    fn apply_impulse(&mut self, tank_id: i64, impulse: Vector2) -> bool {
+        let applied = self
+            .engine()
+            .apply_impulse(tank_id as u32, to_vec2(impulse));
+        match applied {
+            Ok(()) => true,
+            Err(error) => self.fail(Subsystem::Engine, error),
+        }
+    }
+
+    /// Turns on respawning: destroyed tanks come back after `delay_ticks` at whichever spawn
+    /// point is farthest from their enemies, as ghosts for `invulnerable_ticks`.
+    #[func]
+    fn enable_respawn(
+        &mut self,
+        delay_ticks: i64,
+        invulnerable_ticks: i64,
+        spawn_points: PackedVector2Array,
+    ) {
+        self.engine().set_respawn(Some(RespawnConfig {
+            delay_ticks: delay_ticks.max(0) as u32,
+            invulnerable_ticks: invulnerable_ticks.max(0) as u32,
+            spawn_points: spawn_points
+                .as_slice()
+                .iter()
+                .copied()
+                .map(to_vec2)
+                .collect(),
+        }));
+    }
+
+    #[func]
+    fn disable_respawn(&mut self) {
+        self.engine().set_respawn(None);
+    }
+
+    /// Loads a JSON [`Scenario`] to play out over the rest of the match, replacing any other.
+    /// Returns `false` and keeps the current one if it couldn't be loaded.
+    #[func]
+    fn load_scenario(&mut self, scenario_json: GString) -> bool {
+        match Scenario::from_json(&scenario_json.to_string()) {
+            Ok(scenario) => {
+                self.engine().set_scenario(Some(scenario));
+                true
+            }
+            Err(error) => {
+                self.warn(Subsystem::Config, error.to_string());
+                false
+            }
+        }
+    }
+
+    /// Loads a JSON [`Drill`] to practise under the current setup, forgetting any previous
+    /// drill's attempts. Nothing changes until `reset_drill`. Returns `false` if it couldn't be
+    /// parsed.
+    #[func]
+    fn load_drill(&mut self, drill_json: GString) -> bool {
+        match serde_json::from_str::<Drill>(&drill_json.to_string()) {
+            Ok(drill) => {
+                self.range = Some(ShootingRange::new(drill, self.config.clone()));
+                true
+            }
+            Err(error) => {
+                self.warn(Subsystem::Config, format!("invalid drill: {error}"));
+                false
+            }
+        }
+    }
+
+    /// Scores the attempt in progress, if any, and restarts the drill from the top. Returns the
+    /// trainee's tank, which has no controller yet, or -1 if there's no drill or it couldn't
+    /// be set up, with the reason in `get_last_error`.
+    #[func]
+    fn reset_drill(&mut self) -> i64 {
+        let Some(range) = self.range.as_mut() else {
+            self.warn(Subsystem::Host, "no drill loaded".to_string());
+            return -1;
+        };
+        let reset = range.reset(&mut self.engine.lock().expect("simulation thread panicked"));
+        match reset {
+            Ok(shooter) => shooter as i64,
+            Err(error) => {
+                self.fail(Subsystem::Host, error);
+                -1
+            }
+        }
+    }
+
+    /// Returns the attempt in progress so far, as packed by `attempt_dict`, plus `over`: whether
+    /// every drone or the trainee is down, or time is up. Empty if there's no drill.
+    #[func]
+    fn get_drill_attempt(&self) -> Dictionary {
+        let Some(range) = &self.range else {
+            return Dictionary::new();
+        };
+        let engine = self.engine();
+        let mut dict = attempt_dict(&range.attempt(&engine));
+        dict.set("over", range.is_over(&engine));
+        dict
+    }
+
+    /// Returns every finished attempt at the drill, oldest first, as packed by `attempt_dict`.
+    #[func]
+    fn get_drill_attempts(&self) -> Array<Dictionary> {
+        self.range
+            .iter()
+            .flat_map(|range| range.attempts())
+            .map(attempt_dict)
+            .collect()
+    }
+
+    /// Returns the ticks until a destroyed tank respawns, or -1 if it isn't waiting to.
+    #[func]
+    fn get_respawn_ticks(&self, tank_id: i64) -> i64 {
+        self.engine()
+            .state()
+            .tank(tank_id as u32)
+            .and_then(|tank| tank.respawn_in)
+            .map_or(-1, |ticks| ticks as i64)
+    }
+
+    /// Returns each team's objective score as `{ team_id: score }`.
+    #[func]
+    fn get_scores(&self) -> Dictionary {
+        let mut dict = Dictionary::new();
+        for (team_id, score) in &self.engine().mode().scores {
+            dict.set(*team_id as i64, *score as i64);
+        }
+        dict
+    }
+
+    /// Returns the safe zone as `{ center, radius }` for drawing, or an empty dictionary if the
+    /// match has none.
+    #[func]
+    fn get_zone(&self) -> Dictionary {
+        let mut dict = Dictionary::new();
+        if let Some(zone) = self.engine().zone() {
+            dict.set("center", from_vec2(zone.center));
+            dict.set("radius", zone.radius.to_f64());
+        }
+        dict
+    }
+
+    /// Checks the match state's invariants, returning a description of each one broken. Empty
+    /// if the state is sound.
+    #[func]
+    fn validate_state(&self) -> PackedStringArray {
+        match self.engine().validate() {
+            Ok(()) => PackedStringArray::new(),
+            Err(violations) => violations
+                .iter()
+                .map(|violation| GString::from(violation.to_string().as_str()))
+                .collect(),
+        }
+    }
+
+    /// Returns the team that completed the objective, or -1 while the match is undecided.
+    #[func]
+    fn get_winner(&self) -> i64 {
+        self.engine()
+            .mode()
+            .winner
+            .map_or(-1, |team_id| team_id as i64)
+    }
+
+    /// Returns the series as `{ round, wins }`, with `wins` keyed by team, or an empty dictionary
+    /// if the match isn't played in rounds.
+    #[func]
+    fn get_rounds(&self) -> Dictionary {
+        let mut dict = Dictionary::new();
+        if let Some(rounds) = &self.engine().mode().rounds {
+            let mut wins = Dictionary::new();
+            for (team_id, count) in &rounds.wins {
+                wins.set(*team_id as i64, *count as i64);
+            }
+            dict.set("round", rounds.round as i64);
+            dict.set("wins", wins);
+        }
+        dict
+    }
+
+    /// Streams a telemetry frame per tick to a file, as newline-delimited JSON or, if `binary`,
+    /// as length-prefixed bincode. Accepts `res://` and `user://` paths.
+    ///
+    /// Returns `false` if the file couldn't be created.
+    #[func]
+    fn start_telemetry(&mut self, path: GString, binary: bool) -> bool {
+        let path = ProjectSettings::singleton()
+            .globalize_path(&path)
+            .to_string();
+        let file = match File::create(&path) {
+            Ok(file) => BufWriter::new(file),
+            Err(error) => {
+                self.warn(
+                    Subsystem::Telemetry,
+                    format!("could not open telemetry log {path}: {error}"),
+                );
+                return false;
+            }
+        };
+        let sink: Box<dyn TelemetrySink> = if binary {
+            Box::new(BinarySink::new(file))
+        } else {
+            Box::new(JsonLinesSink::new(file))
+        };
+        self.engine().set_telemetry(Some(sink));
+        true
+    }
+
+    /// Stops streaming telemetry and flushes the log.
+    #[func]
+    fn stop_telemetry(&mut self) {
+        self.engine().set_telemetry(None);
+    }
+
+    /// Starts recording a replay, with a full keyframe every `keyframe_interval` ticks.
+    #[func]
+    fn start_replay(&mut self, keyframe_interval: i64) {
+        self.engine().start_replay(keyframe_interval.max(1) as u64);
+    }
+
+    /// Stops recording and writes the replay to a file, for `ReplayViewer`. Accepts `res://`
+    /// and `user://` paths.
+    ///
+    /// Returns `false` if nothing was being recorded or the file couldn't be written.
+    #[func]
+    fn save_replay(&mut self, path: GString) -> bool {
+        let Some(replay) = self.engine().take_replay() else {
+            return false;
+        };
+        let path = ProjectSettings::singleton()
+            .globalize_path(&path)
+            .to_string();
+        match replay
+            .to_bytes()
+            .and_then(|bytes| std::fs::write(&path, bytes))
+        {
+            Ok(()) => true,
+            Err(error) => {
+                let error =
+                    SimError::Serialization(format!("could not write replay {path}: {error}"));
+                self.fail(Subsystem::Replay, error)
+            }
+        }
+    }
+
+    /// Writes the whole match to a file: its config, state, controllers and any replay being
+    /// recorded. Accepts `res://` and `user://` paths.
+    ///
+    /// Returns `false` if a tank is run by a bot that can't be saved, or the file couldn't be
+    /// written.
+    #[func]
+    fn save_match(&mut self, path: GString) -> bool {
+        let path = ProjectSettings::singleton()
+            .globalize_path(&path)
+            .to_string();
+        let saved = self.engine().save().and_then(|save| save.to_bytes());
+        let bytes = match saved {
+            Ok(bytes) => bytes,
+            Err(error) => return self.fail(Subsystem::Save, error.into()),
+        };
+        match std::fs::write(&path, bytes) {
+            Ok(()) => true,
+            Err(error) => {
+                let error =
+                    SimError::Serialization(format!("could not write match {path}: {error}"));
+                self.fail(Subsystem::Save, error)
+            }
+        }
+    }
+
+    /// Resumes a match written by `save_match`, switching to its config and tick rate.
+    ///
+    /// Returns `false` and leaves the current match alone if the file couldn't be read.
+    #[func]
+    fn load_match(&mut self, path: GString) -> bool {
+        let path = ProjectSettings::singleton()
+            .globalize_path(&path)
+            .to_string();
+        let save = match std::fs::read(&path) {
+            Ok(bytes) => MatchSave::from_bytes(&bytes),
+            Err(error) => {
+                let error =
+                    SimError::Serialization(format!("could not read match {path}: {error}"));
+                return self.fail(Subsystem::Save, error);
+            }
+        };
+        let resumed = save.and_then(|save| {
+            let config = save.config.clone().validate().map_err(SaveError::Config)?;
+            Ok((SimEngine::from_save(save)?, config))
+        });
+        match resumed {
+            Ok((engine, config)) => {
+                self.time.set_rate(config.get().tick_rate);
+                *self.engine() = engine;
+                self.config = config;
+                true
+            }
+            Err(error) => self.fail(Subsystem::Save, error.into()),
+        }
+    }
+
+    /// Adds a rectangular trigger volume and returns its ID.
+    #[func]
+    fn add_trigger_box(&mut self, rect: Rect2) -> i64 {
+        let aabb = to_aabb(rect);
+        self.engine().add_trigger(TriggerShape::Box(aabb)) as i64
+    }
+
+    /// Adds a circular trigger volume and returns its ID.
+    #[func]
+    fn add_trigger_circle(&mut self, center: Vector2, radius: f64) -> i64 {
+        let shape = TriggerShape::Circle {
+            center: to_vec2(center),
+            radius: radius.to_scalar(),
+        };
+        self.engine().add_trigger(shape) as i64
+    }
+
+    #[func]
+    fn remove_trigger(&mut self, trigger_id: i64) -> bool {
+        let removed = self.engine().remove_trigger(trigger_id as u32);
+        match removed {
+            Ok(()) => true,
+            Err(error) => self.fail(Subsystem::Engine, error),
+        }
+    }
+
+    /// Returns the IDs of the tanks inside a trigger volume.
+    #[func]
+    fn get_trigger_occupants(&self, trigger_id: i64) -> PackedInt32Array {
+        match self.engine().trigger(trigger_id as u32) {
+            Some(trigger) => trigger.occupants.iter().map(|id| *id as i32).collect(),
+            None => PackedInt32Array::new(),
+        }
+    }
+
+    /// Returns the IDs of the entities with a tag, lowest first. Integers are read as number
+    /// tags, anything else as a name.
+    #[func]
+    fn get_tagged(&self, tag: GString) -> PackedInt32Array {
+        let Ok(tag) = tag.to_string().parse::<Tag>();
+        let tagged = self.engine().state().tags.tagged(&tag);
+        tagged.into_iter().map(|id| id as i32).collect()
+    }
+
+    /// Returns an entity's tags, written as `get_tagged` reads them.
+    #[func]
+    fn get_entity_tags(&self, entity_id: i64) -> PackedStringArray {
+        let engine = self.engine();
+        let tags = engine.state().tags.of(entity_id as u32);
+        tags.map(|tag| GString::from(tag.to_string().as_str()))
+            .collect()
+    }
+
+    /// Returns the IDs of enemy tanks the team can currently see.
+    #[func]
+    fn get_visible_enemies(&self, team_id: i64) -> PackedInt32Array {
+        let engine = self.engine();
+        let seen = engine.visibility().seen_by_team(team_id as u32);
+        seen.iter().map(|id| *id as i32).collect()
+    }
+
+    /// Returns the team's fog of war as `{ width, height, cell_size, cells }`, where `cells`
+    /// holds one byte per cell in row-major order (1 visible, 0 fogged).
+    #[func]
+    fn get_fog_of_war(&self, team_id: i64, cell_size: f64) -> Dictionary {
+        let mask = self
+            .engine()
+            .fog_of_war(team_id as u32, cell_size.to_scalar());
+        let mut dict = Dictionary::new();
+        dict.set("width", mask.width as i64);
+        dict.set("height", mask.height as i64);
+        dict.set("cell_size", cell_size);
+        dict.set("cells", PackedByteArray::from(mask.cells));
+        dict
+    }
+
+    /// Switches collection of a category of debug primitive (`"shapes"`, `"rays"`, `"contacts"`
+    /// or `"grid"`) on or off. Returns `false` if the category is unknown.
+    #[func]
+    fn set_debug_draw(&mut self, category: GString, enabled: bool) -> bool {
+        let Some(category) = DebugCategory::from_name(&category.to_string()) else {
+            self.warn(
+                Subsystem::Host,
+                format!("unknown debug draw category {category}"),
+            );
+            return false;
+        };
+        self.engine().set_debug_draw(category, enabled);
+        true
+    }
+
+    /// Returns the debug primitives collected last tick, packed for `_draw` or a MultiMesh:
+    ///
+    /// - `aabbs`: min x, min y, max x, max y per box
+    /// - `boxes`: centre x, centre y, angle, half width, half height per hull
+    /// - `rays`: from and to points, in pairs
+    /// - `contacts`: point and normal, in pairs
+    /// - `grid`: `{ columns, rows, cell_width, cell_height, counts, suggested_cell_size }`, if
+    ///   collected, the suggestion being zero unless the cell size is well off
+    #[func]
+    fn get_debug_draw(&self) -> Dictionary {
+        let engine = self.engine();
+        let debug = engine.debug_draw();
+        let aabbs: PackedFloat32Array = debug
+            .aabbs
+            .iter()
+            .flat_map(|aabb| [aabb.min.x, aabb.min.y, aabb.max.x, aabb.max.y])
+            .map(|value| value.to_f64() as f32)
+            .collect();
+        let boxes: PackedFloat32Array = debug
+            .boxes
+            .iter()
+            .flat_map(|hull| {
+                [
+                    hull.transform.position.x,
+                    hull.transform.position.y,
+                    hull.transform.rotation.angle().radians(),
+                    hull.half_extents.x,
+                    hull.half_extents.y,
+                ]
+            })
+            .map(|value| value.to_f64() as f32)
+            .collect();
+        let rays: PackedVector2Array = debug
+            .rays
+            .iter()
+            .flat_map(|(from, to)| [from_vec2(*from), from_vec2(*to)])
+            .collect();
+        let contacts: PackedVector2Array = debug
+            .contacts
+            .iter()
+            .flat_map(|contact| [from_vec2(contact.point), from_vec2(contact.normal)])
+            .collect();
+
+        let mut dict = Dictionary::new();
+        dict.set("aabbs", aabbs);
+        dict.set("boxes", boxes);
+        dict.set("rays", rays);
+        dict.set("contacts", contacts);
+        if let Some(grid) = &debug.grid {
+            let counts: PackedInt32Array = grid.counts.iter().map(|count| *count as i32).collect();
+            let mut heatmap = Dictionary::new();
+            heatmap.set("columns", grid.columns as i64);
+            heatmap.set("rows", grid.rows as i64);
+            heatmap.set("cell_width", grid.cell_width.to_f64());
+            heatmap.set("cell_height", grid.cell_height.to_f64());
+            heatmap.set("counts", counts);
+            heatmap.set(
+                "suggested_cell_size",
+                grid.suggested_cell_size.map_or(0.0, |size| size.to_f64()),
+            );
+            dict.set("grid", heatmap);
+        }
+        dict
+    }
+
+    /// Returns the waypoints a tank program would follow between two points, for debug
+    /// drawing. Empty if there is no path.
+    #[func]
+    fn get_nav_path(&self, from: Vector2, to: Vector2) -> PackedVector2Array {
+        let path = self
+            .engine()
+            .nav()
+            .find_path(to_vec2(from), to_vec2(to))
+            .unwrap_or_default();
+        path.into_iter().map(from_vec2).collect()
+    }
+
+    /// Returns the navigation grid as `{ width, height, cell_size, cells }`, where `cells`
+    /// holds one byte per cell in row-major order (1 blocked, 0 free).
+    #[func]
+    fn get_nav_grid(&self) -> Dictionary {
+        nav_grid_dict(self.engine().nav())
+    }
+
+    /// Returns the smallest rectangle holding every live tank, for a camera director to frame.
+    /// Empty, at the origin, once no tanks are left.
+    #[func]
+    fn get_bounding_box_of_action(&self) -> Rect2 {
+        match self.engine().bounding_box_of_action() {
+            Some(bounds) => to_rect2(&bounds),
+            None => Rect2::default(),
+        }
+    }
+
+    /// Returns a summary of any entity by ID, as packed by `entity_dict`. Empty if there's
+    /// no such entity.
+    #[func]
+    fn get_entity_by_id(&self, entity_id: i64) -> Dictionary {
+        match self.engine().entity_by_id(entity_id as u32) {
+            Some(summary) => entity_dict(&summary),
+            None => Dictionary::new(),
+        }
+    }
+
+    /// Returns summaries of the entities in a rectangle, in ID order.
+    #[func]
+    fn entities_in_aabb(&self, rect: Rect2) -> Array<Dictionary> {
+        let area = to_aabb(rect);
+        self.engine()
+            .entities_in_aabb(&area)
+            .iter()
+            .map(entity_dict)
+            .collect()
+    }
+
+    /// Returns how many tanks are in each collision grid cell, in row-major order, with
+    /// `get_grid_size` columns and rows. Cheap enough to call every tick, e.g. to fill a
+    /// texture for a shader to draw density from.
+    #[func]
+    fn get_grid_counts(&self) -> PackedInt32Array {
+        self.engine()
+            .broadphase_counts()
+            .into_iter()
+            .map(|count| count as i32)
+            .collect()
+    }
+
+    /// Returns the collision grid's columns and rows, the shape of `get_grid_counts`.
+    #[func]
+    fn get_grid_size(&self) -> Vector2i {
+        let (columns, rows) = self.engine().broadphase_grid_size();
+        Vector2i::new(columns as i32, rows as i32)
+    }
+
+    /// Returns how tanks are spread over the collision grid as `{ cells, occupied_cells,
+    /// objects, max_per_cell, mean_per_occupied_cell, cells_per_object, histogram,
+    /// pathological, suggested_cell_size }`. `histogram[n]` is the number of cells holding `n`
+    /// tanks, and `suggested_cell_size` is zero unless the configured size is well off.
+    #[func]
+    fn get_grid_occupancy(&self) -> Dictionary {
+        let grid = self.engine().broadphase_stats();
+        let suggested = grid.suggested_cell_size();
+        let stats = grid.occupancy;
+        let histogram: PackedInt32Array =
+            stats.histogram.iter().map(|count| *count as i32).collect();
+        let mut dict = Dictionary::new();
+        dict.set("cells", stats.cells as i64);
+        dict.set("occupied_cells", stats.occupied_cells as i64);
+        dict.set("objects", stats.objects as i64);
+        dict.set("max_per_cell", stats.max_per_cell as i64);
+        dict.set(
+            "mean_per_occupied_cell",
+            stats.mean_per_occupied_cell.to_f64(),
+        );
+        dict.set("cells_per_object", stats.cells_per_object.to_f64());
+        dict.set("histogram", histogram);
+        dict.set("pathological", stats.is_pathological());
+        dict.set(
+            "suggested_cell_size",
+            suggested.map_or(0.0, |size| size.to_f64()),
+        );
+        dict
+    }
+}
+
+/// Lets editor plugins inspect an arena config without running a match.
+///
+/// Runs in the editor. Call `validate_arena` first; the other methods work on the last config
+/// it managed to parse.
+#[derive(GodotClass)]
+#[class(tool, init, base=Node)]
+pub struct ArenaPreview {
+    config: Option<ArenaConfig>,
+    base: Base<Node>,
+}
+
+#[godot_api]
+impl ArenaPreview {
+    /// Parses a JSON [`ArenaConfig`] and returns a description of each problem found in it.
+    /// Empty if the arena is fine.
+    #[func]
+    fn validate_arena(&mut self, config: GString) -> PackedStringArray {
+        let config: ArenaConfig = match serde_json::from_str(&config.to_string()) {
+            Ok(config) => config,
+            Err(error) => {
+                return [GString::from(
+                    format!("invalid arena config: {error}").as_str(),
+                )]
+                .into_iter()
+                .collect();
+            }
+        };
+        let issues = config
+            .validate()
+            .iter()
+            .map(|issue| GString::from(issue.to_string().as_str()))
+            .collect();
+        self.config = Some(config);
+        issues
+    }
+
+    /// Returns the navigation grid a match on the arena would plan paths with, in the same form
+    /// as `Simulation.get_nav_grid`. Empty if no arena has been loaded or it has problems.
+    #[func]
+    fn compute_nav_grid_preview(&self) -> Dictionary {
+        let Some(config) = &self.config else {
+            return Dictionary::new();
+        };
+        let config = SimConfig {
+            arena: config.clone(),
+            ..SimConfig::default()
+        };
+        let Ok(config) = config.validate() else {
+            return Dictionary::new();
+        };
+        let engine = SimEngine::from_config(SimState::with_arena(0, &config.get().arena), &config);
+        nav_grid_dict(engine.nav())
+    }
+
+    /// Returns the arena's spawn points, in the order they were authored.
+    #[func]
+    fn list_spawn_points(&self) -> PackedVector2Array {
+        let Some(config) = &self.config else {
+            return PackedVector2Array::new();
+        };
+        config.spawn_points.iter().copied().map(from_vec2).collect()
+    }
+}
+
+/// Plays back a replay saved by `Simulation.save_replay`, e.g. for slow-motion highlights.
+#[derive(GodotClass)]
+#[class(init, base=Node)]
+pub struct ReplayViewer {
+    replay: Option<Replay>,
+    time: f64,
+    base: Base<Node>,
+}
+
+impl ReplayViewer {
+    fn player(&self) -> Option<ReplayPlayer<'_>> {
+        let mut player = ReplayPlayer::new(self.replay.as_ref()?);
+        player.seek(self.time);
+        Some(player)
+    }
+}
+
+#[godot_api]
+impl ReplayViewer {
+    /// Loads a replay and moves to its first tick. Returns `false` if it couldn't be read.
+    #[func]
+    fn load_replay(&mut self, path: GString) -> bool {
+        let path = ProjectSettings::singleton()
+            .globalize_path(&path)
+            .to_string();
+        match std::fs::read(&path).and_then(|bytes| Replay::from_bytes(&bytes)) {
+            Ok(replay) => {
+                self.time = replay.tick_range().map_or(0.0, |(first, _)| first as f64);
+                self.replay = Some(replay);
+                true
+            }
+            Err(error) => {
+                godot_warn!("could not read replay {path}: {error}");
+                false
+            }
+        }
+    }
+
+    /// Returns the current playback time, in ticks.
+    #[func]
+    fn get_time(&self) -> f64 {
+        self.time
+    }
+
+    /// Jumps to a moment, which may fall between ticks.
+    #[func]
+    fn seek(&mut self, time: f64) {
+        if let Some(mut player) = self.player() {
+            player.seek(time);
+            self.time = player.time();
+        }
+    }
+
+    /// Moves playback on by a number of ticks. Fractions give slow motion.
+    #[func]
+    fn advance(&mut self, ticks: f64) {
+        self.seek(self.time + ticks);
+    }
+
+    #[func]
+    fn is_finished(&self) -> bool {
+        self.player().is_none_or(|player| player.is_finished())
+    }
+
+    /// Returns what to draw at the current moment, as `{ time, tanks, bullets }`.
+    #[func]
+    fn get_transforms(&self) -> Dictionary {
+        match self.player() {
+            Some(player) => transform_dict(&player.transforms()),
+            None => Dictionary::new(),
+        }
+    }
+
+    /// Returns a picture per `1 / frames_per_tick` ticks from the current moment up to
+    /// `until`, in the same form as `get_transforms`, and moves playback to the last one.
+    #[func]
+    fn export_frames(&mut self, until: f64, frames_per_tick: i64) -> Array<Dictionary> {
+        let Some(mut player) = self.player() else {
+            return Array::new();
+        };
+        let frames = player.export(until, frames_per_tick.max(1) as u32);
+        self.time = player.time();
+        frames.iter().map(transform_dict).collect()
+    }
+}
@@ -0,0 +1,135 @@
+use crate::effects;
+use crate::events::SimEvent;
+use crate::physics::collision::AABB;
+use crate::state::Tank;
+use crate::util::math::{Scalar, Vec2};
+use crate::util::spatial::SpatialHashMap;
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+
+/// Target size of a cell in the grid used to find clouds near a line of sight.
+const OCCLUDER_CELL_SIZE: Scalar = dec64!(64);
+
+/// A smoke screen a projectile lays wherever it stops.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SmokeSpec {
+    pub radius: Scalar,
+    /// Ticks the cloud hangs in the air.
+    pub duration: u32,
+}
+
+/// A drifting cloud that radar can't see into, out of or through.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Obscurant {
+    pub id: u32,
+    pub center: Vec2,
+    pub radius: Scalar,
+    /// Ticks left before it dissipates.
+    pub remaining: u32,
+}
+
+/// Blows every cloud along with the wind, then dissipates those that have run out.
+pub fn update(obscurants: &mut Vec<Obscurant>, wind: Vec2, events: &mut Vec<SimEvent>) {
+    obscurants.retain_mut(|cloud| {
+        cloud.center = cloud.center + wind;
+        cloud.remaining = cloud.remaining.saturating_sub(1);
+        if cloud.remaining > 0 {
+            return true;
+        }
+        events.push(SimEvent::SmokeDissipated {
+            obscurant_id: cloud.id,
+        });
+        false
+    });
+}
+
+/// Everything blocking radar at one moment: drifting clouds and the smoke around tanks.
+pub struct Occluders {
+    clouds: Vec<(Vec2, Scalar)>,
+    index: SpatialHashMap,
+}
+
+impl Occluders {
+    pub fn new(width: Scalar, height: Scalar, tanks: &[Tank], obscurants: &[Obscurant]) -> Self {
+        let mut clouds = effects::smoke_clouds(tanks);
+        clouds.extend(obscurants.iter().map(|cloud| (cloud.center, cloud.radius)));
+        let mut index = SpatialHashMap::with_cell_size(width, height, OCCLUDER_CELL_SIZE);
+        for (slot, (center, radius)) in clouds.iter().enumerate() {
+            let reach = Vec2::new(*radius, *radius);
+            index.insert(
+                slot as u32,
+                &AABB::new(center.sub(&reach), center.add(&reach)),
+            );
+        }
+        Occluders { clouds, index }
+    }
+
+    /// Nothing blocks radar.
+    pub fn none() -> Self {
+        Occluders {
+            clouds: Vec::new(),
+            index: SpatialHashMap::new(dec64!(1), dec64!(1), 1, 1),
+        }
+    }
+
+    /// Returns whether the line between two points passes through any cloud.
+    pub fn blocks(&self, from: Vec2, to: Vec2) -> bool {
+        if self.clouds.is_empty() {
+            return false;
+        }
+        let bounds = AABB::new(
+            Vec2::new(from.x.min(to.x), from.y.min(to.y)),
+            Vec2::new(from.x.max(to.x), from.y.max(to.y)),
+        );
+        let nearby: Vec<(Vec2, Scalar)> = self
+            .index
+            .query(&bounds)
+            .into_iter()
+            .map(|slot| self.clouds[slot as usize])
+            .collect();
+        effects::obscured(&nearby, from, to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cloud(id: u32, x: f64, remaining: u32) -> Obscurant {
+        Obscurant {
+            id,
+            center: Vec2::new_from_f64(x, 100.0),
+            radius: dec64!(20),
+            remaining,
+        }
+    }
+
+    #[test]
+    fn update_should_drift_with_wind_and_dissipate() {
+        // Arrange
+        let mut clouds = vec![cloud(1, 100.0, 1), cloud(2, 200.0, 5)];
+        let mut events = Vec::new();
+
+        // Act
+        update(&mut clouds, Vec2::new_from_f64(1.5, -0.5), &mut events);
+
+        // Assert
+        assert_eq!(clouds.len(), 1);
+        assert_eq!(clouds[0].center, Vec2::new_from_f64(201.5, 99.5));
+        assert_eq!(clouds[0].remaining, 4);
+        assert_eq!(events, vec![SimEvent::SmokeDissipated { obscurant_id: 1 }]);
+    }
+
+    #[test]
+    fn blocks_should_only_occlude_lines_through_clouds() {
+        // Arrange
+        let occluders = Occluders::new(dec64!(500), dec64!(500), &[], &[cloud(1, 250.0, 5)]);
+        let at = |x, y| Vec2::new_from_f64(x, y);
+
+        // Act & Assert
+        assert!(occluders.blocks(at(50.0, 100.0), at(450.0, 100.0)));
+        assert!(occluders.blocks(at(250.0, 300.0), at(250.0, 50.0)));
+        assert!(!occluders.blocks(at(50.0, 150.0), at(450.0, 150.0)));
+        assert!(!Occluders::none().blocks(at(50.0, 100.0), at(450.0, 100.0)));
+    }
+}
@@ -0,0 +1,567 @@
+use crate::debug_draw::GridHeatmap;
+use crate::jobs::ScopedSplitter;
+use crate::physics::collision::AABB;
+use crate::spec::{SpecTable, TankSpec};
+use crate::state::Tank;
+use crate::util::math::{Angle, Scalar, Vec2};
+use crate::util::spatial::{GridStats, OccupancyStats, SparseSpatialHash, SpatialHashMap};
+use fastnum::dec64;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Cells beyond which tanks are kept in a sparse grid, which only stores the cells holding
+/// them, rather than in one laid over the whole arena.
+pub const MAX_DENSE_CELLS: u64 = 1 << 18;
+
+/// Returns how far a hull can reach from the tank's centre along either axis, at any heading.
+pub fn reach(spec: &TankSpec) -> Scalar {
+    (spec.hull_size.x + spec.hull_size.y) / dec64!(2)
+}
+
+/// Returns a box around everything a tank's hull could cover at any heading.
+///
+/// Loose, but avoids a square root per tank per tick.
+pub fn tank_bounds(tank: &Tank, specs: &SpecTable) -> Option<AABB> {
+    let reach = reach(specs.tank(tank.loadout.spec_id)?);
+    let reach = Vec2::new(reach, reach);
+    Some(AABB::new(
+        tank.position.sub(&reach),
+        tank.position.add(&reach),
+    ))
+}
+
+/// The cells tanks are kept in: a grid over the whole arena, or only the occupied cells of one
+/// too large to lay out.
+enum TankGrid {
+    Dense(SpatialHashMap),
+    Sparse {
+        grid: SparseSpatialHash,
+        /// The arena's size, for [`TankBroadphase::stats`].
+        width: Scalar,
+        height: Scalar,
+    },
+}
+
+impl TankGrid {
+    fn insert(&mut self, slot: u32, bounds: &AABB) {
+        match self {
+            TankGrid::Dense(grid) => grid.insert(slot, bounds),
+            TankGrid::Sparse { grid, .. } => grid.insert(slot, bounds),
+        }
+    }
+
+    fn remove(&mut self, slot: u32, bounds: &AABB) {
+        match self {
+            TankGrid::Dense(grid) => grid.remove(slot, bounds),
+            TankGrid::Sparse { grid, .. } => grid.remove(slot, bounds),
+        }
+    }
+
+    fn update(&mut self, slot: u32, old: &AABB, new: &AABB) {
+        match self {
+            TankGrid::Dense(grid) => grid.update(slot, old, new),
+            TankGrid::Sparse { grid, .. } => grid.update(slot, old, new),
+        };
+    }
+
+    fn query_circle(&self, center: Vec2, radius: Scalar) -> Vec<u32> {
+        match self {
+            TankGrid::Dense(grid) => grid.query_circle(center, radius).into_iter().collect(),
+            TankGrid::Sparse { grid, .. } => {
+                grid.query_circle(center, radius).into_iter().collect()
+            }
+        }
+    }
+
+    fn query_segment(&self, start: Vec2, end: Vec2) -> Vec<u32> {
+        match self {
+            TankGrid::Dense(grid) => grid.query_segment(start, end),
+            TankGrid::Sparse { grid, .. } => grid.query_segment(start, end).into_iter().collect(),
+        }
+    }
+
+    fn query_arc(&self, center: Vec2, radius: Scalar, facing: Angle, arc: Scalar) -> Vec<u32> {
+        match self {
+            TankGrid::Dense(grid) => grid
+                .query_arc(center, radius, facing, arc)
+                .into_iter()
+                .collect(),
+            // the sector lies inside its circle, and callers check the arc themselves
+            TankGrid::Sparse { grid, .. } => {
+                grid.query_circle(center, radius).into_iter().collect()
+            }
+        }
+    }
+
+    fn k_nearest(
+        &self,
+        center: Vec2,
+        k: usize,
+        distance_squared: impl FnMut(u32) -> Option<Scalar>,
+    ) -> Vec<u32> {
+        match self {
+            TankGrid::Dense(grid) => grid.k_nearest(center, k, distance_squared),
+            TankGrid::Sparse { grid, .. } => grid.k_nearest(center, k, distance_squared),
+        }
+    }
+
+    fn occupancy(&self) -> OccupancyStats {
+        match self {
+            TankGrid::Dense(grid) => grid.occupancy(),
+            TankGrid::Sparse { grid, .. } => grid.occupancy(),
+        }
+    }
+
+    fn stats(&self) -> GridStats {
+        match self {
+            TankGrid::Dense(grid) => grid.stats(),
+            TankGrid::Sparse {
+                grid,
+                width,
+                height,
+            } => GridStats {
+                map_width: *width,
+                map_height: *height,
+                cell_width: grid.cell_size(),
+                cell_height: grid.cell_size(),
+                occupancy: grid.occupancy(),
+            },
+        }
+    }
+}
+
+/// Finds tanks whose hulls might be touching, for the collision step.
+///
+/// The grid persists across ticks: sleeping tanks are left where they are, and awake tanks are
+/// only moved between cells when their bounds cross a cell boundary, so the cost tracks how
+/// many tanks are moving. Tanks are keyed by their slot in the state's tank list.
+pub struct TankBroadphase {
+    grid: TankGrid,
+    /// ID of the tank in each stored slot, and the bounds it was stored with.
+    entries: BTreeMap<usize, (u32, AABB)>,
+}
+
+impl TankBroadphase {
+    /// Creates a broadphase for an arena of the given size, which only stores occupied cells
+    /// when a full grid would take more than [`MAX_DENSE_CELLS`].
+    pub fn new(width: Scalar, height: Scalar, cell_size: Scalar) -> Self {
+        let cells = |extent: Scalar| (extent / cell_size).ceil().to_u64().ok();
+        let dense = match (cells(width), cells(height)) {
+            (Some(columns), Some(rows)) => columns.saturating_mul(rows) <= MAX_DENSE_CELLS,
+            _ => false,
+        };
+        let grid = if dense {
+            TankGrid::Dense(SpatialHashMap::with_cell_size(width, height, cell_size))
+        } else {
+            TankGrid::Sparse {
+                grid: SparseSpatialHash::new(cell_size),
+                width,
+                height,
+            }
+        };
+        TankBroadphase {
+            grid,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Returns whether only occupied cells are stored.
+    pub fn is_sparse(&self) -> bool {
+        matches!(self.grid, TankGrid::Sparse { .. })
+    }
+
+    /// Brings the grid in line with the tanks, touching only those that moved or changed state.
+    fn sync(&mut self, tanks: &[Tank], specs: &SpecTable) {
+        // slots past the end belong to tanks that are gone
+        let stale: Vec<usize> = self
+            .entries
+            .range(tanks.len()..)
+            .map(|(slot, _)| *slot)
+            .collect();
+        for slot in stale {
+            let (_, bounds) = self.entries.remove(&slot).expect("slot came from entries");
+            self.grid.remove(slot as u32, &bounds);
+        }
+
+        for (slot, tank) in tanks.iter().enumerate() {
+            let bounds = if tank.is_alive() && !tank.is_ghost() {
+                tank_bounds(tank, specs)
+            } else {
+                None
+            };
+            // a different tank in the slot means the list shifted, so start it afresh
+            let stored = self.entries.get(&slot).copied();
+            if let Some((tank_id, old)) = stored
+                && tank_id != tank.id
+            {
+                self.grid.remove(slot as u32, &old);
+                self.entries.remove(&slot);
+            }
+            let stored = self.entries.get(&slot).map(|(_, old)| *old);
+            match (stored, bounds) {
+                // a sleeper hasn't moved since it was stored
+                (Some(_), Some(_)) if tank.sleeping => {}
+                (Some(old), Some(new)) => {
+                    if old != new {
+                        self.grid.update(slot as u32, &old, &new);
+                        self.entries.insert(slot, (tank.id, new));
+                    }
+                }
+                (None, Some(new)) => {
+                    self.grid.insert(slot as u32, &new);
+                    self.entries.insert(slot, (tank.id, new));
+                }
+                (Some(old), None) => {
+                    self.grid.remove(slot as u32, &old);
+                    self.entries.remove(&slot);
+                }
+                (None, None) => {}
+            }
+        }
+    }
+
+    /// Returns how many tanks were in each cell as of the last call to [`TankBroadphase::pairs`].
+    ///
+    /// A sparse grid has no rows or columns to lay counts out in, so its heatmap is empty.
+    pub fn heatmap(&self) -> GridHeatmap {
+        let (columns, rows) = self.grid_size();
+        let stats = self.stats();
+        GridHeatmap {
+            columns,
+            rows,
+            cell_width: stats.cell_width,
+            cell_height: stats.cell_height,
+            counts: self.cell_counts(),
+            suggested_cell_size: stats.suggested_cell_size(),
+        }
+    }
+
+    /// Returns the number of columns and rows of cells, or none for a sparse grid.
+    pub fn grid_size(&self) -> (u32, u32) {
+        match &self.grid {
+            TankGrid::Dense(grid) => grid.grid_size(),
+            TankGrid::Sparse { .. } => (0, 0),
+        }
+    }
+
+    /// Returns how many tanks were in each cell as of the last call to
+    /// [`TankBroadphase::pairs`], in row-major order, or nothing for a sparse grid. Cheaper
+    /// than a full [`TankBroadphase::heatmap`].
+    pub fn cell_counts(&self) -> Vec<u32> {
+        match &self.grid {
+            TankGrid::Dense(grid) => grid.cell_counts(),
+            TankGrid::Sparse { .. } => Vec::new(),
+        }
+    }
+
+    /// Summarises how tanks are spread over the grid's cells.
+    pub fn occupancy(&self) -> OccupancyStats {
+        self.grid.occupancy()
+    }
+
+    /// Returns the grid's occupancy and dimensions, with which to suggest a better cell size.
+    pub fn stats(&self) -> GridStats {
+        self.grid.stats()
+    }
+
+    /// Returns the slots of live, solid tanks whose bounds reach into a circle, lowest first,
+    /// e.g. for area effects.
+    pub fn query_circle(
+        &mut self,
+        tanks: &[Tank],
+        specs: &SpecTable,
+        center: Vec2,
+        radius: Scalar,
+    ) -> Vec<usize> {
+        self.sync(tanks, specs);
+        let mut slots: Vec<usize> = self
+            .grid
+            .query_circle(center, radius)
+            .into_iter()
+            .map(|slot| slot as usize)
+            .filter(|slot| self.entries[slot].1.distance_squared_to(center) <= radius * radius)
+            .collect();
+        slots.sort_unstable();
+        slots
+    }
+
+    /// Returns the slots of live, solid tanks whose bounds the segment `start -> end` passes
+    /// through, in the order it reaches them, ties going to the lower slot. E.g. for a pellet
+    /// or beam that stops at whatever it meets first.
+    pub fn query_segment(
+        &mut self,
+        tanks: &[Tank],
+        specs: &SpecTable,
+        start: Vec2,
+        end: Vec2,
+    ) -> Vec<usize> {
+        self.sync(tanks, specs);
+        let mut met: Vec<(Scalar, usize)> = self
+            .grid
+            .query_segment(start, end)
+            .into_iter()
+            .map(|slot| slot as usize)
+            .filter_map(|slot| {
+                let fraction = self.entries[&slot].1.segment_entry(start, end)?;
+                Some((fraction, slot))
+            })
+            .collect();
+        met.sort_unstable();
+        met.into_iter().map(|(_, slot)| slot).collect()
+    }
+
+    /// Returns the slots of live, solid tanks whose centres lie within `radius` of `center` and
+    /// within half of `arc` either side of `facing`, lowest first. E.g. for a radar sweep or a
+    /// cone-shaped blast.
+    pub fn query_arc(
+        &mut self,
+        tanks: &[Tank],
+        specs: &SpecTable,
+        center: Vec2,
+        radius: Scalar,
+        facing: Angle,
+        arc: Scalar,
+    ) -> Vec<usize> {
+        self.sync(tanks, specs);
+        let half_arc = arc / dec64!(2);
+        let mut slots: Vec<usize> = self
+            .grid
+            .query_arc(center, radius, facing, arc)
+            .into_iter()
+            .map(|slot| slot as usize)
+            .filter(|slot| {
+                let offset = tanks[*slot].position.sub(&center);
+                let distance_squared = offset.length_squared();
+                // inside the arc iff facing . offset >= |offset| * cos(arc / 2), as for radar
+                distance_squared <= radius * radius
+                    && (arc >= Scalar::PI * dec64!(2)
+                        || facing.direction().dot(&offset)
+                            >= distance_squared.sqrt() * half_arc.cos())
+            })
+            .collect();
+        slots.sort_unstable();
+        slots
+    }
+
+    /// Returns the slots of up to `k` live, solid tanks that pass `filter`, nearest centre to
+    /// the point first, e.g. for picking targets.
+    pub fn k_nearest(
+        &mut self,
+        tanks: &[Tank],
+        specs: &SpecTable,
+        center: Vec2,
+        k: usize,
+        filter: impl Fn(&Tank) -> bool,
+    ) -> Vec<usize> {
+        self.sync(tanks, specs);
+        self.grid
+            .k_nearest(center, k, |slot| {
+                let tank = &tanks[slot as usize];
+                filter(tank).then(|| tank.position.distance_squared(&center))
+            })
+            .into_iter()
+            .map(|slot| slot as usize)
+            .collect()
+    }
+
+    /// Returns the pairs of live, solid tanks whose bounds overlap, lowest slot first.
+    ///
+    /// Two sleeping tanks are never paired: neither is moving, so they can't have collided.
+    pub fn pairs(&mut self, tanks: &[Tank], specs: &SpecTable) -> Vec<(usize, usize)> {
+        self.pairs_with(tanks, specs, &ScopedSplitter::default())
+    }
+
+    /// Like [`TankBroadphase::pairs`], with the grid's cells split across `splitter`'s threads.
+    pub fn pairs_with(
+        &mut self,
+        tanks: &[Tank],
+        specs: &SpecTable,
+        splitter: &ScopedSplitter,
+    ) -> Vec<(usize, usize)> {
+        self.sync(tanks, specs);
+
+        // tanks that touch share a cell, so each cell can be checked on its own; a pair sharing
+        // several cells is found in each, and sorting puts the results in one order however the
+        // cells were split
+        let sleeping: Vec<bool> = tanks.iter().map(|tank| tank.sleeping).collect();
+        let entries = &self.entries;
+        let cell_pairs = |slots: Vec<usize>, pairs: &mut Vec<(usize, usize)>| {
+            for (index, a) in slots.iter().enumerate() {
+                for b in &slots[index + 1..] {
+                    let (a, b) = (*a.min(b), *a.max(b));
+                    if sleeping[a] && sleeping[b] {
+                        continue;
+                    }
+                    // sharing a cell isn't enough, the bounds have to actually touch
+                    if entries[&a].1.intersects(&entries[&b].1) {
+                        pairs.push((a, b));
+                    }
+                }
+            }
+        };
+        let mut pairs = match &self.grid {
+            TankGrid::Dense(grid) => splitter.map(grid.cells(), |_, cells| {
+                let mut pairs = Vec::new();
+                for cell in cells {
+                    cell_pairs(cell.iter().map(|slot| *slot as usize).collect(), &mut pairs);
+                }
+                pairs
+            }),
+            TankGrid::Sparse { grid, .. } => {
+                let cells: Vec<&BTreeSet<u32>> = grid.cells().map(|(_, cell)| cell).collect();
+                splitter.map(&cells, |_, cells| {
+                    let mut pairs = Vec::new();
+                    for cell in cells {
+                        cell_pairs(cell.iter().map(|slot| *slot as usize).collect(), &mut pairs);
+                    }
+                    pairs
+                })
+            }
+        };
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use crate::util::math::Angle;
+
+    fn tank(id: u32, x: f64, sleeping: bool) -> Tank {
+        let mut tank = test_support::tank(id, 0, Vec2::new_from_f64(x, 100.0));
+        tank.sleeping = sleeping;
+        tank
+    }
+
+    #[test]
+    fn pairs_should_skip_sleeping_pairs_but_not_sleepers_hit_by_movers() {
+        // Arrange
+        let specs = SpecTable::default();
+        let mut broadphase = TankBroadphase::new(dec64!(500), dec64!(500), dec64!(64));
+        let tanks = vec![
+            tank(0, 100.0, true),
+            tank(1, 110.0, true),
+            tank(2, 300.0, true),
+            tank(3, 310.0, false),
+            tank(4, 450.0, false),
+        ];
+
+        // Act
+        let pairs = broadphase.pairs(&tanks, &specs);
+
+        // Assert
+        assert_eq!(pairs, vec![(2, 3)]);
+    }
+
+    #[test]
+    fn pairs_should_track_movers_and_removals_across_calls() {
+        // Arrange
+        let specs = SpecTable::default();
+        let mut broadphase = TankBroadphase::new(dec64!(500), dec64!(500), dec64!(64));
+        let mut tanks = vec![
+            tank(0, 100.0, true),
+            tank(1, 300.0, false),
+            tank(2, 450.0, false),
+        ];
+        let apart = broadphase.pairs(&tanks, &specs);
+
+        // Act
+        // tank 1 drives across several cells onto the sleeper, then tank 0 is destroyed
+        tanks[1].position = Vec2::new_from_f64(110.0, 100.0);
+        let met = broadphase.pairs(&tanks, &specs);
+        tanks[0].health = 0;
+        let destroyed = broadphase.pairs(&tanks, &specs);
+        tanks.remove(0);
+        tanks[0].position = Vec2::new_from_f64(440.0, 100.0);
+        let shifted = broadphase.pairs(&tanks, &specs);
+
+        // Assert
+        assert!(apart.is_empty());
+        assert_eq!(met, vec![(0, 1)]);
+        assert!(destroyed.is_empty());
+        assert_eq!(shifted, vec![(0, 1)]);
+    }
+
+    #[test]
+    fn new_when_arena_huge_should_store_only_occupied_cells() {
+        // Arrange
+        let specs = SpecTable::default();
+        let huge = dec64!(1_000_000_000);
+        let tanks = vec![
+            tank(0, 100.0, false),
+            tank(1, 110.0, false),
+            tank(2, 300.0, false),
+        ];
+        let at = |x| Vec2::new_from_f64(x, 100.0);
+
+        // Act
+        let mut broadphase = TankBroadphase::new(huge, huge, dec64!(64));
+        let pairs = broadphase.pairs(&tanks, &specs);
+        let caught = broadphase.query_circle(&tanks, &specs, at(0.0), huge);
+        let shot = broadphase.query_segment(&tanks, &specs, at(1e8), at(0.0));
+        let nearest = broadphase.k_nearest(&tanks, &specs, at(290.0), 2, |_| true);
+
+        // Assert
+        assert!(broadphase.is_sparse());
+        assert_eq!(pairs, vec![(0, 1)]);
+        assert_eq!(caught, vec![0, 1, 2]);
+        assert_eq!(shot, vec![2, 1, 0]);
+        assert_eq!(nearest, vec![2, 1]);
+        let occupancy = broadphase.occupancy();
+        assert_eq!(occupancy.cells, occupancy.occupied_cells);
+        assert!(occupancy.cells <= 6);
+        assert_eq!(broadphase.grid_size(), (0, 0));
+    }
+
+    #[test]
+    fn queries_should_find_nearby_live_tanks_in_order() {
+        // Arrange
+        let specs = SpecTable::default();
+        let mut broadphase = TankBroadphase::new(dec64!(500), dec64!(500), dec64!(64));
+        let mut tanks = vec![
+            tank(0, 100.0, false),
+            tank(1, 180.0, true),
+            tank(2, 300.0, false),
+            tank(3, 140.0, false),
+        ];
+        tanks[3].health = 0;
+        let center = Vec2::new_from_f64(150.0, 100.0);
+
+        // Act
+        let caught = broadphase.query_circle(&tanks, &specs, center, dec64!(40));
+        let nearest = broadphase.k_nearest(&tanks, &specs, center, 2, |_| true);
+        let awake = broadphase.k_nearest(&tanks, &specs, center, 2, |tank| !tank.sleeping);
+
+        // Assert
+        // the destroyed tank is nearest but isn't in the broadphase at all
+        assert_eq!(caught, vec![0, 1]);
+        assert_eq!(nearest, vec![1, 0]);
+        assert_eq!(awake, vec![0, 2]);
+    }
+
+    #[test]
+    fn shaped_queries_should_find_tanks_along_segment_and_in_arc() {
+        // Arrange
+        let specs = SpecTable::default();
+        let mut broadphase = TankBroadphase::new(dec64!(500), dec64!(500), dec64!(64));
+        let tanks = vec![
+            tank(0, 100.0, false),
+            tank(1, 200.0, true),
+            tank(2, 300.0, false),
+        ];
+        let at = |x| Vec2::new_from_f64(x, 100.0);
+        let left = Angle::new(Scalar::PI);
+
+        // Act
+        let shot = broadphase.query_segment(&tanks, &specs, at(400.0), at(150.0));
+        let swept = broadphase.query_arc(&tanks, &specs, at(250.0), dec64!(160), left, dec64!(1));
+
+        // Assert
+        // the segment reaches the nearest tank first and stops short of the far one
+        assert_eq!(shot, vec![2, 1]);
+        // the tank behind the sweep is in range but outside the arc
+        assert_eq!(swept, vec![0, 1]);
+    }
+}
@@ -0,0 +1,254 @@
+use crate::physics::collision::{AABB, SegmentHit};
+use crate::util::math::{Scalar, Vec2};
+use fastnum::dec64;
+
+/// Most boxes kept in a leaf before it's split.
+const LEAF_SIZE: usize = 2;
+
+/// How far node bounds are grown past the boxes they hold, so rounding in the boxes' own hit
+/// tests can never put a hit outside its node.
+const NODE_MARGIN: Scalar = dec64!(0.001);
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    /// Holds `items[first..first + count]`.
+    Leaf {
+        bounds: AABB,
+        first: usize,
+        count: usize,
+    },
+    /// Holds the nodes at `left` and `left + 1`.
+    Branch { bounds: AABB, left: usize },
+}
+
+impl Node {
+    fn bounds(&self) -> &AABB {
+        match self {
+            Node::Leaf { bounds, .. } | Node::Branch { bounds, .. } => bounds,
+        }
+    }
+}
+
+/// A bounding volume hierarchy over boxes that never move, for casting rays against them.
+///
+/// Built once, e.g. at match start, into a flat array of nodes. A ray only visits the nodes
+/// it passes through, nearest first, and skips any that start beyond the nearest hit found so
+/// far, so long rays over crowded maps look at a handful of boxes rather than every one in
+/// the cells they cross.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StaticBvh {
+    nodes: Vec<Node>,
+    /// The boxes with their IDs, reordered so each leaf's are contiguous.
+    items: Vec<(u32, AABB)>,
+}
+
+impl StaticBvh {
+    pub fn new(boxes: impl IntoIterator<Item = (u32, AABB)>) -> Self {
+        let mut bvh = StaticBvh {
+            nodes: Vec::new(),
+            items: boxes.into_iter().collect(),
+        };
+        // keep the build independent of the order boxes were given in
+        bvh.items.sort_by_key(|(id, _)| *id);
+        if !bvh.items.is_empty() {
+            bvh.nodes.push(Node::Leaf {
+                bounds: AABB::new(Vec2::zero(), Vec2::zero()),
+                first: 0,
+                count: 0,
+            });
+            bvh.build(0, 0, bvh.items.len());
+        }
+        bvh
+    }
+
+    /// Fills in the node at `index` to hold `items[first..end]`, splitting it if it's too big.
+    fn build(&mut self, index: usize, first: usize, end: usize) {
+        let items = &mut self.items[first..end];
+        let bounds = enclose(items.iter().map(|(_, aabb)| *aabb)).expand(NODE_MARGIN);
+        if items.len() <= LEAF_SIZE {
+            self.nodes[index] = Node::Leaf {
+                bounds,
+                first,
+                count: items.len(),
+            };
+            return;
+        }
+
+        // split at the median centre along whichever axis the centres spread furthest
+        let centres = enclose(items.iter().map(|(_, aabb)| {
+            let centre = aabb.center();
+            AABB::new(centre, centre)
+        }));
+        if centres.max.x - centres.min.x >= centres.max.y - centres.min.y {
+            items.sort_by_key(|(id, aabb)| (aabb.min.x + aabb.max.x, *id));
+        } else {
+            items.sort_by_key(|(id, aabb)| (aabb.min.y + aabb.max.y, *id));
+        }
+        let middle = first + items.len() / 2;
+
+        let left = self.nodes.len();
+        self.nodes[index] = Node::Branch { bounds, left };
+        for _ in 0..2 {
+            self.nodes.push(Node::Leaf {
+                bounds,
+                first: 0,
+                count: 0,
+            });
+        }
+        self.build(left, first, middle);
+        self.build(left + 1, middle, end);
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Finds the first box the segment `from -> to` runs into, if any, using `hit` to test the
+    /// segment against each box it might touch.
+    ///
+    /// Ties are broken by ID, so the result is the same as testing every box in ID order.
+    pub fn raycast(
+        &self,
+        from: Vec2,
+        to: Vec2,
+        mut hit: impl FnMut(u32, &AABB) -> Option<SegmentHit>,
+    ) -> Option<(u32, SegmentHit)> {
+        let mut first: Option<(u32, SegmentHit)> = None;
+        let mut stack = Vec::new();
+        if let Some(root) = self.nodes.first()
+            && let Some(entry) = root.bounds().segment_entry(from, to)
+        {
+            stack.push((entry, 0));
+        }
+
+        while let Some((entry, index)) = stack.pop() {
+            if first.is_some_and(|(_, best)| entry > best.fraction) {
+                continue;
+            }
+            match self.nodes[index] {
+                Node::Leaf {
+                    first: start,
+                    count,
+                    ..
+                } => {
+                    for (id, aabb) in &self.items[start..start + count] {
+                        let Some(hit) = hit(*id, aabb) else {
+                            continue;
+                        };
+                        let closer = first.is_none_or(|(best_id, best)| {
+                            (hit.fraction, *id) < (best.fraction, best_id)
+                        });
+                        if closer {
+                            first = Some((*id, hit));
+                        }
+                    }
+                }
+                Node::Branch { left, .. } => {
+                    let mut children: Vec<(Scalar, usize)> = [left, left + 1]
+                        .into_iter()
+                        .filter_map(|child| {
+                            let entry = self.nodes[child].bounds().segment_entry(from, to)?;
+                            Some((entry, child))
+                        })
+                        .collect();
+                    // the nearer child goes on top, to find a close hit early
+                    children.sort_unstable_by(|a, b| b.cmp(a));
+                    stack.extend(children);
+                }
+            }
+        }
+
+        first
+    }
+}
+
+/// Returns the smallest box around all the given ones.
+fn enclose(boxes: impl Iterator<Item = AABB>) -> AABB {
+    boxes
+        .reduce(|total, aabb| {
+            AABB::new(
+                Vec2::new(total.min.x.min(aabb.min.x), total.min.y.min(aabb.min.y)),
+                Vec2::new(total.max.x.max(aabb.max.x), total.max.y.max(aabb.max.y)),
+            )
+        })
+        .unwrap_or(AABB::new(Vec2::zero(), Vec2::zero()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::collision::segment_vs_box;
+    use crate::util::math::{ConvertToScalar, Rotation, Transform2};
+    use proptest::prelude::*;
+
+    fn box_hit(from: Vec2, to: Vec2, aabb: &AABB) -> Option<SegmentHit> {
+        let frame = Transform2::new(aabb.center(), Rotation::IDENTITY);
+        segment_vs_box(from, to, &frame, aabb.half_extents())
+    }
+
+    fn arb_aabb() -> impl Strategy<Value = AABB> {
+        (0u32..200, 0u32..200, 1u32..40, 1u32..40).prop_map(|(x, y, w, h)| {
+            AABB::new(
+                Vec2::new(x.to_scalar(), y.to_scalar()),
+                Vec2::new((x + w).to_scalar(), (y + h).to_scalar()),
+            )
+        })
+    }
+
+    #[test]
+    fn raycast_should_return_nearest_box_in_path() {
+        // Arrange
+        let at = |x, y| Vec2::new_from_f64(x, y);
+        let bvh = StaticBvh::new([
+            (4, AABB::new(at(150.0, 90.0), at(160.0, 110.0))),
+            (9, AABB::new(at(50.0, 90.0), at(60.0, 110.0))),
+            (2, AABB::new(at(100.0, 150.0), at(110.0, 160.0))),
+            (7, AABB::new(at(100.0, 90.0), at(110.0, 110.0))),
+        ]);
+        let (from, to) = (at(0.0, 100.0), at(200.0, 100.0));
+
+        // Act
+        let hit = bvh.raycast(from, to, |_, aabb| box_hit(from, to, aabb));
+        let miss = bvh.raycast(at(0.0, 0.0), at(200.0, 0.0), |_, aabb| {
+            box_hit(at(0.0, 0.0), at(200.0, 0.0), aabb)
+        });
+
+        // Assert
+        assert_eq!(
+            hit.map(|(id, hit)| (id, hit.fraction)),
+            Some((9, dec64!(0.25)))
+        );
+        assert_eq!(miss, None);
+    }
+
+    proptest! {
+        #[test]
+        fn raycast_should_agree_with_testing_every_box(
+            boxes in prop::collection::vec(arb_aabb(), 0..40),
+            from in (0u32..240, 0u32..240),
+            to in (0u32..240, 0u32..240),
+        ) {
+            // Arrange
+            let bvh = StaticBvh::new(
+                boxes.iter().enumerate().map(|(id, aabb)| (id as u32, *aabb)),
+            );
+            let from = Vec2::new(from.0.to_scalar(), from.1.to_scalar());
+            let to = Vec2::new(to.0.to_scalar(), to.1.to_scalar());
+
+            // Act
+            let hit = bvh.raycast(from, to, |_, aabb| box_hit(from, to, aabb));
+
+            // Assert
+            let expected = boxes
+                .iter()
+                .enumerate()
+                .filter_map(|(id, aabb)| Some((box_hit(from, to, aabb)?.fraction, id as u32)))
+                .min();
+            prop_assert_eq!(hit.map(|(id, hit)| (hit.fraction, id)), expected);
+        }
+    }
+}
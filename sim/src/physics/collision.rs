@@ -1,20 +1,23 @@
-use crate::util::math::{Vec2};
+use crate::util::math::{Scalar, Transform2, Vec2};
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
 
 /// An axis-aligned bounding box (AABB).
-#[derive(Clone, Copy, Debug)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct AABB {
     pub min: Vec2,
     pub max: Vec2,
 }
 
 impl AABB {
-    /// Creates a new AABB. 
-    /// 
+    /// Creates a new AABB.
+    ///
     /// Normalizes the min and max vectors so that `min.x <= max.x` and `min.y <= max.y`.
     pub fn new(min: Vec2, max: Vec2) -> Self {
-        AABB { 
-            min: Vec2::new(min.x.min(max.x), min.y.min(max.y)), 
-            max: Vec2::new(min.x.max(max.x), min.y.max(max.y)) 
+        AABB {
+            min: Vec2::new(min.x.min(max.x), min.y.min(max.y)),
+            max: Vec2::new(min.x.max(max.x), min.y.max(max.y)),
         }
     }
 
@@ -25,4 +28,316 @@ impl AABB {
             max: Vec2::new(center.x + size.x / 2.0, center.y + size.y / 2.0),
         }
     }
+
+    /// Returns the centre of the box.
+    pub fn center(&self) -> Vec2 {
+        Vec2::new(
+            (self.min.x + self.max.x) / dec64!(2),
+            (self.min.y + self.max.y) / dec64!(2),
+        )
+    }
+
+    /// Returns half the box's width and height.
+    pub fn half_extents(&self) -> Vec2 {
+        Vec2::new(
+            (self.max.x - self.min.x) / dec64!(2),
+            (self.max.y - self.min.y) / dec64!(2),
+        )
+    }
+
+    /// Returns whether the point lies inside the box or on its boundary.
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.min.x
+            && point.x <= self.max.x
+            && point.y >= self.min.y
+            && point.y <= self.max.y
+    }
+
+    /// Returns whether the two boxes overlap. Boxes that only share an edge don't count.
+    pub fn intersects(&self, other: &AABB) -> bool {
+        self.min.x < other.max.x
+            && other.min.x < self.max.x
+            && self.min.y < other.max.y
+            && other.min.y < self.max.y
+    }
+
+    /// Returns the squared distance from the point to the nearest point of the box, zero if
+    /// it's inside.
+    pub fn distance_squared_to(&self, point: Vec2) -> Scalar {
+        let closest = Vec2::new(
+            point.x.clamp(self.min.x, self.max.x),
+            point.y.clamp(self.min.y, self.max.y),
+        );
+        point.distance_squared(&closest)
+    }
+
+    /// Returns how far along the segment `start -> end` it first touches the box, as a fraction
+    /// in `[0, 1]`, or `None` if it misses. Segments starting inside the box touch at zero, and
+    /// grazing an edge counts.
+    pub fn segment_entry(&self, start: Vec2, end: Vec2) -> Option<Scalar> {
+        let delta = end.sub(&start);
+        let mut t_enter = dec64!(0);
+        let mut t_exit = dec64!(1);
+        for (origin, dir, min, max) in [
+            (start.x, delta.x, self.min.x, self.max.x),
+            (start.y, delta.y, self.min.y, self.max.y),
+        ] {
+            if dir.is_zero() {
+                if origin < min || origin > max {
+                    return None;
+                }
+                continue;
+            }
+            let (near, far) = ((min - origin) / dir, (max - origin) / dir);
+            t_enter = t_enter.max(near.min(far));
+            t_exit = t_exit.min(near.max(far));
+        }
+        (t_enter <= t_exit).then_some(t_enter)
+    }
+
+    /// Returns the box grown by `margin` on every side.
+    pub fn expand(&self, margin: Scalar) -> AABB {
+        AABB {
+            min: Vec2::new(self.min.x - margin, self.min.y - margin),
+            max: Vec2::new(self.max.x + margin, self.max.y + margin),
+        }
+    }
+}
+
+/// Where a segment first touches a shape.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SegmentHit {
+    /// Fraction of the way along the segment, in `[0, 1]`.
+    pub fraction: Scalar,
+    /// Normal of the face that was hit, in the shape's local frame.
+    pub normal: Vec2,
+}
+
+/// Sweeps the segment `start -> end` against an oriented box.
+///
+/// The box is centred on `transform`'s position and extends `half_extents` along its local x
+/// (forward) and y axes. Segments starting inside the box hit at fraction zero. The hit normal
+/// is in the box's frame.
+pub fn segment_vs_box(
+    start: Vec2,
+    end: Vec2,
+    transform: &Transform2,
+    half_extents: Vec2,
+) -> Option<SegmentHit> {
+    // work in the box's frame, where it's just an AABB
+    let local_start = transform.to_local_point(start);
+    let local_end = transform.to_local_point(end);
+    let delta = local_end.sub(&local_start);
+
+    let mut t_enter = dec64!(-1);
+    let mut t_exit = dec64!(2);
+    let mut normal = Vec2::zero();
+
+    for (origin, dir, extent, axis) in [
+        (
+            local_start.x,
+            delta.x,
+            half_extents.x,
+            Vec2::new(dec64!(1), dec64!(0)),
+        ),
+        (
+            local_start.y,
+            delta.y,
+            half_extents.y,
+            Vec2::new(dec64!(0), dec64!(1)),
+        ),
+    ] {
+        if dir.is_zero() {
+            if origin.abs() > extent {
+                return None;
+            }
+            continue;
+        }
+
+        let t_near = (-extent - origin) / dir;
+        let t_far = (extent - origin) / dir;
+        let (t_near, t_far, face) = if dir > dec64!(0) {
+            (t_near, t_far, Vec2::new(-axis.x, -axis.y))
+        } else {
+            (t_far, t_near, axis)
+        };
+
+        if t_near > t_enter {
+            t_enter = t_near;
+            normal = face;
+        }
+        t_exit = t_exit.min(t_far);
+    }
+
+    if t_enter > t_exit || t_exit < dec64!(0) || t_enter > dec64!(1) {
+        return None;
+    }
+
+    Some(SegmentHit {
+        fraction: t_enter.max(dec64!(0)),
+        normal,
+    })
+}
+
+/// How two overlapping boxes touch.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoxContact {
+    /// Unit normal pointing from the first box towards the second, in world space.
+    pub normal: Vec2,
+    /// How far the boxes would have to move apart along the normal to stop overlapping.
+    pub depth: Scalar,
+}
+
+/// An oriented box: where its centre sits and faces, and half its size along its local x and
+/// y axes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrientedBox {
+    pub transform: Transform2,
+    pub half_extents: Vec2,
+}
+
+impl OrientedBox {
+    /// The box's local x and y axes, in world space.
+    fn axes(&self) -> [Vec2; 2] {
+        let forward = self.transform.forward();
+        [forward, Vec2::new(-forward.y, forward.x)]
+    }
+
+    /// Half the box's extent along a world-space unit axis, given its own axes.
+    fn radius_along(&self, [forward, right]: &[Vec2; 2], axis: &Vec2) -> Scalar {
+        (self.half_extents.x * forward.dot(axis)).abs()
+            + (self.half_extents.y * right.dot(axis)).abs()
+    }
+}
+
+/// Tests two oriented boxes for overlap with the separating axis theorem.
+///
+/// Returns the axis of least penetration, or `None` if they don't overlap. Boxes that only
+/// touch along an edge don't count.
+pub fn box_vs_box(a: &OrientedBox, b: &OrientedBox) -> Option<BoxContact> {
+    let offset = b.transform.position.sub(&a.transform.position);
+    let (a_axes, b_axes) = (a.axes(), b.axes());
+    let mut best: Option<BoxContact> = None;
+
+    for axes in [a_axes, b_axes] {
+        for axis in axes {
+            let distance = offset.dot(&axis);
+            let depth =
+                a.radius_along(&a_axes, &axis) + b.radius_along(&b_axes, &axis) - distance.abs();
+            if depth <= dec64!(0) {
+                return None;
+            }
+            if best.is_none_or(|best| depth < best.depth) {
+                let normal = if distance < dec64!(0) {
+                    Vec2::new(-axis.x, -axis.y)
+                } else {
+                    axis
+                };
+                best = Some(BoxContact { normal, depth });
+            }
+        }
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::math::{Angle, ConvertToScalar, Rotation};
+
+    #[test]
+    fn segment_vs_box_when_crossing_front_face_should_report_front_normal() {
+        // Arrange
+        let start = Vec2::new_from_f64(20.0, 0.0);
+        let end = Vec2::new_from_f64(0.0, 0.0);
+        let half_extents = Vec2::new_from_f64(10.0, 5.0);
+
+        // Act
+        let hit = segment_vs_box(
+            start,
+            end,
+            &Transform2::new(Vec2::zero(), Rotation::IDENTITY),
+            half_extents,
+        );
+
+        // Assert
+        let hit = hit.unwrap();
+        assert_eq!(hit.fraction, 0.5.to_scalar());
+        assert_eq!(hit.normal, Vec2::new_from_f64(1.0, 0.0));
+    }
+
+    #[test]
+    fn segment_vs_box_when_box_rotated_should_hit_in_local_frame() {
+        // Arrange
+        // box faces +y in world space, so a shot travelling along -y hits its front
+        let angle = Angle::new(Scalar::PI / 2.0.to_scalar());
+        let start = Vec2::new_from_f64(0.0, 30.0);
+        let end = Vec2::new_from_f64(0.0, 0.0);
+        let half_extents = Vec2::new_from_f64(10.0, 5.0);
+
+        // Act
+        let hit = segment_vs_box(
+            start,
+            end,
+            &Transform2::new(Vec2::zero(), Rotation::new(angle)),
+            half_extents,
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(hit.normal, Vec2::new_from_f64(1.0, 0.0));
+        assert!((hit.fraction - (20.0 / 30.0).to_scalar()).abs() < 1e-15.to_scalar());
+    }
+
+    #[test]
+    fn segment_vs_box_when_missing_should_return_none() {
+        // Arrange
+        let half_extents = Vec2::new_from_f64(10.0, 5.0);
+
+        // Act
+        let parallel = segment_vs_box(
+            Vec2::new_from_f64(-20.0, 6.0),
+            Vec2::new_from_f64(20.0, 6.0),
+            &Transform2::new(Vec2::zero(), Rotation::IDENTITY),
+            half_extents,
+        );
+        let short = segment_vs_box(
+            Vec2::new_from_f64(0.0, 20.0),
+            Vec2::new_from_f64(0.0, 10.0),
+            &Transform2::new(Vec2::zero(), Rotation::IDENTITY),
+            half_extents,
+        );
+
+        // Assert
+        assert!(parallel.is_none());
+        assert!(short.is_none());
+    }
+
+    #[test]
+    fn box_vs_box_should_report_least_penetration_axis() {
+        // Arrange
+        let a = OrientedBox {
+            transform: Transform2::new(Vec2::zero(), Rotation::IDENTITY),
+            half_extents: Vec2::new_from_f64(10.0, 5.0),
+        };
+        let overlapping = OrientedBox {
+            transform: Transform2::new(Vec2::new_from_f64(18.0, 1.0), Rotation::IDENTITY),
+            ..a
+        };
+        let apart = OrientedBox {
+            transform: Transform2::new(Vec2::new_from_f64(0.0, -11.0), Rotation::IDENTITY),
+            ..a
+        };
+
+        // Act
+        let contact = box_vs_box(&a, &overlapping);
+        let miss = box_vs_box(&a, &apart);
+
+        // Assert
+        let contact = contact.unwrap();
+        assert_eq!(contact.normal, Vec2::new_from_f64(1.0, 0.0));
+        assert_eq!(contact.depth, dec64!(2));
+        assert_eq!(miss, None);
+    }
 }
@@ -0,0 +1,263 @@
+use crate::util::math::{Rotation, Scalar, Vec2};
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+
+/// Static parameters of a tracked drivetrain.
+///
+/// All rates are expressed per tick, since the tick count is the only clock the sim knows about.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DrivetrainSpec {
+    /// Top forward speed, in units per tick.
+    pub max_speed: Scalar,
+    /// Top reverse speed, in units per tick. Usually slower than `max_speed`.
+    pub max_reverse_speed: Scalar,
+    /// Acceleration from a standstill, in units per tick squared.
+    ///
+    /// Falls off linearly as the tank approaches its top speed.
+    pub acceleration: Scalar,
+    /// Deceleration when coasting or braking, in units per tick squared.
+    pub braking: Scalar,
+    /// Turn rate when pivoting in place, in radians per tick.
+    pub max_turn_rate: Scalar,
+    /// Fraction of the turn rate lost at top speed, in `[0, 1]`.
+    ///
+    /// Makes the turning radius grow with speed.
+    pub turn_rate_falloff: Scalar,
+    /// Fraction of sideways velocity the tracks cancel each tick, in `[0, 1]`.
+    pub lateral_grip: Scalar,
+}
+
+impl Default for DrivetrainSpec {
+    fn default() -> Self {
+        DrivetrainSpec {
+            max_speed: dec64!(3),
+            max_reverse_speed: dec64!(1.5),
+            acceleration: dec64!(0.2),
+            braking: dec64!(0.3),
+            max_turn_rate: dec64!(0.05),
+            turn_rate_falloff: dec64!(0.6),
+            lateral_grip: dec64!(0.5),
+        }
+    }
+}
+
+/// Per-tick drive commands for a tank, one value per track in `[-1, 1]`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DriveInput {
+    pub left: Scalar,
+    pub right: Scalar,
+}
+
+impl DriveInput {
+    /// Creates a drive input from per-track commands, clamping each to `[-1, 1]`.
+    pub fn tracks(left: Scalar, right: Scalar) -> Self {
+        DriveInput {
+            left: left.clamp(dec64!(-1), dec64!(1)),
+            right: right.clamp(dec64!(-1), dec64!(1)),
+        }
+    }
+
+    /// Creates a drive input from a throttle and a steering command.
+    ///
+    /// Positive steer turns towards increasing angles (clockwise on screen).
+    pub fn throttle_steer(throttle: Scalar, steer: Scalar) -> Self {
+        DriveInput::tracks(throttle + steer, throttle - steer)
+    }
+
+    /// Returns the combined forward command of both tracks.
+    pub fn throttle(&self) -> Scalar {
+        (self.left + self.right) / dec64!(2)
+    }
+
+    /// Returns the turning command implied by the difference between the tracks.
+    pub fn steer(&self) -> Scalar {
+        (self.left - self.right) / dec64!(2)
+    }
+}
+
+/// The result of running the drivetrain for one tick.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DriveOutput {
+    pub velocity: Vec2,
+    pub angular_velocity: Scalar,
+}
+
+/// Computes the velocity and turn rate produced by the tracks over one tick.
+///
+/// `heading` is the hull's rotation and `velocity` the current world-space velocity.
+pub fn drive(
+    spec: &DrivetrainSpec,
+    input: &DriveInput,
+    heading: Rotation,
+    velocity: Vec2,
+) -> DriveOutput {
+    let forward = heading.direction();
+    let right = Vec2::new(-forward.y, forward.x);
+
+    let speed = settle_zero(velocity.dot(&forward));
+    let lateral = velocity.dot(&right) * (dec64!(1) - spec.lateral_grip);
+
+    // reversing is capped separately, since tanks are much slower backing up
+    let throttle = settle_zero(input.throttle());
+    let target = if throttle < dec64!(0) {
+        throttle * spec.max_reverse_speed
+    } else {
+        throttle * spec.max_speed
+    };
+
+    let new_speed = if target > speed && speed >= dec64!(0) {
+        // speeding up forwards, with torque falling off near the top speed
+        let falloff = dec64!(1) - speed / spec.max_speed;
+        (speed + spec.acceleration * falloff.max(dec64!(0))).min(target)
+    } else if target < speed && speed <= dec64!(0) {
+        // speeding up in reverse
+        let falloff = dec64!(1) + speed / spec.max_reverse_speed;
+        (speed - spec.acceleration * falloff.max(dec64!(0))).max(target)
+    } else if target > speed {
+        (speed + spec.braking).min(target)
+    } else {
+        (speed - spec.braking).max(target)
+    };
+
+    // turning slows down with speed, which widens the turning radius
+    let speed_ratio = (new_speed.abs() / spec.max_speed).min(dec64!(1));
+    let turn_rate = spec.max_turn_rate * (dec64!(1) - spec.turn_rate_falloff * speed_ratio);
+
+    DriveOutput {
+        velocity: forward.scale(new_speed).mul_add(&right, lateral),
+        angular_velocity: input.steer() * turn_rate,
+    }
+}
+
+/// Turns -0 into 0. fastnum orders -0 below 0, so a stopped tank would otherwise look like it
+/// was reversing, and brake when told to drive forwards.
+fn settle_zero(value: Scalar) -> Scalar {
+    if value == dec64!(0) { dec64!(0) } else { value }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::math::ConvertToScalar;
+
+    fn run(spec: &DrivetrainSpec, input: &DriveInput, ticks: u32) -> DriveOutput {
+        let mut output = DriveOutput {
+            velocity: Vec2::zero(),
+            angular_velocity: 0.0.to_scalar(),
+        };
+        for _ in 0..ticks {
+            output = drive(spec, input, Rotation::IDENTITY, output.velocity);
+        }
+        output
+    }
+
+    #[test]
+    fn drive_when_full_throttle_should_approach_but_not_exceed_top_speed() {
+        // Arrange
+        let spec = DrivetrainSpec::default();
+        let input = DriveInput::throttle_steer(1.0.to_scalar(), 0.0.to_scalar());
+
+        // Act
+        let early = run(&spec, &input, 1);
+        let late = run(&spec, &input, 200);
+
+        // Assert
+        assert_eq!(early.velocity.x, spec.acceleration);
+        assert!(late.velocity.x <= spec.max_speed);
+        assert!(late.velocity.x > spec.max_speed * 0.99.to_scalar());
+        assert_eq!(late.velocity.y, 0.0.to_scalar());
+    }
+
+    #[test]
+    fn drive_when_reversing_should_be_capped_at_reverse_speed() {
+        // Arrange
+        let spec = DrivetrainSpec::default();
+        let input = DriveInput::throttle_steer((-1.0).to_scalar(), 0.0.to_scalar());
+
+        // Act
+        let output = run(&spec, &input, 200);
+
+        // Assert
+        assert!(output.velocity.x.is_negative());
+        assert!(output.velocity.x >= -spec.max_reverse_speed);
+        assert!(output.velocity.x.abs() < spec.max_speed);
+    }
+
+    #[test]
+    fn drive_when_coasting_should_brake_to_a_stop() {
+        // Arrange
+        let spec = DrivetrainSpec::default();
+        let moving = Vec2::new(spec.max_speed, 0.0.to_scalar());
+
+        // Act
+        let mut velocity = moving;
+        for _ in 0..20 {
+            velocity = drive(&spec, &DriveInput::default(), Rotation::IDENTITY, velocity).velocity;
+        }
+
+        // Assert
+        assert_eq!(velocity, Vec2::zero());
+    }
+
+    #[test]
+    fn drive_when_pivoting_should_turn_at_max_rate() {
+        // Arrange
+        let spec = DrivetrainSpec::default();
+        let input = DriveInput::tracks(1.0.to_scalar(), (-1.0).to_scalar());
+
+        // Act
+        let output = drive(&spec, &input, Rotation::IDENTITY, Vec2::zero());
+
+        // Assert
+        assert_eq!(output.velocity, Vec2::zero());
+        assert_eq!(output.angular_velocity, spec.max_turn_rate);
+    }
+
+    #[test]
+    fn drive_when_moving_fast_should_turn_slower_than_when_slow() {
+        // Arrange
+        let spec = DrivetrainSpec::default();
+        let input = DriveInput::throttle_steer(0.5.to_scalar(), 0.5.to_scalar());
+        let slow = Vec2::zero();
+        let fast = Vec2::new(spec.max_speed, 0.0.to_scalar());
+
+        // Act
+        let slow_output = drive(&spec, &input, Rotation::IDENTITY, slow);
+        let fast_output = drive(&spec, &input, Rotation::IDENTITY, fast);
+
+        // Assert
+        assert!(fast_output.angular_velocity < slow_output.angular_velocity);
+        assert!(fast_output.angular_velocity.is_positive());
+    }
+
+    #[test]
+    fn drive_when_starting_from_negative_zero_should_accelerate_rather_than_brake() {
+        // Arrange
+        let spec = DrivetrainSpec::default();
+        let forwards = DriveInput::throttle_steer(1.0.to_scalar(), 0.0.to_scalar());
+        let backwards = DriveInput::throttle_steer((-1.0).to_scalar(), 0.0.to_scalar());
+        let still = Vec2::new(-dec64!(0), -dec64!(0));
+
+        // Act
+        let ahead = drive(&spec, &forwards, Rotation::IDENTITY, still);
+        let astern = drive(&spec, &backwards, Rotation::IDENTITY, still);
+
+        // Assert
+        assert_eq!(ahead.velocity.x, spec.acceleration);
+        assert_eq!(astern.velocity.x, -spec.acceleration);
+    }
+
+    #[test]
+    fn drive_when_sliding_sideways_should_bleed_lateral_velocity() {
+        // Arrange
+        let spec = DrivetrainSpec::default();
+        let sliding = Vec2::new(0.0.to_scalar(), 2.0.to_scalar());
+
+        // Act
+        let output = drive(&spec, &DriveInput::default(), Rotation::IDENTITY, sliding);
+
+        // Assert
+        assert_eq!(output.velocity.x, 0.0.to_scalar());
+        assert_eq!(output.velocity.y, 1.0.to_scalar());
+    }
+}
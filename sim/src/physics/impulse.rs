@@ -0,0 +1,99 @@
+use crate::events::SimEvent;
+use crate::explosions::ExplosionCause;
+use crate::spec::TankSpec;
+use crate::state::Tank;
+use crate::util::math::{Scalar, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// What pushed a tank.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ImpulseSource {
+    Explosion(ExplosionCause),
+    /// A collision with the given tank.
+    Ram(u32),
+    /// The tank's own shot, from the given weapon.
+    Recoil(u32),
+    /// Requested from outside the match, e.g. by a cutscene or the debug console.
+    External,
+}
+
+/// Changes a tank's velocity by an impulse, scaled by its mass, and logs it.
+///
+/// Every push goes through here, so replays and telemetry see gameplay and scripted pushes alike.
+pub fn apply(
+    tank: &mut Tank,
+    spec: &TankSpec,
+    impulse: Vec2,
+    source: ImpulseSource,
+    events: &mut Vec<SimEvent>,
+) {
+    let change = Vec2::new(impulse.x / spec.mass, impulse.y / spec.mass);
+    tank.velocity = tank.velocity + change;
+    tank.wake();
+    events.push(SimEvent::Impulse {
+        tank_id: tank.id,
+        impulse,
+        source,
+    });
+}
+
+/// Twists a tank's hull by an angle, and logs it.
+///
+/// Turn rates are set afresh by the drivetrain every tick, so the kick turns the hull directly
+/// rather than lingering in its angular velocity.
+pub fn apply_angular(
+    tank: &mut Tank,
+    kick: Scalar,
+    source: ImpulseSource,
+    events: &mut Vec<SimEvent>,
+) {
+    tank.angle = tank.angle + kick;
+    tank.wake();
+    events.push(SimEvent::AngularImpulse {
+        tank_id: tank.id,
+        kick,
+        source,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spec::{Loadout, SpecTable};
+    use crate::util::math::Angle;
+
+    #[test]
+    fn apply_should_divide_by_mass_and_log_impulse() {
+        // Arrange
+        let specs = SpecTable::default();
+        let spec = specs.tank(2).unwrap();
+        let loadout = Loadout {
+            spec_id: 2,
+            weapons: vec![],
+        };
+        let mut tank = Tank::new(4, 0, spec, loadout, Vec2::zero(), Angle::ZERO);
+        tank.velocity = Vec2::new_from_f64(1.0, 0.0);
+        let impulse = Vec2::new_from_f64(0.0, 120.0);
+
+        // Act
+        let mut events = Vec::new();
+        apply(
+            &mut tank,
+            spec,
+            impulse,
+            ImpulseSource::External,
+            &mut events,
+        );
+
+        // Assert
+        assert_eq!(tank.velocity, Vec2::new_from_f64(1.0, 2.0));
+        assert_eq!(
+            events,
+            vec![SimEvent::Impulse {
+                tank_id: 4,
+                impulse,
+                source: ImpulseSource::External,
+            }]
+        );
+    }
+}
@@ -1 +1,7 @@
+pub mod broadphase;
+pub mod bvh;
 pub mod collision;
+pub mod drivetrain;
+pub mod impulse;
+pub mod sleep;
+pub mod turret;
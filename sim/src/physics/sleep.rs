@@ -0,0 +1,64 @@
+use crate::physics::drivetrain::DriveInput;
+use crate::state::Tank;
+use crate::util::math::{Scalar, Vec2};
+use fastnum::dec64;
+
+/// Speed, and turn rate, below which a tank counts as at rest.
+const SLEEP_SPEED: Scalar = dec64!(0.01);
+
+/// Ticks a tank has to stay at rest before it's put to sleep.
+pub const SLEEP_AFTER_TICKS: u32 = 30;
+
+/// Counts how long a tank has been at rest, and puts it to sleep once it's been long enough.
+///
+/// Tanks being told to drive never sleep. Sleeping tanks are snapped to a standstill, so they
+/// can be skipped by integration until something wakes them.
+pub fn update(tank: &mut Tank) {
+    if tank.sleeping {
+        return;
+    }
+    let commanded = tank.is_alive() && tank.drive != DriveInput::default();
+    let at_rest =
+        tank.velocity.is_shorter_than(SLEEP_SPEED) && tank.angular_velocity.abs() < SLEEP_SPEED;
+    if commanded || !at_rest {
+        tank.idle_ticks = 0;
+        return;
+    }
+
+    tank.idle_ticks += 1;
+    if tank.idle_ticks >= SLEEP_AFTER_TICKS {
+        tank.sleeping = true;
+        tank.velocity = Vec2::zero();
+        tank.angular_velocity = dec64!(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn tank() -> Tank {
+        test_support::tank(0, 0, Vec2::zero())
+    }
+
+    #[test]
+    fn update_when_idle_long_enough_should_sleep() {
+        // Arrange
+        let mut idle = tank();
+        idle.velocity = Vec2::new_from_f64(0.001, 0.0);
+        let mut driving = tank();
+        driving.drive = DriveInput::tracks(dec64!(0.5), dec64!(0.5));
+
+        // Act
+        for _ in 0..SLEEP_AFTER_TICKS {
+            update(&mut idle);
+            update(&mut driving);
+        }
+
+        // Assert
+        assert!(idle.sleeping);
+        assert_eq!(idle.velocity, Vec2::zero());
+        assert!(!driving.sleeping);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::util::math::{Angle, Scalar};
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+
+/// Static parameters of a tank's turret.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TurretSpec {
+    /// Maximum turret rotation relative to the hull, in radians per tick.
+    pub max_slew_rate: Scalar,
+    /// Whether the gun is fixed to the hull (e.g. a casemate gun) and cannot traverse at all.
+    pub locked_to_heading: bool,
+}
+
+impl Default for TurretSpec {
+    fn default() -> Self {
+        TurretSpec {
+            max_slew_rate: dec64!(0.08),
+            locked_to_heading: false,
+        }
+    }
+}
+
+/// An aiming command for a turret. Commands persist until replaced.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum TurretCommand {
+    /// Keep the turret where it is relative to the hull.
+    #[default]
+    Hold,
+    /// Turn towards the given world-space angle, compensating for hull rotation.
+    Absolute(Angle),
+    /// Turn towards the given angle relative to the hull heading.
+    Relative(Angle),
+}
+
+/// Computes the new hull-relative turret angle after one tick of slewing.
+///
+/// `hull_angle` should be the hull heading at the end of the tick, so absolute aiming
+/// accounts for the hull turning underneath the turret.
+pub fn slew(
+    spec: &TurretSpec,
+    command: &TurretCommand,
+    hull_angle: Angle,
+    turret_angle: Angle,
+) -> Angle {
+    if spec.locked_to_heading {
+        return Angle::ZERO;
+    }
+
+    let target = match command {
+        TurretCommand::Hold => return turret_angle,
+        TurretCommand::Absolute(angle) => *angle - hull_angle,
+        TurretCommand::Relative(angle) => *angle,
+    };
+
+    turret_angle.step_towards(target, spec.max_slew_rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::math::ConvertToScalar;
+
+    #[test]
+    fn slew_when_target_far_away_should_be_limited_by_slew_rate() {
+        // Arrange
+        let spec = TurretSpec::default();
+        let command = TurretCommand::Relative(Angle::new(1.0.to_scalar()));
+
+        // Act
+        let angle = slew(&spec, &command, Angle::ZERO, Angle::ZERO);
+
+        // Assert
+        assert_eq!(angle.radians(), spec.max_slew_rate);
+    }
+
+    #[test]
+    fn slew_when_target_within_reach_should_stop_on_target() {
+        // Arrange
+        let spec = TurretSpec::default();
+        let command = TurretCommand::Relative(Angle::new(0.05.to_scalar()));
+
+        // Act
+        let angle = slew(&spec, &command, Angle::ZERO, Angle::ZERO);
+
+        // Assert
+        assert_eq!(angle.radians(), 0.05.to_scalar());
+    }
+
+    #[test]
+    fn slew_when_target_across_wrap_point_should_take_shortest_arc() {
+        // Arrange
+        let spec = TurretSpec::default();
+        let current = Angle::new(3.1.to_scalar());
+        let command = TurretCommand::Relative(Angle::new((-3.1).to_scalar()));
+
+        // Act
+        let angle = slew(&spec, &command, Angle::ZERO, current);
+
+        // Assert
+        // going the short way crosses π, so the angle wraps to the negative side
+        assert!(angle.radians().is_negative());
+        assert!(angle.radians() < (-3.1).to_scalar());
+    }
+
+    #[test]
+    fn slew_when_absolute_should_compensate_for_hull_heading() {
+        // Arrange
+        let spec = TurretSpec::default();
+        let hull_angle = Angle::new(0.5.to_scalar());
+        let command = TurretCommand::Absolute(Angle::new(0.5.to_scalar()));
+
+        // Act
+        let angle = slew(&spec, &command, hull_angle, Angle::new(0.03.to_scalar()));
+
+        // Assert
+        // the world-space target is straight ahead of the hull, so the turret re-centres
+        assert_eq!(angle.radians(), 0.0.to_scalar());
+    }
+
+    #[test]
+    fn slew_when_holding_should_keep_relative_angle() {
+        // Arrange
+        let spec = TurretSpec::default();
+
+        // Act
+        let angle = slew(
+            &spec,
+            &TurretCommand::Hold,
+            Angle::new(1.0.to_scalar()),
+            Angle::new(0.3.to_scalar()),
+        );
+
+        // Assert
+        assert_eq!(angle.radians(), 0.3.to_scalar());
+    }
+
+    #[test]
+    fn slew_when_locked_to_heading_should_always_face_forward() {
+        // Arrange
+        let spec = TurretSpec {
+            locked_to_heading: true,
+            ..TurretSpec::default()
+        };
+        let command = TurretCommand::Relative(Angle::new(1.0.to_scalar()));
+
+        // Act
+        let angle = slew(&spec, &command, Angle::ZERO, Angle::new(0.3.to_scalar()));
+
+        // Assert
+        assert_eq!(angle.radians(), 0.0.to_scalar());
+    }
+}
@@ -0,0 +1,129 @@
+//! Python bindings: [`Session`] exported through `pyo3` as the `sim` module, so controllers can
+//! be trained from Python with gym-style `reset`, `act`, `step`, `observe` and `done`.
+//!
+//! Everything structured crosses as JSON strings, as it does for [`Session`], and failures are
+//! raised as `ValueError`s carrying the [`SimError`]'s message.
+
+use crate::config::{self, ValidatedConfig};
+use crate::error::SimError;
+use crate::session::Session;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+fn raise(error: SimError) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+/// A checked match config, built from JSON or left at the defaults.
+#[pyclass(name = "SimConfig", frozen, from_py_object)]
+#[derive(Clone)]
+pub struct SimConfig {
+    config: ValidatedConfig,
+}
+
+#[pymethods]
+impl SimConfig {
+    /// Parses and checks a JSON config, or takes the defaults without one.
+    #[new]
+    #[pyo3(signature = (json = None))]
+    fn new(json: Option<&str>) -> PyResult<Self> {
+        let config = match json {
+            Some(json) => {
+                config::SimConfig::from_json(json).map_err(|errors| raise(errors.into()))?
+            }
+            None => ValidatedConfig::default(),
+        };
+        Ok(SimConfig { config })
+    }
+
+    /// Returns the config as JSON, with every default filled in.
+    fn to_json(&self) -> String {
+        serde_json::to_string(self.config.get()).expect("configs are always serializable")
+    }
+}
+
+/// A match, driven from Python.
+#[pyclass(unsendable)]
+pub struct Simulation {
+    session: Session,
+}
+
+#[pymethods]
+impl Simulation {
+    /// Starts an empty match, with the default config unless one is given.
+    #[new]
+    #[pyo3(signature = (config = None, seed = 0))]
+    fn new(config: Option<SimConfig>, seed: u64) -> Self {
+        let config = config.map(|config| config.config).unwrap_or_default();
+        Simulation {
+            session: Session::with_config(config, seed),
+        }
+    }
+
+    /// Starts the match over, empty, with a new seed.
+    fn reset(&mut self, seed: u64) {
+        self.session.reset(seed);
+    }
+
+    /// Spawns a tank from a JSON `TankSpawn` and returns its ID.
+    fn spawn_tank(&mut self, spawn_json: &str) -> PyResult<u32> {
+        self.session.spawn_tank(spawn_json).map_err(raise)
+    }
+
+    /// Hands a tank to one of the built-in bots, by name.
+    fn load_bot(&mut self, tank_id: u32, name: &str) -> PyResult<()> {
+        self.session.load_bot(tank_id, name).map_err(raise)
+    }
+
+    /// Loads assembled bytecode, bare or packaged, into a tank's VM.
+    fn load_program(&mut self, tank_id: u32, code: &[u8]) -> PyResult<()> {
+        self.session.load_program(tank_id, code).map_err(raise)
+    }
+
+    /// Hands a tank over to a trained network, given as JSON.
+    fn load_network(&mut self, tank_id: u32, network_json: &str) -> PyResult<()> {
+        self.session
+            .load_network(tank_id, network_json)
+            .map_err(raise)
+    }
+
+    /// Queues a JSON array of commands to apply at the start of the next tick.
+    fn act(&mut self, commands_json: &str) -> PyResult<()> {
+        self.session.act(commands_json).map_err(raise)
+    }
+
+    /// Runs some ticks and returns every event they produced, as a JSON array.
+    #[pyo3(signature = (ticks = 1))]
+    fn step(&mut self, ticks: u32) -> String {
+        self.session.step(ticks)
+    }
+
+    /// Returns what a tank knows, as JSON `{ "tank": .., "sensors": .. }`.
+    fn observe(&self, tank_id: u32) -> PyResult<String> {
+        self.session.observe(tank_id).map_err(raise)
+    }
+
+    /// Whether the match is over.
+    #[getter]
+    fn done(&self) -> bool {
+        self.session.is_done()
+    }
+
+    /// The current tick.
+    #[getter]
+    fn tick(&self) -> u64 {
+        self.session.tick()
+    }
+
+    /// Returns the whole match state as JSON.
+    fn get_state(&self) -> String {
+        self.session.get_state()
+    }
+}
+
+#[pymodule]
+fn sim(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<SimConfig>()?;
+    module.add_class::<Simulation>()?;
+    Ok(())
+}
@@ -0,0 +1,392 @@
+use crate::damage::ArmorSide;
+use crate::events::SimEvent;
+use crate::physics::collision::{BoxContact, OrientedBox, box_vs_box};
+use crate::physics::impulse::{self, ImpulseSource};
+use crate::referee::{DamageSource, Judge};
+use crate::spec::SpecTable;
+use crate::state::Tank;
+use crate::util::math::{Scalar, Vec2};
+use fastnum::dec64;
+
+/// Fraction of closing speed tanks bounce apart with. Low, since tanks are heavy and crumple.
+const RAM_RESTITUTION: Scalar = dec64!(0.2);
+
+/// Armor thickness that halves ramming damage. Thicker armor shrugs off more.
+const RAM_ARMOR_REFERENCE: Scalar = dec64!(50);
+
+fn hull(tank: &Tank, specs: &SpecTable) -> Option<OrientedBox> {
+    let spec = specs.tank(tank.loadout.spec_id)?;
+    Some(tank.hull(spec))
+}
+
+/// Returns which side of a hull faces along a world-space direction.
+fn facing_side(tank: &Tank, direction: Vec2) -> ArmorSide {
+    let local = tank.transform().to_local_vector(direction);
+    if local.x.abs() > local.y.abs() {
+        ArmorSide::from_local_normal(Vec2::new(local.x, dec64!(0)))
+    } else {
+        ArmorSide::Side
+    }
+}
+
+fn pair_mut(tanks: &mut [Tank], a: usize, b: usize) -> (&mut Tank, &mut Tank) {
+    if a < b {
+        let (low, high) = tanks.split_at_mut(b);
+        (&mut low[a], &mut high[0])
+    } else {
+        let (low, high) = tanks.split_at_mut(a);
+        (&mut high[0], &mut low[b])
+    }
+}
+
+/// What one collision does to the pair of tanks in it, worked out before any of it is applied.
+struct Outcome {
+    first: usize,
+    second: usize,
+    contact: BoxContact,
+    /// How far each tank is pushed.
+    shifts: [Vec2; 2],
+    /// The impulse the second tank takes; the first takes the opposite.
+    bounce: Option<Vec2>,
+    /// Damage each tank takes, before the referee has its say.
+    damage: [u32; 2],
+}
+
+/// Pushes overlapping tanks apart and damages both parties of a collision.
+///
+/// Tanks are separated in proportion to each other's mass and bounce apart along the contact
+/// normal. Damage is proportional to the energy of the impact, weighted towards the lighter
+/// tank, and reduced by the armor on the side that took it.
+///
+/// Every collision is worked out from where the tanks stood and how they moved before any of
+/// them was resolved, then the results are applied in order of tank ID. So pile-ups come out
+/// the same whatever order the pairs were found in or the tanks were added in.
+///
+/// `pairs` are the slots the broad phase found might be touching. Overlaps are only pushed
+/// apart down to `slop`.
+///
+/// Returns the slots of each pair found touching, with how they touched, the lower ID first.
+pub fn resolve(
+    tanks: &mut [Tank],
+    specs: &SpecTable,
+    pairs: Vec<(usize, usize)>,
+    ram_damage: Scalar,
+    slop: Scalar,
+    judge: &mut Judge,
+    events: &mut Vec<SimEvent>,
+) -> Vec<(usize, usize, BoxContact)> {
+    let mut touching = Vec::new();
+    for (a, b) in pairs {
+        // the same pair gives the same contact whichever slots its tanks are in
+        let (a, b) = if tanks[a].id <= tanks[b].id {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        let (first, second) = (&tanks[a], &tanks[b]);
+        if !first.is_alive() || !second.is_alive() {
+            continue;
+        }
+        let (Some(first_hull), Some(second_hull)) = (hull(first, specs), hull(second, specs))
+        else {
+            continue;
+        };
+        if let Some(contact) = box_vs_box(&first_hull, &second_hull) {
+            touching.push((a, b, contact));
+        }
+    }
+    touching.sort_by_key(|(a, b, _)| (tanks[*a].id, tanks[*b].id));
+
+    let outcomes: Vec<Outcome> = touching
+        .into_iter()
+        .map(|(first, second, contact)| {
+            collide(tanks, specs, first, second, contact, ram_damage, slop)
+        })
+        .collect();
+
+    let mut contacts = Vec::with_capacity(outcomes.len());
+    for outcome in outcomes {
+        let (first, second) = pair_mut(tanks, outcome.first, outcome.second);
+        first.wake();
+        second.wake();
+        first.position = first.position + outcome.shifts[0];
+        second.position = second.position + outcome.shifts[1];
+        if let (Some(bounce), Some(first_spec), Some(second_spec)) = (
+            outcome.bounce,
+            specs.tank(first.loadout.spec_id),
+            specs.tank(second.loadout.spec_id),
+        ) {
+            let (first_id, second_id) = (first.id, second.id);
+            impulse::apply(
+                first,
+                first_spec,
+                Vec2::zero().sub(&bounce),
+                ImpulseSource::Ram(second_id),
+                events,
+            );
+            impulse::apply(
+                second,
+                second_spec,
+                bounce,
+                ImpulseSource::Ram(first_id),
+                events,
+            );
+        }
+
+        let (first_id, second_id) = (first.id, second.id);
+        let hits = [
+            (first, second_id, outcome.damage[0]),
+            (second, first_id, outcome.damage[1]),
+        ];
+        for (tank, rammer_id, damage) in hits {
+            // an earlier collision this tick may have destroyed it already
+            if damage == 0 || !tank.is_alive() {
+                continue;
+            }
+            let (damage, destroyed) = judge.deal(tank, DamageSource::Ram { rammer_id }, damage);
+            if damage == 0 {
+                continue;
+            }
+            events.push(SimEvent::Ram {
+                rammer_id,
+                target_id: tank.id,
+                damage,
+            });
+            if destroyed {
+                events.push(SimEvent::TankDestroyed {
+                    tank_id: tank.id,
+                    killer: Some(rammer_id),
+                });
+            }
+        }
+        contacts.push((outcome.first, outcome.second, outcome.contact));
+    }
+    contacts
+}
+
+/// Works out what a collision does to both tanks, without changing either.
+fn collide(
+    tanks: &[Tank],
+    specs: &SpecTable,
+    first: usize,
+    second: usize,
+    contact: BoxContact,
+    ram_damage: Scalar,
+    slop: Scalar,
+) -> Outcome {
+    let mut outcome = Outcome {
+        first,
+        second,
+        contact,
+        shifts: [Vec2::zero(), Vec2::zero()],
+        bounce: None,
+        damage: [0, 0],
+    };
+    let (first, second) = (&tanks[first], &tanks[second]);
+    let (Some(first_spec), Some(second_spec)) = (
+        specs.tank(first.loadout.spec_id),
+        specs.tank(second.loadout.spec_id),
+    ) else {
+        return outcome;
+    };
+    let total_mass = first_spec.mass + second_spec.mass;
+    let normal = contact.normal;
+
+    // separate, moving the lighter tank further, but leave the slop overlapping so resting
+    // tanks stay in contact
+    let depth = (contact.depth - slop).max(dec64!(0));
+    outcome.shifts = [
+        normal.scale(-depth * second_spec.mass / total_mass),
+        normal.scale(depth * first_spec.mass / total_mass),
+    ];
+
+    let closing_speed = first.velocity.sub(&second.velocity).dot(&normal);
+    if closing_speed <= dec64!(0) {
+        return outcome;
+    }
+    let reduced_mass = first_spec.mass * second_spec.mass / total_mass;
+    outcome.bounce =
+        Some(normal.scale((dec64!(1) + RAM_RESTITUTION) * closing_speed * reduced_mass));
+
+    let energy = reduced_mass * closing_speed * closing_speed / dec64!(2) * ram_damage;
+    let hits = [
+        (first, first_spec, normal, second_spec.mass / total_mass),
+        (
+            second,
+            second_spec,
+            Vec2::zero().sub(&normal),
+            first_spec.mass / total_mass,
+        ),
+    ];
+    for (damage, (tank, spec, towards_rammer, share)) in outcome.damage.iter_mut().zip(hits) {
+        let thickness = facing_side(tank, towards_rammer).thickness(&spec.armor);
+        *damage = (energy * share * RAM_ARMOR_REFERENCE / (RAM_ARMOR_REFERENCE + thickness))
+            .floor()
+            .to_u32()
+            .unwrap_or(0);
+    }
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::broadphase::TankBroadphase;
+    use crate::test_support;
+    use crate::util::math::{Angle, ConvertToScalar};
+    use proptest::prelude::*;
+
+    fn tank(id: u32, x: f64, angle: Scalar, speed: f64) -> Tank {
+        let mut tank = test_support::tank(id, id, Vec2::new_from_f64(x, 100.0));
+        tank.angle = Angle::new(angle);
+        tank.velocity = Vec2::new_from_f64(speed, 0.0);
+        tank
+    }
+
+    #[test]
+    fn resolve_when_ramming_rear_should_hurt_target_more_and_push_apart() {
+        // Arrange
+        // tank 1 drives into the back of tank 2, which faces away
+        let specs = SpecTable::default();
+        let mut tanks = vec![
+            tank(1, 100.0, dec64!(0), 3.0),
+            tank(2, 118.0, dec64!(0), 0.0),
+        ];
+
+        // Act
+        let mut events = Vec::new();
+        let pairs = TankBroadphase::new(dec64!(500), dec64!(500), dec64!(64)).pairs(&tanks, &specs);
+        resolve(
+            &mut tanks,
+            &specs,
+            pairs,
+            dec64!(0.2),
+            dec64!(0),
+            &mut Judge::none(),
+            &mut events,
+        );
+
+        // Assert
+        let damage_to = |target: u32| {
+            events.iter().find_map(|event| match event {
+                SimEvent::Ram {
+                    target_id, damage, ..
+                } if *target_id == target => Some(*damage),
+                _ => None,
+            })
+        };
+        let rammer = damage_to(1).unwrap();
+        let target = damage_to(2).unwrap();
+        assert!(target > rammer);
+        assert_eq!(tanks[1].position.x - tanks[0].position.x, dec64!(20));
+        assert!(tanks[0].velocity.x < tanks[1].velocity.x);
+    }
+
+    #[test]
+    fn resolve_when_separating_should_not_damage() {
+        // Arrange
+        let specs = SpecTable::default();
+        let mut tanks = vec![
+            tank(1, 100.0, dec64!(0), -1.0),
+            tank(2, 118.0, dec64!(0), 1.0),
+        ];
+
+        // Act
+        let mut events = Vec::new();
+        let pairs = TankBroadphase::new(dec64!(500), dec64!(500), dec64!(64)).pairs(&tanks, &specs);
+        resolve(
+            &mut tanks,
+            &specs,
+            pairs,
+            dec64!(0.2),
+            dec64!(0),
+            &mut Judge::none(),
+            &mut events,
+        );
+
+        // Assert
+        assert!(events.is_empty());
+        assert_eq!(tanks[1].position.x - tanks[0].position.x, dec64!(20));
+    }
+
+    #[test]
+    fn resolve_should_leave_slop_overlapping() {
+        // Arrange
+        let specs = SpecTable::default();
+        let mut tanks = vec![
+            tank(1, 100.0, dec64!(0), 0.0),
+            tank(2, 118.0, dec64!(0), 0.0),
+        ];
+
+        // Act
+        let mut events = Vec::new();
+        let pairs = TankBroadphase::new(dec64!(500), dec64!(500), dec64!(64)).pairs(&tanks, &specs);
+        let contacts = resolve(
+            &mut tanks,
+            &specs,
+            pairs,
+            dec64!(0.2),
+            dec64!(0.5),
+            &mut Judge::none(),
+            &mut events,
+        );
+
+        // Assert
+        assert_eq!(contacts.len(), 1);
+        assert_eq!(tanks[1].position.x - tanks[0].position.x, dec64!(19.5));
+    }
+
+    /// Resolves a pile-up of four tanks added in the given order, returning how each ended up
+    /// by ID and what was logged.
+    fn pile_up(order: &[usize]) -> (Vec<Tank>, Vec<SimEvent>) {
+        let specs = SpecTable::default();
+        let starts = [
+            (100.0, 100.0, 4.0),
+            (117.0, 103.0, -2.0),
+            (108.0, 112.0, 0.0),
+            (125.0, 114.0, -3.0),
+        ];
+        let mut tanks: Vec<Tank> = order
+            .iter()
+            .map(|&i| {
+                let (x, y, speed) = starts[i];
+                let mut tank = tank(i as u32, x, dec64!(0), speed);
+                tank.position.y = y.to_scalar();
+                tank
+            })
+            .collect();
+        let pairs = TankBroadphase::new(dec64!(500), dec64!(500), dec64!(64)).pairs(&tanks, &specs);
+        let mut events = Vec::new();
+        resolve(
+            &mut tanks,
+            &specs,
+            pairs,
+            dec64!(0.2),
+            dec64!(0),
+            &mut Judge::none(),
+            &mut events,
+        );
+        tanks.sort_by_key(|tank| tank.id);
+        (tanks, events)
+    }
+
+    proptest! {
+        #[test]
+        fn resolve_should_not_depend_on_insertion_order(
+            order in Just(vec![0usize, 1, 2, 3]).prop_shuffle(),
+        ) {
+            // Act
+            let (shuffled, shuffled_events) = pile_up(&order);
+            let (sorted, sorted_events) = pile_up(&[0, 1, 2, 3]);
+
+            // Assert
+            let rams = sorted_events
+                .iter()
+                .filter(|event| matches!(event, SimEvent::Ram { .. }))
+                .count();
+            prop_assert!(rams > 2, "only {} rams", rams);
+            prop_assert_eq!(shuffled, sorted);
+            prop_assert_eq!(shuffled_events, sorted_events);
+        }
+    }
+}
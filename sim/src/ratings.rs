@@ -0,0 +1,347 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+/// How ratings move after a match.
+///
+/// Ratings live outside the simulation, so unlike match state they're plain floats.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RatingModel {
+    /// Classic Elo: one number per bot, moved by at most `k_factor` per match.
+    Elo { k_factor: f64 },
+    /// A two-player TrueSkill-style update, tracking how sure each rating is so new bots settle
+    /// quickly and established ones stay put.
+    Skill {
+        /// Performance spread within a single match.
+        beta: f64,
+        /// Uncertainty added before every match, so ratings can follow bots that change.
+        dynamics: f64,
+    },
+}
+
+impl Default for RatingModel {
+    fn default() -> Self {
+        RatingModel::Elo { k_factor: 32.0 }
+    }
+}
+
+impl RatingModel {
+    /// Returns the rating a bot starts the ladder on.
+    pub fn initial(&self) -> Rating {
+        match self {
+            RatingModel::Elo { .. } => Rating {
+                mean: 1500.0,
+                deviation: 0.0,
+                ..Rating::default()
+            },
+            RatingModel::Skill { .. } => Rating {
+                mean: 25.0,
+                deviation: 25.0 / 3.0,
+                ..Rating::default()
+            },
+        }
+    }
+}
+
+/// A bot's standing on the ladder.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Rating {
+    pub mean: f64,
+    /// How unsure the rating is. Always zero under Elo.
+    pub deviation: f64,
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+impl Rating {
+    /// Returns a rating the bot is very likely to be at least as good as, for ranking bots that
+    /// haven't played much below ones that have proven themselves.
+    pub fn conservative(&self) -> f64 {
+        self.mean - 3.0 * self.deviation
+    }
+
+    pub fn played(&self) -> u32 {
+        self.wins + self.losses + self.draws
+    }
+}
+
+/// A match result, from the first bot's point of view.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Outcome {
+    Win,
+    Loss,
+    Draw,
+}
+
+impl Outcome {
+    /// Reads the result of a match between two teams from its winner, e.g.
+    /// [`ModeState::winner`](crate::modes::ModeState::winner).
+    pub fn of_team(team_id: u32, winner: Option<u32>) -> Self {
+        match winner {
+            Some(winner) if winner == team_id => Outcome::Win,
+            Some(_) => Outcome::Loss,
+            None => Outcome::Draw,
+        }
+    }
+
+    fn reversed(self) -> Self {
+        match self {
+            Outcome::Win => Outcome::Loss,
+            Outcome::Loss => Outcome::Win,
+            Outcome::Draw => Outcome::Draw,
+        }
+    }
+
+    fn score(self) -> f64 {
+        match self {
+            Outcome::Win => 1.0,
+            Outcome::Loss => 0.0,
+            Outcome::Draw => 0.5,
+        }
+    }
+}
+
+/// Problems reading or writing a ladder file.
+#[derive(Debug)]
+pub enum LadderError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+}
+
+impl fmt::Display for LadderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LadderError::Io(error) => write!(f, "couldn't access ladder file: {error}"),
+            LadderError::Parse(error) => write!(f, "invalid ladder file: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for LadderError {}
+
+/// Ratings for every bot in a tournament, kept between runs in a JSON ladder file.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Ladder {
+    pub model: RatingModel,
+    /// Ratings by bot name.
+    pub ratings: BTreeMap<String, Rating>,
+}
+
+impl Ladder {
+    pub fn new(model: RatingModel) -> Self {
+        Ladder {
+            model,
+            ratings: BTreeMap::new(),
+        }
+    }
+
+    /// Returns a bot's rating, or the starting rating if it hasn't played yet.
+    pub fn rating(&self, bot: &str) -> Rating {
+        self.ratings
+            .get(bot)
+            .cloned()
+            .unwrap_or_else(|| self.model.initial())
+    }
+
+    /// Enters a bot on the ladder without it playing.
+    pub fn add(&mut self, bot: &str) {
+        if !self.ratings.contains_key(bot) {
+            self.ratings.insert(bot.to_string(), self.model.initial());
+        }
+    }
+
+    /// Updates both bots' ratings after they played each other.
+    pub fn record(&mut self, first: &str, second: &str, outcome: Outcome) {
+        let mut a = self.rating(first);
+        let mut b = self.rating(second);
+        match self.model {
+            RatingModel::Elo { k_factor } => {
+                let expected = 1.0 / (1.0 + 10f64.powf((b.mean - a.mean) / 400.0));
+                let change = k_factor * (outcome.score() - expected);
+                a.mean += change;
+                b.mean -= change;
+            }
+            RatingModel::Skill { beta, dynamics } => {
+                skill_update(&mut a, &mut b, outcome, beta, dynamics);
+            }
+        }
+        tally(&mut a, outcome);
+        tally(&mut b, outcome.reversed());
+        self.ratings.insert(first.to_string(), a);
+        self.ratings.insert(second.to_string(), b);
+    }
+
+    /// Returns the bots best first, by conservative rating and then by name.
+    pub fn standings(&self) -> Vec<(&str, &Rating)> {
+        let mut standings: Vec<(&str, &Rating)> = self
+            .ratings
+            .iter()
+            .map(|(bot, rating)| (bot.as_str(), rating))
+            .collect();
+        standings.sort_by(|(a_bot, a), (b_bot, b)| {
+            b.conservative()
+                .total_cmp(&a.conservative())
+                .then_with(|| a_bot.cmp(b_bot))
+        });
+        standings
+    }
+
+    /// Pairs up bots for the next ladder round, each against its neighbour in the standings.
+    ///
+    /// Close matches tell the ratings the most, so this converges in far fewer matches than a
+    /// round robin. With an odd number of bots, the lowest ranked one sits the round out.
+    pub fn pairings(&self) -> Vec<(String, String)> {
+        self.standings()
+            .chunks_exact(2)
+            .map(|pair| (pair[0].0.to_string(), pair[1].0.to_string()))
+            .collect()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("ladder is always serializable")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, LadderError> {
+        serde_json::from_str(json).map_err(LadderError::Parse)
+    }
+
+    /// Reads a ladder file, or starts an empty ladder if there isn't one yet.
+    pub fn load(path: impl AsRef<Path>, model: RatingModel) -> Result<Self, LadderError> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => Ladder::from_json(&json),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Ladder::new(model)),
+            Err(error) => Err(LadderError::Io(error)),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), LadderError> {
+        std::fs::write(path, self.to_json()).map_err(LadderError::Io)
+    }
+}
+
+fn tally(rating: &mut Rating, outcome: Outcome) {
+    match outcome {
+        Outcome::Win => rating.wins += 1,
+        Outcome::Loss => rating.losses += 1,
+        Outcome::Draw => rating.draws += 1,
+    }
+}
+
+/// Moves both ratings towards the result, by more the less sure they are, and makes them surer.
+fn skill_update(a: &mut Rating, b: &mut Rating, outcome: Outcome, beta: f64, dynamics: f64) {
+    let a_var = a.deviation.powi(2) + dynamics.powi(2);
+    let b_var = b.deviation.powi(2) + dynamics.powi(2);
+    let c = (2.0 * beta.powi(2) + a_var + b_var).sqrt();
+    // `v` is how far the means move, `w` how much the variances shrink, both from the first
+    // bot's side; a draw is the limit of an ever narrower draw margin
+    let t = (a.mean - b.mean) / c;
+    let (v, w) = match outcome {
+        Outcome::Win => {
+            let v = normal_pdf(t) / normal_cdf(t);
+            (v, v * (v + t))
+        }
+        Outcome::Loss => {
+            let v = normal_pdf(-t) / normal_cdf(-t);
+            (-v, v * (v - t))
+        }
+        Outcome::Draw => (-t, 1.0),
+    };
+    a.mean += a_var / c * v;
+    b.mean -= b_var / c * v;
+    a.deviation = (a_var * (1.0 - a_var / c.powi(2) * w)).max(0.0).sqrt();
+    b.deviation = (b_var * (1.0 - b_var / c.powi(2) * w)).max(0.0).sqrt();
+}
+
+fn normal_pdf(x: f64) -> f64 {
+    (-x * x / 2.0).exp() / (2.0 * std::f64::consts::PI).sqrt()
+}
+
+fn normal_cdf(x: f64) -> f64 {
+    0.5 * (1.0 + erf(x / std::f64::consts::SQRT_2))
+}
+
+/// Abramowitz and Stegun 7.1.26, good to about 1e-7.
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let poly = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let y = 1.0 - poly * (-x * x).exp();
+    if x < 0.0 { -y } else { y }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_under_elo_should_move_ratings_evenly() {
+        // Arrange
+        let mut ladder = Ladder::new(RatingModel::Elo { k_factor: 32.0 });
+
+        // Act
+        ladder.record("rammer", "sniper", Outcome::Win);
+
+        // Assert
+        let (winner, loser) = (ladder.rating("rammer"), ladder.rating("sniper"));
+        assert_eq!(winner.mean, 1516.0);
+        assert_eq!(loser.mean, 1484.0);
+        assert_eq!((winner.wins, loser.losses), (1, 1));
+    }
+
+    #[test]
+    fn record_under_skill_should_favour_winner_and_shrink_deviation() {
+        // Arrange
+        let mut ladder = Ladder::new(RatingModel::Skill {
+            beta: 25.0 / 6.0,
+            dynamics: 25.0 / 300.0,
+        });
+
+        // Act
+        ladder.record("rammer", "sniper", Outcome::Win);
+        ladder.record("camper", "turtle", Outcome::Draw);
+
+        // Assert
+        let (winner, loser) = (ladder.rating("rammer"), ladder.rating("sniper"));
+        assert!((winner.mean - 29.2).abs() < 0.1, "{winner:?}");
+        assert!((loser.mean - 20.8).abs() < 0.1, "{loser:?}");
+        assert!(winner.deviation < 25.0 / 3.0);
+        let (camper, turtle) = (ladder.rating("camper"), ladder.rating("turtle"));
+        assert_eq!(camper.mean, turtle.mean);
+        assert!(camper.deviation < 25.0 / 3.0);
+    }
+
+    #[test]
+    fn pairings_should_match_neighbours_and_sit_out_the_last() {
+        // Arrange
+        let mut ladder = Ladder::default();
+        for bot in ["a", "b", "c", "d", "e"] {
+            ladder.add(bot);
+        }
+        ladder.record("d", "e", Outcome::Win);
+        ladder.record("c", "b", Outcome::Win);
+
+        // Act
+        let pairings = ladder.pairings();
+
+        // Assert
+        let pair = |a: &str, b: &str| (a.to_string(), b.to_string());
+        assert_eq!(pairings, vec![pair("c", "d"), pair("a", "b")]);
+    }
+
+    #[test]
+    fn from_json_should_read_back_saved_ladder() {
+        // Arrange
+        let mut ladder = Ladder::default();
+        ladder.record("rammer", "sniper", Outcome::Draw);
+
+        // Act
+        let loaded = Ladder::from_json(&ladder.to_json());
+
+        // Assert
+        assert_eq!(loaded.unwrap(), ladder);
+        assert!(matches!(Ladder::from_json("{"), Err(LadderError::Parse(_))));
+    }
+}
@@ -0,0 +1,397 @@
+use crate::events::SimEvent;
+use crate::explosions::ExplosionCause;
+use crate::rules::{FriendlyFire, Handicap, MatchConfig};
+use crate::state::{SimState, Tank};
+use crate::util::math::Scalar;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// What dealt some damage.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DamageSource {
+    /// A projectile hit the tank directly.
+    Bullet {
+        bullet_id: u32,
+        shooter: Option<u32>,
+    },
+    /// The tank was caught in a blast.
+    Explosion {
+        cause: ExplosionCause,
+        attacker: Option<u32>,
+    },
+    /// Another tank drove into it.
+    Ram { rammer_id: u32 },
+    /// It was caught outside the safe zone.
+    Zone,
+    /// It was on fire, set alight by `attacker` if anyone.
+    Burn { attacker: Option<u32> },
+}
+
+/// Damage about to be dealt to a tank, for a [`Referee`] to judge.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Damage {
+    pub target_id: u32,
+    pub target_team: u32,
+    pub source: DamageSource,
+    /// The tank to credit, if any. See [`DamageSource::attacker`].
+    pub attacker: Option<u32>,
+    pub attacker_team: Option<u32>,
+    /// Damage the rules would deal, after armor and falloff.
+    pub amount: u32,
+}
+
+impl Damage {
+    /// Returns whether the damage comes from a teammate.
+    pub fn is_friendly(&self) -> bool {
+        self.attacker_team == Some(self.target_team) && self.attacker != Some(self.target_id)
+    }
+}
+
+/// House rules layered over the built-in ones, for embedders who want to change how a match
+/// plays out without forking the engine.
+///
+/// Every callback has a default that leaves the match alone, so a referee only implements the
+/// ones it needs. Callbacks run at fixed points in the tick, in a fixed order, so a referee
+/// that decides only from what it's given keeps the match deterministic.
+pub trait Referee: Send {
+    /// Called near the end of every tick, after objectives are updated and before sensors are,
+    /// to change the state directly. Tanks it destroys should be reported with
+    /// [`SimEvent::TankDestroyed`] so they respawn and count as kills.
+    fn on_tick(&mut self, _state: &mut SimState, _events: &mut Vec<SimEvent>) {}
+
+    /// Decides how much damage is actually dealt; zero vetoes it outright.
+    fn on_damage(&mut self, damage: &Damage) -> u32 {
+        damage.amount
+    }
+
+    /// Decides whether a tank whose health just ran out is destroyed. Vetoed deaths leave it on
+    /// one health.
+    fn on_death(&mut self, _tank_id: u32, _cause: &Damage) -> bool {
+        true
+    }
+
+    /// Sees every event of the tick once it has finished, in order.
+    fn on_event(&mut self, _state: &SimState, _event: &SimEvent) {}
+}
+
+impl DamageSource {
+    /// Returns the tank to credit, if any: the shooter of a bullet or of the bullet that set
+    /// off a blast, the rammer, or whoever set the tank alight.
+    pub fn attacker(&self) -> Option<u32> {
+        match *self {
+            DamageSource::Bullet { shooter, .. } => shooter,
+            DamageSource::Explosion { attacker, .. } => attacker,
+            DamageSource::Ram { rammer_id } => Some(rammer_id),
+            DamageSource::Burn { attacker } => attacker,
+            DamageSource::Zone => None,
+        }
+    }
+}
+
+/// A blow one tank dealt a teammate, after the friendly-fire policy was applied.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FriendlyHit {
+    pub attacker: u32,
+    pub target_id: u32,
+    pub source: DamageSource,
+    /// Damage the teammate took, or with `reflected`, the damage owed by the attacker instead.
+    pub damage: u32,
+    pub reflected: bool,
+}
+
+/// Applies damage on behalf of a tick's phases, enforcing the friendly-fire policy and asking
+/// the referee, if there is one, to judge it first.
+pub struct Judge<'a> {
+    referee: Option<&'a mut (dyn Referee + 'static)>,
+    teams: BTreeMap<u32, u32>,
+    friendly_fire: FriendlyFire,
+    /// Damage multipliers of handicapped teams.
+    damage_factors: BTreeMap<u32, Scalar>,
+    friendly_hits: Vec<FriendlyHit>,
+}
+
+impl<'a> Judge<'a> {
+    /// Deals damage exactly as the built-in rules say, with friendly fire in full.
+    pub fn none() -> Self {
+        Judge {
+            referee: None,
+            teams: BTreeMap::new(),
+            friendly_fire: FriendlyFire::Full,
+            damage_factors: BTreeMap::new(),
+            friendly_hits: Vec::new(),
+        }
+    }
+
+    /// Scales blows by the attacking team's handicap, applies the rules' friendly-fire policy
+    /// to blows between teammates, and asks the referee about each one.
+    pub fn new(
+        referee: Option<&'a mut (dyn Referee + 'static)>,
+        tanks: &[Tank],
+        rules: &MatchConfig,
+    ) -> Self {
+        Judge {
+            referee,
+            teams: tanks.iter().map(|tank| (tank.id, tank.team_id)).collect(),
+            friendly_fire: rules.friendly_fire,
+            damage_factors: rules
+                .handicaps
+                .iter()
+                .map(|(&team_id, handicap)| (team_id, handicap.damage))
+                .collect(),
+            friendly_hits: Vec::new(),
+        }
+    }
+
+    /// Takes up to `amount` health from a living tank, returning how much was dealt and
+    /// whether the tank was destroyed.
+    ///
+    /// Blows are scaled by the attacking team's handicap, and then blows from a teammate by
+    /// the friendly-fire policy, before the referee sees them. Blows from teammates are
+    /// recorded for [`Judge::into_friendly_hits`].
+    pub fn deal(&mut self, tank: &mut Tank, source: DamageSource, amount: u32) -> (u32, bool) {
+        let attacker = source.attacker();
+        let attacker_team = attacker.and_then(|tank_id| self.teams.get(&tank_id).copied());
+        let amount = match attacker_team.and_then(|team_id| self.damage_factors.get(&team_id)) {
+            Some(factor) => Handicap::scale(amount, *factor),
+            None => amount,
+        };
+        let mut damage = Damage {
+            target_id: tank.id,
+            target_team: tank.team_id,
+            source,
+            attacker,
+            attacker_team,
+            amount,
+        };
+        let friendly = attacker.filter(|_| damage.is_friendly());
+        if friendly.is_some() {
+            damage.amount = self.friendly_fire.scale(amount);
+        }
+        let (dealt, destroyed) = self.judge(tank, damage);
+        if let Some(attacker) = friendly {
+            let reflected = self.friendly_fire == FriendlyFire::Reflected;
+            self.friendly_hits.push(FriendlyHit {
+                attacker,
+                target_id: tank.id,
+                source,
+                damage: if reflected { amount } else { dealt },
+                reflected,
+            });
+        }
+        (dealt, destroyed)
+    }
+
+    /// Returns the blows dealt to teammates so far, in order. Reflected ones are still owed by
+    /// their attackers.
+    pub fn into_friendly_hits(self) -> Vec<FriendlyHit> {
+        self.friendly_hits
+    }
+
+    fn judge(&mut self, tank: &mut Tank, mut damage: Damage) -> (u32, bool) {
+        let Some(referee) = self.referee.as_deref_mut() else {
+            tank.health = tank.health.saturating_sub(damage.amount);
+            return (damage.amount, !tank.is_alive());
+        };
+        damage.amount = referee.on_damage(&damage);
+        tank.health = tank.health.saturating_sub(damage.amount);
+        if tank.is_alive() || damage.amount == 0 {
+            return (damage.amount, false);
+        }
+        if !referee.on_death(tank.id, &damage) {
+            tank.health = 1;
+            return (damage.amount, false);
+        }
+        (damage.amount, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SimConfig;
+    use crate::sim::{SimEngine, TankSpawn};
+    use crate::util::math::Vec2;
+    use fastnum::dec64;
+
+    /// No friendly fire, and nobody dies.
+    #[derive(Default)]
+    struct HouseRules {
+        deaths_vetoed: u32,
+    }
+
+    impl Referee for HouseRules {
+        fn on_damage(&mut self, damage: &Damage) -> u32 {
+            if damage.is_friendly() {
+                0
+            } else {
+                damage.amount
+            }
+        }
+
+        fn on_death(&mut self, _tank_id: u32, _cause: &Damage) -> bool {
+            self.deaths_vetoed += 1;
+            false
+        }
+    }
+
+    fn spawn(engine: &mut SimEngine, team_id: u32, x: f64) -> u32 {
+        engine
+            .spawn_tank(TankSpawn::medium(team_id, Vec2::new_from_f64(x, 0.0)))
+            .unwrap()
+    }
+
+    #[test]
+    fn deal_when_referee_vetoes_death_should_leave_one_health() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(3));
+        let tank = spawn(&mut engine, 0, 0.0);
+        let mut referee = HouseRules::default();
+        let mut judge = Judge::new(
+            Some(&mut referee),
+            &engine.state().tanks,
+            &MatchConfig::default(),
+        );
+        let mut target = engine.state().tank(tank).unwrap().clone();
+
+        // Act
+        let source = DamageSource::Explosion {
+            cause: ExplosionCause::External,
+            attacker: None,
+        };
+        let (dealt, destroyed) = judge.deal(&mut target, source, 10_000);
+
+        // Assert
+        assert_eq!((dealt, destroyed), (10_000, false));
+        assert_eq!(target.health, 1);
+        assert_eq!(referee.deaths_vetoed, 1);
+    }
+
+    #[test]
+    fn step_with_referee_should_veto_friendly_fire() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(3));
+        let shooter = spawn(&mut engine, 0, 0.0);
+        let teammate = spawn(&mut engine, 0, 100.0);
+        let mut plain = SimEngine::new(engine.state().clone());
+        engine.set_referee(Some(Box::new(HouseRules::default())));
+
+        // Act
+        let mut hits = 0;
+        for engine in [&mut engine, &mut plain] {
+            engine.set_fire(shooter, Some(0)).unwrap();
+            for _ in 0..20 {
+                engine.step();
+                hits += engine
+                    .events()
+                    .iter()
+                    .filter(|event| matches!(event, SimEvent::Hit { .. }))
+                    .count();
+            }
+        }
+
+        // Assert
+        let max_health = engine.specs().tank(1).unwrap().max_health;
+        assert_eq!(hits, 2);
+        assert_eq!(engine.state().tank(teammate).unwrap().health, max_health);
+        assert!(plain.state().tank(teammate).unwrap().health < max_health);
+    }
+
+    #[test]
+    fn deal_when_friendly_fire_reduced_should_scale_and_record_blow() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(3));
+        let shooter = spawn(&mut engine, 0, 0.0);
+        let teammate = spawn(&mut engine, 0, 100.0);
+        let tanks = &engine.state().tanks;
+        let rules = MatchConfig {
+            friendly_fire: FriendlyFire::Reduced { percent: 25 },
+            ..MatchConfig::default()
+        };
+        let mut judge = Judge::new(None, tanks, &rules);
+        let mut target = engine.state().tank(teammate).unwrap().clone();
+        let health = target.health;
+        let source = DamageSource::Ram { rammer_id: shooter };
+
+        // Act
+        let (dealt, destroyed) = judge.deal(&mut target, source, 10);
+
+        // Assert
+        assert_eq!((dealt, destroyed), (2, false));
+        assert_eq!(target.health, health - 2);
+        assert_eq!(
+            judge.into_friendly_hits(),
+            vec![FriendlyHit {
+                attacker: shooter,
+                target_id: teammate,
+                source,
+                damage: 2,
+                reflected: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn deal_when_attacker_handicapped_should_scale_blow() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(3));
+        let weak = spawn(&mut engine, 0, 0.0);
+        let strong = spawn(&mut engine, 1, 100.0);
+        let mut rules = MatchConfig::default();
+        rules.handicaps.insert(
+            0,
+            Handicap {
+                damage: dec64!(0.5),
+                ..Handicap::default()
+            },
+        );
+        let tanks = &engine.state().tanks;
+        let mut judge = Judge::new(None, tanks, &rules);
+        let mut weak_tank = engine.state().tank(weak).unwrap().clone();
+        let mut strong_tank = engine.state().tank(strong).unwrap().clone();
+
+        // Act
+        let (from_weak, _) =
+            judge.deal(&mut strong_tank, DamageSource::Ram { rammer_id: weak }, 15);
+        let (from_strong, _) =
+            judge.deal(&mut weak_tank, DamageSource::Ram { rammer_id: strong }, 15);
+
+        // Assert
+        assert_eq!(from_weak, 7);
+        assert_eq!(from_strong, 15);
+    }
+
+    #[test]
+    fn step_when_friendly_fire_reflected_should_damage_shooter_instead() {
+        // Arrange
+        let mut config = SimConfig::default();
+        config.rules.friendly_fire = FriendlyFire::Reflected;
+        let mut engine = SimEngine::from_config(SimState::new(3), &config.validate().unwrap());
+        let shooter = spawn(&mut engine, 0, 0.0);
+        let teammate = spawn(&mut engine, 0, 100.0);
+        engine.set_fire(shooter, Some(0)).unwrap();
+
+        // Act
+        let mut reflected = Vec::new();
+        for _ in 0..20 {
+            engine.step();
+            reflected.extend(engine.events().iter().filter_map(|event| match *event {
+                SimEvent::FriendlyFire {
+                    attacker,
+                    target_id,
+                    damage,
+                    reflected: true,
+                } => Some((attacker, target_id, damage)),
+                _ => None,
+            }));
+        }
+
+        // Assert
+        let max_health = engine.specs().tank(1).unwrap().max_health;
+        let shooter_health = engine.state().tank(shooter).unwrap().health;
+        assert_eq!(reflected.len(), 1);
+        assert_eq!(reflected[0].0, shooter);
+        assert_eq!(reflected[0].1, teammate);
+        assert_eq!(shooter_health, max_health - reflected[0].2);
+        assert_eq!(engine.state().tank(teammate).unwrap().health, max_health);
+    }
+}
@@ -0,0 +1,533 @@
+use crate::clock::SimClock;
+use crate::commands::Command;
+use crate::events::SimEvent;
+use crate::state::SimState;
+use crate::telemetry::TelemetryFrame;
+use crate::util::math::{Angle, ConvertToScalar, Scalar, Vec2};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+/// Marks the start and end of a replay file.
+const MAGIC: &[u8; 4] = b"ATRP";
+const VERSION: u32 = 12;
+/// Magic and version, at the very start of the file.
+const HEADER_LEN: usize = 4 + 4;
+/// Magic, index offset and keyframe interval, at the very end of the file.
+const FOOTER_LEN: usize = 4 + 8 + 8;
+
+const KEYFRAME_RECORD: u8 = 0;
+const FRAME_RECORD: u8 = 1;
+const INPUT_RECORD: u8 = 2;
+
+/// A recorded match: what every tick looked like, plus a full snapshot every so often.
+///
+/// Frames are enough to draw the match at any tick. Keyframes hold everything else, such as
+/// program memory, for tools that need the whole state at a point in the match. Inputs are the
+/// commands queued from outside, such as a human's driving, which together with the first
+/// keyframe and the same controllers are enough to play the match again.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    keyframe_interval: u64,
+    /// Full states at the end of every `keyframe_interval`-th tick, oldest first.
+    keyframes: Vec<SimState>,
+    /// One frame per tick, oldest first.
+    frames: Vec<TelemetryFrame>,
+    /// Commands queued from outside for each tick that had any, by the tick they led to,
+    /// oldest first.
+    inputs: Vec<(u64, Vec<Command>)>,
+}
+
+impl Replay {
+    pub fn new(keyframe_interval: u64) -> Self {
+        Replay {
+            keyframe_interval: keyframe_interval.max(1),
+            keyframes: Vec::new(),
+            frames: Vec::new(),
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Adds the state at the end of a tick, along with the tick's events and the commands
+    /// queued from outside that it started with.
+    pub fn record(
+        &mut self,
+        state: &SimState,
+        events: &[SimEvent],
+        inputs: &[Command],
+        clock: &SimClock,
+    ) {
+        if !inputs.is_empty() {
+            self.inputs.push((state.time, inputs.to_vec()));
+        }
+        if state.time.is_multiple_of(self.keyframe_interval) || self.keyframes.is_empty() {
+            self.keyframes.push(state.clone());
+        }
+        self.frames
+            .push(TelemetryFrame::capture(state, events, clock));
+    }
+
+    /// Drops everything recorded after the given tick, so recording can carry on from there.
+    pub fn truncate_after(&mut self, tick: u64) {
+        self.keyframes.retain(|keyframe| keyframe.time <= tick);
+        self.frames.retain(|frame| frame.tick <= tick);
+        self.inputs.retain(|(time, _)| *time <= tick);
+    }
+
+    pub fn keyframe_interval(&self) -> u64 {
+        self.keyframe_interval
+    }
+
+    /// Returns the first and last recorded ticks, or `None` if nothing has been recorded.
+    pub fn tick_range(&self) -> Option<(u64, u64)> {
+        Some((self.frames.first()?.tick, self.frames.last()?.tick))
+    }
+
+    /// Returns the frame recorded at the given tick.
+    pub fn frame(&self, tick: u64) -> Option<&TelemetryFrame> {
+        let first = self.frames.first()?.tick;
+        self.frames.get(tick.checked_sub(first)? as usize)
+    }
+
+    /// Returns the commands queued from outside that the given tick started with. Queue them
+    /// again before stepping to that tick to play the match back.
+    pub fn inputs_at(&self, tick: u64) -> &[Command] {
+        match self.inputs.binary_search_by_key(&tick, |(time, _)| *time) {
+            Ok(index) => &self.inputs[index].1,
+            Err(_) => &[],
+        }
+    }
+
+    /// Returns the latest full state recorded at or before the given tick.
+    pub fn keyframe_at(&self, tick: u64) -> Option<&SimState> {
+        let index = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.time <= tick);
+        index.checked_sub(1).map(|index| &self.keyframes[index])
+    }
+
+    /// Writes the replay in its file format: a header, then each tick's keyframe and inputs
+    /// (if it has them) and frame as length-prefixed records, then an index of where each keyframe
+    /// starts, so readers can seek without decoding what comes before.
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+
+        let mut index = Vec::with_capacity(self.keyframes.len());
+        let mut keyframes = self.keyframes.iter().peekable();
+        let mut inputs = self.inputs.iter().peekable();
+        for frame in &self.frames {
+            while let Some(keyframe) = keyframes.next_if(|keyframe| keyframe.time <= frame.tick) {
+                index.push((keyframe.time, bytes.len() as u64));
+                write_record(&mut bytes, KEYFRAME_RECORD, keyframe)?;
+            }
+            while let Some(input) = inputs.next_if(|(time, _)| *time <= frame.tick) {
+                write_record(&mut bytes, INPUT_RECORD, input)?;
+            }
+            write_record(&mut bytes, FRAME_RECORD, frame)?;
+        }
+
+        let index_offset = bytes.len() as u64;
+        bytes.extend_from_slice(&(index.len() as u64).to_le_bytes());
+        for (tick, offset) in index {
+            bytes.extend_from_slice(&tick.to_le_bytes());
+            bytes.extend_from_slice(&offset.to_le_bytes());
+        }
+        bytes.extend_from_slice(&index_offset.to_le_bytes());
+        bytes.extend_from_slice(&self.keyframe_interval.to_le_bytes());
+        bytes.extend_from_slice(MAGIC);
+        Ok(bytes)
+    }
+
+    /// Reads a whole replay written by [`Replay::to_bytes`]. Use [`ReplayReader`] to look at
+    /// parts of a long one without decoding all of it.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let reader = ReplayReader::open(bytes)?;
+        let mut replay = Replay::new(reader.keyframe_interval);
+        let mut offset = HEADER_LEN;
+        while offset < reader.index_offset {
+            let (kind, payload, next) = reader.record(offset)?;
+            match kind {
+                KEYFRAME_RECORD => replay.keyframes.push(decode(payload)?),
+                INPUT_RECORD => replay.inputs.push(decode(payload)?),
+                _ => replay.frames.push(decode(payload)?),
+            }
+            offset = next;
+        }
+        Ok(replay)
+    }
+}
+
+fn write_record<T: Serialize>(bytes: &mut Vec<u8>, kind: u8, value: &T) -> io::Result<()> {
+    let payload = bincode::serialize(value).map_err(io::Error::other)?;
+    bytes.push(kind);
+    bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&payload);
+    Ok(())
+}
+
+fn decode<T: DeserializeOwned>(payload: &[u8]) -> io::Result<T> {
+    bincode::deserialize(payload).map_err(io::Error::other)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> io::Result<u64> {
+    let chunk = bytes
+        .get(offset..offset + 8)
+        .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+    Ok(u64::from_le_bytes(
+        chunk.try_into().expect("slice is 8 bytes"),
+    ))
+}
+
+/// Random access into a replay file, through its keyframe index.
+///
+/// Getting at any tick decodes only the keyframe before it and the frames in between, so the
+/// cost stays bounded by the keyframe interval however long the match ran.
+pub struct ReplayReader<'a> {
+    bytes: &'a [u8],
+    keyframe_interval: u64,
+    index_offset: usize,
+    /// Tick and byte offset of each keyframe, in tick order.
+    index: Vec<(u64, usize)>,
+}
+
+impl<'a> ReplayReader<'a> {
+    /// Checks the header and footer and loads the index.
+    pub fn open(bytes: &'a [u8]) -> io::Result<Self> {
+        if bytes.len() < HEADER_LEN + FOOTER_LEN
+            || &bytes[..4] != MAGIC
+            || &bytes[bytes.len() - 4..] != MAGIC
+        {
+            return Err(invalid("not a replay file"));
+        }
+        let version = u32::from_le_bytes(bytes[4..8].try_into().expect("slice is 4 bytes"));
+        if version != VERSION {
+            return Err(invalid("unsupported replay version"));
+        }
+        let footer = bytes.len() - FOOTER_LEN;
+        let index_offset = read_u64(bytes, footer)? as usize;
+        let keyframe_interval = read_u64(bytes, footer + 8)?;
+        if index_offset > footer {
+            return Err(invalid("replay index out of bounds"));
+        }
+
+        let count = read_u64(bytes, index_offset)? as usize;
+        let mut index = Vec::with_capacity(count.min(footer));
+        for entry in 0..count {
+            let at = index_offset + 8 + entry * 16;
+            let offset = read_u64(bytes, at + 8)? as usize;
+            if offset >= index_offset {
+                return Err(invalid("replay index out of bounds"));
+            }
+            index.push((read_u64(bytes, at)?, offset));
+        }
+        Ok(ReplayReader {
+            bytes,
+            keyframe_interval,
+            index_offset,
+            index,
+        })
+    }
+
+    pub fn keyframe_interval(&self) -> u64 {
+        self.keyframe_interval
+    }
+
+    /// Returns the ticks that have keyframes, in order.
+    pub fn keyframe_ticks(&self) -> impl Iterator<Item = u64> + '_ {
+        self.index.iter().map(|(tick, _)| *tick)
+    }
+
+    /// Returns the kind and payload of the record at an offset, and where the next one starts.
+    fn record(&self, offset: usize) -> io::Result<(u8, &'a [u8], usize)> {
+        let header = self
+            .bytes
+            .get(offset..offset + 5)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+        let length = u32::from_le_bytes(header[1..].try_into().expect("slice is 4 bytes")) as usize;
+        let end = offset + 5 + length;
+        if end > self.index_offset {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok((header[0], &self.bytes[offset + 5..end], end))
+    }
+
+    /// Returns the offset of the latest keyframe at or before the tick.
+    fn keyframe_offset(&self, tick: u64) -> Option<usize> {
+        let index = self.index.partition_point(|(time, _)| *time <= tick);
+        index.checked_sub(1).map(|index| self.index[index].1)
+    }
+
+    /// Decodes the latest full state recorded at or before the given tick.
+    pub fn keyframe_at(&self, tick: u64) -> io::Result<Option<SimState>> {
+        let Some(offset) = self.keyframe_offset(tick) else {
+            return Ok(None);
+        };
+        let (_, payload, _) = self.record(offset)?;
+        decode(payload).map(Some)
+    }
+
+    /// Decodes the frame recorded at the given tick, scanning forward from the keyframe before
+    /// it.
+    pub fn frame(&self, tick: u64) -> io::Result<Option<TelemetryFrame>> {
+        let Some(mut offset) = self.keyframe_offset(tick) else {
+            return Ok(None);
+        };
+        while offset < self.index_offset {
+            let (kind, payload, next) = self.record(offset)?;
+            offset = next;
+            if kind != FRAME_RECORD {
+                continue;
+            }
+            let frame: TelemetryFrame = decode(payload)?;
+            if frame.tick >= tick {
+                return Ok((frame.tick == tick).then_some(frame));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Where a tank is drawn at some moment, possibly between ticks.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TankTransform {
+    pub id: u32,
+    pub team_id: u32,
+    pub position: Vec2,
+    pub angle: Angle,
+    pub turret_angle: Angle,
+    pub alive: bool,
+}
+
+/// Where a projectile is drawn at some moment, possibly between ticks.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BulletTransform {
+    pub id: u32,
+    pub position: Vec2,
+}
+
+/// Everything needed to render one picture of a match.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TransformFrame {
+    /// Tick the picture shows, with fractions falling between ticks.
+    pub time: f64,
+    pub tanks: Vec<TankTransform>,
+    pub bullets: Vec<BulletTransform>,
+}
+
+fn lerp(from: Scalar, to: Scalar, t: Scalar) -> Scalar {
+    from + (to - from) * t
+}
+
+fn lerp_vec(from: Vec2, to: Vec2, t: Scalar) -> Vec2 {
+    Vec2::new(lerp(from.x, to.x, t), lerp(from.y, to.y, t))
+}
+
+/// Plays a replay back at any speed, including slow motion between ticks.
+pub struct ReplayPlayer<'a> {
+    replay: &'a Replay,
+    time: f64,
+}
+
+impl<'a> ReplayPlayer<'a> {
+    /// Starts at the first recorded tick.
+    pub fn new(replay: &'a Replay) -> Self {
+        let time = replay.tick_range().map_or(0.0, |(first, _)| first as f64);
+        ReplayPlayer { replay, time }
+    }
+
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Jumps straight to a moment, clamped to the recorded ticks.
+    pub fn seek(&mut self, time: f64) {
+        self.time = match self.replay.tick_range() {
+            Some((first, last)) => time.clamp(first as f64, last as f64),
+            None => 0.0,
+        };
+    }
+
+    /// Moves playback on by a number of ticks, which may be fractional for slow motion or
+    /// negative to play backwards.
+    pub fn advance(&mut self, ticks: f64) {
+        self.seek(self.time + ticks);
+    }
+
+    /// Returns whether playback has reached the last recorded tick.
+    pub fn is_finished(&self) -> bool {
+        self.replay
+            .tick_range()
+            .is_none_or(|(_, last)| self.time >= last as f64)
+    }
+
+    /// Returns the full state as of the nearest keyframe at or before the current moment.
+    pub fn keyframe(&self) -> Option<&SimState> {
+        self.replay.keyframe_at(self.time.floor() as u64)
+    }
+
+    /// Returns what to draw at the current moment.
+    ///
+    /// Between ticks, tanks and projectiles present on both sides are interpolated; anything
+    /// else is shown as of the earlier tick.
+    pub fn transforms(&self) -> TransformFrame {
+        let tick = self.time.floor() as u64;
+        let t = (self.time - tick as f64).to_scalar();
+        let (Some(from), next) = (self.replay.frame(tick), self.replay.frame(tick + 1)) else {
+            return TransformFrame {
+                time: self.time,
+                tanks: Vec::new(),
+                bullets: Vec::new(),
+            };
+        };
+        let next = next.unwrap_or(from);
+
+        let tanks = from
+            .tanks
+            .iter()
+            .map(|tank| {
+                let to = next
+                    .tanks
+                    .iter()
+                    .find(|other| other.id == tank.id)
+                    .unwrap_or(tank);
+                TankTransform {
+                    id: tank.id,
+                    team_id: tank.team_id,
+                    position: lerp_vec(tank.position, to.position, t),
+                    angle: tank.angle.lerp(to.angle, t),
+                    turret_angle: tank.turret_angle.lerp(to.turret_angle, t),
+                    alive: tank.health > 0,
+                }
+            })
+            .collect();
+        let bullets = from
+            .bullets
+            .iter()
+            .map(|bullet| {
+                let to = next
+                    .bullets
+                    .iter()
+                    .find(|other| other.id == bullet.id)
+                    .map_or(bullet.position, |other| other.position);
+                BulletTransform {
+                    id: bullet.id,
+                    position: lerp_vec(bullet.position, to, t),
+                }
+            })
+            .collect();
+        TransformFrame {
+            time: self.time,
+            tanks,
+            bullets,
+        }
+    }
+
+    /// Renders a stretch of the match at `frames_per_tick` pictures per tick, e.g. 8 for an
+    /// eighth-speed highlight, from the current moment up to `until`. Leaves playback at the
+    /// last exported moment.
+    pub fn export(&mut self, until: f64, frames_per_tick: u32) -> Vec<TransformFrame> {
+        let step = 1.0 / frames_per_tick.max(1) as f64;
+        let start = self.time;
+        let mut frames = Vec::new();
+        let mut index = 0;
+        loop {
+            let time = start + index as f64 * step;
+            if time > until {
+                break;
+            }
+            self.seek(time);
+            frames.push(self.transforms());
+            if self.is_finished() {
+                break;
+            }
+            index += 1;
+        }
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use crate::util::numeric;
+    use fastnum::dec64;
+
+    fn replay() -> Replay {
+        let mut state = SimState::new(0);
+        let mut tank = test_support::tank(0, 0, Vec2::zero());
+        tank.angle = Angle::new(dec64!(3));
+        state.tanks.push(tank);
+
+        let mut replay = Replay::new(4);
+        for tick in 1..=10 {
+            state.time = tick;
+            state.tanks[0].position = Vec2::new_from_f64(tick as f64 * 10.0, 0.0);
+            // heading crosses from just under π to just over -π
+            state.tanks[0].angle = Angle::new(dec64!(3) + Scalar::from(tick) * dec64!(0.1));
+            replay.record(&state, &[], &[], &SimClock::default());
+        }
+        replay
+    }
+
+    #[test]
+    fn reader_should_seek_through_keyframe_index() {
+        // Arrange
+        let replay = replay();
+        let bytes = replay.to_bytes().unwrap();
+
+        // Act
+        let reader = ReplayReader::open(&bytes).unwrap();
+
+        // Assert
+        assert_eq!(reader.keyframe_ticks().collect::<Vec<_>>(), [1, 4, 8]);
+        assert_eq!(reader.keyframe_at(7).unwrap().unwrap().time, 4);
+        assert_eq!(reader.frame(7).unwrap().as_ref(), replay.frame(7));
+        assert!(reader.frame(11).unwrap().is_none());
+        assert!(reader.keyframe_at(0).unwrap().is_none());
+        assert!(ReplayReader::open(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn transforms_should_interpolate_between_ticks() {
+        // Arrange
+        let replay = replay();
+        let mut player = ReplayPlayer::new(&replay);
+
+        // Act
+        player.seek(2.25);
+        let frame = player.transforms();
+
+        // Assert
+        let tank = &frame.tanks[0];
+        assert_eq!(tank.position, Vec2::new_from_f64(22.5, 0.0));
+        // turns through π rather than back around the long way
+        let expected = Angle::new(dec64!(3.225));
+        assert!(numeric::angles_approx_eq(tank.angle, expected));
+    }
+
+    #[test]
+    fn export_should_produce_slow_motion_frames_and_keyframes_should_cover_seeks() {
+        // Arrange
+        let replay = Replay::from_bytes(&replay().to_bytes().unwrap()).unwrap();
+        let mut player = ReplayPlayer::new(&replay);
+        player.seek(9.0);
+
+        // Act
+        let frames = player.export(20.0, 4);
+
+        // Assert
+        let times: Vec<f64> = frames.iter().map(|frame| frame.time).collect();
+        assert_eq!(times, [9.0, 9.25, 9.5, 9.75, 10.0]);
+        assert!(player.is_finished());
+        assert_eq!(player.keyframe().unwrap().time, 8);
+        assert_eq!(replay.keyframe_at(3).unwrap().time, 1);
+        assert_eq!(replay.tick_range(), Some((1, 10)));
+    }
+}
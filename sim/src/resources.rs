@@ -0,0 +1,94 @@
+use crate::arena::ArenaConfig;
+use crate::node::{to_aabb, to_vec2};
+use crate::spec::Loadout;
+use crate::util::math::ConvertToScalar;
+use godot::prelude::*;
+
+/// A tank's controller, saved as a `.tres` so it can be picked in the inspector.
+///
+/// Uses the built-in bot named by `builtin` if that's set, and otherwise runs `code`.
+#[derive(GodotClass)]
+#[class(base=Resource, init, tool)]
+pub struct BotProgramResource {
+    /// Name of a built-in bot, such as `tracker`. Takes precedence over `code`.
+    #[export]
+    builtin: GString,
+    /// Assembled bytecode for the tank's VM.
+    #[export]
+    code: PackedByteArray,
+    /// Tank class the bot drives when the Simulation node spawns it.
+    #[export]
+    #[init(val = 1)]
+    spec_id: i64,
+    /// Weapons fitted to that tank, by weapon spec ID.
+    #[export]
+    #[init(val = PackedInt32Array::from(&[0]))]
+    weapons: PackedInt32Array,
+    base: Base<Resource>,
+}
+
+/// What a [`BotProgramResource`] asks a tank to be controlled by.
+pub enum BotProgram {
+    Builtin(String),
+    Code(Vec<u8>),
+}
+
+impl BotProgramResource {
+    pub fn program(&self) -> BotProgram {
+        if self.builtin.is_empty() {
+            BotProgram::Code(self.code.as_slice().to_vec())
+        } else {
+            BotProgram::Builtin(self.builtin.to_string())
+        }
+    }
+
+    pub fn loadout(&self) -> Loadout {
+        Loadout {
+            spec_id: self.spec_id as u32,
+            weapons: self
+                .weapons
+                .as_slice()
+                .iter()
+                .map(|id| *id as u32)
+                .collect(),
+        }
+    }
+}
+
+/// An arena layout, saved as a `.tres` so maps can be edited in the inspector.
+///
+/// Covers the parts of [`ArenaConfig`] that map onto inspector types. Triggers and tags still
+/// come from a JSON config.
+#[derive(GodotClass)]
+#[class(base=Resource, init, tool)]
+pub struct ArenaConfigResource {
+    #[export]
+    #[init(val = Vector2::new(1024.0, 768.0))]
+    size: Vector2,
+    /// Walls, as rects from their top-left corner.
+    #[export]
+    obstacles: Array<Rect2>,
+    /// Where tanks are meant to start or respawn.
+    #[export]
+    spawn_points: PackedVector2Array,
+    base: Base<Resource>,
+}
+
+impl ArenaConfigResource {
+    /// Returns the layout as an arena config, with no triggers or tags.
+    pub fn arena(&self) -> ArenaConfig {
+        ArenaConfig {
+            width: (self.size.x as f64).to_scalar(),
+            height: (self.size.y as f64).to_scalar(),
+            obstacles: self.obstacles.iter_shared().map(to_aabb).collect(),
+            spawn_points: self
+                .spawn_points
+                .as_slice()
+                .iter()
+                .copied()
+                .map(to_vec2)
+                .collect(),
+            ..ArenaConfig::default()
+        }
+    }
+}
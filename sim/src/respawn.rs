@@ -0,0 +1,213 @@
+use crate::events::SimEvent;
+use crate::rules::Handicap;
+use crate::spec::SpecTable;
+use crate::state::{SimState, Tank};
+use crate::util::math::{Angle, Scalar, Vec2};
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How destroyed tanks come back into the match.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RespawnConfig {
+    /// Ticks between a tank's destruction and its return.
+    pub delay_ticks: u32,
+    /// Ticks after respawning during which the tank is a ghost: it can't be hit and can't fire.
+    pub invulnerable_ticks: u32,
+    /// Where tanks may reappear. Without any, tanks stay dead.
+    pub spawn_points: Vec<Vec2>,
+}
+
+/// Returns the spawn point farthest from any living enemy of the team.
+///
+/// Ties, including when there are no enemies at all, go to the earliest point in the list.
+pub fn farthest_spawn_point(points: &[Vec2], tanks: &[Tank], team_id: u32) -> Option<Vec2> {
+    let mut best: Option<(Vec2, Option<Scalar>)> = None;
+    for point in points {
+        // distance to the closest enemy, or `None` if there are none
+        let nearest = tanks
+            .iter()
+            .filter(|tank| tank.is_alive() && tank.team_id != team_id)
+            .map(|tank| tank.position.sub(point).length_squared())
+            .min();
+        let better = match (best, nearest) {
+            (None, _) => true,
+            (Some((_, Some(best))), Some(nearest)) => nearest > best,
+            _ => false,
+        };
+        if better {
+            best = Some((*point, nearest));
+        }
+    }
+    best.map(|(point, _)| point)
+}
+
+/// Counts down respawn timers and ghost periods, and brings back tanks whose timers ran out.
+///
+/// Tanks destroyed this tick start their timer here, so it must run after damage is dealt.
+/// Handicapped teams wait their share of the delay.
+pub fn update(
+    config: &RespawnConfig,
+    handicaps: &BTreeMap<u32, Handicap>,
+    state: &mut SimState,
+    specs: &SpecTable,
+    center: Vec2,
+    events: &mut Vec<SimEvent>,
+) {
+    for index in 0..state.tanks.len() {
+        let tank = &mut state.tanks[index];
+        if tank.is_alive() {
+            tank.invulnerable = tank.invulnerable.saturating_sub(1);
+            continue;
+        }
+        match tank.respawn_in {
+            None => {
+                let delay = match handicaps.get(&tank.team_id) {
+                    Some(handicap) => Handicap::scale(config.delay_ticks, handicap.respawn_time),
+                    None => config.delay_ticks,
+                };
+                tank.respawn_in = Some(delay);
+            }
+            Some(remaining) if remaining > 0 => tank.respawn_in = Some(remaining - 1),
+            Some(_) => {
+                let team_id = tank.team_id;
+                let Some(position) =
+                    farthest_spawn_point(&config.spawn_points, &state.tanks, team_id)
+                else {
+                    continue;
+                };
+                let tank = &mut state.tanks[index];
+                let Some(spec) = specs.tank(tank.loadout.spec_id) else {
+                    continue;
+                };
+                // face the middle of the arena, which is usually where the action is
+                tank.angle = Angle::of(center.sub(&position));
+                tank.position = position;
+                tank.velocity = Vec2::zero();
+                tank.angular_velocity = dec64!(0);
+                tank.turret_angle = Angle::ZERO;
+                tank.reload.fill(0);
+                tank.health = spec.max_health;
+                tank.respawn_in = None;
+                tank.invulnerable = config.invulnerable_ticks;
+                tank.wake();
+                events.push(SimEvent::TankRespawned {
+                    tank_id: tank.id,
+                    position,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn tank(id: u32, team_id: u32, x: f64) -> Tank {
+        test_support::tank(id, team_id, Vec2::new_from_f64(x, 100.0))
+    }
+
+    fn config(delay_ticks: u32) -> RespawnConfig {
+        RespawnConfig {
+            delay_ticks,
+            invulnerable_ticks: 5,
+            spawn_points: vec![
+                Vec2::new_from_f64(100.0, 100.0),
+                Vec2::new_from_f64(500.0, 100.0),
+                Vec2::new_from_f64(900.0, 100.0),
+            ],
+        }
+    }
+
+    #[test]
+    fn farthest_spawn_point_should_avoid_enemies_and_ignore_friends() {
+        // Arrange
+        let points = config(0).spawn_points;
+        let mut dead_enemy = tank(3, 1, 100.0);
+        dead_enemy.health = 0;
+        let tanks = vec![tank(1, 0, 900.0), tank(2, 1, 850.0), dead_enemy];
+
+        // Act
+        let for_team_zero = farthest_spawn_point(&points, &tanks, 0);
+        let for_team_two = farthest_spawn_point(&points, &[], 2);
+
+        // Assert
+        assert_eq!(for_team_zero, Some(points[0]));
+        assert_eq!(for_team_two, Some(points[0]));
+    }
+
+    #[test]
+    fn update_when_delay_elapsed_should_respawn_as_ghost() {
+        // Arrange
+        let config = config(2);
+        let specs = SpecTable::default();
+        let mut state = SimState::new(0);
+        state.tanks = vec![tank(1, 0, 450.0), tank(2, 1, 100.0)];
+        state.tanks[0].health = 0;
+        state.tanks[0].reload[0] = 30;
+        let center = Vec2::new_from_f64(500.0, 500.0);
+
+        // Act
+        let mut ticks = Vec::new();
+        for _ in 0..4 {
+            let mut events = Vec::new();
+            update(
+                &config,
+                &BTreeMap::new(),
+                &mut state,
+                &specs,
+                center,
+                &mut events,
+            );
+            ticks.push(events);
+        }
+
+        // Assert
+        assert!(ticks[..3].iter().all(|events| events.is_empty()));
+        assert_eq!(
+            ticks[3],
+            vec![SimEvent::TankRespawned {
+                tank_id: 1,
+                position: config.spawn_points[2],
+            }]
+        );
+        let tank = &state.tanks[0];
+        assert_eq!(tank.health, specs.tank(1).unwrap().max_health);
+        assert_eq!(tank.reload, vec![0]);
+        assert_eq!(tank.invulnerable, 5);
+        assert_eq!(tank.respawn_in, None);
+    }
+
+    #[test]
+    fn update_when_team_handicapped_should_scale_delay() {
+        // Arrange
+        let specs = SpecTable::default();
+        let mut state = SimState::new(0);
+        state.tanks = vec![tank(1, 0, 450.0), tank(2, 1, 100.0)];
+        state.tanks[0].health = 0;
+        state.tanks[1].health = 0;
+        let handicap = Handicap {
+            respawn_time: dec64!(0.5),
+            ..Handicap::default()
+        };
+        let handicaps = BTreeMap::from([(1, handicap)]);
+        let center = Vec2::new_from_f64(500.0, 500.0);
+
+        // Act
+        let mut events = Vec::new();
+        update(
+            &config(5),
+            &handicaps,
+            &mut state,
+            &specs,
+            center,
+            &mut events,
+        );
+
+        // Assert
+        assert_eq!(state.tanks[0].respawn_in, Some(5));
+        assert_eq!(state.tanks[1].respawn_in, Some(2));
+    }
+}
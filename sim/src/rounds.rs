@@ -0,0 +1,267 @@
+use crate::events::SimEvent;
+use crate::spec::SpecTable;
+use crate::state::SimState;
+use crate::util::math::{Angle, Vec2};
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Plays the match as a best-of-N series of rounds.
+///
+/// A round ends when the mode declares a winner or at most one team is left in the fight.
+/// Everyone then goes back to where they started the first round and the next round begins on
+/// the following tick, until a team has won a majority or every round has been played.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RoundsConfig {
+    pub best_of: u32,
+    /// Survivors keep their damage into the next round instead of being repaired.
+    #[serde(default)]
+    pub carry_over_damage: bool,
+    /// Survivors keep their weapons' reload timers instead of starting loaded.
+    #[serde(default)]
+    pub carry_over_reload: bool,
+}
+
+/// Where a tank begins each round.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TankStart {
+    pub position: Vec2,
+    pub angle: Angle,
+}
+
+/// Progress through a series of rounds. Part of the mode state, so it rewinds and replays with
+/// everything else.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RoundState {
+    /// The round being played, counting from zero.
+    pub round: u32,
+    /// The seed the match began with, from which every round's seed is derived.
+    pub match_seed: u64,
+    /// Rounds won by each team.
+    pub wins: BTreeMap<u32, u32>,
+    /// Mode scores summed over the finished rounds, such as captures in capture the flag.
+    pub totals: BTreeMap<u32, u64>,
+    /// Where each tank starts a round, taken from where it was first seen.
+    pub starts: BTreeMap<u32, TankStart>,
+    /// Whether the series is over.
+    pub decided: bool,
+}
+
+/// Returns the seed for a round, spreading the match seed so neighbouring rounds differ in
+/// every bit. Round zero keeps the match seed.
+pub fn round_seed(match_seed: u64, round: u32) -> u64 {
+    if round == 0 {
+        return match_seed;
+    }
+    // splitmix64
+    let mut z = match_seed.wrapping_add((round as u64).wrapping_mul(0x9e3779b97f4a7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// Ends the round if it's been decided, then either starts the next one or settles the match.
+///
+/// Must run after the mode has been updated for the tick.
+pub fn update(
+    config: &RoundsConfig,
+    state: &mut SimState,
+    specs: &SpecTable,
+    events: &mut Vec<SimEvent>,
+) {
+    let seed = state.seed;
+    let rounds = state.mode.rounds.get_or_insert_with(|| RoundState {
+        match_seed: seed,
+        ..RoundState::default()
+    });
+    for tank in &state.tanks {
+        rounds.starts.entry(tank.id).or_insert(TankStart {
+            position: tank.position,
+            angle: tank.angle,
+        });
+    }
+    if rounds.decided {
+        return;
+    }
+
+    let teams: BTreeSet<u32> = state.tanks.iter().map(|tank| tank.team_id).collect();
+    let fighting: BTreeSet<u32> = state
+        .tanks
+        .iter()
+        .filter(|tank| tank.is_alive() || tank.respawn_in.is_some())
+        .map(|tank| tank.team_id)
+        .collect();
+    let winner = match state.mode.winner {
+        Some(team_id) => Some(team_id),
+        None if teams.len() >= 2 && fighting.len() <= 1 => fighting.first().copied(),
+        None => return,
+    };
+    let round = rounds.round;
+    events.push(SimEvent::RoundEnded { round, winner });
+    if let Some(team_id) = winner {
+        *rounds.wins.entry(team_id).or_default() += 1;
+    }
+    for (team_id, score) in &state.mode.scores {
+        *rounds.totals.entry(*team_id).or_default() += score;
+    }
+
+    let majority = config.best_of / 2 + 1;
+    let clinched = rounds.wins.values().any(|wins| *wins >= majority);
+    if clinched || round + 1 >= config.best_of {
+        rounds.decided = true;
+        let best = rounds.wins.values().max().copied().unwrap_or(0);
+        let leaders: Vec<u32> = rounds
+            .wins
+            .iter()
+            .filter(|(_, wins)| **wins == best)
+            .map(|(team_id, _)| *team_id)
+            .collect();
+        let winner = match leaders.as_slice() {
+            [team_id] => Some(*team_id),
+            _ => None,
+        };
+        state.mode.winner = winner;
+        events.push(SimEvent::SeriesDecided { winner });
+        return;
+    }
+
+    rounds.round += 1;
+    state.seed = round_seed(rounds.match_seed, rounds.round);
+    let starts = rounds.starts.clone();
+    reset_arena(config, state, specs, &starts);
+    events.push(SimEvent::RoundStarted {
+        round: round + 1,
+        seed: state.seed,
+    });
+}
+
+/// Puts every tank back at its start, clears projectiles and resets the objective.
+fn reset_arena(
+    config: &RoundsConfig,
+    state: &mut SimState,
+    specs: &SpecTable,
+    starts: &BTreeMap<u32, TankStart>,
+) {
+    state.bullets.clear();
+    state.obscurants.clear();
+    for tank in state.tanks.iter_mut() {
+        let survived = tank.is_alive();
+        if let Some(start) = starts.get(&tank.id) {
+            tank.position = start.position;
+            tank.angle = start.angle;
+        }
+        tank.velocity = Vec2::zero();
+        tank.angular_velocity = dec64!(0);
+        tank.turret_angle = Angle::ZERO;
+        tank.respawn_in = None;
+        tank.invulnerable = 0;
+        tank.effects.clear();
+        tank.wake();
+        if !(survived && config.carry_over_damage)
+            && let Some(spec) = specs.tank(tank.loadout.spec_id)
+        {
+            tank.health = spec.max_health;
+        }
+        if !(survived && config.carry_over_reload) {
+            tank.reload.iter_mut().for_each(|ticks| *ticks = 0);
+        }
+    }
+
+    let progress = &mut state.mode;
+    progress.winner = None;
+    progress.scores.values_mut().for_each(|score| *score = 0);
+    for flag in progress.flags.iter_mut() {
+        flag.carrier = None;
+        flag.at_base = true;
+        flag.position = flag.home;
+    }
+    if let Some(hill) = progress.hill.as_mut() {
+        hill.controller = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn state() -> (SimState, SpecTable) {
+        let mut state = SimState::new(11);
+        for (team_id, x) in [(0, dec64!(100)), (1, dec64!(500))] {
+            let id = state.allocate_id();
+            let position = Vec2::new(x, dec64!(100));
+            state.tanks.push(test_support::tank(id, team_id, position));
+        }
+        (state, SpecTable::default())
+    }
+
+    fn best_of_three() -> RoundsConfig {
+        RoundsConfig {
+            best_of: 3,
+            carry_over_damage: true,
+            carry_over_reload: false,
+        }
+    }
+
+    #[test]
+    fn update_when_one_team_left_should_start_next_round_with_new_seed() {
+        // Arrange
+        let (mut state, specs) = state();
+        let config = best_of_three();
+        let mut events = Vec::new();
+        update(&config, &mut state, &specs, &mut events);
+        state.tanks[0].position = Vec2::new(dec64!(300), dec64!(300));
+        state.tanks[0].health = 40;
+        state.tanks[1].health = 0;
+
+        // Act
+        update(&config, &mut state, &specs, &mut events);
+
+        // Assert
+        let rounds = state.mode.rounds.as_ref().unwrap();
+        assert_eq!(rounds.round, 1);
+        assert_eq!(rounds.wins, BTreeMap::from([(0, 1)]));
+        assert_eq!(state.seed, round_seed(11, 1));
+        assert_ne!(state.seed, 11);
+        assert_eq!(state.tanks[0].position, Vec2::new(dec64!(100), dec64!(100)));
+        assert_eq!(state.tanks[0].health, 40);
+        assert_eq!(state.tanks[1].health, specs.tank(1).unwrap().max_health);
+        assert_eq!(
+            events,
+            vec![
+                SimEvent::RoundEnded {
+                    round: 0,
+                    winner: Some(0)
+                },
+                SimEvent::RoundStarted {
+                    round: 1,
+                    seed: state.seed
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn update_when_majority_won_should_decide_series() {
+        // Arrange
+        let (mut state, specs) = state();
+        let config = best_of_three();
+        let mut events = Vec::new();
+
+        // Act
+        for _ in 0..3 {
+            state.tanks[1].health = 0;
+            update(&config, &mut state, &specs, &mut events);
+        }
+
+        // Assert
+        let rounds = state.mode.rounds.as_ref().unwrap();
+        assert!(rounds.decided);
+        assert_eq!(rounds.wins, BTreeMap::from([(0, 2)]));
+        assert_eq!(state.mode.winner, Some(0));
+        assert_eq!(
+            events.last(),
+            Some(&SimEvent::SeriesDecided { winner: Some(0) })
+        );
+    }
+}
@@ -0,0 +1,416 @@
+use crate::modes::GameMode;
+use crate::respawn::RespawnConfig;
+use crate::rounds::RoundsConfig;
+use crate::scenario::Scenario;
+use crate::spec::{Loadout, SpecTable};
+use crate::state::SimState;
+use crate::util::math::{Scalar, Vec2};
+use crate::util::numeric::NumericPolicy;
+use crate::zone::ZoneConfig;
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Match-wide rules constraining what teams are allowed to field.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MatchConfig {
+    /// Tank classes that may be spawned. Empty means any class.
+    pub allowed_tank_specs: Vec<u32>,
+    /// Weapons that may be fitted. Empty means any weapon.
+    pub allowed_weapons: Vec<u32>,
+    pub max_tanks_per_team: u32,
+    /// Maximum total loadout cost per team, if limited.
+    pub team_budget: Option<u32>,
+    #[serde(default)]
+    pub mode: GameMode,
+    /// Damage per unit of impact energy when tanks collide, before armor. Zero turns ramming
+    /// damage off, though tanks still bump into each other.
+    #[serde(default = "default_ram_damage")]
+    pub ram_damage: Scalar,
+    /// Physics substeps per tick. Tank movement and collisions are integrated this many times
+    /// per tick, while programs still run once. Projectiles are swept, so they don't need it.
+    ///
+    /// Part of the config rather than a local setting, so replays come out the same anywhere.
+    #[serde(default = "default_substeps")]
+    pub substeps: u32,
+    /// Brings destroyed tanks back, if set.
+    #[serde(default)]
+    pub respawn: Option<RespawnConfig>,
+    /// Scripted events to play out over the match, if any.
+    #[serde(default)]
+    pub scenario: Option<Scenario>,
+    /// A safe zone that closes in, damaging tanks left outside it, if set.
+    #[serde(default)]
+    pub zone: Option<ZoneConfig>,
+    /// Accepts cheat commands, such as teleporting tanks, for development. Off by default.
+    #[serde(default)]
+    pub cheats: bool,
+    /// Plays the match as a best-of series of rounds, if set.
+    #[serde(default)]
+    pub rounds: Option<RoundsConfig>,
+    /// What happens when a tank damages a teammate.
+    #[serde(default)]
+    pub friendly_fire: FriendlyFire,
+    /// How far smoke clouds drift every tick.
+    #[serde(default = "Vec2::zero")]
+    pub wind: Vec2,
+    /// Tolerances and limits for the physics.
+    #[serde(default)]
+    pub numeric: NumericPolicy,
+    /// Handicaps by team ID. Teams without one play by the rules as they are.
+    #[serde(default)]
+    pub handicaps: BTreeMap<u32, Handicap>,
+}
+
+/// Multipliers that make a team stronger or weaker than the rules otherwise would, to balance
+/// a lopsided matchup or ease a tutorial. A multiplier of one leaves that parameter alone.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Handicap {
+    /// Scales the damage the team's tanks deal, rounded down.
+    pub damage: Scalar,
+    /// Scales the team's top speeds, forwards and in reverse.
+    pub speed: Scalar,
+    /// Scales the instructions the team's programs run per tick, rounded down. The
+    /// match-wide instruction limit still applies.
+    pub vm_budget: Scalar,
+    /// Scales how long the team's destroyed tanks wait to respawn, rounded down.
+    pub respawn_time: Scalar,
+}
+
+impl Default for Handicap {
+    fn default() -> Self {
+        Handicap {
+            damage: dec64!(1),
+            speed: dec64!(1),
+            vm_budget: dec64!(1),
+            respawn_time: dec64!(1),
+        }
+    }
+}
+
+impl Handicap {
+    /// Returns whether any multiplier is negative.
+    pub fn is_negative(&self) -> bool {
+        [self.damage, self.speed, self.vm_budget, self.respawn_time]
+            .iter()
+            .any(|factor| *factor < dec64!(0))
+    }
+
+    /// Scales a count by one of the multipliers, rounding down.
+    pub fn scale(amount: u32, factor: Scalar) -> u32 {
+        if factor == dec64!(1) {
+            return amount;
+        }
+        (Scalar::from(amount) * factor)
+            .floor()
+            .to_u32()
+            .unwrap_or(u32::MAX)
+    }
+}
+
+/// How damage dealt to a teammate is handled. Damage a tank does to itself is always dealt in
+/// full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FriendlyFire {
+    /// Teammates take damage like anyone else.
+    #[default]
+    Full,
+    /// Teammates take this percentage of the damage, rounded down.
+    Reduced { percent: u32 },
+    /// The teammate is spared, and the tank that dealt the damage takes it instead.
+    Reflected,
+    /// Teammates take no damage.
+    Disabled,
+}
+
+impl FriendlyFire {
+    /// Returns how much of `amount` the teammate takes.
+    pub fn scale(&self, amount: u32) -> u32 {
+        match *self {
+            FriendlyFire::Full => amount,
+            FriendlyFire::Reduced { percent } => (amount as u64 * percent as u64 / 100) as u32,
+            FriendlyFire::Reflected | FriendlyFire::Disabled => 0,
+        }
+    }
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        MatchConfig {
+            allowed_tank_specs: Vec::new(),
+            allowed_weapons: Vec::new(),
+            max_tanks_per_team: 8,
+            team_budget: None,
+            mode: GameMode::Deathmatch,
+            ram_damage: default_ram_damage(),
+            substeps: default_substeps(),
+            respawn: None,
+            scenario: None,
+            zone: None,
+            cheats: false,
+            rounds: None,
+            friendly_fire: FriendlyFire::Full,
+            wind: Vec2::zero(),
+            numeric: NumericPolicy::default(),
+            handicaps: BTreeMap::new(),
+        }
+    }
+}
+
+fn default_substeps() -> u32 {
+    1
+}
+
+fn default_ram_damage() -> Scalar {
+    dec64!(0.2)
+}
+
+/// Reasons a loadout can be rejected at spawn time.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoadoutError {
+    UnknownTankSpec(u32),
+    UnknownWeapon(u32),
+    TankSpecNotAllowed(u32),
+    WeaponNotAllowed(u32),
+    TooManyWeapons {
+        slots: u32,
+        fitted: u32,
+    },
+    TeamFull {
+        team_id: u32,
+        max: u32,
+    },
+    OverBudget {
+        team_id: u32,
+        cost: u32,
+        budget: u32,
+    },
+}
+
+impl fmt::Display for LoadoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadoutError::UnknownTankSpec(id) => write!(f, "unknown tank spec {id}"),
+            LoadoutError::UnknownWeapon(id) => write!(f, "unknown weapon {id}"),
+            LoadoutError::TankSpecNotAllowed(id) => {
+                write!(f, "tank spec {id} is not allowed in this match")
+            }
+            LoadoutError::WeaponNotAllowed(id) => {
+                write!(f, "weapon {id} is not allowed in this match")
+            }
+            LoadoutError::TooManyWeapons { slots, fitted } => {
+                write!(
+                    f,
+                    "{fitted} weapons fitted but only {slots} slots available"
+                )
+            }
+            LoadoutError::TeamFull { team_id, max } => {
+                write!(f, "team {team_id} already has the maximum of {max} tanks")
+            }
+            LoadoutError::OverBudget {
+                team_id,
+                cost,
+                budget,
+            } => write!(
+                f,
+                "team {team_id} would spend {cost} of a {budget} point budget"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LoadoutError {}
+
+impl MatchConfig {
+    /// Returns a team's handicap, which is no handicap at all unless one is configured.
+    pub fn handicap(&self, team_id: u32) -> Handicap {
+        self.handicaps.get(&team_id).copied().unwrap_or_default()
+    }
+
+    /// Checks whether a team may spawn a tank with the given loadout in the current state.
+    pub fn validate_loadout(
+        &self,
+        specs: &SpecTable,
+        state: &SimState,
+        team_id: u32,
+        loadout: &Loadout,
+    ) -> Result<(), LoadoutError> {
+        let spec = specs
+            .tank(loadout.spec_id)
+            .ok_or(LoadoutError::UnknownTankSpec(loadout.spec_id))?;
+        if !self.allowed_tank_specs.is_empty() && !self.allowed_tank_specs.contains(&spec.id) {
+            return Err(LoadoutError::TankSpecNotAllowed(spec.id));
+        }
+
+        let fitted = loadout.weapons.len() as u32;
+        if fitted > spec.weapon_slots {
+            return Err(LoadoutError::TooManyWeapons {
+                slots: spec.weapon_slots,
+                fitted,
+            });
+        }
+        for weapon_id in &loadout.weapons {
+            if specs.weapon(*weapon_id).is_none() {
+                return Err(LoadoutError::UnknownWeapon(*weapon_id));
+            }
+            if !self.allowed_weapons.is_empty() && !self.allowed_weapons.contains(weapon_id) {
+                return Err(LoadoutError::WeaponNotAllowed(*weapon_id));
+            }
+        }
+
+        let team = state.tanks.iter().filter(|tank| tank.team_id == team_id);
+        if team.clone().count() as u32 >= self.max_tanks_per_team {
+            return Err(LoadoutError::TeamFull {
+                team_id,
+                max: self.max_tanks_per_team,
+            });
+        }
+
+        if let Some(budget) = self.team_budget {
+            let spent: u32 = team
+                .filter_map(|tank| specs.loadout_cost(&tank.loadout))
+                .sum();
+            // unknown specs were already rejected above, so the cost is always known here
+            let cost = spent + specs.loadout_cost(loadout).unwrap_or(0);
+            if cost > budget {
+                return Err(LoadoutError::OverBudget {
+                    team_id,
+                    cost,
+                    budget,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::Tank;
+    use crate::util::math::{Angle, Vec2};
+
+    fn loadout(spec_id: u32, weapons: &[u32]) -> Loadout {
+        Loadout {
+            spec_id,
+            weapons: weapons.to_vec(),
+        }
+    }
+
+    fn add_tank(state: &mut SimState, specs: &SpecTable, team_id: u32, loadout: Loadout) {
+        let id = state.allocate_id();
+        let spec = specs.tank(loadout.spec_id).unwrap();
+        let tank = Tank::new(id, team_id, spec, loadout, Vec2::zero(), Angle::ZERO);
+        state.tanks.push(tank);
+    }
+
+    #[test]
+    fn validate_loadout_when_within_rules_should_succeed() {
+        // Arrange
+        let specs = SpecTable::default();
+        let state = SimState::new(0);
+        let rules = MatchConfig::default();
+
+        // Act
+        let result = rules.validate_loadout(&specs, &state, 0, &loadout(2, &[0, 1]));
+
+        // Assert
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn validate_loadout_when_spec_or_weapon_unknown_should_fail() {
+        // Arrange
+        let specs = SpecTable::default();
+        let state = SimState::new(0);
+        let rules = MatchConfig::default();
+
+        // Act
+        let bad_spec = rules.validate_loadout(&specs, &state, 0, &loadout(42, &[]));
+        let bad_weapon = rules.validate_loadout(&specs, &state, 0, &loadout(1, &[42]));
+
+        // Assert
+        assert_eq!(bad_spec, Err(LoadoutError::UnknownTankSpec(42)));
+        assert_eq!(bad_weapon, Err(LoadoutError::UnknownWeapon(42)));
+    }
+
+    #[test]
+    fn validate_loadout_when_too_many_weapons_should_fail() {
+        // Arrange
+        let specs = SpecTable::default();
+        let state = SimState::new(0);
+        let rules = MatchConfig::default();
+
+        // Act
+        let result = rules.validate_loadout(&specs, &state, 0, &loadout(0, &[0, 1]));
+
+        // Assert
+        assert_eq!(
+            result,
+            Err(LoadoutError::TooManyWeapons {
+                slots: 1,
+                fitted: 2
+            })
+        );
+    }
+
+    #[test]
+    fn validate_loadout_when_class_or_weapon_banned_should_fail() {
+        // Arrange
+        let specs = SpecTable::default();
+        let state = SimState::new(0);
+        let rules = MatchConfig {
+            allowed_tank_specs: vec![0, 1],
+            allowed_weapons: vec![1],
+            ..MatchConfig::default()
+        };
+
+        // Act
+        let banned_spec = rules.validate_loadout(&specs, &state, 0, &loadout(2, &[]));
+        let banned_weapon = rules.validate_loadout(&specs, &state, 0, &loadout(1, &[0]));
+
+        // Assert
+        assert_eq!(banned_spec, Err(LoadoutError::TankSpecNotAllowed(2)));
+        assert_eq!(banned_weapon, Err(LoadoutError::WeaponNotAllowed(0)));
+    }
+
+    #[test]
+    fn validate_loadout_when_team_full_or_over_budget_should_fail() {
+        // Arrange
+        let specs = SpecTable::default();
+        let mut state = SimState::new(0);
+        add_tank(&mut state, &specs, 0, loadout(2, &[0])); // costs 6
+        add_tank(&mut state, &specs, 1, loadout(0, &[])); // other team, costs 2
+        let full = MatchConfig {
+            max_tanks_per_team: 1,
+            ..MatchConfig::default()
+        };
+        let budgeted = MatchConfig {
+            team_budget: Some(10),
+            ..MatchConfig::default()
+        };
+
+        // Act
+        let team_full = full.validate_loadout(&specs, &state, 0, &loadout(0, &[]));
+        let over_budget = budgeted.validate_loadout(&specs, &state, 0, &loadout(1, &[0, 1]));
+        let within_budget = budgeted.validate_loadout(&specs, &state, 0, &loadout(1, &[0]));
+
+        // Assert
+        assert_eq!(
+            team_full,
+            Err(LoadoutError::TeamFull { team_id: 0, max: 1 })
+        );
+        assert_eq!(
+            over_budget,
+            Err(LoadoutError::OverBudget {
+                team_id: 0,
+                cost: 11,
+                budget: 10
+            })
+        );
+        assert_eq!(within_budget, Ok(()));
+    }
+}
@@ -0,0 +1,188 @@
+use crate::agent::AgentAction;
+use crate::bots::{BotCommand, ControlOverride, SavedBot};
+use crate::commands::Command;
+use crate::config::{ConfigError, SimConfig};
+use crate::network::NetworkController;
+use crate::replay::Replay;
+use crate::state::SimState;
+use crate::stats::MatchStats;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Marks the start of a save file.
+const MAGIC: &[u8; 4] = b"ATSV";
+const VERSION: u32 = 9;
+
+/// What runs a tank, as stored in a save.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SavedController {
+    Program(Vec<u8>),
+    Bot(SavedBot),
+    Agent(AgentAction),
+    Network(Box<NetworkController>),
+    Manual(BotCommand),
+}
+
+/// Everything needed to suspend a match and resume it later, bit for bit.
+///
+/// Saves are taken between ticks. Commands queued for the next tick are kept, but blasts,
+/// pushes and program swaps requested from outside are not, nor are history, debug drawing,
+/// telemetry and profiling, which belong to whoever is watching rather than to the match.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MatchSave {
+    /// The setup the match runs under, including any rules changed since it began.
+    pub config: SimConfig,
+    pub state: SimState,
+    pub stats: MatchStats,
+    /// Each controlled tank's controller, in ID order.
+    pub controllers: Vec<(u32, SavedController)>,
+    /// Channels humans have taken from controllers, in tank ID order.
+    pub overrides: Vec<(u32, ControlOverride)>,
+    /// Each team's ROM, in team ID order.
+    pub team_roms: Vec<(u32, Vec<u32>)>,
+    /// Commands waiting for the next tick.
+    pub queued_commands: Vec<Command>,
+    /// The replay recorded so far, which carries on recording once resumed.
+    pub replay: Option<Replay>,
+}
+
+/// Errors produced while saving or resuming a match.
+#[derive(Debug)]
+pub enum SaveError {
+    /// A tank is run by a custom bot that can't be saved.
+    UnsavableBot {
+        tank_id: u32,
+    },
+    Encode(String),
+    Decode(String),
+    /// The data isn't a save, or is from an incompatible version.
+    BadHeader,
+    /// The saved config is no longer valid.
+    Config(Vec<ConfigError>),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::UnsavableBot { tank_id } => {
+                write!(f, "tank {tank_id} is run by a bot that can't be saved")
+            }
+            SaveError::Encode(message) => write!(f, "could not encode save: {message}"),
+            SaveError::Decode(message) => write!(f, "could not decode save: {message}"),
+            SaveError::BadHeader => write!(f, "not a save file, or from another version"),
+            SaveError::Config(errors) => {
+                write!(f, "saved config is invalid:")?;
+                for error in errors {
+                    write!(f, " {error};")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl MatchSave {
+    /// Writes the save in its file format: a magic number and version, then the bundle.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SaveError> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bincode::serialize_into(&mut bytes, self).map_err(|e| SaveError::Encode(e.to_string()))?;
+        Ok(bytes)
+    }
+
+    /// Reads a save written by [`MatchSave::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SaveError> {
+        let header = MAGIC.len() + 4;
+        if bytes.len() < header
+            || &bytes[..MAGIC.len()] != MAGIC
+            || bytes[MAGIC.len()..header] != VERSION.to_le_bytes()
+        {
+            return Err(SaveError::BadHeader);
+        }
+        bincode::deserialize(&bytes[header..]).map_err(|e| SaveError::Decode(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bots::{BotCommand, BotController, BotView, Circler, Tracker};
+    use crate::sim::{SimEngine, TankSpawn};
+    use crate::util::math::Vec2;
+
+    struct Custom;
+
+    impl BotController for Custom {
+        fn think(&mut self, _view: &BotView) -> BotCommand {
+            BotCommand::default()
+        }
+    }
+
+    fn engine() -> (SimEngine, [u32; 3]) {
+        let mut engine = SimEngine::new(SimState::new(9));
+        let mut spawn = |team_id: u32, x: f64| {
+            engine
+                .spawn_tank(TankSpawn::medium(team_id, Vec2::new_from_f64(x, 300.0)))
+                .unwrap()
+        };
+        let tanks = [spawn(0, 200.0), spawn(1, 600.0), spawn(1, 700.0)];
+        engine
+            .set_bot(tanks[0], Box::new(Tracker::default()))
+            .unwrap();
+        engine
+            .set_bot(tanks[1], Box::new(Circler::default()))
+            .unwrap();
+        engine.attach_agent(tanks[2]).unwrap();
+        engine
+            .set_agent_action(tanks[2], AgentAction::Discrete(7))
+            .unwrap();
+        (engine, tanks)
+    }
+
+    #[test]
+    fn resumed_match_should_carry_on_exactly_like_the_original() {
+        // Arrange
+        let (mut original, _) = engine();
+        original.start_replay(10);
+        for _ in 0..25 {
+            original.step();
+        }
+
+        // Act
+        let bytes = original.save().unwrap().to_bytes().unwrap();
+        let mut resumed = SimEngine::from_save(MatchSave::from_bytes(&bytes).unwrap()).unwrap();
+        for _ in 0..40 {
+            original.step();
+            resumed.step();
+        }
+
+        // Assert
+        assert_eq!(resumed.state(), original.state());
+        assert_eq!(resumed.stats(), original.stats());
+        assert_eq!(resumed.replay(), original.replay());
+        assert_eq!(resumed.replay().unwrap().tick_range(), Some((0, 65)));
+    }
+
+    #[test]
+    fn save_when_bot_is_custom_should_name_the_tank() {
+        // Arrange
+        let (mut engine, tanks) = engine();
+        engine.set_bot(tanks[1], Box::new(Custom)).unwrap();
+
+        // Act
+        let result = engine.save();
+
+        // Assert
+        assert!(matches!(
+            result,
+            Err(SaveError::UnsavableBot { tank_id }) if tank_id == tanks[1]
+        ));
+        assert!(matches!(
+            MatchSave::from_bytes(b"ATRP\x03\0\0\0"),
+            Err(SaveError::BadHeader)
+        ));
+    }
+}
@@ -0,0 +1,177 @@
+use crate::commands::Command;
+use crate::sim::TankSpawn;
+use crate::util::math::Vec2;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Something a scenario does when its time comes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ScenarioAction {
+    /// Queues a single command.
+    Command(Command),
+    /// Spawns `count` copies of a tank in a line, each `spacing` further on from the last.
+    Wave {
+        count: u32,
+        spawn: TankSpawn,
+        spacing: Vec2,
+    },
+}
+
+/// An action and the tick it happens on.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimedAction {
+    pub tick: u64,
+    pub action: ScenarioAction,
+}
+
+/// A script of timed actions, for PvE and training matches rather than symmetric ones.
+///
+/// Actions are turned into commands and queued ahead of any others at the start of their tick,
+/// so a scenario plays out the same way every time.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    pub actions: Vec<TimedAction>,
+}
+
+/// Errors produced while loading scenarios.
+#[derive(Debug)]
+pub enum ScenarioError {
+    Parse(String),
+    EmptyWave { tick: u64 },
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::Parse(message) => write!(f, "invalid scenario: {message}"),
+            ScenarioError::EmptyWave { tick } => write!(f, "wave at tick {tick} spawns no tanks"),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+impl Scenario {
+    /// Parses and checks a scenario.
+    pub fn from_json(json: &str) -> Result<Self, ScenarioError> {
+        let scenario: Scenario =
+            serde_json::from_str(json).map_err(|e| ScenarioError::Parse(e.to_string()))?;
+
+        for timed in &scenario.actions {
+            if let ScenarioAction::Wave { count: 0, .. } = timed.action {
+                return Err(ScenarioError::EmptyWave { tick: timed.tick });
+            }
+        }
+
+        Ok(scenario)
+    }
+
+    /// Returns the commands due on a tick, in the order the actions are listed.
+    pub fn commands_at(&self, tick: u64) -> Vec<Command> {
+        let mut commands = Vec::new();
+        for timed in self.actions.iter().filter(|timed| timed.tick == tick) {
+            match &timed.action {
+                ScenarioAction::Command(command) => commands.push(command.clone()),
+                ScenarioAction::Wave {
+                    count,
+                    spawn,
+                    spacing,
+                } => {
+                    let mut position = spawn.position;
+                    for _ in 0..*count {
+                        commands.push(Command::SpawnTank(TankSpawn {
+                            position,
+                            ..spawn.clone()
+                        }));
+                        position = position + *spacing;
+                    }
+                }
+            }
+        }
+        commands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drone() -> TankSpawn {
+        TankSpawn::medium(1, Vec2::new_from_f64(100.0, 50.0))
+    }
+
+    #[test]
+    fn commands_at_should_expand_waves_in_listed_order() {
+        // Arrange
+        let scenario = Scenario {
+            actions: vec![
+                TimedAction {
+                    tick: 600,
+                    action: ScenarioAction::Wave {
+                        count: 3,
+                        spawn: drone(),
+                        spacing: Vec2::new_from_f64(0.0, 40.0),
+                    },
+                },
+                TimedAction {
+                    tick: 1200,
+                    action: ScenarioAction::Command(Command::RemoveEntity { entity_id: 4 }),
+                },
+                TimedAction {
+                    tick: 600,
+                    action: ScenarioAction::Command(Command::SetTeam {
+                        tank_id: 0,
+                        team_id: 1,
+                    }),
+                },
+            ],
+        };
+
+        // Act
+        let wave = scenario.commands_at(600);
+        let gate = scenario.commands_at(1200);
+        let quiet = scenario.commands_at(601);
+
+        // Assert
+        let positions: Vec<Vec2> = wave
+            .iter()
+            .filter_map(|command| match command {
+                Command::SpawnTank(spawn) => Some(spawn.position),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            positions,
+            [
+                Vec2::new_from_f64(100.0, 50.0),
+                Vec2::new_from_f64(100.0, 90.0),
+                Vec2::new_from_f64(100.0, 130.0),
+            ]
+        );
+        assert!(matches!(wave[3], Command::SetTeam { .. }));
+        assert_eq!(gate, [Command::RemoveEntity { entity_id: 4 }]);
+        assert!(quiet.is_empty());
+    }
+
+    #[test]
+    fn from_json_when_wave_empty_should_fail() {
+        // Arrange
+        let scenario = Scenario {
+            actions: vec![TimedAction {
+                tick: 10,
+                action: ScenarioAction::Wave {
+                    count: 0,
+                    spawn: drone(),
+                    spacing: Vec2::zero(),
+                },
+            }],
+        };
+        let json = serde_json::to_string(&scenario).unwrap();
+
+        // Act
+        let result = Scenario::from_json(&json);
+
+        // Assert
+        assert!(matches!(result, Err(ScenarioError::EmptyWave { tick: 10 })));
+    }
+}
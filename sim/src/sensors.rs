@@ -0,0 +1,83 @@
+use crate::spec::SpecTable;
+use crate::state::{Bullet, Tank};
+use crate::util::math::{Angle, Vec2};
+use crate::util::pool::Pool;
+use crate::visibility::Visibility;
+use crate::zone::Zone;
+use serde::{Deserialize, Serialize};
+
+/// An enemy picked up on radar.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Contact {
+    pub id: u32,
+    pub team_id: u32,
+    pub position: Vec2,
+    pub velocity: Vec2,
+}
+
+/// One of the tank's own guided projectiles, reported back over its datalink.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MissileTrack {
+    pub id: u32,
+    pub position: Vec2,
+    /// Direction it's flying in, which lags the ordered heading while it turns.
+    pub heading: Angle,
+    /// Instructions each steering order costs.
+    pub steer_cost: u32,
+}
+
+/// What a tank's controller is allowed to know about the world this tick.
+///
+/// Only contacts the tank itself can see are included, never the whole state.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SensorData {
+    pub contacts: Vec<Contact>,
+    /// The safe zone, if the match has one.
+    #[serde(default)]
+    pub zone: Option<Zone>,
+    /// The tank's guided projectiles still in flight, in ID order.
+    #[serde(default)]
+    pub missiles: Vec<MissileTrack>,
+}
+
+impl SensorData {
+    /// Builds the sensor readout for a tank from the current visibility.
+    pub fn read(tank_id: u32, tanks: &[Tank], visibility: &Visibility, zone: Option<Zone>) -> Self {
+        let contacts = visibility
+            .seen_by_tank(tank_id)
+            .iter()
+            .filter_map(|id| tanks.iter().find(|tank| tank.id == *id))
+            .map(|tank| Contact {
+                id: tank.id,
+                team_id: tank.team_id,
+                position: tank.position,
+                velocity: tank.velocity,
+            })
+            .collect();
+
+        SensorData {
+            contacts,
+            zone,
+            missiles: Vec::new(),
+        }
+    }
+}
+
+/// Tracks a tank's guided projectiles, so its program can steer them.
+pub fn datalink(tank_id: u32, bullets: &Pool<Bullet>, specs: &SpecTable) -> Vec<MissileTrack> {
+    let mut missiles: Vec<MissileTrack> = bullets
+        .iter()
+        .filter(|bullet| bullet.owner == Some(tank_id))
+        .filter_map(|bullet| {
+            let guidance = specs.weapon(bullet.weapon_id)?.guidance.as_ref()?;
+            Some(MissileTrack {
+                id: bullet.id,
+                position: bullet.position,
+                heading: Angle::of(bullet.velocity),
+                steer_cost: guidance.steer_cost,
+            })
+        })
+        .collect();
+    missiles.sort_unstable_by_key(|missile| missile.id);
+    missiles
+}
@@ -0,0 +1,291 @@
+use crate::bots;
+use crate::commands::Command;
+use crate::config::{SimConfig, ValidatedConfig};
+use crate::console;
+use crate::error::{BotLoadError, SimError};
+use crate::network::NetworkController;
+use crate::sim::{SimEngine, TankSpawn};
+use crate::state::SimState;
+
+/// A match driven entirely through strings, bytes and integers.
+///
+/// Frontends that can't share Rust types, such as a browser build or scripting bindings, wrap
+/// this rather than [`SimEngine`], so each of them stays a thin layer of glue. Everything
+/// structured goes in and out as JSON, and every failure comes back as a [`SimError`].
+///
+/// Training loops get gym-style semantics from [`Session::reset`], [`Session::act`],
+/// [`Session::step`], [`Session::observe`] and [`Session::is_done`].
+pub struct Session {
+    config: ValidatedConfig,
+    engine: SimEngine,
+}
+
+impl Session {
+    /// Starts an empty match from a JSON [`SimConfig`], or the defaults if `config_json` is
+    /// empty.
+    pub fn new(config_json: &str, seed: u64) -> Result<Self, SimError> {
+        let config = if config_json.trim().is_empty() {
+            ValidatedConfig::default()
+        } else {
+            SimConfig::from_json(config_json)?
+        };
+        Ok(Session::with_config(config, seed))
+    }
+
+    /// Starts an empty match from a config that has already been checked.
+    pub fn with_config(config: ValidatedConfig, seed: u64) -> Self {
+        let engine =
+            SimEngine::from_config(SimState::with_arena(seed, &config.get().arena), &config);
+        Session { config, engine }
+    }
+
+    /// Starts the match over, empty, with a new seed.
+    pub fn reset(&mut self, seed: u64) {
+        let state = SimState::with_arena(seed, &self.config.get().arena);
+        self.engine = SimEngine::from_config(state, &self.config);
+    }
+
+    /// Spawns a tank from a JSON [`TankSpawn`] and returns its ID.
+    pub fn spawn_tank(&mut self, spawn_json: &str) -> Result<u32, SimError> {
+        let spawn: TankSpawn = serde_json::from_str(spawn_json)?;
+        self.engine.spawn_tank(spawn)
+    }
+
+    /// Hands a tank to one of the built-in bots (see [`bots::builtin`]).
+    pub fn load_bot(&mut self, tank_id: u32, name: &str) -> Result<(), SimError> {
+        let bot = bots::builtin(name).ok_or_else(|| BotLoadError::UnknownBot(name.into()))?;
+        self.engine.set_bot(tank_id, bot)
+    }
+
+    /// Loads assembled bytecode, bare or packaged, into a tank's VM.
+    pub fn load_program(&mut self, tank_id: u32, code: &[u8]) -> Result<(), SimError> {
+        self.engine.load_program(tank_id, code.to_vec())
+    }
+
+    /// Hands a tank over to a trained network, given as a JSON
+    /// [`NetworkFile`](crate::network::NetworkFile).
+    pub fn load_network(&mut self, tank_id: u32, network_json: &str) -> Result<(), SimError> {
+        let network = NetworkController::from_json(network_json)?;
+        self.engine.set_network(tank_id, network)
+    }
+
+    /// Queues a JSON array of [`Command`]s, e.g. an agent's drive, turret and fire orders, to
+    /// apply at the start of the next tick.
+    pub fn act(&mut self, commands_json: &str) -> Result<(), SimError> {
+        let commands: Vec<Command> = serde_json::from_str(commands_json)?;
+        for command in commands {
+            self.engine.queue_command(command);
+        }
+        Ok(())
+    }
+
+    /// Runs a line of the development console (see [`console::parse`]) and returns what to
+    /// print. Only works if the config's rules allow cheats.
+    pub fn console(&mut self, line: &str) -> Result<String, SimError> {
+        Ok(console::execute(&mut self.engine, line)?)
+    }
+
+    /// Returns what a tank knows, as JSON `{ "tank": .., "sensors": .. }`: its own state and
+    /// the contacts it can see, never the rest of the match.
+    pub fn observe(&self, tank_id: u32) -> Result<String, SimError> {
+        let tank = self
+            .engine
+            .state()
+            .tank(tank_id)
+            .ok_or(BotLoadError::UnknownTank(tank_id))?;
+        let sensors = self.engine.sensors(tank_id).cloned().unwrap_or_default();
+        let observation = serde_json::json!({ "tank": tank, "sensors": sensors });
+        Ok(observation.to_string())
+    }
+
+    /// Returns whether the match is over: a mode has declared a winner, or at most one team
+    /// still has tanks in the fight or waiting to respawn.
+    pub fn is_done(&self) -> bool {
+        self.engine.is_decided()
+    }
+
+    /// Runs some ticks and returns every event they produced, as a JSON array.
+    pub fn step(&mut self, ticks: u32) -> String {
+        let mut events = Vec::new();
+        for _ in 0..ticks {
+            self.engine.step();
+            events.extend_from_slice(self.engine.events());
+        }
+        serde_json::to_string(&events).expect("events are always serializable")
+    }
+
+    /// Returns the whole match state as JSON.
+    pub fn get_state(&self) -> String {
+        serde_json::to_string(self.engine.state()).expect("state is always serializable")
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.engine.state().time
+    }
+
+    /// Returns the engine, for frontends written in Rust that want more than strings.
+    pub fn engine(&self) -> &SimEngine {
+        &self.engine
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::SimEvent;
+    use crate::physics::drivetrain::DriveInput;
+    use crate::state::Tank;
+    use crate::util::math::{Angle, Vec2};
+    use crate::vm::isa::{ISA_VERSION, Opcode};
+    use crate::vm::package::{BotPackage, PackageError};
+    use crate::vm::verify::VerifyError;
+    use fastnum::dec64;
+
+    #[test]
+    fn session_should_drive_match_through_json() {
+        // Arrange
+        let mut session = Session::new("", 3).unwrap();
+        let start = Vec2::new_from_f64(100.0, 100.0);
+        let spawn = TankSpawn::medium(0, start);
+        let tank_id = session
+            .spawn_tank(&serde_json::to_string(&spawn).unwrap())
+            .unwrap();
+        session.load_bot(tank_id, "circler").unwrap();
+
+        // Act
+        let events: Vec<SimEvent> = serde_json::from_str(&session.step(5)).unwrap();
+        let state: SimState = serde_json::from_str(&session.get_state()).unwrap();
+
+        // Assert
+        assert!(events.is_empty());
+        assert_eq!(session.tick(), 5);
+        assert_ne!(state.tanks[0].position, start);
+        assert!(matches!(
+            session.load_bot(tank_id, "kamikaze"),
+            Err(SimError::BotLoad(BotLoadError::UnknownBot(_)))
+        ));
+    }
+
+    #[test]
+    fn act_should_steer_tank_and_observe_should_show_only_its_view() {
+        // Arrange
+        let mut session = Session::new("", 0).unwrap();
+        let mut ids = Vec::new();
+        for (team_id, x) in [(0, 100.0), (1, 200.0)] {
+            let spawn = TankSpawn::medium(team_id, Vec2::new_from_f64(x, 100.0));
+            ids.push(
+                session
+                    .spawn_tank(&serde_json::to_string(&spawn).unwrap())
+                    .unwrap(),
+            );
+        }
+        let drive = vec![Command::SetDriveInput {
+            tank_id: ids[0],
+            input: DriveInput::tracks(dec64!(1), dec64!(1)),
+        }];
+
+        // Act
+        session
+            .act(&serde_json::to_string(&drive).unwrap())
+            .unwrap();
+        session.step(3);
+        let observation: serde_json::Value =
+            serde_json::from_str(&session.observe(ids[0]).unwrap()).unwrap();
+
+        // Assert
+        // both tracks forward drives it straight along its heading, +x
+        let tank: Tank = serde_json::from_value(observation["tank"].clone()).unwrap();
+        assert_eq!(tank.angle, Angle::ZERO);
+        assert!(tank.velocity.x > dec64!(0));
+        assert_eq!(tank.velocity.y, dec64!(0));
+        assert!(tank.position.x > dec64!(100));
+        assert_eq!(tank.position.y, dec64!(100));
+        assert_eq!(observation["sensors"]["contacts"][0]["id"], ids[1]);
+        assert!(!session.is_done());
+        assert!(matches!(
+            session.observe(99),
+            Err(SimError::BotLoad(BotLoadError::UnknownTank(99)))
+        ));
+    }
+
+    #[test]
+    fn load_program_when_bytecode_malformed_should_report_why() {
+        // Arrange
+        let mut session = Session::new("", 0).unwrap();
+        let spawn = TankSpawn::medium(0, Vec2::new_from_f64(100.0, 100.0));
+        let tank_id = session
+            .spawn_tank(&serde_json::to_string(&spawn).unwrap())
+            .unwrap();
+        let halt = [Opcode::Halt as u8];
+        // a push missing three of its operand's bytes
+        let cut_short = [Opcode::Push as u8, 1];
+
+        // Act
+        let malformed = session.load_program(tank_id, &cut_short);
+        let elsewhere = session.load_program(99, &halt);
+
+        // Assert
+        assert!(matches!(
+            malformed,
+            Err(SimError::BotLoad(BotLoadError::Verify(
+                VerifyError::TruncatedInstruction { pc: 0 }
+            )))
+        ));
+        assert!(matches!(
+            elsewhere,
+            Err(SimError::BotLoad(BotLoadError::UnknownTank(99)))
+        ));
+        assert!(session.load_program(tank_id, &halt).is_ok());
+    }
+
+    #[test]
+    fn load_program_when_package_corrupt_should_report_why() {
+        // Arrange
+        let mut session = Session::new("", 0).unwrap();
+        let spawn = TankSpawn::medium(0, Vec2::new_from_f64(100.0, 100.0));
+        let tank_id = session
+            .spawn_tank(&serde_json::to_string(&spawn).unwrap())
+            .unwrap();
+        let package = BotPackage::new(vec![Opcode::Halt as u8]).to_bytes();
+        let future = BotPackage {
+            isa_version: ISA_VERSION + 1,
+            ..BotPackage::new(vec![Opcode::Halt as u8])
+        };
+
+        // Act
+        let truncated = session.load_program(tank_id, &package[..5]);
+        let unsupported = session.load_program(tank_id, &future.to_bytes());
+
+        // Assert
+        assert!(matches!(
+            truncated,
+            Err(SimError::BotLoad(BotLoadError::Package(
+                PackageError::Truncated
+            )))
+        ));
+        assert!(matches!(
+            unsupported,
+            Err(SimError::BotLoad(BotLoadError::Package(
+                PackageError::UnsupportedIsa { version }
+            ))) if version == ISA_VERSION + 1
+        ));
+        assert!(session.load_program(tank_id, &package).is_ok());
+    }
+
+    #[test]
+    fn new_when_config_invalid_should_report_every_problem() {
+        // Arrange
+        let mut config = SimConfig::default();
+        config.limits.instructions_per_tick = 0;
+        let json = serde_json::to_string(&config).unwrap();
+
+        // Act
+        let result = Session::new(&json, 0);
+
+        // Assert
+        let Err(SimError::Config(errors)) = result else {
+            panic!("config should have been rejected");
+        };
+        assert_eq!(errors.len(), 3);
+    }
+}
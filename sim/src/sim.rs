@@ -1,5 +1,3569 @@
+use crate::agent::{self, AgentAction, ObservationSpec};
+use crate::arena::Arena;
+use crate::bots::{BotCommand, BotController, BotView, ControlOverride};
+use crate::clock::SimClock;
+use crate::commands::Command;
+use crate::config::{GridResolution, SimConfig, ValidatedConfig};
+use crate::damage::{self, ArmorSide, HitOutcome, Impact};
+use crate::debug_draw::{DebugCategory, DebugDraw};
+use crate::effects;
+use crate::error::{BotLoadError, SimError};
+use crate::events::{ImpactMaterial, SimEvent};
+use crate::explosions::{self, Explosion, ExplosionCause};
+use crate::history::{FrozenTimeline, History};
+use crate::intercept;
+use crate::jobs::ScopedSplitter;
+use crate::lifecycle::{Lifecycle, Spawn};
+use crate::limits::{self, BotLimit, BotLimits};
+use crate::log::{Severity, SimLog, Subsystem};
+use crate::modes::{self, GameMode, ModeState};
+use crate::nav::{self, NavGrid};
+use crate::network::NetworkController;
+use crate::obscurants::{self, Obscurant, Occluders};
+use crate::physics::broadphase::TankBroadphase;
+use crate::physics::collision::{AABB, SegmentHit, segment_vs_box};
+use crate::physics::drivetrain::{self, DriveInput, DrivetrainSpec};
+use crate::physics::impulse::{self, ImpulseSource};
+use crate::physics::sleep;
+use crate::physics::turret::{self, TurretCommand};
+use crate::ramming;
+use crate::referee::{DamageSource, FriendlyHit, Judge, Referee};
+use crate::replay::Replay;
+use crate::respawn::{self, RespawnConfig};
+use crate::rounds;
+use crate::rules::{Handicap, LoadoutError, MatchConfig};
+use crate::save::{MatchSave, SaveError, SavedController};
+use crate::scenario::Scenario;
+use crate::sensors::{self, SensorData};
+use crate::spec::{ExplosionSpec, Loadout, SpecTable, WeaponSpec};
+use crate::spectator::{self, EntitySummary};
 use crate::state::*;
+use crate::stats::MatchStats;
+use crate::telemetry::{TelemetryFrame, TelemetrySink};
+use crate::triggers::{Trigger, TriggerIndex, TriggerShape};
+use crate::util::math::{Angle, Scalar, Vec2};
+use crate::util::spatial::{GridStats, OccupancyStats};
+use crate::visibility::{FogMask, Visibility};
+use crate::vm::abi::{self, TankIo};
+use crate::vm::profile::{FunctionSymbol, Profiler, VmProfile};
+use crate::vm::threaded::ThreadedProgram;
+use crate::vm::{self, Execution, VmFault, package, verify};
+use crate::watchdog::{Phase, PhaseTimings, Watchdog};
+use crate::zone::{self, Zone};
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::num::NonZeroUsize;
+
+/// A request to add a tank to the match.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TankSpawn {
+    pub team_id: u32,
+    pub loadout: Loadout,
+    pub position: Vec2,
+    pub angle: Angle,
+}
+
+impl TankSpawn {
+    /// A medium tank with a cannon, facing along +x.
+    pub fn medium(team_id: u32, position: Vec2) -> Self {
+        Self {
+            team_id,
+            loadout: Loadout {
+                spec_id: 1,
+                weapons: vec![0],
+            },
+            position,
+            angle: Angle::ZERO,
+        }
+    }
+}
+
+/// What happens to a program's RAM when it's swapped for a new one mid-match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReloadPolicy {
+    /// Start from zeroed RAM, as if the program had been loaded fresh.
+    Reset,
+    /// Keep RAM as the old program left it, so state carried there survives.
+    KeepMemory,
+}
+
+/// Whatever decides a tank's commands each tick.
+enum Controller {
+    Program(LoadedProgram),
+    Bot(Box<dyn BotController>),
+    /// A learning agent outside the engine, repeating its last action until it sends another.
+    Agent(AgentAction),
+    /// A trained network, run inside the engine like a bot.
+    Network(Box<NetworkController>),
+    /// A human, whose input arrives through the command queue and holds until the next.
+    Manual(BotCommand),
+}
+
+/// A tank's bytecode, and the same program decoded up front if the match runs threaded code.
+struct LoadedProgram {
+    code: Vec<u8>,
+    threaded: Option<ThreadedProgram>,
+}
+
+impl LoadedProgram {
+    fn new(code: Vec<u8>, execution: Execution) -> Self {
+        let threaded = (execution == Execution::Threaded).then(|| ThreadedProgram::compile(&code));
+        LoadedProgram { code, threaded }
+    }
+}
+
+/// What a tank's program did in a tick besides driving its own tank, to apply once every
+/// program has run.
+struct ProgramRun {
+    tank_id: u32,
+    cycles: u32,
+    profile: Option<VmProfile>,
+    exceeded: Vec<BotLimit>,
+    /// Where the program faulted, if it did this tick.
+    fault: Option<(u32, VmFault)>,
+    /// Steering orders, as missile IDs and headings, in order.
+    steering: Vec<(u32, Angle)>,
+}
+
+/// What tank programs did during a tick.
+#[derive(Default)]
+struct ProgramReport {
+    /// Instructions each program executed.
+    vm_cycles: Vec<(u32, u32)>,
+    /// Profiles of the programs being profiled.
+    vm_profiles: Vec<(u32, VmProfile)>,
+}
+
+/// What every program can read besides its own tank.
+struct ProgramContext<'a> {
+    time: u64,
+    nav: &'a NavGrid,
+    sensors: &'a BTreeMap<u32, SensorData>,
+    team_roms: &'a BTreeMap<u32, Vec<u32>>,
+    limits: &'a BotLimits,
+}
+
+/// Runs a tank's program for a tick and applies its actuators to the tank. Changes nothing but
+/// the tank, so programs can run side by side.
+fn run_program(
+    tank: &mut Tank,
+    program: &LoadedProgram,
+    budget: u32,
+    functions: Option<&Vec<FunctionSymbol>>,
+    context: &ProgramContext,
+) -> ProgramRun {
+    let mut vm_state = std::mem::take(&mut tank.vm);
+    let was_faulted = vm_state.fault.is_some();
+    let mut io = TankIo::new(
+        context.time,
+        tank,
+        context.sensors.get(&tank.id),
+        context.nav,
+        context
+            .team_roms
+            .get(&tank.team_id)
+            .map_or(&[], Vec::as_slice),
+        context.limits.path_queries_per_tick,
+    );
+    let mut profile = None;
+    let report = match functions {
+        Some(functions) => {
+            let mut profiler = Profiler::new(functions);
+            let code = &program.code;
+            let report = vm::run_profiled(&mut vm_state, code, budget, &mut io, &mut profiler);
+            profile = Some(profiler.finish());
+            report
+        }
+        None => match &program.threaded {
+            Some(threaded) => vm::threaded::run(&mut vm_state, threaded, budget, &mut io),
+            None => vm::run(&mut vm_state, &program.code, budget, &mut io),
+        },
+    };
+    let mut exceeded: Vec<BotLimit> = limits::exceeded(&report).into_iter().collect();
+    if io.refused_path_queries > 0 {
+        exceeded.push(BotLimit::PathQueries);
+    }
+    let (actuators, steering) = (io.actuators, io.steering);
+    let fault = match vm_state.fault {
+        Some(fault) if !was_faulted => Some((vm_state.pc, fault)),
+        _ => None,
+    };
+    tank.vm = vm_state;
+    actuators.apply(tank);
+    ProgramRun {
+        tank_id: tank.id,
+        cycles: report.cycles,
+        profile,
+        exceeded,
+        fault,
+        steering,
+    }
+}
+
+/// The blast a weapon's projectile sets off where it stops, if it has one.
+fn detonation(weapon: &WeaponSpec, bullet: &Bullet, center: Vec2) -> Option<Explosion> {
+    let spec = weapon.explosion.clone()?;
+    Some(Explosion {
+        cause: ExplosionCause::Bullet(bullet.id),
+        attacker: bullet.owner,
+        center,
+        spec,
+    })
+}
+
+/// Builds the path planning grid, wide enough for the widest hull to drive down any path.
+fn build_nav(
+    arena: &Arena,
+    specs: &SpecTable,
+    obstacles: &[Obstacle],
+    cell_size: Scalar,
+) -> NavGrid {
+    NavGrid::new(
+        arena.width(),
+        arena.height(),
+        cell_size,
+        nav::clearance(specs),
+        obstacles,
+    )
+}
 
 pub struct SimEngine {
-    state: SimState
+    state: SimState,
+    /// The config the engine was set up from, kept for saves.
+    config: SimConfig,
+    specs: SpecTable,
+    rules: MatchConfig,
+    arena: Arena,
+    grid: GridResolution,
+    clock: SimClock,
+    limits: BotLimits,
+    nav: NavGrid,
+    triggers: TriggerIndex,
+    broadphase: TankBroadphase,
+    controllers: BTreeMap<u32, Controller>,
+    /// Channels humans have taken from tanks' controllers.
+    overrides: BTreeMap<u32, ControlOverride>,
+    /// Function tables of the tanks whose programs are being profiled.
+    vm_profiling: BTreeMap<u32, Vec<FunctionSymbol>>,
+    /// Constant data mapped into every program on a team, by team ID.
+    team_roms: BTreeMap<u32, Vec<u32>>,
+    /// Times each tick, when a budget is configured.
+    watchdog: Watchdog,
+    /// Splits the parts of a tick that run in parallel across threads.
+    splitter: ScopedSplitter,
+    history: History,
+    timelines: Vec<FrozenTimeline>,
+    stats: MatchStats,
+    telemetry: Option<Box<dyn TelemetrySink>>,
+    /// House rules judging damage and deaths, if any.
+    referee: Option<Box<dyn Referee>>,
+    /// The replay being recorded, if any.
+    replay: Option<Replay>,
+    events: Vec<SimEvent>,
+    /// Blasts requested from outside, set off on the next tick.
+    queued_explosions: Vec<Explosion>,
+    /// Pushes requested from outside, applied on the next tick.
+    queued_impulses: Vec<(u32, Vec2)>,
+    /// Commands to apply at the start of the next tick.
+    queued_commands: Vec<Command>,
+    /// Commands applied at the start of the last tick.
+    applied_commands: Vec<Command>,
+    /// How many of `applied_commands` came from the scenario rather than from outside.
+    scripted_commands: usize,
+    /// Programs to swap in at the start of the next tick.
+    queued_reloads: Vec<(u32, Vec<u8>, ReloadPolicy)>,
+    /// Entities to add or remove at the end of the current phase.
+    lifecycle: Lifecycle,
+    visibility: Visibility,
+    sensors: BTreeMap<u32, SensorData>,
+    debug: DebugDraw,
+    /// Diagnostics, which nothing in a tick reads back.
+    log: SimLog,
+}
+
+impl SimEngine {
+    /// Creates an engine using the built-in tank classes, default match rules and arena size.
+    pub fn new(state: SimState) -> Self {
+        SimEngine::from_config(state, &ValidatedConfig::default())
+    }
+
+    /// Creates an engine for the given state.
+    ///
+    /// Only the arena's dimensions are taken from the config; obstacles always come from the
+    /// state (see [`SimState::with_arena`]), so resumed matches keep whatever changed since. The
+    /// match mode's objectives are placed the first time a state is used with it.
+    pub fn from_config(mut state: SimState, config: &ValidatedConfig) -> Self {
+        let config = config.get();
+        let specs = config.specs.clone();
+        let rules = config.rules.clone();
+        let grid = config.grid;
+        let limits = config.limits.clone();
+        let clock = config.clock();
+        if rules.mode != GameMode::Deathmatch && !state.mode.is_set_up() {
+            modes::setup(&rules.mode, &mut state);
+        }
+        let arena = Arena::new(config.arena.width, config.arena.height, &state.obstacles);
+        let nav = build_nav(&arena, &specs, &state.obstacles, grid.nav_cell_size);
+        let triggers = TriggerIndex::new(arena.width(), arena.height(), &state.triggers);
+        let broadphase =
+            TankBroadphase::new(arena.width(), arena.height(), grid.broadphase_cell_size);
+        let mut engine = SimEngine {
+            state,
+            config: config.clone(),
+            specs,
+            rules,
+            arena,
+            grid,
+            clock,
+            limits,
+            nav,
+            triggers,
+            broadphase,
+            controllers: BTreeMap::new(),
+            overrides: BTreeMap::new(),
+            vm_profiling: BTreeMap::new(),
+            team_roms: BTreeMap::new(),
+            watchdog: Watchdog::new(config.watchdog.clone()),
+            splitter: ScopedSplitter::default(),
+            history: History::default(),
+            timelines: Vec::new(),
+            stats: MatchStats::default(),
+            telemetry: None,
+            referee: None,
+            replay: None,
+            events: Vec::new(),
+            queued_explosions: Vec::new(),
+            queued_impulses: Vec::new(),
+            queued_commands: Vec::new(),
+            applied_commands: Vec::new(),
+            scripted_commands: 0,
+            queued_reloads: Vec::new(),
+            lifecycle: Lifecycle::default(),
+            visibility: Visibility::default(),
+            sensors: BTreeMap::new(),
+            debug: DebugDraw::default(),
+            log: SimLog::default(),
+        };
+        engine.update_sensors();
+        engine
+    }
+
+    /// Switches collection of a kind of debug primitive on or off, from the next tick on.
+    pub fn set_debug_draw(&mut self, category: DebugCategory, enabled: bool) {
+        self.debug.set_enabled(category, enabled);
+    }
+
+    /// Returns the debug primitives collected during the last tick.
+    pub fn debug_draw(&self) -> &DebugDraw {
+        &self.debug
+    }
+
+    /// Returns the diagnostics written so far.
+    pub fn log(&self) -> &SimLog {
+        &self.log
+    }
+
+    /// For changing what the log keeps, or for hosts writing their own diagnostics next to
+    /// the engine's.
+    pub fn log_mut(&mut self) -> &mut SimLog {
+        &mut self.log
+    }
+
+    /// Sets off an explosion during the next tick, right after any projectiles detonate.
+    pub fn explode(&mut self, center: Vec2, spec: ExplosionSpec) {
+        self.queued_explosions.push(Explosion {
+            cause: ExplosionCause::External,
+            attacker: None,
+            center,
+            spec,
+        });
+    }
+
+    /// Pushes a tank during the next tick, after blasts are resolved. Fails if the tank doesn't
+    /// exist.
+    pub fn apply_impulse(&mut self, tank_id: u32, impulse: Vec2) -> Result<(), SimError> {
+        self.require_tank(tank_id)?;
+        self.queued_impulses.push((tank_id, impulse));
+        Ok(())
+    }
+
+    /// Replaces the match's scenario. Actions scheduled for ticks already played never happen.
+    pub fn set_scenario(&mut self, scenario: Option<Scenario>) {
+        self.rules.scenario = scenario;
+    }
+
+    /// Hands judgement of damage and deaths to house rules, or back to the built-in rules with
+    /// `None`. Referees aren't part of saves or replays, so set the same one again when resuming.
+    pub fn set_referee(&mut self, referee: Option<Box<dyn Referee>>) {
+        self.referee = referee;
+    }
+
+    /// Returns the safe zone as it stands this tick, if the match has one.
+    pub fn zone(&self) -> Option<Zone> {
+        self.rules
+            .zone
+            .as_ref()
+            .map(|zone| zone.at(self.state.time))
+    }
+
+    /// Allows or refuses cheat commands for the rest of the match.
+    pub fn set_cheats(&mut self, enabled: bool) {
+        self.rules.cheats = enabled;
+    }
+
+    pub fn cheats_enabled(&self) -> bool {
+        self.rules.cheats
+    }
+
+    /// Turns respawning on or off for the rest of the match.
+    pub fn set_respawn(&mut self, config: Option<RespawnConfig>) {
+        self.rules.respawn = config;
+    }
+
+    /// Returns the objective progress: scores, flags, hill and winner.
+    pub fn mode(&self) -> &ModeState {
+        &self.state.mode
+    }
+
+    /// Returns whether the match is over: a mode has declared a winner, or at most one team
+    /// still has tanks in the fight or waiting to respawn.
+    pub fn is_decided(&self) -> bool {
+        self.state.mode.winner.is_some()
+            || (!self.state.tanks.is_empty() && self.teams_standing().len() <= 1)
+    }
+
+    /// Returns the team that won, either declared by the mode or the last one standing. `None`
+    /// while the match is undecided, or if it ended with nobody left.
+    pub fn winner(&self) -> Option<u32> {
+        if let Some(winner) = self.state.mode.winner {
+            return Some(winner);
+        }
+        let teams = self.teams_standing();
+        match (self.is_decided(), teams.first()) {
+            (true, Some(team_id)) => Some(*team_id),
+            _ => None,
+        }
+    }
+
+    /// Returns the teams with tanks in the fight or waiting to respawn.
+    fn teams_standing(&self) -> BTreeSet<u32> {
+        self.state
+            .tanks
+            .iter()
+            .filter(|tank| tank.is_alive() || tank.respawn_in.is_some())
+            .map(|tank| tank.team_id)
+            .collect()
+    }
+
+    /// Returns how ticks map onto simulated seconds in this match.
+    pub fn clock(&self) -> &SimClock {
+        &self.clock
+    }
+
+    /// Returns the simulated seconds since the match began.
+    pub fn seconds(&self) -> Scalar {
+        self.clock.to_seconds(self.state.time)
+    }
+
+    /// Returns the current simulation state.
+    pub fn state(&self) -> &SimState {
+        &self.state
+    }
+
+    /// Checks the match state's invariants against this match's tank classes and arena. See
+    /// [`SimState::validate`].
+    pub fn validate(&self) -> Result<(), Vec<InvariantViolation>> {
+        self.state.validate(&self.specs, &self.arena.bounds())
+    }
+
+    /// Returns a mutable reference to a tank, for debugging tools that edit the match directly.
+    pub fn tank_mut(&mut self, tank_id: u32) -> Option<&mut Tank> {
+        self.state.tank_mut(tank_id)
+    }
+
+    /// Replaces the whole match state, e.g. with a snapshot taken earlier.
+    ///
+    /// Static lookups are rebuilt from the new state's obstacles, and sensors are refreshed.
+    /// Controllers stay attached to whichever tank IDs they were attached to. Statistics are
+    /// left alone, so after a rewind they still cover every tick that was simulated.
+    pub fn restore(&mut self, state: SimState) {
+        self.arena = Arena::new(self.arena.width(), self.arena.height(), &state.obstacles);
+        self.nav = build_nav(
+            &self.arena,
+            &self.specs,
+            &state.obstacles,
+            self.grid.nav_cell_size,
+        );
+        self.triggers = TriggerIndex::new(self.arena.width(), self.arena.height(), &state.triggers);
+        self.broadphase = TankBroadphase::new(
+            self.arena.width(),
+            self.arena.height(),
+            self.grid.broadphase_cell_size,
+        );
+        self.state = state;
+        self.events.clear();
+        self.update_sensors();
+    }
+
+    /// Starts recording a snapshot every `interval` ticks, keeping the most recent `capacity`.
+    ///
+    /// Any previously recorded history is discarded.
+    pub fn enable_history(&mut self, interval: u64, capacity: usize) {
+        self.history = History::new(interval, capacity);
+    }
+
+    /// Returns the snapshots recorded so far.
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Returns the timelines abandoned by [`SimEngine::rewind`], oldest first.
+    pub fn timelines(&self) -> &[FrozenTimeline] {
+        &self.timelines
+    }
+
+    /// Rewinds the match to an earlier tick, branching a new timeline from there.
+    ///
+    /// The current timeline is frozen and kept in [`SimEngine::timelines`]. The match is
+    /// restored from the latest snapshot at or before `tick` and re-simulated up to it, so with
+    /// an interval above one, commands issued between snapshots are not replayed. A replay being
+    /// recorded is cut back to the snapshot and records the new timeline from there. Fails,
+    /// changing nothing, if `tick` is in the future or older than the history.
+    pub fn rewind(&mut self, tick: u64) -> Result<(), SimError> {
+        let snapshot = self
+            .history
+            .latest_at(tick)
+            .filter(|_| tick <= self.state.time)
+            .cloned()
+            .ok_or(SimError::TickUnavailable(tick))?;
+
+        self.timelines.push(FrozenTimeline::new(
+            self.history.snapshots().cloned().collect(),
+            self.state.clone(),
+        ));
+        self.history.truncate_after(snapshot.time);
+        if let Some(replay) = self.replay.as_mut() {
+            replay.truncate_after(snapshot.time);
+        }
+        self.restore(snapshot);
+        while self.state.time < tick {
+            self.step();
+        }
+        Ok(())
+    }
+
+    /// Returns statistics aggregated over every tick simulated so far.
+    pub fn stats(&self) -> &MatchStats {
+        &self.stats
+    }
+
+    /// Starts recording a replay from the current tick, with a full keyframe every
+    /// `keyframe_interval` ticks. Any replay being recorded is discarded.
+    pub fn start_replay(&mut self, keyframe_interval: u64) {
+        let mut replay = Replay::new(keyframe_interval);
+        replay.record(&self.state, &[], &[], &self.clock);
+        self.replay = Some(replay);
+    }
+
+    /// Returns the replay recorded so far, if recording.
+    pub fn replay(&self) -> Option<&Replay> {
+        self.replay.as_ref()
+    }
+
+    /// Stops recording and hands over the replay.
+    pub fn take_replay(&mut self) -> Option<Replay> {
+        self.replay.take()
+    }
+
+    /// Captures the match between ticks, so it can be resumed with [`SimEngine::from_save`].
+    ///
+    /// Fails if a tank is run by a bot that doesn't support saving.
+    pub fn save(&self) -> Result<MatchSave, SaveError> {
+        let mut config = self.config.clone();
+        config.rules = self.rules.clone();
+        let controllers = self
+            .controllers
+            .iter()
+            .map(|(&tank_id, controller)| {
+                let saved = match controller {
+                    Controller::Program(program) => SavedController::Program(program.code.clone()),
+                    Controller::Bot(bot) => {
+                        SavedController::Bot(bot.save().ok_or(SaveError::UnsavableBot { tank_id })?)
+                    }
+                    Controller::Agent(action) => SavedController::Agent(*action),
+                    Controller::Network(network) => SavedController::Network(network.clone()),
+                    Controller::Manual(input) => SavedController::Manual(*input),
+                };
+                Ok((tank_id, saved))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(MatchSave {
+            config,
+            state: self.state.clone(),
+            stats: self.stats.clone(),
+            controllers,
+            overrides: self
+                .overrides
+                .iter()
+                .map(|(&tank_id, channels)| (tank_id, *channels))
+                .collect(),
+            team_roms: self
+                .team_roms
+                .iter()
+                .map(|(&team_id, rom)| (team_id, rom.clone()))
+                .collect(),
+            queued_commands: self.queued_commands.clone(),
+            replay: self.replay.clone(),
+        })
+    }
+
+    /// Resumes a match from a save. A replay that was being recorded carries on recording.
+    pub fn from_save(save: MatchSave) -> Result<SimEngine, SaveError> {
+        let config = save.config.validate().map_err(SaveError::Config)?;
+        let mut engine = SimEngine::from_config(save.state, &config);
+        let execution = config.get().vm_execution;
+        engine.controllers = save
+            .controllers
+            .into_iter()
+            .map(|(tank_id, saved)| {
+                let controller = match saved {
+                    SavedController::Program(code) => {
+                        Controller::Program(LoadedProgram::new(code, execution))
+                    }
+                    SavedController::Bot(bot) => Controller::Bot(bot.into_bot()),
+                    SavedController::Agent(action) => Controller::Agent(action),
+                    SavedController::Network(network) => Controller::Network(network),
+                    SavedController::Manual(input) => Controller::Manual(input),
+                };
+                (tank_id, controller)
+            })
+            .collect();
+        engine.overrides = save.overrides.into_iter().collect();
+        engine.team_roms = save.team_roms.into_iter().collect();
+        engine.stats = save.stats;
+        engine.queued_commands = save.queued_commands;
+        engine.replay = save.replay;
+        Ok(engine)
+    }
+
+    /// Starts streaming a frame to the sink at the end of every tick, replacing any previous
+    /// sink. The previous sink is flushed and returned.
+    ///
+    /// A sink that fails to record is detached, so broken telemetry never stops the match.
+    pub fn set_telemetry(
+        &mut self,
+        sink: Option<Box<dyn TelemetrySink>>,
+    ) -> Option<Box<dyn TelemetrySink>> {
+        let mut previous = std::mem::replace(&mut self.telemetry, sink);
+        if let Some(previous) = previous.as_mut() {
+            // nothing left to do about a failed flush of a sink being let go
+            let _ = previous.flush();
+        }
+        previous
+    }
+
+    /// Adds a trigger volume to the match and returns its ID.
+    ///
+    /// Tanks already inside are reported as entering at the end of the next tick.
+    pub fn add_trigger(&mut self, shape: TriggerShape) -> u32 {
+        let id = self.state.allocate_id();
+        self.state.triggers.push(Trigger::new(id, shape));
+        self.rebuild_triggers();
+        id
+    }
+
+    /// Removes a trigger volume without reporting anyone leaving it.
+    ///
+    /// Fails if no trigger has the given ID.
+    pub fn remove_trigger(&mut self, trigger_id: u32) -> Result<(), SimError> {
+        let count = self.state.triggers.len();
+        self.state
+            .triggers
+            .retain(|trigger| trigger.id != trigger_id);
+        if self.state.triggers.len() == count {
+            return Err(SimError::UnknownTrigger(trigger_id));
+        }
+        self.rebuild_triggers();
+        Ok(())
+    }
+
+    /// Returns the trigger with the given ID, including who is inside it.
+    pub fn trigger(&self, trigger_id: u32) -> Option<&Trigger> {
+        self.state
+            .triggers
+            .iter()
+            .find(|trigger| trigger.id == trigger_id)
+    }
+
+    fn rebuild_triggers(&mut self) {
+        self.triggers = TriggerIndex::new(
+            self.arena.width(),
+            self.arena.height(),
+            &self.state.triggers,
+        );
+    }
+
+    /// Returns the events produced by the most recent tick.
+    pub fn events(&self) -> &[SimEvent] {
+        &self.events
+    }
+
+    /// Returns the arena's static geometry.
+    pub fn arena(&self) -> &Arena {
+        &self.arena
+    }
+
+    /// Returns the smallest box holding every live tank, for a camera to frame, or `None` when
+    /// no tanks are left.
+    pub fn bounding_box_of_action(&self) -> Option<AABB> {
+        spectator::action_bounds(&self.state, &self.specs)
+    }
+
+    /// Returns a summary of the entity with the given ID, whatever kind it is.
+    pub fn entity_by_id(&self, entity_id: u32) -> Option<EntitySummary> {
+        spectator::entity(&self.state, entity_id)
+    }
+
+    /// Returns summaries of the entities in an area, in ID order.
+    pub fn entities_in_aabb(&self, area: &AABB) -> Vec<EntitySummary> {
+        spectator::entities_in_aabb(&self.state, area)
+    }
+
+    /// Returns the IDs of the trigger volumes containing a point, lowest first.
+    pub fn triggers_at(&self, point: Vec2) -> Vec<u32> {
+        self.triggers.containing(point)
+    }
+
+    /// Returns the IDs of live, solid tanks whose hulls might reach into a circle, lowest first.
+    /// Hulls are approximated by the boxes they could cover at any heading.
+    pub fn tanks_in_circle(&mut self, center: Vec2, radius: Scalar) -> Vec<u32> {
+        let slots = self
+            .broadphase
+            .query_circle(&self.state.tanks, &self.specs, center, radius);
+        let mut ids: Vec<u32> = slots
+            .into_iter()
+            .map(|slot| self.state.tanks[slot].id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Returns the IDs of live, solid tanks whose hull bounds the segment `start -> end` passes
+    /// through, in the order it reaches them.
+    pub fn tanks_on_segment(&mut self, start: Vec2, end: Vec2) -> Vec<u32> {
+        let slots = self
+            .broadphase
+            .query_segment(&self.state.tanks, &self.specs, start, end);
+        slots
+            .into_iter()
+            .map(|slot| self.state.tanks[slot].id)
+            .collect()
+    }
+
+    /// Returns the IDs of live, solid tanks centred within `radius` of `center` and within half
+    /// of `arc` either side of `facing`, lowest first.
+    pub fn tanks_in_arc(
+        &mut self,
+        center: Vec2,
+        radius: Scalar,
+        facing: Angle,
+        arc: Scalar,
+    ) -> Vec<u32> {
+        let slots =
+            self.broadphase
+                .query_arc(&self.state.tanks, &self.specs, center, radius, facing, arc);
+        let mut ids: Vec<u32> = slots
+            .into_iter()
+            .map(|slot| self.state.tanks[slot].id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Returns the IDs of up to `k` live, solid tanks that pass `filter`, nearest first.
+    pub fn nearest_tanks(
+        &mut self,
+        center: Vec2,
+        k: usize,
+        filter: impl Fn(&Tank) -> bool,
+    ) -> Vec<u32> {
+        let slots = self
+            .broadphase
+            .k_nearest(&self.state.tanks, &self.specs, center, k, filter);
+        slots
+            .into_iter()
+            .map(|slot| self.state.tanks[slot].id)
+            .collect()
+    }
+
+    /// Summarises how tanks are spread over the collision grid, as of the last tick, to spot
+    /// cell sizes that don't suit the tanks in play.
+    pub fn broadphase_occupancy(&self) -> OccupancyStats {
+        self.broadphase.occupancy()
+    }
+
+    /// Returns the collision grid's columns and rows.
+    pub fn broadphase_grid_size(&self) -> (u32, u32) {
+        self.broadphase.grid_size()
+    }
+
+    /// Returns how many tanks were in each collision grid cell as of the last tick, in
+    /// row-major order, e.g. to draw their density without going through the tanks.
+    pub fn broadphase_counts(&self) -> Vec<u32> {
+        self.broadphase.cell_counts()
+    }
+
+    /// Like [`SimEngine::broadphase_occupancy`], with the grid's dimensions, so a better
+    /// `broadphase_cell_size` can be suggested (see [`GridStats::suggested_cell_size`]).
+    pub fn broadphase_stats(&self) -> GridStats {
+        self.broadphase.stats()
+    }
+
+    /// Returns the grid used to plan paths for tank programs.
+    pub fn nav(&self) -> &NavGrid {
+        &self.nav
+    }
+
+    /// Returns which enemies every tank and team could see at the end of the last tick.
+    pub fn visibility(&self) -> &Visibility {
+        &self.visibility
+    }
+
+    /// Returns what a tank's sensors picked up at the end of the last tick.
+    pub fn sensors(&self, tank_id: u32) -> Option<&SensorData> {
+        self.sensors.get(&tank_id)
+    }
+
+    /// Computes which parts of the arena a team currently has radar coverage over.
+    pub fn fog_of_war(&self, team_id: u32, cell_size: Scalar) -> FogMask {
+        FogMask::compute(
+            team_id,
+            &self.state.tanks,
+            &self.specs,
+            &self.arena,
+            &self.occluders(),
+            cell_size,
+        )
+    }
+
+    /// Indexes the smoke that blocks radar right now.
+    fn occluders(&self) -> Occluders {
+        Occluders::new(
+            self.arena.width(),
+            self.arena.height(),
+            &self.state.tanks,
+            &self.state.obscurants,
+        )
+    }
+
+    /// Returns the tank classes and weapons available in this match.
+    pub fn specs(&self) -> &SpecTable {
+        &self.specs
+    }
+
+    /// Spawns a tank after checking its loadout against the match rules.
+    ///
+    /// Returns the new tank's ID.
+    pub fn spawn_tank(&mut self, spawn: TankSpawn) -> Result<u32, SimError> {
+        self.rules
+            .validate_loadout(&self.specs, &self.state, spawn.team_id, &spawn.loadout)?;
+
+        // validation guarantees the spec exists
+        let spec = self
+            .specs
+            .tank(spawn.loadout.spec_id)
+            .ok_or(LoadoutError::UnknownTankSpec(spawn.loadout.spec_id))?;
+        let id = self.state.allocate_id();
+        let tank = Tank::new(
+            id,
+            spawn.team_id,
+            spec,
+            spawn.loadout,
+            spawn.position,
+            spawn.angle,
+        );
+        self.state.tanks.push(tank);
+
+        Ok(id)
+    }
+
+    /// Loads a bytecode program to control a tank, replacing any previous controller.
+    ///
+    /// `code` is bare bytecode or a bot package, which is checked against this sim's VM and
+    /// upgraded if it was built for an older instruction set, then
+    /// [verified](crate::vm::verify) against the match's limits. The program starts from
+    /// scratch on the next tick. Fails if no tank has the given ID, the package can't be run
+    /// or the program is sure to fault.
+    pub fn load_program(&mut self, tank_id: u32, code: Vec<u8>) -> Result<(), SimError> {
+        let code = package::unpack(&code)?;
+        verify::verify(&code, &abi::platform(&self.limits))?;
+        let tank = self
+            .state
+            .tank_mut(tank_id)
+            .ok_or(BotLoadError::UnknownTank(tank_id))?;
+        tank.vm = VmState::with_sizes(
+            self.limits.memory_words,
+            self.limits.stack_words,
+            self.limits.call_depth,
+        );
+        let program = LoadedProgram::new(code, self.config.vm_execution);
+        self.controllers
+            .insert(tank_id, Controller::Program(program));
+        Ok(())
+    }
+
+    /// Maps constant data into the address space of every program on a team, from
+    /// [`abi::TEAM_ROM`] on, replacing the team's previous ROM. Programs see it from the next
+    /// tick, and an empty ROM unmaps it.
+    ///
+    /// The ROM counts once against the team's [`BotLimits::team_rom_words`], however many
+    /// tanks read it. Fails if it's over that limit.
+    pub fn load_team_rom(&mut self, team_id: u32, rom: Vec<u32>) -> Result<(), SimError> {
+        let limit = self.limits.team_rom_words;
+        if rom.len() > limit as usize {
+            return Err(BotLoadError::TeamRomTooLarge {
+                team_id,
+                words: rom.len().min(u32::MAX as usize) as u32,
+                limit,
+            }
+            .into());
+        }
+        if rom.is_empty() {
+            self.team_roms.remove(&team_id);
+        } else {
+            self.team_roms.insert(team_id, rom);
+        }
+        Ok(())
+    }
+
+    /// Returns a team's ROM, empty if it hasn't loaded one.
+    pub fn team_rom(&self, team_id: u32) -> &[u32] {
+        self.team_roms.get(&team_id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Fails with the fault that stopped a tank's program, if one has.
+    pub fn check_program(&self, tank_id: u32) -> Result<(), SimError> {
+        let tank = self
+            .state
+            .tank(tank_id)
+            .ok_or(BotLoadError::UnknownTank(tank_id))?;
+        match tank.vm.fault {
+            Some(fault) => Err(SimError::VmFault { tank_id, fault }),
+            None => Ok(()),
+        }
+    }
+
+    /// Fails if this engine's state doesn't hash to `expected`, e.g. the checksum a lockstep
+    /// peer or a recording has for the same tick.
+    pub fn verify_checksum(&self, expected: u64) -> Result<(), SimError> {
+        let actual = self.state.checksum();
+        if actual != expected {
+            return Err(SimError::DesyncDetected {
+                tick: self.state.time,
+                expected,
+                actual,
+            });
+        }
+        Ok(())
+    }
+
+    /// Queues a command to apply at the start of the next tick.
+    pub fn queue_command(&mut self, command: Command) {
+        self.queued_commands.push(command);
+    }
+
+    /// Returns the commands applied at the start of the last tick, in order. Together with the
+    /// state before it, they're all it takes to replay the tick.
+    pub fn applied_commands(&self) -> &[Command] {
+        &self.applied_commands
+    }
+
+    /// Returns the commands applied at the start of the last tick that were queued from
+    /// outside rather than scripted by the scenario, i.e. what a replay records.
+    pub fn input_commands(&self) -> &[Command] {
+        &self.applied_commands[self.scripted_commands..]
+    }
+
+    /// Swaps a tank's program for a new one at the start of the next tick, without restarting
+    /// the match.
+    ///
+    /// The new program starts from its first instruction with an empty stack, and a halted or
+    /// faulted VM runs again. `policy` decides whether RAM survives the swap. `code` may be a
+    /// bot package, and is verified, as for [`SimEngine::load_program`]. Fails if no tank has
+    /// the given ID, the package can't be run or the program is sure to fault.
+    pub fn reload_program(
+        &mut self,
+        tank_id: u32,
+        code: Vec<u8>,
+        policy: ReloadPolicy,
+    ) -> Result<(), SimError> {
+        self.require_tank(tank_id)?;
+        let code = package::unpack(&code)?;
+        verify::verify(&code, &abi::platform(&self.limits))?;
+        self.queued_reloads.push((tank_id, code, policy));
+        Ok(())
+    }
+
+    /// Hands a tank over to a native controller, replacing any previous controller.
+    ///
+    /// Fails if no tank has the given ID.
+    pub fn set_bot(&mut self, tank_id: u32, bot: Box<dyn BotController>) -> Result<(), SimError> {
+        self.require_tank(tank_id)?;
+        self.controllers.insert(tank_id, Controller::Bot(bot));
+        Ok(())
+    }
+
+    /// Hands a tank over to a learning agent, replacing any previous controller.
+    ///
+    /// The tank idles until the agent sends an action with [`SimEngine::set_agent_action`] or
+    /// [`Command::SetAgentAction`]. Fails if no tank has the given ID.
+    pub fn attach_agent(&mut self, tank_id: u32) -> Result<(), SimError> {
+        self.require_tank(tank_id)?;
+        self.controllers
+            .insert(tank_id, Controller::Agent(AgentAction::default()));
+        Ok(())
+    }
+
+    /// Hands a tank over to a human, replacing any previous controller.
+    ///
+    /// The tank idles until input arrives through [`Command::SetManualInput`], which goes
+    /// through the command queue so replays and lockstep peers see it like any other command.
+    /// Fails if no tank has the given ID.
+    pub fn take_manual_control(&mut self, tank_id: u32) -> Result<(), SimError> {
+        self.require_tank(tank_id)?;
+        self.controllers
+            .insert(tank_id, Controller::Manual(BotCommand::default()));
+        Ok(())
+    }
+
+    /// Sets the input a manually controlled tank follows from the next tick onwards.
+    ///
+    /// Returns `false` if the tank isn't under manual control.
+    fn set_manual_input(&mut self, tank_id: u32, input: BotCommand) -> bool {
+        match self.controllers.get_mut(&tank_id) {
+            Some(Controller::Manual(current)) => {
+                *current = input;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Takes channels of a tank's controls from its controller from the next tick onwards,
+    /// replacing any taken before, or hands them all back if `channels` is empty. Goes through
+    /// [`Command::SetOverride`] when it should be recorded.
+    ///
+    /// Fails if no tank has the given ID.
+    pub fn set_override(
+        &mut self,
+        tank_id: u32,
+        channels: ControlOverride,
+    ) -> Result<(), SimError> {
+        self.require_tank(tank_id)?;
+        if channels.is_empty() {
+            self.overrides.remove(&tank_id);
+        } else {
+            self.overrides.insert(tank_id, channels);
+        }
+        Ok(())
+    }
+
+    /// Returns the channels a human has taken from a tank's controller, if any.
+    pub fn control_override(&self, tank_id: u32) -> Option<&ControlOverride> {
+        self.overrides.get(&tank_id)
+    }
+
+    fn require_tank(&self, tank_id: u32) -> Result<(), BotLoadError> {
+        match self.state.tank(tank_id) {
+            Some(_) => Ok(()),
+            None => Err(BotLoadError::UnknownTank(tank_id)),
+        }
+    }
+
+    /// Sets the action an agent's tank follows from the next tick onwards.
+    ///
+    /// Fails if the tank doesn't exist or isn't controlled by an agent.
+    pub fn set_agent_action(&mut self, tank_id: u32, action: AgentAction) -> Result<(), SimError> {
+        self.require_tank(tank_id)?;
+        match self.controllers.get_mut(&tank_id) {
+            Some(Controller::Agent(current)) => {
+                *current = action;
+                Ok(())
+            }
+            _ => Err(SimError::NotAnAgent(tank_id)),
+        }
+    }
+
+    /// Builds a tank's observation for a learning agent, from last tick's sensors.
+    ///
+    /// Returns `None` if no tank has the given ID.
+    pub fn observation(&self, tank_id: u32, spec: &ObservationSpec) -> Option<Vec<f32>> {
+        let tank = self.state.tank(tank_id)?;
+        let tank_spec = self.specs.tank(tank.loadout.spec_id)?;
+        let no_contacts = SensorData::default();
+        let sensors = self.sensors.get(&tank_id).unwrap_or(&no_contacts);
+        Some(agent::observe(spec, tank, tank_spec, sensors, &self.arena))
+    }
+
+    /// Hands a tank over to a trained network, replacing any previous controller.
+    ///
+    /// Fails if no tank has the given ID.
+    pub fn set_network(
+        &mut self,
+        tank_id: u32,
+        network: NetworkController,
+    ) -> Result<(), SimError> {
+        self.require_tank(tank_id)?;
+        self.controllers
+            .insert(tank_id, Controller::Network(Box::new(network)));
+        Ok(())
+    }
+
+    /// Starts profiling a tank's program, attributing cycles to functions with the given table.
+    ///
+    /// Each tick's counts go out with its telemetry frame, and add up in the match stats.
+    /// Fails if no tank has the given ID.
+    pub fn enable_vm_profiling(
+        &mut self,
+        tank_id: u32,
+        functions: Vec<FunctionSymbol>,
+    ) -> Result<(), SimError> {
+        self.require_tank(tank_id)?;
+        self.vm_profiling.insert(tank_id, functions);
+        Ok(())
+    }
+
+    /// Spreads the broad phase and tank programs over this many threads, counting the caller's.
+    /// Results are identical whatever the count; more threads only pay off in big battles.
+    /// Without the `parallel` feature, everything stays on the calling thread. The extra threads
+    /// are spawned afresh for each parallel phase rather than kept around; see [`ScopedSplitter`].
+    pub fn set_threads(&mut self, threads: NonZeroUsize) {
+        self.splitter = ScopedSplitter::new(threads);
+    }
+
+    /// Turns per-phase timing of each tick on or off. It's always on while the config sets a
+    /// tick budget, and never on wasm32, which has no clock to time with.
+    pub fn set_phase_timing(&mut self, enabled: bool) {
+        self.watchdog.set_timing(enabled);
+    }
+
+    /// Returns where the last tick's wall-clock time went, if timing is on.
+    pub fn last_tick_timings(&self) -> Option<&PhaseTimings> {
+        self.watchdog.last_tick()
+    }
+
+    /// Stops profiling a tank's program. Returns `false` if it wasn't being profiled.
+    pub fn disable_vm_profiling(&mut self, tank_id: u32) -> bool {
+        self.vm_profiling.remove(&tank_id).is_some()
+    }
+
+    /// Releases a tank from its program or bot. Its last commands stay in effect.
+    pub fn clear_controller(&mut self, tank_id: u32) -> bool {
+        self.controllers.remove(&tank_id).is_some()
+    }
+
+    /// Sets the track commands a tank will use from the next tick onwards.
+    ///
+    /// Fails if no tank has the given ID.
+    pub fn set_drive_input(&mut self, tank_id: u32, input: DriveInput) -> Result<(), SimError> {
+        let tank = self
+            .state
+            .tank_mut(tank_id)
+            .ok_or(BotLoadError::UnknownTank(tank_id))?;
+        tank.drive = input;
+        Ok(())
+    }
+
+    /// Sets the aiming command a tank's turret will follow from the next tick onwards.
+    ///
+    /// Fails if no tank has the given ID.
+    pub fn set_turret_command(
+        &mut self,
+        tank_id: u32,
+        command: TurretCommand,
+    ) -> Result<(), SimError> {
+        let tank = self
+            .state
+            .tank_mut(tank_id)
+            .ok_or(BotLoadError::UnknownTank(tank_id))?;
+        tank.turret = command;
+        Ok(())
+    }
+
+    /// Sets which weapon slot a tank fires whenever it's reloaded, or `None` to hold fire.
+    ///
+    /// Fails if no tank has the given ID.
+    pub fn set_fire(&mut self, tank_id: u32, slot: Option<u32>) -> Result<(), SimError> {
+        let tank = self
+            .state
+            .tank_mut(tank_id)
+            .ok_or(BotLoadError::UnknownTank(tank_id))?;
+        tank.fire = slot;
+        Ok(())
+    }
+
+    /// Advances the simulation by one tick.
+    pub fn step(&mut self) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("step", tick = self.state.time).entered();
+        self.history.record(&self.state);
+        self.events.clear();
+        self.debug.clear();
+
+        self.watchdog.begin_tick(self.state.time);
+        self.watchdog.enter(Phase::Vm);
+        self.apply_queued_commands();
+        self.apply_queued_reloads();
+        let programs = self.run_controllers();
+        let since = self.events.len();
+        self.move_tanks();
+        self.update_effects();
+        obscurants::update(
+            &mut self.state.obscurants,
+            self.rules.wind,
+            &mut self.events,
+        );
+        self.watchdog.enter(Phase::NarrowPhase);
+        self.fire_weapons();
+        self.apply_lifecycle();
+        intercept::resolve(
+            &mut self.state.bullets,
+            &self.state.tanks,
+            &self.specs,
+            self.arena.width(),
+            self.arena.height(),
+            &mut self.events,
+        );
+        let mut explosions = self.move_bullets();
+        self.apply_lifecycle();
+        self.watchdog.enter(Phase::Events);
+        if let Some(config) = &self.rules.zone {
+            let mut judge = Judge::new(self.referee.as_deref_mut(), &self.state.tanks, &self.rules);
+            zone::update(
+                config,
+                self.state.time,
+                &mut self.state.tanks,
+                &mut judge,
+                &mut self.events,
+            );
+        }
+        explosions.append(&mut self.queued_explosions);
+        self.resolve_explosions(explosions, since);
+        self.apply_queued_impulses();
+        if let Some(config) = &self.rules.respawn {
+            let center = Vec2::new(
+                self.arena.width() / dec64!(2),
+                self.arena.height() / dec64!(2),
+            );
+            respawn::update(
+                config,
+                &self.rules.handicaps,
+                &mut self.state,
+                &self.specs,
+                center,
+                &mut self.events,
+            );
+        }
+        self.triggers.update(
+            &mut self.state.triggers,
+            &self.state.tanks,
+            &mut self.events,
+        );
+        modes::update(&self.rules.mode, &mut self.state, &mut self.events);
+        if let Some(referee) = self.referee.as_mut() {
+            referee.on_tick(&mut self.state, &mut self.events);
+        }
+        if let Some(config) = &self.rules.rounds {
+            rounds::update(config, &mut self.state, &self.specs, &mut self.events);
+        }
+        self.update_sensors();
+        if self.debug.is_active() && !self.watchdog.is_throttled() {
+            self.collect_debug_draw();
+        }
+        self.watchdog.end_tick(&mut self.events);
+        self.state.prune_tags();
+
+        self.state.time += 1;
+        #[cfg(debug_assertions)]
+        if let Err(violations) = self.validate() {
+            panic!(
+                "tick {} broke the state: {violations:?}",
+                self.state.time - 1
+            );
+        }
+        self.stats
+            .record_tick(&self.state, &self.events, &programs.vm_cycles);
+        self.stats.record_vm_profiles(&programs.vm_profiles);
+        if let Some(sink) = self.telemetry.as_mut() {
+            let mut frame = TelemetryFrame::capture(&self.state, &self.events, &self.clock);
+            frame.vm_profiles = programs.vm_profiles;
+            if let Err(error) = sink.record(&frame) {
+                self.telemetry = None;
+                self.log.record(
+                    self.state.time,
+                    Severity::Error,
+                    Subsystem::Telemetry,
+                    format!("detached telemetry sink: {error}"),
+                );
+            }
+        }
+        if let Some(replay) = self.replay.as_mut() {
+            replay.record(
+                &self.state,
+                &self.events,
+                &self.applied_commands[self.scripted_commands..],
+                &self.clock,
+            );
+        }
+        if let Some(referee) = self.referee.as_mut() {
+            for event in &self.events {
+                referee.on_event(&self.state, event);
+            }
+        }
+    }
+
+    /// Runs every live, unjammed tank's program against last tick's sensors, split across the
+    /// engine's threads, then applies what they did beyond driving their own tanks, in tank order.
+    ///
+    /// Returns how many instructions each program executed, and where profiled ones spent them.
+    fn run_programs(&mut self) -> ProgramReport {
+        let time = self.state.time;
+        let throttled = self.watchdog.is_throttled();
+        let context = ProgramContext {
+            time,
+            nav: &self.nav,
+            sensors: &self.sensors,
+            team_roms: &self.team_roms,
+            limits: &self.limits,
+        };
+        let (specs, profiling, rules) = (&self.specs, &self.vm_profiling, &self.rules);
+        // only programs go to other threads, so other controllers needn't be thread-safe
+        let codes: BTreeMap<u32, &LoadedProgram> = self
+            .controllers
+            .iter()
+            .filter_map(|(tank_id, controller)| match controller {
+                Controller::Program(program) => Some((*tank_id, program)),
+                _ => None,
+            })
+            .collect();
+        let runs = self.splitter.map_mut(&mut self.state.tanks, |_, tanks| {
+            tanks
+                .iter_mut()
+                .filter_map(|tank| {
+                    let code = codes.get(&tank.id)?;
+                    // an EMP holds the controls neutral, whoever is driving
+                    if !tank.is_alive() || effects::is_jammed(tank) {
+                        return None;
+                    }
+                    let spec = specs.tank(tank.loadout.spec_id)?;
+                    let factor = rules.handicap(tank.team_id).vm_budget;
+                    let budget = Handicap::scale(spec.vm_clock_speed, factor)
+                        .min(context.limits.instructions_per_tick);
+                    let functions = profiling.get(&tank.id).filter(|_| !throttled);
+                    Some(run_program(tank, code, budget, functions, &context))
+                })
+                .collect()
+        });
+
+        let mut programs = ProgramReport::default();
+        for run in runs {
+            programs.vm_cycles.push((run.tank_id, run.cycles));
+            if let Some(profile) = run.profile {
+                programs.vm_profiles.push((run.tank_id, profile));
+            }
+            for limit in run.exceeded {
+                self.events.push(SimEvent::LimitExceeded {
+                    tank_id: run.tank_id,
+                    limit,
+                });
+            }
+            if let Some((pc, fault)) = run.fault {
+                self.log.record(
+                    time,
+                    Severity::Warn,
+                    Subsystem::Vm,
+                    format!("tank {} faulted at pc {pc}: {fault:?}", run.tank_id),
+                );
+            }
+            for (missile_id, heading) in run.steering {
+                let missile =
+                    self.state.bullets.iter_mut().find(|bullet| {
+                        bullet.id == missile_id && bullet.owner == Some(run.tank_id)
+                    });
+                if let Some(missile) = missile {
+                    missile.heading = Some(heading);
+                }
+            }
+        }
+        programs
+    }
+
+    fn run_controllers(&mut self) -> ProgramReport {
+        let no_contacts = SensorData::default();
+        let programs = self.run_programs();
+
+        for tank in self.state.tanks.iter_mut() {
+            // an EMP holds the controls neutral, whoever is driving
+            if tank.is_alive() && effects::is_jammed(tank) {
+                tank.drive = DriveInput::default();
+                tank.turret = TurretCommand::Hold;
+                tank.fire = None;
+                continue;
+            }
+            let Some(controller) = self.controllers.get_mut(&tank.id) else {
+                if let Some(channels) = self.overrides.get(&tank.id) {
+                    channels.apply(tank);
+                }
+                continue;
+            };
+            let Some(spec) = self.specs.tank(tank.loadout.spec_id) else {
+                continue;
+            };
+            if !tank.is_alive() {
+                continue;
+            }
+            let sensors = self.sensors.get(&tank.id);
+
+            match controller {
+                // run by `run_programs`, before any other controller
+                Controller::Program(_) => {}
+                Controller::Bot(bot) => {
+                    let command = bot.think(&BotView {
+                        tick: self.state.time,
+                        tank,
+                        sensors: sensors.unwrap_or(&no_contacts),
+                    });
+                    tank.drive = command.drive;
+                    tank.turret = command.turret;
+                    tank.fire = command.fire;
+                }
+                Controller::Agent(action) => {
+                    let command = action.decode(tank, spec);
+                    tank.drive = command.drive;
+                    tank.turret = command.turret;
+                    tank.fire = command.fire;
+                }
+                Controller::Manual(input) => {
+                    tank.drive = input.drive;
+                    tank.turret = input.turret;
+                    tank.fire = input.fire;
+                }
+                Controller::Network(network) => {
+                    let observation = agent::observe(
+                        network.observation_spec(),
+                        tank,
+                        spec,
+                        sensors.unwrap_or(&no_contacts),
+                        &self.arena,
+                    );
+                    let command = network.act(&observation).decode(tank, spec);
+                    tank.drive = command.drive;
+                    tank.turret = command.turret;
+                    tank.fire = command.fire;
+                }
+            }
+            // overridden channels win over whatever the controller decided
+            if let Some(channels) = self.overrides.get(&tank.id) {
+                channels.apply(tank);
+            }
+        }
+
+        programs
+    }
+
+    /// Runs the drivetrains once, then integrates and resolves collisions over each substep.
+    ///
+    /// Sleeping tanks are skipped until they're told to drive or something disturbs them.
+    fn move_tanks(&mut self) {
+        self.watchdog.enter(Phase::Integrate);
+        for tank in self.state.tanks.iter_mut() {
+            if tank.is_alive() && tank.drive != DriveInput::default() {
+                tank.wake();
+            }
+            if tank.sleeping {
+                continue;
+            }
+            let Some(spec) = self.specs.tank(tank.loadout.spec_id) else {
+                continue;
+            };
+            // wrecks coast to a halt
+            let input = if tank.is_alive() {
+                tank.drive
+            } else {
+                DriveInput::default()
+            };
+            let speed = self.rules.handicap(tank.team_id).speed;
+            let output = if speed == dec64!(1) {
+                drivetrain::drive(&spec.drivetrain, &input, tank.rotation(), tank.velocity)
+            } else {
+                let drivetrain = DrivetrainSpec {
+                    max_speed: spec.drivetrain.max_speed * speed,
+                    max_reverse_speed: spec.drivetrain.max_reverse_speed * speed,
+                    ..spec.drivetrain.clone()
+                };
+                drivetrain::drive(&drivetrain, &input, tank.rotation(), tank.velocity)
+            };
+            tank.velocity = output.velocity;
+            let cap = effects::speed_cap(tank).map_or(self.rules.numeric.max_tank_speed, |cap| {
+                cap.min(self.rules.numeric.max_tank_speed)
+            });
+            tank.velocity = tank.velocity.clamp_length(cap);
+            tank.angular_velocity = output.angular_velocity;
+        }
+
+        let substeps = self.rules.substeps.max(1);
+        let dt = dec64!(1) / Scalar::from(substeps);
+        let mut touching = Vec::new();
+        for _ in 0..substeps {
+            self.watchdog.enter(Phase::Integrate);
+            for tank in self.state.tanks.iter_mut().filter(|tank| !tank.sleeping) {
+                tank.position = tank.position.mul_add(&tank.velocity, dt);
+                tank.angle = tank.angle + tank.angular_velocity * dt;
+            }
+            self.watchdog.enter(Phase::BroadPhase);
+            let pairs = self
+                .broadphase
+                .pairs_with(&self.state.tanks, &self.specs, &self.splitter);
+            self.watchdog.enter(Phase::NarrowPhase);
+            let mut judge = Judge::new(self.referee.as_deref_mut(), &self.state.tanks, &self.rules);
+            let contacts = ramming::resolve(
+                &mut self.state.tanks,
+                &self.specs,
+                pairs,
+                self.rules.ram_damage,
+                self.rules.numeric.penetration_slop,
+                &mut judge,
+                &mut self.events,
+            );
+            let friendly_hits = judge.into_friendly_hits();
+            self.settle_friendly_fire(friendly_hits);
+            // roughly where the hulls meet, which is close enough to draw
+            for (a, b, contact) in contacts {
+                let (first, second) = (&self.state.tanks[a], &self.state.tanks[b]);
+                let point = (first.position + second.position).scale(dec64!(0.5));
+                self.debug.contact(point, contact.normal);
+                touching.push((first.id, second.id));
+            }
+        }
+        self.watchdog.enter(Phase::Integrate);
+        self.state
+            .contacts
+            .update(&touching, self.state.time, &mut self.events);
+        for tank in self.state.tanks.iter_mut() {
+            sleep::update(tank);
+        }
+
+        // turrets aim against where the hull ends up
+        for tank in self.state.tanks.iter_mut() {
+            if let Some(spec) = self.specs.tank(tank.loadout.spec_id) {
+                tank.turret_angle =
+                    turret::slew(&spec.turret, &tank.turret, tank.angle, tank.turret_angle);
+            }
+        }
+    }
+
+    fn fire_weapons(&mut self) {
+        for tank in self.state.tanks.iter_mut() {
+            for reload in tank.reload.iter_mut() {
+                *reload = reload.saturating_sub(1);
+            }
+
+            let Some(slot) = tank.fire else {
+                continue;
+            };
+            let slot = slot as usize;
+            if !tank.is_alive()
+                || tank.is_ghost()
+                || tank.reload.get(slot).is_none_or(|reload| *reload > 0)
+            {
+                continue;
+            }
+            let (Some(spec), Some(weapon)) = (
+                self.specs.tank(tank.loadout.spec_id),
+                self.specs.weapon(tank.loadout.weapons[slot]),
+            ) else {
+                continue;
+            };
+
+            let barrel = tank.turret_transform().forward();
+            let origin = tank.muzzle(spec);
+            let velocity = barrel.scale(weapon.muzzle_speed);
+            tank.reload[slot] = weapon.reload_ticks;
+            self.lifecycle.spawn(Spawn::Shot {
+                tank_id: tank.id,
+                weapon_id: weapon.id,
+                muzzle: origin,
+                velocity,
+                angle: tank.turret_world_angle(),
+            });
+
+            // the hull is shoved back along the barrel and twisted away from where it points
+            if let Some(recoil) = &weapon.recoil {
+                let source = ImpulseSource::Recoil(weapon.id);
+                let push = barrel.scale(-recoil.impulse);
+                impulse::apply(tank, spec, push, source, &mut self.events);
+                let kick = -recoil.angular_kick * tank.turret_angle.sin();
+                impulse::apply_angular(tank, kick, source, &mut self.events);
+            }
+        }
+    }
+
+    /// Adds whatever was spawned during the phase that just ended, handing out IDs and logging
+    /// each as it enters the match.
+    fn apply_lifecycle(&mut self) {
+        if self.lifecycle.is_empty() {
+            return;
+        }
+        for spawn in self.lifecycle.take() {
+            let id = self.state.allocate_id();
+            match spawn {
+                Spawn::Shot {
+                    tank_id,
+                    weapon_id,
+                    muzzle,
+                    velocity,
+                    angle,
+                } => {
+                    self.state.bullets.insert(Bullet {
+                        id,
+                        weapon_id,
+                        origin: muzzle,
+                        position: muzzle,
+                        velocity,
+                        bounces: 0,
+                        owner: Some(tank_id),
+                        heading: None,
+                    });
+                    self.events.push(SimEvent::ShotFired {
+                        tank_id,
+                        bullet_id: id,
+                        weapon_id,
+                        muzzle,
+                        angle,
+                    });
+                }
+                Spawn::Smoke { smoke, center } => {
+                    self.state.obscurants.push(Obscurant {
+                        id,
+                        center,
+                        radius: smoke.radius,
+                        remaining: smoke.duration,
+                    });
+                    self.events.push(SimEvent::SmokeDeployed {
+                        obscurant_id: id,
+                        center,
+                        radius: smoke.radius,
+                    });
+                }
+            }
+        }
+    }
+
+    fn update_sensors(&mut self) {
+        let occluders = self.occluders();
+        self.visibility =
+            Visibility::compute(&self.state.tanks, &self.specs, &self.arena, &occluders);
+        let zone = self.zone();
+        self.sensors = self
+            .state
+            .tanks
+            .iter()
+            .filter(|tank| tank.is_alive())
+            .map(|tank| {
+                let mut sensors =
+                    SensorData::read(tank.id, &self.state.tanks, &self.visibility, zone);
+                sensors.missiles = sensors::datalink(tank.id, &self.state.bullets, &self.specs);
+                (tank.id, sensors)
+            })
+            .collect();
+    }
+
+    /// Moves projectiles and resolves their hits. Returns the blasts set off by those that stopped.
+    fn move_bullets(&mut self) -> Vec<Explosion> {
+        let mut judge = Judge::new(self.referee.as_deref_mut(), &self.state.tanks, &self.rules);
+        let SimState { tanks, bullets, .. } = &mut self.state;
+        let specs = &self.specs;
+        let arena = &self.arena;
+        let clearance = self.rules.numeric.surface_clearance;
+        let events = &mut self.events;
+        let debug = &mut self.debug;
+        let mut detonations = Vec::new();
+        let mut screens = Vec::new();
+        // tanks destroyed partway through still stop the rest of this tick's bullets, so it
+        // doesn't matter which bullet happened to be moved first
+        let solid: Vec<bool> = tanks
+            .iter()
+            .map(|tank| tank.is_alive() && !tank.is_ghost())
+            .collect();
+
+        bullets.retain_mut(|bullet| {
+            let Some(weapon) = specs.weapon(bullet.weapon_id) else {
+                return false;
+            };
+            if let (Some(guidance), Some(heading)) = (&weapon.guidance, bullet.heading) {
+                let speed = bullet.velocity.length();
+                let turned = Angle::of(bullet.velocity).step_towards(heading, guidance.turn_rate);
+                bullet.velocity = turned.direction().scale(speed);
+            }
+            // what's left of this tick's movement, shortened and turned by each bounce
+            let mut start = bullet.position;
+            let mut travel = bullet.velocity;
+
+            let (index, hit) = loop {
+                let end = start + travel;
+
+                // find the first hull the bullet passes through
+                let mut first_hit: Option<(usize, SegmentHit)> = None;
+                for (index, tank) in tanks.iter().enumerate() {
+                    let Some(spec) = specs.tank(tank.loadout.spec_id) else {
+                        continue;
+                    };
+                    if !solid[index] {
+                        continue;
+                    }
+                    let hull = tank.hull(spec);
+                    let Some(hit) = segment_vs_box(start, end, &hull.transform, hull.half_extents)
+                    else {
+                        continue;
+                    };
+                    if first_hit.is_none_or(|(_, best)| hit.fraction < best.fraction) {
+                        first_hit = Some((index, hit));
+                    }
+                }
+
+                // walls stop bullets before anything behind them, unless they bounce off
+                let bounces_left = weapon
+                    .ricochet
+                    .as_ref()
+                    .is_some_and(|ricochet| bullet.bounces < ricochet.max_bounces);
+                let wall_hit = if bounces_left {
+                    arena.raycast_walls(start, end)
+                } else {
+                    arena.raycast(start, end).map(|(id, hit)| (Some(id), hit))
+                };
+                if let Some((obstacle, wall_hit)) = wall_hit
+                    && first_hit.is_none_or(|(_, hit)| wall_hit.fraction <= hit.fraction)
+                {
+                    let material = match obstacle {
+                        Some(_) => ImpactMaterial::Obstacle,
+                        None => ImpactMaterial::Wall,
+                    };
+                    let contact = start.mul_add(&travel, wall_hit.fraction);
+                    debug.ray(start, contact);
+                    debug.contact(contact, wall_hit.normal);
+                    let Some(ricochet) = weapon.ricochet.as_ref().filter(|_| bounces_left) else {
+                        events.push(SimEvent::BulletStopped {
+                            bullet_id: bullet.id,
+                            position: contact,
+                            normal: wall_hit.normal,
+                            material,
+                        });
+                        let center = contact.mul_add(&wall_hit.normal, clearance);
+                        detonations.extend(detonation(weapon, bullet, center));
+                        screens.extend(weapon.smoke.clone().map(|smoke| (smoke, center)));
+                        return false;
+                    };
+                    let remaining = dec64!(1) - wall_hit.fraction;
+                    bullet.bounces += 1;
+                    bullet.velocity = bullet
+                        .velocity
+                        .reflect(&wall_hit.normal)
+                        .scale(ricochet.restitution);
+                    travel = travel
+                        .reflect(&wall_hit.normal)
+                        .scale(remaining * ricochet.restitution);
+                    // lift off the surface, so the next sweep doesn't start inside the wall
+                    start = contact.mul_add(&wall_hit.normal, clearance);
+                    events.push(SimEvent::Ricochet {
+                        bullet_id: bullet.id,
+                        position: contact,
+                        normal: wall_hit.normal,
+                        heading: Angle::of(bullet.velocity),
+                        material,
+                    });
+                    continue;
+                }
+
+                let Some(first_hit) = first_hit else {
+                    debug.ray(start, end);
+                    bullet.position = end;
+                    if !arena.bounds().contains(end) {
+                        return false;
+                    }
+                    if end.sub(&bullet.origin).is_longer_than(weapon.max_range) {
+                        detonations.extend(detonation(weapon, bullet, end));
+                        screens.extend(weapon.smoke.clone().map(|smoke| (smoke, end)));
+                        return false;
+                    }
+                    return true;
+                };
+                break first_hit;
+            };
+
+            let target = &mut tanks[index];
+            target.wake();
+            let Some(spec) = specs.tank(target.loadout.spec_id) else {
+                return false;
+            };
+            let point = start.mul_add(&travel, hit.fraction);
+            debug.ray(start, point);
+            let frame = target.transform();
+            debug.contact(point, frame.to_world_vector(hit.normal));
+            let direction = frame.to_local_vector(bullet.velocity).approx_normalize();
+            let impact = Impact {
+                side: ArmorSide::from_local_normal(hit.normal),
+                cos_incidence: -direction.dot(&hit.normal),
+                distance: point.sub(&bullet.origin).length(),
+            };
+
+            detonations.extend(detonation(weapon, bullet, point));
+            screens.extend(weapon.smoke.clone().map(|smoke| (smoke, point)));
+
+            // a wreck made earlier this tick soaks up the shot
+            let mut outcome = if target.is_alive() {
+                damage::resolve_hit(weapon, &spec.armor, &impact)
+            } else {
+                HitOutcome::Absorbed
+            };
+            let mut destroyed = false;
+            if let HitOutcome::Penetrated { damage } = &mut outcome {
+                let source = DamageSource::Bullet {
+                    bullet_id: bullet.id,
+                    shooter: bullet.owner,
+                };
+                (*damage, destroyed) = judge.deal(target, source, *damage);
+            }
+            events.push(SimEvent::Hit {
+                bullet_id: bullet.id,
+                shooter: bullet.owner,
+                target_id: target.id,
+                side: impact.side,
+                outcome,
+                position: point,
+            });
+            if let Some(effect) = &weapon.effect
+                && target.is_alive()
+                && outcome != HitOutcome::Ricochet
+            {
+                effects::apply(target, effect, bullet.owner, events);
+            }
+            if destroyed {
+                events.push(SimEvent::TankDestroyed {
+                    tank_id: target.id,
+                    killer: bullet.owner,
+                });
+            }
+
+            false
+        });
+
+        let friendly_hits = judge.into_friendly_hits();
+        self.settle_friendly_fire(friendly_hits);
+        for (smoke, center) in screens {
+            self.lifecycle.spawn(Spawn::Smoke { smoke, center });
+        }
+        detonations
+    }
+
+    /// Records the end-of-tick shapes and grid occupancy, for whichever categories are on.
+    fn collect_debug_draw(&mut self) {
+        if self.debug.is_enabled(DebugCategory::Shapes) {
+            for obstacle in &self.state.obstacles {
+                self.debug.aabb(obstacle.aabb);
+            }
+            for trigger in &self.state.triggers {
+                self.debug.aabb(trigger.shape.bounds());
+            }
+            for tank in &self.state.tanks {
+                let Some(spec) = self.specs.tank(tank.loadout.spec_id) else {
+                    continue;
+                };
+                self.debug.oriented_box(tank.hull(spec));
+            }
+        }
+        if self.debug.is_enabled(DebugCategory::Grid) {
+            self.debug.grid = Some(self.broadphase.heatmap());
+        }
+    }
+
+    /// Applies the scenario's commands for this tick, then any queued from outside.
+    fn apply_queued_commands(&mut self) {
+        let mut commands = match &self.rules.scenario {
+            Some(scenario) => scenario.commands_at(self.state.time),
+            None => Vec::new(),
+        };
+        self.scripted_commands = commands.len();
+        commands.append(&mut self.queued_commands);
+        for (index, command) in commands.iter().enumerate() {
+            if !self.apply_command(command.clone()) {
+                self.events.push(SimEvent::CommandRejected {
+                    index: index as u32,
+                });
+                self.log.record(
+                    self.state.time,
+                    Severity::Warn,
+                    Subsystem::Commands,
+                    format!("rejected command {index}: {command:?}"),
+                );
+            }
+        }
+        self.applied_commands = commands;
+    }
+
+    /// Applies one command, returning whether it took effect.
+    fn apply_command(&mut self, command: Command) -> bool {
+        if command.is_cheat() && !self.rules.cheats {
+            return false;
+        }
+        match command {
+            Command::SpawnTank(spawn) => {
+                let team_id = spawn.team_id;
+                let Ok(tank_id) = self.spawn_tank(spawn) else {
+                    return false;
+                };
+                self.events.push(SimEvent::TankSpawned { tank_id, team_id });
+                true
+            }
+            Command::SpawnBullet {
+                weapon_id,
+                position,
+                velocity,
+            } => {
+                if self.specs.weapon(weapon_id).is_none() {
+                    return false;
+                }
+                let id = self.state.allocate_id();
+                self.state.bullets.insert(Bullet {
+                    id,
+                    weapon_id,
+                    origin: position,
+                    position,
+                    velocity,
+                    bounces: 0,
+                    owner: None,
+                    heading: None,
+                });
+                true
+            }
+            Command::RemoveEntity { entity_id } => {
+                let removed = self.remove_entity(entity_id);
+                if removed {
+                    self.events.push(SimEvent::EntityRemoved { entity_id });
+                }
+                removed
+            }
+            Command::TagEntity { entity_id, tag } => {
+                if !self.state.has_entity(entity_id) {
+                    return false;
+                }
+                self.state.tags.add(entity_id, tag);
+                true
+            }
+            Command::UntagEntity { entity_id, tag } => self.state.tags.remove(entity_id, &tag),
+            Command::RemoveTagged { tag } => {
+                let mut removed = false;
+                for entity_id in self.state.tags.tagged(&tag) {
+                    if self.remove_entity(entity_id) {
+                        self.events.push(SimEvent::EntityRemoved { entity_id });
+                        removed = true;
+                    }
+                }
+                removed
+            }
+            Command::SetTeam { tank_id, team_id } => match self.state.tank_mut(tank_id) {
+                Some(tank) => {
+                    tank.team_id = team_id;
+                    true
+                }
+                None => false,
+            },
+            Command::SetDriveInput { tank_id, input } => {
+                self.set_drive_input(tank_id, input).is_ok()
+            }
+            Command::SetTurretCommand { tank_id, command } => {
+                self.set_turret_command(tank_id, command).is_ok()
+            }
+            Command::SetFire { tank_id, slot } => self.set_fire(tank_id, slot).is_ok(),
+            Command::SetAgentAction { tank_id, action } => {
+                self.set_agent_action(tank_id, action).is_ok()
+            }
+            Command::SetManualInput { tank_id, input } => self.set_manual_input(tank_id, input),
+            Command::SetOverride { tank_id, channels } => {
+                self.set_override(tank_id, channels).is_ok()
+            }
+            Command::ApplyEffect { tank_id, effect } => match self.state.tank_mut(tank_id) {
+                Some(tank) if tank.is_alive() && !tank.is_ghost() => {
+                    effects::apply(tank, &effect, None, &mut self.events);
+                    true
+                }
+                _ => false,
+            },
+            Command::Teleport { tank_id, position } => match self.state.tank_mut(tank_id) {
+                Some(tank) => {
+                    tank.position = position;
+                    tank.velocity = Vec2::zero();
+                    tank.angular_velocity = dec64!(0);
+                    tank.sleeping = false;
+                    tank.idle_ticks = 0;
+                    true
+                }
+                None => false,
+            },
+            Command::SetHealth { tank_id, health } => self.set_health(tank_id, health),
+            Command::GiveFlag {
+                tank_id,
+                flag_team_id,
+            } => modes::give_flag(&mut self.state, flag_team_id, tank_id, &mut self.events),
+            Command::Kill { entity_id } => {
+                if self.state.tank(entity_id).is_some() {
+                    return self.set_health(entity_id, 0);
+                }
+                let removed = self.remove_entity(entity_id);
+                if removed {
+                    self.events.push(SimEvent::EntityRemoved { entity_id });
+                }
+                removed
+            }
+        }
+    }
+
+    /// Sets a living tank's health, reporting its destruction if that kills it.
+    fn set_health(&mut self, tank_id: u32, health: u32) -> bool {
+        let Some(tank) = self.state.tank_mut(tank_id).filter(|tank| tank.is_alive()) else {
+            return false;
+        };
+        tank.health = health;
+        if health == 0 {
+            self.events.push(SimEvent::TankDestroyed {
+                tank_id,
+                killer: None,
+            });
+        }
+        true
+    }
+
+    /// Removes whatever entity has the given ID, returning whether there was one.
+    fn remove_entity(&mut self, entity_id: u32) -> bool {
+        if let Some(index) = self
+            .state
+            .tanks
+            .iter()
+            .position(|tank| tank.id == entity_id)
+        {
+            self.state.tanks.remove(index);
+            // shells still in flight are orphaned rather than credited to a tank that's gone
+            for bullet in self.state.bullets.iter_mut() {
+                if bullet.owner == Some(entity_id) {
+                    bullet.owner = None;
+                }
+            }
+            self.state.contacts.forget(entity_id);
+            self.controllers.remove(&entity_id);
+            self.overrides.remove(&entity_id);
+            self.vm_profiling.remove(&entity_id);
+            self.sensors.remove(&entity_id);
+            return true;
+        }
+        let bullet = self
+            .state
+            .bullets
+            .handles()
+            .find_map(|(handle, bullet)| (bullet.id == entity_id).then_some(handle));
+        if let Some(handle) = bullet {
+            self.state.bullets.remove(handle);
+            return true;
+        }
+        if let Some(index) = self
+            .state
+            .obstacles
+            .iter()
+            .position(|obstacle| obstacle.id == entity_id)
+        {
+            self.state.obstacles.remove(index);
+            self.arena = Arena::new(
+                self.arena.width(),
+                self.arena.height(),
+                &self.state.obstacles,
+            );
+            self.nav = build_nav(
+                &self.arena,
+                &self.specs,
+                &self.state.obstacles,
+                self.grid.nav_cell_size,
+            );
+            return true;
+        }
+        if let Some(index) = self
+            .state
+            .obscurants
+            .iter()
+            .position(|cloud| cloud.id == entity_id)
+        {
+            self.state.obscurants.remove(index);
+            return true;
+        }
+        self.remove_trigger(entity_id).is_ok()
+    }
+
+    fn apply_queued_reloads(&mut self) {
+        for (tank_id, code, policy) in std::mem::take(&mut self.queued_reloads) {
+            let Some(tank) = self.state.tank_mut(tank_id) else {
+                continue;
+            };
+            let memory = std::mem::take(&mut tank.vm.memory);
+            tank.vm = VmState::with_sizes(
+                self.limits.memory_words,
+                self.limits.stack_words,
+                self.limits.call_depth,
+            );
+            if policy == ReloadPolicy::KeepMemory {
+                tank.vm.memory = memory;
+            }
+            let program = LoadedProgram::new(code, self.config.vm_execution);
+            self.controllers
+                .insert(tank_id, Controller::Program(program));
+            self.events.push(SimEvent::ProgramReloaded { tank_id });
+        }
+    }
+
+    fn apply_queued_impulses(&mut self) {
+        for (tank_id, push) in std::mem::take(&mut self.queued_impulses) {
+            let Some(tank) = self.state.tanks.iter_mut().find(|tank| tank.id == tank_id) else {
+                continue;
+            };
+            let Some(spec) = self.specs.tank(tank.loadout.spec_id) else {
+                continue;
+            };
+            impulse::apply(tank, spec, push, ImpulseSource::External, &mut self.events);
+        }
+    }
+
+    /// Resolves this tick's blasts, plus the wrecks of tanks destroyed since the given event.
+    fn resolve_explosions(&mut self, mut explosions: Vec<Explosion>, since: usize) {
+        for event in &self.events[since..] {
+            let SimEvent::TankDestroyed { tank_id, killer } = *event else {
+                continue;
+            };
+            let Some(tank) = self.state.tank(tank_id) else {
+                continue;
+            };
+            let Some(spec) = self.specs.tank(tank.loadout.spec_id) else {
+                continue;
+            };
+            if let Some(wreck) = &spec.death_explosion {
+                explosions.push(Explosion {
+                    cause: ExplosionCause::Wreck(tank_id),
+                    attacker: killer,
+                    center: tank.position,
+                    spec: wreck.clone(),
+                });
+            }
+        }
+        let mut judge = Judge::new(self.referee.as_deref_mut(), &self.state.tanks, &self.rules);
+        explosions::resolve(
+            explosions,
+            &mut self.state.tanks,
+            &self.specs,
+            &self.arena,
+            &mut judge,
+            &mut self.events,
+        );
+        let friendly_hits = judge.into_friendly_hits();
+        self.settle_friendly_fire(friendly_hits);
+    }
+
+    /// Burns tanks that are on fire and counts their status effects down.
+    fn update_effects(&mut self) {
+        let mut judge = Judge::new(self.referee.as_deref_mut(), &self.state.tanks, &self.rules);
+        effects::update(&mut self.state.tanks, &mut judge, &mut self.events);
+        let friendly_hits = judge.into_friendly_hits();
+        self.settle_friendly_fire(friendly_hits);
+    }
+
+    /// Reports blows dealt to teammates, and deals reflected ones to the tanks that owe them.
+    fn settle_friendly_fire(&mut self, hits: Vec<FriendlyHit>) {
+        for hit in hits {
+            let mut damage = hit.damage;
+            let mut destroyed = false;
+            if hit.reflected {
+                let mut judge =
+                    Judge::new(self.referee.as_deref_mut(), &self.state.tanks, &self.rules);
+                let attacker =
+                    self.state.tanks.iter_mut().find(|tank| {
+                        tank.id == hit.attacker && tank.is_alive() && !tank.is_ghost()
+                    });
+                (damage, destroyed) = match attacker {
+                    Some(attacker) => judge.deal(attacker, hit.source, hit.damage),
+                    None => (0, false),
+                };
+            }
+            self.events.push(SimEvent::FriendlyFire {
+                attacker: hit.attacker,
+                target_id: hit.target_id,
+                damage,
+                reflected: hit.reflected,
+            });
+            if destroyed {
+                self.events.push(SimEvent::TankDestroyed {
+                    tank_id: hit.attacker,
+                    killer: Some(hit.attacker),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::ArenaConfig;
+    use crate::bots::{SittingDuck, Tracker};
+    use crate::config::SimConfig;
+    use crate::effects::{StatusEffectSpec, StatusKind};
+    use crate::network::{ActionHead, Activation, LayerFile, NetworkFile};
+    use crate::obscurants::SmokeSpec;
+    use crate::spec::{GuidanceSpec, RecoilSpec, RicochetSpec};
+    use crate::tags::Tag;
+    use crate::util::math::ConvertToScalar;
+    use crate::util::numeric;
+    use crate::vm::abi;
+    use crate::vm::isa::{Assembler, Opcode};
+    use crate::vm::verify::VerifyError;
+    use proptest::prelude::*;
+
+    fn spawn(engine: &mut SimEngine, team_id: u32, x: f64, angle: f64) -> u32 {
+        engine
+            .spawn_tank(TankSpawn {
+                angle: Angle::new(angle.to_scalar()),
+                ..TankSpawn::medium(team_id, Vec2::new_from_f64(x, 100.0))
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn step_when_program_loaded_should_drive_tank() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        let tank = spawn(&mut engine, 0, 100.0, 0.0);
+        let mut asm = Assembler::new();
+        let start = asm.label();
+        asm.bind(start)
+            .push(1 << 16)
+            .op(Opcode::Dup)
+            .push(abi::LEFT_TRACK)
+            .op(Opcode::Store)
+            .push(abi::RIGHT_TRACK)
+            .op(Opcode::Store)
+            .op(Opcode::Yield)
+            .jump(Opcode::Jmp, start);
+        engine.load_program(tank, asm.finish()).unwrap();
+
+        // Act
+        for _ in 0..10 {
+            engine.step();
+        }
+
+        // Assert
+        let tank = engine.state().tank(tank).unwrap();
+        assert_eq!(tank.drive, DriveInput::tracks(dec64!(1), dec64!(1)));
+        assert!(tank.position.x > dec64!(100));
+        assert_eq!(tank.vm.fault, None);
+    }
+
+    #[test]
+    fn engine_should_be_movable_to_another_thread() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        let tank = spawn(&mut engine, 0, 100.0, 0.0);
+        engine.set_bot(tank, Box::new(Tracker::default())).unwrap();
+
+        // Act
+        let engine = std::thread::spawn(move || {
+            engine.step();
+            engine
+        })
+        .join()
+        .unwrap();
+
+        // Assert
+        assert_eq!(engine.state().time, 1);
+    }
+
+    #[test]
+    fn broadphase_counts_should_cover_grid_and_count_tanks_where_they_are() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        spawn(&mut engine, 0, 100.0, 0.0);
+        spawn(&mut engine, 1, 900.0, 0.0);
+
+        // Act
+        engine.step();
+        let (columns, rows) = engine.broadphase_grid_size();
+        let counts = engine.broadphase_counts();
+
+        // Assert
+        assert_eq!(counts.len(), (columns * rows) as usize);
+        let occupied: Vec<usize> = (0..counts.len()).filter(|cell| counts[*cell] > 0).collect();
+        assert!(counts.iter().sum::<u32>() >= 2);
+        let column = |cell: usize| cell % columns as usize;
+        assert!(column(occupied[0]) < column(*occupied.last().unwrap()));
+    }
+
+    #[test]
+    fn load_program_when_program_sure_to_fault_should_reject_it() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        let tank = spawn(&mut engine, 0, 100.0, 0.0);
+        let code = Assembler::new()
+            .push(0x6000)
+            .op(Opcode::Load)
+            .op(Opcode::Halt)
+            .finish();
+
+        // Act
+        let result = engine.load_program(tank, code);
+
+        // Assert
+        assert!(matches!(
+            result,
+            Err(SimError::BotLoad(BotLoadError::Verify(
+                VerifyError::UnmappedRead {
+                    address: 0x6000,
+                    ..
+                }
+            )))
+        ));
+        assert!(!engine.controllers.contains_key(&tank));
+    }
+
+    #[test]
+    fn load_team_rom_should_share_it_with_teammates_only() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        let tanks = [
+            spawn(&mut engine, 0, 100.0, 0.0),
+            spawn(&mut engine, 0, 300.0, 0.0),
+            spawn(&mut engine, 1, 500.0, 0.0),
+        ];
+        let code = Assembler::new()
+            .push(abi::TEAM_ROM + 1)
+            .op(Opcode::Load)
+            .push(0)
+            .op(Opcode::Store)
+            .op(Opcode::Halt)
+            .finish();
+        for tank in tanks {
+            engine.load_program(tank, code.clone()).unwrap();
+        }
+
+        // Act
+        engine.load_team_rom(0, vec![7, 42]).unwrap();
+        engine.step();
+
+        // Assert
+        for tank in &tanks[..2] {
+            assert_eq!(engine.state().tank(*tank).unwrap().vm.memory[0], 42);
+        }
+        assert!(matches!(
+            engine.check_program(tanks[2]),
+            Err(SimError::VmFault {
+                fault: VmFault::BadAddress { .. },
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn load_team_rom_when_over_limit_should_keep_previous_rom() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        engine.load_team_rom(2, vec![1, 2, 3]).unwrap();
+        let limit = engine.limits.team_rom_words;
+
+        // Act
+        let result = engine.load_team_rom(2, vec![0; limit as usize + 1]);
+
+        // Assert
+        assert!(matches!(
+            result,
+            Err(SimError::BotLoad(BotLoadError::TeamRomTooLarge {
+                team_id: 2,
+                ..
+            }))
+        ));
+        assert_eq!(engine.team_rom(2), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn check_program_when_program_faulted_should_report_fault() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        let tank = spawn(&mut engine, 0, 100.0, 0.0);
+        // the depth after the branch depends on which way it went, so only running it shows
+        // the second pop underflowing
+        let mut asm = Assembler::new();
+        let skip = asm.label();
+        asm.push(1).jump(Opcode::Jz, skip).push(7);
+        asm.bind(skip)
+            .op(Opcode::Pop)
+            .op(Opcode::Pop)
+            .op(Opcode::Halt);
+        engine.load_program(tank, asm.finish()).unwrap();
+        let expected = engine.state().checksum();
+
+        // Act
+        engine.step();
+
+        // Assert
+        assert!(matches!(
+            engine.check_program(tank),
+            Err(SimError::VmFault {
+                fault: VmFault::StackUnderflow,
+                ..
+            })
+        ));
+        assert!(matches!(
+            engine.check_program(99),
+            Err(SimError::BotLoad(BotLoadError::UnknownTank(99)))
+        ));
+        assert!(matches!(
+            engine.verify_checksum(expected),
+            Err(SimError::DesyncDetected { tick: 1, .. })
+        ));
+        assert!(engine.verify_checksum(engine.state().checksum()).is_ok());
+    }
+
+    #[test]
+    fn step_when_program_steers_missile_should_turn_it_at_weapon_rate() {
+        // Arrange
+        let mut config = SimConfig::default();
+        config.specs.weapons[0].guidance = Some(GuidanceSpec {
+            turn_rate: dec64!(0.1),
+            steer_cost: 5,
+        });
+        let mut engine = SimEngine::from_config(SimState::new(0), &config.validate().unwrap());
+        let tank = spawn(&mut engine, 0, 100.0, 0.0);
+        let down = Angle::new(Scalar::PI / dec64!(2));
+        let mut asm = Assembler::new();
+        let (start, skip) = (asm.label(), asm.label());
+        asm.bind(start)
+            .push(1)
+            .push(abi::FIRE)
+            .op(Opcode::Store)
+            .push(abi::MISSILE_COUNT)
+            .op(Opcode::Load)
+            .jump(Opcode::Jz, skip)
+            .push(abi::MISSILES)
+            .op(Opcode::Load)
+            .push(abi::to_fixed(down.radians()))
+            .syscall(abi::SYS_STEER_MISSILE)
+            .op(Opcode::Pop)
+            .bind(skip)
+            .op(Opcode::Yield)
+            .jump(Opcode::Jmp, start);
+        engine.load_program(tank, asm.finish()).unwrap();
+
+        // Act
+        let mut turns = Vec::new();
+        for _ in 0..30 {
+            engine.step();
+            if let Some(missile) = engine.state().bullets.iter().next() {
+                turns.push(Angle::of(missile.velocity).radians());
+            }
+        }
+
+        // Assert
+        // launched straight ahead, steered from the tick after it shows up on the datalink
+        assert_eq!(turns[0], dec64!(0));
+        let steps: Vec<Scalar> = turns.windows(2).map(|pair| pair[1] - pair[0]).collect();
+        assert!(
+            steps
+                .iter()
+                .all(|step| *step <= dec64!(0.1) + dec64!(0.0001))
+        );
+        assert!((turns[turns.len() - 1] - down.radians()).abs() < dec64!(0.001));
+    }
+
+    #[test]
+    fn step_when_program_over_limits_should_report_each_limit() {
+        // Arrange
+        let mut config = SimConfig::default();
+        config.limits.memory_words = 8;
+        let config = config.validate().unwrap();
+        let mut engine = SimEngine::from_config(SimState::new(0), &config);
+        let tank = spawn(&mut engine, 0, 100.0, 0.0);
+        let mut asm = Assembler::new();
+        for _ in 0..2 {
+            asm.push(90 << 16)
+                .push(20 << 16)
+                .syscall(abi::SYS_NEXT_WAYPOINT);
+        }
+        // an address worked out at run time, so it's past RAM only once it runs
+        asm.push(4)
+            .push(4)
+            .op(Opcode::Add)
+            .op(Opcode::Load)
+            .op(Opcode::Halt);
+        engine.load_program(tank, asm.finish()).unwrap();
+
+        // Act
+        engine.step();
+        let first: Vec<SimEvent> = engine.events().to_vec();
+        engine.step();
+
+        // Assert
+        let exceeded = |limit| SimEvent::LimitExceeded {
+            tank_id: tank,
+            limit,
+        };
+        assert!(first.contains(&exceeded(BotLimit::Memory)));
+        assert!(first.contains(&exceeded(BotLimit::PathQueries)));
+        assert_eq!(engine.state().tank(tank).unwrap().vm.memory.len(), 8);
+        assert!(engine.events().is_empty());
+    }
+
+    #[test]
+    fn step_when_profiling_program_should_add_up_profile_in_stats() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        let tank = spawn(&mut engine, 0, 100.0, 0.0);
+        let mut asm = Assembler::new();
+        let start = asm.label();
+        asm.bind(start)
+            .push(1)
+            .op(Opcode::Pop)
+            .op(Opcode::Yield)
+            .jump(Opcode::Jmp, start);
+        engine.load_program(tank, asm.finish()).unwrap();
+        let functions = vec![FunctionSymbol {
+            name: "loop".to_string(),
+            start: 0,
+            end: 7,
+        }];
+        engine.enable_vm_profiling(tank, functions).unwrap();
+
+        // Act
+        for _ in 0..3 {
+            engine.step();
+        }
+        engine.disable_vm_profiling(tank);
+        engine.step();
+
+        // Assert
+        // the jump back runs at the start of every tick after the first, outside the table
+        let profile = &engine.stats().tank(tank).unwrap().vm_profile;
+        assert_eq!(profile.instructions["Push"], 3);
+        assert_eq!(profile.instructions["Jmp"], 2);
+        assert_eq!(profile.functions["loop"], 9);
+        assert_eq!(profile.functions[crate::vm::profile::UNKNOWN_FUNCTION], 2);
+    }
+
+    #[test]
+    fn reload_program_should_swap_code_and_keep_memory_only_if_asked() {
+        // Arrange
+        // counts ticks in RAM word 0, optionally by a different step
+        let counter = |step: u32| {
+            let mut asm = Assembler::new();
+            let start = asm.label();
+            asm.bind(start)
+                .push(0)
+                .op(Opcode::Load)
+                .push(step)
+                .op(Opcode::Add)
+                .push(0)
+                .op(Opcode::Store)
+                .op(Opcode::Yield)
+                .jump(Opcode::Jmp, start);
+            asm.finish()
+        };
+        let mut engine = SimEngine::new(SimState::new(0));
+        let kept = spawn(&mut engine, 0, 100.0, 0.0);
+        let reset = spawn(&mut engine, 1, 300.0, 0.0);
+        engine.load_program(kept, counter(1)).unwrap();
+        engine.load_program(reset, counter(1)).unwrap();
+        for _ in 0..3 {
+            engine.step();
+        }
+
+        // Act
+        engine
+            .reload_program(kept, counter(10), ReloadPolicy::KeepMemory)
+            .unwrap();
+        engine
+            .reload_program(reset, counter(10), ReloadPolicy::Reset)
+            .unwrap();
+        engine.step();
+
+        // Assert
+        assert_eq!(engine.state().tank(kept).unwrap().vm.memory[0], 13);
+        assert_eq!(engine.state().tank(reset).unwrap().vm.memory[0], 10);
+        assert!(
+            engine
+                .events()
+                .contains(&SimEvent::ProgramReloaded { tank_id: kept })
+        );
+        assert!(
+            engine
+                .reload_program(99, counter(1), ReloadPolicy::Reset)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn step_should_apply_queued_commands_in_order_at_start_of_tick() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        let doomed = spawn(&mut engine, 0, 100.0, 0.0);
+        let spawn = TankSpawn::medium(1, Vec2::new_from_f64(300.0, 100.0));
+        engine.queue_command(Command::SpawnTank(spawn));
+        engine.queue_command(Command::RemoveEntity { entity_id: doomed });
+        engine.queue_command(Command::SetTeam {
+            tank_id: doomed,
+            team_id: 2,
+        });
+
+        // Act
+        let before = engine.state().tanks.len();
+        engine.step();
+
+        // Assert
+        // the new tank takes the next ID after the one spawned directly
+        let spawned = doomed + 1;
+        assert_eq!(before, 1);
+        assert_eq!(engine.state().tanks.len(), 1);
+        assert_eq!(engine.state().tanks[0].id, spawned);
+        assert_eq!(
+            engine.events()[..3],
+            [
+                SimEvent::TankSpawned {
+                    tank_id: spawned,
+                    team_id: 1
+                },
+                SimEvent::EntityRemoved { entity_id: doomed },
+                SimEvent::CommandRejected { index: 2 },
+            ]
+        );
+        assert_eq!(engine.applied_commands().len(), 3);
+        engine.step();
+        assert!(engine.applied_commands().is_empty());
+    }
+
+    #[test]
+    fn step_when_tracker_bot_sees_enemy_should_shoot_it() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        let hunter = spawn(&mut engine, 0, 100.0, 0.0);
+        let target = spawn(&mut engine, 1, 300.0, 0.0);
+        engine
+            .set_bot(hunter, Box::new(Tracker::default()))
+            .unwrap();
+        engine.set_bot(target, Box::new(SittingDuck)).unwrap();
+
+        // Act
+        let mut hits = 0;
+        for _ in 0..120 {
+            engine.step();
+            hits += engine
+                .events()
+                .iter()
+                .filter(|event| matches!(event, SimEvent::Hit { target_id, .. } if *target_id == target))
+                .count();
+        }
+
+        // Assert
+        assert!(hits > 0);
+    }
+
+    #[test]
+    fn step_when_firing_at_rear_armor_should_damage_target() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        let shooter = spawn(&mut engine, 0, 0.0, 0.0);
+        let target = spawn(&mut engine, 1, 100.0, 0.0); // facing away from the shooter
+        engine.set_fire(shooter, Some(0)).unwrap();
+
+        // Act
+        let mut events = Vec::new();
+        for _ in 0..20 {
+            engine.step();
+            events.extend_from_slice(engine.events());
+        }
+
+        // Assert
+        let damage = engine.specs().weapon(0).unwrap().damage;
+        assert!(matches!(events[0], SimEvent::ShotFired { tank_id, .. } if tank_id == shooter));
+        assert!(events.contains(&SimEvent::Hit {
+            bullet_id: 2,
+            shooter: Some(shooter),
+            target_id: target,
+            side: ArmorSide::Rear,
+            outcome: HitOutcome::Penetrated { damage },
+            position: Vec2::new_from_f64(90.0, 100.0),
+        }));
+        let health = engine.state().tank(target).unwrap().health;
+        assert_eq!(health, engine.specs().tank(1).unwrap().max_health - damage);
+    }
+
+    #[test]
+    fn controls_when_target_unknown_should_fail_naming_it() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        let trigger = engine.add_trigger(TriggerShape::Circle {
+            center: Vec2::new_from_f64(100.0, 100.0),
+            radius: dec64!(10),
+        });
+
+        // Act
+        let fire = engine.set_fire(7, Some(0));
+        let impulse = engine.apply_impulse(7, Vec2::zero());
+        let removed = engine.remove_trigger(trigger);
+        let removed_again = engine.remove_trigger(trigger);
+
+        // Assert
+        assert!(matches!(
+            fire,
+            Err(SimError::BotLoad(BotLoadError::UnknownTank(7)))
+        ));
+        assert!(matches!(
+            impulse,
+            Err(SimError::BotLoad(BotLoadError::UnknownTank(7)))
+        ));
+        assert!(removed.is_ok());
+        assert!(matches!(removed_again, Err(SimError::UnknownTrigger(id)) if id == trigger));
+    }
+
+    #[test]
+    fn step_when_recording_replay_should_keep_frames_and_keyframes() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        spawn(&mut engine, 0, 100.0, 0.0);
+        engine.start_replay(4);
+
+        // Act
+        for _ in 0..10 {
+            engine.step();
+        }
+        let replay = engine.take_replay().unwrap();
+
+        // Assert
+        assert_eq!(replay.tick_range(), Some((0, 10)));
+        assert_eq!(replay.keyframe_at(7).unwrap().time, 4);
+        assert_eq!(replay.frame(10).unwrap().tanks.len(), 1);
+        assert!(engine.replay().is_none());
+    }
+
+    #[test]
+    fn step_when_manual_input_replayed_should_reproduce_match() {
+        // Arrange
+        let setup = || {
+            let mut engine = SimEngine::new(SimState::new(3));
+            let human = spawn(&mut engine, 0, 100.0, 0.0);
+            let bot = spawn(&mut engine, 1, 400.0, 3.0);
+            engine.take_manual_control(human).unwrap();
+            engine.set_bot(bot, Box::new(Tracker::default())).unwrap();
+            (engine, human)
+        };
+        let (mut engine, human) = setup();
+        engine.start_replay(10);
+
+        // Act
+        for tick in 0..60u32 {
+            if tick % 20 == 0 {
+                let turn = Scalar::from(tick) / dec64!(40);
+                engine.queue_command(Command::SetManualInput {
+                    tank_id: human,
+                    input: BotCommand {
+                        drive: DriveInput::tracks(dec64!(1), dec64!(1) - turn),
+                        turret: TurretCommand::Absolute(Angle::new(turn)),
+                        fire: Some(0),
+                    },
+                });
+            }
+            engine.step();
+        }
+        let bytes = engine.take_replay().unwrap().to_bytes().unwrap();
+        let replay = Replay::from_bytes(&bytes).unwrap();
+        let (mut replayed, _) = setup();
+        for tick in 1..=60 {
+            for command in replay.inputs_at(tick) {
+                replayed.queue_command(command.clone());
+            }
+            replayed.step();
+        }
+
+        // Assert
+        assert_eq!(replay.inputs_at(1).len(), 1);
+        assert!(replay.inputs_at(2).is_empty());
+        assert_ne!(engine.state().tank(human).unwrap().position.y, dec64!(100));
+        assert_eq!(replayed.state().checksum(), engine.state().checksum());
+    }
+
+    #[test]
+    fn step_when_turret_overridden_should_leave_driving_to_program() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        let tank = spawn(&mut engine, 0, 100.0, 0.0);
+        let mut asm = Assembler::new();
+        let start = asm.label();
+        asm.bind(start)
+            .push(1 << 16)
+            .op(Opcode::Dup)
+            .push(abi::LEFT_TRACK)
+            .op(Opcode::Store)
+            .push(abi::RIGHT_TRACK)
+            .op(Opcode::Store)
+            .push(abi::TURRET_RELATIVE)
+            .push(abi::TURRET_MODE)
+            .op(Opcode::Store)
+            .op(Opcode::Yield)
+            .jump(Opcode::Jmp, start);
+        engine.load_program(tank, asm.finish()).unwrap();
+        let aim = TurretCommand::Absolute(Angle::new(dec64!(1.5)));
+        engine.queue_command(Command::SetOverride {
+            tank_id: tank,
+            channels: ControlOverride {
+                turret: Some(aim),
+                ..ControlOverride::default()
+            },
+        });
+
+        // Act
+        for _ in 0..10 {
+            engine.step();
+        }
+        let overridden = engine.state().tank(tank).unwrap().clone();
+        engine.queue_command(Command::SetOverride {
+            tank_id: tank,
+            channels: ControlOverride::default(),
+        });
+        engine.step();
+
+        // Assert
+        assert_eq!(overridden.turret, aim);
+        assert!(overridden.drive.left > dec64!(0));
+        assert!(overridden.position.x > dec64!(100));
+        assert!(engine.control_override(tank).is_none());
+        assert!(matches!(
+            engine.state().tank(tank).unwrap().turret,
+            TurretCommand::Relative(_)
+        ));
+    }
+
+    #[test]
+    fn step_when_team_speed_handicapped_should_cover_less_ground() {
+        // Arrange
+        let mut config = SimConfig::default();
+        config.rules.handicaps.insert(
+            1,
+            Handicap {
+                speed: dec64!(0.5),
+                ..Handicap::default()
+            },
+        );
+        let mut engine = SimEngine::from_config(SimState::new(0), &config.validate().unwrap());
+        let full = spawn(&mut engine, 0, 100.0, 0.0);
+        let slowed = spawn(&mut engine, 1, 100.0, 0.0);
+        engine.state.tank_mut(slowed).unwrap().position.y = dec64!(600);
+        for tank in [full, slowed] {
+            engine
+                .set_drive_input(tank, DriveInput::tracks(dec64!(1), dec64!(1)))
+                .unwrap();
+        }
+
+        // Act
+        for _ in 0..60 {
+            engine.step();
+        }
+
+        // Assert
+        let traveled = |tank| engine.state().tank(tank).unwrap().position.x - dec64!(100);
+        assert!(traveled(slowed) > dec64!(0));
+        assert!(traveled(slowed) < traveled(full) * dec64!(0.6));
+    }
+
+    #[test]
+    fn step_when_agent_attached_should_follow_its_latest_action() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        let agent = spawn(&mut engine, 0, 100.0, 0.0);
+        let bot = spawn(&mut engine, 1, 400.0, 0.0);
+        engine.attach_agent(agent).unwrap();
+        let forward = AgentAction::Continuous {
+            left: dec64!(1),
+            right: dec64!(1),
+            turret: dec64!(0),
+            fire: dec64!(1),
+        };
+
+        // Act
+        engine.step();
+        let idle = engine.state().tank(agent).unwrap().position;
+        engine.queue_command(Command::SetAgentAction {
+            tank_id: agent,
+            action: forward,
+        });
+        engine.step();
+        engine.step();
+        let observation = engine
+            .observation(agent, &ObservationSpec::default())
+            .unwrap();
+
+        // Assert
+        let tank = engine.state().tank(agent).unwrap();
+        assert_eq!(idle, Vec2::new_from_f64(100.0, 100.0));
+        assert!(tank.position.x > idle.x);
+        assert_eq!(engine.stats().tank(agent).unwrap().shots_fired, 1);
+        assert_eq!(observation.len(), ObservationSpec::default().size());
+        assert!(matches!(
+            engine.set_agent_action(bot, forward),
+            Err(SimError::NotAnAgent(id)) if id == bot
+        ));
+    }
+
+    #[test]
+    fn step_when_network_loaded_should_drive_tank_from_its_outputs() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        let tank_id = spawn(&mut engine, 0, 100.0, 0.0);
+        let observation = ObservationSpec::default();
+        // no weights, so the biases alone pick "drive forward"
+        let mut biases = vec![0.0; agent::DISCRETE_ACTIONS as usize];
+        biases[1] = 1.0;
+        let network = NetworkController::from_file(NetworkFile {
+            layers: vec![LayerFile {
+                weights: vec![vec![0.0; observation.size()]; biases.len()],
+                biases,
+                activation: Activation::Linear,
+            }],
+            observation,
+            head: ActionHead::Discrete,
+        })
+        .unwrap();
+        engine.set_network(tank_id, network).unwrap();
+
+        // Act
+        for _ in 0..3 {
+            engine.step();
+        }
+
+        // Assert
+        let tank = engine.state().tank(tank_id).unwrap();
+        assert_eq!(tank.drive, DriveInput::tracks(dec64!(1), dec64!(1)));
+        assert!(tank.position.x > dec64!(100));
+    }
+
+    #[test]
+    fn step_when_debug_draw_enabled_should_collect_only_those_categories() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        let shooter = spawn(&mut engine, 0, 0.0, 0.0);
+        let target = spawn(&mut engine, 1, 100.0, 0.0);
+        engine.set_fire(shooter, Some(0)).unwrap();
+        engine.set_debug_draw(DebugCategory::Rays, true);
+        engine.set_debug_draw(DebugCategory::Contacts, true);
+
+        // Act
+        let mut rays = 0;
+        let mut contacts = Vec::new();
+        while !engine
+            .events()
+            .iter()
+            .any(|event| matches!(event, SimEvent::Hit { .. }))
+        {
+            engine.step();
+            rays += engine.debug_draw().rays.len();
+            contacts.extend_from_slice(&engine.debug_draw().contacts);
+        }
+
+        // Assert
+        let target = engine.state().tank(target).unwrap();
+        assert!(rays > 0);
+        assert_eq!(contacts.len(), 1);
+        assert_eq!(contacts[0].normal, Vec2::new_from_f64(-1.0, 0.0));
+        assert!(contacts[0].point.x < target.position.x);
+        assert!(engine.debug_draw().boxes.is_empty());
+        assert!(engine.debug_draw().grid.is_none());
+    }
+
+    #[test]
+    fn step_when_respawning_should_return_as_ghost_that_shots_pass_through() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        let shooter = spawn(&mut engine, 0, 0.0, 0.0);
+        let target = spawn(&mut engine, 1, 100.0, 0.0);
+        engine.set_respawn(Some(RespawnConfig {
+            delay_ticks: 5,
+            invulnerable_ticks: 100,
+            spawn_points: vec![Vec2::new_from_f64(100.0, 100.0)],
+        }));
+        engine.tank_mut(target).unwrap().health = 1;
+        engine.set_fire(shooter, Some(0)).unwrap();
+
+        // Act
+        let mut events = Vec::new();
+        for _ in 0..90 {
+            engine.step();
+            events.extend_from_slice(engine.events());
+        }
+
+        // Assert
+        let hits = events
+            .iter()
+            .filter(|event| matches!(event, SimEvent::Hit { .. }))
+            .count();
+        let shots = events
+            .iter()
+            .filter(|event| matches!(event, SimEvent::ShotFired { .. }))
+            .count();
+        assert_eq!(hits, 1);
+        assert_eq!(shots, 2);
+        assert!(events.contains(&SimEvent::TankDestroyed {
+            tank_id: target,
+            killer: Some(shooter)
+        }));
+        assert!(events.contains(&SimEvent::TankRespawned {
+            tank_id: target,
+            position: Vec2::new_from_f64(100.0, 100.0),
+        }));
+        let tank = engine.state().tank(target).unwrap();
+        assert_eq!(tank.health, engine.specs().tank(1).unwrap().max_health);
+        assert!(tank.is_ghost());
+    }
+
+    #[test]
+    fn step_when_shooter_removed_should_orphan_its_shells() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        let shooter = spawn(&mut engine, 0, 100.0, 0.0);
+        engine.set_fire(shooter, Some(0)).unwrap();
+        engine.step();
+        engine.queue_command(Command::RemoveEntity { entity_id: shooter });
+
+        // Act
+        engine.step();
+
+        // Assert
+        let shells: Vec<Option<u32>> = engine
+            .state()
+            .bullets
+            .iter()
+            .map(|bullet| bullet.owner)
+            .collect();
+        assert_eq!(shells, vec![None]);
+        assert_eq!(engine.validate(), Ok(()));
+    }
+
+    #[test]
+    fn step_when_target_destroyed_should_still_stop_shots_arriving_same_tick() {
+        // Arrange
+        // two shells meet in the target from either side on the same tick
+        let mut engine = SimEngine::new(SimState::new(0));
+        let left = spawn(&mut engine, 0, 0.0, 0.0);
+        let target = spawn(&mut engine, 1, 100.0, 0.0);
+        let right = spawn(&mut engine, 2, 200.0, std::f64::consts::PI);
+        engine.tank_mut(target).unwrap().health = 1;
+        engine.set_fire(left, Some(0)).unwrap();
+        engine.set_fire(right, Some(0)).unwrap();
+
+        // Act
+        let mut events = Vec::new();
+        for _ in 0..30 {
+            engine.step();
+            engine.set_fire(left, None).unwrap();
+            engine.set_fire(right, None).unwrap();
+            if engine
+                .events()
+                .iter()
+                .any(|event| matches!(event, SimEvent::Hit { .. }))
+            {
+                events = engine.events().to_vec();
+                break;
+            }
+        }
+
+        // Assert
+        let outcomes: Vec<HitOutcome> = events
+            .iter()
+            .filter_map(|event| match event {
+                SimEvent::Hit {
+                    target_id, outcome, ..
+                } if *target_id == target => Some(*outcome),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(outcomes.len(), 2);
+        assert!(matches!(outcomes[0], HitOutcome::Penetrated { .. }));
+        assert_eq!(outcomes[1], HitOutcome::Absorbed);
+        let destroyed = events
+            .iter()
+            .filter(|event| matches!(event, SimEvent::TankDestroyed { .. }))
+            .count();
+        assert_eq!(destroyed, 1);
+        assert!(engine.state().tank(left).unwrap().is_alive());
+        assert!(engine.state().tank(right).unwrap().is_alive());
+    }
+
+    #[test]
+    fn step_when_weapon_has_recoil_should_push_and_twist_shooter() {
+        // Arrange
+        let mut specs = SpecTable::default();
+        specs.weapons[0].recoil = Some(RecoilSpec {
+            impulse: dec64!(60),
+            angular_kick: dec64!(0.1),
+        });
+        let config = SimConfig {
+            specs,
+            ..SimConfig::default()
+        };
+        let mut engine = SimEngine::from_config(SimState::new(0), &config.validate().unwrap());
+        let shooter = spawn(&mut engine, 0, 100.0, 0.0);
+        // gun trained over the right side
+        engine.tank_mut(shooter).unwrap().turret_angle = Angle::new(Scalar::PI / dec64!(2));
+        engine.set_fire(shooter, Some(0)).unwrap();
+
+        // Act
+        engine.step();
+
+        // Assert
+        let tank = engine.state().tank(shooter).unwrap();
+        // 60 over a mass of 30, straight back from the gun
+        assert!(numeric::approx_eq(tank.velocity.y, dec64!(-2)));
+        assert!(numeric::angles_approx_eq(
+            tank.angle,
+            Angle::new(dec64!(-0.1))
+        ));
+        assert!(engine.events().iter().any(|event| matches!(
+            event,
+            SimEvent::AngularImpulse {
+                source: ImpulseSource::Recoil(0),
+                ..
+            }
+        )));
+    }
+
+    #[test]
+    fn step_when_tank_jammed_by_emp_should_hold_fire_until_it_wears_off() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        let tank = spawn(&mut engine, 0, 100.0, 0.0);
+        engine.queue_command(Command::ApplyEffect {
+            tank_id: tank,
+            effect: StatusEffectSpec {
+                kind: StatusKind::Emp,
+                duration: 10,
+            },
+        });
+        engine.set_fire(tank, Some(0)).unwrap();
+
+        // Act
+        let mut shots = Vec::new();
+        let mut expired = Vec::new();
+        for tick in 0..12 {
+            engine.step();
+            for event in engine.events() {
+                match event {
+                    SimEvent::ShotFired { .. } => shots.push(tick),
+                    SimEvent::StatusExpired { .. } => expired.push(tick),
+                    _ => {}
+                }
+            }
+            // the EMP dropped the trigger, so pull it again
+            engine.set_fire(tank, Some(0)).unwrap();
+        }
+
+        // Assert
+        assert_eq!(expired, vec![9]);
+        assert_eq!(shots, vec![10]);
+    }
+
+    #[test]
+    fn step_when_shell_lays_smoke_should_hide_target_until_it_drifts_off() {
+        // Arrange
+        let mut config = SimConfig::default();
+        config.specs.weapons[0].smoke = Some(SmokeSpec {
+            radius: dec64!(30),
+            duration: 40,
+        });
+        config.rules.wind = Vec2::new_from_f64(0.0, 2.0);
+        let mut engine = SimEngine::from_config(SimState::new(0), &config.validate().unwrap());
+        let shooter = spawn(&mut engine, 0, 100.0, 0.0);
+        let target = spawn(&mut engine, 1, 300.0, 0.0);
+        engine.set_fire(shooter, Some(0)).unwrap();
+        let mut deployed = None;
+        while deployed.is_none() {
+            engine.step();
+            engine.set_fire(shooter, None).unwrap();
+            deployed = engine.events().iter().find_map(|event| match event {
+                SimEvent::SmokeDeployed { center, .. } => Some(*center),
+                _ => None,
+            });
+        }
+
+        // Act
+        let hidden = !engine.visibility().seen_by_tank(shooter).contains(&target);
+        for _ in 0..30 {
+            engine.step();
+        }
+
+        // Assert
+        let cloud = &engine.state().obscurants[0];
+        assert!(hidden);
+        assert_eq!(
+            cloud.center,
+            deployed.unwrap() + Vec2::new_from_f64(0.0, 60.0)
+        );
+        assert!(engine.visibility().seen_by_tank(shooter).contains(&target));
+    }
+
+    #[test]
+    fn step_when_weapon_ricochets_should_bounce_off_wall() {
+        // Arrange
+        let arena = ArenaConfig {
+            obstacles: vec![AABB::new(
+                Vec2::new_from_f64(300.0, 0.0),
+                Vec2::new_from_f64(320.0, 200.0),
+            )],
+            ..ArenaConfig::default()
+        };
+        let mut specs = SpecTable::default();
+        specs.weapons[0].ricochet = Some(RicochetSpec {
+            max_bounces: 1,
+            restitution: dec64!(0.5),
+        });
+        let state = SimState::with_arena(0, &arena);
+        let config = SimConfig {
+            arena,
+            specs,
+            ..SimConfig::default()
+        };
+        let mut engine = SimEngine::from_config(state, &config.validate().unwrap());
+        let shooter = spawn(&mut engine, 0, 100.0, 0.0);
+        engine.set_fire(shooter, Some(0)).unwrap();
+
+        // Act
+        let mut events = Vec::new();
+        for _ in 0..60 {
+            engine.step();
+            events.extend_from_slice(engine.events());
+            engine.set_fire(shooter, None).unwrap();
+        }
+
+        // Assert
+        let bounce = events
+            .iter()
+            .find_map(|event| match event {
+                SimEvent::Ricochet { position, .. } => Some(*position),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(bounce.x, dec64!(300));
+        // the bullet comes back at half speed and hits its own shooter
+        assert!(events.iter().any(
+            |event| matches!(event, SimEvent::Hit { target_id, .. } if *target_id == shooter)
+        ));
+    }
+
+    #[test]
+    fn step_when_bullet_stops_on_obstacle_should_report_where_and_what_it_struck() {
+        // Arrange
+        let arena = ArenaConfig {
+            obstacles: vec![AABB::new(
+                Vec2::new_from_f64(300.0, 0.0),
+                Vec2::new_from_f64(320.0, 200.0),
+            )],
+            ..ArenaConfig::default()
+        };
+        let state = SimState::with_arena(0, &arena);
+        let config = SimConfig {
+            arena,
+            ..SimConfig::default()
+        };
+        let mut engine = SimEngine::from_config(state, &config.validate().unwrap());
+        let shooter = spawn(&mut engine, 0, 100.0, 0.0);
+        engine.set_fire(shooter, Some(0)).unwrap();
+
+        // Act
+        let mut events = Vec::new();
+        for _ in 0..30 {
+            engine.step();
+            events.extend_from_slice(engine.events());
+            engine.set_fire(shooter, None).unwrap();
+        }
+
+        // Assert
+        assert!(matches!(
+            events[0],
+            SimEvent::ShotFired { angle, .. } if angle == Angle::ZERO
+        ));
+        let stopped: Vec<&SimEvent> = events
+            .iter()
+            .filter(|event| matches!(event, SimEvent::BulletStopped { .. }))
+            .collect();
+        assert_eq!(
+            stopped,
+            vec![&SimEvent::BulletStopped {
+                bullet_id: 2,
+                position: Vec2::new_from_f64(300.0, 100.0),
+                normal: Vec2::new_from_f64(-1.0, 0.0),
+                material: ImpactMaterial::Obstacle,
+            }]
+        );
+    }
+
+    #[test]
+    fn step_when_removing_tagged_gate_should_clear_obstacle_and_its_tags() {
+        // Arrange
+        let gate = AABB::new(
+            Vec2::new_from_f64(300.0, 0.0),
+            Vec2::new_from_f64(320.0, 200.0),
+        );
+        let arena = ArenaConfig {
+            obstacles: vec![gate, gate],
+            obstacle_tags: BTreeMap::from([(1, vec![Tag::from("gate_1")])]),
+            ..ArenaConfig::default()
+        };
+        let state = SimState::with_arena(0, &arena);
+        let config = SimConfig {
+            arena,
+            ..SimConfig::default()
+        };
+        let mut engine = SimEngine::from_config(state, &config.validate().unwrap());
+        let gate_id = engine.state().tags.tagged(&"gate_1".into())[0];
+        engine.queue_command(Command::RemoveTagged {
+            tag: "gate_1".into(),
+        });
+        engine.queue_command(Command::RemoveTagged {
+            tag: "gate_1".into(),
+        });
+
+        // Act
+        engine.step();
+
+        // Assert
+        assert_eq!(gate_id, 1);
+        assert_eq!(engine.state().obstacles.len(), 1);
+        assert_eq!(engine.state().obstacles[0].id, 0);
+        assert!(engine.state().tags.is_empty());
+        assert_eq!(
+            engine.events()[..2],
+            [
+                SimEvent::EntityRemoved { entity_id: gate_id },
+                SimEvent::CommandRejected { index: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn step_when_substepping_should_move_tanks_as_far_as_whole_ticks() {
+        // Arrange
+        let substepped = MatchConfig {
+            substeps: 4,
+            ..MatchConfig::default()
+        };
+        let mut engines = [
+            SimEngine::new(SimState::new(0)),
+            SimEngine::from_config(
+                SimState::new(0),
+                &SimConfig {
+                    rules: substepped,
+                    ..SimConfig::default()
+                }
+                .validate()
+                .unwrap(),
+            ),
+        ];
+        for engine in engines.iter_mut() {
+            let tank = spawn(engine, 0, 100.0, 0.0);
+            engine
+                .set_drive_input(tank, DriveInput::tracks(dec64!(1), dec64!(0.5)))
+                .unwrap();
+        }
+
+        // Act
+        for engine in engines.iter_mut() {
+            for _ in 0..30 {
+                engine.step();
+            }
+        }
+
+        // Assert
+        // only rounding differs, since velocity is constant over a tick
+        let [whole, split] = &engines;
+        let (whole, split) = (&whole.state().tanks[0], &split.state().tanks[0]);
+        assert!(whole.position.x > dec64!(150));
+        assert!(whole.position.sub(&split.position).length_squared() < dec64!(1e-12));
+        assert!(whole.angle.shortest_to(split.angle).abs() < dec64!(1e-12));
+    }
+
+    #[test]
+    fn rewind_should_branch_without_losing_original_timeline() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        engine.enable_history(1, 100);
+        let shooter = spawn(&mut engine, 0, 0.0, 0.0);
+        let target = spawn(&mut engine, 1, 100.0, 0.0);
+        engine.set_fire(shooter, Some(0)).unwrap();
+        for _ in 0..20 {
+            engine.step();
+        }
+        let max_health = engine.specs().tank(1).unwrap().max_health;
+        assert!(engine.state().tank(target).unwrap().health < max_health);
+
+        // Act
+        let rewound = engine.rewind(0);
+        engine.tank_mut(shooter).unwrap().fire = None;
+        for _ in 0..20 {
+            engine.step();
+        }
+
+        // Assert
+        assert!(rewound.is_ok());
+        assert_eq!(engine.state().time, 20);
+        assert_eq!(engine.state().tank(target).unwrap().health, max_health);
+        let original = &engine.timelines()[0];
+        assert_eq!(original.end_tick(), 20);
+        let health = original.final_state().tank(target).unwrap().health;
+        assert!(health < max_health);
+        assert_eq!(original.at(5).unwrap().time, 5);
+    }
+
+    #[test]
+    fn rewind_when_recording_replay_should_keep_one_frame_per_tick() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        engine.enable_history(5, 100);
+        let tank = spawn(&mut engine, 0, 100.0, 0.0);
+        engine.start_replay(4);
+        for tick in 0..12 {
+            if tick == 2 || tick == 9 {
+                engine.queue_command(Command::SetTeam {
+                    tank_id: tank,
+                    team_id: tick,
+                });
+            }
+            engine.step();
+        }
+
+        // Act
+        engine.rewind(7).unwrap();
+        for _ in 0..5 {
+            engine.step();
+        }
+        let replay = engine.take_replay().unwrap();
+
+        // Assert
+        assert_eq!(replay.tick_range(), Some((0, 12)));
+        for tick in 0..=12 {
+            assert_eq!(replay.frame(tick).unwrap().tick, tick);
+        }
+        assert_eq!(replay.frame(12).unwrap().tanks[0].team_id, 2);
+        assert_eq!(replay.keyframe_at(11).unwrap().time, 8);
+        assert_eq!(replay.inputs_at(3).len(), 1);
+        assert!(replay.inputs_at(10).is_empty());
+    }
+
+    #[test]
+    fn rewind_when_tick_not_recorded_should_do_nothing() {
+        // Arrange
+        let mut engine = SimEngine::new(SimState::new(0));
+        engine.enable_history(1, 5);
+        for _ in 0..20 {
+            engine.step();
+        }
+
+        // Act
+        let too_old = engine.rewind(3);
+        let future = engine.rewind(30);
+
+        // Assert
+        assert!(matches!(too_old, Err(SimError::TickUnavailable(3))));
+        assert!(matches!(future, Err(SimError::TickUnavailable(30))));
+        assert_eq!(engine.state().time, 20);
+        assert!(engine.timelines().is_empty());
+    }
+
+    /// Something a player might do between ticks. Tanks are picked by index.
+    #[derive(Clone, Debug)]
+    enum Action {
+        Drive { tank: usize, left: i8, right: i8 },
+        Aim { tank: usize, angle: i8 },
+        Fire { tank: usize, slot: Option<u32> },
+        Step { ticks: u8 },
+    }
+
+    fn arb_action() -> impl Strategy<Value = Action> {
+        prop_oneof![
+            (0..4usize, -4..=4i8, -4..=4i8).prop_map(|(tank, left, right)| Action::Drive {
+                tank,
+                left,
+                right
+            }),
+            (0..4usize, -12..=12i8).prop_map(|(tank, angle)| Action::Aim { tank, angle }),
+            (0..4usize, prop::option::of(0..2u32))
+                .prop_map(|(tank, slot)| Action::Fire { tank, slot }),
+            (1..8u8).prop_map(|ticks| Action::Step { ticks }),
+        ]
+    }
+
+    fn apply(engine: &mut SimEngine, tanks: &[u32], action: &Action) {
+        match *action {
+            Action::Drive { tank, left, right } => {
+                let left = (left as f64 / 4.0).to_scalar();
+                let right = (right as f64 / 4.0).to_scalar();
+                engine
+                    .set_drive_input(tanks[tank], DriveInput::tracks(left, right))
+                    .unwrap();
+            }
+            Action::Aim { tank, angle } => {
+                let angle = (angle as f64 / 4.0).to_scalar();
+                engine
+                    .set_turret_command(tanks[tank], TurretCommand::Absolute(Angle::new(angle)))
+                    .unwrap();
+            }
+            Action::Fire { tank, slot } => {
+                engine.set_fire(tanks[tank], slot).unwrap();
+            }
+            Action::Step { ticks } => {
+                for _ in 0..ticks {
+                    engine.step();
+                }
+            }
+        }
+    }
+
+    proptest! {
+        // each case runs a whole skirmish, so keep the count down
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn step_when_replaying_same_actions_should_produce_identical_checksums(
+            actions in prop::collection::vec(arb_action(), 0..40),
+        ) {
+            // Arrange
+            let mut engines = [SimEngine::new(SimState::new(7)), SimEngine::new(SimState::new(7))];
+            let tanks: Vec<Vec<u32>> = engines
+                .iter_mut()
+                .map(|engine| {
+                    vec![
+                        spawn(engine, 0, 100.0, 0.0),
+                        spawn(engine, 0, 100.0, 1.5),
+                        spawn(engine, 1, 300.0, 3.0),
+                        spawn(engine, 1, 400.0, -1.5),
+                    ]
+                })
+                .collect();
+
+            for action in &actions {
+                // Act
+                for (engine, tanks) in engines.iter_mut().zip(&tanks) {
+                    apply(engine, tanks, action);
+                }
+
+                // Assert
+                prop_assert_eq!(engines[0].state().checksum(), engines[1].state().checksum());
+                prop_assert_eq!(engines[0].events(), engines[1].events());
+            }
+        }
+    }
 }
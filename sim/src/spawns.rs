@@ -0,0 +1,248 @@
+use crate::config::SimConfig;
+use crate::nav::NavGrid;
+use crate::symmetry;
+use crate::util::math::{Scalar, Vec2};
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+
+/// Nav cells between the candidates [`place`] considers, in each direction.
+const CANDIDATE_STRIDE: u32 = 4;
+
+/// How much worse unequal distances are than spawn points being close together, when
+/// [`place`] weighs one against the other.
+const SPREAD_WEIGHT: Scalar = dec64!(4);
+
+/// How evenly a set of spawn points treats the teams starting on them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpawnFairness {
+    /// Closest any two spawn points are, as the crow flies.
+    pub min_separation: Scalar,
+    /// Largest difference between two spawn points' distances to the arena centre.
+    pub center_spread: Scalar,
+    /// Largest difference between two spawn points' path lengths to the same objective, or
+    /// `None` if some spawn point can't reach an objective at all.
+    pub objective_spread: Option<Scalar>,
+}
+
+impl SpawnFairness {
+    /// Returns whether every spawn point is within `tolerance` as far from the centre and from
+    /// each objective as every other.
+    pub fn is_fair(&self, tolerance: Scalar) -> bool {
+        self.center_spread <= tolerance
+            && self
+                .objective_spread
+                .is_some_and(|spread| spread <= tolerance)
+    }
+}
+
+/// Measures the arena's own spawn points, e.g. to vet a hand-made map.
+pub fn check(sim: &SimConfig) -> SpawnFairness {
+    measure(sim, &sim.arena.spawn_points)
+}
+
+/// Measures how fair some spawn points would be in the config's arena and mode.
+pub fn measure(sim: &SimConfig, spawns: &[Vec2]) -> SpawnFairness {
+    let nav = NavGrid::for_arena(&sim.arena, &sim.specs, sim.grid.nav_cell_size);
+    let objectives = symmetry::objectives(&sim.arena, &sim.rules.mode);
+    let min_separation = spawns
+        .iter()
+        .enumerate()
+        .flat_map(|(index, a)| spawns[index + 1..].iter().map(|b| distance(*a, *b)))
+        .min()
+        .unwrap_or(dec64!(0));
+    let center_spread = spread(spawns.iter().map(|spawn| distance(*spawn, center(sim))));
+    let paths: Option<Vec<Vec<Scalar>>> = spawns
+        .iter()
+        .map(|spawn| {
+            objectives
+                .iter()
+                .map(|objective| nav.path_length(*spawn, *objective))
+                .collect()
+        })
+        .collect();
+    let objective_spread = paths.map(|paths| {
+        (0..objectives.len())
+            .map(|objective| spread(paths.iter().map(|lengths| lengths[objective])))
+            .max()
+            .unwrap_or(dec64!(0))
+    });
+    SpawnFairness {
+        min_separation,
+        center_spread,
+        objective_spread,
+    }
+}
+
+/// Picks `count` spawn points that are as far from each other as they can be while starting
+/// each team equally far from the centre and from every objective.
+///
+/// Candidates are spread evenly over the free nav cells. Each is tried as the first spawn point,
+/// and the rest are added greedily, each the candidate that best trades distance from those
+/// already chosen against matching the first one's distances. Returns `None` if there aren't
+/// enough candidates that can reach every objective.
+pub fn place(sim: &SimConfig, count: u32) -> Option<Vec<Vec2>> {
+    let count = count as usize;
+    let nav = NavGrid::for_arena(&sim.arena, &sim.specs, sim.grid.nav_cell_size);
+    let objectives = symmetry::objectives(&sim.arena, &sim.rules.mode);
+    let mut candidates = Vec::new();
+    for y in (0..nav.height()).step_by(CANDIDATE_STRIDE as usize) {
+        for x in (0..nav.width()).step_by(CANDIDATE_STRIDE as usize) {
+            if nav.is_blocked(x as i64, y as i64) {
+                continue;
+            }
+            let point = nav.cell_center(x, y);
+            if let Some(profile) = profile(sim, &nav, &objectives, point) {
+                candidates.push((point, profile));
+            }
+        }
+    }
+    if candidates.len() < count {
+        return None;
+    }
+    if count == 0 {
+        return Some(Vec::new());
+    }
+
+    let distances: Vec<Vec<Scalar>> = candidates
+        .iter()
+        .map(|(a, _)| candidates.iter().map(|(b, _)| distance(*a, *b)).collect())
+        .collect();
+    let deviation = |a: usize, b: usize| {
+        let (a, b) = (&candidates[a].1, &candidates[b].1);
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| (*a - *b).abs())
+            .max()
+            .unwrap_or(dec64!(0))
+    };
+
+    let mut best: Option<(Scalar, Vec<usize>)> = None;
+    for first in 0..candidates.len() {
+        let mut chosen = vec![first];
+        let mut worst_deviation = dec64!(0);
+        while chosen.len() < count {
+            let separation = |candidate: usize| {
+                chosen
+                    .iter()
+                    .map(|other| distances[candidate][*other])
+                    .min()
+                    .unwrap_or(dec64!(0))
+            };
+            let next = (0..candidates.len())
+                .filter(|candidate| !chosen.contains(candidate))
+                .map(|candidate| {
+                    let score = separation(candidate) - SPREAD_WEIGHT * deviation(first, candidate);
+                    (score, candidate)
+                })
+                .reduce(|best, next| if next.0 > best.0 { next } else { best });
+            let Some((_, next)) = next else {
+                break;
+            };
+            worst_deviation = worst_deviation.max(deviation(first, next));
+            chosen.push(next);
+        }
+        let min_separation = chosen
+            .iter()
+            .enumerate()
+            .flat_map(|(index, a)| chosen[index + 1..].iter().map(|b| distances[*a][*b]))
+            .min()
+            .unwrap_or(dec64!(0));
+        let score = min_separation - SPREAD_WEIGHT * worst_deviation;
+        if best.as_ref().is_none_or(|(best, _)| score > *best) {
+            best = Some((score, chosen));
+        }
+    }
+    let (_, chosen) = best?;
+    Some(
+        chosen
+            .into_iter()
+            .map(|index| candidates[index].0)
+            .collect(),
+    )
+}
+
+/// A point's distance to the arena centre, then its path length to each objective, or `None`
+/// if it can't reach one.
+fn profile(
+    sim: &SimConfig,
+    nav: &NavGrid,
+    objectives: &[Vec2],
+    point: Vec2,
+) -> Option<Vec<Scalar>> {
+    let mut profile = vec![distance(point, center(sim))];
+    for objective in objectives {
+        profile.push(nav.path_length(point, *objective)?);
+    }
+    Some(profile)
+}
+
+fn center(sim: &SimConfig) -> Vec2 {
+    Vec2::new(sim.arena.width / dec64!(2), sim.arena.height / dec64!(2))
+}
+
+fn distance(a: Vec2, b: Vec2) -> Scalar {
+    a.sub(&b).length_squared().sqrt()
+}
+
+/// The difference between the largest and smallest of some values.
+fn spread(values: impl Iterator<Item = Scalar> + Clone) -> Scalar {
+    match (values.clone().max(), values.min()) {
+        (Some(max), Some(min)) => max - min,
+        _ => dec64!(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modes::GameMode;
+    use crate::triggers::TriggerShape;
+
+    fn config() -> SimConfig {
+        let mut config = SimConfig::default();
+        config.arena.width = dec64!(768);
+        config.arena.height = dec64!(512);
+        config.rules.mode = GameMode::KingOfTheHill {
+            hill: TriggerShape::Circle {
+                center: Vec2::new(dec64!(384), dec64!(256)),
+                radius: dec64!(48),
+            },
+            ticks_to_win: 600,
+        };
+        config
+    }
+
+    #[test]
+    fn check_when_one_spawn_is_closer_to_the_hill_should_report_spread() {
+        // Arrange
+        let mut config = config();
+        config.arena.spawn_points = vec![
+            Vec2::new(dec64!(64), dec64!(256)),
+            Vec2::new(dec64!(600), dec64!(256)),
+        ];
+
+        // Act
+        let fairness = check(&config);
+
+        // Assert
+        assert_eq!(fairness.min_separation, dec64!(536));
+        assert_eq!(fairness.center_spread, dec64!(104));
+        assert!(fairness.objective_spread.unwrap() >= dec64!(100));
+        assert!(!fairness.is_fair(dec64!(32)));
+    }
+
+    #[test]
+    fn place_should_spread_spawns_evenly_around_the_hill() {
+        // Arrange
+        let config = config();
+
+        // Act
+        let spawns = place(&config, 2).unwrap();
+
+        // Assert
+        let fairness = measure(&config, &spawns);
+        assert_eq!(spawns.len(), 2);
+        assert!(fairness.is_fair(dec64!(16)), "{fairness:?}");
+        assert!(fairness.min_separation > dec64!(600), "{fairness:?}");
+    }
+}
@@ -0,0 +1,401 @@
+use crate::effects::StatusEffectSpec;
+use crate::obscurants::SmokeSpec;
+use crate::physics::drivetrain::DrivetrainSpec;
+use crate::physics::turret::TurretSpec;
+use crate::util::math::{Scalar, Vec2};
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Armor thickness on each side of a hull.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ArmorSpec {
+    pub front: Scalar,
+    pub side: Scalar,
+    pub rear: Scalar,
+}
+
+/// A radar mounted on the turret, sweeping an arc centred on where the gun points.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RadarSpec {
+    pub range: Scalar,
+    /// Full width of the arc, in radians. Anything from 2π up sees all around.
+    pub arc: Scalar,
+}
+
+/// Stats for a class of tank, shared by every tank of that class.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TankSpec {
+    pub id: u32,
+    pub name: String,
+    /// Hull length (along the heading) and width.
+    pub hull_size: Vec2,
+    pub mass: Scalar,
+    pub max_health: u32,
+    pub armor: ArmorSpec,
+    pub drivetrain: DrivetrainSpec,
+    pub turret: TurretSpec,
+    pub radar: RadarSpec,
+    /// Maximum number of weapons in a loadout.
+    pub weapon_slots: u32,
+    /// VM instructions executed per tick.
+    pub vm_clock_speed: u32,
+    /// Points charged against a team's budget when spawning this class.
+    pub cost: u32,
+    /// Blast set off when the tank is destroyed.
+    #[serde(default)]
+    pub death_explosion: Option<ExplosionSpec>,
+}
+
+/// How a projectile bounces off static geometry.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RicochetSpec {
+    /// Bounces before the next wall stops the projectile.
+    pub max_bounces: u32,
+    /// Fraction of speed kept after each bounce, in `[0, 1]`.
+    pub restitution: Scalar,
+}
+
+/// How the firing tank's program can steer a projectile in flight.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GuidanceSpec {
+    /// Most the projectile turns towards its ordered heading each tick, in radians.
+    pub turn_rate: Scalar,
+    /// Instructions charged to the program for each steering order.
+    pub steer_cost: u32,
+}
+
+/// Lets a weapon's projectiles shoot down other projectiles, for point defense.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InterceptSpec {
+    /// How close another projectile must pass to be shot down.
+    pub radius: Scalar,
+}
+
+/// A blast that damages and shoves everything nearby.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExplosionSpec {
+    pub radius: Scalar,
+    /// Damage dealt at the centre, falling to nothing at the edge.
+    pub damage: u32,
+    /// Impulse given at the centre, falling to nothing at the edge.
+    pub knockback: Scalar,
+}
+
+/// The kick a weapon gives the tank firing it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecoilSpec {
+    /// Impulse pushing the hull back along the barrel.
+    pub impulse: Scalar,
+    /// Radians the hull twists per shot when firing broadside, and less the closer the gun
+    /// points along the hull.
+    pub angular_kick: Scalar,
+}
+
+/// Stats for a weapon that can be fitted into a tank's loadout.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WeaponSpec {
+    pub id: u32,
+    pub name: String,
+    pub damage: u32,
+    /// Armor thickness a head-on shot can defeat at close range.
+    pub penetration: Scalar,
+    /// Distance up to which the shot keeps full damage and penetration.
+    pub falloff_start: Scalar,
+    /// Distance at which the shot has decayed to `min_falloff`.
+    pub falloff_end: Scalar,
+    /// Fraction of damage and penetration left beyond `falloff_end`.
+    pub min_falloff: Scalar,
+    /// Distance after which projectiles are removed.
+    pub max_range: Scalar,
+    /// Projectile speed, in units per tick.
+    pub muzzle_speed: Scalar,
+    /// Ticks between shots.
+    pub reload_ticks: u32,
+    /// Seconds between shots, for configs authored in seconds. Replaces `reload_ticks` when
+    /// the config is validated, at its tick rate.
+    #[serde(default)]
+    pub reload_seconds: Option<Scalar>,
+    /// Points charged against a team's budget when fitted.
+    pub cost: u32,
+    /// Whether projectiles bounce off walls and obstacles instead of stopping.
+    #[serde(default)]
+    pub ricochet: Option<RicochetSpec>,
+    /// Blast set off wherever a projectile stops, for missiles and shells.
+    #[serde(default)]
+    pub explosion: Option<ExplosionSpec>,
+    /// Kick given to the firing tank, for heavy guns.
+    #[serde(default)]
+    pub recoil: Option<RecoilSpec>,
+    /// Status effect put on tanks the projectile strikes without glancing off.
+    #[serde(default)]
+    pub effect: Option<StatusEffectSpec>,
+    /// Smoke screen laid wherever a projectile stops inside the arena.
+    #[serde(default)]
+    pub smoke: Option<SmokeSpec>,
+    /// Lets the firing tank's program steer projectiles in flight, for guided missiles.
+    #[serde(default)]
+    pub guidance: Option<GuidanceSpec>,
+    /// Lets projectiles shoot down hostile projectiles they pass near.
+    #[serde(default)]
+    pub intercept: Option<InterceptSpec>,
+}
+
+/// A tank class plus the weapons fitted into its slots.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Loadout {
+    pub spec_id: u32,
+    pub weapons: Vec<u32>,
+}
+
+/// Errors produced while loading spec tables.
+#[derive(Debug)]
+pub enum SpecError {
+    Parse(String),
+    DuplicateTankSpec(u32),
+    DuplicateWeaponSpec(u32),
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpecError::Parse(message) => write!(f, "invalid spec config: {message}"),
+            SpecError::DuplicateTankSpec(id) => write!(f, "duplicate tank spec id {id}"),
+            SpecError::DuplicateWeaponSpec(id) => write!(f, "duplicate weapon spec id {id}"),
+        }
+    }
+}
+
+impl std::error::Error for SpecError {}
+
+/// The set of tank classes and weapons available in a match.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpecTable {
+    pub tanks: Vec<TankSpec>,
+    pub weapons: Vec<WeaponSpec>,
+}
+
+impl SpecTable {
+    /// Loads a spec table from a JSON config, rejecting duplicate IDs.
+    pub fn from_json(json: &str) -> Result<Self, SpecError> {
+        let table: SpecTable =
+            serde_json::from_str(json).map_err(|e| SpecError::Parse(e.to_string()))?;
+        table.check_ids()?;
+        Ok(table)
+    }
+
+    /// Checks that no two tank classes, and no two weapons, share an ID.
+    pub fn check_ids(&self) -> Result<(), SpecError> {
+        for (i, spec) in self.tanks.iter().enumerate() {
+            if self.tanks[..i].iter().any(|other| other.id == spec.id) {
+                return Err(SpecError::DuplicateTankSpec(spec.id));
+            }
+        }
+        for (i, spec) in self.weapons.iter().enumerate() {
+            if self.weapons[..i].iter().any(|other| other.id == spec.id) {
+                return Err(SpecError::DuplicateWeaponSpec(spec.id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the tank class with the given ID, if it exists.
+    pub fn tank(&self, id: u32) -> Option<&TankSpec> {
+        self.tanks.iter().find(|spec| spec.id == id)
+    }
+
+    /// Returns the weapon with the given ID, if it exists.
+    pub fn weapon(&self, id: u32) -> Option<&WeaponSpec> {
+        self.weapons.iter().find(|spec| spec.id == id)
+    }
+
+    /// Returns the total point cost of a loadout, or `None` if it references unknown specs.
+    pub fn loadout_cost(&self, loadout: &Loadout) -> Option<u32> {
+        let mut cost = self.tank(loadout.spec_id)?.cost;
+        for weapon_id in &loadout.weapons {
+            cost += self.weapon(*weapon_id)?.cost;
+        }
+        Some(cost)
+    }
+}
+
+impl Default for SpecTable {
+    /// A small built-in roster: a light scout, a medium tank, and a heavy tank.
+    fn default() -> Self {
+        SpecTable {
+            tanks: vec![
+                TankSpec {
+                    id: 0,
+                    name: "scout".into(),
+                    hull_size: Vec2::new(dec64!(16), dec64!(10)),
+                    mass: dec64!(12),
+                    max_health: 60,
+                    armor: ArmorSpec {
+                        front: dec64!(15),
+                        side: dec64!(8),
+                        rear: dec64!(5),
+                    },
+                    drivetrain: DrivetrainSpec {
+                        max_speed: dec64!(4.5),
+                        max_reverse_speed: dec64!(2),
+                        acceleration: dec64!(0.35),
+                        max_turn_rate: dec64!(0.08),
+                        ..DrivetrainSpec::default()
+                    },
+                    turret: TurretSpec {
+                        max_slew_rate: dec64!(0.12),
+                        ..TurretSpec::default()
+                    },
+                    radar: RadarSpec {
+                        range: dec64!(600),
+                        arc: dec64!(3.2),
+                    },
+                    weapon_slots: 1,
+                    vm_clock_speed: 120,
+                    cost: 2,
+                    death_explosion: None,
+                },
+                TankSpec {
+                    id: 1,
+                    name: "medium".into(),
+                    hull_size: Vec2::new(dec64!(20), dec64!(13)),
+                    mass: dec64!(30),
+                    max_health: 100,
+                    armor: ArmorSpec {
+                        front: dec64!(40),
+                        side: dec64!(20),
+                        rear: dec64!(10),
+                    },
+                    drivetrain: DrivetrainSpec::default(),
+                    turret: TurretSpec::default(),
+                    radar: RadarSpec {
+                        range: dec64!(450),
+                        arc: dec64!(1.6),
+                    },
+                    weapon_slots: 2,
+                    vm_clock_speed: 100,
+                    cost: 3,
+                    death_explosion: None,
+                },
+                TankSpec {
+                    id: 2,
+                    name: "heavy".into(),
+                    hull_size: Vec2::new(dec64!(26), dec64!(16)),
+                    mass: dec64!(60),
+                    max_health: 180,
+                    armor: ArmorSpec {
+                        front: dec64!(80),
+                        side: dec64!(35),
+                        rear: dec64!(15),
+                    },
+                    drivetrain: DrivetrainSpec {
+                        max_speed: dec64!(2),
+                        max_reverse_speed: dec64!(1),
+                        acceleration: dec64!(0.1),
+                        max_turn_rate: dec64!(0.03),
+                        ..DrivetrainSpec::default()
+                    },
+                    turret: TurretSpec {
+                        max_slew_rate: dec64!(0.04),
+                        ..TurretSpec::default()
+                    },
+                    radar: RadarSpec {
+                        range: dec64!(350),
+                        arc: dec64!(1.2),
+                    },
+                    weapon_slots: 3,
+                    vm_clock_speed: 80,
+                    cost: 5,
+                    death_explosion: None,
+                },
+            ],
+            weapons: vec![
+                WeaponSpec {
+                    id: 0,
+                    name: "cannon".into(),
+                    damage: 25,
+                    penetration: dec64!(60),
+                    falloff_start: dec64!(150),
+                    falloff_end: dec64!(600),
+                    min_falloff: dec64!(0.5),
+                    max_range: dec64!(900),
+                    muzzle_speed: dec64!(12),
+                    reload_ticks: 60,
+                    reload_seconds: None,
+                    cost: 1,
+                    ricochet: None,
+                    explosion: None,
+                    recoil: None,
+                    effect: None,
+                    smoke: None,
+                    guidance: None,
+                    intercept: None,
+                },
+                WeaponSpec {
+                    id: 1,
+                    name: "machine gun".into(),
+                    damage: 4,
+                    penetration: dec64!(12),
+                    falloff_start: dec64!(80),
+                    falloff_end: dec64!(300),
+                    min_falloff: dec64!(0.25),
+                    max_range: dec64!(400),
+                    muzzle_speed: dec64!(16),
+                    reload_ticks: 6,
+                    reload_seconds: None,
+                    cost: 1,
+                    ricochet: None,
+                    explosion: None,
+                    recoil: None,
+                    effect: None,
+                    smoke: None,
+                    guidance: None,
+                    intercept: None,
+                },
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spec_table_from_json_should_round_trip_default_table() {
+        // Arrange
+        let table = SpecTable::default();
+        let json = serde_json::to_string(&table).unwrap();
+
+        // Act
+        let loaded = SpecTable::from_json(&json).unwrap();
+
+        // Assert
+        assert_eq!(loaded, table);
+        assert_eq!(loaded.tank(2).unwrap().name, "heavy");
+        assert!(loaded.tank(3).is_none());
+    }
+
+    #[test]
+    fn spec_table_from_json_when_ids_duplicated_should_fail() {
+        // Arrange
+        let mut table = SpecTable::default();
+        table.weapons[1].id = table.weapons[0].id;
+        let json = serde_json::to_string(&table).unwrap();
+
+        // Act
+        let result = SpecTable::from_json(&json);
+
+        // Assert
+        assert!(matches!(result, Err(SpecError::DuplicateWeaponSpec(0))));
+    }
+
+    #[test]
+    fn spec_table_from_json_when_malformed_should_fail() {
+        // Arrange & Act
+        let result = SpecTable::from_json("{ \"tanks\": 3 }");
+
+        // Assert
+        assert!(matches!(result, Err(SpecError::Parse(_))));
+    }
+}
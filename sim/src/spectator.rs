@@ -0,0 +1,187 @@
+use crate::physics::broadphase;
+use crate::physics::collision::AABB;
+use crate::spec::SpecTable;
+use crate::state::SimState;
+use crate::util::math::{Angle, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// What a spectator needs to know about an entity, without its simulation internals.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EntitySummary {
+    Tank {
+        id: u32,
+        team_id: u32,
+        position: Vec2,
+        angle: Angle,
+        turret_angle: Angle,
+        health: u32,
+        alive: bool,
+    },
+    Bullet {
+        id: u32,
+        weapon_id: u32,
+        position: Vec2,
+        velocity: Vec2,
+    },
+    Obstacle {
+        id: u32,
+        aabb: AABB,
+    },
+    Trigger {
+        id: u32,
+        bounds: AABB,
+    },
+}
+
+impl EntitySummary {
+    pub fn id(&self) -> u32 {
+        match self {
+            EntitySummary::Tank { id, .. }
+            | EntitySummary::Bullet { id, .. }
+            | EntitySummary::Obstacle { id, .. }
+            | EntitySummary::Trigger { id, .. } => *id,
+        }
+    }
+}
+
+/// Returns the smallest box holding the hulls of every live tank, for framing the fight, or
+/// `None` once nobody is left.
+pub fn action_bounds(state: &SimState, specs: &SpecTable) -> Option<AABB> {
+    state
+        .tanks
+        .iter()
+        .filter(|tank| tank.is_alive())
+        .filter_map(|tank| broadphase::tank_bounds(tank, specs))
+        .reduce(|a, b| {
+            AABB::new(
+                Vec2::new(a.min.x.min(b.min.x), a.min.y.min(b.min.y)),
+                Vec2::new(a.max.x.max(b.max.x), a.max.y.max(b.max.y)),
+            )
+        })
+}
+
+/// Returns every entity in the state, in ID order.
+fn summaries(state: &SimState) -> Vec<EntitySummary> {
+    let tanks = state.tanks.iter().map(|tank| EntitySummary::Tank {
+        id: tank.id,
+        team_id: tank.team_id,
+        position: tank.position,
+        angle: tank.angle,
+        turret_angle: tank.turret_angle,
+        health: tank.health,
+        alive: tank.is_alive(),
+    });
+    let bullets = state.bullets.iter().map(|bullet| EntitySummary::Bullet {
+        id: bullet.id,
+        weapon_id: bullet.weapon_id,
+        position: bullet.position,
+        velocity: bullet.velocity,
+    });
+    let obstacles = state
+        .obstacles
+        .iter()
+        .map(|obstacle| EntitySummary::Obstacle {
+            id: obstacle.id,
+            aabb: obstacle.aabb,
+        });
+    let triggers = state.triggers.iter().map(|trigger| EntitySummary::Trigger {
+        id: trigger.id,
+        bounds: trigger.shape.bounds(),
+    });
+    let mut all: Vec<EntitySummary> = tanks
+        .chain(bullets)
+        .chain(obstacles)
+        .chain(triggers)
+        .collect();
+    all.sort_by_key(EntitySummary::id);
+    all
+}
+
+/// Returns the entity with the given ID, whatever kind it is.
+pub fn entity(state: &SimState, entity_id: u32) -> Option<EntitySummary> {
+    summaries(state)
+        .into_iter()
+        .find(|summary| summary.id() == entity_id)
+}
+
+/// Returns the entities in an area, in ID order: tanks and bullets whose centres lie inside
+/// it, and obstacles and triggers that overlap it.
+pub fn entities_in_aabb(state: &SimState, area: &AABB) -> Vec<EntitySummary> {
+    summaries(state)
+        .into_iter()
+        .filter(|summary| match summary {
+            EntitySummary::Tank { position, .. } | EntitySummary::Bullet { position, .. } => {
+                area.contains(*position)
+            }
+            EntitySummary::Obstacle { aabb, .. } => aabb.intersects(area),
+            EntitySummary::Trigger { bounds, .. } => bounds.intersects(area),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::Obstacle;
+    use crate::test_support;
+
+    fn state() -> SimState {
+        let mut state = SimState::new(0);
+        for (team_id, x) in [(0, 100.0), (1, 300.0), (1, 900.0)] {
+            let id = state.allocate_id();
+            let position = Vec2::new_from_f64(x, 100.0);
+            state.tanks.push(test_support::tank(id, team_id, position));
+        }
+        let id = state.allocate_id();
+        state.obstacles.push(Obstacle {
+            id,
+            aabb: AABB::new(
+                Vec2::new_from_f64(180.0, 0.0),
+                Vec2::new_from_f64(220.0, 50.0),
+            ),
+        });
+        state
+    }
+
+    #[test]
+    fn action_bounds_should_cover_only_live_tanks() {
+        // Arrange
+        let mut state = state();
+        state.tanks[2].health = 0;
+
+        // Act
+        let bounds = action_bounds(&state, &SpecTable::default()).unwrap();
+
+        // Assert
+        // medium hulls reach 16.5 either way
+        assert_eq!(bounds.min, Vec2::new_from_f64(83.5, 83.5));
+        assert_eq!(bounds.max, Vec2::new_from_f64(316.5, 116.5));
+    }
+
+    #[test]
+    fn entities_in_aabb_should_return_typed_summaries_in_id_order() {
+        // Arrange
+        let state = state();
+        let area = AABB::new(
+            Vec2::new_from_f64(0.0, 0.0),
+            Vec2::new_from_f64(350.0, 150.0),
+        );
+
+        // Act
+        let found = entities_in_aabb(&state, &area);
+
+        // Assert
+        let ids: Vec<u32> = found.iter().map(EntitySummary::id).collect();
+        assert_eq!(ids, [0, 1, 3]);
+        assert!(matches!(found[2], EntitySummary::Obstacle { id: 3, .. }));
+        assert!(matches!(
+            entity(&state, 2),
+            Some(EntitySummary::Tank {
+                team_id: 1,
+                alive: true,
+                ..
+            })
+        ));
+        assert!(entity(&state, 4).is_none());
+    }
+}
@@ -1,38 +1,584 @@
-use crate::util::math::{Scalar, Vec2};
-use serde::{Serialize, Deserialize};
+use crate::arena::ArenaConfig;
+use crate::contacts::Contacts;
+use crate::effects::StatusEffect;
+use crate::modes::ModeState;
+use crate::obscurants::Obscurant;
+use crate::physics::collision::{AABB, OrientedBox};
+use crate::physics::drivetrain::DriveInput;
+use crate::physics::turret::TurretCommand;
+use crate::spec::{Loadout, SpecTable, TankSpec};
+use crate::tags::EntityTags;
+use crate::triggers::Trigger;
+use crate::util::math::{Angle, Rotation, RotationCache, Scalar, Transform2, Vec2};
+use crate::util::pool::Pool;
+use crate::vm::{self, VmFault};
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Bullet {
     pub id: u32,
+    pub weapon_id: u32,
+    pub origin: Vec2, // where it was fired from, for range and damage falloff
     pub position: Vec2,
-    pub velocity: Vec2
+    pub velocity: Vec2,
+    #[serde(default)]
+    pub bounces: u32, // times it has ricocheted off walls
+    /// Tank that fired it, credited with whatever it and its blast do. Bullets spawned by
+    /// commands have none.
+    #[serde(default)]
+    pub owner: Option<u32>,
+    /// Heading a guided projectile's owner last ordered it to turn to, if any.
+    #[serde(default)]
+    pub heading: Option<Angle>,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Obstacle {
+    pub id: u32,
+    pub aabb: AABB,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct VmState {
-    // TODO: actually implement lol
     pub pc: u32,
     pub sp: u32,
     pub stack: Vec<u32>,
-    pub memory: Vec<u32>
+    /// Where the running function's locals start on the stack.
+    pub fp: u32,
+    /// The calls in progress, innermost last.
+    pub frames: Vec<Frame>,
+    /// Most calls that may be in progress at once.
+    pub call_depth: u32,
+    pub memory: Vec<u32>,
+    pub halted: bool,
+    pub fault: Option<VmFault>,
+}
+
+/// Where to pick up once a call returns.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Frame {
+    pub return_pc: u32,
+    /// The caller's frame pointer.
+    pub fp: u32,
+}
+
+impl VmState {
+    /// Creates a VM at the start of its program, with zeroed stack and RAM.
+    pub fn new() -> Self {
+        VmState::with_sizes(
+            vm::MEMORY_SIZE as u32,
+            vm::STACK_SIZE as u32,
+            vm::CALL_DEPTH as u32,
+        )
+    }
+
+    /// Like [`VmState::new`], but with the given words of RAM and stack and call depth, as set
+    /// by the match's [`BotLimits`](crate::limits::BotLimits).
+    pub fn with_sizes(memory_words: u32, stack_words: u32, call_depth: u32) -> Self {
+        VmState {
+            stack: vec![0; stack_words as usize],
+            call_depth,
+            memory: vec![0; memory_words as usize],
+            ..VmState::default()
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Tank {
     pub id: u32,
+    pub loadout: Loadout, // class is looked up by loadout.spec_id
     pub position: Vec2,
     pub velocity: Vec2,
-    pub angle: Scalar,
-    pub turret_angle: Scalar,
-    pub health: u32, // TODO: replace with component health
+    pub angle: Angle,
+    pub angular_velocity: Scalar,
+    pub turret_angle: Angle, // relative to the hull
+    pub drive: DriveInput,   // track commands for the current tick
+    pub turret: TurretCommand,
+    pub fire: Option<u32>, // weapon slot to fire whenever it's reloaded
+    pub reload: Vec<u32>,  // ticks until each weapon slot can fire again
+    pub health: u32,       // TODO: replace with component health
     pub vm: VmState,
-    pub team_id: u32
+    pub team_id: u32,
+    /// Ticks until a destroyed tank respawns, once its timer has started.
+    #[serde(default)]
+    pub respawn_in: Option<u32>,
+    /// Ticks left as a ghost after respawning.
+    #[serde(default)]
+    pub invulnerable: u32,
+    /// Whether the physics step is skipping this tank until something disturbs it.
+    #[serde(default)]
+    pub sleeping: bool,
+    /// Consecutive ticks spent at rest, counting towards falling asleep.
+    #[serde(default)]
+    pub idle_ticks: u32,
+    /// Timed status effects, at most one of each kind.
+    #[serde(default)]
+    pub effects: Vec<StatusEffect>,
+    #[serde(skip)]
+    hull_rotation: RotationCache,
+    #[serde(skip)]
+    turret_rotation: RotationCache,
+}
+
+impl Tank {
+    /// Creates a stationary, full-health tank of the given class.
+    pub fn new(
+        id: u32,
+        team_id: u32,
+        spec: &TankSpec,
+        loadout: Loadout,
+        position: Vec2,
+        angle: Angle,
+    ) -> Self {
+        Tank {
+            id,
+            reload: vec![0; loadout.weapons.len()],
+            loadout,
+            position,
+            velocity: Vec2::zero(),
+            angle,
+            angular_velocity: dec64!(0),
+            turret_angle: Angle::ZERO,
+            drive: DriveInput::default(),
+            turret: TurretCommand::default(),
+            fire: None,
+            health: spec.max_health,
+            vm: VmState::default(),
+            team_id,
+            respawn_in: None,
+            invulnerable: 0,
+            sleeping: false,
+            idle_ticks: 0,
+            effects: Vec::new(),
+            hull_rotation: RotationCache::default(),
+            turret_rotation: RotationCache::default(),
+        }
+    }
+
+    /// Returns whether the tank is still in the fight.
+    pub fn is_alive(&self) -> bool {
+        self.health > 0
+    }
+
+    /// Brings the tank out of sleep, e.g. because it was hit or told to move.
+    pub fn wake(&mut self) {
+        self.sleeping = false;
+        self.idle_ticks = 0;
+    }
+
+    /// Returns whether the tank is a freshly respawned ghost, which can't be hit or fire.
+    pub fn is_ghost(&self) -> bool {
+        self.invulnerable > 0
+    }
+
+    /// Returns the world-space angle the turret is pointing at, for firing and rendering.
+    pub fn turret_world_angle(&self) -> Angle {
+        self.angle + self.turret_angle
+    }
+
+    /// Returns the hull's frame: its centre, facing along the heading.
+    pub fn transform(&self) -> Transform2 {
+        Transform2::new(self.position, self.rotation())
+    }
+
+    /// Returns the hull's heading with its trig worked out, reusing it until the heading
+    /// changes.
+    pub fn rotation(&self) -> Rotation {
+        self.hull_rotation.get(self.angle)
+    }
+
+    /// Returns the turret's frame: the hull's centre, facing where the gun points.
+    pub fn turret_transform(&self) -> Transform2 {
+        let rotation = self.turret_rotation.get(self.turret_world_angle());
+        Transform2::new(self.position, rotation)
+    }
+
+    /// Returns the hull's hitbox.
+    pub fn hull(&self, spec: &TankSpec) -> OrientedBox {
+        OrientedBox {
+            transform: self.transform(),
+            half_extents: spec.hull_size.scale(dec64!(0.5)),
+        }
+    }
+
+    /// Returns where shots leave the gun: a hull length ahead of the centre, clear of the
+    /// tank's own hull.
+    pub fn muzzle(&self, spec: &TankSpec) -> Vec2 {
+        self.turret_transform()
+            .to_world_point(Vec2::new(spec.hull_size.x, dec64!(0)))
+    }
+}
+
+/// 64-bit FNV-1a, a simple hash that's the same on every platform and build.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct SimState {
     pub time: u64,
     pub seed: u64,
+    pub next_id: u32,
     pub tanks: Vec<Tank>,
-    pub bullets: Vec<Bullet>
+    pub bullets: Pool<Bullet>,
+    pub obstacles: Vec<Obstacle>,
+    pub triggers: Vec<Trigger>,
+    #[serde(default)]
+    pub mode: ModeState,
+    /// Tanks touching each other, as of the end of the last tick.
+    #[serde(default)]
+    pub contacts: Contacts,
+    /// Smoke clouds drifting over the arena.
+    #[serde(default)]
+    pub obscurants: Vec<Obscurant>,
+    /// Labels on entities, for rules, scenarios and tools to find them by.
+    #[serde(default)]
+    pub tags: EntityTags,
+}
+
+impl SimState {
+    /// Creates an empty state at tick zero.
+    pub fn new(seed: u64) -> Self {
+        SimState {
+            time: 0,
+            seed,
+            next_id: 0,
+            tanks: Vec::new(),
+            bullets: Pool::new(),
+            obstacles: Vec::new(),
+            triggers: Vec::new(),
+            mode: ModeState::default(),
+            contacts: Contacts::default(),
+            obscurants: Vec::new(),
+            tags: EntityTags::default(),
+        }
+    }
+
+    /// Creates a state at tick zero containing the arena's obstacles and triggers, with their
+    /// tags.
+    pub fn with_arena(seed: u64, arena: &ArenaConfig) -> Self {
+        let mut state = SimState::new(seed);
+        for (index, aabb) in arena.obstacles.iter().enumerate() {
+            let id = state.allocate_id();
+            state.obstacles.push(Obstacle { id, aabb: *aabb });
+            for tag in arena.obstacle_tags.get(&index).into_iter().flatten() {
+                state.tags.add(id, tag.clone());
+            }
+        }
+        for (index, shape) in arena.triggers.iter().enumerate() {
+            let id = state.allocate_id();
+            state.triggers.push(Trigger::new(id, *shape));
+            for tag in arena.trigger_tags.get(&index).into_iter().flatten() {
+                state.tags.add(id, tag.clone());
+            }
+        }
+        state
+    }
+
+    /// Hands out a fresh entity ID. IDs are never reused within a match.
+    pub fn allocate_id(&mut self) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    /// Returns a stable hash of the whole state, for checking that two runs stayed in lockstep.
+    ///
+    /// FNV-1a over the serialized state, so the value is the same on every platform and build.
+    pub fn checksum(&self) -> u64 {
+        let bytes = serde_json::to_vec(self).expect("state is always serializable");
+        fnv1a(&bytes)
+    }
+
+    /// Returns whether any tank, bullet, obstacle, trigger or smoke cloud has the given ID.
+    pub fn has_entity(&self, id: u32) -> bool {
+        self.tanks.iter().any(|tank| tank.id == id)
+            || self.bullets.iter().any(|bullet| bullet.id == id)
+            || self.obstacles.iter().any(|obstacle| obstacle.id == id)
+            || self.triggers.iter().any(|trigger| trigger.id == id)
+            || self.obscurants.iter().any(|cloud| cloud.id == id)
+    }
+
+    /// Drops the tags of entities that are gone, such as spent bullets and dissipated smoke.
+    pub fn prune_tags(&mut self) {
+        if self.tags.is_empty() {
+            return;
+        }
+        let mut tags = std::mem::take(&mut self.tags);
+        tags.retain(|id| self.has_entity(id));
+        self.tags = tags;
+    }
+
+    /// Returns the tank with the given ID, if it exists.
+    pub fn tank(&self, id: u32) -> Option<&Tank> {
+        self.tanks.iter().find(|tank| tank.id == id)
+    }
+
+    /// Returns a mutable reference to the tank with the given ID, if it exists.
+    pub fn tank_mut(&mut self, id: u32) -> Option<&mut Tank> {
+        self.tanks.iter_mut().find(|tank| tank.id == id)
+    }
+
+    /// Checks the invariants every tick should leave the state in, returning every one broken.
+    ///
+    /// `bounds` is the arena's. Projectiles are dropped as soon as they leave it, so they're
+    /// checked against it. Nothing holds tanks inside the walls, so only their positions being
+    /// numbers is checked.
+    ///
+    /// The engine runs this after every tick in debug builds. Tests and desync hunts can call
+    /// it on any state, e.g. one loaded from a save or replay.
+    pub fn validate(
+        &self,
+        specs: &SpecTable,
+        bounds: &AABB,
+    ) -> Result<(), Vec<InvariantViolation>> {
+        let mut violations = Vec::new();
+
+        let ids = self
+            .tanks
+            .iter()
+            .map(|tank| tank.id)
+            .chain(self.bullets.iter().map(|bullet| bullet.id))
+            .chain(self.obstacles.iter().map(|obstacle| obstacle.id))
+            .chain(self.triggers.iter().map(|trigger| trigger.id))
+            .chain(self.obscurants.iter().map(|cloud| cloud.id));
+        let mut seen = BTreeSet::new();
+        for id in ids {
+            if !seen.insert(id) {
+                violations.push(InvariantViolation::DuplicateId(id));
+            }
+            if id >= self.next_id {
+                violations.push(InvariantViolation::UnallocatedId(id));
+            }
+        }
+
+        for tank in &self.tanks {
+            if !tank.position.x.is_finite() || !tank.position.y.is_finite() {
+                violations.push(InvariantViolation::NonFinitePosition { entity_id: tank.id });
+            }
+            match specs.tank(tank.loadout.spec_id) {
+                Some(spec) if tank.health > spec.max_health => {
+                    violations.push(InvariantViolation::HealthAboveMax {
+                        tank_id: tank.id,
+                        health: tank.health,
+                        max: spec.max_health,
+                    });
+                }
+                Some(_) => {}
+                None => violations.push(InvariantViolation::UnknownTankSpec {
+                    tank_id: tank.id,
+                    spec_id: tank.loadout.spec_id,
+                }),
+            }
+            if tank.vm.sp as usize > tank.vm.stack.len() {
+                violations.push(InvariantViolation::StackPointerOutOfRange {
+                    tank_id: tank.id,
+                    sp: tank.vm.sp,
+                    stack_words: tank.vm.stack.len() as u32,
+                });
+            }
+        }
+
+        for bullet in self.bullets.iter() {
+            if !bullet.position.x.is_finite() || !bullet.position.y.is_finite() {
+                violations.push(InvariantViolation::NonFinitePosition {
+                    entity_id: bullet.id,
+                });
+            } else if !bounds.contains(bullet.position) {
+                violations.push(InvariantViolation::OutOfBounds {
+                    entity_id: bullet.id,
+                });
+            }
+            if let Some(owner) = bullet.owner
+                && self.tank(owner).is_none()
+            {
+                violations.push(InvariantViolation::MissingOwner {
+                    bullet_id: bullet.id,
+                    owner,
+                });
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+/// A broken invariant found by [`SimState::validate`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum InvariantViolation {
+    /// More than one entity has this ID.
+    DuplicateId(u32),
+    /// An entity has an ID that hasn't been handed out yet, so it would be handed out again.
+    UnallocatedId(u32),
+    NonFinitePosition {
+        entity_id: u32,
+    },
+    OutOfBounds {
+        entity_id: u32,
+    },
+    UnknownTankSpec {
+        tank_id: u32,
+        spec_id: u32,
+    },
+    HealthAboveMax {
+        tank_id: u32,
+        health: u32,
+        max: u32,
+    },
+    StackPointerOutOfRange {
+        tank_id: u32,
+        sp: u32,
+        stack_words: u32,
+    },
+    /// A projectile is credited to a tank that's no longer in the match. Bullets outliving
+    /// their owner should be orphaned, with no owner at all.
+    MissingOwner {
+        bullet_id: u32,
+        owner: u32,
+    },
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvariantViolation::DuplicateId(id) => write!(f, "ID {id} is used more than once"),
+            InvariantViolation::UnallocatedId(id) => {
+                write!(f, "ID {id} is in use but hasn't been allocated")
+            }
+            InvariantViolation::NonFinitePosition { entity_id } => {
+                write!(f, "entity {entity_id} has a non-finite position")
+            }
+            InvariantViolation::OutOfBounds { entity_id } => {
+                write!(f, "entity {entity_id} is outside the arena")
+            }
+            InvariantViolation::UnknownTankSpec { tank_id, spec_id } => {
+                write!(f, "tank {tank_id} has unknown class {spec_id}")
+            }
+            InvariantViolation::HealthAboveMax {
+                tank_id,
+                health,
+                max,
+            } => write!(
+                f,
+                "tank {tank_id} has {health} health, over its maximum of {max}"
+            ),
+            InvariantViolation::StackPointerOutOfRange {
+                tank_id,
+                sp,
+                stack_words,
+            } => write!(
+                f,
+                "tank {tank_id}'s stack pointer {sp} is past its {stack_words}-word stack"
+            ),
+            InvariantViolation::MissingOwner { bullet_id, owner } => {
+                write!(f, "bullet {bullet_id} belongs to missing tank {owner}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_with_tank(specs: &SpecTable) -> SimState {
+        let mut state = SimState::new(0);
+        let id = state.allocate_id();
+        let loadout = Loadout {
+            spec_id: 1,
+            weapons: vec![],
+        };
+        let position = Vec2::new_from_f64(100.0, 100.0);
+        let tank = Tank::new(
+            id,
+            0,
+            specs.tank(1).unwrap(),
+            loadout,
+            position,
+            Angle::ZERO,
+        );
+        state.tanks.push(tank);
+        state
+    }
+
+    #[test]
+    fn validate_when_consistent_should_pass() {
+        // Arrange
+        let specs = SpecTable::default();
+        let state = state_with_tank(&specs);
+        let bounds = AABB::new(Vec2::zero(), Vec2::new_from_f64(500.0, 500.0));
+
+        // Act
+        let result = state.validate(&specs, &bounds);
+
+        // Assert
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn validate_should_report_every_broken_invariant() {
+        // Arrange
+        let specs = SpecTable::default();
+        let mut state = state_with_tank(&specs);
+        let bounds = AABB::new(Vec2::zero(), Vec2::new_from_f64(500.0, 500.0));
+        let tank = &mut state.tanks[0];
+        tank.health = specs.tank(1).unwrap().max_health + 1;
+        tank.vm.sp = 1;
+        let bullet = state.allocate_id();
+        state.bullets.insert(Bullet {
+            id: bullet,
+            weapon_id: 0,
+            origin: Vec2::zero(),
+            position: Vec2::new_from_f64(600.0, 100.0),
+            velocity: Vec2::zero(),
+            bounces: 0,
+            owner: Some(7),
+            heading: None,
+        });
+        state.obstacles.push(Obstacle {
+            id: bullet,
+            aabb: bounds,
+        });
+        state.obstacles.push(Obstacle {
+            id: 9,
+            aabb: bounds,
+        });
+
+        // Act
+        let violations = state.validate(&specs, &bounds).unwrap_err();
+
+        // Assert
+        assert_eq!(
+            violations,
+            vec![
+                InvariantViolation::DuplicateId(bullet),
+                InvariantViolation::UnallocatedId(9),
+                InvariantViolation::HealthAboveMax {
+                    tank_id: 0,
+                    health: specs.tank(1).unwrap().max_health + 1,
+                    max: specs.tank(1).unwrap().max_health,
+                },
+                InvariantViolation::StackPointerOutOfRange {
+                    tank_id: 0,
+                    sp: 1,
+                    stack_words: 0,
+                },
+                InvariantViolation::OutOfBounds { entity_id: bullet },
+                InvariantViolation::MissingOwner {
+                    bullet_id: bullet,
+                    owner: 7
+                },
+            ]
+        );
+    }
 }
@@ -0,0 +1,333 @@
+use crate::damage::HitOutcome;
+use crate::events::SimEvent;
+use crate::state::SimState;
+use crate::util::math::Scalar;
+use crate::vm::profile::VmProfile;
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Running totals for one tank.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TankStats {
+    pub team_id: u32,
+    pub shots_fired: u32,
+    /// Shots that struck an enemy, whether or not they penetrated.
+    pub hits: u32,
+    /// Fraction of shots fired that hit an enemy.
+    pub accuracy: Scalar,
+    pub damage_dealt: u32,
+    pub damage_taken: u32,
+    pub kills: u32,
+    /// Blows dealt to teammates, whatever the friendly-fire policy made of them.
+    #[serde(default)]
+    pub friendly_hits: u32,
+    /// Damage teammates took from this tank.
+    #[serde(default)]
+    pub friendly_damage: u32,
+    /// Hostile projectiles this tank's point defense shot down.
+    #[serde(default)]
+    pub interceptions: u32,
+    pub distance_traveled: Scalar,
+    pub ticks_alive: u64,
+    /// VM instructions executed, over all ticks the tank ran a program.
+    pub vm_cycles: u64,
+    /// VM instructions executed per tick, on ticks the tank ran a program.
+    pub average_vm_cycles: Scalar,
+    /// Where the tank's program spent its cycles, over the ticks it was profiled.
+    #[serde(default)]
+    pub vm_profile: VmProfile,
+    vm_ticks: u64,
+}
+
+/// Totals for a whole team, summed over its tanks.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TeamStats {
+    pub shots_fired: u32,
+    pub hits: u32,
+    pub accuracy: Scalar,
+    pub damage_dealt: u32,
+    pub damage_taken: u32,
+    pub kills: u32,
+    pub tanks_alive: u32,
+}
+
+/// Per-tank and per-team statistics, aggregated tick by tick over a match.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MatchStats {
+    pub ticks: u64,
+    pub tanks: BTreeMap<u32, TankStats>,
+    pub teams: BTreeMap<u32, TeamStats>,
+}
+
+fn ratio(numerator: u64, denominator: u64) -> Scalar {
+    if denominator == 0 {
+        return dec64!(0);
+    }
+    Scalar::from(numerator) / Scalar::from(denominator)
+}
+
+impl MatchStats {
+    /// Folds one tick's worth of events into the totals.
+    ///
+    /// `state` is the state at the end of the tick, and `vm_cycles` lists how many instructions
+    /// each tank's program executed during it.
+    pub fn record_tick(&mut self, state: &SimState, events: &[SimEvent], vm_cycles: &[(u32, u32)]) {
+        self.ticks += 1;
+
+        for tank in &state.tanks {
+            let stats = self.tanks.entry(tank.id).or_default();
+            stats.team_id = tank.team_id;
+            if tank.is_alive() {
+                stats.ticks_alive += 1;
+                stats.distance_traveled += tank.velocity.length_squared().sqrt();
+            }
+        }
+        for (tank_id, cycles) in vm_cycles {
+            let stats = self.tanks.entry(*tank_id).or_default();
+            stats.vm_cycles += *cycles as u64;
+            stats.vm_ticks += 1;
+        }
+
+        // credit goes to the tank behind each blow, unless it landed on its own team
+        let enemy = |attacker: Option<u32>, target_id: u32| {
+            let team = |tank_id: u32| state.tank(tank_id).map(|tank| tank.team_id);
+            attacker.filter(|attacker| team(*attacker) != team(target_id))
+        };
+        for event in events {
+            match *event {
+                SimEvent::ShotFired { tank_id, .. } => {
+                    self.tanks.entry(tank_id).or_default().shots_fired += 1;
+                }
+                SimEvent::Hit {
+                    shooter,
+                    target_id,
+                    outcome,
+                    ..
+                } => {
+                    let damage = match outcome {
+                        HitOutcome::Penetrated { damage } => damage,
+                        _ => 0,
+                    };
+                    self.tanks.entry(target_id).or_default().damage_taken += damage;
+                    if let Some(shooter) = enemy(shooter, target_id) {
+                        let stats = self.tanks.entry(shooter).or_default();
+                        stats.hits += 1;
+                        stats.damage_dealt += damage;
+                    }
+                }
+                SimEvent::SplashDamage {
+                    attacker,
+                    target_id,
+                    damage,
+                    ..
+                } => {
+                    self.tanks.entry(target_id).or_default().damage_taken += damage;
+                    if let Some(attacker) = enemy(attacker, target_id) {
+                        self.tanks.entry(attacker).or_default().damage_dealt += damage;
+                    }
+                }
+                SimEvent::BurnDamage {
+                    attacker,
+                    target_id,
+                    damage,
+                } => {
+                    self.tanks.entry(target_id).or_default().damage_taken += damage;
+                    if let Some(attacker) = enemy(attacker, target_id) {
+                        self.tanks.entry(attacker).or_default().damage_dealt += damage;
+                    }
+                }
+                SimEvent::ZoneDamage { target_id, damage } => {
+                    self.tanks.entry(target_id).or_default().damage_taken += damage;
+                }
+                SimEvent::Ram {
+                    rammer_id,
+                    target_id,
+                    damage,
+                } => {
+                    self.tanks.entry(target_id).or_default().damage_taken += damage;
+                    if let Some(rammer) = enemy(Some(rammer_id), target_id) {
+                        self.tanks.entry(rammer).or_default().damage_dealt += damage;
+                    }
+                }
+                SimEvent::FriendlyFire {
+                    attacker,
+                    damage,
+                    reflected,
+                    ..
+                } => {
+                    let stats = self.tanks.entry(attacker).or_default();
+                    stats.friendly_hits += 1;
+                    if reflected {
+                        stats.damage_taken += damage;
+                    } else {
+                        stats.friendly_damage += damage;
+                    }
+                }
+                SimEvent::Intercepted {
+                    shooter: Some(shooter),
+                    ..
+                } => {
+                    self.tanks.entry(shooter).or_default().interceptions += 1;
+                }
+                SimEvent::TankDestroyed { tank_id, killer } => {
+                    if let Some(killer) = enemy(killer, tank_id) {
+                        self.tanks.entry(killer).or_default().kills += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.update_derived(state);
+    }
+
+    /// Adds a tick's worth of program profiles to each tank's totals.
+    pub fn record_vm_profiles(&mut self, profiles: &[(u32, VmProfile)]) {
+        for (tank_id, profile) in profiles {
+            self.tanks
+                .entry(*tank_id)
+                .or_default()
+                .vm_profile
+                .merge(profile);
+        }
+    }
+
+    fn update_derived(&mut self, state: &SimState) {
+        self.teams.clear();
+        for (tank_id, stats) in self.tanks.iter_mut() {
+            stats.accuracy = ratio(stats.hits as u64, stats.shots_fired as u64);
+            stats.average_vm_cycles = ratio(stats.vm_cycles, stats.vm_ticks);
+
+            let team = self.teams.entry(stats.team_id).or_default();
+            team.shots_fired += stats.shots_fired;
+            team.hits += stats.hits;
+            team.damage_dealt += stats.damage_dealt;
+            team.damage_taken += stats.damage_taken;
+            team.kills += stats.kills;
+            if state.tank(*tank_id).is_some_and(|tank| tank.is_alive()) {
+                team.tanks_alive += 1;
+            }
+        }
+        for team in self.teams.values_mut() {
+            team.accuracy = ratio(team.hits as u64, team.shots_fired as u64);
+        }
+    }
+
+    /// Returns the totals for a tank, if it has taken part in the match.
+    pub fn tank(&self, tank_id: u32) -> Option<&TankStats> {
+        self.tanks.get(&tank_id)
+    }
+
+    /// Returns the totals for a team, if it has taken part in the match.
+    pub fn team(&self, team_id: u32) -> Option<&TeamStats> {
+        self.teams.get(&team_id)
+    }
+
+    /// Serializes the statistics, e.g. for saving at the end of a match.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("stats are always serializable")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::damage::ArmorSide;
+    use crate::test_support;
+    use crate::util::math::{Angle, Vec2};
+
+    fn state() -> SimState {
+        let mut state = SimState::new(0);
+        for team_id in 0..2 {
+            let id = state.allocate_id();
+            state
+                .tanks
+                .push(test_support::tank(id, team_id, Vec2::zero()));
+        }
+        state
+    }
+
+    fn hit(bullet_id: u32, shooter: u32, target_id: u32, damage: u32) -> SimEvent {
+        SimEvent::Hit {
+            bullet_id,
+            shooter: Some(shooter),
+            target_id,
+            side: ArmorSide::Front,
+            outcome: HitOutcome::Penetrated { damage },
+            position: Vec2::zero(),
+        }
+    }
+
+    #[test]
+    fn record_tick_should_credit_hits_and_kills_to_shooter() {
+        // Arrange
+        let mut stats = MatchStats::default();
+        let mut state = state();
+        let fired = [
+            SimEvent::ShotFired {
+                tank_id: 0,
+                bullet_id: 10,
+                weapon_id: 0,
+                muzzle: Vec2::zero(),
+                angle: Angle::ZERO,
+            },
+            SimEvent::ShotFired {
+                tank_id: 0,
+                bullet_id: 11,
+                weapon_id: 0,
+                muzzle: Vec2::zero(),
+                angle: Angle::ZERO,
+            },
+        ];
+
+        // Act
+        // bullet 11 misses, bullet 10 lands next tick
+        stats.record_tick(&state, &fired, &[(0, 40)]);
+        state.tanks[1].health = 0;
+        let landed = [
+            hit(10, 0, 1, 30),
+            SimEvent::TankDestroyed {
+                tank_id: 1,
+                killer: Some(0),
+            },
+        ];
+        stats.record_tick(&state, &landed, &[(0, 20)]);
+
+        // Assert
+        let shooter = stats.tank(0).unwrap();
+        assert_eq!(shooter.shots_fired, 2);
+        assert_eq!(shooter.hits, 1);
+        assert_eq!(shooter.accuracy, dec64!(0.5));
+        assert_eq!(shooter.damage_dealt, 30);
+        assert_eq!(shooter.kills, 1);
+        assert_eq!(shooter.average_vm_cycles, dec64!(30));
+        assert_eq!(stats.tank(1).unwrap().damage_taken, 30);
+        assert_eq!(stats.team(1).unwrap().tanks_alive, 0);
+        assert_eq!(stats.team(0).unwrap().kills, 1);
+    }
+
+    #[test]
+    fn record_tick_when_hitting_teammate_should_not_count_as_hit() {
+        // Arrange
+        let mut stats = MatchStats::default();
+        let mut state = state();
+        state.tanks[1].team_id = 0;
+        let fired = [SimEvent::ShotFired {
+            tank_id: 0,
+            bullet_id: 10,
+            weapon_id: 0,
+            muzzle: Vec2::zero(),
+            angle: Angle::ZERO,
+        }];
+        stats.record_tick(&state, &fired, &[]);
+
+        // Act
+        stats.record_tick(&state, &[hit(10, 0, 1, 5)], &[]);
+
+        // Assert
+        assert_eq!(stats.tank(0).unwrap().hits, 0);
+        assert_eq!(stats.tank(0).unwrap().damage_dealt, 0);
+        assert_eq!(stats.tank(1).unwrap().damage_taken, 5);
+    }
+}
@@ -0,0 +1,275 @@
+use crate::arena::ArenaConfig;
+use crate::config::SimConfig;
+use crate::modes::GameMode;
+use crate::nav::NavGrid;
+use crate::physics::collision::AABB;
+use crate::triggers::TriggerShape;
+use crate::util::math::{Scalar, Vec2};
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How one side of a competitive map maps onto the other.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Symmetry {
+    /// Mirrored across the vertical centre line.
+    LeftRight,
+    /// Mirrored across the horizontal centre line.
+    TopBottom,
+    /// Turned half way around the centre.
+    HalfTurn,
+}
+
+impl Symmetry {
+    /// Returns where a point lands on the other side of an arena.
+    pub fn apply(self, point: Vec2, width: Scalar, height: Scalar) -> Vec2 {
+        match self {
+            Symmetry::LeftRight => Vec2::new(width - point.x, point.y),
+            Symmetry::TopBottom => Vec2::new(point.x, height - point.y),
+            Symmetry::HalfTurn => Vec2::new(width - point.x, height - point.y),
+        }
+    }
+}
+
+/// Something that makes a map favour one side.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SymmetryIssue {
+    /// The obstacle at this index has no counterpart on the other side.
+    UnmatchedObstacle(usize),
+    UnmatchedTrigger(usize),
+    UnmatchedSpawnPoint(usize),
+    /// An objective, such as a flag base or the hill, has no counterpart on the other side.
+    UnmatchedObjective(Vec2),
+    /// Getting from a spawn point to an objective takes longer than from the mirrored spawn
+    /// point to the mirrored objective. `None` means there's no way through at all.
+    UnequalPath {
+        spawn: usize,
+        objective: Vec2,
+        length: Option<Scalar>,
+        mirrored_length: Option<Scalar>,
+    },
+}
+
+impl fmt::Display for SymmetryIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let describe = |length: &Option<Scalar>| match length {
+            Some(length) => length.to_string(),
+            None => "unreachable".to_string(),
+        };
+        match self {
+            SymmetryIssue::UnmatchedObstacle(index) => {
+                write!(f, "obstacle {index} has no mirrored counterpart")
+            }
+            SymmetryIssue::UnmatchedTrigger(index) => {
+                write!(f, "trigger {index} has no mirrored counterpart")
+            }
+            SymmetryIssue::UnmatchedSpawnPoint(index) => {
+                write!(f, "spawn point {index} has no mirrored counterpart")
+            }
+            SymmetryIssue::UnmatchedObjective(at) => {
+                write!(
+                    f,
+                    "objective at ({}, {}) has no mirrored counterpart",
+                    at.x, at.y
+                )
+            }
+            SymmetryIssue::UnequalPath {
+                spawn,
+                objective,
+                length,
+                mirrored_length,
+            } => write!(
+                f,
+                "path from spawn point {spawn} to objective at ({}, {}) is {}, but {} from the other side",
+                objective.x,
+                objective.y,
+                describe(length),
+                describe(mirrored_length)
+            ),
+        }
+    }
+}
+
+/// Checks that a map is fair under the given symmetry, returning every asymmetry found.
+///
+/// Obstacles, triggers, spawn points and the mode's objectives must each have an exact mirror
+/// image, and every spawn point must be as far from each objective, by the paths tanks would
+/// plan, as its mirror is from the mirrored objective. Path lengths may differ by up to two nav
+/// cells, since the grid can't line up with both sides of every map.
+pub fn check(config: &SimConfig, symmetry: Symmetry) -> Vec<SymmetryIssue> {
+    let arena = &config.arena;
+    let mirror = |point: Vec2| symmetry.apply(point, arena.width, arena.height);
+    let mirror_box = |aabb: &AABB| AABB::new(mirror(aabb.min), mirror(aabb.max));
+    let mut issues = Vec::new();
+
+    for (index, aabb) in arena.obstacles.iter().enumerate() {
+        if !arena.obstacles.contains(&mirror_box(aabb)) {
+            issues.push(SymmetryIssue::UnmatchedObstacle(index));
+        }
+    }
+    for (index, shape) in arena.triggers.iter().enumerate() {
+        let mirrored = match shape {
+            TriggerShape::Box(aabb) => TriggerShape::Box(mirror_box(aabb)),
+            TriggerShape::Circle { center, radius } => TriggerShape::Circle {
+                center: mirror(*center),
+                radius: *radius,
+            },
+        };
+        if !arena.triggers.contains(&mirrored) {
+            issues.push(SymmetryIssue::UnmatchedTrigger(index));
+        }
+    }
+    let spawn_mirrors: Vec<Option<usize>> = arena
+        .spawn_points
+        .iter()
+        .map(|point| {
+            let mirrored = mirror(*point);
+            arena
+                .spawn_points
+                .iter()
+                .position(|other| *other == mirrored)
+        })
+        .collect();
+    for (index, mirrored) in spawn_mirrors.iter().enumerate() {
+        if mirrored.is_none() {
+            issues.push(SymmetryIssue::UnmatchedSpawnPoint(index));
+        }
+    }
+    let objectives = objectives(arena, &config.rules.mode);
+    for objective in &objectives {
+        if !objectives.contains(&mirror(*objective)) {
+            issues.push(SymmetryIssue::UnmatchedObjective(*objective));
+        }
+    }
+
+    let nav = NavGrid::for_arena(arena, &config.specs, config.grid.nav_cell_size);
+    let tolerance = nav.cell_size() * dec64!(2);
+    for (spawn, mirrored_spawn) in spawn_mirrors.iter().enumerate() {
+        let Some(mirrored_spawn) = *mirrored_spawn else {
+            continue;
+        };
+        let from = arena.spawn_points[spawn];
+        let mirrored_from = arena.spawn_points[mirrored_spawn];
+        for objective in &objectives {
+            let mirrored_objective = mirror(*objective);
+            // each pair would otherwise be reported from both sides
+            if (mirrored_spawn, mirrored_objective.x, mirrored_objective.y)
+                < (spawn, objective.x, objective.y)
+            {
+                continue;
+            }
+            let length = nav.path_length(from, *objective);
+            let mirrored_length = nav.path_length(mirrored_from, mirrored_objective);
+            let fair = match (length, mirrored_length) {
+                (Some(a), Some(b)) => (a - b).abs() <= tolerance,
+                (None, None) => true,
+                _ => false,
+            };
+            if !fair {
+                issues.push(SymmetryIssue::UnequalPath {
+                    spawn,
+                    objective: *objective,
+                    length,
+                    mirrored_length,
+                });
+            }
+        }
+    }
+    issues
+}
+
+/// Returns the points teams fight over: the arena's triggers and the mode's objectives.
+pub(crate) fn objectives(arena: &ArenaConfig, mode: &GameMode) -> Vec<Vec2> {
+    let center = |shape: &TriggerShape| match shape {
+        TriggerShape::Box(aabb) => aabb.center(),
+        TriggerShape::Circle { center, .. } => *center,
+    };
+    let mut objectives: Vec<Vec2> = arena.triggers.iter().map(center).collect();
+    match mode {
+        GameMode::Deathmatch => {}
+        GameMode::CaptureTheFlag { bases, .. } => {
+            objectives.extend(bases.iter().map(|base| base.position));
+        }
+        GameMode::KingOfTheHill { hill, .. } => objectives.push(center(hill)),
+    }
+    objectives
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modes::FlagBase;
+
+    fn config() -> SimConfig {
+        let mut config = SimConfig::default();
+        config.arena.width = dec64!(1024);
+        config.arena.height = dec64!(512);
+        config.arena.obstacles = vec![
+            AABB::new(
+                Vec2::new(dec64!(256), dec64!(128)),
+                Vec2::new(dec64!(320), dec64!(384)),
+            ),
+            AABB::new(
+                Vec2::new(dec64!(704), dec64!(128)),
+                Vec2::new(dec64!(768), dec64!(384)),
+            ),
+        ];
+        config.arena.triggers = vec![TriggerShape::Circle {
+            center: Vec2::new(dec64!(512), dec64!(256)),
+            radius: dec64!(32),
+        }];
+        config.arena.spawn_points = vec![
+            Vec2::new(dec64!(64), dec64!(256)),
+            Vec2::new(dec64!(960), dec64!(256)),
+        ];
+        let base = |team_id, x| FlagBase {
+            team_id,
+            position: Vec2::new(x, dec64!(256)),
+            radius: dec64!(32),
+        };
+        config.rules.mode = GameMode::CaptureTheFlag {
+            bases: vec![base(0, dec64!(128)), base(1, dec64!(896))],
+            captures_to_win: 3,
+            pickup_radius: dec64!(16),
+        };
+        config
+    }
+
+    #[test]
+    fn check_when_mirrored_should_find_nothing() {
+        // Arrange
+        let config = config();
+
+        // Act
+        let issues = check(&config, Symmetry::LeftRight);
+
+        // Assert
+        assert_eq!(issues, vec![]);
+    }
+
+    #[test]
+    fn check_when_wall_favours_one_side_should_report_it() {
+        // Arrange
+        let mut config = config();
+        config.arena.obstacles[1] = AABB::new(
+            Vec2::new(dec64!(704), dec64!(0)),
+            Vec2::new(dec64!(768), dec64!(448)),
+        );
+
+        // Act
+        let issues = check(&config, Symmetry::LeftRight);
+
+        // Assert
+        assert!(issues.contains(&SymmetryIssue::UnmatchedObstacle(0)));
+        assert!(issues.contains(&SymmetryIssue::UnmatchedObstacle(1)));
+        assert!(
+            issues
+                .iter()
+                .any(|issue| matches!(issue, SymmetryIssue::UnequalPath { .. }))
+        );
+        assert_eq!(
+            check(&config, Symmetry::TopBottom)[0],
+            SymmetryIssue::UnmatchedObstacle(1)
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+
+/// A label put on an entity, so rules, scenarios and tools can find it without knowing its ID.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Tag {
+    /// A name such as `"gate_1"`.
+    Name(String),
+    /// A number such as a squad or wave.
+    Number(i64),
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tag::Name(name) => write!(f, "{name}"),
+            Tag::Number(number) => write!(f, "{number}"),
+        }
+    }
+}
+
+impl From<&str> for Tag {
+    fn from(name: &str) -> Self {
+        Tag::Name(name.to_string())
+    }
+}
+
+/// Reads a tag as typed in a console or script: integers are numbers, anything else a name.
+impl FromStr for Tag {
+    type Err = Infallible;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Ok(match text.parse() {
+            Ok(number) => Tag::Number(number),
+            Err(_) => Tag::Name(text.to_string()),
+        })
+    }
+}
+
+impl From<i64> for Tag {
+    fn from(number: i64) -> Self {
+        Tag::Number(number)
+    }
+}
+
+/// The tags on every entity, kept in the state so replays and saves keep them.
+///
+/// An entity can have any number of tags, and a tag can be on any number of entities. Tags go
+/// when their entity is removed.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EntityTags {
+    by_entity: BTreeMap<u32, BTreeSet<Tag>>,
+}
+
+impl EntityTags {
+    /// Puts a tag on an entity. Returns `false` if it already had it.
+    pub fn add(&mut self, entity_id: u32, tag: Tag) -> bool {
+        self.by_entity.entry(entity_id).or_default().insert(tag)
+    }
+
+    /// Takes a tag off an entity. Returns `false` if it didn't have it.
+    pub fn remove(&mut self, entity_id: u32, tag: &Tag) -> bool {
+        let Some(tags) = self.by_entity.get_mut(&entity_id) else {
+            return false;
+        };
+        let removed = tags.remove(tag);
+        if tags.is_empty() {
+            self.by_entity.remove(&entity_id);
+        }
+        removed
+    }
+
+    pub fn has(&self, entity_id: u32, tag: &Tag) -> bool {
+        self.by_entity
+            .get(&entity_id)
+            .is_some_and(|tags| tags.contains(tag))
+    }
+
+    /// Returns an entity's tags, in order.
+    pub fn of(&self, entity_id: u32) -> impl Iterator<Item = &Tag> {
+        self.by_entity.get(&entity_id).into_iter().flatten()
+    }
+
+    /// Returns the IDs of the entities with a tag, lowest first.
+    pub fn tagged(&self, tag: &Tag) -> Vec<u32> {
+        self.by_entity
+            .iter()
+            .filter(|(_, tags)| tags.contains(tag))
+            .map(|(entity_id, _)| *entity_id)
+            .collect()
+    }
+
+    /// Drops all of an entity's tags, e.g. when it's removed.
+    pub fn forget(&mut self, entity_id: u32) {
+        self.by_entity.remove(&entity_id);
+    }
+
+    /// Drops the tags of every entity for which `exists` returns `false`.
+    pub fn retain(&mut self, mut exists: impl FnMut(u32) -> bool) {
+        self.by_entity.retain(|entity_id, _| exists(*entity_id));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_entity.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tagged_should_find_every_entity_with_tag_in_id_order() {
+        // Arrange
+        let mut tags = EntityTags::default();
+        tags.add(7, "gate_1".into());
+        tags.add(3, "gate_1".into());
+        tags.add(3, Tag::Number(2));
+        tags.add(5, "gate_2".into());
+
+        // Act
+        let gates = tags.tagged(&"gate_1".into());
+
+        // Assert
+        assert_eq!(gates, vec![3, 7]);
+        let on_three: Vec<&Tag> = tags.of(3).collect();
+        assert_eq!(on_three, vec![&Tag::Name("gate_1".into()), &Tag::Number(2)]);
+    }
+
+    #[test]
+    fn remove_when_last_tag_should_forget_entity() {
+        // Arrange
+        let mut tags = EntityTags::default();
+        tags.add(4, Tag::Number(1));
+
+        // Act
+        let removed = tags.remove(4, &Tag::Number(1));
+        let again = tags.remove(4, &Tag::Number(1));
+
+        // Assert
+        assert!(removed);
+        assert!(!again);
+        assert!(tags.is_empty());
+    }
+}
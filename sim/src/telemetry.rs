@@ -0,0 +1,248 @@
+use crate::clock::SimClock;
+use crate::events::SimEvent;
+use crate::state::{Bullet, SimState};
+use crate::util::math::{Angle, Scalar, Vec2};
+use crate::vm::profile::VmProfile;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::sync::mpsc::Sender;
+
+/// A tank's externally visible state at the end of a tick.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TankTelemetry {
+    pub id: u32,
+    pub team_id: u32,
+    pub spec_id: u32,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub angle: Angle,
+    pub turret_angle: Angle,
+    pub health: u32,
+}
+
+/// Everything that happened in one tick, flattened for external analysis.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TelemetryFrame {
+    pub tick: u64,
+    /// Simulated seconds since the match began, at the end of the tick.
+    #[serde(default)]
+    pub seconds: Scalar,
+    pub tanks: Vec<TankTelemetry>,
+    pub bullets: Vec<Bullet>,
+    pub events: Vec<SimEvent>,
+    /// Where each profiled tank's program spent its cycles this tick.
+    #[serde(default)]
+    pub vm_profiles: Vec<(u32, VmProfile)>,
+}
+
+impl TelemetryFrame {
+    /// Captures the state at the end of a tick, along with the tick's events.
+    pub fn capture(state: &SimState, events: &[SimEvent], clock: &SimClock) -> Self {
+        TelemetryFrame {
+            tick: state.time,
+            seconds: clock.to_seconds(state.time),
+            tanks: state
+                .tanks
+                .iter()
+                .map(|tank| TankTelemetry {
+                    id: tank.id,
+                    team_id: tank.team_id,
+                    spec_id: tank.loadout.spec_id,
+                    position: tank.position,
+                    velocity: tank.velocity,
+                    angle: tank.angle,
+                    turret_angle: tank.turret_angle,
+                    health: tank.health,
+                })
+                .collect(),
+            bullets: state.bullets.iter().cloned().collect(),
+            events: events.to_vec(),
+            vm_profiles: Vec::new(),
+        }
+    }
+}
+
+/// Somewhere to stream telemetry to, one frame per tick.
+pub trait TelemetrySink: Send {
+    fn record(&mut self, frame: &TelemetryFrame) -> io::Result<()>;
+
+    /// Pushes out anything buffered. Called when the sink is detached.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes one JSON object per line, ready for `pandas.read_json(..., lines=True)`.
+pub struct JsonLinesSink<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JsonLinesSink<W> {
+    pub fn new(writer: W) -> Self {
+        JsonLinesSink { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write + Send> TelemetrySink for JsonLinesSink<W> {
+    fn record(&mut self, frame: &TelemetryFrame) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, frame)?;
+        self.writer.write_all(b"\n")
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Writes frames as bincode, each prefixed with its length as a little-endian `u32`.
+pub struct BinarySink<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> BinarySink<W> {
+    pub fn new(writer: W) -> Self {
+        BinarySink { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write + Send> TelemetrySink for BinarySink<W> {
+    fn record(&mut self, frame: &TelemetryFrame) -> io::Result<()> {
+        let bytes = bincode::serialize(frame).map_err(io::Error::other)?;
+        self.writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+        self.writer.write_all(&bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Sends frames to another thread, e.g. one feeding a socket.
+pub struct ChannelSink {
+    sender: Sender<TelemetryFrame>,
+}
+
+impl ChannelSink {
+    pub fn new(sender: Sender<TelemetryFrame>) -> Self {
+        ChannelSink { sender }
+    }
+}
+
+impl TelemetrySink for ChannelSink {
+    fn record(&mut self, frame: &TelemetryFrame) -> io::Result<()> {
+        self.sender
+            .send(frame.clone())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "telemetry receiver hung up"))
+    }
+}
+
+/// Reads back a log written by [`BinarySink`].
+pub fn read_binary_log(mut bytes: &[u8]) -> io::Result<Vec<TelemetryFrame>> {
+    let mut frames = Vec::new();
+    while !bytes.is_empty() {
+        let (length, rest) = bytes
+            .split_first_chunk::<4>()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+        let length = u32::from_le_bytes(*length) as usize;
+        if rest.len() < length {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let frame = bincode::deserialize(&rest[..length]).map_err(io::Error::other)?;
+        frames.push(frame);
+        bytes = &rest[length..];
+    }
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spec::{Loadout, SpecTable};
+    use crate::state::Tank;
+    use fastnum::dec64;
+
+    fn frame() -> TelemetryFrame {
+        let specs = SpecTable::default();
+        let mut state = SimState::new(0);
+        let loadout = Loadout {
+            spec_id: 0,
+            weapons: vec![0],
+        };
+        let position = Vec2::new(dec64!(10.5), dec64!(3));
+        let tank = Tank::new(
+            0,
+            1,
+            specs.tank(0).unwrap(),
+            loadout,
+            position,
+            Angle::new(dec64!(0.25)),
+        );
+        state.tanks.push(tank);
+        state.time = 12;
+        TelemetryFrame::capture(
+            &state,
+            &[SimEvent::TankDestroyed {
+                tank_id: 0,
+                killer: None,
+            }],
+            &SimClock::default(),
+        )
+    }
+
+    #[test]
+    fn json_lines_sink_should_write_one_parsable_line_per_frame() {
+        // Arrange
+        let mut sink = JsonLinesSink::new(Vec::new());
+
+        // Act
+        sink.record(&frame()).unwrap();
+        sink.record(&frame()).unwrap();
+        let output = String::from_utf8(sink.into_inner()).unwrap();
+
+        // Assert
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let parsed: TelemetryFrame = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(parsed, frame());
+        assert_eq!(parsed.seconds, dec64!(0.2));
+    }
+
+    #[test]
+    fn binary_sink_should_round_trip_through_reader() {
+        // Arrange
+        let mut sink = BinarySink::new(Vec::new());
+
+        // Act
+        sink.record(&frame()).unwrap();
+        sink.record(&frame()).unwrap();
+        let frames = read_binary_log(&sink.into_inner()).unwrap();
+
+        // Assert
+        assert_eq!(frames, vec![frame(), frame()]);
+    }
+
+    #[test]
+    fn channel_sink_when_receiver_dropped_should_fail() {
+        // Arrange
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut sink = ChannelSink::new(sender);
+        sink.record(&frame()).unwrap();
+
+        // Act
+        let received = receiver.recv().unwrap();
+        drop(receiver);
+        let result = sink.record(&frame());
+
+        // Assert
+        assert_eq!(received.tick, 12);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,16 @@
+//! Fixtures shared by the unit tests.
+
+use crate::sim::TankSpawn;
+use crate::spec::SpecTable;
+use crate::state::Tank;
+use crate::util::math::Vec2;
+
+/// A tank built from [`TankSpawn::medium`]. Tests set whatever else they need on it.
+pub fn tank(id: u32, team_id: u32, position: Vec2) -> Tank {
+    let specs = SpecTable::default();
+    let spawn = TankSpawn::medium(team_id, position);
+    let spec = specs
+        .tank(spawn.loadout.spec_id)
+        .expect("the default specs have a medium tank");
+    Tank::new(id, team_id, spec, spawn.loadout, position, spawn.angle)
+}
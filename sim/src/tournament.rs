@@ -0,0 +1,284 @@
+use crate::bots::{self, BotController};
+use crate::config::ValidatedConfig;
+use crate::error::{BotLoadError, SimError};
+use crate::network::NetworkController;
+use crate::ratings::{Ladder, Outcome};
+use crate::sim::{SimEngine, TankSpawn};
+use crate::spawns;
+use crate::spec::Loadout;
+use crate::state::SimState;
+use crate::util::math::{Angle, Vec2};
+use fastnum::dec64;
+use std::num::NonZeroUsize;
+use std::thread;
+
+/// How every match in a tournament is played.
+#[derive(Clone, Debug)]
+pub struct MatchSetup {
+    pub config: ValidatedConfig,
+    /// Tank every entrant drives.
+    pub loadout: Loadout,
+    /// Ticks after which an undecided match is called a draw.
+    pub max_ticks: u64,
+}
+
+/// Two entrants meeting, and the seed their match is played with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fixture {
+    pub first: String,
+    pub second: String,
+    pub seed: u64,
+}
+
+/// How a fixture went, from the first entrant's side.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchResult {
+    pub fixture: Fixture,
+    pub outcome: Outcome,
+    /// Ticks played before the match was decided or called.
+    pub ticks: u64,
+}
+
+/// Whatever controls an entrant's tank.
+pub enum Entrant {
+    Bot(Box<dyn BotController>),
+    /// Bytecode for the tank's VM.
+    Program(Vec<u8>),
+    Network(NetworkController),
+}
+
+impl Entrant {
+    /// Looks an entrant up among the built-in bots, for `entrants` callbacks to fall back on.
+    pub fn builtin(name: &str) -> Option<Entrant> {
+        bots::builtin(name).map(Entrant::Bot)
+    }
+}
+
+/// Plays one fixture to the end: the first entrant on team 0 and the second on team 1, each
+/// on one of the arena's spawn points, or fairly placed ones if it has fewer than two.
+/// `entrants` turns an entrant's name into a fresh controller, or `None` if there's no such
+/// entrant.
+pub fn play<F>(setup: &MatchSetup, fixture: &Fixture, entrants: &F) -> Result<MatchResult, SimError>
+where
+    F: Fn(&str) -> Option<Entrant>,
+{
+    let config = setup.config.get();
+    let positions = match config.arena.spawn_points.get(..2) {
+        Some(points) => points.to_vec(),
+        None => spawns::place(config, 2).unwrap_or_else(|| {
+            // nowhere fair to put them, so start them on opposite sides of the centre
+            let y = config.arena.height / dec64!(2);
+            vec![
+                Vec2::new(config.arena.width / dec64!(4), y),
+                Vec2::new(config.arena.width * dec64!(0.75), y),
+            ]
+        }),
+    };
+    let center = Vec2::new(
+        config.arena.width / dec64!(2),
+        config.arena.height / dec64!(2),
+    );
+
+    let state = SimState::with_arena(fixture.seed, &config.arena);
+    let mut engine = SimEngine::from_config(state, &setup.config);
+    for (team_id, (name, position)) in [&fixture.first, &fixture.second]
+        .into_iter()
+        .zip(positions)
+        .enumerate()
+    {
+        let entrant = entrants(name).ok_or_else(|| BotLoadError::UnknownBot(name.clone()))?;
+        let tank_id = engine.spawn_tank(TankSpawn {
+            team_id: team_id as u32,
+            loadout: setup.loadout.clone(),
+            position,
+            angle: Angle::of(center - position),
+        })?;
+        match entrant {
+            Entrant::Bot(bot) => engine.set_bot(tank_id, bot)?,
+            Entrant::Program(code) => engine.load_program(tank_id, code)?,
+            Entrant::Network(network) => engine.set_network(tank_id, network)?,
+        }
+    }
+
+    while !engine.is_decided() && engine.state().time < setup.max_ticks {
+        engine.step();
+    }
+    Ok(MatchResult {
+        fixture: fixture.clone(),
+        outcome: Outcome::of_team(0, engine.winner()),
+        ticks: engine.state().time,
+    })
+}
+
+/// Plays every fixture, spread over up to `threads` threads, and returns the results in
+/// fixture order.
+///
+/// Each match has its own engine and nothing is shared between them, so results are the same
+/// however many threads play them.
+pub fn play_all<F>(
+    setup: &MatchSetup,
+    fixtures: &[Fixture],
+    threads: NonZeroUsize,
+    entrants: &F,
+) -> Vec<Result<MatchResult, SimError>>
+where
+    F: Fn(&str) -> Option<Entrant> + Sync,
+{
+    let threads = threads.get().min(fixtures.len()).max(1);
+    let mut results: Vec<(usize, Result<MatchResult, SimError>)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|worker| {
+                scope.spawn(move || {
+                    fixtures
+                        .iter()
+                        .enumerate()
+                        .skip(worker)
+                        .step_by(threads)
+                        .map(|(index, fixture)| (index, play(setup, fixture, entrants)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("tournament worker panicked"))
+            .collect()
+    });
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Plays a ladder round: the ladder's pairings, seeded `seed`, `seed + 1` and so on, in
+/// parallel. Records each result on the ladder in pairing order, so ratings come out the same
+/// however many threads played, and returns them all. Fixtures that couldn't be played are
+/// left off the ladder.
+pub fn play_round<F>(
+    ladder: &mut Ladder,
+    setup: &MatchSetup,
+    seed: u64,
+    threads: NonZeroUsize,
+    entrants: &F,
+) -> Vec<Result<MatchResult, SimError>>
+where
+    F: Fn(&str) -> Option<Entrant> + Sync,
+{
+    let fixtures: Vec<Fixture> = ladder
+        .pairings()
+        .into_iter()
+        .zip(seed..)
+        .map(|((first, second), seed)| Fixture {
+            first,
+            second,
+            seed,
+        })
+        .collect();
+    let results = play_all(setup, &fixtures, threads, entrants);
+    for result in results.iter().flatten() {
+        ladder.record(
+            &result.fixture.first,
+            &result.fixture.second,
+            result.outcome,
+        );
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SimConfig;
+    use crate::ratings::RatingModel;
+
+    fn setup() -> MatchSetup {
+        let mut config = SimConfig::default();
+        config.arena.spawn_points = vec![
+            Vec2::new_from_f64(300.0, 384.0),
+            Vec2::new_from_f64(724.0, 384.0),
+        ];
+        MatchSetup {
+            config: config.validate().unwrap(),
+            loadout: Loadout {
+                spec_id: 1,
+                weapons: vec![0],
+            },
+            max_ticks: 300,
+        }
+    }
+
+    fn fixtures() -> Vec<Fixture> {
+        let names = ["tracker", "sitting_duck", "circler"];
+        (0..6)
+            .map(|index| Fixture {
+                first: names[index % 3].to_string(),
+                second: names[(index + 1) % 3].to_string(),
+                seed: index as u64,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn play_all_should_match_results_played_on_one_thread() {
+        // Arrange
+        let setup = setup();
+        let fixtures = fixtures();
+
+        // Act
+        let serial = play_all(&setup, &fixtures, NonZeroUsize::MIN, &Entrant::builtin);
+        let parallel = play_all(
+            &setup,
+            &fixtures,
+            NonZeroUsize::new(4).unwrap(),
+            &Entrant::builtin,
+        );
+
+        // Assert
+        let serial: Vec<MatchResult> = serial.into_iter().map(Result::unwrap).collect();
+        let parallel: Vec<MatchResult> = parallel.into_iter().map(Result::unwrap).collect();
+        assert_eq!(serial, parallel);
+        let played: Vec<&Fixture> = parallel.iter().map(|result| &result.fixture).collect();
+        assert_eq!(played, fixtures.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn play_when_entrant_unknown_should_fail_with_bot_load() {
+        // Arrange
+        let fixture = Fixture {
+            first: "tracker".to_string(),
+            second: "nobody".to_string(),
+            seed: 0,
+        };
+
+        // Act
+        let result = play(&setup(), &fixture, &Entrant::builtin);
+
+        // Assert
+        assert!(matches!(
+            result,
+            Err(SimError::BotLoad(BotLoadError::UnknownBot(name))) if name == "nobody"
+        ));
+    }
+
+    #[test]
+    fn play_round_should_record_every_result_on_ladder() {
+        // Arrange
+        let mut ladder = Ladder::new(RatingModel::default());
+        for bot in ["tracker", "sitting_duck", "circler", "missing"] {
+            ladder.add(bot);
+        }
+
+        // Act
+        let results = play_round(
+            &mut ladder,
+            &setup(),
+            7,
+            NonZeroUsize::new(2).unwrap(),
+            &Entrant::builtin,
+        );
+
+        // Assert
+        assert_eq!(results.len(), 2);
+        let played: u32 = ladder.ratings.values().map(|rating| rating.played()).sum();
+        let recorded = results.iter().filter(|result| result.is_ok()).count() as u32;
+        assert_eq!(played, recorded * 2);
+    }
+}
@@ -0,0 +1,230 @@
+use crate::bots::{BotController, Circler, Shuttle, SittingDuck};
+use crate::config::ValidatedConfig;
+use crate::error::SimError;
+use crate::sim::{SimEngine, TankSpawn};
+use crate::state::SimState;
+use crate::stats::TankStats;
+use crate::util::math::Scalar;
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+
+/// How a target drone moves. Drones never shoot back.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DroneMotion {
+    /// Stays where it was put.
+    Frozen,
+    /// Drives in endless circles, as the `circler` bot does.
+    Circle { throttle: Scalar, steer: Scalar },
+    /// Drives back and forth, as the `shuttle` bot does.
+    Shuttle { throttle: Scalar, period: u64 },
+}
+
+impl DroneMotion {
+    fn bot(self) -> Box<dyn BotController> {
+        match self {
+            DroneMotion::Frozen => Box::new(SittingDuck),
+            DroneMotion::Circle { throttle, steer } => Box::new(Circler { throttle, steer }),
+            DroneMotion::Shuttle { throttle, period } => Box::new(Shuttle { throttle, period }),
+        }
+    }
+}
+
+/// A target for the trainee to shoot at.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Drone {
+    pub spawn: TankSpawn,
+    pub motion: DroneMotion,
+}
+
+/// A shooting drill: one trainee's tank and the drones it has to take down, played from the
+/// same start every attempt.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Drill {
+    /// The trainee's tank, which starts without a controller.
+    pub shooter: TankSpawn,
+    pub drones: Vec<Drone>,
+    /// Ticks an attempt lasts before it's called, or zero for no limit.
+    pub time_limit: u64,
+    /// Seed every attempt is played with.
+    pub seed: u64,
+}
+
+/// How an attempt at a drill went, or is going.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AttemptStats {
+    pub ticks: u64,
+    pub shots_fired: u32,
+    /// Shots that struck a drone.
+    pub hits: u32,
+    /// Fraction of shots fired that struck a drone.
+    pub accuracy: Scalar,
+    pub drones_destroyed: u32,
+    /// Whether every drone was destroyed.
+    pub cleared: bool,
+}
+
+/// Runs a drill over and over, keeping score of each attempt, for a shooting-range scene.
+pub struct ShootingRange {
+    drill: Drill,
+    config: ValidatedConfig,
+    shooter: Option<u32>,
+    drones: Vec<u32>,
+    attempts: Vec<AttemptStats>,
+}
+
+impl ShootingRange {
+    /// Sets up a range for a drill, played under `config`. Nothing is spawned until `reset`.
+    pub fn new(drill: Drill, config: ValidatedConfig) -> Self {
+        ShootingRange {
+            drill,
+            config,
+            shooter: None,
+            drones: Vec::new(),
+            attempts: Vec::new(),
+        }
+    }
+
+    pub fn drill(&self) -> &Drill {
+        &self.drill
+    }
+
+    /// Returns the trainee's tank in the current attempt, if one has started.
+    pub fn shooter(&self) -> Option<u32> {
+        self.shooter
+    }
+
+    /// Scores the attempt in progress, if any, then starts the next one: replaces the match
+    /// with a fresh one and puts the trainee's tank and the drones at their starts. Returns
+    /// the trainee's tank, for the caller to hand to a controller.
+    pub fn reset(&mut self, engine: &mut SimEngine) -> Result<u32, SimError> {
+        if self.shooter.is_some() {
+            let attempt = self.attempt(engine);
+            self.attempts.push(attempt);
+            self.shooter = None;
+        }
+        let config = self.config.get();
+        let state = SimState::with_arena(self.drill.seed, &config.arena);
+        let mut fresh = SimEngine::from_config(state, &self.config);
+        let shooter = fresh.spawn_tank(self.drill.shooter.clone())?;
+        let drones = self
+            .drill
+            .drones
+            .iter()
+            .map(|drone| {
+                let tank_id = fresh.spawn_tank(drone.spawn.clone())?;
+                fresh.set_bot(tank_id, drone.motion.bot())?;
+                Ok(tank_id)
+            })
+            .collect::<Result<_, SimError>>()?;
+        *engine = fresh;
+        self.shooter = Some(shooter);
+        self.drones = drones;
+        Ok(shooter)
+    }
+
+    /// Scores the attempt in progress so far.
+    pub fn attempt(&self, engine: &SimEngine) -> AttemptStats {
+        let Some(shooter) = self.shooter else {
+            return AttemptStats::default();
+        };
+        let fallback = TankStats::default();
+        let stats = engine.stats().tank(shooter).unwrap_or(&fallback);
+        let drones_destroyed = self
+            .drones
+            .iter()
+            .filter(|drone| {
+                engine
+                    .state()
+                    .tank(**drone)
+                    .is_none_or(|tank| !tank.is_alive())
+            })
+            .count() as u32;
+        let accuracy = if stats.shots_fired == 0 {
+            dec64!(0)
+        } else {
+            Scalar::from(stats.hits) / Scalar::from(stats.shots_fired)
+        };
+        AttemptStats {
+            ticks: engine.state().time,
+            shots_fired: stats.shots_fired,
+            hits: stats.hits,
+            accuracy,
+            drones_destroyed,
+            cleared: drones_destroyed as usize == self.drones.len(),
+        }
+    }
+
+    /// Returns whether the attempt in progress is over: every drone destroyed, the trainee's
+    /// tank destroyed, or out of time.
+    pub fn is_over(&self, engine: &SimEngine) -> bool {
+        let Some(shooter) = self.shooter else {
+            return false;
+        };
+        let shooter_down = engine
+            .state()
+            .tank(shooter)
+            .is_none_or(|tank| !tank.is_alive());
+        let out_of_time = self.drill.time_limit > 0 && engine.state().time >= self.drill.time_limit;
+        shooter_down || out_of_time || self.attempt(engine).cleared
+    }
+
+    /// Returns the scores of every finished attempt, oldest first.
+    pub fn attempts(&self) -> &[AttemptStats] {
+        &self.attempts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bots::Tracker;
+    use crate::config::SimConfig;
+    use crate::util::math::Vec2;
+
+    fn spawn(team_id: u32, x: f64, weapons: Vec<u32>) -> TankSpawn {
+        let mut spawn = TankSpawn::medium(team_id, Vec2::new_from_f64(x, 384.0));
+        spawn.loadout.weapons = weapons;
+        spawn
+    }
+
+    fn drill() -> Drill {
+        Drill {
+            shooter: spawn(0, 300.0, vec![0]),
+            drones: vec![Drone {
+                spawn: spawn(1, 500.0, vec![]),
+                motion: DroneMotion::Frozen,
+            }],
+            time_limit: 240,
+            seed: 3,
+        }
+    }
+
+    #[test]
+    fn reset_should_score_attempt_and_restart_drill() {
+        // Arrange
+        let mut range = ShootingRange::new(drill(), SimConfig::default().validate().unwrap());
+        let mut engine = SimEngine::new(SimState::new(0));
+        let shooter = range.reset(&mut engine).unwrap();
+        engine
+            .set_bot(shooter, Box::new(Tracker::default()))
+            .unwrap();
+
+        // Act
+        while !range.is_over(&engine) {
+            engine.step();
+        }
+        let restarted = range.reset(&mut engine).unwrap();
+
+        // Assert
+        let [attempt] = range.attempts() else {
+            panic!("expected one attempt, got {:?}", range.attempts());
+        };
+        assert!(attempt.shots_fired > 0);
+        assert!(attempt.hits > 0);
+        assert!(attempt.accuracy > dec64!(0));
+        assert!(attempt.ticks <= 240);
+        assert_eq!(restarted, range.shooter().unwrap());
+        assert_eq!(engine.state().time, 0);
+        assert_eq!(range.attempt(&engine), AttemptStats::default());
+    }
+}
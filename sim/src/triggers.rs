@@ -0,0 +1,238 @@
+use crate::events::SimEvent;
+use crate::physics::collision::AABB;
+use crate::state::Tank;
+use crate::util::math::{Scalar, Vec2};
+use crate::util::spatial::{BroadPhase, LayeredSpatialHash};
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Target size of a cell in the trigger grid.
+const TRIGGER_CELL_SIZE: Scalar = dec64!(64);
+
+/// Target size of a cell in the grid holding triggers too big for the trigger grid, such as
+/// zones covering much of the map.
+const LARGE_TRIGGER_CELL_SIZE: Scalar = dec64!(512);
+
+/// The area a trigger covers.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TriggerShape {
+    Box(AABB),
+    Circle { center: Vec2, radius: Scalar },
+}
+
+impl TriggerShape {
+    /// Returns whether the point lies inside the shape or on its boundary.
+    pub fn contains(&self, point: Vec2) -> bool {
+        match self {
+            TriggerShape::Box(aabb) => aabb.contains(point),
+            TriggerShape::Circle { center, radius } => {
+                point.sub(center).length_squared() <= *radius * *radius
+            }
+        }
+    }
+
+    /// Returns the smallest box containing the shape.
+    pub fn bounds(&self) -> AABB {
+        match self {
+            TriggerShape::Box(aabb) => *aabb,
+            TriggerShape::Circle { center, radius } => {
+                AABB::new_from_size(*center, Vec2::new(*radius * 2.0, *radius * 2.0))
+            }
+        }
+    }
+}
+
+/// A non-solid volume that reports tanks entering and leaving it.
+///
+/// A tank counts as inside while its centre is, and only while it's alive.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Trigger {
+    pub id: u32,
+    pub shape: TriggerShape,
+    /// Tanks inside as of the end of the last tick.
+    pub occupants: BTreeSet<u32>,
+}
+
+impl Trigger {
+    pub fn new(id: u32, shape: TriggerShape) -> Self {
+        Trigger {
+            id,
+            shape,
+            occupants: BTreeSet::new(),
+        }
+    }
+}
+
+/// Spatial lookup over the match's triggers.
+///
+/// Rebuilt whenever triggers are added or removed.
+pub struct TriggerIndex {
+    index: LayeredSpatialHash,
+    /// Each trigger's shape, by ID, so points can be checked without the state.
+    shapes: BTreeMap<u32, TriggerShape>,
+}
+
+impl TriggerIndex {
+    pub fn new(width: Scalar, height: Scalar, triggers: &[Trigger]) -> Self {
+        let mut index =
+            LayeredSpatialHash::new(width, height, TRIGGER_CELL_SIZE, LARGE_TRIGGER_CELL_SIZE);
+        for trigger in triggers {
+            index.insert(trigger.id, &trigger.shape.bounds());
+        }
+        let shapes = triggers
+            .iter()
+            .map(|trigger| (trigger.id, trigger.shape))
+            .collect();
+        TriggerIndex { index, shapes }
+    }
+
+    /// Returns the IDs of the triggers whose volumes contain a point, lowest first.
+    ///
+    /// Only the triggers stored in the point's cell are checked, so this is cheap enough to
+    /// call for every tank every tick.
+    pub fn containing(&self, point: Vec2) -> Vec<u32> {
+        let mut ids: Vec<u32> = self
+            .index
+            .query_point(point)
+            .filter(|id| self.shapes[id].contains(point))
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Recomputes who is inside each trigger, emitting exits and then entries.
+    ///
+    /// Events are ordered by trigger ID, then tank ID.
+    pub fn update(&self, triggers: &mut [Trigger], tanks: &[Tank], events: &mut Vec<SimEvent>) {
+        let mut inside: Vec<(u32, u32)> = Vec::new();
+        for tank in tanks.iter().filter(|tank| tank.is_alive()) {
+            for trigger_id in self.containing(tank.position) {
+                inside.push((trigger_id, tank.id));
+            }
+        }
+        inside.sort_unstable();
+
+        let mut entered = Vec::new();
+        for trigger in triggers.iter_mut() {
+            let now: BTreeSet<u32> = inside
+                .iter()
+                .filter(|(trigger_id, _)| *trigger_id == trigger.id)
+                .map(|(_, tank_id)| *tank_id)
+                .collect();
+
+            for tank_id in trigger.occupants.difference(&now) {
+                events.push(SimEvent::TriggerExited {
+                    trigger_id: trigger.id,
+                    tank_id: *tank_id,
+                });
+            }
+            for tank_id in now.difference(&trigger.occupants) {
+                entered.push(SimEvent::TriggerEntered {
+                    trigger_id: trigger.id,
+                    tank_id: *tank_id,
+                });
+            }
+            trigger.occupants = now;
+        }
+        events.append(&mut entered);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn tank(id: u32, x: f64) -> Tank {
+        test_support::tank(id, 0, Vec2::new_from_f64(x, 50.0))
+    }
+
+    #[test]
+    fn update_should_report_entries_and_exits_once() {
+        // Arrange
+        let mut triggers = vec![
+            Trigger::new(
+                1,
+                TriggerShape::Box(AABB::new(
+                    Vec2::new_from_f64(0.0, 0.0),
+                    Vec2::new_from_f64(100.0, 100.0),
+                )),
+            ),
+            Trigger::new(
+                2,
+                TriggerShape::Circle {
+                    center: Vec2::new_from_f64(150.0, 50.0),
+                    radius: dec64!(30),
+                },
+            ),
+        ];
+        let index = TriggerIndex::new(dec64!(300), dec64!(300), &triggers);
+        let mut tanks = vec![tank(7, 50.0)];
+
+        // Act
+        let mut first = Vec::new();
+        index.update(&mut triggers, &tanks, &mut first);
+        let mut idle = Vec::new();
+        index.update(&mut triggers, &tanks, &mut idle);
+        tanks[0].position = Vec2::new_from_f64(140.0, 50.0);
+        let mut moved = Vec::new();
+        index.update(&mut triggers, &tanks, &mut moved);
+        tanks[0].health = 0;
+        let mut died = Vec::new();
+        index.update(&mut triggers, &tanks, &mut died);
+
+        // Assert
+        let entered = |trigger_id| SimEvent::TriggerEntered {
+            trigger_id,
+            tank_id: 7,
+        };
+        let exited = |trigger_id| SimEvent::TriggerExited {
+            trigger_id,
+            tank_id: 7,
+        };
+        assert_eq!(first, [entered(1)]);
+        assert!(idle.is_empty());
+        assert_eq!(moved, [exited(1), entered(2)]);
+        assert_eq!(died, [exited(2)]);
+    }
+
+    #[test]
+    fn containing_should_list_every_volume_around_point_in_id_order() {
+        // Arrange
+        let zone = TriggerShape::Box(AABB::new(
+            Vec2::new_from_f64(0.0, 0.0),
+            Vec2::new_from_f64(1000.0, 1000.0),
+        ));
+        let pad = TriggerShape::Circle {
+            center: Vec2::new_from_f64(100.0, 100.0),
+            radius: dec64!(20),
+        };
+        let triggers = [Trigger::new(5, pad), Trigger::new(3, zone)];
+        let index = TriggerIndex::new(dec64!(1000), dec64!(1000), &triggers);
+
+        // Act
+        let on_pad = index.containing(Vec2::new_from_f64(110.0, 100.0));
+        let pad_corner = index.containing(Vec2::new_from_f64(118.0, 118.0));
+        let outside = index.containing(Vec2::new_from_f64(1200.0, 100.0));
+
+        // Assert
+        assert_eq!(on_pad, vec![3, 5]);
+        assert_eq!(pad_corner, vec![3]);
+        assert!(outside.is_empty());
+    }
+
+    #[test]
+    fn circle_contains_should_exclude_corners_of_bounds() {
+        // Arrange
+        let shape = TriggerShape::Circle {
+            center: Vec2::zero(),
+            radius: dec64!(10),
+        };
+
+        // Act & Assert
+        assert!(shape.bounds().contains(Vec2::new_from_f64(9.0, 9.0)));
+        assert!(!shape.contains(Vec2::new_from_f64(9.0, 9.0)));
+        assert!(shape.contains(Vec2::new_from_f64(0.0, -10.0)));
+    }
+}
@@ -1,5 +1,6 @@
 use fastnum::{D64, dec64};
 use serde::{Deserialize, Serialize};
+use std::cell::Cell;
 
 /// A scalar (one-dimensional) value.
 pub type Scalar = D64;
@@ -73,6 +74,46 @@ impl Vec2 {
         self.dot(self)
     }
 
+    /// Computes the length of the vector. Prefer [`Vec2::is_longer_than`] or
+    /// [`Vec2::is_shorter_than`] when only comparing it, as they skip the square root.
+    pub fn length(&self) -> Scalar {
+        self.length_squared().sqrt()
+    }
+
+    /// Returns whether the vector is longer than `limit`, comparing squares.
+    pub fn is_longer_than(&self, limit: Scalar) -> bool {
+        self.length_squared() > limit * limit
+    }
+
+    /// Returns whether the vector is shorter than `limit`, comparing squares.
+    pub fn is_shorter_than(&self, limit: Scalar) -> bool {
+        self.length_squared() < limit * limit
+    }
+
+    /// Computes the square of the distance between two points, without building their
+    /// difference.
+    pub fn distance_squared(&self, other: &Vec2) -> Scalar {
+        let (dx, dy) = (self.x - other.x, self.y - other.y);
+        dx * dx + dy * dy
+    }
+
+    /// Computes `self + other * factor`, rounding each component once rather than twice.
+    pub fn mul_add(&self, other: &Vec2, factor: Scalar) -> Vec2 {
+        Vec2::new(
+            other.x.mul_add(factor, self.x),
+            other.y.mul_add(factor, self.y),
+        )
+    }
+
+    /// Shortens the vector to `max` if it's longer, keeping its direction. Vectors within the
+    /// limit are returned as they are, without taking a square root.
+    pub fn clamp_length(&self, max: Scalar) -> Vec2 {
+        if !self.is_longer_than(max) {
+            return *self;
+        }
+        self.scale(max / self.length())
+    }
+
     /// Rotates the vector by the given angle, in radians.
     pub fn rotate(&self, angle: Scalar) -> Vec2 {
         Vec2::new(
@@ -87,9 +128,66 @@ impl Vec2 {
         Vec2::new(self.x / length, self.y / length)
     }
 
+    /// Like [`Vec2::normalize`], but divides once for the inverse length and multiplies by
+    /// it, so the result can differ from an exact unit vector in the last digit.
+    pub fn approx_normalize(&self) -> Vec2 {
+        self.scale(dec64!(1) / self.length())
+    }
+
+    /// Multiplies both components by a factor.
+    pub fn scale(&self, factor: Scalar) -> Vec2 {
+        Vec2::new(self.x * factor, self.y * factor)
+    }
+
+    /// Mirrors the vector about a surface with the given unit normal.
+    pub fn reflect(&self, normal: &Vec2) -> Vec2 {
+        self.sub(&normal.scale(dec64!(2) * self.dot(normal)))
+    }
+
     /// Converts the vector to polar coordinates (r, theta).
-    pub fn to_polar(&self) -> (Scalar, Scalar) {
-        (self.length_squared().sqrt(), self.y.atan2(self.x))
+    pub fn to_polar(self) -> (Scalar, Scalar) {
+        (self.length_squared().sqrt(), self.angle())
+    }
+
+    /// Returns the angle from the positive x axis, in `(-π, π]`, or zero for the zero vector.
+    ///
+    /// fastnum's `atan2` fails outright once `|y / x|` gets large, so this keeps the ratio
+    /// handed to `atan` within `[-1, 1]` and works out the quadrant itself.
+    pub fn angle(&self) -> Scalar {
+        let (x, y) = (self.x, self.y);
+        let zero = dec64!(0);
+        let quarter = Scalar::PI / dec64!(2);
+        if x == zero && y == zero {
+            zero
+        } else if x.abs() >= y.abs() {
+            let base = (y / x).atan();
+            if x > zero {
+                base
+            } else if y >= zero {
+                base + Scalar::PI
+            } else {
+                base - Scalar::PI
+            }
+        } else if y > zero {
+            quarter - (x / y).atan()
+        } else {
+            -quarter - (x / y).atan()
+        }
+    }
+}
+
+/// Wraps an angle, in radians, into the range `(-π, π]`.
+pub fn wrap_angle(angle: Scalar) -> Scalar {
+    if angle > -Scalar::PI && angle <= Scalar::PI {
+        return angle;
+    }
+
+    let tau = Scalar::PI * dec64!(2);
+    let wrapped = angle - tau * ((angle + Scalar::PI) / tau).floor();
+    if wrapped == -Scalar::PI {
+        Scalar::PI
+    } else {
+        wrapped
     }
 }
 
@@ -108,6 +206,257 @@ impl std::ops::Sub for Vec2 {
     }
 }
 
+/// An angle in radians, always kept within `(-π, π]`.
+///
+/// Serializes as the bare radian value, so state and replays don't change shape.
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(from = "Scalar", into = "Scalar")]
+pub struct Angle(Scalar);
+
+impl Angle {
+    pub const ZERO: Angle = Angle(dec64!(0));
+
+    /// Creates an angle from radians, wrapping it into range.
+    pub fn new(radians: Scalar) -> Angle {
+        Angle(wrap_angle(radians))
+    }
+
+    /// Creates an angle from degrees, wrapping it into range.
+    pub fn from_degrees(degrees: Scalar) -> Angle {
+        Angle::new(degrees * Scalar::PI / dec64!(180))
+    }
+
+    /// Returns the angle a vector points at, or zero for the zero vector.
+    pub fn of(vector: Vec2) -> Angle {
+        Angle::new(vector.angle())
+    }
+
+    pub fn radians(self) -> Scalar {
+        self.0
+    }
+
+    pub fn degrees(self) -> Scalar {
+        self.0 * dec64!(180) / Scalar::PI
+    }
+
+    pub fn cos(self) -> Scalar {
+        self.0.cos()
+    }
+
+    pub fn sin(self) -> Scalar {
+        self.0.sin()
+    }
+
+    /// Returns the unit vector pointing along the angle.
+    pub fn direction(self) -> Vec2 {
+        Vec2::new_from_angle(dec64!(1), self.0)
+    }
+
+    /// Returns the signed turn, in radians, that takes this angle to `target` the short way
+    /// round. Exactly opposite angles turn by `+π`.
+    pub fn shortest_to(self, target: Angle) -> Scalar {
+        wrap_angle(target.0 - self.0)
+    }
+
+    /// Turns towards `target` the short way round by at most `max_step` radians.
+    pub fn step_towards(self, target: Angle, max_step: Scalar) -> Angle {
+        self + self.shortest_to(target).clamp(-max_step, max_step)
+    }
+
+    /// Interpolates towards `target` the short way round, `t` being 0 at `self` and 1 at
+    /// `target`.
+    pub fn lerp(self, target: Angle, t: Scalar) -> Angle {
+        self + self.shortest_to(target) * t
+    }
+}
+
+impl From<Scalar> for Angle {
+    fn from(radians: Scalar) -> Self {
+        Angle::new(radians)
+    }
+}
+
+impl From<Angle> for Scalar {
+    fn from(angle: Angle) -> Self {
+        angle.0
+    }
+}
+
+impl std::ops::Add<Scalar> for Angle {
+    type Output = Angle;
+    fn add(self, radians: Scalar) -> Self::Output {
+        Angle::new(self.0 + radians)
+    }
+}
+
+impl std::ops::Add for Angle {
+    type Output = Angle;
+    fn add(self, other: Angle) -> Self::Output {
+        self + other.0
+    }
+}
+
+impl std::ops::Sub<Scalar> for Angle {
+    type Output = Angle;
+    fn sub(self, radians: Scalar) -> Self::Output {
+        Angle::new(self.0 - radians)
+    }
+}
+
+impl std::ops::Sub for Angle {
+    type Output = Angle;
+    fn sub(self, other: Angle) -> Self::Output {
+        self - other.0
+    }
+}
+
+impl std::ops::Neg for Angle {
+    type Output = Angle;
+    fn neg(self) -> Self::Output {
+        Angle::new(-self.0)
+    }
+}
+
+/// An angle along with its cosine and sine, so rotating by it doesn't redo the trig, which is
+/// slow in decimal.
+///
+/// Serializes as the bare angle.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Angle", into = "Angle")]
+pub struct Rotation {
+    angle: Angle,
+    cos: Scalar,
+    sin: Scalar,
+}
+
+impl Rotation {
+    pub const IDENTITY: Rotation = Rotation {
+        angle: Angle::ZERO,
+        cos: dec64!(1),
+        sin: dec64!(0),
+    };
+
+    pub fn new(angle: Angle) -> Rotation {
+        Rotation {
+            angle,
+            cos: angle.cos(),
+            sin: angle.sin(),
+        }
+    }
+
+    pub fn angle(&self) -> Angle {
+        self.angle
+    }
+
+    pub fn cos(&self) -> Scalar {
+        self.cos
+    }
+
+    pub fn sin(&self) -> Scalar {
+        self.sin
+    }
+
+    /// Returns the unit vector pointing along the angle.
+    pub fn direction(&self) -> Vec2 {
+        Vec2::new(self.cos, self.sin)
+    }
+
+    /// Rotates a vector by the angle.
+    pub fn rotate(&self, vector: Vec2) -> Vec2 {
+        Vec2::new(
+            vector.x * self.cos - vector.y * self.sin,
+            vector.x * self.sin + vector.y * self.cos,
+        )
+    }
+
+    /// Rotates a vector back by the angle, undoing [`Rotation::rotate`].
+    pub fn unrotate(&self, vector: Vec2) -> Vec2 {
+        Vec2::new(
+            vector.x * self.cos + vector.y * self.sin,
+            vector.y * self.cos - vector.x * self.sin,
+        )
+    }
+}
+
+impl From<Angle> for Rotation {
+    fn from(angle: Angle) -> Self {
+        Rotation::new(angle)
+    }
+}
+
+impl From<Rotation> for Angle {
+    fn from(rotation: Rotation) -> Self {
+        rotation.angle
+    }
+}
+
+/// A [`Rotation`] worked out on first use and kept until asked for a different angle.
+///
+/// It's only a cache, not state: it's never serialized and always compares equal, so an
+/// entity holding one hashes and replays the same whether or not it's warm.
+#[derive(Clone, Debug, Default)]
+pub struct RotationCache(Cell<Option<Rotation>>);
+
+impl RotationCache {
+    /// Returns the rotation for an angle, reusing the cached one if the angle hasn't changed.
+    pub fn get(&self, angle: Angle) -> Rotation {
+        match self.0.get() {
+            Some(rotation) if rotation.angle == angle => rotation,
+            _ => {
+                let rotation = Rotation::new(angle);
+                self.0.set(Some(rotation));
+                rotation
+            }
+        }
+    }
+}
+
+impl PartialEq for RotationCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+/// A position and rotation: where a frame, such as a hull or a turret, sits in the world.
+///
+/// Local coordinates have x pointing along the rotation (forward) and y to its right.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Transform2 {
+    pub position: Vec2,
+    pub rotation: Rotation,
+}
+
+impl Transform2 {
+    pub fn new(position: Vec2, rotation: Rotation) -> Transform2 {
+        Transform2 { position, rotation }
+    }
+
+    /// Returns the world-space direction of the local x axis.
+    pub fn forward(&self) -> Vec2 {
+        self.rotation.direction()
+    }
+
+    /// Converts a point in this frame to world space.
+    pub fn to_world_point(&self, local: Vec2) -> Vec2 {
+        self.position + self.to_world_vector(local)
+    }
+
+    /// Converts a world-space point into this frame.
+    pub fn to_local_point(&self, world: Vec2) -> Vec2 {
+        self.to_local_vector(world - self.position)
+    }
+
+    /// Rotates a direction or velocity in this frame into world space.
+    pub fn to_world_vector(&self, local: Vec2) -> Vec2 {
+        self.rotation.rotate(local)
+    }
+
+    /// Rotates a world-space direction or velocity into this frame.
+    pub fn to_local_vector(&self, world: Vec2) -> Vec2 {
+        self.rotation.unrotate(world)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -232,6 +581,19 @@ mod tests {
         assert_eq!(rotated_180.y, angle_180.sin());
     }
 
+    #[test]
+    fn vec2_reflect_should_mirror_about_normal() {
+        // Arrange
+        let v = Vec2::new_from_f64(3.0, -4.0);
+        let normal = Vec2::new_from_f64(0.0, 1.0);
+
+        // Act
+        let reflected = v.reflect(&normal);
+
+        // Assert
+        assert_eq!(reflected, Vec2::new_from_f64(3.0, 4.0));
+    }
+
     #[test]
     fn vec2_normalize_should_return_unit_vector() {
         // Arrange
@@ -248,6 +610,30 @@ mod tests {
         assert_eq!(normalized.length_squared(), 1.0.to_scalar());
     }
 
+    #[test]
+    fn vec2_fused_helpers_should_match_plain_operations() {
+        // Arrange
+        let v = Vec2::new_from_f64(3.0, 4.0);
+        let w = Vec2::new_from_f64(-1.0, 2.0);
+
+        // Act
+        let moved = v.mul_add(&w, 0.5.to_scalar());
+        let clamped = v.clamp_length(2.5.to_scalar());
+        let untouched = v.clamp_length(6.0.to_scalar());
+        let unit = v.approx_normalize();
+
+        // Assert
+        assert_eq!(moved, Vec2::new_from_f64(2.5, 5.0));
+        assert_eq!(v.distance_squared(&w), v.sub(&w).length_squared());
+        assert_eq!(v.length(), 5.0.to_scalar());
+        assert!(v.is_longer_than(4.9.to_scalar()) && !v.is_longer_than(5.0.to_scalar()));
+        assert!(v.is_shorter_than(5.1.to_scalar()) && !v.is_shorter_than(5.0.to_scalar()));
+        assert_eq!(clamped, Vec2::new_from_f64(1.5, 2.0));
+        assert_eq!(untouched, v);
+        assert!((unit.x - 0.6.to_scalar()).abs() < 1e-15.to_scalar());
+        assert!((unit.y - 0.8.to_scalar()).abs() < 1e-15.to_scalar());
+    }
+
     #[test]
     fn vec2_to_polar_should_convert_to_polar_coordinates() {
         // Arrange
@@ -272,4 +658,126 @@ mod tests {
         assert_eq!(magnitude2, 1.0.to_scalar());
         assert_eq!(angle2, Scalar::PI);
     }
+
+    #[test]
+    fn vec2_angle_when_nearly_vertical_should_match_f64_atan2() {
+        // Arrange
+        let cases = [
+            (-0.09, 449.8),
+            (0.5, 10.0),
+            (-1.0, -10.0),
+            (3.0, -0.25),
+            (-2.0, 0.0),
+            (0.0, -7.0),
+        ];
+
+        for (x, y) in cases {
+            // Act
+            let angle = Vec2::new_from_f64(x, y).angle();
+
+            // Assert
+            let error = (angle.to_f64() - y.atan2(x)).abs();
+            assert!(error < 1e-12, "angle of ({x}, {y}) was {angle}");
+        }
+        assert_eq!(Vec2::zero().angle(), 0.0.to_scalar());
+    }
+
+    #[test]
+    fn wrap_angle_should_keep_angles_within_half_open_range() {
+        // Arrange
+        let pi = Scalar::PI;
+        let tau = pi * 2.0.to_scalar();
+        let quarter = pi / 2.0.to_scalar();
+
+        // Act & Assert
+        assert_eq!(wrap_angle(quarter), quarter);
+        assert_eq!(wrap_angle(pi), pi);
+        assert_eq!(wrap_angle(-pi), pi);
+        assert_eq!(wrap_angle(4.0.to_scalar()), 4.0.to_scalar() - tau);
+        assert_eq!(wrap_angle((-4.0).to_scalar()), (-4.0).to_scalar() + tau);
+
+        // D64 rounds in the last digit when adding full turns, so only check the range here.
+        let wrapped = wrap_angle(-quarter - tau * 3.0.to_scalar());
+        assert!(wrapped > -pi && wrapped <= pi);
+        assert!((wrapped + quarter).abs() < 1e-15.to_scalar());
+    }
+
+    #[test]
+    fn angle_should_stay_in_range_and_turn_the_short_way_round() {
+        // Arrange
+        let pi = Scalar::PI;
+        let near_pi = Angle::new(dec64!(3));
+        let near_minus_pi = Angle::new(dec64!(-3));
+
+        // Act
+        let wrapped = Angle::new(pi * 3.0.to_scalar());
+        let sum = near_pi + near_pi;
+        let turn = near_pi.shortest_to(near_minus_pi);
+        let stepped = near_pi.step_towards(near_minus_pi, dec64!(0.1));
+        let halfway = near_pi.lerp(near_minus_pi, 0.5.to_scalar());
+
+        // Assert
+        assert_eq!(wrapped.radians(), pi);
+        assert_eq!(sum.radians(), dec64!(6) - pi * 2.0.to_scalar());
+        assert_eq!(turn, pi * 2.0.to_scalar() - dec64!(6));
+        assert_eq!(stepped.radians(), dec64!(3.1));
+        assert_eq!(halfway.radians(), pi);
+        let degrees = Angle::from_degrees(dec64!(90)).degrees();
+        assert!((degrees - dec64!(90)).abs() < dec64!(1e-12));
+        assert_eq!(
+            Angle::of(Vec2::new_from_f64(0.0, -2.0)).radians(),
+            -pi / 2.0.to_scalar()
+        );
+    }
+
+    #[test]
+    fn transform2_should_round_trip_points_and_vectors() {
+        // Arrange
+        let transform = Transform2::new(
+            Vec2::new_from_f64(10.0, 5.0),
+            Rotation::new(Angle::new(Scalar::PI / dec64!(2))),
+        );
+        let close = |a: Vec2, b: Vec2| a.sub(&b).length_squared() < dec64!(1e-24);
+
+        // Act
+        let point = transform.to_world_point(Vec2::new_from_f64(2.0, 1.0));
+        let vector = transform.to_world_vector(Vec2::new_from_f64(2.0, 1.0));
+        let back = transform.to_local_point(point);
+
+        // Assert
+        // forward is +y in world space, and right is -x
+        assert!(close(point, Vec2::new_from_f64(9.0, 7.0)));
+        assert!(close(vector, Vec2::new_from_f64(-1.0, 2.0)));
+        assert!(close(back, Vec2::new_from_f64(2.0, 1.0)));
+        assert!(close(
+            transform.to_local_vector(vector),
+            Vec2::new_from_f64(2.0, 1.0)
+        ));
+        assert!(close(transform.forward(), Vec2::new_from_f64(0.0, 1.0)));
+    }
+
+    #[test]
+    fn rotation_cache_should_recompute_only_when_the_angle_changes() {
+        // Arrange
+        let cache = RotationCache::default();
+        let (first, second) = (Angle::new(dec64!(0.5)), Angle::new(dec64!(-2)));
+
+        // Act
+        let cold = cache.get(first);
+        let warm = cache.get(first);
+        let changed = cache.get(second);
+
+        // Assert
+        assert_eq!(cold, Rotation::new(first));
+        assert_eq!(warm, cold);
+        assert_eq!(changed, Rotation::new(second));
+        assert_eq!(cache.0.get(), Some(changed));
+        let round_trip = changed.unrotate(changed.rotate(Vec2::new_from_f64(3.0, 4.0)));
+        assert!(
+            round_trip
+                .sub(&Vec2::new_from_f64(3.0, 4.0))
+                .length_squared()
+                < dec64!(1e-24)
+        );
+    }
 }
@@ -1,2 +1,5 @@
 pub mod math;
+pub mod numeric;
+pub mod pool;
+pub mod rng;
 pub mod spatial;
@@ -0,0 +1,128 @@
+use crate::util::math::{Angle, Scalar};
+use fastnum::dec64;
+use serde::{Deserialize, Serialize};
+
+/// Differences smaller than this are treated as rounding rather than real.
+///
+/// Scalars are decimal, so sums of exact inputs stay exact, but division and square roots
+/// round after 16 digits, and this leaves plenty of room above that.
+pub const EPSILON: Scalar = dec64!(0.000000001);
+
+/// Radians within which two headings count as the same.
+pub const ANGLE_TOLERANCE: Scalar = dec64!(0.000001);
+
+/// Default for [`NumericPolicy::penetration_slop`].
+pub const PENETRATION_SLOP: Scalar = dec64!(0.005);
+
+/// Default for [`NumericPolicy::surface_clearance`].
+pub const SURFACE_CLEARANCE: Scalar = dec64!(0.001);
+
+/// Default for [`NumericPolicy::max_tank_speed`].
+pub const MAX_TANK_SPEED: Scalar = dec64!(50);
+
+/// Returns whether `a` and `b` are within [`EPSILON`] of each other.
+pub fn approx_eq(a: Scalar, b: Scalar) -> bool {
+    approx_eq_within(a, b, EPSILON)
+}
+
+/// Returns whether `a` and `b` are within `tolerance` of each other.
+pub fn approx_eq_within(a: Scalar, b: Scalar, tolerance: Scalar) -> bool {
+    (a - b).abs() <= tolerance
+}
+
+/// Returns whether `value` is within [`EPSILON`] of zero.
+pub fn is_zero(value: Scalar) -> bool {
+    value.abs() <= EPSILON
+}
+
+/// Returns whether `a` and `b` point the same way to within [`ANGLE_TOLERANCE`], going the
+/// short way round.
+pub fn angles_approx_eq(a: Angle, b: Angle) -> bool {
+    a.shortest_to(b).abs() <= ANGLE_TOLERANCE
+}
+
+/// Tolerances and limits the physics works to, so they're tuned in one place rather than
+/// scattered through the code as literals.
+///
+/// Part of the match rules, so replays come out the same wherever they're played.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NumericPolicy {
+    /// Overlap between tanks that's left in place rather than pushed apart, so resting
+    /// contacts stay in touch instead of jittering in and out of contact every tick.
+    pub penetration_slop: Scalar,
+    /// How far projectiles are pushed off a surface they bounce from or detonate against.
+    pub surface_clearance: Scalar,
+    /// Fastest a tank can move in a tick, whatever pushes it, so a pile of impulses can't fling
+    /// it through walls.
+    pub max_tank_speed: Scalar,
+}
+
+impl Default for NumericPolicy {
+    fn default() -> Self {
+        NumericPolicy {
+            penetration_slop: PENETRATION_SLOP,
+            surface_clearance: SURFACE_CLEARANCE,
+            max_tank_speed: MAX_TANK_SPEED,
+        }
+    }
+}
+
+impl NumericPolicy {
+    /// Returns whether every setting is usable: none negative, and tanks allowed to move.
+    pub fn is_valid(&self) -> bool {
+        self.penetration_slop >= dec64!(0)
+            && self.surface_clearance >= dec64!(0)
+            && self.max_tank_speed > dec64!(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn approx_eq_should_ignore_rounding_but_not_real_differences() {
+        // Arrange
+        let third = dec64!(1) / dec64!(3);
+
+        // Act
+        let rounded = third * dec64!(3);
+
+        // Assert
+        assert!(approx_eq(rounded, dec64!(1)));
+        assert!(!approx_eq(dec64!(1.001), dec64!(1)));
+        assert!(approx_eq_within(dec64!(1.001), dec64!(1), dec64!(0.01)));
+        assert!(is_zero(rounded - dec64!(1)));
+        assert!(!is_zero(dec64!(0.001)));
+    }
+
+    #[test]
+    fn angles_approx_eq_should_compare_across_wrap() {
+        // Arrange
+        let pi = Angle::new(dec64!(3.14159265358979));
+
+        // Act
+        let other_side = Angle::new(-dec64!(3.14159265358979));
+
+        // Assert
+        assert!(angles_approx_eq(pi, other_side));
+        assert!(!angles_approx_eq(pi, Angle::ZERO));
+    }
+
+    #[test]
+    fn is_valid_when_speed_limit_zero_should_fail() {
+        // Arrange
+        let policy = NumericPolicy {
+            max_tank_speed: dec64!(0),
+            ..NumericPolicy::default()
+        };
+
+        // Act
+        let valid = policy.is_valid();
+
+        // Assert
+        assert!(NumericPolicy::default().is_valid());
+        assert!(!valid);
+    }
+}
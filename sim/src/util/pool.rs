@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+
+/// Refers to a value in a [`Pool`]. Goes stale once the value is removed, even if its slot is
+/// reused for something else.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Handle {
+    index: u32,
+    generation: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// A store for short-lived values that reuses slots instead of shifting or reallocating.
+///
+/// Values never move once inserted, so handles stay valid until removal, and once the pool has
+/// grown to its working size, inserting and removing don't allocate. Iteration is in slot order,
+/// which depends only on the sequence of inserts and removals, so it's deterministic.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Pool<T> {
+    slots: Vec<Slot<T>>,
+    /// Empty slots, reused last-freed first.
+    free: Vec<u32>,
+    len: usize,
+}
+
+impl<T> Default for Pool<T> {
+    fn default() -> Self {
+        Pool {
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+        }
+    }
+}
+
+impl<T> Pool<T> {
+    pub fn new() -> Self {
+        Pool::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Stores a value, reusing an empty slot if there is one.
+    pub fn insert(&mut self, value: T) -> Handle {
+        self.len += 1;
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index as usize];
+            slot.value = Some(value);
+            return Handle {
+                index,
+                generation: slot.generation,
+            };
+        }
+        self.slots.push(Slot {
+            generation: 0,
+            value: Some(value),
+        });
+        Handle {
+            index: self.slots.len() as u32 - 1,
+            generation: 0,
+        }
+    }
+
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        let slot = self.slots.get(handle.index as usize)?;
+        (slot.generation == handle.generation)
+            .then_some(slot.value.as_ref())
+            .flatten()
+    }
+
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        let slot = self.slots.get_mut(handle.index as usize)?;
+        (slot.generation == handle.generation)
+            .then_some(slot.value.as_mut())
+            .flatten()
+    }
+
+    /// Takes a value out, or returns `None` if the handle is stale.
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let slot = self.slots.get_mut(handle.index as usize)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        let value = slot.value.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(handle.index);
+        self.len -= 1;
+        Some(value)
+    }
+
+    /// Removes every value for which `keep` returns `false`, visiting them in slot order.
+    pub fn retain_mut(&mut self, mut keep: impl FnMut(&mut T) -> bool) {
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            let Some(value) = slot.value.as_mut() else {
+                continue;
+            };
+            if !keep(value) {
+                slot.value = None;
+                slot.generation = slot.generation.wrapping_add(1);
+                self.free.push(index as u32);
+                self.len -= 1;
+            }
+        }
+    }
+
+    /// Removes everything, keeping the slots for reuse.
+    pub fn clear(&mut self) {
+        self.retain_mut(|_| false);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter().filter_map(|slot| slot.value.as_ref())
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.slots.iter_mut().filter_map(|slot| slot.value.as_mut())
+    }
+
+    /// Like [`Pool::iter`], along with each value's handle.
+    pub fn handles(&self) -> impl Iterator<Item = (Handle, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let handle = Handle {
+                index: index as u32,
+                generation: slot.generation,
+            };
+            slot.value.as_ref().map(|value| (handle, value))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pool_remove_should_reuse_slot_and_invalidate_old_handle() {
+        // Arrange
+        let mut pool = Pool::new();
+        let first = pool.insert("first");
+        let second = pool.insert("second");
+
+        // Act
+        pool.remove(first);
+        let third = pool.insert("third");
+
+        // Assert
+        assert_eq!(pool.get(first), None);
+        assert_eq!(pool.get(third), Some(&"third"));
+        assert_eq!(pool.get(second), Some(&"second"));
+        assert_eq!(
+            pool.iter().copied().collect::<Vec<_>>(),
+            ["third", "second"]
+        );
+        assert_eq!(pool.remove(first), None);
+        assert_eq!(pool.len(), 2);
+    }
+
+    #[test]
+    fn pool_retain_mut_should_free_slots_without_moving_survivors() {
+        // Arrange
+        let mut pool = Pool::new();
+        let handles: Vec<Handle> = (0..5).map(|value| pool.insert(value)).collect();
+
+        // Act
+        pool.retain_mut(|value| {
+            *value *= 10;
+            *value != 20
+        });
+
+        // Assert
+        assert_eq!(pool.len(), 4);
+        assert_eq!(pool.get(handles[2]), None);
+        assert_eq!(pool.get(handles[3]), Some(&30));
+        assert_eq!(pool.iter().copied().collect::<Vec<_>>(), [0, 10, 30, 40]);
+    }
+}
@@ -0,0 +1,52 @@
+/// A small seeded random number generator for procedural content.
+///
+/// SplitMix64, spelled out here rather than taken from a crate, so a seed produces the same
+/// content on every platform, build and dependency version.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `min..=max`.
+    pub fn range(&mut self, min: u32, max: u32) -> u32 {
+        if max <= min {
+            return min;
+        }
+        let span = (max - min) as u128 + 1;
+        min + ((self.next_u64() as u128 * span) >> 64) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range_should_stay_in_bounds_and_repeat_for_seed() {
+        // Arrange
+        let mut first = Rng::new(42);
+        let mut second = Rng::new(42);
+
+        // Act
+        let a: Vec<u32> = (0..100).map(|_| first.range(3, 7)).collect();
+        let b: Vec<u32> = (0..100).map(|_| second.range(3, 7)).collect();
+
+        // Assert
+        assert_eq!(a, b);
+        assert!(a.iter().all(|value| (3..=7).contains(value)));
+        assert!((3..=7).all(|value| a.contains(&value)));
+    }
+}
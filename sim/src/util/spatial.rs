@@ -1,6 +1,77 @@
 use crate::physics::collision::AABB;
-use crate::util::math::{ConvertToScalar, Scalar};
-use std::collections::HashSet;
+use crate::util::math::{Angle, ConvertToScalar, Scalar, Vec2};
+use fastnum::dec64;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// Cells per object beyond which an auto-sized grid stops shrinking its cells.
+const MAX_CELLS_PER_OBJECT: Scalar = dec64!(4);
+
+/// How objects are spread over a grid's cells, for spotting badly sized grids.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OccupancyStats {
+    pub cells: u32,
+    pub occupied_cells: u32,
+    /// Objects stored, each counted once however many cells it covers.
+    pub objects: u32,
+    pub max_per_cell: u32,
+    /// Objects per occupied cell, on average.
+    pub mean_per_occupied_cell: Scalar,
+    /// Cells each object covers, on average. Well above 4 means cells are small for the objects.
+    pub cells_per_object: Scalar,
+    /// How many cells hold each number of objects: `histogram[n]` cells hold exactly `n`.
+    pub histogram: Vec<u32>,
+}
+
+impl OccupancyStats {
+    /// Returns whether the grid is sized badly enough to hurt: objects smeared over many cells,
+    /// or piled into a few cells so that lookups degrade towards checking everything.
+    pub fn is_pathological(&self) -> bool {
+        self.cells_per_object > dec64!(9) || self.max_per_cell > 32
+    }
+}
+
+/// A grid's occupancy along with its dimensions, enough to suggest a better cell size.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GridStats {
+    pub map_width: Scalar,
+    pub map_height: Scalar,
+    pub cell_width: Scalar,
+    pub cell_height: Scalar,
+    pub occupancy: OccupancyStats,
+}
+
+impl GridStats {
+    /// Suggests a cell size for the objects the grid holds, if the current one is off by more
+    /// than a factor of two, or `None` if it will do.
+    ///
+    /// The objects' size is worked back from how many cells each covers: an object `e` across
+    /// covers about `(1 + e / s)²` cells of size `s`. The suggestion then follows the same rule
+    /// as [`SpatialHashMap::auto_sized`].
+    pub fn suggested_cell_size(&self) -> Option<Scalar> {
+        let occupancy = &self.occupancy;
+        if occupancy.objects == 0 {
+            return None;
+        }
+        let current = (self.cell_width + self.cell_height) / dec64!(2);
+        let extent = (occupancy.cells_per_object.sqrt() - dec64!(1)).max(dec64!(0)) * current;
+        let max_cells = Scalar::from(occupancy.objects) * MAX_CELLS_PER_OBJECT;
+        let smallest = (self.map_width * self.map_height / max_cells).sqrt();
+        let suggested = (extent * dec64!(2)).max(smallest);
+        (suggested * dec64!(2) < current || suggested > current * dec64!(2)).then_some(suggested)
+    }
+}
+
+/// Returns the average of each box's width and height, e.g. to size a grid with.
+pub fn average_extent(boxes: &[AABB]) -> Scalar {
+    if boxes.is_empty() {
+        return dec64!(0);
+    }
+    let total: Scalar = boxes
+        .iter()
+        .map(|aabb| (aabb.max.x - aabb.min.x + aabb.max.y - aabb.min.y) / dec64!(2))
+        .sum();
+    total / Scalar::from(boxes.len() as u64)
+}
 
 /// A spatial hashmap for storing objects (with AABB bounding boxes) in a 2D grid.
 ///
@@ -35,6 +106,43 @@ impl SpatialHashMap {
         }
     }
 
+    /// Creates a grid with square-ish cells of about the given size, fitted to the map.
+    pub fn with_cell_size(map_width: Scalar, map_height: Scalar, cell_size: Scalar) -> Self {
+        let grid_width = (map_width / cell_size).ceil().to_u32().unwrap_or(1).max(1);
+        let grid_height = (map_height / cell_size).ceil().to_u32().unwrap_or(1).max(1);
+        SpatialHashMap::new(map_width, map_height, grid_width, grid_height)
+    }
+
+    /// Creates a grid sized for the objects it's expected to hold.
+    ///
+    /// Cells are about twice the objects' average extent (see [`average_extent`]), so most
+    /// objects cover one to four cells, but never so small that there are many more cells than
+    /// objects.
+    pub fn auto_sized(
+        map_width: Scalar,
+        map_height: Scalar,
+        average_extent: Scalar,
+        expected_count: u32,
+    ) -> Self {
+        let max_cells = Scalar::from(expected_count.max(1)) * MAX_CELLS_PER_OBJECT;
+        let smallest = (map_width * map_height / max_cells).sqrt();
+        let cell_size = (average_extent * dec64!(2)).max(smallest);
+        if cell_size <= dec64!(0) {
+            return SpatialHashMap::new(map_width, map_height, 1, 1);
+        }
+        SpatialHashMap::with_cell_size(map_width, map_height, cell_size)
+    }
+
+    /// Returns the width of a single cell.
+    pub fn cell_width(&self) -> Scalar {
+        self.cell_width
+    }
+
+    /// Returns the height of a single cell.
+    pub fn cell_height(&self) -> Scalar {
+        self.cell_height
+    }
+
     /// Returns the keys of all the cells that contain the given AABB.
     pub fn keys_iter(&self, aabb: &AABB) -> impl Iterator<Item = u32> + use<> {
         // clamp AABB to be within the map bounds
@@ -82,6 +190,301 @@ impl SpatialHashMap {
         }
     }
 
+    /// Returns the column and row of the cell a point falls in, clamped to the grid.
+    fn cell_coords(&self, point: Vec2) -> (u32, u32) {
+        let column = (point.x * self.inv_cell_width)
+            .floor()
+            .to_i64()
+            .unwrap_or(0)
+            .clamp(0, self.grid_width as i64 - 1);
+        let row = (point.y * self.inv_cell_height)
+            .floor()
+            .to_i64()
+            .unwrap_or(0)
+            .clamp(0, self.grid_height as i64 - 1);
+        (column as u32, row as u32)
+    }
+
+    /// Returns the area a cell covers.
+    fn cell_bounds(&self, key: u32) -> AABB {
+        let column = Scalar::from(key % self.grid_width);
+        let row = Scalar::from(key / self.grid_width);
+        let min = Vec2::new(column * self.cell_width, row * self.cell_height);
+        AABB::new(min, min + Vec2::new(self.cell_width, self.cell_height))
+    }
+
+    /// Returns the keys of the cells exactly `ring` cells away from a cell, counting diagonal
+    /// steps as one, that lie on the grid. Ring zero is the cell itself.
+    fn ring_keys(&self, (column, row): (u32, u32), ring: u32) -> Vec<u32> {
+        let (column, row, ring) = (column as i64, row as i64, ring as i64);
+        let on_grid = |x: i64, y: i64| {
+            (0..self.grid_width as i64).contains(&x) && (0..self.grid_height as i64).contains(&y)
+        };
+        let key = |x: i64, y: i64| (x + y * self.grid_width as i64) as u32;
+        let mut keys = Vec::new();
+        for x in column - ring..=column + ring {
+            for y in [row - ring, row + ring] {
+                if on_grid(x, y) {
+                    keys.push(key(x, y));
+                }
+                if ring == 0 {
+                    break;
+                }
+            }
+        }
+        for y in row - ring + 1..row + ring {
+            for x in [column - ring, column + ring] {
+                if on_grid(x, y) {
+                    keys.push(key(x, y));
+                }
+            }
+        }
+        keys
+    }
+
+    /// Returns the objects in the cell a point falls in: what a query with a box shrunk to the
+    /// point would find, without the box or a copy of the set.
+    pub fn query_point(&self, point: Vec2) -> &HashSet<u32> {
+        let (column, row) = self.cell_coords(point);
+        &self.grid[(column + row * self.grid_width) as usize]
+    }
+
+    /// Returns the IDs of objects in the cells a circle touches. Like [`SpatialHashMap::query`],
+    /// these are candidates: callers check the objects' actual shapes.
+    pub fn query_circle(&self, center: Vec2, radius: Scalar) -> HashSet<u32> {
+        let reach = Vec2::new(radius, radius);
+        let bounds = AABB::new(center.sub(&reach), center.add(&reach));
+        let mut result = HashSet::new();
+        for key in self.keys_iter(&bounds) {
+            // the box around the circle takes in corner cells the circle misses
+            if self.cell_bounds(key).distance_squared_to(center) > radius * radius {
+                continue;
+            }
+            if let Some(cell) = self.grid.get(key as usize) {
+                result.extend(cell);
+            }
+        }
+        result
+    }
+
+    /// Returns how far along a segment, as a fraction, it next crosses a cell boundary on one
+    /// axis, or `None` if it doesn't cross another before leaving the grid. Edge cells reach
+    /// out past the map, as objects beyond it are clamped into them.
+    fn next_crossing(
+        origin: Scalar,
+        delta: Scalar,
+        index: u32,
+        count: u32,
+        size: Scalar,
+    ) -> Option<Scalar> {
+        let boundary = if delta > dec64!(0) && index + 1 < count {
+            Scalar::from(index + 1) * size
+        } else if delta < dec64!(0) && index > 0 {
+            Scalar::from(index) * size
+        } else {
+            return None;
+        };
+        Some((boundary - origin) / delta)
+    }
+
+    /// Returns the IDs of objects in the cells the segment `start -> end` passes through, in
+    /// the order it reaches the cells, each once. Objects first met in the same cell are in ID
+    /// order. Like [`SpatialHashMap::query`], these are candidates: callers check the objects'
+    /// actual shapes, and sort by where the segment meets them if the order matters.
+    ///
+    /// Cells are walked one boundary crossing at a time, so only cells the segment passes
+    /// through are looked at, however long it is.
+    pub fn query_segment(&self, start: Vec2, end: Vec2) -> Vec<u32> {
+        let delta = end.sub(&start);
+        let (mut column, mut row) = self.cell_coords(start);
+        let mut seen = HashSet::new();
+        let mut result = Vec::new();
+        loop {
+            let key = column + row * self.grid_width;
+            let mut met: Vec<u32> = self.grid[key as usize]
+                .iter()
+                .copied()
+                .filter(|object_id| seen.insert(*object_id))
+                .collect();
+            met.sort_unstable();
+            result.extend(met);
+
+            let across =
+                Self::next_crossing(start.x, delta.x, column, self.grid_width, self.cell_width);
+            let down =
+                Self::next_crossing(start.y, delta.y, row, self.grid_height, self.cell_height);
+            // through a corner, the column is stepped first and the row on the next pass
+            match (across, down) {
+                (Some(x), Some(y)) if x <= y && x <= dec64!(1) => {
+                    column = Self::step(column, delta.x)
+                }
+                (Some(x), None) if x <= dec64!(1) => column = Self::step(column, delta.x),
+                (_, Some(y)) if y <= dec64!(1) => row = Self::step(row, delta.y),
+                _ => break,
+            }
+        }
+        result
+    }
+
+    fn step(index: u32, delta: Scalar) -> u32 {
+        if delta > dec64!(0) {
+            index + 1
+        } else {
+            index - 1
+        }
+    }
+
+    /// Returns the IDs of objects in the cells a circular sector touches: those within `radius`
+    /// of `center` and within half of `arc` either side of `facing`. Like
+    /// [`SpatialHashMap::query`], these are candidates: callers check the objects' actual
+    /// shapes.
+    pub fn query_arc(
+        &self,
+        center: Vec2,
+        radius: Scalar,
+        facing: Angle,
+        arc: Scalar,
+    ) -> HashSet<u32> {
+        if arc >= Scalar::PI * dec64!(2) {
+            return self.query_circle(center, radius);
+        }
+        // the sector's bounds take in its tip, both ends of its rim, and any point of the rim
+        // that lies furthest along an axis
+        let half_arc = arc / dec64!(2);
+        let mut corners = vec![
+            center,
+            center + (facing - half_arc).direction().scale(radius),
+            center + (facing + half_arc).direction().scale(radius),
+        ];
+        for quarter in 0..4 {
+            let axis = Angle::new(Scalar::PI / dec64!(2) * Scalar::from(quarter));
+            if facing.shortest_to(axis).abs() <= half_arc {
+                corners.push(center + axis.direction().scale(radius));
+            }
+        }
+        let bounds = corners
+            .iter()
+            .fold(AABB::new(center, center), |bounds, corner| {
+                AABB::new(
+                    Vec2::new(bounds.min.x.min(corner.x), bounds.min.y.min(corner.y)),
+                    Vec2::new(bounds.max.x.max(corner.x), bounds.max.y.max(corner.y)),
+                )
+            });
+        let mut result = HashSet::new();
+        for key in self.keys_iter(&bounds) {
+            if self.cell_bounds(key).distance_squared_to(center) > radius * radius {
+                continue;
+            }
+            if let Some(cell) = self.grid.get(key as usize) {
+                result.extend(cell);
+            }
+        }
+        result
+    }
+
+    /// Returns up to `k` objects nearest a point, nearest first, with ties going to the lower
+    /// ID.
+    ///
+    /// `distance_squared` measures an object's squared distance from the point, or returns
+    /// `None` to leave it out. Cells are searched in rings spreading out from the point's cell,
+    /// stopping once nothing in the rings left could be nearer than what's been found, so
+    /// nearby queries only look at nearby cells.
+    pub fn k_nearest(
+        &self,
+        center: Vec2,
+        k: usize,
+        mut distance_squared: impl FnMut(u32) -> Option<Scalar>,
+    ) -> Vec<u32> {
+        if k == 0 {
+            return Vec::new();
+        }
+        let origin = self.cell_coords(center);
+        let step = self.cell_width.min(self.cell_height);
+        let mut seen = HashSet::new();
+        let mut found: Vec<(Scalar, u32)> = Vec::new();
+        for ring in 0..self.grid_width.max(self.grid_height) {
+            for key in self.ring_keys(origin, ring) {
+                for object_id in &self.grid[key as usize] {
+                    if seen.insert(*object_id)
+                        && let Some(distance) = distance_squared(*object_id)
+                    {
+                        found.push((distance, *object_id));
+                    }
+                }
+            }
+            found.sort_unstable();
+            found.truncate(k);
+            // anything not yet seen is in a farther ring, at least this many cells away
+            let horizon = Scalar::from(ring) * step;
+            if found.len() == k && found[k - 1].0 <= horizon * horizon {
+                break;
+            }
+        }
+        found.into_iter().map(|(_, object_id)| object_id).collect()
+    }
+
+    /// Returns the number of columns and rows of cells.
+    pub fn grid_size(&self) -> (u32, u32) {
+        (self.grid_width, self.grid_height)
+    }
+
+    /// Returns the objects in each cell, in row-major order.
+    pub fn cells(&self) -> &[HashSet<u32>] {
+        &self.grid
+    }
+
+    /// Returns how many objects each cell holds, in row-major order.
+    pub fn cell_counts(&self) -> Vec<u32> {
+        self.grid.iter().map(|cell| cell.len() as u32).collect()
+    }
+
+    /// Summarises how the stored objects are spread over the cells.
+    pub fn occupancy(&self) -> OccupancyStats {
+        let mut objects = HashSet::new();
+        let mut entries = 0u64;
+        let mut occupied_cells = 0;
+        let mut max_per_cell = 0;
+        let mut histogram = vec![0; 1];
+        for cell in &self.grid {
+            tally(&mut histogram, cell.len());
+            if cell.is_empty() {
+                continue;
+            }
+            objects.extend(cell.iter().copied());
+            entries += cell.len() as u64;
+            occupied_cells += 1;
+            max_per_cell = max_per_cell.max(cell.len() as u32);
+        }
+        let ratio = |numerator: u64, denominator: u64| {
+            if denominator == 0 {
+                dec64!(0)
+            } else {
+                Scalar::from(numerator) / Scalar::from(denominator)
+            }
+        };
+        OccupancyStats {
+            cells: self.grid.len() as u32,
+            occupied_cells,
+            objects: objects.len() as u32,
+            max_per_cell,
+            mean_per_occupied_cell: ratio(entries, occupied_cells as u64),
+            cells_per_object: ratio(entries, objects.len() as u64),
+            histogram,
+        }
+    }
+
+    /// Returns the grid's occupancy and dimensions, e.g. to check whether its cell size suits
+    /// what it holds.
+    pub fn stats(&self) -> GridStats {
+        GridStats {
+            map_width: self.map_width,
+            map_height: self.map_height,
+            cell_width: self.cell_width,
+            cell_height: self.cell_height,
+            occupancy: self.occupancy(),
+        }
+    }
+
     /// Returns all unique object IDs in the specified cell.
     pub fn get(&self, key: u32) -> HashSet<u32> {
         self.grid.get(key as usize).cloned().unwrap_or_default()
@@ -100,6 +503,38 @@ impl SpatialHashMap {
         result
     }
 
+    /// Removes an object that was inserted with the given AABB.
+    pub fn remove(&mut self, object_id: u32, aabb: &AABB) {
+        for key in self.keys_iter(aabb) {
+            if let Some(cell) = self.grid.get_mut(key as usize) {
+                cell.remove(&object_id);
+            }
+        }
+    }
+
+    /// Moves an object from one AABB to another, touching only the cells that differ.
+    ///
+    /// Returns whether the set of cells it covers changed.
+    pub fn update(&mut self, object_id: u32, old: &AABB, new: &AABB) -> bool {
+        let old_keys: Vec<u32> = self.keys_iter(old).collect();
+        let new_keys: Vec<u32> = self.keys_iter(new).collect();
+        if old_keys == new_keys {
+            return false;
+        }
+
+        for key in old_keys.iter().filter(|key| !new_keys.contains(key)) {
+            if let Some(cell) = self.grid.get_mut(*key as usize) {
+                cell.remove(&object_id);
+            }
+        }
+        for key in new_keys.iter().filter(|key| !old_keys.contains(key)) {
+            if let Some(cell) = self.grid.get_mut(*key as usize) {
+                cell.insert(object_id);
+            }
+        }
+        true
+    }
+
     /// Clears all objects from the grid.
     pub fn clear(&mut self) {
         for cell in self.grid.iter_mut() {
@@ -108,12 +543,416 @@ impl SpatialHashMap {
     }
 }
 
-// ...existing code...
+/// A grid that objects can be put in and looked up by area, whatever its layout.
+pub trait BroadPhase {
+    fn insert(&mut self, object_id: u32, aabb: &AABB);
+
+    /// Takes an object out, given the AABB it was inserted or last updated with.
+    fn remove(&mut self, object_id: u32, aabb: &AABB);
+
+    /// Moves an object from one AABB to another. Returns whether the cells it covers changed.
+    fn update(&mut self, object_id: u32, old: &AABB, new: &AABB) -> bool;
+
+    /// Returns the IDs of objects in the cells the AABB touches. These are candidates:
+    /// callers check the objects' actual shapes.
+    fn query(&self, aabb: &AABB) -> HashSet<u32>;
+
+    /// Like [`BroadPhase::query`], for the cells a circle touches.
+    fn query_circle(&self, center: Vec2, radius: Scalar) -> HashSet<u32>;
+
+    /// Returns the IDs of objects in the cell a point falls in, without building a box or a
+    /// set around it. An object in more than one layer's cell is only listed once per layer.
+    fn query_point(&self, point: Vec2) -> impl Iterator<Item = u32> + '_;
+
+    fn clear(&mut self);
+}
+
+impl BroadPhase for SpatialHashMap {
+    fn insert(&mut self, object_id: u32, aabb: &AABB) {
+        SpatialHashMap::insert(self, object_id, aabb);
+    }
+
+    fn remove(&mut self, object_id: u32, aabb: &AABB) {
+        SpatialHashMap::remove(self, object_id, aabb);
+    }
+
+    fn update(&mut self, object_id: u32, old: &AABB, new: &AABB) -> bool {
+        SpatialHashMap::update(self, object_id, old, new)
+    }
+
+    fn query(&self, aabb: &AABB) -> HashSet<u32> {
+        SpatialHashMap::query(self, aabb)
+    }
+
+    fn query_circle(&self, center: Vec2, radius: Scalar) -> HashSet<u32> {
+        SpatialHashMap::query_circle(self, center, radius)
+    }
+
+    fn query_point(&self, point: Vec2) -> impl Iterator<Item = u32> + '_ {
+        SpatialHashMap::query_point(self, point).iter().copied()
+    }
+
+    fn clear(&mut self) {
+        SpatialHashMap::clear(self);
+    }
+}
+
+/// Two grids over the same map: a fine one for small objects and a coarse one for large ones.
+///
+/// A huge obstacle in a fine grid covers hundreds of cells, so every query near it wades
+/// through its ID over and over, and inserting or moving it touches all those cells. Here
+/// anything wider or taller than two fine cells goes in the coarse grid instead, where it
+/// covers only a few. Queries look in both and merge the results, so callers can't tell.
+pub struct LayeredSpatialHash {
+    fine: SpatialHashMap,
+    coarse: SpatialHashMap,
+    /// Objects wider or taller than this go in the coarse grid.
+    large_extent: Scalar,
+}
+
+impl LayeredSpatialHash {
+    pub fn new(
+        map_width: Scalar,
+        map_height: Scalar,
+        fine_cell_size: Scalar,
+        coarse_cell_size: Scalar,
+    ) -> Self {
+        LayeredSpatialHash {
+            fine: SpatialHashMap::with_cell_size(map_width, map_height, fine_cell_size),
+            coarse: SpatialHashMap::with_cell_size(map_width, map_height, coarse_cell_size),
+            large_extent: fine_cell_size * dec64!(2),
+        }
+    }
+
+    /// Returns whether an object this size belongs in the coarse grid.
+    pub fn is_large(&self, aabb: &AABB) -> bool {
+        aabb.max.x - aabb.min.x > self.large_extent || aabb.max.y - aabb.min.y > self.large_extent
+    }
+
+    fn layer(&mut self, aabb: &AABB) -> &mut SpatialHashMap {
+        if self.is_large(aabb) {
+            &mut self.coarse
+        } else {
+            &mut self.fine
+        }
+    }
+
+    /// Returns the grid holding small objects.
+    pub fn fine(&self) -> &SpatialHashMap {
+        &self.fine
+    }
+
+    /// Returns the grid holding large objects.
+    pub fn coarse(&self) -> &SpatialHashMap {
+        &self.coarse
+    }
+}
+
+impl BroadPhase for LayeredSpatialHash {
+    fn insert(&mut self, object_id: u32, aabb: &AABB) {
+        self.layer(aabb).insert(object_id, aabb);
+    }
+
+    fn remove(&mut self, object_id: u32, aabb: &AABB) {
+        self.layer(aabb).remove(object_id, aabb);
+    }
+
+    fn update(&mut self, object_id: u32, old: &AABB, new: &AABB) -> bool {
+        if self.is_large(old) == self.is_large(new) {
+            return self.layer(new).update(object_id, old, new);
+        }
+        // grew or shrank past the threshold, so it changes grids
+        self.layer(old).remove(object_id, old);
+        self.layer(new).insert(object_id, new);
+        true
+    }
+
+    fn query(&self, aabb: &AABB) -> HashSet<u32> {
+        let mut result = self.fine.query(aabb);
+        result.extend(self.coarse.query(aabb));
+        result
+    }
+
+    fn query_circle(&self, center: Vec2, radius: Scalar) -> HashSet<u32> {
+        let mut result = self.fine.query_circle(center, radius);
+        result.extend(self.coarse.query_circle(center, radius));
+        result
+    }
+
+    fn query_point(&self, point: Vec2) -> impl Iterator<Item = u32> + '_ {
+        // each object is in one layer only, so nothing comes up twice
+        let fine = self.fine.query_point(point).iter();
+        fine.chain(self.coarse.query_point(point)).copied()
+    }
+
+    fn clear(&mut self) {
+        self.fine.clear();
+        self.coarse.clear();
+    }
+}
+
+/// A spatial hash that only stores the cells something is in, for huge or unbounded maps.
+///
+/// Cells are addressed by signed coordinates, so objects may sit anywhere, including at negative
+/// positions. Memory follows the number of occupied cells rather than the map's area, and cells
+/// are kept in coordinate order so iterating them is deterministic.
+pub struct SparseSpatialHash {
+    cell_size: Scalar,
+    cells: BTreeMap<(i32, i32), BTreeSet<u32>>,
+}
+
+impl SparseSpatialHash {
+    pub fn new(cell_size: Scalar) -> Self {
+        SparseSpatialHash {
+            cell_size,
+            cells: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the size of a single cell.
+    pub fn cell_size(&self) -> Scalar {
+        self.cell_size
+    }
+
+    fn cell_coord(&self, value: Scalar) -> i32 {
+        (value / self.cell_size)
+            .floor()
+            .to_i32()
+            .unwrap_or(if value < dec64!(0) {
+                i32::MIN
+            } else {
+                i32::MAX
+            })
+    }
+
+    /// Returns the first and last cell the given AABB covers.
+    fn cell_range(&self, aabb: &AABB) -> ((i32, i32), (i32, i32)) {
+        (
+            (self.cell_coord(aabb.min.x), self.cell_coord(aabb.min.y)),
+            (self.cell_coord(aabb.max.x), self.cell_coord(aabb.max.y)),
+        )
+    }
+
+    /// Returns the area a cell covers.
+    fn cell_bounds(&self, (x, y): (i32, i32)) -> AABB {
+        let min = Vec2::new(Scalar::from(x), Scalar::from(y)).scale(self.cell_size);
+        AABB::new(min, min + Vec2::new(self.cell_size, self.cell_size))
+    }
+
+    /// Returns the coordinates of all the cells that contain the given AABB.
+    pub fn keys_iter(&self, aabb: &AABB) -> impl Iterator<Item = (i32, i32)> + use<> {
+        let ((min_x, min_y), (max_x, max_y)) = self.cell_range(aabb);
+        (min_y..=max_y).flat_map(move |y| (min_x..=max_x).map(move |x| (x, y)))
+    }
+
+    /// Returns the occupied cells that contain the given AABB, and what they hold.
+    ///
+    /// A box covering more cells than are occupied walks the occupied cells instead, so a
+    /// query over a huge area costs no more than one over the whole map.
+    fn occupied(&self, aabb: &AABB) -> Vec<((i32, i32), &BTreeSet<u32>)> {
+        let ((min_x, min_y), (max_x, max_y)) = self.cell_range(aabb);
+        if max_x < min_x || max_y < min_y {
+            return Vec::new();
+        }
+        let columns = (max_x as i64 - min_x as i64 + 1) as u64;
+        let rows = (max_y as i64 - min_y as i64 + 1) as u64;
+        if columns.saturating_mul(rows) <= self.cells.len() as u64 {
+            return self
+                .keys_iter(aabb)
+                .filter_map(|key| Some((key, self.cells.get(&key)?)))
+                .collect();
+        }
+        // keys order by column first, so this range takes in every column of the box, along
+        // with rows of the columns between that lie outside it
+        self.cells
+            .range((min_x, min_y)..=(max_x, max_y))
+            .filter(|((_, y), _)| (min_y..=max_y).contains(y))
+            .map(|(key, cell)| (*key, cell))
+            .collect()
+    }
+
+    /// Inserts an object with the given AABB.
+    pub fn insert(&mut self, object_id: u32, aabb: &AABB) {
+        for key in self.keys_iter(aabb) {
+            self.cells.entry(key).or_default().insert(object_id);
+        }
+    }
+
+    /// Removes an object that was inserted with the given AABB, dropping cells left empty.
+    pub fn remove(&mut self, object_id: u32, aabb: &AABB) {
+        let keys: Vec<(i32, i32)> = self
+            .occupied(aabb)
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        for key in keys {
+            self.remove_from(key, object_id);
+        }
+    }
+
+    fn remove_from(&mut self, key: (i32, i32), object_id: u32) {
+        if let Some(cell) = self.cells.get_mut(&key) {
+            cell.remove(&object_id);
+            if cell.is_empty() {
+                self.cells.remove(&key);
+            }
+        }
+    }
+
+    /// Moves an object from one AABB to another, touching only the cells that differ.
+    ///
+    /// Returns whether the set of cells it covers changed.
+    pub fn update(&mut self, object_id: u32, old: &AABB, new: &AABB) -> bool {
+        let old_keys: Vec<(i32, i32)> = self.keys_iter(old).collect();
+        let new_keys: Vec<(i32, i32)> = self.keys_iter(new).collect();
+        if old_keys == new_keys {
+            return false;
+        }
+
+        for key in old_keys.iter().filter(|key| !new_keys.contains(key)) {
+            self.remove_from(*key, object_id);
+        }
+        for key in new_keys.iter().filter(|key| !old_keys.contains(key)) {
+            self.cells.entry(*key).or_default().insert(object_id);
+        }
+        true
+    }
+
+    /// Returns all unique object IDs that overlap with the given AABB, in ascending order.
+    pub fn query(&self, aabb: &AABB) -> BTreeSet<u32> {
+        let mut result = BTreeSet::new();
+        for (_, cell) in self.occupied(aabb) {
+            result.extend(cell);
+        }
+        result
+    }
+
+    /// Returns the IDs of objects in the cells a circle touches, in ascending order. Like
+    /// [`SparseSpatialHash::query`], these are candidates: callers check the objects' actual
+    /// shapes.
+    pub fn query_circle(&self, center: Vec2, radius: Scalar) -> BTreeSet<u32> {
+        let reach = Vec2::new(radius, radius);
+        let bounds = AABB::new(center.sub(&reach), center.add(&reach));
+        let mut result = BTreeSet::new();
+        for (key, cell) in self.occupied(&bounds) {
+            // the box around the circle takes in corner cells the circle misses
+            if self.cell_bounds(key).distance_squared_to(center) <= radius * radius {
+                result.extend(cell);
+            }
+        }
+        result
+    }
+
+    /// Returns the IDs of objects in the cells the segment `start -> end` passes through, in
+    /// ascending order.
+    pub fn query_segment(&self, start: Vec2, end: Vec2) -> BTreeSet<u32> {
+        let bounds = AABB::new(
+            Vec2::new(start.x.min(end.x), start.y.min(end.y)),
+            Vec2::new(start.x.max(end.x), start.y.max(end.y)),
+        );
+        let mut result = BTreeSet::new();
+        for (key, cell) in self.occupied(&bounds) {
+            if self.cell_bounds(key).segment_entry(start, end).is_some() {
+                result.extend(cell);
+            }
+        }
+        result
+    }
+
+    /// Returns up to `k` objects nearest a point, nearest first, with ties going to the lower
+    /// ID. `distance_squared` works as for [`SpatialHashMap::k_nearest`].
+    ///
+    /// Occupied cells are searched nearest first, stopping once none left could hold anything
+    /// nearer than what's been found.
+    pub fn k_nearest(
+        &self,
+        center: Vec2,
+        k: usize,
+        mut distance_squared: impl FnMut(u32) -> Option<Scalar>,
+    ) -> Vec<u32> {
+        if k == 0 {
+            return Vec::new();
+        }
+        let mut cells: Vec<(Scalar, (i32, i32))> = self
+            .cells
+            .keys()
+            .map(|key| (self.cell_bounds(*key).distance_squared_to(center), *key))
+            .collect();
+        cells.sort_unstable();
+        let mut seen = BTreeSet::new();
+        let mut found: Vec<(Scalar, u32)> = Vec::new();
+        for (horizon, key) in cells {
+            if found.len() == k && found[k - 1].0 <= horizon {
+                break;
+            }
+            for object_id in &self.cells[&key] {
+                if seen.insert(*object_id)
+                    && let Some(distance) = distance_squared(*object_id)
+                {
+                    found.push((distance, *object_id));
+                }
+            }
+            found.sort_unstable();
+            found.truncate(k);
+        }
+        found.into_iter().map(|(_, object_id)| object_id).collect()
+    }
+
+    /// Returns the occupied cells and what they hold, in coordinate order.
+    pub fn cells(&self) -> impl Iterator<Item = ((i32, i32), &BTreeSet<u32>)> {
+        self.cells.iter().map(|(key, cell)| (*key, cell))
+    }
+
+    /// Summarises how the stored objects are spread over the cells. Only occupied cells
+    /// exist, so `cells` and `occupied_cells` are the same.
+    pub fn occupancy(&self) -> OccupancyStats {
+        let mut objects = BTreeSet::new();
+        let mut entries = 0u64;
+        let mut max_per_cell = 0;
+        let mut histogram = vec![0; 1];
+        for cell in self.cells.values() {
+            tally(&mut histogram, cell.len());
+            objects.extend(cell.iter().copied());
+            entries += cell.len() as u64;
+            max_per_cell = max_per_cell.max(cell.len() as u32);
+        }
+        let ratio = |numerator: u64, denominator: u64| {
+            if denominator == 0 {
+                dec64!(0)
+            } else {
+                Scalar::from(numerator) / Scalar::from(denominator)
+            }
+        };
+        OccupancyStats {
+            cells: self.cells.len() as u32,
+            occupied_cells: self.cells.len() as u32,
+            objects: objects.len() as u32,
+            max_per_cell,
+            mean_per_occupied_cell: ratio(entries, self.cells.len() as u64),
+            cells_per_object: ratio(entries, objects.len() as u64),
+            histogram,
+        }
+    }
+
+    /// Clears all objects, releasing every cell.
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+}
+
+/// Counts one more cell holding `count` objects.
+fn tally(histogram: &mut Vec<u32>, count: usize) {
+    if histogram.len() <= count {
+        histogram.resize(count + 1, 0);
+    }
+    histogram[count] += 1;
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::util::math::{ConvertToScalar, Vec2};
+    use proptest::prelude::*;
     use std::collections::HashSet;
 
     // Helper to create an AABB
@@ -396,4 +1235,397 @@ mod tests {
         let expected_keys: HashSet<u32> = [99].iter().cloned().collect();
         assert_eq!(keys, expected_keys);
     }
+
+    fn arb_aabb() -> impl Strategy<Value = AABB> {
+        (0u32..100, 0u32..100, 0u32..40, 0u32..40).prop_map(|(x, y, w, h)| {
+            AABB::new(
+                Vec2::new(x.to_scalar(), y.to_scalar()),
+                Vec2::new((x + w).min(100).to_scalar(), (y + h).min(100).to_scalar()),
+            )
+        })
+    }
+
+    /// Overlap including touching edges, which is what the grid has to be conservative about.
+    fn touches(a: &AABB, b: &AABB) -> bool {
+        a.min.x <= b.max.x && b.min.x <= a.max.x && a.min.y <= b.max.y && b.min.y <= a.max.y
+    }
+
+    proptest! {
+        #[test]
+        fn query_should_agree_with_brute_force(
+            boxes in prop::collection::vec(arb_aabb(), 0..50),
+            probe in arb_aabb(),
+            grid_width in 1u32..16,
+            grid_height in 1u32..16,
+        ) {
+            // Arrange
+            let mut shm = SpatialHashMap::new(
+                100.0.to_scalar(),
+                100.0.to_scalar(),
+                grid_width,
+                grid_height,
+            );
+            for (id, aabb) in boxes.iter().enumerate() {
+                shm.insert(id as u32, aabb);
+            }
+
+            // Act
+            let candidates = shm.query(&probe);
+
+            // Assert
+            // the grid may return extra candidates, but after the exact test it must match
+            let expected: HashSet<u32> = (0..boxes.len() as u32)
+                .filter(|id| touches(&boxes[*id as usize], &probe))
+                .collect();
+            let actual: HashSet<u32> = candidates
+                .into_iter()
+                .filter(|id| touches(&boxes[*id as usize], &probe))
+                .collect();
+            prop_assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn query_point_should_match_point_sized_box_query() {
+        // Arrange
+        let mut shm = SpatialHashMap::new(20.0.to_scalar(), 20.0.to_scalar(), 2, 2); // 10x10 cells
+        shm.insert(1, &create_aabb(5.0, 5.0, 15.0, 15.0));
+        shm.insert(2, &create_aabb(1.0, 1.0, 2.0, 2.0));
+
+        // Act & Assert
+        for (x, y) in [
+            (5.0, 5.0),
+            (10.0, 10.0),
+            (15.0, 5.0),
+            (25.0, -3.0),
+            (19.0, 19.0),
+        ] {
+            let point = Vec2::new_from_f64(x, y);
+            assert_eq!(
+                shm.query_point(point),
+                &shm.query(&create_aabb(x, y, x, y)),
+                "at ({x}, {y})"
+            );
+        }
+    }
+
+    #[test]
+    fn query_circle_should_skip_corner_cells_circle_misses() {
+        // Arrange
+        let mut shm = SpatialHashMap::new(30.0.to_scalar(), 30.0.to_scalar(), 3, 3); // 10x10 cells
+        for (id, x, y) in [
+            (1, 15.0, 15.0),
+            (2, 5.0, 15.0),
+            (3, 5.0, 5.0),
+            (4, 25.0, 25.0),
+        ] {
+            shm.insert(id, &create_aabb(x, y, x, y));
+        }
+
+        // Act
+        let near = shm.query_circle(Vec2::new_from_f64(15.0, 15.0), 6.0.to_scalar());
+
+        // Assert
+        // the circle reaches the side cells but not the diagonal ones
+        let expected: HashSet<u32> = [1, 2].into_iter().collect();
+        assert_eq!(near, expected);
+    }
+
+    proptest! {
+        #[test]
+        fn k_nearest_should_agree_with_brute_force(
+            points in prop::collection::vec((0u32..100, 0u32..100), 0..40),
+            center in (0u32..100, 0u32..100),
+            k in 0usize..6,
+            grid_size in 1u32..12,
+        ) {
+            // Arrange
+            let mut shm =
+                SpatialHashMap::new(100.0.to_scalar(), 100.0.to_scalar(), grid_size, grid_size);
+            let points: Vec<Vec2> = points
+                .into_iter()
+                .map(|(x, y)| Vec2::new(x.to_scalar(), y.to_scalar()))
+                .collect();
+            for (id, point) in points.iter().enumerate() {
+                shm.insert(id as u32, &AABB::new(*point, *point));
+            }
+            let center = Vec2::new(center.0.to_scalar(), center.1.to_scalar());
+            let distance = |id: u32| points[id as usize].sub(&center).length_squared();
+
+            // Act
+            // odd IDs are filtered out
+            let nearest = shm.k_nearest(center, k, |id| (id % 2 == 0).then(|| distance(id)));
+
+            // Assert
+            let mut expected: Vec<(Scalar, u32)> = (0..points.len() as u32)
+                .filter(|id| id % 2 == 0)
+                .map(|id| (distance(id), id))
+                .collect();
+            expected.sort_unstable();
+            let expected: Vec<u32> = expected.into_iter().take(k).map(|(_, id)| id).collect();
+            prop_assert_eq!(nearest, expected);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn query_segment_should_agree_with_brute_force(
+            boxes in prop::collection::vec(arb_aabb(), 0..50),
+            start in (-20i32..120, -20i32..120),
+            end in (-20i32..120, -20i32..120),
+            grid_width in 1u32..16,
+            grid_height in 1u32..16,
+        ) {
+            // Arrange
+            let mut shm = SpatialHashMap::new(
+                100.0.to_scalar(),
+                100.0.to_scalar(),
+                grid_width,
+                grid_height,
+            );
+            for (id, aabb) in boxes.iter().enumerate() {
+                shm.insert(id as u32, aabb);
+            }
+            let start = Vec2::new_from_f64(start.0 as f64, start.1 as f64);
+            let end = Vec2::new_from_f64(end.0 as f64, end.1 as f64);
+            let crossed = |id: &u32| boxes[*id as usize].segment_entry(start, end).is_some();
+
+            // Act
+            let candidates = shm.query_segment(start, end);
+
+            // Assert
+            let unique: HashSet<u32> = candidates.iter().copied().collect();
+            prop_assert_eq!(unique.len(), candidates.len());
+            let expected: HashSet<u32> = (0..boxes.len() as u32).filter(crossed).collect();
+            let actual: HashSet<u32> = unique.into_iter().filter(crossed).collect();
+            prop_assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn query_segment_should_return_candidates_in_order_cells_are_reached() {
+        // Arrange
+        let mut shm = SpatialHashMap::new(40.0.to_scalar(), 10.0.to_scalar(), 4, 1); // 10x10 cells
+        for (id, x) in [(1, 5.0), (2, 15.0), (3, 25.0), (4, 35.0)] {
+            shm.insert(id, &create_aabb(x, 5.0, x, 5.0));
+        }
+
+        // Act
+        let leftwards =
+            shm.query_segment(Vec2::new_from_f64(38.0, 5.0), Vec2::new_from_f64(12.0, 5.0));
+
+        // Assert
+        assert_eq!(leftwards, vec![4, 3, 2]);
+    }
+
+    #[test]
+    fn query_arc_should_skip_cells_behind_sector() {
+        // Arrange
+        let mut shm = SpatialHashMap::new(30.0.to_scalar(), 30.0.to_scalar(), 3, 3); // 10x10 cells
+        for (id, x, y) in [
+            (1, 15.0, 15.0),
+            (2, 25.0, 15.0),
+            (3, 5.0, 15.0),
+            (4, 15.0, 25.0),
+        ] {
+            shm.insert(id, &create_aabb(x, y, x, y));
+        }
+
+        // Act
+        // a narrow sector pointing along +x from the middle of the centre cell
+        let ahead = shm.query_arc(
+            Vec2::new_from_f64(15.0, 15.0),
+            12.0.to_scalar(),
+            Angle::ZERO,
+            0.5.to_scalar(),
+        );
+
+        // Assert
+        let expected: HashSet<u32> = [1, 2].into_iter().collect();
+        assert_eq!(ahead, expected);
+    }
+
+    #[test]
+    fn spatial_hashmap_update_should_only_move_between_differing_cells() {
+        let mut shm = SpatialHashMap::new(20.0.to_scalar(), 20.0.to_scalar(), 2, 2); // 10x10 cells
+        let obj_id = 400;
+        let start = create_aabb(1.0, 1.0, 4.0, 4.0);
+        shm.insert(obj_id, &start);
+
+        // nudging within cell (0,0) doesn't change coverage
+        let nudged = create_aabb(2.0, 2.0, 5.0, 5.0);
+        assert!(!shm.update(obj_id, &start, &nudged));
+
+        // straddling into cell (1,0) does
+        let straddling = create_aabb(8.0, 2.0, 12.0, 5.0);
+        assert!(shm.update(obj_id, &nudged, &straddling));
+        assert!(
+            shm.query(&create_aabb(15.0, 3.0, 15.0, 3.0))
+                .contains(&obj_id)
+        );
+        assert!(
+            shm.query(&create_aabb(3.0, 3.0, 3.0, 3.0))
+                .contains(&obj_id)
+        );
+
+        // and leaving cell (0,0) entirely drops it from there
+        let moved = create_aabb(12.0, 2.0, 15.0, 5.0);
+        assert!(shm.update(obj_id, &straddling, &moved));
+        assert!(shm.query(&create_aabb(3.0, 3.0, 3.0, 3.0)).is_empty());
+
+        shm.remove(obj_id, &moved);
+        assert!(shm.query(&create_aabb(0.0, 0.0, 20.0, 20.0)).is_empty());
+    }
+
+    #[test]
+    fn auto_sized_should_pick_cells_about_twice_the_object_size() {
+        // Arrange
+        let boxes = [
+            create_aabb(0.0, 0.0, 8.0, 12.0),
+            create_aabb(50.0, 50.0, 62.0, 58.0),
+        ];
+
+        // Act
+        let extent = average_extent(&boxes);
+        let roomy = SpatialHashMap::auto_sized(200.0.to_scalar(), 200.0.to_scalar(), extent, 100);
+        let sparse = SpatialHashMap::auto_sized(200.0.to_scalar(), 200.0.to_scalar(), extent, 1);
+
+        // Assert
+        assert_eq!(extent, 10.0.to_scalar());
+        assert_eq!(roomy.grid_size(), (10, 10));
+        // a lone object doesn't need more than a handful of cells
+        assert_eq!(sparse.grid_size(), (2, 2));
+    }
+
+    #[test]
+    fn occupancy_should_report_spread_of_objects() {
+        // Arrange
+        let mut shm = SpatialHashMap::new(20.0.to_scalar(), 20.0.to_scalar(), 2, 2); // 10x10 cells
+        shm.insert(1, &create_aabb(5.0, 5.0, 15.0, 15.0));
+        shm.insert(2, &create_aabb(1.0, 1.0, 2.0, 2.0));
+
+        // Act
+        let stats = shm.occupancy();
+
+        // Assert
+        assert_eq!(stats.cells, 4);
+        assert_eq!(stats.occupied_cells, 4);
+        assert_eq!(stats.objects, 2);
+        assert_eq!(stats.max_per_cell, 2);
+        assert_eq!(stats.mean_per_occupied_cell, 1.25.to_scalar());
+        assert_eq!(stats.cells_per_object, 2.5.to_scalar());
+        assert_eq!(stats.histogram, vec![0, 3, 1]);
+        assert!(!stats.is_pathological());
+    }
+
+    #[test]
+    fn suggested_cell_size_when_cells_are_far_too_small_should_suggest_bigger_ones() {
+        // Arrange
+        // 18x18 objects on 5x5 cells each cover 16 cells
+        let mut tiny = SpatialHashMap::new(100.0.to_scalar(), 100.0.to_scalar(), 20, 20);
+        let mut fitted = SpatialHashMap::new(100.0.to_scalar(), 100.0.to_scalar(), 2, 2);
+        for (id, x) in [(1, 1.0), (2, 41.0), (3, 61.0)] {
+            tiny.insert(id, &create_aabb(x, 1.0, x + 18.0, 19.0));
+            fitted.insert(id, &create_aabb(x, 1.0, x + 18.0, 19.0));
+        }
+
+        // Act
+        let suggested = tiny.stats().suggested_cell_size();
+        let kept = fitted.stats().suggested_cell_size();
+
+        // Assert
+        // four cells each way means objects about 15 across, going by the count alone
+        assert_eq!(suggested, Some(30.0.to_scalar()));
+        assert_eq!(kept, None);
+    }
+
+    #[test]
+    fn layered_hash_should_keep_large_objects_out_of_fine_cells_but_still_find_them() {
+        // Arrange
+        let mut layered = LayeredSpatialHash::new(
+            400.0.to_scalar(),
+            400.0.to_scalar(),
+            10.0.to_scalar(),
+            100.0.to_scalar(),
+        );
+        let wall = create_aabb(0.0, 110.0, 400.0, 130.0);
+        let crate_box = create_aabb(195.0, 135.0, 205.0, 145.0);
+        layered.insert(1, &wall);
+        layered.insert(2, &crate_box);
+
+        // Act
+        let near_middle = layered.query(&create_aabb(190.0, 130.0, 210.0, 140.0));
+        let far_left = layered.query_circle(Vec2::new_from_f64(5.0, 120.0), 2.0.to_scalar());
+        let (fine_before, coarse_before) =
+            (layered.fine().occupancy(), layered.coarse().occupancy());
+        let grown = layered.update(2, &crate_box, &create_aabb(150.0, 150.0, 250.0, 250.0));
+
+        // Assert
+        assert_eq!(near_middle, [1, 2].into_iter().collect());
+        assert_eq!(far_left, [1].into_iter().collect());
+        // the wall covers a row of 4 coarse cells rather than over a hundred fine ones
+        assert_eq!(fine_before.objects, 1);
+        assert_eq!(coarse_before.cells_per_object, 4.0.to_scalar());
+        assert!(grown);
+        assert_eq!(layered.fine().occupancy().objects, 0);
+        assert_eq!(layered.coarse().occupancy().objects, 2);
+    }
+
+    #[test]
+    fn sparse_hash_should_store_far_apart_objects_without_filling_the_gap() {
+        // Arrange
+        let mut sparse = SparseSpatialHash::new(10.0.to_scalar());
+        let near = create_aabb(-15.0, -5.0, -12.0, -2.0);
+        let far = create_aabb(1_000_000.0, 1_000_000.0, 1_000_005.0, 1_000_005.0);
+
+        // Act
+        sparse.insert(1, &near);
+        sparse.insert(2, &far);
+        let moved = create_aabb(-15.0, -5.0, -5.0, -2.0);
+        let changed = sparse.update(1, &near, &moved);
+
+        // Assert
+        assert!(changed);
+        let keys: Vec<(i32, i32)> = sparse.cells().map(|(key, _)| key).collect();
+        assert_eq!(keys, [(-2, -1), (-1, -1), (100_000, 100_000)]);
+        assert_eq!(
+            sparse.query(&create_aabb(-20.0, -20.0, 0.0, 0.0)),
+            BTreeSet::from([1])
+        );
+        assert_eq!(
+            sparse.query(&create_aabb(999_999.0, 999_999.0, 1_000_001.0, 1_000_001.0)),
+            BTreeSet::from([2])
+        );
+
+        sparse.remove(2, &far);
+        assert_eq!(sparse.occupancy().cells, 2);
+    }
+
+    #[test]
+    fn sparse_query_when_box_spans_more_cells_than_stored_should_walk_only_occupied_ones() {
+        // Arrange
+        let mut sparse = SparseSpatialHash::new(1.0.to_scalar());
+        let near = create_aabb(-3.0, 2.0, -2.0, 3.0);
+        let far = create_aabb(50_000.0, -70_000.0, 50_001.0, -69_999.0);
+        sparse.insert(1, &near);
+        sparse.insert(2, &far);
+        let everywhere = create_aabb(-1e12, -1e12, 1e12, 1e12);
+        // spans every column holding something, but only rows between them
+        let band = create_aabb(-1e12, -10.0, 1e12, 10.0);
+
+        // Act
+        let all = sparse.query(&everywhere);
+        let banded = sparse.query(&band);
+        let circled = sparse.query_circle(Vec2::zero(), 1e6.to_scalar());
+        let crossed =
+            sparse.query_segment(Vec2::new_from_f64(-1e9, -1e9), Vec2::new_from_f64(1e9, 1e9));
+        sparse.remove(2, &everywhere);
+
+        // Assert
+        assert_eq!(all, BTreeSet::from([1, 2]));
+        assert_eq!(banded, BTreeSet::from([1]));
+        assert_eq!(circled, BTreeSet::from([1, 2]));
+        assert!(crossed.is_empty());
+        assert_eq!(sparse.occupancy().objects, 1);
+    }
 }
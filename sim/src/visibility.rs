@@ -0,0 +1,271 @@
+use crate::arena::Arena;
+use crate::obscurants::Occluders;
+use crate::spec::{RadarSpec, SpecTable};
+use crate::state::Tank;
+use crate::util::math::{ConvertToScalar, Scalar, Vec2};
+use fastnum::dec64;
+use std::collections::BTreeMap;
+
+/// Returns whether an observer's radar picks up the given point.
+///
+/// The point has to be within range, inside the arc around the turret, and not hidden behind
+/// static geometry.
+pub fn can_see(observer: &Tank, radar: &RadarSpec, point: Vec2, arena: &Arena) -> bool {
+    let offset = point.sub(&observer.position);
+    let distance_squared = offset.length_squared();
+    if distance_squared > radar.range * radar.range {
+        return false;
+    }
+
+    if radar.arc < Scalar::PI * dec64!(2) {
+        // inside the arc iff the angle to the point is at most half the arc,
+        // i.e. facing . offset >= |offset| * cos(arc / 2)
+        let facing = observer.turret_transform().forward();
+        let half_arc = radar.arc / dec64!(2);
+        if facing.dot(&offset) < distance_squared.sqrt() * half_arc.cos() {
+            return false;
+        }
+    }
+
+    arena.line_of_sight(observer.position, point)
+}
+
+/// Which enemy tanks each tank and each team can currently see.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Visibility {
+    by_tank: BTreeMap<u32, Vec<u32>>,
+    by_team: BTreeMap<u32, Vec<u32>>,
+}
+
+impl Visibility {
+    /// Computes visibility for every live tank. Contact lists are sorted by ID. Smoke hides
+    /// anything the line of sight passes through.
+    pub fn compute(
+        tanks: &[Tank],
+        specs: &SpecTable,
+        arena: &Arena,
+        occluders: &Occluders,
+    ) -> Self {
+        let mut visibility = Visibility::default();
+
+        for observer in tanks.iter().filter(|tank| tank.is_alive()) {
+            let Some(spec) = specs.tank(observer.loadout.spec_id) else {
+                continue;
+            };
+            let seen: Vec<u32> = tanks
+                .iter()
+                .filter(|target| target.is_alive() && target.team_id != observer.team_id)
+                .filter(|target| can_see(observer, &spec.radar, target.position, arena))
+                .filter(|target| !occluders.blocks(observer.position, target.position))
+                .map(|target| target.id)
+                .collect();
+
+            let team = visibility.by_team.entry(observer.team_id).or_default();
+            team.extend_from_slice(&seen);
+            visibility.by_tank.insert(observer.id, seen);
+        }
+
+        for seen in visibility.by_team.values_mut() {
+            seen.sort_unstable();
+            seen.dedup();
+        }
+
+        visibility
+    }
+
+    /// Returns the enemies a single tank can see.
+    pub fn seen_by_tank(&self, tank_id: u32) -> &[u32] {
+        self.by_tank.get(&tank_id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the enemies any member of a team can see.
+    pub fn seen_by_team(&self, team_id: u32) -> &[u32] {
+        self.by_team.get(&team_id).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// A per-team grid over the arena marking which cells are under radar coverage.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FogMask {
+    pub width: u32,
+    pub height: u32,
+    pub cell_size: Scalar,
+    /// Row-major, 1 for visible cells and 0 for fogged ones.
+    pub cells: Vec<u8>,
+}
+
+impl FogMask {
+    /// Computes fog of war for one team, sampling each cell at its centre.
+    pub fn compute(
+        team_id: u32,
+        tanks: &[Tank],
+        specs: &SpecTable,
+        arena: &Arena,
+        occluders: &Occluders,
+        cell_size: Scalar,
+    ) -> Self {
+        let width = (arena.width() / cell_size).ceil().to_u32().unwrap_or(0);
+        let height = (arena.height() / cell_size).ceil().to_u32().unwrap_or(0);
+        let observers: Vec<(&Tank, &RadarSpec)> = tanks
+            .iter()
+            .filter(|tank| tank.team_id == team_id && tank.is_alive())
+            .filter_map(|tank| Some((tank, &specs.tank(tank.loadout.spec_id)?.radar)))
+            .collect();
+
+        let mut cells = vec![0; width as usize * height as usize];
+        for y in 0..height {
+            for x in 0..width {
+                let center = Vec2::new(
+                    (x.to_scalar() + dec64!(0.5)) * cell_size,
+                    (y.to_scalar() + dec64!(0.5)) * cell_size,
+                );
+                if observers.iter().any(|(observer, radar)| {
+                    can_see(observer, radar, center, arena)
+                        && !occluders.blocks(observer.position, center)
+                }) {
+                    cells[x as usize + y as usize * width as usize] = 1;
+                }
+            }
+        }
+
+        FogMask {
+            width,
+            height,
+            cell_size,
+            cells,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obscurants::Obscurant;
+    use crate::physics::collision::AABB;
+    use crate::state::Obstacle;
+    use crate::test_support;
+    use crate::util::math::Angle;
+
+    fn tank(id: u32, team_id: u32, x: f64, y: f64, angle: f64) -> Tank {
+        let mut tank = test_support::tank(id, team_id, Vec2::new_from_f64(x, y));
+        tank.angle = Angle::new(angle.to_scalar());
+        tank
+    }
+
+    fn arena_with_wall() -> Arena {
+        let wall = Obstacle {
+            id: 100,
+            aabb: AABB::new(
+                Vec2::new_from_f64(190.0, 0.0),
+                Vec2::new_from_f64(210.0, 100.0),
+            ),
+        };
+        Arena::new(400.0.to_scalar(), 400.0.to_scalar(), &[wall])
+    }
+
+    #[test]
+    fn visibility_when_enemy_in_arc_and_clear_should_be_seen() {
+        // Arrange
+        let tanks = [tank(0, 0, 100.0, 200.0, 0.0), tank(1, 1, 300.0, 200.0, 0.0)];
+
+        // Act
+        let visibility = Visibility::compute(
+            &tanks,
+            &SpecTable::default(),
+            &arena_with_wall(),
+            &Occluders::none(),
+        );
+
+        // Assert
+        assert_eq!(visibility.seen_by_tank(0), &[1]);
+        assert_eq!(visibility.seen_by_team(0), &[1]);
+        // tank 1 is facing away, so it can't see tank 0 behind it
+        assert!(visibility.seen_by_team(1).is_empty());
+    }
+
+    #[test]
+    fn visibility_when_wall_between_should_not_be_seen() {
+        // Arrange
+        let tanks = [tank(0, 0, 100.0, 50.0, 0.0), tank(1, 1, 300.0, 50.0, 0.0)];
+
+        // Act
+        let visibility = Visibility::compute(
+            &tanks,
+            &SpecTable::default(),
+            &arena_with_wall(),
+            &Occluders::none(),
+        );
+
+        // Assert
+        assert!(visibility.seen_by_team(0).is_empty());
+    }
+
+    #[test]
+    fn visibility_should_ignore_allies_and_out_of_range_enemies() {
+        // Arrange
+        let tanks = [
+            tank(0, 0, 100.0, 300.0, 0.0),
+            tank(1, 0, 150.0, 300.0, 0.0), // ally right in front
+            tank(2, 1, 350.0, 300.0, 0.0), // enemy 250 away
+        ];
+        let mut specs = SpecTable::default();
+        specs.tanks[1].radar.range = 200.0.to_scalar();
+
+        // Act
+        let visibility =
+            Visibility::compute(&tanks, &specs, &arena_with_wall(), &Occluders::none());
+
+        // Assert
+        assert!(visibility.seen_by_tank(0).is_empty());
+        // the ally is 200 away from the enemy, right at the edge of its radar range
+        assert_eq!(visibility.seen_by_team(0), &[2]);
+    }
+
+    #[test]
+    fn visibility_when_smoke_between_should_not_be_seen() {
+        // Arrange
+        let tanks = [tank(0, 0, 100.0, 200.0, 0.0), tank(1, 1, 300.0, 200.0, 0.0)];
+        let smoke = Obscurant {
+            id: 50,
+            center: Vec2::new_from_f64(200.0, 210.0),
+            radius: 20.0.to_scalar(),
+            remaining: 10,
+        };
+        let occluders = Occluders::new(400.0.to_scalar(), 400.0.to_scalar(), &tanks, &[smoke]);
+
+        // Act
+        let visibility = Visibility::compute(
+            &tanks,
+            &SpecTable::default(),
+            &arena_with_wall(),
+            &occluders,
+        );
+
+        // Assert
+        assert!(visibility.seen_by_team(0).is_empty());
+    }
+
+    #[test]
+    fn fog_mask_should_only_reveal_cells_in_front_of_the_team() {
+        // Arrange
+        let tanks = [tank(0, 0, 50.0, 50.0, 0.0)];
+        let cell_size = 100.0.to_scalar();
+
+        // Act
+        let mask = FogMask::compute(
+            0,
+            &tanks,
+            &SpecTable::default(),
+            &arena_with_wall(),
+            &Occluders::none(),
+            cell_size,
+        );
+
+        // Assert
+        assert_eq!((mask.width, mask.height), (4, 4));
+        // the cell ahead is visible, the one behind the wall and the one off to the side aren't
+        assert_eq!(mask.cells[1], 1);
+        assert_eq!(mask.cells[3], 0);
+        assert_eq!(mask.cells[3 * 4], 0);
+    }
+}
@@ -0,0 +1,551 @@
+//! How a tank's program sees the world: the memory map of its sensors and actuators, and the
+//! syscalls it can make.
+//!
+//! Everything is exchanged in 32-bit words. Positions, angles and track commands are signed 16.16
+//! fixed point; IDs, counts and ticks are plain integers.
+
+use super::verify::Platform;
+use super::{Stack, VmFault, VmIo};
+use crate::effects;
+use crate::limits::BotLimits;
+use crate::nav::NavGrid;
+use crate::physics::drivetrain::DriveInput;
+use crate::physics::turret::TurretCommand;
+use crate::sensors::SensorData;
+use crate::state::Tank;
+use crate::util::math::{Angle, Scalar, Vec2};
+use fastnum::{D64, dec64};
+
+/// Version of the memory map and syscalls, bumped whenever a program built against the old
+/// ones could behave differently.
+pub const ABI_VERSION: u16 = 1;
+
+/// Read-only: the current tick.
+pub const TICK: u32 = 0x4000;
+pub const SELF_ID: u32 = 0x4001;
+pub const SELF_TEAM: u32 = 0x4002;
+pub const SELF_X: u32 = 0x4003;
+pub const SELF_Y: u32 = 0x4004;
+pub const SELF_ANGLE: u32 = 0x4005;
+/// Turret angle relative to the hull.
+pub const SELF_TURRET: u32 = 0x4006;
+pub const SELF_VX: u32 = 0x4007;
+pub const SELF_VY: u32 = 0x4008;
+pub const SELF_HEALTH: u32 = 0x4009;
+/// Ticks until each weapon slot can fire again, one word per slot.
+pub const SELF_RELOAD: u32 = 0x400a;
+pub const MAX_WEAPON_SLOTS: u32 = 4;
+/// Status effects on the tank, as [`StatusKind::bit`](crate::effects::StatusKind::bit) flags.
+pub const SELF_STATUS: u32 = 0x400e;
+
+/// Number of enemies on radar, capped at [`MAX_CONTACTS`].
+pub const CONTACT_COUNT: u32 = 0x4010;
+/// Contact records, each `id, team, x, y`.
+pub const CONTACTS: u32 = 0x4011;
+pub const CONTACT_STRIDE: u32 = 4;
+pub const MAX_CONTACTS: u32 = 8;
+
+/// Safe zone centre and radius, or all zero if the match has no zone.
+pub const ZONE_X: u32 = 0x4040;
+pub const ZONE_Y: u32 = 0x4041;
+pub const ZONE_RADIUS: u32 = 0x4042;
+
+/// Number of the tank's guided missiles on the datalink, capped at [`MAX_MISSILES`].
+pub const MISSILE_COUNT: u32 = 0x4050;
+/// Missile records, each `id, x, y, heading`.
+pub const MISSILES: u32 = 0x4051;
+pub const MISSILE_STRIDE: u32 = 4;
+pub const MAX_MISSILES: u32 = 4;
+
+/// Words in the team ROM, zero if the tank's team hasn't loaded one.
+pub const TEAM_ROM_SIZE: u32 = 0x4070;
+
+/// Read-only: the first word of the team ROM, constant data shared by every tank on a team.
+/// Reads past its end fault.
+pub const TEAM_ROM: u32 = 0x1_0000;
+/// Most words a team ROM can hold, whatever the match's limits.
+pub const MAX_TEAM_ROM_WORDS: u32 = 0x1_0000;
+
+/// Read-write: left track command, in `[-1, 1]`.
+pub const LEFT_TRACK: u32 = 0x5000;
+/// Read-write: right track command, in `[-1, 1]`.
+pub const RIGHT_TRACK: u32 = 0x5001;
+/// Read-write: one of [`TURRET_HOLD`], [`TURRET_ABSOLUTE`] or [`TURRET_RELATIVE`].
+pub const TURRET_MODE: u32 = 0x5002;
+/// Read-write: the angle the turret mode aims at.
+pub const TURRET_ANGLE: u32 = 0x5003;
+/// Read-write: weapon slot to fire plus one, or zero to hold fire.
+pub const FIRE: u32 = 0x5004;
+
+pub const TURRET_HOLD: u32 = 0;
+pub const TURRET_ABSOLUTE: u32 = 1;
+pub const TURRET_RELATIVE: u32 = 2;
+
+/// Plans a path to `x y` and returns `x y status` for the next waypoint along it.
+///
+/// Queries past the match's per-tick limit return [`PATH_RATE_LIMITED`].
+pub const SYS_NEXT_WAYPOINT: u8 = 0;
+
+pub const PATH_OK: u32 = 0;
+pub const PATH_NONE: u32 = 1;
+pub const PATH_RATE_LIMITED: u32 = 2;
+
+/// Orders the guided missile `id` to turn towards `heading` and returns `status`. The missile
+/// turns at its weapon's rate until it faces the heading, and each order costs the weapon's
+/// steering cost in extra cycles.
+///
+/// Missiles not on the tank's datalink return [`STEER_UNKNOWN`], for free.
+pub const SYS_STEER_MISSILE: u8 = 1;
+
+pub const STEER_OK: u32 = 0;
+pub const STEER_UNKNOWN: u32 = 1;
+
+const FIXED_ONE: Scalar = dec64!(65536);
+
+/// Converts a scalar to signed 16.16 fixed point, saturating at the ends of the range.
+pub fn to_fixed(value: Scalar) -> u32 {
+    let fixed = (value * FIXED_ONE).floor();
+    let clamped = fixed.clamp(D64::from_i32(i32::MIN), D64::from_i32(i32::MAX));
+    clamped.to_i32().unwrap_or(0) as u32
+}
+
+/// Converts signed 16.16 fixed point to a scalar.
+pub fn from_fixed(word: u32) -> Scalar {
+    D64::from_i32(word as i32) / FIXED_ONE
+}
+
+/// Returns whether a word outside of RAM is mapped for reading. Must agree with
+/// [`TankIo::read`], except that the whole team ROM window counts, since how much of it is
+/// loaded is only known once the program runs.
+pub fn readable(address: u32) -> bool {
+    matches!(
+        address,
+        TICK..=SELF_HEALTH
+            | SELF_STATUS
+            | CONTACT_COUNT
+            | ZONE_X..=ZONE_RADIUS
+            | MISSILE_COUNT
+            | TEAM_ROM_SIZE
+            | LEFT_TRACK..=FIRE
+    ) || (SELF_RELOAD..SELF_RELOAD + MAX_WEAPON_SLOTS).contains(&address)
+        || (CONTACTS..CONTACTS + MAX_CONTACTS * CONTACT_STRIDE).contains(&address)
+        || (MISSILES..MISSILES + MAX_MISSILES * MISSILE_STRIDE).contains(&address)
+        || (TEAM_ROM..TEAM_ROM + MAX_TEAM_ROM_WORDS).contains(&address)
+}
+
+/// Returns the words a syscall pops and then pushes, or `None` if there's no such syscall.
+pub fn syscall_effect(number: u8) -> Option<(u32, u32)> {
+    match number {
+        SYS_NEXT_WAYPOINT => Some((2, 3)),
+        SYS_STEER_MISSILE => Some((2, 1)),
+        _ => None,
+    }
+}
+
+/// Describes what a tank's program runs on under the given limits, for the verifier.
+pub fn platform(limits: &BotLimits) -> Platform {
+    Platform {
+        memory_words: limits.memory_words,
+        stack_words: limits.stack_words,
+        readable,
+        syscall_effect,
+    }
+}
+
+/// The actuator registers, as the program last wrote them.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Actuators {
+    pub left: u32,
+    pub right: u32,
+    pub turret_mode: u32,
+    pub turret_angle: u32,
+    pub fire: u32,
+}
+
+impl Actuators {
+    /// Encodes a tank's current commands, so programs can read back what they last wrote.
+    pub fn read(tank: &Tank) -> Self {
+        let (turret_mode, turret_angle) = match tank.turret {
+            TurretCommand::Hold => (TURRET_HOLD, 0),
+            TurretCommand::Absolute(angle) => (TURRET_ABSOLUTE, to_fixed(angle.radians())),
+            TurretCommand::Relative(angle) => (TURRET_RELATIVE, to_fixed(angle.radians())),
+        };
+        Actuators {
+            left: to_fixed(tank.drive.left),
+            right: to_fixed(tank.drive.right),
+            turret_mode,
+            turret_angle,
+            fire: tank.fire.map_or(0, |slot| slot + 1),
+        }
+    }
+
+    /// Decodes the registers into commands for the tank. Unknown turret modes hold.
+    pub fn apply(&self, tank: &mut Tank) {
+        tank.drive = DriveInput::tracks(from_fixed(self.left), from_fixed(self.right));
+        let angle = Angle::new(from_fixed(self.turret_angle));
+        tank.turret = match self.turret_mode {
+            TURRET_ABSOLUTE => TurretCommand::Absolute(angle),
+            TURRET_RELATIVE => TurretCommand::Relative(angle),
+            _ => TurretCommand::Hold,
+        };
+        tank.fire = self.fire.checked_sub(1);
+    }
+}
+
+/// Binds a tank's program to the world for one tick.
+pub struct TankIo<'a> {
+    tick: u64,
+    tank: &'a Tank,
+    sensors: Option<&'a SensorData>,
+    nav: &'a NavGrid,
+    /// The tank's team ROM, empty if there's none.
+    rom: &'a [u32],
+    path_queries: u32,
+    path_query_limit: u32,
+    /// Path queries refused this tick for going over the limit.
+    pub refused_path_queries: u32,
+    pub actuators: Actuators,
+    /// Steering orders given this tick, as missile IDs and headings, in order.
+    pub steering: Vec<(u32, Angle)>,
+    syscall_cost: u32,
+}
+
+impl<'a> TankIo<'a> {
+    pub fn new(
+        tick: u64,
+        tank: &'a Tank,
+        sensors: Option<&'a SensorData>,
+        nav: &'a NavGrid,
+        rom: &'a [u32],
+        path_query_limit: u32,
+    ) -> Self {
+        TankIo {
+            tick,
+            tank,
+            sensors,
+            nav,
+            rom,
+            path_queries: 0,
+            path_query_limit,
+            refused_path_queries: 0,
+            actuators: Actuators::read(tank),
+            steering: Vec::new(),
+            syscall_cost: 0,
+        }
+    }
+
+    fn read_contact(&self, offset: u32) -> u32 {
+        let (index, field) = (offset / CONTACT_STRIDE, offset % CONTACT_STRIDE);
+        let Some(contact) = self
+            .sensors
+            .and_then(|sensors| sensors.contacts.get(index as usize))
+        else {
+            return 0;
+        };
+        match field {
+            0 => contact.id,
+            1 => contact.team_id,
+            2 => to_fixed(contact.position.x),
+            _ => to_fixed(contact.position.y),
+        }
+    }
+
+    fn read_missile(&self, offset: u32) -> u32 {
+        let (index, field) = (offset / MISSILE_STRIDE, offset % MISSILE_STRIDE);
+        let Some(missile) = self
+            .sensors
+            .and_then(|sensors| sensors.missiles.get(index as usize))
+        else {
+            return 0;
+        };
+        match field {
+            0 => missile.id,
+            1 => to_fixed(missile.position.x),
+            2 => to_fixed(missile.position.y),
+            _ => to_fixed(missile.heading.radians()),
+        }
+    }
+
+    fn steer_missile(&mut self, stack: &mut Stack) -> Result<(), VmFault> {
+        let heading = Angle::new(from_fixed(stack.pop()?));
+        let id = stack.pop()?;
+        let missile = self
+            .sensors
+            .and_then(|sensors| sensors.missiles.iter().find(|missile| missile.id == id));
+        let Some(missile) = missile else {
+            return stack.push(STEER_UNKNOWN);
+        };
+        self.syscall_cost = missile.steer_cost;
+        self.steering.push((id, heading));
+        stack.push(STEER_OK)
+    }
+
+    fn next_waypoint(&mut self, stack: &mut Stack) -> Result<(), VmFault> {
+        let y = from_fixed(stack.pop()?);
+        let x = from_fixed(stack.pop()?);
+
+        let (status, waypoint) = if self.path_queries >= self.path_query_limit {
+            self.refused_path_queries += 1;
+            (PATH_RATE_LIMITED, None)
+        } else {
+            self.path_queries += 1;
+            match self.nav.next_waypoint(self.tank.position, Vec2::new(x, y)) {
+                Some(waypoint) => (PATH_OK, Some(waypoint)),
+                None => (PATH_NONE, None),
+            }
+        };
+
+        let waypoint = waypoint.unwrap_or(Vec2::zero());
+        stack.push(to_fixed(waypoint.x))?;
+        stack.push(to_fixed(waypoint.y))?;
+        stack.push(status)
+    }
+}
+
+impl VmIo for TankIo<'_> {
+    fn read(&mut self, address: u32) -> Option<u32> {
+        let tank = self.tank;
+        let value = match address {
+            TICK => self.tick as u32,
+            SELF_ID => tank.id,
+            SELF_TEAM => tank.team_id,
+            SELF_X => to_fixed(tank.position.x),
+            SELF_Y => to_fixed(tank.position.y),
+            SELF_ANGLE => to_fixed(tank.angle.radians()),
+            SELF_TURRET => to_fixed(tank.turret_angle.radians()),
+            SELF_VX => to_fixed(tank.velocity.x),
+            SELF_VY => to_fixed(tank.velocity.y),
+            SELF_HEALTH => tank.health,
+            SELF_STATUS => effects::status_bits(tank),
+            _ if (SELF_RELOAD..SELF_RELOAD + MAX_WEAPON_SLOTS).contains(&address) => {
+                let slot = (address - SELF_RELOAD) as usize;
+                tank.reload.get(slot).copied().unwrap_or(0)
+            }
+            CONTACT_COUNT => self
+                .sensors
+                .map_or(0, |sensors| sensors.contacts.len() as u32)
+                .min(MAX_CONTACTS),
+            _ if (CONTACTS..CONTACTS + MAX_CONTACTS * CONTACT_STRIDE).contains(&address) => {
+                self.read_contact(address - CONTACTS)
+            }
+            MISSILE_COUNT => self
+                .sensors
+                .map_or(0, |sensors| sensors.missiles.len() as u32)
+                .min(MAX_MISSILES),
+            _ if (MISSILES..MISSILES + MAX_MISSILES * MISSILE_STRIDE).contains(&address) => {
+                self.read_missile(address - MISSILES)
+            }
+            TEAM_ROM_SIZE => self.rom.len() as u32,
+            _ if address >= TEAM_ROM => {
+                return self.rom.get((address - TEAM_ROM) as usize).copied();
+            }
+            ZONE_X | ZONE_Y | ZONE_RADIUS => {
+                let Some(zone) = self.sensors.and_then(|sensors| sensors.zone) else {
+                    return Some(0);
+                };
+                match address {
+                    ZONE_X => to_fixed(zone.center.x),
+                    ZONE_Y => to_fixed(zone.center.y),
+                    _ => to_fixed(zone.radius),
+                }
+            }
+            LEFT_TRACK => self.actuators.left,
+            RIGHT_TRACK => self.actuators.right,
+            TURRET_MODE => self.actuators.turret_mode,
+            TURRET_ANGLE => self.actuators.turret_angle,
+            FIRE => self.actuators.fire,
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    fn write(&mut self, address: u32, value: u32) -> bool {
+        let register = match address {
+            LEFT_TRACK => &mut self.actuators.left,
+            RIGHT_TRACK => &mut self.actuators.right,
+            TURRET_MODE => &mut self.actuators.turret_mode,
+            TURRET_ANGLE => &mut self.actuators.turret_angle,
+            FIRE => &mut self.actuators.fire,
+            _ => return false,
+        };
+        *register = value;
+        true
+    }
+
+    fn syscall(&mut self, number: u8, stack: &mut Stack) -> Result<(), VmFault> {
+        match number {
+            SYS_NEXT_WAYPOINT => self.next_waypoint(stack),
+            SYS_STEER_MISSILE => self.steer_missile(stack),
+            _ => Err(VmFault::BadSyscall { number }),
+        }
+    }
+
+    fn syscall_cost(&mut self) -> u32 {
+        std::mem::take(&mut self.syscall_cost)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::Contact;
+    use crate::state::VmState;
+    use crate::test_support;
+    use crate::vm::isa::{Assembler, Opcode};
+    use crate::vm::{self, RunOutcome};
+
+    fn tank() -> Tank {
+        let mut tank = test_support::tank(3, 1, Vec2::new(dec64!(50), dec64!(20)));
+        tank.angle = Angle::new(dec64!(0.5));
+        tank
+    }
+
+    fn nav() -> NavGrid {
+        NavGrid::new(dec64!(100), dec64!(100), dec64!(10), dec64!(0), &[])
+    }
+
+    #[test]
+    fn to_fixed_should_round_trip_and_saturate() {
+        // Arrange
+        let value = dec64!(-12.25);
+
+        // Act
+        let word = to_fixed(value);
+
+        // Assert
+        assert_eq!(word as i32, -12 * 65536 - 65536 / 4);
+        assert_eq!(from_fixed(word), value);
+        assert_eq!(to_fixed(dec64!(1000000)), i32::MAX as u32);
+    }
+
+    #[test]
+    fn read_should_expose_self_and_contacts() {
+        // Arrange
+        let tank = tank();
+        let nav = nav();
+        let sensors = SensorData {
+            zone: None,
+            missiles: Vec::new(),
+            contacts: vec![Contact {
+                id: 9,
+                team_id: 2,
+                position: Vec2::new(dec64!(70), dec64!(-4.5)),
+                velocity: Vec2::zero(),
+            }],
+        };
+        let mut io = TankIo::new(42, &tank, Some(&sensors), &nav, &[], 1);
+
+        // Act & Assert
+        assert_eq!(io.read(TICK), Some(42));
+        assert_eq!(io.read(SELF_ID), Some(3));
+        assert_eq!(io.read(SELF_X), Some(50 << 16));
+        assert_eq!(io.read(SELF_ANGLE), Some(1 << 15));
+        assert_eq!(io.read(CONTACT_COUNT), Some(1));
+        assert_eq!(io.read(CONTACTS), Some(9));
+        assert_eq!(io.read(CONTACTS + 3), Some((-9 << 15) as u32));
+        assert_eq!(io.read(CONTACTS + CONTACT_STRIDE), Some(0));
+        assert_eq!(io.read(0x6000), None);
+    }
+
+    #[test]
+    fn readable_should_agree_with_tank_io() {
+        // Arrange
+        let tank = tank();
+        let nav = nav();
+        let mut io = TankIo::new(0, &tank, None, &nav, &[], 1);
+
+        // Act & Assert
+        for address in TICK..=FIRE + 1 {
+            assert_eq!(
+                readable(address),
+                io.read(address).is_some(),
+                "{address:#x}"
+            );
+        }
+    }
+
+    #[test]
+    fn read_should_expose_team_rom_up_to_its_end() {
+        // Arrange
+        let tank = tank();
+        let nav = nav();
+        let rom = [7, 11, 13];
+        let mut io = TankIo::new(0, &tank, None, &nav, &rom, 1);
+
+        // Act & Assert
+        assert_eq!(io.read(TEAM_ROM_SIZE), Some(3));
+        assert_eq!(io.read(TEAM_ROM), Some(7));
+        assert_eq!(io.read(TEAM_ROM + 2), Some(13));
+        assert_eq!(io.read(TEAM_ROM + 3), None);
+        assert!(!io.write(TEAM_ROM, 1));
+        assert!(readable(TEAM_ROM + MAX_TEAM_ROM_WORDS - 1));
+        assert!(!readable(TEAM_ROM + MAX_TEAM_ROM_WORDS));
+    }
+
+    #[test]
+    fn apply_should_decode_actuator_writes() {
+        // Arrange
+        let mut tank = tank();
+        let nav = nav();
+        let code = Assembler::new()
+            .push(1 << 16)
+            .push(LEFT_TRACK)
+            .op(Opcode::Store)
+            .push((-1i32 << 15) as u32)
+            .push(RIGHT_TRACK)
+            .op(Opcode::Store)
+            .push(TURRET_RELATIVE)
+            .push(TURRET_MODE)
+            .op(Opcode::Store)
+            .push(1 << 14)
+            .push(TURRET_ANGLE)
+            .op(Opcode::Store)
+            .push(1)
+            .push(FIRE)
+            .op(Opcode::Store)
+            .op(Opcode::Halt)
+            .finish();
+
+        // Act
+        let actuators = {
+            let mut io = TankIo::new(0, &tank, None, &nav, &[], 1);
+            vm::run(&mut VmState::new(), &code, 100, &mut io);
+            io.actuators
+        };
+        actuators.apply(&mut tank);
+
+        // Assert
+        assert_eq!(tank.drive, DriveInput::tracks(dec64!(1), dec64!(-0.5)));
+        assert_eq!(
+            tank.turret,
+            TurretCommand::Relative(Angle::new(dec64!(0.25)))
+        );
+        assert_eq!(tank.fire, Some(0));
+        assert_eq!(Actuators::read(&tank), actuators);
+    }
+
+    #[test]
+    fn next_waypoint_should_be_rate_limited_per_tick() {
+        // Arrange
+        let tank = tank();
+        let nav = nav();
+        let mut asm = Assembler::new();
+        for _ in 0..2 {
+            asm.push(90 << 16).push(20 << 16).syscall(SYS_NEXT_WAYPOINT);
+        }
+        let code = asm.op(Opcode::Halt).finish();
+        let mut state = VmState::new();
+
+        // Act
+        let mut io = TankIo::new(0, &tank, None, &nav, &[], 1);
+        let report = vm::run(&mut state, &code, 100, &mut io);
+
+        // Assert
+        assert_eq!(report.outcome, RunOutcome::Halted);
+        assert_eq!(io.refused_path_queries, 1);
+        assert_eq!(
+            &state.stack[..6],
+            &[90 << 16, 20 << 16, PATH_OK, 0, 0, PATH_RATE_LIMITED]
+        );
+    }
+}
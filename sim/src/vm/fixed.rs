@@ -0,0 +1,145 @@
+//! Math on signed 16.16 fixed point, the format the ABI exchanges positions and angles in, for
+//! the VM's fixed-point opcodes.
+//!
+//! Everything here is integer arithmetic, so programs compute the same results on every
+//! platform. Results that don't fit a word wrap, as integer arithmetic does.
+
+/// 1.0 in 16.16 fixed point.
+pub const ONE: i64 = 1 << 16;
+/// π, rounded down.
+pub const PI: i32 = 205_887;
+/// π / 2, rounded down.
+pub const HALF_PI: i32 = 102_943;
+
+/// Steps between the entries of [`ATAN`], as a 16.16 ratio.
+const ATAN_STEP_BITS: u32 = 10;
+/// `atan(i / 64)` for `i` in `0..=64`, in 16.16 radians, rounded to nearest.
+const ATAN: [i32; 65] = [
+    0, 1024, 2047, 3070, 4091, 5110, 6126, 7140, 8150, 9156, 10158, 11155, 12147, 13133, 14114,
+    15088, 16055, 17015, 17968, 18913, 19850, 20779, 21699, 22610, 23512, 24406, 25289, 26163,
+    27028, 27882, 28727, 29561, 30386, 31200, 32003, 32797, 33580, 34353, 35115, 35867, 36608,
+    37340, 38060, 38771, 39472, 40162, 40842, 41512, 42172, 42823, 43464, 44095, 44716, 45328,
+    45931, 46525, 47109, 47685, 48251, 48809, 49359, 49899, 50432, 50956, 51472,
+];
+
+/// Multiplies, rounding towards negative infinity.
+pub fn mul(a: u32, b: u32) -> u32 {
+    ((a as i32 as i64 * b as i32 as i64) >> 16) as u32
+}
+
+/// Divides, rounding towards negative infinity, or `None` when dividing by zero.
+pub fn div(a: u32, b: u32) -> Option<u32> {
+    let (n, d) = ((a as i32 as i64) * ONE, b as i32 as i64);
+    if d == 0 {
+        return None;
+    }
+    let quotient = n / d;
+    let floored = if n % d != 0 && (n < 0) != (d < 0) {
+        quotient - 1
+    } else {
+        quotient
+    };
+    Some(floored as u32)
+}
+
+/// Returns the square root, rounded down. Negative values have none, and give zero.
+pub fn sqrt(a: u32) -> u32 {
+    let value = a as i32;
+    if value <= 0 {
+        return 0;
+    }
+    ((value as u64) << 16).isqrt() as u32
+}
+
+/// Returns the angle of `(x, y)` from the positive x axis, in 16.16 radians in `(-π, π]`, or
+/// zero for the origin. Accurate to within a few units in the last place.
+pub fn atan2(y: u32, x: u32) -> u32 {
+    let (y, x) = (y as i32 as i64, x as i32 as i64);
+    if x == 0 && y == 0 {
+        return 0;
+    }
+    // fold into the first octant, then unfold the angle found there
+    let (ax, ay) = (x.abs(), y.abs());
+    let angle = if ax >= ay {
+        atan_unit(ay * ONE / ax)
+    } else {
+        HALF_PI - atan_unit(ax * ONE / ay)
+    };
+    let angle = if x < 0 { PI - angle } else { angle };
+    let angle = if y < 0 { -angle } else { angle };
+    angle as u32
+}
+
+/// `atan` of a 16.16 ratio in `[0, 1]`, interpolated between the entries of [`ATAN`].
+fn atan_unit(ratio: i64) -> i32 {
+    let index = (ratio >> ATAN_STEP_BITS) as usize;
+    let Some(&next) = ATAN.get(index + 1) else {
+        return ATAN[ATAN.len() - 1];
+    };
+    let fraction = ratio & ((1 << ATAN_STEP_BITS) - 1);
+    let base = ATAN[index] as i64;
+    (base + (((next as i64 - base) * fraction) >> ATAN_STEP_BITS)) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixed(value: f64) -> u32 {
+        (value * ONE as f64).floor() as i32 as u32
+    }
+
+    fn real(word: u32) -> f64 {
+        word as i32 as f64 / ONE as f64
+    }
+
+    #[test]
+    fn mul_and_div_should_round_towards_negative_infinity() {
+        // Arrange
+        let tiny = 1;
+        let half = fixed(0.5);
+
+        // Act & Assert
+        assert_eq!(mul(fixed(1.5), fixed(-2.25)), fixed(-3.375));
+        assert_eq!(mul(tiny, half), 0);
+        assert_eq!(mul(tiny.wrapping_neg(), half), u32::MAX);
+        assert_eq!(div(fixed(-3.375), fixed(1.5)), Some(fixed(-2.25)));
+        assert_eq!(div(fixed(1.0), fixed(3.0)), Some(21845));
+        assert_eq!(div(fixed(-1.0), fixed(3.0)), Some(-21846i32 as u32));
+        assert_eq!(div(fixed(1.0), 0), None);
+    }
+
+    #[test]
+    fn sqrt_should_round_down_and_give_zero_for_negatives() {
+        // Act & Assert
+        assert_eq!(sqrt(fixed(6.25)), fixed(2.5));
+        assert_eq!(sqrt(fixed(2.0)), 92681);
+        assert_eq!(sqrt(fixed(-4.0)), 0);
+        assert_eq!(real(sqrt(i32::MAX as u32)).floor(), 181.0);
+    }
+
+    #[test]
+    fn atan2_should_stay_within_a_few_units_of_the_true_angle() {
+        // Arrange
+        let mut worst = 0.0f64;
+
+        // Act
+        for y in -40..=40 {
+            for x in -40..=40 {
+                let (y, x) = (y as f64 * 0.37, x as f64 * 0.41);
+                let angle = real(atan2(fixed(y), fixed(x)));
+                let expected = if x == 0.0 && y == 0.0 {
+                    0.0
+                } else {
+                    real(fixed(y)).atan2(real(fixed(x)))
+                };
+                worst = worst.max((angle - expected).abs());
+            }
+        }
+
+        // Assert
+        assert!(worst * ONE as f64 <= 4.0, "off by {worst}");
+        assert_eq!(atan2(0, fixed(-1.0)), PI as u32);
+        assert_eq!(atan2(fixed(-1.0), 0), (-HALF_PI) as u32);
+    }
+}
@@ -0,0 +1,260 @@
+/// Version of the instruction set, bumped whenever opcodes are added, renumbered or change
+/// meaning. Programs built for older versions are brought up to date through
+/// [`package::SHIMS`](super::package::SHIMS).
+pub const ISA_VERSION: u16 = 3;
+
+/// Instruction opcodes. Every instruction is a single opcode byte, followed by a little-endian
+/// operand for the few that take one (see [`Opcode::operand_size`]).
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Opcode {
+    Nop = 0x00,
+    /// Pushes a 32-bit immediate.
+    Push = 0x01,
+    Pop = 0x02,
+    Dup = 0x03,
+    Swap = 0x04,
+    /// Copies the second item to the top: `a b -> a b a`.
+    Over = 0x05,
+
+    Add = 0x10,
+    Sub = 0x11,
+    Mul = 0x12,
+    /// Signed division, faults on division by zero.
+    Div = 0x13,
+    /// Signed remainder, faults on division by zero.
+    Mod = 0x14,
+    Neg = 0x15,
+    /// Signed 16.16 fixed-point multiplication, rounding towards negative infinity.
+    FixMul = 0x16,
+    /// Signed 16.16 fixed-point division, rounding towards negative infinity. Faults on
+    /// division by zero.
+    FixDiv = 0x17,
+
+    Eq = 0x18,
+    Ne = 0x19,
+    /// Signed comparisons, pushing 1 for true and 0 for false.
+    Lt = 0x1a,
+    Le = 0x1b,
+    Gt = 0x1c,
+    Ge = 0x1d,
+    /// Logical not: pushes 1 if the top is zero, otherwise 0.
+    Not = 0x1e,
+
+    /// Jumps to a 32-bit absolute byte address.
+    Jmp = 0x20,
+    /// Pops a value and jumps if it is zero.
+    Jz = 0x21,
+    /// Pops a value and jumps if it is non-zero.
+    Jnz = 0x22,
+    /// Calls the function at a 32-bit absolute byte address, starting a frame with no locals.
+    /// Faults if the program's call depth is used up.
+    Call = 0x23,
+    /// Returns from the current function, leaving the top `n` words, for an 8-bit `n`, in
+    /// place of its frame.
+    Ret = 0x24,
+    /// Makes the top `n` words, for an 8-bit `n`, the first locals of the current frame, as a
+    /// function's arguments.
+    Enter = 0x25,
+
+    /// Pops an address and pushes the word stored there.
+    Load = 0x30,
+    /// Pops an address, then a value, and stores the value at the address.
+    Store = 0x31,
+    /// Pushes the local with the given 8-bit index, counted from the frame pointer.
+    LoadLocal = 0x32,
+    /// Pops a value into the local with the given 8-bit index.
+    StoreLocal = 0x33,
+
+    /// Calls the host service with the given 8-bit number.
+    Syscall = 0x40,
+    /// Ends the tick early; execution resumes at the next instruction next tick.
+    Yield = 0x50,
+    And = 0x60,
+    Or = 0x61,
+    Xor = 0x62,
+    /// Shifts the second item left by the top item, modulo 32.
+    Shl = 0x63,
+    /// Logical shift right, by the top item modulo 32.
+    Shr = 0x64,
+    /// Arithmetic shift right, by the top item modulo 32, keeping the sign.
+    Sar = 0x65,
+
+    /// Square root of a 16.16 value, rounded down. Negative values give zero.
+    Sqrt = 0x70,
+    /// Pops `x`, then `y`, and pushes the angle of `(x, y)` in 16.16 radians, in `(-π, π]`.
+    Atan2 = 0x71,
+
+    /// Stops the program for good.
+    Halt = 0xff,
+}
+
+impl Opcode {
+    /// Decodes an opcode byte.
+    pub const fn from_byte(byte: u8) -> Option<Opcode> {
+        use Opcode::*;
+        let op = match byte {
+            0x00 => Nop,
+            0x01 => Push,
+            0x02 => Pop,
+            0x03 => Dup,
+            0x04 => Swap,
+            0x05 => Over,
+            0x10 => Add,
+            0x11 => Sub,
+            0x12 => Mul,
+            0x13 => Div,
+            0x14 => Mod,
+            0x15 => Neg,
+            0x16 => FixMul,
+            0x17 => FixDiv,
+            0x18 => Eq,
+            0x19 => Ne,
+            0x1a => Lt,
+            0x1b => Le,
+            0x1c => Gt,
+            0x1d => Ge,
+            0x1e => Not,
+            0x20 => Jmp,
+            0x21 => Jz,
+            0x22 => Jnz,
+            0x23 => Call,
+            0x24 => Ret,
+            0x25 => Enter,
+            0x30 => Load,
+            0x31 => Store,
+            0x32 => LoadLocal,
+            0x33 => StoreLocal,
+            0x40 => Syscall,
+            0x50 => Yield,
+            0x60 => And,
+            0x61 => Or,
+            0x62 => Xor,
+            0x63 => Shl,
+            0x64 => Shr,
+            0x65 => Sar,
+            0x70 => Sqrt,
+            0x71 => Atan2,
+            0xff => Halt,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    /// Returns the number of operand bytes following the opcode.
+    pub fn operand_size(self) -> usize {
+        match self {
+            Opcode::Push | Opcode::Jmp | Opcode::Jz | Opcode::Jnz | Opcode::Call => 4,
+            Opcode::Syscall | Opcode::Ret | Opcode::Enter => 1,
+            Opcode::LoadLocal | Opcode::StoreLocal => 1,
+            _ => 0,
+        }
+    }
+
+    /// Returns the words the instruction pops and then pushes. A syscall's effect depends on
+    /// the service, and a call's or return's on the function, so neither is included.
+    pub fn stack_effect(self) -> (u32, u32) {
+        use Opcode::*;
+        match self {
+            Nop | Jmp | Call | Ret | Enter | Syscall | Yield | Halt => (0, 0),
+            Push | LoadLocal => (0, 1),
+            Pop | Jz | Jnz | StoreLocal => (1, 0),
+            Dup => (1, 2),
+            Swap => (2, 2),
+            Over => (2, 3),
+            Add | Sub | Mul | Div | Mod | FixMul | FixDiv | Eq | Ne | Lt | Le | Gt | Ge => (2, 1),
+            And | Or | Xor | Shl | Shr | Sar | Atan2 => (2, 1),
+            Neg | Not | Sqrt | Load => (1, 1),
+            Store => (2, 0),
+        }
+    }
+
+    /// Returns whether execution can carry on to the next instruction.
+    pub fn falls_through(self) -> bool {
+        !matches!(self, Opcode::Jmp | Opcode::Ret | Opcode::Halt)
+    }
+
+    /// Returns whether the operand is a jump target. Calls count, landing on the function.
+    pub fn is_jump(self) -> bool {
+        matches!(self, Opcode::Jmp | Opcode::Jz | Opcode::Jnz | Opcode::Call)
+    }
+}
+
+/// A forward-referenceable jump target in an [`Assembler`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Label(usize);
+
+/// A tiny assembler for building bytecode by hand, mostly for tests and built-in programs.
+#[derive(Default)]
+pub struct Assembler {
+    code: Vec<u8>,
+    labels: Vec<Option<u32>>,
+    fixups: Vec<(usize, Label)>,
+}
+
+impl Assembler {
+    pub fn new() -> Self {
+        Assembler::default()
+    }
+
+    /// Emits an instruction without operands.
+    pub fn op(&mut self, op: Opcode) -> &mut Self {
+        debug_assert_eq!(op.operand_size(), 0, "{op:?} takes an operand");
+        self.code.push(op as u8);
+        self
+    }
+
+    /// Emits a `PUSH` of the given immediate.
+    pub fn push(&mut self, value: u32) -> &mut Self {
+        self.code.push(Opcode::Push as u8);
+        self.code.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    /// Emits a `SYSCALL` to the given service.
+    pub fn syscall(&mut self, number: u8) -> &mut Self {
+        self.op_u8(Opcode::Syscall, number)
+    }
+
+    /// Emits an instruction with an 8-bit operand, such as `RET` or `LOADLOCAL`.
+    pub fn op_u8(&mut self, op: Opcode, operand: u8) -> &mut Self {
+        debug_assert_eq!(op.operand_size(), 1, "{op:?} doesn't take an 8-bit operand");
+        self.code.push(op as u8);
+        self.code.push(operand);
+        self
+    }
+
+    /// Creates a new, unbound label.
+    pub fn label(&mut self) -> Label {
+        self.labels.push(None);
+        Label(self.labels.len() - 1)
+    }
+
+    /// Binds a label to the current position.
+    pub fn bind(&mut self, label: Label) -> &mut Self {
+        self.labels[label.0] = Some(self.code.len() as u32);
+        self
+    }
+
+    /// Emits a `JMP`, `JZ`, `JNZ` or `CALL` to the given label.
+    pub fn jump(&mut self, op: Opcode, label: Label) -> &mut Self {
+        debug_assert!(op.is_jump());
+        self.code.push(op as u8);
+        self.fixups.push((self.code.len(), label));
+        self.code.extend_from_slice(&[0; 4]);
+        self
+    }
+
+    /// Resolves all jumps and returns the bytecode.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a label was jumped to but never bound.
+    pub fn finish(&mut self) -> Vec<u8> {
+        for (offset, label) in self.fixups.drain(..) {
+            let target = self.labels[label.0].expect("jump to unbound label");
+            self.code[offset..offset + 4].copy_from_slice(&target.to_le_bytes());
+        }
+        std::mem::take(&mut self.code)
+    }
+}
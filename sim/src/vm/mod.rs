@@ -0,0 +1,730 @@
+pub mod abi;
+pub mod fixed;
+pub mod isa;
+pub mod package;
+pub mod profile;
+pub mod threaded;
+pub mod verify;
+
+use crate::state::{Frame, VmState};
+use isa::Opcode;
+use profile::Profiler;
+use serde::{Deserialize, Serialize};
+
+/// Default number of words on the operand stack.
+pub const STACK_SIZE: usize = 256;
+/// Default number of words of general-purpose RAM, mapped from address zero.
+pub const MEMORY_SIZE: usize = 1024;
+/// Default number of calls that may be in progress at once.
+pub const CALL_DEPTH: usize = 64;
+
+/// How a match runs its tanks' programs. Both give exactly the same results.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Execution {
+    /// Decodes each instruction as it's reached, with [`run`].
+    #[default]
+    Interpreted,
+    /// Decodes each program once, when it's loaded, and runs it with [`threaded::run`].
+    Threaded,
+}
+
+/// Errors that stop a program for good.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VmFault {
+    InvalidOpcode {
+        pc: u32,
+    },
+    PcOutOfRange {
+        pc: u32,
+    },
+    StackOverflow,
+    StackUnderflow,
+    DivisionByZero {
+        pc: u32,
+    },
+    BadAddress {
+        address: u32,
+    },
+    BadSyscall {
+        number: u8,
+    },
+    /// A call past the program's call depth.
+    CallStackOverflow,
+    /// A return with no call in progress.
+    CallStackUnderflow,
+    /// A local past the top of the stack, or below its bottom.
+    BadLocal {
+        index: u32,
+    },
+}
+
+/// Why a call to [`run`] returned.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RunOutcome {
+    Yielded,
+    OutOfCycles,
+    Halted,
+    Faulted(VmFault),
+}
+
+/// What happened during a call to [`run`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RunReport {
+    pub cycles: u32,
+    pub outcome: RunOutcome,
+}
+
+/// The operand stack, as seen by syscall handlers.
+pub struct Stack<'a> {
+    state: &'a mut VmState,
+}
+
+impl Stack<'_> {
+    pub fn pop(&mut self) -> Result<u32, VmFault> {
+        if self.state.sp == 0 {
+            return Err(VmFault::StackUnderflow);
+        }
+        self.state.sp -= 1;
+        Ok(self.state.stack[self.state.sp as usize])
+    }
+
+    pub fn push(&mut self, value: u32) -> Result<(), VmFault> {
+        let slot = self
+            .state
+            .stack
+            .get_mut(self.state.sp as usize)
+            .ok_or(VmFault::StackOverflow)?;
+        *slot = value;
+        self.state.sp += 1;
+        Ok(())
+    }
+}
+
+/// The host side of a VM: memory-mapped I/O outside of RAM, and syscalls.
+pub trait VmIo {
+    /// Reads a memory-mapped word, or `None` if nothing readable is mapped there.
+    fn read(&mut self, address: u32) -> Option<u32>;
+
+    /// Writes a memory-mapped word, returning `false` if nothing writable is mapped there.
+    fn write(&mut self, address: u32, value: u32) -> bool;
+
+    /// Services a syscall, popping its arguments from and pushing its results to the stack.
+    fn syscall(&mut self, number: u8, stack: &mut Stack) -> Result<(), VmFault>;
+
+    /// Returns the cycles the syscall just serviced costs on top of its own, for host work
+    /// worth charging for. Asked once after every syscall.
+    fn syscall_cost(&mut self) -> u32 {
+        0
+    }
+}
+
+enum Flow {
+    Continue,
+    /// Carry on, charging this many extra cycles.
+    Charge(u32),
+    Yield,
+    Halt,
+}
+
+/// Runs a program for at most `budget` instructions, picking up where it left off last time.
+///
+/// Faults and halts are sticky: once either happens, later calls do nothing.
+pub fn run(state: &mut VmState, code: &[u8], budget: u32, io: &mut impl VmIo) -> RunReport {
+    run_with(state, budget, io, |state, io| execute(state, code, io))
+}
+
+/// Like [`run`], but records every cycle spent with the profiler.
+pub fn run_profiled(
+    state: &mut VmState,
+    code: &[u8],
+    budget: u32,
+    io: &mut impl VmIo,
+    profiler: &mut Profiler,
+) -> RunReport {
+    run_with(state, budget, io, |state, io| {
+        profiler.record(state.pc, code.get(state.pc as usize).copied());
+        execute(state, code, io)
+    })
+}
+
+/// Runs instructions with `execute` until the budget runs out or the program stops, sharing
+/// the bookkeeping between the ways of executing them.
+fn run_with<I: VmIo>(
+    state: &mut VmState,
+    budget: u32,
+    io: &mut I,
+    mut execute: impl FnMut(&mut VmState, &mut I) -> Result<Flow, VmFault>,
+) -> RunReport {
+    if let Some(fault) = state.fault {
+        return RunReport {
+            cycles: 0,
+            outcome: RunOutcome::Faulted(fault),
+        };
+    }
+    if state.halted {
+        return RunReport {
+            cycles: 0,
+            outcome: RunOutcome::Halted,
+        };
+    }
+
+    let mut cycles = 0;
+    while cycles < budget {
+        cycles += 1;
+        let outcome = match execute(state, io) {
+            Ok(Flow::Continue) => continue,
+            Ok(Flow::Charge(extra)) => {
+                cycles = cycles.saturating_add(extra).min(budget);
+                continue;
+            }
+            Ok(Flow::Yield) => RunOutcome::Yielded,
+            Ok(Flow::Halt) => {
+                state.halted = true;
+                RunOutcome::Halted
+            }
+            Err(fault) => {
+                state.fault = Some(fault);
+                RunOutcome::Faulted(fault)
+            }
+        };
+        return RunReport { cycles, outcome };
+    }
+
+    RunReport {
+        cycles,
+        outcome: RunOutcome::OutOfCycles,
+    }
+}
+
+fn read_operand(code: &[u8], pc: u32, size: usize) -> Result<u32, VmFault> {
+    let start = pc as usize + 1;
+    let bytes = code
+        .get(start..start + size)
+        .ok_or(VmFault::PcOutOfRange { pc })?;
+    let mut word = [0; 4];
+    word[..size].copy_from_slice(bytes);
+    Ok(u32::from_le_bytes(word))
+}
+
+fn load(state: &VmState, io: &mut impl VmIo, address: u32) -> Result<u32, VmFault> {
+    match state.memory.get(address as usize) {
+        Some(value) => Ok(*value),
+        None => io.read(address).ok_or(VmFault::BadAddress { address }),
+    }
+}
+
+fn store(state: &mut VmState, io: &mut impl VmIo, address: u32, value: u32) -> Result<(), VmFault> {
+    match state.memory.get_mut(address as usize) {
+        Some(slot) => {
+            *slot = value;
+            Ok(())
+        }
+        None if io.write(address, value) => Ok(()),
+        None => Err(VmFault::BadAddress { address }),
+    }
+}
+
+/// Starts a frame for a call to `target`, returning to wherever the program counter points.
+fn call(state: &mut VmState, target: u32) -> Result<(), VmFault> {
+    if state.frames.len() >= state.call_depth as usize {
+        return Err(VmFault::CallStackOverflow);
+    }
+    state.frames.push(Frame {
+        return_pc: state.pc,
+        fp: state.fp,
+    });
+    state.fp = state.sp;
+    state.pc = target;
+    Ok(())
+}
+
+/// Ends the current frame, moving its top `results` words down to where it started.
+fn ret(state: &mut VmState, results: u32) -> Result<(), VmFault> {
+    let frame = *state.frames.last().ok_or(VmFault::CallStackUnderflow)?;
+    let (fp, sp) = (state.fp, state.sp);
+    if sp.checked_sub(fp).is_none_or(|words| words < results) {
+        return Err(VmFault::StackUnderflow);
+    }
+    state
+        .stack
+        .copy_within((sp - results) as usize..sp as usize, fp as usize);
+    state.frames.pop();
+    state.sp = fp + results;
+    state.fp = frame.fp;
+    state.pc = frame.return_pc;
+    Ok(())
+}
+
+fn enter(state: &mut VmState, args: u32) -> Result<(), VmFault> {
+    state.fp = state.sp.checked_sub(args).ok_or(VmFault::StackUnderflow)?;
+    Ok(())
+}
+
+/// Returns where the local `index` is on the stack.
+fn local(state: &VmState, index: u32) -> Result<usize, VmFault> {
+    let slot = state.fp.saturating_add(index);
+    if slot >= state.sp {
+        return Err(VmFault::BadLocal { index });
+    }
+    Ok(slot as usize)
+}
+
+fn load_local(state: &mut VmState, index: u32) -> Result<(), VmFault> {
+    let value = state.stack[local(state, index)?];
+    Stack { state }.push(value)
+}
+
+fn store_local(state: &mut VmState, index: u32) -> Result<(), VmFault> {
+    let value = Stack { state }.pop()?;
+    let slot = local(state, index)?;
+    state.stack[slot] = value;
+    Ok(())
+}
+
+fn execute(state: &mut VmState, code: &[u8], io: &mut impl VmIo) -> Result<Flow, VmFault> {
+    let pc = state.pc;
+    let byte = *code.get(pc as usize).ok_or(VmFault::PcOutOfRange { pc })?;
+    let op = Opcode::from_byte(byte).ok_or(VmFault::InvalidOpcode { pc })?;
+    let operand = read_operand(code, pc, op.operand_size())?;
+    state.pc = pc + 1 + op.operand_size() as u32;
+
+    // signed binary operations share the same pop/push shape
+    macro_rules! binary {
+        ($f:expr) => {{
+            let mut stack = Stack { state };
+            let b = stack.pop()?;
+            let a = stack.pop()?;
+            let f: fn(u32, u32) -> u32 = $f;
+            stack.push(f(a, b))?;
+        }};
+    }
+
+    match op {
+        Opcode::Nop => {}
+        Opcode::Push => Stack { state }.push(operand)?,
+        Opcode::Pop => {
+            Stack { state }.pop()?;
+        }
+        Opcode::Dup => {
+            let mut stack = Stack { state };
+            let a = stack.pop()?;
+            stack.push(a)?;
+            stack.push(a)?;
+        }
+        Opcode::Swap => {
+            let mut stack = Stack { state };
+            let b = stack.pop()?;
+            let a = stack.pop()?;
+            stack.push(b)?;
+            stack.push(a)?;
+        }
+        Opcode::Over => {
+            let mut stack = Stack { state };
+            let b = stack.pop()?;
+            let a = stack.pop()?;
+            stack.push(a)?;
+            stack.push(b)?;
+            stack.push(a)?;
+        }
+        Opcode::Add => binary!(|a, b| a.wrapping_add(b)),
+        Opcode::Sub => binary!(|a, b| a.wrapping_sub(b)),
+        Opcode::Mul => binary!(|a, b| a.wrapping_mul(b)),
+        Opcode::Div | Opcode::Mod => {
+            let mut stack = Stack { state };
+            let b = stack.pop()? as i32;
+            let a = stack.pop()? as i32;
+            if b == 0 {
+                return Err(VmFault::DivisionByZero { pc });
+            }
+            let result = if op == Opcode::Div {
+                a.wrapping_div(b)
+            } else {
+                a.wrapping_rem(b)
+            };
+            stack.push(result as u32)?;
+        }
+        Opcode::FixMul => binary!(fixed::mul),
+        Opcode::FixDiv => {
+            let mut stack = Stack { state };
+            let b = stack.pop()?;
+            let a = stack.pop()?;
+            let quotient = fixed::div(a, b).ok_or(VmFault::DivisionByZero { pc })?;
+            stack.push(quotient)?;
+        }
+        Opcode::Neg => {
+            let mut stack = Stack { state };
+            let a = stack.pop()?;
+            stack.push(a.wrapping_neg())?;
+        }
+        Opcode::Eq => binary!(|a, b| (a == b) as u32),
+        Opcode::Ne => binary!(|a, b| (a != b) as u32),
+        Opcode::Lt => binary!(|a, b| ((a as i32) < (b as i32)) as u32),
+        Opcode::Le => binary!(|a, b| ((a as i32) <= (b as i32)) as u32),
+        Opcode::Gt => binary!(|a, b| ((a as i32) > (b as i32)) as u32),
+        Opcode::Ge => binary!(|a, b| ((a as i32) >= (b as i32)) as u32),
+        Opcode::Not => {
+            let mut stack = Stack { state };
+            let a = stack.pop()?;
+            stack.push((a == 0) as u32)?;
+        }
+        Opcode::And => binary!(|a, b| a & b),
+        Opcode::Or => binary!(|a, b| a | b),
+        Opcode::Xor => binary!(|a, b| a ^ b),
+        Opcode::Shl => binary!(|a, b| a.wrapping_shl(b)),
+        Opcode::Shr => binary!(|a, b| a.wrapping_shr(b)),
+        Opcode::Sar => binary!(|a, b| (a as i32).wrapping_shr(b) as u32),
+        Opcode::Sqrt => {
+            let mut stack = Stack { state };
+            let a = stack.pop()?;
+            stack.push(fixed::sqrt(a))?;
+        }
+        Opcode::Atan2 => binary!(fixed::atan2),
+        Opcode::Jmp => state.pc = operand,
+        Opcode::Jz | Opcode::Jnz => {
+            let value = Stack { state }.pop()?;
+            if (value == 0) == (op == Opcode::Jz) {
+                state.pc = operand;
+            }
+        }
+        Opcode::Call => call(state, operand)?,
+        Opcode::Ret => ret(state, operand)?,
+        Opcode::Enter => enter(state, operand)?,
+        Opcode::Load => {
+            let address = Stack { state }.pop()?;
+            let value = load(state, io, address)?;
+            Stack { state }.push(value)?;
+        }
+        Opcode::Store => {
+            let mut stack = Stack { state };
+            let address = stack.pop()?;
+            let value = stack.pop()?;
+            store(state, io, address, value)?;
+        }
+        Opcode::LoadLocal => load_local(state, operand)?,
+        Opcode::StoreLocal => store_local(state, operand)?,
+        Opcode::Syscall => {
+            io.syscall(operand as u8, &mut Stack { state })?;
+            let extra = io.syscall_cost();
+            if extra > 0 {
+                return Ok(Flow::Charge(extra));
+            }
+        }
+        Opcode::Yield => return Ok(Flow::Yield),
+        Opcode::Halt => return Ok(Flow::Halt),
+    }
+
+    Ok(Flow::Continue)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use isa::Assembler;
+    use profile::FunctionSymbol;
+    use proptest::prelude::*;
+
+    /// I/O with a single read-write register at 0x8000 and an "add" syscall costing three extra
+    /// cycles.
+    #[derive(Default)]
+    struct TestIo {
+        register: u32,
+    }
+
+    impl VmIo for TestIo {
+        fn read(&mut self, address: u32) -> Option<u32> {
+            (address == 0x8000).then_some(self.register)
+        }
+
+        fn write(&mut self, address: u32, value: u32) -> bool {
+            if address == 0x8000 {
+                self.register = value;
+            }
+            address == 0x8000
+        }
+
+        fn syscall(&mut self, number: u8, stack: &mut Stack) -> Result<(), VmFault> {
+            if number != 7 {
+                return Err(VmFault::BadSyscall { number });
+            }
+            let b = stack.pop()?;
+            let a = stack.pop()?;
+            stack.push(a + b)
+        }
+
+        fn syscall_cost(&mut self) -> u32 {
+            3
+        }
+    }
+
+    fn run_program(code: &[u8], budget: u32) -> (VmState, RunReport) {
+        let mut state = VmState::new();
+        let report = run(&mut state, code, budget, &mut TestIo::default());
+        (state, report)
+    }
+
+    fn top(state: &VmState) -> u32 {
+        state.stack[state.sp as usize - 1]
+    }
+
+    #[test]
+    fn run_should_evaluate_arithmetic() {
+        // Arrange
+        // (7 - 10) * 3 / 2 = -4 (truncating)
+        let code = Assembler::new()
+            .push(7)
+            .push(10)
+            .op(Opcode::Sub)
+            .push(3)
+            .op(Opcode::Mul)
+            .push(2)
+            .op(Opcode::Div)
+            .op(Opcode::Halt)
+            .finish();
+
+        // Act
+        let (state, report) = run_program(&code, 100);
+
+        // Assert
+        assert_eq!(report.outcome, RunOutcome::Halted);
+        assert_eq!(report.cycles, 8);
+        assert_eq!(top(&state) as i32, -4);
+    }
+
+    #[test]
+    fn run_should_loop_with_conditional_jumps() {
+        // Arrange
+        // count down from 5, storing the number of iterations at address 0
+        let mut asm = Assembler::new();
+        let (start, end) = (asm.label(), asm.label());
+        asm.push(5)
+            .bind(start)
+            .op(Opcode::Dup)
+            .jump(Opcode::Jz, end);
+        asm.push(0)
+            .op(Opcode::Load)
+            .push(1)
+            .op(Opcode::Add)
+            .push(0)
+            .op(Opcode::Store);
+        asm.push(1).op(Opcode::Sub).jump(Opcode::Jmp, start);
+        asm.bind(end).op(Opcode::Halt);
+        let code = asm.finish();
+
+        // Act
+        let (state, report) = run_program(&code, 1000);
+
+        // Assert
+        assert_eq!(report.outcome, RunOutcome::Halted);
+        assert_eq!(state.memory[0], 5);
+    }
+
+    #[test]
+    fn run_should_recurse_through_calls_with_locals() {
+        // Arrange
+        // fact(n) = n == 0 ? 1 : n * fact(n - 1)
+        let mut asm = Assembler::new();
+        let (fact, recurse) = (asm.label(), asm.label());
+        asm.push(5).jump(Opcode::Call, fact).op(Opcode::Halt);
+        asm.bind(fact)
+            .op_u8(Opcode::Enter, 1)
+            .op_u8(Opcode::LoadLocal, 0)
+            .jump(Opcode::Jnz, recurse)
+            .push(1)
+            .op_u8(Opcode::Ret, 1);
+        asm.bind(recurse)
+            .op_u8(Opcode::LoadLocal, 0)
+            .op_u8(Opcode::LoadLocal, 0)
+            .push(1)
+            .op(Opcode::Sub)
+            .jump(Opcode::Call, fact)
+            .op(Opcode::Mul)
+            .op_u8(Opcode::Ret, 1);
+        let code = asm.finish();
+
+        // Act
+        let (state, report) = run_program(&code, 1000);
+
+        // Assert
+        assert_eq!(report.outcome, RunOutcome::Halted);
+        assert_eq!(state.sp, 1);
+        assert_eq!(top(&state), 120);
+        assert_eq!((state.fp, state.frames.len()), (0, 0));
+    }
+
+    #[test]
+    fn run_when_budget_exhausted_should_resume_next_call() {
+        // Arrange
+        let code = Assembler::new()
+            .push(1)
+            .push(2)
+            .op(Opcode::Yield)
+            .op(Opcode::Add)
+            .op(Opcode::Halt)
+            .finish();
+        let mut state = VmState::new();
+        let mut io = TestIo::default();
+
+        // Act
+        let first = run(&mut state, &code, 1, &mut io);
+        let second = run(&mut state, &code, 10, &mut io);
+        let third = run(&mut state, &code, 10, &mut io);
+
+        // Assert
+        assert_eq!(first.outcome, RunOutcome::OutOfCycles);
+        assert_eq!(second.outcome, RunOutcome::Yielded);
+        assert_eq!(second.cycles, 2);
+        assert_eq!(third.outcome, RunOutcome::Halted);
+        assert_eq!(top(&state), 3);
+    }
+
+    #[test]
+    fn run_profiled_should_count_opcodes_and_cycles_per_function() {
+        // Arrange
+        // main pushes twice and jumps into a helper that adds and halts
+        let mut asm = Assembler::new();
+        let helper = asm.label();
+        asm.push(1)
+            .push(2)
+            .jump(Opcode::Jmp, helper)
+            .op(Opcode::Nop);
+        asm.bind(helper).op(Opcode::Add).op(Opcode::Halt);
+        let code = asm.finish();
+        let functions = [
+            FunctionSymbol {
+                name: "main".to_string(),
+                start: 0,
+                end: 16,
+            },
+            FunctionSymbol {
+                name: "helper".to_string(),
+                start: 16,
+                end: code.len() as u32,
+            },
+        ];
+        let mut profiler = Profiler::new(&functions);
+
+        // Act
+        let report = run_profiled(
+            &mut VmState::new(),
+            &code,
+            100,
+            &mut TestIo::default(),
+            &mut profiler,
+        );
+
+        // Assert
+        let profile = profiler.finish();
+        assert_eq!(report.cycles, 5);
+        assert_eq!(profile.instructions["Push"], 2);
+        assert_eq!(profile.instructions["Jmp"], 1);
+        assert!(!profile.instructions.contains_key("Nop"));
+        assert_eq!(profile.functions["main"], 3);
+        assert_eq!(profile.functions["helper"], 2);
+        assert!(!profile.functions.contains_key(profile::UNKNOWN_FUNCTION));
+    }
+
+    #[test]
+    fn run_should_route_io_and_syscalls_to_host() {
+        // Arrange
+        let code = Assembler::new()
+            .push(40)
+            .push(2)
+            .syscall(7)
+            .push(0x8000)
+            .op(Opcode::Store)
+            .push(0x8000)
+            .op(Opcode::Load)
+            .op(Opcode::Halt)
+            .finish();
+
+        // Act
+        let (state, report) = run_program(&code, 100);
+
+        // Assert
+        assert_eq!(top(&state), 42);
+        assert_eq!(report.cycles, 8 + 3);
+    }
+
+    #[test]
+    fn run_when_faulting_should_stop_for_good() {
+        // Arrange
+        let cases: [(Vec<u8>, VmFault); 8] = [
+            (vec![Opcode::Pop as u8], VmFault::StackUnderflow),
+            (
+                Assembler::new().push(1).push(0).op(Opcode::Div).finish(),
+                VmFault::DivisionByZero { pc: 10 },
+            ),
+            (vec![0xee], VmFault::InvalidOpcode { pc: 0 }),
+            (
+                Assembler::new().push(0x9000).op(Opcode::Load).finish(),
+                VmFault::BadAddress { address: 0x9000 },
+            ),
+            (
+                Assembler::new().syscall(3).finish(),
+                VmFault::BadSyscall { number: 3 },
+            ),
+            (
+                Assembler::new().op_u8(Opcode::Ret, 0).finish(),
+                VmFault::CallStackUnderflow,
+            ),
+            (
+                Assembler::new()
+                    .push(1)
+                    .op_u8(Opcode::LoadLocal, 1)
+                    .finish(),
+                VmFault::BadLocal { index: 1 },
+            ),
+            (
+                vec![Opcode::Call as u8, 0, 0, 0, 0],
+                VmFault::CallStackOverflow,
+            ),
+        ];
+
+        for (code, expected) in cases {
+            // Act
+            let (mut state, report) = run_program(&code, 100);
+            let again = run(&mut state, &code, 100, &mut TestIo::default());
+
+            // Assert
+            assert_eq!(report.outcome, RunOutcome::Faulted(expected));
+            assert_eq!(again.cycles, 0);
+            assert_eq!(state.fault, Some(expected));
+        }
+    }
+
+    #[test]
+    fn run_when_running_off_the_end_should_fault() {
+        // Arrange
+        let code = Assembler::new().push(1).finish();
+
+        // Act
+        let (_, report) = run_program(&code, 100);
+
+        // Assert
+        assert_eq!(
+            report.outcome,
+            RunOutcome::Faulted(VmFault::PcOutOfRange { pc: 5 })
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn run_when_fed_random_bytecode_should_never_panic(
+            code in prop::collection::vec(any::<u8>(), 0..256),
+            budget in 0u32..2000,
+        ) {
+            // Arrange
+            let mut first = VmState::new();
+            let mut second = VmState::new();
+
+            // Act
+            let a = run(&mut first, &code, budget, &mut TestIo::default());
+            let b = run(&mut second, &code, budget, &mut TestIo::default());
+
+            // Assert
+            prop_assert!(a.cycles <= budget);
+            prop_assert_eq!(a, b);
+            prop_assert_eq!(first, second);
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! Bot packages: bytecode stamped with the instruction set and ABI it was built for, so the
+//! sim can tell whether it still understands a program before running it.
+//!
+//! A package is [`MAGIC`], the ISA and ABI versions as little-endian `u16`s, then the code.
+//! Bare bytecode, without the header, is taken to be built for the current versions; it can't
+//! be mistaken for a package, since the magic doesn't start with a valid opcode.
+
+use super::abi::ABI_VERSION;
+use super::isa::{ISA_VERSION, Opcode};
+use std::fmt;
+
+/// Marks the start of a bot package.
+pub const MAGIC: &[u8; 4] = b"ATBP";
+const HEADER_LEN: usize = 4 + 2 + 2;
+
+/// How to bring programs built for one ISA version up to the next.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct IsaShim {
+    /// The version this shim upgrades from, to `from + 1`.
+    pub from: u16,
+    /// Opcode bytes that were renumbered, as old and new byte. Opcodes left out kept theirs.
+    pub opcodes: &'static [(u8, u8)],
+}
+
+/// Every shim, one per ISA version that programs can still be upgraded from, in version order.
+/// A version without one can't be loaded any more.
+pub const SHIMS: &[IsaShim] = &[
+    // version 2 added calls and locals, without renumbering anything
+    IsaShim {
+        from: 1,
+        opcodes: &[],
+    },
+    // version 3 added bitwise and fixed-point math, likewise
+    IsaShim {
+        from: 2,
+        opcodes: &[],
+    },
+];
+
+/// Errors produced while unpacking a bot.
+#[derive(Debug, PartialEq)]
+pub enum PackageError {
+    /// The package ends before its header does.
+    Truncated,
+    /// Built for an instruction set this sim has no shim for, or one newer than its own.
+    UnsupportedIsa { version: u16 },
+    /// Built against a different memory map or syscalls.
+    UnsupportedAbi { version: u16 },
+}
+
+impl fmt::Display for PackageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PackageError::Truncated => write!(f, "bot package is truncated"),
+            PackageError::UnsupportedIsa { version } => write!(
+                f,
+                "bot was built for ISA version {version}, which this sim (version {ISA_VERSION}) \
+                 can't run"
+            ),
+            PackageError::UnsupportedAbi { version } => write!(
+                f,
+                "bot was built for ABI version {version}, but this sim provides version \
+                 {ABI_VERSION}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PackageError {}
+
+/// A program and the versions it was built for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BotPackage {
+    pub isa_version: u16,
+    pub abi_version: u16,
+    pub code: Vec<u8>,
+}
+
+impl BotPackage {
+    /// Packages code built for this sim's versions.
+    pub fn new(code: Vec<u8>) -> Self {
+        BotPackage {
+            isa_version: ISA_VERSION,
+            abi_version: ABI_VERSION,
+            code,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.code.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.isa_version.to_le_bytes());
+        bytes.extend_from_slice(&self.abi_version.to_le_bytes());
+        bytes.extend_from_slice(&self.code);
+        bytes
+    }
+
+    /// Reads a package, or bare bytecode as a package for the current versions.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PackageError> {
+        if !bytes.starts_with(MAGIC) {
+            return Ok(BotPackage::new(bytes.to_vec()));
+        }
+        let header = bytes.get(..HEADER_LEN).ok_or(PackageError::Truncated)?;
+        Ok(BotPackage {
+            isa_version: u16::from_le_bytes([header[4], header[5]]),
+            abi_version: u16::from_le_bytes([header[6], header[7]]),
+            code: bytes[HEADER_LEN..].to_vec(),
+        })
+    }
+
+    /// Checks the package against this sim and returns its code, upgraded through `shims` to
+    /// the current instruction set if it was built for an older one.
+    pub fn into_code(self, shims: &[IsaShim]) -> Result<Vec<u8>, PackageError> {
+        if self.abi_version != ABI_VERSION {
+            return Err(PackageError::UnsupportedAbi {
+                version: self.abi_version,
+            });
+        }
+        if self.isa_version > ISA_VERSION {
+            return Err(PackageError::UnsupportedIsa {
+                version: self.isa_version,
+            });
+        }
+        let mut code = self.code;
+        for version in self.isa_version..ISA_VERSION {
+            let shim = shims.iter().find(|shim| shim.from == version).ok_or(
+                PackageError::UnsupportedIsa {
+                    version: self.isa_version,
+                },
+            )?;
+            code = shim.upgrade(code);
+        }
+        Ok(code)
+    }
+}
+
+impl IsaShim {
+    /// Renumbers the opcodes of a program, stepping over operands so they're left alone.
+    /// Operand sizes are the current ones, so a shim can't change how long an instruction is;
+    /// jump targets stay where they were.
+    fn upgrade(&self, mut code: Vec<u8>) -> Vec<u8> {
+        let mut pc = 0;
+        while pc < code.len() {
+            if let Some((_, new)) = self.opcodes.iter().find(|(old, _)| *old == code[pc]) {
+                code[pc] = *new;
+            }
+            pc += 1 + Opcode::from_byte(code[pc]).map_or(0, Opcode::operand_size);
+        }
+        code
+    }
+}
+
+/// Reads a package, or bare bytecode, and returns code this sim can run.
+pub fn unpack(bytes: &[u8]) -> Result<Vec<u8>, PackageError> {
+    BotPackage::from_bytes(bytes)?.into_code(SHIMS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unpack_should_accept_bare_code_and_current_packages() {
+        // Arrange
+        let code = vec![Opcode::Push as u8, 1, 0, 0, 0, Opcode::Halt as u8];
+        let package = BotPackage::new(code.clone()).to_bytes();
+
+        // Act
+        let bare = unpack(&code);
+        let packaged = unpack(&package);
+
+        // Assert
+        assert_eq!(bare, Ok(code.clone()));
+        assert_eq!(packaged, Ok(code));
+    }
+
+    #[test]
+    fn unpack_when_built_for_isa_1_should_leave_code_as_it_was() {
+        // Arrange
+        let code = vec![Opcode::Push as u8, 0x23, 0, 0, 0, Opcode::Halt as u8];
+        let package = BotPackage {
+            isa_version: 1,
+            ..BotPackage::new(code.clone())
+        };
+
+        // Act
+        let unpacked = unpack(&package.to_bytes());
+
+        // Assert
+        assert_eq!(unpacked, Ok(code));
+    }
+
+    #[test]
+    fn unpack_when_versions_unknown_should_fail() {
+        // Arrange
+        let newer = BotPackage {
+            isa_version: ISA_VERSION + 1,
+            ..BotPackage::new(vec![])
+        };
+        let other_abi = BotPackage {
+            abi_version: ABI_VERSION + 1,
+            ..BotPackage::new(vec![])
+        };
+
+        // Act
+        let newer = unpack(&newer.to_bytes());
+        let other_abi = unpack(&other_abi.to_bytes());
+        let truncated = unpack(&MAGIC[..]);
+
+        // Assert
+        assert_eq!(
+            newer,
+            Err(PackageError::UnsupportedIsa {
+                version: ISA_VERSION + 1
+            })
+        );
+        assert_eq!(
+            other_abi,
+            Err(PackageError::UnsupportedAbi {
+                version: ABI_VERSION + 1
+            })
+        );
+        assert_eq!(truncated, Err(PackageError::Truncated));
+    }
+
+    #[test]
+    fn into_code_when_older_isa_should_upgrade_through_shims() {
+        // Arrange
+        // pretend the previous version had `Halt` at 0x60, and 0x60 turns up again as a push
+        // operand
+        let shims = [IsaShim {
+            from: ISA_VERSION - 1,
+            opcodes: &[(0x60, Opcode::Halt as u8)],
+        }];
+        let package = BotPackage {
+            isa_version: ISA_VERSION - 1,
+            ..BotPackage::new(vec![Opcode::Push as u8, 0x60, 0, 0, 0, 0x60])
+        };
+        let unshimmed = package.clone();
+
+        // Act
+        let upgraded = package.into_code(&shims);
+        let refused = unshimmed.into_code(&[]);
+
+        // Assert
+        assert_eq!(
+            upgraded,
+            Ok(vec![Opcode::Push as u8, 0x60, 0, 0, 0, Opcode::Halt as u8])
+        );
+        assert_eq!(
+            refused,
+            Err(PackageError::UnsupportedIsa {
+                version: ISA_VERSION - 1
+            })
+        );
+    }
+}
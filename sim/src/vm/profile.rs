@@ -0,0 +1,96 @@
+use super::isa::Opcode;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Name cycles are filed under when they fall outside every function in the table.
+pub const UNKNOWN_FUNCTION: &str = "<unknown>";
+
+/// A function's span of bytecode, as listed in a compiler's function table.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FunctionSymbol {
+    pub name: String,
+    /// Byte address of the first instruction.
+    pub start: u32,
+    /// Byte address just past the last instruction.
+    pub end: u32,
+}
+
+/// Where a program spent its instruction budget.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct VmProfile {
+    /// Instructions executed, by opcode name. Undecodable bytes are named by their hex value.
+    pub instructions: BTreeMap<String, u64>,
+    /// Cycles spent in each function, by name.
+    pub functions: BTreeMap<String, u64>,
+}
+
+impl VmProfile {
+    /// Adds another profile's counts to this one's.
+    pub fn merge(&mut self, other: &VmProfile) {
+        for (name, count) in &other.instructions {
+            *self.instructions.entry(name.clone()).or_default() += count;
+        }
+        for (name, count) in &other.functions {
+            *self.functions.entry(name.clone()).or_default() += count;
+        }
+    }
+}
+
+/// Counts instructions as a program runs, cheaply enough to leave on for a whole match.
+pub struct Profiler<'a> {
+    functions: &'a [FunctionSymbol],
+    opcodes: [u32; 256],
+    /// Cycles per function, in table order, then one for code outside every function.
+    function_cycles: Vec<u32>,
+}
+
+impl<'a> Profiler<'a> {
+    /// Creates a profiler that attributes cycles using the given function table.
+    pub fn new(functions: &'a [FunctionSymbol]) -> Self {
+        Profiler {
+            functions,
+            opcodes: [0; 256],
+            function_cycles: vec![0; functions.len() + 1],
+        }
+    }
+
+    /// Records one cycle spent at `pc`, executing `byte` if the pc was inside the code.
+    pub fn record(&mut self, pc: u32, byte: Option<u8>) {
+        if let Some(byte) = byte {
+            self.opcodes[byte as usize] += 1;
+        }
+        // the first function listed wins where spans overlap
+        let function = self
+            .functions
+            .iter()
+            .position(|symbol| symbol.start <= pc && pc < symbol.end)
+            .unwrap_or(self.functions.len());
+        self.function_cycles[function] += 1;
+    }
+
+    /// Returns the counts recorded so far, leaving out anything that never ran.
+    pub fn finish(&self) -> VmProfile {
+        let mut profile = VmProfile::default();
+        for (byte, count) in self.opcodes.iter().enumerate() {
+            if *count == 0 {
+                continue;
+            }
+            let name = match Opcode::from_byte(byte as u8) {
+                Some(op) => format!("{op:?}"),
+                None => format!("0x{byte:02x}"),
+            };
+            profile.instructions.insert(name, *count as u64);
+        }
+        for (index, count) in self.function_cycles.iter().enumerate() {
+            if *count == 0 {
+                continue;
+            }
+            let name = self
+                .functions
+                .get(index)
+                .map_or(UNKNOWN_FUNCTION, |symbol| symbol.name.as_str());
+            *profile.functions.entry(name.to_string()).or_default() += *count as u64;
+        }
+        profile
+    }
+}
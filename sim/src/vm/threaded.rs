@@ -0,0 +1,480 @@
+//! Threaded code: programs decoded once, up front, into a handler and operand per instruction,
+//! so running them skips decoding and dispatches straight to each instruction's handler.
+//!
+//! Every byte of a program is decoded as if an instruction started there, since a jump may
+//! land anywhere, so a [`ThreadedProgram`] behaves exactly like its bytecode under [`run`],
+//! faults included. The conformance suite checks both against the same goldens.
+//!
+//! [`run`]: super::run
+
+use super::fixed;
+use super::isa::Opcode;
+use super::{
+    Flow, RunReport, Stack, VmFault, VmIo, call, enter, load, load_local, ret, run_with, store,
+    store_local,
+};
+use crate::state::VmState;
+use std::marker::PhantomData;
+
+/// Runs one instruction, given its operand and address. The program counter has already
+/// moved on to the next instruction.
+type Handler<I> = fn(&mut VmState, u32, u32, &mut I) -> Result<Flow, VmFault>;
+
+/// Handler index for an instruction cut off by the end of the program, after the one per
+/// opcode byte.
+const TRUNCATED: u16 = 256;
+
+/// One decoded instruction.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Decoded {
+    handler: u16,
+    operand: u32,
+    /// Where execution carries on, or the instruction's own address if it can only fault.
+    next: u32,
+}
+
+/// A program decoded for [`run`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ThreadedProgram {
+    /// One entry per byte of bytecode.
+    decoded: Vec<Decoded>,
+}
+
+impl ThreadedProgram {
+    /// Decodes a program. Never fails: bytes that don't decode become instructions that fault
+    /// as the interpreter would.
+    pub fn compile(code: &[u8]) -> Self {
+        let decoded = (0..code.len())
+            .map(|pc| {
+                let byte = code[pc];
+                let Some(op) = Opcode::from_byte(byte) else {
+                    return Decoded {
+                        handler: byte as u16,
+                        operand: 0,
+                        next: pc as u32,
+                    };
+                };
+                let size = op.operand_size();
+                let Some(bytes) = code.get(pc + 1..pc + 1 + size) else {
+                    return Decoded {
+                        handler: TRUNCATED,
+                        operand: 0,
+                        next: pc as u32,
+                    };
+                };
+                let mut word = [0; 4];
+                word[..size].copy_from_slice(bytes);
+                Decoded {
+                    handler: byte as u16,
+                    operand: u32::from_le_bytes(word),
+                    next: (pc + 1 + size) as u32,
+                }
+            })
+            .collect();
+        ThreadedProgram { decoded }
+    }
+
+    fn execute<I: VmIo>(&self, state: &mut VmState, io: &mut I) -> Result<Flow, VmFault> {
+        let pc = state.pc;
+        let decoded = self
+            .decoded
+            .get(pc as usize)
+            .ok_or(VmFault::PcOutOfRange { pc })?;
+        state.pc = decoded.next;
+        Handlers::<I>::TABLE[decoded.handler as usize](state, decoded.operand, pc, io)
+    }
+}
+
+/// Runs a decoded program for at most `budget` instructions, exactly as [`run`](super::run)
+/// runs its bytecode.
+pub fn run(
+    state: &mut VmState,
+    program: &ThreadedProgram,
+    budget: u32,
+    io: &mut impl VmIo,
+) -> RunReport {
+    run_with(state, budget, io, |state, io| program.execute(state, io))
+}
+
+/// The handlers for one kind of I/O, indexed by opcode byte, then [`TRUNCATED`].
+struct Handlers<I>(PhantomData<I>);
+
+impl<I: VmIo> Handlers<I> {
+    const TABLE: [Handler<I>; 257] = {
+        let mut table = [invalid as Handler<I>; 257];
+        let mut byte = 0;
+        while byte < 256 {
+            table[byte] = handler(byte as u8);
+            byte += 1;
+        }
+        table[TRUNCATED as usize] = truncated;
+        table
+    };
+}
+
+const fn handler<I: VmIo>(byte: u8) -> Handler<I> {
+    let Some(op) = Opcode::from_byte(byte) else {
+        return invalid;
+    };
+    match op {
+        Opcode::Nop => nop,
+        Opcode::Push => push,
+        Opcode::Pop => pop,
+        Opcode::Dup => dup,
+        Opcode::Swap => swap,
+        Opcode::Over => over,
+        Opcode::Add => add,
+        Opcode::Sub => sub,
+        Opcode::Mul => mul,
+        Opcode::Div => div,
+        Opcode::Mod => rem,
+        Opcode::FixMul => fix_mul,
+        Opcode::FixDiv => fix_div,
+        Opcode::Neg => neg,
+        Opcode::Eq => eq,
+        Opcode::Ne => ne,
+        Opcode::Lt => lt,
+        Opcode::Le => le,
+        Opcode::Gt => gt,
+        Opcode::Ge => ge,
+        Opcode::Not => not,
+        Opcode::And => and,
+        Opcode::Or => or,
+        Opcode::Xor => xor,
+        Opcode::Shl => shl,
+        Opcode::Shr => shr,
+        Opcode::Sar => sar,
+        Opcode::Sqrt => sqrt,
+        Opcode::Atan2 => atan2,
+        Opcode::Jmp => jmp,
+        Opcode::Jz => jz,
+        Opcode::Jnz => jnz,
+        Opcode::Call => call_function,
+        Opcode::Ret => return_from_function,
+        Opcode::Enter => enter_function,
+        Opcode::Load => load_word,
+        Opcode::Store => store_word,
+        Opcode::LoadLocal => load_local_word,
+        Opcode::StoreLocal => store_local_word,
+        Opcode::Syscall => syscall,
+        Opcode::Yield => yield_tick,
+        Opcode::Halt => halt,
+    }
+}
+
+fn invalid<I: VmIo>(_: &mut VmState, _: u32, pc: u32, _: &mut I) -> Result<Flow, VmFault> {
+    Err(VmFault::InvalidOpcode { pc })
+}
+
+fn truncated<I: VmIo>(_: &mut VmState, _: u32, pc: u32, _: &mut I) -> Result<Flow, VmFault> {
+    Err(VmFault::PcOutOfRange { pc })
+}
+
+fn nop<I: VmIo>(_: &mut VmState, _: u32, _: u32, _: &mut I) -> Result<Flow, VmFault> {
+    Ok(Flow::Continue)
+}
+
+fn push<I: VmIo>(state: &mut VmState, operand: u32, _: u32, _: &mut I) -> Result<Flow, VmFault> {
+    Stack { state }.push(operand)?;
+    Ok(Flow::Continue)
+}
+
+fn pop<I: VmIo>(state: &mut VmState, _: u32, _: u32, _: &mut I) -> Result<Flow, VmFault> {
+    Stack { state }.pop()?;
+    Ok(Flow::Continue)
+}
+
+fn dup<I: VmIo>(state: &mut VmState, _: u32, _: u32, _: &mut I) -> Result<Flow, VmFault> {
+    let mut stack = Stack { state };
+    let a = stack.pop()?;
+    stack.push(a)?;
+    stack.push(a)?;
+    Ok(Flow::Continue)
+}
+
+fn swap<I: VmIo>(state: &mut VmState, _: u32, _: u32, _: &mut I) -> Result<Flow, VmFault> {
+    let mut stack = Stack { state };
+    let b = stack.pop()?;
+    let a = stack.pop()?;
+    stack.push(b)?;
+    stack.push(a)?;
+    Ok(Flow::Continue)
+}
+
+fn over<I: VmIo>(state: &mut VmState, _: u32, _: u32, _: &mut I) -> Result<Flow, VmFault> {
+    let mut stack = Stack { state };
+    let b = stack.pop()?;
+    let a = stack.pop()?;
+    stack.push(a)?;
+    stack.push(b)?;
+    stack.push(a)?;
+    Ok(Flow::Continue)
+}
+
+// binary operations share the same pop/push shape
+macro_rules! binary {
+    ($($name:ident => $f:expr),* $(,)?) => {$(
+        fn $name<I: VmIo>(state: &mut VmState, _: u32, _: u32, _: &mut I) -> Result<Flow, VmFault> {
+            let mut stack = Stack { state };
+            let b = stack.pop()?;
+            let a = stack.pop()?;
+            let f: fn(u32, u32) -> u32 = $f;
+            stack.push(f(a, b))?;
+            Ok(Flow::Continue)
+        }
+    )*};
+}
+
+binary! {
+    add => |a, b| a.wrapping_add(b),
+    sub => |a, b| a.wrapping_sub(b),
+    mul => |a, b| a.wrapping_mul(b),
+    eq => |a, b| (a == b) as u32,
+    ne => |a, b| (a != b) as u32,
+    lt => |a, b| ((a as i32) < (b as i32)) as u32,
+    le => |a, b| ((a as i32) <= (b as i32)) as u32,
+    gt => |a, b| ((a as i32) > (b as i32)) as u32,
+    ge => |a, b| ((a as i32) >= (b as i32)) as u32,
+    fix_mul => fixed::mul,
+    and => |a, b| a & b,
+    or => |a, b| a | b,
+    xor => |a, b| a ^ b,
+    shl => |a, b| a.wrapping_shl(b),
+    shr => |a, b| a.wrapping_shr(b),
+    sar => |a, b| (a as i32).wrapping_shr(b) as u32,
+    atan2 => fixed::atan2,
+}
+
+/// Pops a divisor, then a dividend, and pushes `f` of them, faulting on division by zero.
+fn divide(state: &mut VmState, pc: u32, f: fn(i32, i32) -> i32) -> Result<Flow, VmFault> {
+    let mut stack = Stack { state };
+    let b = stack.pop()? as i32;
+    let a = stack.pop()? as i32;
+    if b == 0 {
+        return Err(VmFault::DivisionByZero { pc });
+    }
+    stack.push(f(a, b) as u32)?;
+    Ok(Flow::Continue)
+}
+
+fn div<I: VmIo>(state: &mut VmState, _: u32, pc: u32, _: &mut I) -> Result<Flow, VmFault> {
+    divide(state, pc, i32::wrapping_div)
+}
+
+fn rem<I: VmIo>(state: &mut VmState, _: u32, pc: u32, _: &mut I) -> Result<Flow, VmFault> {
+    divide(state, pc, i32::wrapping_rem)
+}
+
+fn fix_div<I: VmIo>(state: &mut VmState, _: u32, pc: u32, _: &mut I) -> Result<Flow, VmFault> {
+    let mut stack = Stack { state };
+    let b = stack.pop()?;
+    let a = stack.pop()?;
+    let quotient = fixed::div(a, b).ok_or(VmFault::DivisionByZero { pc })?;
+    stack.push(quotient)?;
+    Ok(Flow::Continue)
+}
+
+fn neg<I: VmIo>(state: &mut VmState, _: u32, _: u32, _: &mut I) -> Result<Flow, VmFault> {
+    let mut stack = Stack { state };
+    let a = stack.pop()?;
+    stack.push(a.wrapping_neg())?;
+    Ok(Flow::Continue)
+}
+
+fn not<I: VmIo>(state: &mut VmState, _: u32, _: u32, _: &mut I) -> Result<Flow, VmFault> {
+    let mut stack = Stack { state };
+    let a = stack.pop()?;
+    stack.push((a == 0) as u32)?;
+    Ok(Flow::Continue)
+}
+
+fn sqrt<I: VmIo>(state: &mut VmState, _: u32, _: u32, _: &mut I) -> Result<Flow, VmFault> {
+    let mut stack = Stack { state };
+    let a = stack.pop()?;
+    stack.push(fixed::sqrt(a))?;
+    Ok(Flow::Continue)
+}
+
+fn jmp<I: VmIo>(state: &mut VmState, operand: u32, _: u32, _: &mut I) -> Result<Flow, VmFault> {
+    state.pc = operand;
+    Ok(Flow::Continue)
+}
+
+fn jz<I: VmIo>(state: &mut VmState, operand: u32, _: u32, _: &mut I) -> Result<Flow, VmFault> {
+    let value = Stack { state }.pop()?;
+    if value == 0 {
+        state.pc = operand;
+    }
+    Ok(Flow::Continue)
+}
+
+fn jnz<I: VmIo>(state: &mut VmState, operand: u32, _: u32, _: &mut I) -> Result<Flow, VmFault> {
+    let value = Stack { state }.pop()?;
+    if value != 0 {
+        state.pc = operand;
+    }
+    Ok(Flow::Continue)
+}
+
+fn call_function<I: VmIo>(
+    state: &mut VmState,
+    operand: u32,
+    _: u32,
+    _: &mut I,
+) -> Result<Flow, VmFault> {
+    call(state, operand)?;
+    Ok(Flow::Continue)
+}
+
+fn return_from_function<I: VmIo>(
+    state: &mut VmState,
+    operand: u32,
+    _: u32,
+    _: &mut I,
+) -> Result<Flow, VmFault> {
+    ret(state, operand)?;
+    Ok(Flow::Continue)
+}
+
+fn enter_function<I: VmIo>(
+    state: &mut VmState,
+    operand: u32,
+    _: u32,
+    _: &mut I,
+) -> Result<Flow, VmFault> {
+    enter(state, operand)?;
+    Ok(Flow::Continue)
+}
+
+fn load_local_word<I: VmIo>(
+    state: &mut VmState,
+    operand: u32,
+    _: u32,
+    _: &mut I,
+) -> Result<Flow, VmFault> {
+    load_local(state, operand)?;
+    Ok(Flow::Continue)
+}
+
+fn store_local_word<I: VmIo>(
+    state: &mut VmState,
+    operand: u32,
+    _: u32,
+    _: &mut I,
+) -> Result<Flow, VmFault> {
+    store_local(state, operand)?;
+    Ok(Flow::Continue)
+}
+
+fn load_word<I: VmIo>(state: &mut VmState, _: u32, _: u32, io: &mut I) -> Result<Flow, VmFault> {
+    let address = Stack { state }.pop()?;
+    let value = load(state, io, address)?;
+    Stack { state }.push(value)?;
+    Ok(Flow::Continue)
+}
+
+fn store_word<I: VmIo>(state: &mut VmState, _: u32, _: u32, io: &mut I) -> Result<Flow, VmFault> {
+    let mut stack = Stack { state };
+    let address = stack.pop()?;
+    let value = stack.pop()?;
+    store(state, io, address, value)?;
+    Ok(Flow::Continue)
+}
+
+fn syscall<I: VmIo>(
+    state: &mut VmState,
+    operand: u32,
+    _: u32,
+    io: &mut I,
+) -> Result<Flow, VmFault> {
+    io.syscall(operand as u8, &mut Stack { state })?;
+    match io.syscall_cost() {
+        0 => Ok(Flow::Continue),
+        extra => Ok(Flow::Charge(extra)),
+    }
+}
+
+fn yield_tick<I: VmIo>(_: &mut VmState, _: u32, _: u32, _: &mut I) -> Result<Flow, VmFault> {
+    Ok(Flow::Yield)
+}
+
+fn halt<I: VmIo>(_: &mut VmState, _: u32, _: u32, _: &mut I) -> Result<Flow, VmFault> {
+    Ok(Flow::Halt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm;
+    use proptest::prelude::*;
+
+    /// I/O with a single read-write register at 0x8000 and an "add" syscall costing two extra
+    /// cycles.
+    #[derive(Default, Debug, PartialEq)]
+    struct TestIo {
+        register: u32,
+    }
+
+    impl VmIo for TestIo {
+        fn read(&mut self, address: u32) -> Option<u32> {
+            (address == 0x8000).then_some(self.register)
+        }
+
+        fn write(&mut self, address: u32, value: u32) -> bool {
+            if address == 0x8000 {
+                self.register = value;
+            }
+            address == 0x8000
+        }
+
+        fn syscall(&mut self, number: u8, stack: &mut Stack) -> Result<(), VmFault> {
+            if number != 7 {
+                return Err(VmFault::BadSyscall { number });
+            }
+            let b = stack.pop()?;
+            let a = stack.pop()?;
+            stack.push(a.wrapping_add(b))
+        }
+
+        fn syscall_cost(&mut self) -> u32 {
+            2
+        }
+    }
+
+    fn opcode_bytes() -> Vec<u8> {
+        (0..=u8::MAX)
+            .filter(|byte| Opcode::from_byte(*byte).is_some())
+            .collect()
+    }
+
+    proptest! {
+        #[test]
+        fn run_should_match_interpreter_on_random_bytecode(
+            code in prop::collection::vec(
+                prop_oneof![
+                    // plenty of real opcodes, so programs get somewhere before faulting
+                    prop::sample::select(opcode_bytes()),
+                    any::<u8>(),
+                ],
+                0..128,
+            ),
+            budgets in prop::collection::vec(0u32..200, 1..5),
+        ) {
+            // Arrange
+            let program = ThreadedProgram::compile(&code);
+            let (mut interpreted, mut threaded) = (VmState::new(), VmState::new());
+            let (mut interpreted_io, mut threaded_io) = (TestIo::default(), TestIo::default());
+
+            for budget in budgets {
+                // Act
+                let expected = vm::run(&mut interpreted, &code, budget, &mut interpreted_io);
+                let actual = run(&mut threaded, &program, budget, &mut threaded_io);
+
+                // Assert
+                prop_assert_eq!(actual, expected);
+                prop_assert_eq!(&threaded, &interpreted);
+                prop_assert_eq!(&threaded_io, &interpreted_io);
+            }
+        }
+    }
+}
@@ -0,0 +1,399 @@
+//! Static checks run on a program when it's loaded, so bytecode that's sure to fault is turned
+//! away up front instead of stopping a tank mid-match.
+//!
+//! The verifier decodes every instruction and checks that jumps land on one, that constant
+//! addresses read from are mapped and that syscalls exist. It then follows the stack depth
+//! from the start of the program: wherever every path to an instruction agrees on the depth,
+//! as in straight-line code and in loops that leave the stack as they found it, the
+//! instruction is checked against under- and overflow. Where paths disagree, and from a call
+//! on, since functions are called from anywhere and leave what they like, the depth isn't
+//! provable and the check is left to run time, so a program that passes can still fault.
+
+use super::isa::Opcode;
+use std::collections::VecDeque;
+use std::fmt;
+
+/// What a program is checked against: the VM's sizes and the host's memory map and syscalls.
+#[derive(Copy, Clone, Debug)]
+pub struct Platform {
+    pub memory_words: u32,
+    pub stack_words: u32,
+    /// Whether a word outside of RAM is mapped for reading.
+    pub readable: fn(u32) -> bool,
+    /// The words a syscall pops and then pushes, or `None` if there's no such syscall.
+    pub syscall_effect: fn(u8) -> Option<(u32, u32)>,
+}
+
+/// Why a program was rejected. `pc` is the address of the offending instruction.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VerifyError {
+    InvalidOpcode {
+        pc: u32,
+    },
+    /// The program ends partway through an instruction's operand.
+    TruncatedInstruction {
+        pc: u32,
+    },
+    /// A jump to past the end of the program or into the middle of an instruction.
+    BadJumpTarget {
+        pc: u32,
+        target: u32,
+    },
+    /// Execution can carry on past the last instruction.
+    RunsOffEnd {
+        pc: u32,
+    },
+    /// A load from a constant address with neither RAM nor anything readable there.
+    UnmappedRead {
+        pc: u32,
+        address: u32,
+    },
+    BadSyscall {
+        pc: u32,
+        number: u8,
+    },
+    StackUnderflow {
+        pc: u32,
+    },
+    StackOverflow {
+        pc: u32,
+    },
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::InvalidOpcode { pc } => write!(f, "invalid opcode at {pc}"),
+            VerifyError::TruncatedInstruction { pc } => {
+                write!(
+                    f,
+                    "instruction at {pc} is cut off by the end of the program"
+                )
+            }
+            VerifyError::BadJumpTarget { pc, target } => {
+                write!(f, "jump at {pc} doesn't land on an instruction: {target}")
+            }
+            VerifyError::RunsOffEnd { pc } => {
+                write!(f, "execution runs off the end of the program after {pc}")
+            }
+            VerifyError::UnmappedRead { pc, address } => {
+                write!(f, "load at {pc} reads unmapped address {address:#x}")
+            }
+            VerifyError::BadSyscall { pc, number } => {
+                write!(f, "syscall at {pc} calls unknown service {number}")
+            }
+            VerifyError::StackUnderflow { pc } => write!(f, "stack underflows at {pc}"),
+            VerifyError::StackOverflow { pc } => write!(f, "stack overflows at {pc}"),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+#[derive(Copy, Clone)]
+struct Instruction {
+    pc: u32,
+    op: Opcode,
+    operand: u32,
+}
+
+/// What's known of the stack depth on reaching an instruction.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Depth {
+    Unreached,
+    Known(u32),
+    /// Paths to the instruction disagree.
+    Unknown,
+}
+
+impl Depth {
+    fn join(self, other: Depth) -> Depth {
+        match (self, other) {
+            (Depth::Unreached, depth) | (depth, Depth::Unreached) => depth,
+            (Depth::Known(a), Depth::Known(b)) if a == b => Depth::Known(a),
+            _ => Depth::Unknown,
+        }
+    }
+}
+
+/// Checks a program before it's run on `platform`, returning the first problem found.
+pub fn verify(code: &[u8], platform: &Platform) -> Result<(), VerifyError> {
+    let instructions = decode(code)?;
+    let index_of = |target: u32| {
+        instructions
+            .binary_search_by_key(&target, |instruction| instruction.pc)
+            .ok()
+    };
+
+    let mut jump_targets = Vec::new();
+    for instruction in &instructions {
+        let Instruction { pc, op, operand } = *instruction;
+        if op.is_jump() {
+            index_of(operand).ok_or(VerifyError::BadJumpTarget {
+                pc,
+                target: operand,
+            })?;
+            jump_targets.push(operand);
+        }
+        if op == Opcode::Syscall {
+            let number = operand as u8;
+            (platform.syscall_effect)(number).ok_or(VerifyError::BadSyscall { pc, number })?;
+        }
+    }
+    jump_targets.sort_unstable();
+
+    // `PUSH address; LOAD` always reads the same word, unless something jumps to the load
+    for pair in instructions.windows(2) {
+        let [push, load] = pair else { unreachable!() };
+        if push.op == Opcode::Push
+            && load.op == Opcode::Load
+            && jump_targets.binary_search(&load.pc).is_err()
+        {
+            let address = push.operand;
+            if address >= platform.memory_words && !(platform.readable)(address) {
+                return Err(VerifyError::UnmappedRead {
+                    pc: load.pc,
+                    address,
+                });
+            }
+        }
+    }
+
+    check_stack(&instructions, platform, index_of)
+}
+
+fn decode(code: &[u8]) -> Result<Vec<Instruction>, VerifyError> {
+    let mut instructions = Vec::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        let at = pc as u32;
+        let op = Opcode::from_byte(code[pc]).ok_or(VerifyError::InvalidOpcode { pc: at })?;
+        let size = op.operand_size();
+        let bytes = code
+            .get(pc + 1..pc + 1 + size)
+            .ok_or(VerifyError::TruncatedInstruction { pc: at })?;
+        let mut word = [0; 4];
+        word[..size].copy_from_slice(bytes);
+        instructions.push(Instruction {
+            pc: at,
+            op,
+            operand: u32::from_le_bytes(word),
+        });
+        pc += 1 + size;
+    }
+    Ok(instructions)
+}
+
+/// Follows the stack depth through every reachable instruction, joining it where paths meet,
+/// then checks each instruction whose depth came out known. Each depth only ever goes from
+/// unreached to known to unknown, so this settles.
+fn check_stack(
+    instructions: &[Instruction],
+    platform: &Platform,
+    index_of: impl Fn(u32) -> Option<usize>,
+) -> Result<(), VerifyError> {
+    if instructions.is_empty() {
+        return Err(VerifyError::RunsOffEnd { pc: 0 });
+    }
+    let mut depths = vec![Depth::Unreached; instructions.len()];
+    depths[0] = Depth::Known(0);
+    let mut pending = VecDeque::from([0]);
+
+    let effect = |instruction: &Instruction| match instruction.op {
+        Opcode::Syscall => (platform.syscall_effect)(instruction.operand as u8).unwrap_or((0, 0)),
+        op => op.stack_effect(),
+    };
+
+    while let Some(index) = pending.pop_front() {
+        let Instruction { pc, op, operand } = instructions[index];
+        let (pops, pushes) = effect(&instructions[index]);
+        // a depth that's out of bounds here is caught below, if it's still known once settled
+        let after = match depths[index] {
+            _ if op == Opcode::Call => Depth::Unknown,
+            Depth::Known(depth) => depth
+                .checked_sub(pops)
+                .map_or(Depth::Unknown, |popped| Depth::Known(popped + pushes)),
+            depth => depth,
+        };
+
+        let mut successors = Vec::with_capacity(2);
+        if op.is_jump() {
+            successors.extend(index_of(operand));
+        }
+        if op.falls_through() {
+            if index + 1 == instructions.len() {
+                return Err(VerifyError::RunsOffEnd { pc });
+            }
+            successors.push(index + 1);
+        }
+        for successor in successors {
+            let joined = depths[successor].join(after);
+            if joined != depths[successor] {
+                depths[successor] = joined;
+                pending.push_back(successor);
+            }
+        }
+    }
+
+    for (instruction, depth) in instructions.iter().zip(depths) {
+        let Depth::Known(depth) = depth else {
+            continue;
+        };
+        let pc = instruction.pc;
+        let (pops, pushes) = effect(instruction);
+        let popped = depth
+            .checked_sub(pops)
+            .ok_or(VerifyError::StackUnderflow { pc })?;
+        if popped + pushes > platform.stack_words {
+            return Err(VerifyError::StackOverflow { pc });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::isa::Assembler;
+
+    fn platform() -> Platform {
+        Platform {
+            memory_words: 16,
+            stack_words: 4,
+            readable: |address| address == 0x8000,
+            syscall_effect: |number| (number == 7).then_some((2, 1)),
+        }
+    }
+
+    #[test]
+    fn verify_should_accept_loops_that_keep_the_stack_balanced() {
+        // Arrange
+        // counts down from 3 in RAM, reading the register every time round
+        let mut asm = Assembler::new();
+        let (start, end) = (asm.label(), asm.label());
+        asm.push(3)
+            .bind(start)
+            .op(Opcode::Dup)
+            .jump(Opcode::Jz, end);
+        asm.push(0x8000)
+            .op(Opcode::Load)
+            .push(0)
+            .op(Opcode::Store)
+            .push(1)
+            .op(Opcode::Sub)
+            .op(Opcode::Yield)
+            .jump(Opcode::Jmp, start);
+        asm.bind(end).push(2).syscall(7).op(Opcode::Halt);
+        let code = asm.finish();
+
+        // Act
+        let result = verify(&code, &platform());
+
+        // Assert
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn verify_when_program_is_sure_to_fault_should_reject_it() {
+        // Arrange
+        let cases: [(Vec<u8>, VerifyError); 10] = [
+            (vec![], VerifyError::RunsOffEnd { pc: 0 }),
+            (vec![0xee], VerifyError::InvalidOpcode { pc: 0 }),
+            (
+                vec![Opcode::Push as u8, 1, 0],
+                VerifyError::TruncatedInstruction { pc: 0 },
+            ),
+            (
+                vec![Opcode::Jmp as u8, 2, 0, 0, 0, Opcode::Halt as u8],
+                VerifyError::BadJumpTarget { pc: 0, target: 2 },
+            ),
+            (
+                vec![Opcode::Call as u8, 9, 0, 0, 0, Opcode::Halt as u8],
+                VerifyError::BadJumpTarget { pc: 0, target: 9 },
+            ),
+            (
+                Assembler::new().push(1).op(Opcode::Pop).finish(),
+                VerifyError::RunsOffEnd { pc: 5 },
+            ),
+            (
+                Assembler::new()
+                    .push(0x9000)
+                    .op(Opcode::Load)
+                    .op(Opcode::Halt)
+                    .finish(),
+                VerifyError::UnmappedRead {
+                    pc: 5,
+                    address: 0x9000,
+                },
+            ),
+            (
+                Assembler::new().syscall(3).op(Opcode::Halt).finish(),
+                VerifyError::BadSyscall { pc: 0, number: 3 },
+            ),
+            (
+                Assembler::new()
+                    .push(1)
+                    .op(Opcode::Add)
+                    .op(Opcode::Halt)
+                    .finish(),
+                VerifyError::StackUnderflow { pc: 5 },
+            ),
+            (
+                Assembler::new()
+                    .push(1)
+                    .push(2)
+                    .push(3)
+                    .push(4)
+                    .push(5)
+                    .op(Opcode::Halt)
+                    .finish(),
+                VerifyError::StackOverflow { pc: 20 },
+            ),
+        ];
+
+        for (code, expected) in cases {
+            // Act
+            let result = verify(&code, &platform());
+
+            // Assert
+            assert_eq!(result, Err(expected), "{code:?}");
+        }
+    }
+
+    #[test]
+    fn verify_should_accept_calls_to_functions_that_take_arguments() {
+        // Arrange
+        let mut asm = Assembler::new();
+        let sum = asm.label();
+        asm.push(2).push(3).jump(Opcode::Call, sum).op(Opcode::Halt);
+        asm.bind(sum)
+            .op_u8(Opcode::Enter, 2)
+            .op_u8(Opcode::LoadLocal, 0)
+            .op_u8(Opcode::LoadLocal, 1)
+            .op(Opcode::Add)
+            .op_u8(Opcode::Ret, 1);
+        let code = asm.finish();
+
+        // Act
+        let result = verify(&code, &platform());
+
+        // Assert
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn verify_when_depth_differs_between_paths_should_leave_it_to_run_time() {
+        // Arrange
+        // pushes once more every time round, which overflows, but only at run time
+        let mut asm = Assembler::new();
+        let start = asm.label();
+        asm.bind(start).push(1).jump(Opcode::Jmp, start);
+        let code = asm.finish();
+
+        // Act
+        let result = verify(&code, &platform());
+
+        // Assert
+        assert_eq!(result, Ok(()));
+    }
+}
@@ -0,0 +1,80 @@
+//! Browser bindings: [`Session`] exported through `wasm-bindgen`, so a web page can run
+//! matches, e.g. to play back shared replays or host a bot ladder.
+//!
+//! Everything structured crosses as JSON strings, as it does for [`Session`], and failures are
+//! thrown as JavaScript `Error`s carrying the [`SimError`](crate::error::SimError)'s message.
+
+use crate::session::Session;
+use wasm_bindgen::prelude::*;
+
+/// A match, driven from JavaScript.
+#[wasm_bindgen]
+pub struct Simulation {
+    session: Session,
+}
+
+#[wasm_bindgen]
+impl Simulation {
+    /// Starts an empty match from a JSON `SimConfig`, or the defaults if `config_json` is
+    /// empty.
+    #[wasm_bindgen(constructor)]
+    pub fn new(config_json: &str, seed: u64) -> Result<Simulation, JsError> {
+        let session = Session::new(config_json, seed)?;
+        Ok(Simulation { session })
+    }
+
+    /// Starts the match over, empty, with a new seed.
+    pub fn reset(&mut self, seed: u64) {
+        self.session.reset(seed);
+    }
+
+    /// Spawns a tank from a JSON `TankSpawn` and returns its ID.
+    #[wasm_bindgen(js_name = spawnTank)]
+    pub fn spawn_tank(&mut self, spawn_json: &str) -> Result<u32, JsError> {
+        Ok(self.session.spawn_tank(spawn_json)?)
+    }
+
+    /// Hands a tank to one of the built-in bots, by name.
+    #[wasm_bindgen(js_name = loadBot)]
+    pub fn load_bot(&mut self, tank_id: u32, name: &str) -> Result<(), JsError> {
+        Ok(self.session.load_bot(tank_id, name)?)
+    }
+
+    /// Loads assembled bytecode, bare or packaged, into a tank's VM.
+    #[wasm_bindgen(js_name = loadProgram)]
+    pub fn load_program(&mut self, tank_id: u32, code: &[u8]) -> Result<(), JsError> {
+        Ok(self.session.load_program(tank_id, code)?)
+    }
+
+    /// Queues a JSON array of commands to apply at the start of the next tick.
+    pub fn act(&mut self, commands_json: &str) -> Result<(), JsError> {
+        Ok(self.session.act(commands_json)?)
+    }
+
+    /// Runs some ticks and returns every event they produced, as a JSON array.
+    pub fn step(&mut self, ticks: u32) -> String {
+        self.session.step(ticks)
+    }
+
+    /// Returns the whole match state as JSON.
+    #[wasm_bindgen(js_name = getState)]
+    pub fn get_state(&self) -> String {
+        self.session.get_state()
+    }
+
+    /// Returns what a tank knows, as JSON `{ tank, sensors }`.
+    pub fn observe(&self, tank_id: u32) -> Result<String, JsError> {
+        Ok(self.session.observe(tank_id)?)
+    }
+
+    /// Returns whether the match is over.
+    #[wasm_bindgen(js_name = isDone)]
+    pub fn is_done(&self) -> bool {
+        self.session.is_done()
+    }
+
+    /// Returns the current tick.
+    pub fn tick(&self) -> u64 {
+        self.session.tick()
+    }
+}
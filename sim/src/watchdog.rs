@@ -0,0 +1,232 @@
+use crate::events::SimEvent;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Whether this target has a clock to time ticks with. wasm32 doesn't: `Instant::now` panics
+/// there.
+pub const HAS_CLOCK: bool = !cfg!(target_arch = "wasm32");
+
+/// How long a tick may take before the engine reports it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WatchdogConfig {
+    /// Wall-clock budget for one tick, in microseconds.
+    pub budget_micros: u64,
+    /// Whether to skip optional work (debug drawing and VM profiling) on the tick after an
+    /// overrun, to give the match a chance to catch up.
+    #[serde(default)]
+    pub throttle: bool,
+}
+
+/// A part of the tick that the watchdog times separately.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// Applying commands and running programs, bots and networks.
+    Vm,
+    /// Driving tanks and moving them along their velocities.
+    Integrate,
+    /// Finding which tanks might be touching.
+    BroadPhase,
+    /// Resolving what actually touches, including bullets hitting hulls.
+    NarrowPhase,
+    /// Explosions, respawns, triggers, modes, sensors and debug drawing.
+    Events,
+}
+
+impl Phase {
+    const COUNT: usize = 5;
+
+    #[cfg(feature = "tracing")]
+    fn span(self, tick: u64) -> tracing::Span {
+        match self {
+            Phase::Vm => tracing::info_span!("vm", tick),
+            Phase::Integrate => tracing::info_span!("integrate", tick),
+            Phase::BroadPhase => tracing::info_span!("broad_phase", tick),
+            Phase::NarrowPhase => tracing::info_span!("narrow_phase", tick),
+            Phase::Events => tracing::info_span!("events", tick),
+        }
+    }
+}
+
+/// Where a tick's wall-clock time went, in microseconds.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseTimings {
+    pub vm_micros: u64,
+    pub integrate_micros: u64,
+    pub broad_phase_micros: u64,
+    pub narrow_phase_micros: u64,
+    pub events_micros: u64,
+}
+
+impl PhaseTimings {
+    pub fn total_micros(&self) -> u64 {
+        self.vm_micros
+            + self.integrate_micros
+            + self.broad_phase_micros
+            + self.narrow_phase_micros
+            + self.events_micros
+    }
+}
+
+/// Times each phase of a tick, reporting ticks that go over budget.
+///
+/// Timings are wall-clock, so they differ from run to run; they only ever end up in events
+/// and diagnostics and never feed back into the state. The clock is only read while timing is
+/// on, which it is whenever there's a budget. Without a clock (see [`HAS_CLOCK`]) timing never
+/// comes on; config validation rejects budgets there. With the `tracing` feature, each phase is
+/// also entered as a span.
+#[derive(Debug, Default)]
+pub struct Watchdog {
+    config: Option<WatchdogConfig>,
+    timing: bool,
+    current: Option<(Phase, Instant)>,
+    phases: [Duration; Phase::COUNT],
+    last_tick: Option<PhaseTimings>,
+    throttled: bool,
+    tick: u64,
+    /// The phase's span, entered by hand rather than through a guard, so the watchdog and its
+    /// engine can still move between threads.
+    #[cfg(feature = "tracing")]
+    span: Option<tracing::Span>,
+}
+
+impl Watchdog {
+    pub fn new(config: Option<WatchdogConfig>) -> Self {
+        Watchdog {
+            config,
+            ..Watchdog::default()
+        }
+    }
+
+    /// Turns timing on or off even without a budget, e.g. for a performance overlay.
+    pub fn set_timing(&mut self, enabled: bool) {
+        self.timing = enabled;
+        if !self.is_timing() {
+            self.last_tick = None;
+        }
+    }
+
+    fn is_timing(&self) -> bool {
+        HAS_CLOCK && (self.timing || self.config.is_some())
+    }
+
+    /// Starts a tick.
+    pub fn begin_tick(&mut self, tick: u64) {
+        self.tick = tick;
+        self.phases = [Duration::ZERO; Phase::COUNT];
+        self.current = None;
+    }
+
+    /// Moves on to a phase, charging the time since the last one began to it.
+    pub fn enter(&mut self, phase: Phase) {
+        #[cfg(feature = "tracing")]
+        {
+            self.exit_span();
+            let span = phase.span(self.tick);
+            span.with_subscriber(|(id, dispatch)| dispatch.enter(id));
+            self.span = Some(span);
+        }
+        if !self.is_timing() {
+            return;
+        }
+        let now = Instant::now();
+        if let Some((previous, started)) = self.current {
+            self.phases[previous as usize] += now - started;
+        }
+        self.current = Some((phase, now));
+    }
+
+    /// Finishes the tick, reporting a [`SimEvent::SimOverrun`] if it went over budget.
+    pub fn end_tick(&mut self, events: &mut Vec<SimEvent>) {
+        #[cfg(feature = "tracing")]
+        self.exit_span();
+        if let Some((previous, started)) = self.current.take() {
+            self.phases[previous as usize] += started.elapsed();
+        } else {
+            return;
+        }
+        let micros = |phase: Phase| self.phases[phase as usize].as_micros() as u64;
+        let timings = PhaseTimings {
+            vm_micros: micros(Phase::Vm),
+            integrate_micros: micros(Phase::Integrate),
+            broad_phase_micros: micros(Phase::BroadPhase),
+            narrow_phase_micros: micros(Phase::NarrowPhase),
+            events_micros: micros(Phase::Events),
+        };
+        self.last_tick = Some(timings);
+
+        let Some(config) = &self.config else {
+            return;
+        };
+        let elapsed: Duration = self.phases.iter().sum();
+        let overran = elapsed > Duration::from_micros(config.budget_micros);
+        self.throttled = overran && config.throttle;
+        if overran {
+            events.push(SimEvent::SimOverrun {
+                tick: self.tick,
+                budget_micros: config.budget_micros,
+                timings,
+            });
+        }
+    }
+
+    /// Returns where the last timed tick's time went, if timing is on.
+    pub fn last_tick(&self) -> Option<&PhaseTimings> {
+        self.last_tick.as_ref()
+    }
+
+    /// Returns whether optional work should be skipped this tick, because the last one overran.
+    pub fn is_throttled(&self) -> bool {
+        self.throttled
+    }
+
+    #[cfg(feature = "tracing")]
+    fn exit_span(&mut self) {
+        if let Some(span) = self.span.take() {
+            span.with_subscriber(|(id, dispatch)| dispatch.exit(id));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn end_tick_when_over_budget_should_report_phase_timings_and_throttle() {
+        // Arrange
+        let mut tight = Watchdog::new(Some(WatchdogConfig {
+            budget_micros: 0,
+            throttle: true,
+        }));
+        let mut unwatched = Watchdog::new(None);
+        let mut events = Vec::new();
+
+        // Act
+        tight.begin_tick(7);
+        tight.enter(Phase::Vm);
+        std::thread::sleep(Duration::from_millis(1));
+        tight.enter(Phase::Events);
+        tight.end_tick(&mut events);
+        unwatched.begin_tick(7);
+        unwatched.enter(Phase::Vm);
+        unwatched.end_tick(&mut events);
+
+        // Assert
+        let [
+            SimEvent::SimOverrun {
+                tick,
+                budget_micros,
+                timings,
+            },
+        ] = events.as_slice()
+        else {
+            panic!("expected one overrun, got {events:?}");
+        };
+        assert_eq!((*tick, *budget_micros), (7, 0));
+        assert!(timings.vm_micros >= 1000);
+        assert_eq!(tight.last_tick(), Some(timings));
+        assert!(tight.is_throttled());
+        assert!(!unwatched.is_throttled());
+        assert_eq!(unwatched.last_tick(), None);
+    }
+}
@@ -0,0 +1,277 @@
+use crate::sim::SimEngine;
+use crate::telemetry::TelemetryFrame;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// A slot one thread fills and another empties, without either waiting on the other.
+///
+/// Together with the value each side holds on to, this makes a double buffer: the producer
+/// builds the next value while the consumer reads the last one it took. Putting a value
+/// replaces one that was never taken.
+pub struct Handoff<T> {
+    slot: AtomicPtr<T>,
+}
+
+// SAFETY: values only ever move between threads whole, through the atomic swaps
+unsafe impl<T: Send> Send for Handoff<T> {}
+unsafe impl<T: Send> Sync for Handoff<T> {}
+
+impl<T> Handoff<T> {
+    pub fn new() -> Self {
+        Handoff {
+            slot: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Fills the slot, returning the value it replaced if that was never taken.
+    pub fn put(&self, value: T) -> Option<T> {
+        let old = self
+            .slot
+            .swap(Box::into_raw(Box::new(value)), Ordering::AcqRel);
+        Self::unbox(old)
+    }
+
+    /// Empties the slot, returning its value if there was one.
+    pub fn take(&self) -> Option<T> {
+        Self::unbox(self.slot.swap(ptr::null_mut(), Ordering::AcqRel))
+    }
+
+    fn unbox(pointer: *mut T) -> Option<T> {
+        // SAFETY: non-null pointers in the slot come from `Box::into_raw` in `put`, and the
+        // swap that took this one out left nobody else holding it
+        (!pointer.is_null()).then(|| *unsafe { Box::from_raw(pointer) })
+    }
+}
+
+impl<T> Default for Handoff<T> {
+    fn default() -> Self {
+        Handoff::new()
+    }
+}
+
+impl<T> Drop for Handoff<T> {
+    fn drop(&mut self) {
+        self.take();
+    }
+}
+
+/// What the worker thread and its owner both see.
+#[derive(Default)]
+struct Shared {
+    /// Ticks asked for but not yet started.
+    pending: AtomicU64,
+    stopping: AtomicBool,
+    frames: Handoff<TelemetryFrame>,
+}
+
+/// Steps a match on a thread of its own, so a heavy battle doesn't hold up the caller.
+///
+/// The engine stays shared: the worker locks it for one tick at a time, so anyone else can lock
+/// it in between to look at or change the match, waiting at most for the tick in progress. The
+/// worker hands each completed tick back through a [`Handoff`], so reading where things are
+/// never waits on the engine at all.
+///
+/// Dropping the worker stops it, finishing the tick in progress and dropping queued ones.
+pub struct SimWorker {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SimWorker {
+    /// Starts a worker for an engine. It sits idle until asked to `run`.
+    pub fn spawn(engine: Arc<Mutex<SimEngine>>) -> Self {
+        let shared = Arc::new(Shared::default());
+        let thread = {
+            let shared = shared.clone();
+            thread::spawn(move || work(&engine, &shared))
+        };
+        SimWorker {
+            shared,
+            thread: Some(thread),
+        }
+    }
+
+    /// Queues ticks to run after any already queued.
+    pub fn run(&self, ticks: u64) {
+        if ticks > 0 {
+            self.shared.pending.fetch_add(ticks, Ordering::AcqRel);
+            self.wake();
+        }
+    }
+
+    /// Drops the queued ticks, letting the one in progress finish, and returns how many were
+    /// dropped.
+    pub fn pause(&self) -> u64 {
+        self.shared.pending.swap(0, Ordering::AcqRel)
+    }
+
+    /// Returns how many ticks are queued.
+    pub fn pending(&self) -> u64 {
+        self.shared.pending.load(Ordering::Acquire)
+    }
+
+    /// Takes the latest completed tick, if there's been one since the last call. Its events
+    /// include those of every tick in between, so none are missed.
+    pub fn latest(&self) -> Option<TelemetryFrame> {
+        self.shared.frames.take()
+    }
+
+    /// Stops the thread once the tick in progress is done, and waits for it.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.shared.stopping.store(true, Ordering::Release);
+        self.wake();
+        if let Some(thread) = self.thread.take() {
+            // a panic on the worker poisons the engine's mutex, so whoever locks it next hears
+            // about it
+            let _ = thread.join();
+        }
+    }
+
+    fn wake(&self) {
+        if let Some(thread) = &self.thread {
+            thread.thread().unpark();
+        }
+    }
+}
+
+impl Drop for SimWorker {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn work(engine: &Mutex<SimEngine>, shared: &Shared) {
+    while !shared.stopping.load(Ordering::Acquire) {
+        let claimed = shared
+            .pending
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |ticks| {
+                ticks.checked_sub(1)
+            })
+            .is_ok();
+        if !claimed {
+            // `run` and `stop` unpark us, and an unpark that comes first makes this return
+            thread::park();
+            continue;
+        }
+        let mut frame = {
+            let Ok(mut engine) = engine.lock() else {
+                return;
+            };
+            engine.step();
+            TelemetryFrame::capture(engine.state(), engine.events(), engine.clock())
+        };
+        if let Some(mut unseen) = shared.frames.take() {
+            unseen.events.append(&mut frame.events);
+            frame.events = unseen.events;
+        }
+        shared.frames.put(frame);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bots::Tracker;
+    use crate::sim::TankSpawn;
+    use crate::state::SimState;
+    use crate::util::math::Vec2;
+    use std::time::{Duration, Instant};
+
+    fn wait_for(worker: &SimWorker, tick: u64) -> TelemetryFrame {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut events = Vec::new();
+        loop {
+            if let Some(mut frame) = worker.latest() {
+                events.append(&mut frame.events);
+                if frame.tick == tick {
+                    frame.events = events;
+                    return frame;
+                }
+            }
+            assert!(
+                Instant::now() < deadline,
+                "worker never reached tick {tick}"
+            );
+            thread::yield_now();
+        }
+    }
+
+    fn duel() -> SimEngine {
+        let mut engine = SimEngine::new(SimState::new(0));
+        for (team_id, x) in [(0, 100.0), (1, 400.0)] {
+            let tank = engine
+                .spawn_tank(TankSpawn::medium(team_id, Vec2::new_from_f64(x, 100.0)))
+                .unwrap();
+            engine.set_bot(tank, Box::new(Tracker::default())).unwrap();
+        }
+        engine
+    }
+
+    #[test]
+    fn handoff_should_keep_only_newest_value() {
+        // Arrange
+        let handoff = Handoff::new();
+
+        // Act
+        let first = handoff.put(1);
+        let replaced = handoff.put(2);
+
+        // Assert
+        assert_eq!(first, None);
+        assert_eq!(replaced, Some(1));
+        assert_eq!(handoff.take(), Some(2));
+        assert_eq!(handoff.take(), None);
+    }
+
+    #[test]
+    fn run_should_step_engine_and_hand_back_every_event() {
+        // Arrange
+        let mut expected = duel();
+        let engine = Arc::new(Mutex::new(duel()));
+        let worker = SimWorker::spawn(engine.clone());
+
+        // Act
+        worker.run(90);
+        let frame = wait_for(&worker, 90);
+        worker.stop();
+
+        // Assert
+        let mut events = Vec::new();
+        for _ in 0..90 {
+            expected.step();
+            events.extend_from_slice(expected.events());
+        }
+        let engine = engine.lock().unwrap();
+        assert_eq!(engine.state().time, 90);
+        assert_eq!(engine.state().checksum(), expected.state().checksum());
+        assert!(!events.is_empty());
+        assert_eq!(frame.events, events);
+    }
+
+    #[test]
+    fn pause_should_drop_queued_ticks() {
+        // Arrange
+        let engine = Arc::new(Mutex::new(SimEngine::new(SimState::new(0))));
+        let worker = SimWorker::spawn(engine.clone());
+        let held = engine.lock().unwrap();
+        worker.run(1_000);
+
+        // Act
+        let dropped = worker.pause();
+        let pending = worker.pending();
+        drop(held);
+        worker.stop();
+
+        // Assert
+        let time = engine.lock().unwrap().state().time;
+        assert_eq!(pending, 0);
+        assert_eq!(dropped + time, 1_000);
+        assert!(time <= 1);
+    }
+}
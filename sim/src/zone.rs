@@ -0,0 +1,153 @@
+use crate::events::SimEvent;
+use crate::referee::{DamageSource, Judge};
+use crate::state::Tank;
+use crate::util::math::{Scalar, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// A battle-royale safe zone that closes in over the match, so passive tanks can't stall it
+/// forever.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ZoneConfig {
+    pub center: Vec2,
+    pub start_radius: Scalar,
+    pub end_radius: Scalar,
+    /// Tick the zone starts closing.
+    pub shrink_start: u64,
+    /// Ticks the zone takes to close from its start radius to its end radius.
+    pub shrink_ticks: u64,
+    /// Damage dealt every tick to each tank outside the zone.
+    pub damage_per_tick: u32,
+}
+
+/// The safe zone at some tick.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Zone {
+    pub center: Vec2,
+    pub radius: Scalar,
+}
+
+impl ZoneConfig {
+    /// Returns the zone at a tick. The radius shrinks linearly, so it's the same on every run.
+    pub fn at(&self, tick: u64) -> Zone {
+        let elapsed = tick.saturating_sub(self.shrink_start);
+        let radius = if elapsed >= self.shrink_ticks {
+            self.end_radius
+        } else {
+            let progress = Scalar::from(elapsed) / Scalar::from(self.shrink_ticks);
+            self.start_radius + (self.end_radius - self.start_radius) * progress
+        };
+        Zone {
+            center: self.center,
+            radius,
+        }
+    }
+}
+
+impl Zone {
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.sub(&self.center).length_squared() <= self.radius * self.radius
+    }
+}
+
+/// Damages every living, solid tank outside the zone.
+pub fn update(
+    config: &ZoneConfig,
+    tick: u64,
+    tanks: &mut [Tank],
+    judge: &mut Judge,
+    events: &mut Vec<SimEvent>,
+) {
+    if config.damage_per_tick == 0 {
+        return;
+    }
+    let zone = config.at(tick);
+    for tank in tanks.iter_mut() {
+        if !tank.is_alive() || tank.is_ghost() || zone.contains(tank.position) {
+            continue;
+        }
+        let (damage, destroyed) = judge.deal(tank, DamageSource::Zone, config.damage_per_tick);
+        if damage == 0 {
+            continue;
+        }
+        events.push(SimEvent::ZoneDamage {
+            target_id: tank.id,
+            damage,
+        });
+        if destroyed {
+            events.push(SimEvent::TankDestroyed {
+                tank_id: tank.id,
+                killer: None,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spec::SpecTable;
+    use crate::test_support;
+    use fastnum::dec64;
+
+    fn config() -> ZoneConfig {
+        ZoneConfig {
+            center: Vec2::new(dec64!(500), dec64!(500)),
+            start_radius: dec64!(400),
+            end_radius: dec64!(100),
+            shrink_start: 100,
+            shrink_ticks: 300,
+            damage_per_tick: 3,
+        }
+    }
+
+    #[test]
+    fn at_should_interpolate_radius_over_shrink() {
+        // Arrange
+        let config = config();
+
+        // Act
+        let radii: Vec<Scalar> = [0, 100, 250, 400, 1000]
+            .into_iter()
+            .map(|tick| config.at(tick).radius)
+            .collect();
+
+        // Assert
+        assert_eq!(
+            radii,
+            vec![
+                dec64!(400),
+                dec64!(400),
+                dec64!(250),
+                dec64!(100),
+                dec64!(100)
+            ]
+        );
+    }
+
+    #[test]
+    fn update_when_tank_outside_zone_should_damage_it() {
+        // Arrange
+        let specs = SpecTable::default();
+        let spec = specs.tank(1).unwrap();
+        let at = |x| Vec2::new(x, dec64!(500));
+        let mut tanks = vec![
+            test_support::tank(0, 0, at(dec64!(550))),
+            test_support::tank(1, 1, at(dec64!(950))),
+        ];
+        let mut events = Vec::new();
+
+        // Act
+        update(&config(), 0, &mut tanks, &mut Judge::none(), &mut events);
+
+        // Assert
+        assert_eq!(tanks[0].health, spec.max_health);
+        assert_eq!(tanks[1].health, spec.max_health - 3);
+        assert_eq!(
+            events,
+            vec![SimEvent::ZoneDamage {
+                target_id: 1,
+                damage: 3
+            }]
+        );
+    }
+}
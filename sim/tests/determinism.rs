@@ -0,0 +1,170 @@
+//! Runs a canned match and checks it ends in exactly the state it always has.
+//!
+//! The golden checksum is shared by every platform, so a failure on one target (say aarch64)
+//! but not another means platform-dependent behavior has crept into the simulation: float math
+//! leaking into `Scalar`, hash map iteration order, or similar. If a change alters the match on
+//! purpose, update the golden value in the same commit and say why.
+//!
+//! Run it on each target that ships, e.g. `cargo test --test determinism` natively on an
+//! aarch64 machine or through `cross test --target aarch64-unknown-linux-gnu`.
+
+use sim::arena::ArenaConfig;
+use sim::bots::{Circler, Tracker};
+use sim::config::SimConfig;
+use sim::rules::MatchConfig;
+use sim::sim::{SimEngine, TankSpawn};
+use sim::spec::{ExplosionSpec, Loadout};
+use sim::state::SimState;
+use sim::util::math::{Angle, ConvertToScalar, Vec2};
+use sim::vm::Execution;
+use sim::vm::abi;
+use sim::vm::isa::{Assembler, Opcode};
+use std::num::NonZeroUsize;
+
+const TICKS: u64 = 300;
+
+/// Checksum of the canned match's final state.
+const GOLDEN_CHECKSUM: u64 = 0x37002fc9cf0718e8;
+
+/// Eight tanks in two teams on the default arena, hunting and circling each other, with an
+/// explosion partway through to shake things up. Programs run as `vm_execution` says.
+fn canned_match(vm_execution: Execution) -> SimEngine {
+    let config = SimConfig {
+        rules: MatchConfig {
+            substeps: 2,
+            ..MatchConfig::default()
+        },
+        vm_execution,
+        ..SimConfig::default()
+    }
+    .validate()
+    .expect("canned config is valid");
+    let arena = ArenaConfig::default();
+    let mut engine = SimEngine::from_config(SimState::with_arena(7, &arena), &config);
+
+    for i in 0..8 {
+        let team_id = i % 2;
+        let x = 150.0 + (i / 2) as f64 * 200.0;
+        let y = if team_id == 0 { 150.0 } else { 600.0 };
+        let id = engine
+            .spawn_tank(TankSpawn {
+                team_id,
+                loadout: Loadout {
+                    spec_id: 1 + i % 2,
+                    weapons: vec![0, 1],
+                },
+                position: Vec2::new_from_f64(x, y),
+                angle: Angle::new((team_id as f64 * 3.0).to_scalar()),
+            })
+            .expect("canned spawn is valid");
+        if i % 4 < 2 {
+            engine.set_bot(id, Box::new(Tracker::default())).unwrap();
+        } else {
+            engine.set_bot(id, Box::new(Circler::default())).unwrap();
+        }
+    }
+    engine
+}
+
+/// The canned match with four more tanks driven by programs, which swerve, sweep their turrets
+/// and fire at will.
+fn programmed_match(vm_execution: Execution) -> SimEngine {
+    let mut engine = canned_match(vm_execution);
+    for i in 0..4u32 {
+        let id = engine
+            .spawn_tank(TankSpawn {
+                angle: Angle::new((i as f64).to_scalar()),
+                ..TankSpawn::medium(i % 2, Vec2::new_from_f64(250.0 + i as f64 * 150.0, 375.0))
+            })
+            .expect("programmed spawn is valid");
+        let mut asm = Assembler::new();
+        let start = asm.label();
+        asm.bind(start)
+            .push(1 << 16)
+            .push(abi::LEFT_TRACK)
+            .op(Opcode::Store)
+            .push((i + 1) << 14)
+            .push(abi::RIGHT_TRACK)
+            .op(Opcode::Store)
+            .push(abi::TURRET_RELATIVE)
+            .push(abi::TURRET_MODE)
+            .op(Opcode::Store)
+            .push(1 << 14)
+            .push(abi::TURRET_ANGLE)
+            .op(Opcode::Store)
+            .push(1)
+            .push(abi::FIRE)
+            .op(Opcode::Store)
+            .op(Opcode::Yield)
+            .jump(Opcode::Jmp, start);
+        engine.load_program(id, asm.finish()).unwrap();
+    }
+    engine
+}
+
+#[test]
+fn canned_match_should_end_with_golden_checksum() {
+    // Arrange
+    let mut engine = canned_match(Execution::Interpreted);
+
+    // Act
+    for tick in 0..TICKS {
+        if tick == 200 {
+            engine.explode(
+                Vec2::new_from_f64(500.0, 375.0),
+                ExplosionSpec {
+                    radius: 200.0.to_scalar(),
+                    damage: 30,
+                    knockback: 40.0.to_scalar(),
+                },
+            );
+        }
+        engine.step();
+    }
+
+    // Assert
+    let checksum = engine.state().checksum();
+    assert_eq!(
+        checksum, GOLDEN_CHECKSUM,
+        "final state after {TICKS} ticks changed; got {checksum:#018x}"
+    );
+}
+
+#[test]
+fn threaded_match_should_step_exactly_like_single_thread() {
+    // Arrange
+    let mut serial = programmed_match(Execution::Interpreted);
+    let mut threaded = programmed_match(Execution::Interpreted);
+    threaded.set_threads(NonZeroUsize::new(4).unwrap());
+
+    // Act & Assert
+    for tick in 0..TICKS {
+        serial.step();
+        threaded.step();
+        assert_eq!(
+            threaded.state().checksum(),
+            serial.state().checksum(),
+            "threaded run diverged at tick {tick}"
+        );
+        assert_eq!(threaded.events(), serial.events());
+    }
+}
+
+#[test]
+fn threaded_code_match_should_step_exactly_like_interpreted() {
+    // Arrange
+    let mut interpreted = programmed_match(Execution::Interpreted);
+    let mut threaded_code = programmed_match(Execution::Threaded);
+
+    // Act & Assert
+    for tick in 0..TICKS {
+        interpreted.step();
+        threaded_code.step();
+        assert_eq!(
+            threaded_code.state().checksum(),
+            interpreted.state().checksum(),
+            "threaded code diverged at tick {tick}"
+        );
+        assert_eq!(threaded_code.events(), interpreted.events());
+    }
+}
@@ -0,0 +1,340 @@
+//! Runs the VM conformance suite: small programs with the exact state each must end in, one
+//! golden file per opcode under `tests/golden/vm`.
+//!
+//! The goldens are the reference semantics of the instruction set. Every [`Execution`] mode
+//! has to reproduce them exactly, and has to end in the same state however its budget is split
+//! across calls. Blessing records what the interpreter does.
+//!
+//! [`Execution`]: sim::vm::Execution
+//!
+//! Programs are written one instruction per line, as the opcode's name and its operand, if
+//! any, in decimal or `0x` hex; jumps and calls take byte addresses. When a change to the instruction
+//! set alters an outcome on purpose, rerun with `BLESS_VM_GOLDEN=1` to rewrite the
+//! expectations, and review the diff in the same commit.
+
+use serde::{Deserialize, Serialize};
+use sim::state::VmState;
+use sim::vm::isa::Opcode;
+use sim::vm::threaded::{self, ThreadedProgram};
+use sim::vm::{self, Execution, RunOutcome, RunReport, Stack, VmFault, VmIo};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The memory-mapped register the suite's host exposes.
+const REGISTER: u32 = 0x8000;
+/// The syscall the suite's host serves: pops two words and pushes their sum.
+const SYS_ADD: u8 = 7;
+/// Extra cycles [`SYS_ADD`] costs.
+const SYS_ADD_COST: u32 = 3;
+
+const EXECUTIONS: [Execution; 2] = [Execution::Interpreted, Execution::Threaded];
+
+/// A host with one read-write register and one syscall, standing in for a tank.
+#[derive(Default)]
+struct GoldenIo {
+    register: u32,
+}
+
+impl VmIo for GoldenIo {
+    fn read(&mut self, address: u32) -> Option<u32> {
+        (address == REGISTER).then_some(self.register)
+    }
+
+    fn write(&mut self, address: u32, value: u32) -> bool {
+        if address == REGISTER {
+            self.register = value;
+        }
+        address == REGISTER
+    }
+
+    fn syscall(&mut self, number: u8, stack: &mut Stack) -> Result<(), VmFault> {
+        if number != SYS_ADD {
+            return Err(VmFault::BadSyscall { number });
+        }
+        let b = stack.pop()?;
+        let a = stack.pop()?;
+        stack.push(a.wrapping_add(b))
+    }
+
+    fn syscall_cost(&mut self) -> u32 {
+        SYS_ADD_COST
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Golden {
+    opcode: String,
+    cases: Vec<Case>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Case {
+    name: String,
+    code: Vec<String>,
+    #[serde(default = "default_budget")]
+    budget: u32,
+    #[serde(
+        default = "default_call_depth",
+        skip_serializing_if = "is_default_call_depth"
+    )]
+    call_depth: u32,
+    expect: Expected,
+}
+
+fn default_budget() -> u32 {
+    1000
+}
+
+fn default_call_depth() -> u32 {
+    vm::CALL_DEPTH as u32
+}
+
+fn is_default_call_depth(call_depth: &u32) -> bool {
+    *call_depth == default_call_depth()
+}
+
+/// Everything a program leaves behind. Stack and RAM words are signed, for readability.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Expected {
+    outcome: Outcome,
+    cycles: u32,
+    pc: u32,
+    /// The live stack, bottom first.
+    stack: Vec<i32>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    fp: u32,
+    /// Calls in progress, innermost last, as return address and the caller's frame pointer.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    frames: Vec<(u32, u32)>,
+    /// Non-zero words of RAM, as address and value.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    memory: Vec<(u32, i32)>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    register: i32,
+}
+
+fn is_zero<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+/// [`RunOutcome`], as written in goldens.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum Outcome {
+    Yielded,
+    OutOfCycles,
+    Halted,
+    Faulted(VmFault),
+}
+
+impl From<RunOutcome> for Outcome {
+    fn from(outcome: RunOutcome) -> Self {
+        match outcome {
+            RunOutcome::Yielded => Outcome::Yielded,
+            RunOutcome::OutOfCycles => Outcome::OutOfCycles,
+            RunOutcome::Halted => Outcome::Halted,
+            RunOutcome::Faulted(fault) => Outcome::Faulted(fault),
+        }
+    }
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/vm")
+}
+
+fn opcodes() -> impl Iterator<Item = Opcode> {
+    (0..=u8::MAX).filter_map(Opcode::from_byte)
+}
+
+fn name(op: Opcode) -> String {
+    format!("{op:?}").to_lowercase()
+}
+
+fn parse_operand(text: &str) -> u32 {
+    let value = match text.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16),
+        None => text.parse::<i64>(),
+    };
+    value.unwrap_or_else(|_| panic!("bad operand {text:?}")) as u32
+}
+
+/// Assembles a golden program. `byte N` emits a raw byte, for invalid encodings.
+fn assemble(lines: &[String]) -> Vec<u8> {
+    let mut code = Vec::new();
+    for line in lines {
+        let mut parts = line.split_whitespace();
+        let mnemonic = parts.next().expect("empty line");
+        let operand = parts.next().map(parse_operand);
+        if mnemonic == "byte" {
+            code.push(operand.expect("byte needs a value") as u8);
+            continue;
+        }
+        let op = opcodes()
+            .find(|op| name(*op) == mnemonic)
+            .unwrap_or_else(|| panic!("unknown mnemonic {mnemonic:?}"));
+        code.push(op as u8);
+        let size = op.operand_size();
+        if size > 0 {
+            let operand = operand.unwrap_or_else(|| panic!("{mnemonic} needs an operand"));
+            code.extend_from_slice(&operand.to_le_bytes()[..size]);
+        }
+    }
+    code
+}
+
+fn observe(state: &VmState, io: &GoldenIo, report: RunReport) -> Expected {
+    Expected {
+        outcome: report.outcome.into(),
+        cycles: report.cycles,
+        pc: state.pc,
+        stack: state.stack[..state.sp as usize]
+            .iter()
+            .map(|word| *word as i32)
+            .collect(),
+        fp: state.fp,
+        frames: state
+            .frames
+            .iter()
+            .map(|frame| (frame.return_pc, frame.fp))
+            .collect(),
+        memory: (0..state.memory.len() as u32)
+            .zip(&state.memory)
+            .filter(|(_, word)| **word != 0)
+            .map(|(address, word)| (address, *word as i32))
+            .collect(),
+        register: io.register as i32,
+    }
+}
+
+fn run_as(
+    execution: Execution,
+    state: &mut VmState,
+    code: &[u8],
+    budget: u32,
+    io: &mut GoldenIo,
+) -> RunReport {
+    match execution {
+        Execution::Interpreted => vm::run(state, code, budget, io),
+        Execution::Threaded => threaded::run(state, &ThreadedProgram::compile(code), budget, io),
+    }
+}
+
+fn start(call_depth: u32) -> VmState {
+    VmState {
+        call_depth,
+        ..VmState::new()
+    }
+}
+
+fn run_case(execution: Execution, case: &Case) -> (VmState, Expected) {
+    let code = assemble(&case.code);
+    let mut state = start(case.call_depth);
+    let mut io = GoldenIo::default();
+    let report = run_as(execution, &mut state, &code, case.budget, &mut io);
+    let observed = observe(&state, &io, report);
+    (state, observed)
+}
+
+/// Runs a program one cycle per call until it stops, as a tank starved of budget would.
+fn run_stepwise(execution: Execution, case: &Case) -> VmState {
+    let code = assemble(&case.code);
+    let mut state = start(case.call_depth);
+    let mut io = GoldenIo::default();
+    for _ in 0..case.budget {
+        if run_as(execution, &mut state, &code, 1, &mut io).outcome != RunOutcome::OutOfCycles {
+            break;
+        }
+    }
+    state
+}
+
+fn load(path: &Path) -> Golden {
+    let text = fs::read_to_string(path).unwrap_or_else(|err| panic!("{path:?}: {err}"));
+    serde_json::from_str(&text).unwrap_or_else(|err| panic!("{path:?}: {err}"))
+}
+
+#[test]
+fn every_opcode_should_have_a_golden_file() {
+    // Arrange
+    let dir = golden_dir();
+
+    // Act
+    let missing: Vec<String> = opcodes()
+        .map(name)
+        .filter(|name| !dir.join(format!("{name}.json")).exists())
+        .collect();
+
+    // Assert
+    assert!(missing.is_empty(), "no golden file for {missing:?}");
+}
+
+#[test]
+fn run_should_match_golden_outcomes() {
+    // Arrange
+    let bless = std::env::var_os("BLESS_VM_GOLDEN").is_some();
+    let mut failures = Vec::new();
+
+    for op in opcodes() {
+        let path = golden_dir().join(format!("{}.json", name(op)));
+        let mut golden = load(&path);
+        assert_eq!(golden.opcode, name(op), "{path:?}");
+
+        for case in &mut golden.cases {
+            for execution in EXECUTIONS {
+                // Act
+                let (state, observed) = run_case(execution, case);
+                let (again, _) = run_case(execution, case);
+
+                // Assert
+                assert_eq!(
+                    state, again,
+                    "{}: {} ({execution:?}) isn't repeatable",
+                    golden.opcode, case.name
+                );
+                if observed != case.expect {
+                    if bless && execution == Execution::Interpreted {
+                        case.expect = observed;
+                    } else {
+                        failures.push(format!(
+                            "{}: {} ({execution:?})\n  expected {:?}\n  observed {:?}",
+                            golden.opcode, case.name, case.expect, observed
+                        ));
+                    }
+                }
+            }
+        }
+
+        if bless {
+            let text = serde_json::to_string_pretty(&golden).unwrap() + "\n";
+            fs::write(&path, text).unwrap();
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn run_when_budget_split_into_single_cycles_should_end_in_same_state() {
+    for op in opcodes() {
+        let golden = load(&golden_dir().join(format!("{}.json", name(op))));
+        for case in golden
+            .cases
+            .iter()
+            .filter(|case| case.expect.outcome != Outcome::OutOfCycles)
+        {
+            for execution in EXECUTIONS {
+                // Arrange
+                let (whole, _) = run_case(execution, case);
+
+                // Act
+                let stepwise = run_stepwise(execution, case);
+
+                // Assert
+                assert_eq!(
+                    stepwise, whole,
+                    "{}: {} ({execution:?})",
+                    golden.opcode, case.name
+                );
+            }
+        }
+    }
+}